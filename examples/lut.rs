@@ -63,6 +63,18 @@ impl Instantiable for Lut {
             Parameter::BitVec(self.lookup_table.clone()),
         ))
     }
+
+    fn set_parameter(&mut self, id: &Identifier, val: Parameter) -> Option<Parameter> {
+        match val {
+            Parameter::BitVec(bv)
+                if self.has_parameter(id) && bv.len() == self.lookup_table.len() =>
+            {
+                let old = std::mem::replace(&mut self.lookup_table, bv);
+                Some(Parameter::BitVec(old))
+            }
+            _ => None,
+        }
+    }
 }
 
 fn main() {
@@ -0,0 +1,1274 @@
+/*!
+
+  And-Inverter Graphs, with conversion from netlists, AIGER I/O and the don't-cares of local functions.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable},
+    constraint::InputConstraint,
+    netlist::{DrivenNet, Gate, NetRef, Netlist},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+};
+
+/// A reference to a node of an [Aig], which may be inverted.
+/// Literals are numbered like in AIGER: twice the node index, plus one when inverted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Lit(u32);
+
+impl Lit {
+    /// The constant false literal
+    pub const FALSE: Lit = Lit(0);
+    /// The constant true literal
+    pub const TRUE: Lit = Lit(1);
+
+    /// Creates a literal of the node at `var`
+    fn new(var: usize, inverted: bool) -> Self {
+        Lit((var as u32) << 1 | inverted as u32)
+    }
+
+    /// Returns the index of the node this literal refers to
+    pub fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    /// Returns true if the literal is the inverse of its node
+    pub fn is_inverted(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    /// Returns true if the literal is constant true or false
+    pub fn is_const(self) -> bool {
+        self.var() == 0
+    }
+
+    /// Returns the literal with the inversion removed
+    pub fn regular(self) -> Self {
+        Lit(self.0 & !1)
+    }
+}
+
+impl std::ops::Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Self::Output {
+        Lit(self.0 ^ 1)
+    }
+}
+
+impl std::fmt::Display for Lit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A node of an [Aig]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    /// The constant false node
+    Const,
+    /// The primary input at the index
+    Input(usize),
+    /// The latch at the index
+    Latch(usize),
+    /// The conjunction of two literals
+    And(Lit, Lit),
+}
+
+/// A latch of an [Aig]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Latch {
+    /// The output of the latch
+    output: Lit,
+    /// The next state of the latch
+    next: Lit,
+    /// The initial value of the latch, or [None] if it is uninitialized
+    init: Option<bool>,
+    /// The name of the latch
+    name: Option<String>,
+}
+
+impl Latch {
+    /// Returns the literal of the latch output
+    pub fn output(&self) -> Lit {
+        self.output
+    }
+
+    /// Returns the literal of the next state of the latch
+    pub fn next(&self) -> Lit {
+        self.next
+    }
+
+    /// Returns the initial value of the latch, or [None] if it is uninitialized
+    pub fn init(&self) -> Option<bool> {
+        self.init
+    }
+
+    /// Returns the name of the latch
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// How [Aig::evaluate_ternary] propagates unknown values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XMode {
+    /// Every node with an unknown fanin is unknown unless its other fanin is false, like gate-level simulation
+    #[default]
+    Pessimistic,
+    /// Like [XMode::Pessimistic], except muxes with an unknown select and equal, known data inputs take the value of their data inputs
+    Resolve,
+}
+
+/// The values of the outputs and the next states of the latches from [Aig::evaluate_ternary]
+pub type TernaryEval = (Vec<Option<bool>>, Vec<Option<bool>>);
+
+/// The function of a node over a set of leaves that determine it, with its don't-cares, as found by [Aig::local_function].
+/// The combinations of leaf values are numbered as minterms, where bit `i` of a minterm is the value of leaf `i`.
+/// A node can be replaced by any logic over the leaves that agrees with it on every minterm that is not a don't-care.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalFunction {
+    /// The value of the node under each minterm, or [None] if no pattern produced the minterm
+    values: Vec<Option<bool>>,
+    /// Whether inverting the node under each minterm changed an output or next state
+    observable: Vec<bool>,
+}
+
+impl LocalFunction {
+    /// The most leaves a local function can have
+    pub const MAX_LEAVES: usize = 16;
+
+    /// Creates a function of `leaves` leaves where every minterm is a don't-care
+    fn new(leaves: usize) -> Self {
+        Self {
+            values: vec![None; 1 << leaves],
+            observable: vec![false; 1 << leaves],
+        }
+    }
+
+    /// Records that the node takes `value` under `minterm`, and whether inverting it there was `observable`.
+    /// Returns `false` if the node took the other value under the same minterm before.
+    fn record(&mut self, minterm: usize, value: bool, observable: bool) -> bool {
+        if self.values[minterm].is_some_and(|v| v != value) {
+            return false;
+        }
+        self.values[minterm] = Some(value);
+        self.observable[minterm] |= observable;
+        true
+    }
+
+    /// Returns the number of leaves
+    pub fn num_leaves(&self) -> usize {
+        self.values.len().trailing_zeros() as usize
+    }
+
+    /// Returns the value the node must keep under `minterm`, or [None] if it is a don't-care
+    pub fn value(&self, minterm: usize) -> Option<bool> {
+        self.values[minterm].filter(|_| self.observable[minterm])
+    }
+
+    /// Returns `true` if the leaves never take the values of `minterm`
+    pub fn is_satisfiability_dont_care(&self, minterm: usize) -> bool {
+        self.values[minterm].is_none()
+    }
+
+    /// Returns `true` if the leaves can take the values of `minterm`, but the value of the node is then never observed
+    pub fn is_observability_dont_care(&self, minterm: usize) -> bool {
+        self.values[minterm].is_some() && !self.observable[minterm]
+    }
+
+    /// Returns the minterms that are don't-cares
+    pub fn dont_cares(&self) -> impl Iterator<Item = usize> {
+        (0..self.values.len()).filter(|m| self.value(*m).is_none())
+    }
+
+    /// Returns `true` if `f`, given the values of the leaves, agrees with the node on every minterm that is not a don't-care,
+    /// so the node can be replaced by it
+    pub fn admits(&self, f: impl Fn(&[bool]) -> bool) -> bool {
+        (0..self.values.len()).all(|m| {
+            let leaves: Vec<bool> = (0..self.num_leaves()).map(|i| m >> i & 1 == 1).collect();
+            self.value(m).is_none_or(|v| f(&leaves) == v)
+        })
+    }
+}
+
+/// An And-Inverter Graph: a circuit of two-input AND nodes connected by optionally inverted edges.
+/// AND nodes are structurally hashed and trivial ones are folded away as they are created.
+#[derive(Debug, Clone)]
+pub struct Aig {
+    /// The nodes of the graph. Node 0 is the constant false.
+    nodes: Vec<Node>,
+    /// The primary inputs, with their names
+    inputs: Vec<(Lit, Option<String>)>,
+    /// The latches
+    latches: Vec<Latch>,
+    /// The primary outputs, with their names
+    outputs: Vec<(Lit, Option<String>)>,
+    /// Maps the operands of every AND node to the node
+    strash: HashMap<(Lit, Lit), Lit>,
+    /// The constraints on the values of the inputs, with the positions of their inputs
+    constraints: Vec<(InputConstraint, Vec<usize>)>,
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aig {
+    /// The most inputs and latches that [Aig::exhaustive_patterns] and [region_function] enumerate every value of
+    pub const MAX_EXHAUSTIVE: usize = 20;
+
+    /// Creates an empty graph
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::Const],
+            inputs: Vec::new(),
+            latches: Vec::new(),
+            outputs: Vec::new(),
+            strash: HashMap::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Adds a primary input to the graph and returns its literal
+    pub fn add_input(&mut self, name: Option<String>) -> Lit {
+        let lit = Lit::new(self.nodes.len(), false);
+        self.nodes.push(Node::Input(self.inputs.len()));
+        self.inputs.push((lit, name));
+        lit
+    }
+
+    /// Adds a latch to the graph and returns the literal of its output.
+    /// The next state is constant false until set with [Aig::set_latch_next].
+    pub fn add_latch(&mut self, name: Option<String>, init: Option<bool>) -> Lit {
+        let output = Lit::new(self.nodes.len(), false);
+        self.nodes.push(Node::Latch(self.latches.len()));
+        self.latches.push(Latch {
+            output,
+            next: Lit::FALSE,
+            init,
+            name,
+        });
+        output
+    }
+
+    /// Sets the next state of the latch with output `latch`
+    pub fn set_latch_next(&mut self, latch: Lit, next: Lit) -> Result<(), String> {
+        self.check(next)?;
+        match self.nodes.get(latch.var()) {
+            Some(Node::Latch(idx)) if !latch.is_inverted() => {
+                self.latches[*idx].next = next;
+                Ok(())
+            }
+            _ => Err(format!("Literal {latch} is not the output of a latch")),
+        }
+    }
+
+    /// Adds a primary output driven by `lit`
+    pub fn add_output(&mut self, lit: Lit, name: Option<String>) -> Result<(), String> {
+        self.check(lit)?;
+        self.outputs.push((lit, name));
+        Ok(())
+    }
+
+    /// Returns an error if `lit` does not refer to a node of the graph
+    fn check(&self, lit: Lit) -> Result<(), String> {
+        if lit.var() < self.nodes.len() {
+            Ok(())
+        } else {
+            Err(format!("Literal {lit} is not in the graph"))
+        }
+    }
+
+    /// Returns the conjunction of `a` and `b`, reusing an existing node when possible
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        let (a, b) = if a > b { (a, b) } else { (b, a) };
+        if b == Lit::FALSE || a == !b {
+            return Lit::FALSE;
+        }
+        if b == Lit::TRUE || a == b {
+            return a;
+        }
+        if let Some(lit) = self.strash.get(&(a, b)) {
+            return *lit;
+        }
+        let lit = Lit::new(self.nodes.len(), false);
+        self.nodes.push(Node::And(a, b));
+        self.strash.insert((a, b), lit);
+        lit
+    }
+
+    /// Returns the disjunction of `a` and `b`
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    /// Returns the exclusive or of `a` and `b`
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let both = self.and(a, b);
+        let neither = self.and(!a, !b);
+        self.and(!both, !neither)
+    }
+
+    /// Returns `t` if `s` is true, else `e`
+    pub fn mux(&mut self, s: Lit, t: Lit, e: Lit) -> Lit {
+        let then = self.and(s, t);
+        let other = self.and(!s, e);
+        self.or(then, other)
+    }
+
+    /// Returns the fanins of `lit`, if it refers to an AND node
+    pub fn fanins(&self, lit: Lit) -> Option<(Lit, Lit)> {
+        match self.nodes.get(lit.var()) {
+            Some(Node::And(a, b)) => Some((*a, *b)),
+            _ => None,
+        }
+    }
+
+    /// Returns the primary inputs, with their names
+    pub fn inputs(&self) -> &[(Lit, Option<String>)] {
+        &self.inputs
+    }
+
+    /// Returns the latches
+    pub fn latches(&self) -> &[Latch] {
+        &self.latches
+    }
+
+    /// Returns the primary outputs, with their names
+    pub fn outputs(&self) -> &[(Lit, Option<String>)] {
+        &self.outputs
+    }
+
+    /// Returns the number of AND nodes
+    pub fn num_ands(&self) -> usize {
+        self.strash.len()
+    }
+
+    /// Adds a constraint on the values of the inputs, which are found by name.
+    /// [Aig::evaluate] rejects input values that violate it, and [Aig::evaluate_ternary] fills in the values it implies.
+    pub fn add_constraint(&mut self, constraint: InputConstraint) -> Result<(), String> {
+        let positions = constraint
+            .inputs()
+            .iter()
+            .map(|name| {
+                let name = name.to_string();
+                self.inputs
+                    .iter()
+                    .position(|(_, n)| n.as_ref() == Some(&name))
+                    .ok_or(format!("The graph has no input {name}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.constraints.push((constraint, positions));
+        Ok(())
+    }
+
+    /// Returns the constraints on the values of the inputs
+    pub fn constraints(&self) -> impl Iterator<Item = &InputConstraint> {
+        self.constraints.iter().map(|(c, _)| c)
+    }
+
+    /// Removes the constraints on the values of the inputs, returning them
+    pub fn clear_constraints(&mut self) -> Vec<InputConstraint> {
+        std::mem::take(&mut self.constraints)
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect()
+    }
+
+    /// Returns `true` if the values of the inputs satisfy every constraint
+    pub fn satisfies(&self, inputs: &[bool]) -> bool {
+        self.violated(inputs).is_none()
+    }
+
+    /// Returns the first constraint that the values of the inputs violate
+    fn violated(&self, inputs: &[bool]) -> Option<&InputConstraint> {
+        self.constraints
+            .iter()
+            .find(|(c, pos)| !c.holds(&pos.iter().map(|p| inputs[*p]).collect::<Vec<_>>()))
+            .map(|(c, _)| c)
+    }
+
+    /// Returns a literal that is true exactly when every constraint holds.
+    /// SAT-based checks can assume it, so they only consider input values that can occur.
+    pub fn constraint_lit(&mut self) -> Lit {
+        let mut all = Lit::TRUE;
+        for (constraint, positions) in self.constraints.clone() {
+            let lits: Vec<Lit> = positions.iter().map(|p| self.inputs[*p].0).collect();
+            // At most one is true when no pair is true together
+            let mut at_most_one = Lit::TRUE;
+            for (i, a) in lits.iter().enumerate() {
+                for b in &lits[i + 1..] {
+                    let both = self.and(*a, *b);
+                    at_most_one = self.and(at_most_one, !both);
+                }
+            }
+            let holds = match constraint {
+                InputConstraint::Constant(_, true) => lits[0],
+                InputConstraint::Constant(_, false) => !lits[0],
+                InputConstraint::OneHot(_) => {
+                    let any = lits.iter().fold(Lit::FALSE, |any, l| self.or(any, *l));
+                    self.and(any, at_most_one)
+                }
+                InputConstraint::Exclusive(_) => at_most_one,
+            };
+            all = self.and(all, holds);
+        }
+        all
+    }
+
+    /// Evaluates the graph under the values of the inputs and latches.
+    /// Returns the values of the outputs and the next states of the latches.
+    /// Returns an error if the values of the inputs violate a constraint.
+    pub fn evaluate(
+        &self,
+        inputs: &[bool],
+        state: &[bool],
+    ) -> Result<(Vec<bool>, Vec<bool>), String> {
+        self.check_lengths(inputs.len(), state.len())?;
+        if let Some(constraint) = self.violated(inputs) {
+            return Err(format!("The inputs violate the constraint {constraint}"));
+        }
+        let values = self.simulate(inputs, state, None);
+        Ok(self.sinks(&values))
+    }
+
+    /// Returns the value of every node under the values of the inputs and latches, with the node `flip` inverted if given
+    fn simulate(&self, inputs: &[bool], state: &[bool], flip: Option<usize>) -> Vec<bool> {
+        // Every AND node comes after its fanins
+        let mut values = Vec::with_capacity(self.nodes.len());
+        for (var, node) in self.nodes.iter().enumerate() {
+            let v = match node {
+                Node::Const => false,
+                Node::Input(idx) => inputs[*idx],
+                Node::Latch(idx) => state[*idx],
+                Node::And(a, b) => lit_value(&values, *a) && lit_value(&values, *b),
+            };
+            values.push(v ^ (flip == Some(var)));
+        }
+        values
+    }
+
+    /// Returns the values of the outputs and the next states of the latches, from the values of the nodes
+    fn sinks(&self, values: &[bool]) -> (Vec<bool>, Vec<bool>) {
+        (
+            self.outputs
+                .iter()
+                .map(|(l, _)| lit_value(values, *l))
+                .collect(),
+            self.latches
+                .iter()
+                .map(|l| lit_value(values, l.next))
+                .collect(),
+        )
+    }
+
+    /// Returns the function of `node` over the `leaves`, with its don't-cares, by simulating the graph under `patterns` of input and latch values.
+    /// A combination of leaf values that no pattern produces is a satisfiability don't-care.
+    /// One under which inverting `node` changes no output or next state is an observability don't-care.
+    /// Patterns that violate a constraint are skipped, so the combinations only they produce are don't-cares too.
+    /// The don't-cares are exact when the patterns cover every value of the inputs and latches, like those of [Aig::exhaustive_patterns].
+    /// Otherwise a rewrite that relies on them must be checked another way.
+    /// Returns an error if there are more than [LocalFunction::MAX_LEAVES] leaves, or if the leaves do not determine the value of `node`.
+    pub fn local_function(
+        &self,
+        node: Lit,
+        leaves: &[Lit],
+        patterns: impl IntoIterator<Item = (Vec<bool>, Vec<bool>)>,
+    ) -> Result<LocalFunction, String> {
+        self.check(node)?;
+        for leaf in leaves {
+            self.check(*leaf)?;
+        }
+        if leaves.len() > LocalFunction::MAX_LEAVES {
+            return Err(format!(
+                "Expected at most {} leaves, got {}",
+                LocalFunction::MAX_LEAVES,
+                leaves.len()
+            ));
+        }
+
+        let mut function = LocalFunction::new(leaves.len());
+        for (inputs, state) in patterns {
+            self.check_lengths(inputs.len(), state.len())?;
+            if !self.satisfies(&inputs) {
+                continue;
+            }
+            let values = self.simulate(&inputs, &state, None);
+            let minterm = minterm(&values, leaves);
+            let observable = function.observable[minterm] || {
+                let flipped = self.simulate(&inputs, &state, Some(node.var()));
+                self.sinks(&values) != self.sinks(&flipped)
+            };
+            if !function.record(minterm, lit_value(&values, node), observable) {
+                return Err(format!(
+                    "The leaves do not determine the value of literal {node}"
+                ));
+            }
+        }
+        Ok(function)
+    }
+
+    /// Returns every combination of values of the inputs and latches, as patterns for [Aig::local_function].
+    /// There are two to the power of the number of inputs and latches of them,
+    /// so this returns an error if there are more than [Aig::MAX_EXHAUSTIVE] inputs and latches.
+    pub fn exhaustive_patterns(
+        &self,
+    ) -> Result<impl Iterator<Item = (Vec<bool>, Vec<bool>)> + use<>, String> {
+        let (inputs, latches) = (self.inputs.len(), self.latches.len());
+        if inputs + latches > Self::MAX_EXHAUSTIVE {
+            return Err(format!(
+                "Expected at most {} inputs and latches, got {}",
+                Self::MAX_EXHAUSTIVE,
+                inputs + latches
+            ));
+        }
+        Ok((0..1usize << (inputs + latches)).map(move |m| {
+            let bits: Vec<bool> = (0..inputs + latches).map(|i| m >> i & 1 == 1).collect();
+            (bits[..inputs].to_vec(), bits[inputs..].to_vec())
+        }))
+    }
+
+    /// Returns the values of the latches at time zero, where uninitialized latches start at false
+    pub fn initial_state(&self) -> Vec<bool> {
+        self.latches
+            .iter()
+            .map(|l| l.init.unwrap_or(false))
+            .collect()
+    }
+
+    /// Returns an error unless there are `inputs` input values and `state` latch values
+    fn check_lengths(&self, inputs: usize, state: usize) -> Result<(), String> {
+        if inputs != self.inputs.len() || state != self.latches.len() {
+            return Err(format!(
+                "Expected {} inputs and {} latches, got {inputs} and {state}",
+                self.inputs.len(),
+                self.latches.len(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Evaluates the graph under the values of the inputs and latches, where [None] is an unknown value.
+    /// Returns the values of the outputs and the next states of the latches.
+    /// With [XMode::Resolve], muxes built like [Aig::mux] are recognized so equal data inputs hide an unknown select.
+    /// Unknown inputs take the values implied by the constraints, such as constant pins.
+    /// Returns an error if the known inputs violate a constraint.
+    pub fn evaluate_ternary(
+        &self,
+        inputs: &[Option<bool>],
+        state: &[Option<bool>],
+        mode: XMode,
+    ) -> Result<TernaryEval, String> {
+        self.check_lengths(inputs.len(), state.len())?;
+        let mut inputs = inputs.to_vec();
+        for (constraint, positions) in &self.constraints {
+            let mut values: Vec<Option<bool>> = positions.iter().map(|p| inputs[*p]).collect();
+            constraint.imply(&mut values)?;
+            for (p, v) in positions.iter().zip(values) {
+                inputs[*p] = v;
+            }
+        }
+        // Every AND node comes after its fanins
+        let mut values: Vec<Option<bool>> = Vec::with_capacity(self.nodes.len());
+        let value =
+            |values: &Vec<Option<bool>>, lit: Lit| values[lit.var()].map(|v| v ^ lit.is_inverted());
+        for node in &self.nodes {
+            let v = match node {
+                Node::Const => Some(false),
+                Node::Input(idx) => inputs[*idx],
+                Node::Latch(idx) => state[*idx],
+                Node::And(a, b) => match (value(&values, *a), value(&values, *b)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ if mode == XMode::Resolve => self.resolve_mux(*a, *b, |l| value(&values, l)),
+                    _ => None,
+                },
+            };
+            values.push(v);
+        }
+        Ok((
+            self.outputs
+                .iter()
+                .map(|(l, _)| value(&values, *l))
+                .collect(),
+            self.latches
+                .iter()
+                .map(|l| value(&values, l.next))
+                .collect(),
+        ))
+    }
+
+    /// Returns the value of the node `AND(a, b)` if it is the complement of a mux whose data inputs are equal and known
+    fn resolve_mux(&self, a: Lit, b: Lit, value: impl Fn(Lit) -> Option<bool>) -> Option<bool> {
+        if !a.is_inverted() || !b.is_inverted() {
+            return None;
+        }
+        let (a0, a1) = self.fanins(a)?;
+        let (b0, b1) = self.fanins(b)?;
+        // One fanin of each AND is the select, in opposite polarities
+        let (t, e) = [(a0, a1), (a1, a0)]
+            .into_iter()
+            .flat_map(|(s, t)| [(b0, b1), (b1, b0)].map(|(ns, e)| (s, t, ns, e)))
+            .find(|(s, _, ns, _)| *s == !*ns)
+            .map(|(_, t, _, e)| (t, e))?;
+        match (value(t), value(e)) {
+            (Some(t), Some(e)) if t == e => Some(!t),
+            _ => None,
+        }
+    }
+
+    /// Returns the values of the latches at time zero, where uninitialized latches are unknown
+    pub fn initial_ternary_state(&self) -> Vec<Option<bool>> {
+        self.latches.iter().map(|l| l.init).collect()
+    }
+
+    /// Converts the combinational `netlist` to a graph.
+    /// The inputs and outputs of the netlist become inputs and outputs of the graph with the same names.
+    /// `decompose` builds the logic of each instance from the literals of its inputs, returning a literal per output.
+    pub fn from_netlist<I, F>(netlist: &Netlist<I>, decompose: F) -> Result<Self, String>
+    where
+        I: Instantiable,
+        F: Fn(&I, &mut Aig, &[Lit]) -> Result<Vec<Lit>, String>,
+    {
+        Self::from_sequential_netlist(netlist, |_| None, decompose)
+    }
+
+    /// Converts `netlist` to a graph like [Aig::from_netlist], with its registers as latches named after the instances.
+    /// `data_input` identifies the registers by returning the index of their data input. Their other inputs, like clocks, are ignored.
+    /// Latches start at the initial values of their registers, as given by [NetRef::get_initial_value].
+    /// The input constraints of the netlist are added to the graph.
+    pub fn from_sequential_netlist<I, R, F>(
+        netlist: &Netlist<I>,
+        data_input: R,
+        decompose: F,
+    ) -> Result<Self, String>
+    where
+        I: Instantiable,
+        R: Fn(&I) -> Option<usize>,
+        F: Fn(&I, &mut Aig, &[Lit]) -> Result<Vec<Lit>, String>,
+    {
+        let mut aig = Aig::new();
+        let mut lits: HashMap<NetRef<I>, Vec<Lit>> = HashMap::new();
+        for input in netlist.inputs() {
+            let lit = aig.add_input(Some(input.get_identifier().to_string()));
+            lits.insert(input.unwrap(), vec![lit]);
+        }
+        let mut registers = Vec::new();
+        for node in netlist.objects() {
+            let Some(d) = node.get_instance_type().and_then(|t| data_input(&t)) else {
+                continue;
+            };
+            let name = node.get_instance_name().unwrap();
+            if node.is_multi_output() {
+                return Err(format!("Register {name} has more than one output"));
+            }
+            let latch = aig.add_latch(Some(name.to_string()), node.get_initial_value());
+            lits.insert(node.clone(), vec![latch]);
+            registers.push((node, d, latch));
+        }
+
+        let mut visiting: HashSet<NetRef<I>> = HashSet::new();
+        for (driver, net) in netlist.outputs() {
+            let node = driver.clone().unwrap();
+            lower(&mut aig, &mut lits, &mut visiting, node.clone(), &decompose)?;
+            let lit = lits[&node][driver.get_position()];
+            aig.add_output(lit, Some(net.get_identifier().to_string()))?;
+        }
+        for (node, d, latch) in registers {
+            let (Some(driver), Some((_, pin))) = (node.get_driver(d), node.get_driver_net(d))
+            else {
+                return Err(format!(
+                    "Input {d} of {} is unconnected",
+                    node.get_instance_name().unwrap()
+                ));
+            };
+            lower(
+                &mut aig,
+                &mut lits,
+                &mut visiting,
+                driver.clone(),
+                &decompose,
+            )?;
+            aig.set_latch_next(latch, lits[&driver][pin])?;
+        }
+        for constraint in netlist.get_input_constraints() {
+            aig.add_constraint(constraint)?;
+        }
+        Ok(aig)
+    }
+
+    /// Converts a netlist of [Gate]s to a graph, with the gates decomposed by [decompose_gate]
+    pub fn from_gate_netlist(netlist: &Netlist<Gate>) -> Result<Self, String> {
+        Self::from_netlist(netlist, decompose_gate)
+    }
+
+    /// Numbers the nodes for AIGER: inputs, then latches, then AND nodes.
+    /// Returns the new literal of each node.
+    fn aiger_numbering(&self) -> Vec<Lit> {
+        let mut vars = vec![0; self.nodes.len()];
+        let ordered = self
+            .inputs
+            .iter()
+            .map(|(l, _)| l.var())
+            .chain(self.latches.iter().map(|l| l.output.var()))
+            .chain((0..self.nodes.len()).filter(|v| matches!(self.nodes[*v], Node::And(..))));
+        for (i, var) in ordered.enumerate() {
+            vars[var] = i + 1;
+        }
+        vars.into_iter().map(|v| Lit::new(v, false)).collect()
+    }
+
+    /// Writes the graph as an AIGER file, in the binary format if `binary` is set or else the ASCII format
+    pub fn write_aiger(&self, mut writer: impl Write, binary: bool) -> std::io::Result<()> {
+        let numbering = self.aiger_numbering();
+        let map = |lit: Lit| Lit(numbering[lit.var()].0 | lit.is_inverted() as u32);
+        writeln!(
+            writer,
+            "{} {} {} {} {} {}",
+            if binary { "aig" } else { "aag" },
+            self.inputs.len() + self.latches.len() + self.num_ands(),
+            self.inputs.len(),
+            self.latches.len(),
+            self.outputs.len(),
+            self.num_ands()
+        )?;
+        if !binary {
+            for (lit, _) in &self.inputs {
+                writeln!(writer, "{}", map(*lit))?;
+            }
+        }
+        for latch in &self.latches {
+            if !binary {
+                write!(writer, "{} ", map(latch.output))?;
+            }
+            write!(writer, "{}", map(latch.next))?;
+            match latch.init {
+                Some(false) => writeln!(writer)?,
+                Some(true) => writeln!(writer, " 1")?,
+                None => writeln!(writer, " {}", map(latch.output))?,
+            }
+        }
+        for (lit, _) in &self.outputs {
+            writeln!(writer, "{}", map(*lit))?;
+        }
+        for (var, node) in self.nodes.iter().enumerate() {
+            if let Node::And(a, b) = node {
+                let (lhs, a, b) = (numbering[var].0, map(*a).0, map(*b).0);
+                let (a, b) = if a > b { (a, b) } else { (b, a) };
+                if binary {
+                    write_delta(&mut writer, lhs - a)?;
+                    write_delta(&mut writer, a - b)?;
+                } else {
+                    writeln!(writer, "{lhs} {a} {b}")?;
+                }
+            }
+        }
+        let symbols = [
+            ('i', self.inputs.iter().map(|(_, n)| n).collect::<Vec<_>>()),
+            ('l', self.latches.iter().map(|l| &l.name).collect()),
+            ('o', self.outputs.iter().map(|(_, n)| n).collect()),
+        ];
+        for (kind, names) in symbols {
+            for (i, name) in names.into_iter().enumerate() {
+                if let Some(name) = name {
+                    writeln!(writer, "{kind}{i} {name}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a graph from an AIGER file, in either the binary or the ASCII format.
+    /// AND nodes are structurally hashed as they are read, so redundant ones are merged.
+    pub fn read_aiger(mut reader: impl Read) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        let mut parser = Parser { bytes, pos: 0 };
+
+        let header = parser.line()?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let binary = match fields.first() {
+            Some(&"aig") => true,
+            Some(&"aag") => false,
+            _ => return Err("Expected an AIGER header".to_string()),
+        };
+        let counts = fields[1..]
+            .iter()
+            .map(|f| f.parse::<usize>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if counts.len() < 5 {
+            return Err("Expected five counts in the AIGER header".to_string());
+        }
+        if counts[5..].iter().any(|c| *c != 0) {
+            return Err("AIGER properties beyond outputs are not supported".to_string());
+        }
+        let (m, i, l, o, a) = (counts[0], counts[1], counts[2], counts[3], counts[4]);
+        let defined = i
+            .checked_add(l)
+            .and_then(|x| x.checked_add(a))
+            .ok_or("Invalid counts in the AIGER header")?;
+        if binary && m != defined {
+            return Err("Expected M = I + L + A in a binary AIGER file".to_string());
+        }
+        // Every variable is a literal, and every line or AND node takes at least two bytes
+        let remaining = parser.bytes.len() - parser.pos;
+        let listed = l.checked_add(o).and_then(|x| x.checked_add(a));
+        if m > (u32::MAX >> 1) as usize
+            || defined > m
+            || listed.is_none_or(|x| x > remaining / 2)
+            || (!binary && i > remaining / 2)
+        {
+            return Err("The counts in the AIGER header do not match the file".to_string());
+        }
+
+        // The definitions of the variables in the file
+        let mut defs: HashMap<usize, Node> = HashMap::new();
+        let mut aig = Aig::new();
+        let mut lits: HashMap<usize, Lit> = HashMap::from([(0, Lit::FALSE)]);
+        let define = |defs: &mut HashMap<usize, Node>, lit: u32, node: Node| {
+            if lit & 1 == 1 || lit == 0 || (lit >> 1) as usize > m {
+                return Err(format!("Invalid definition of literal {lit}"));
+            }
+            match defs.insert((lit >> 1) as usize, node) {
+                Some(_) => Err(format!("Literal {lit} is defined twice")),
+                None => Ok(()),
+            }
+        };
+
+        for idx in 0..i {
+            let lit = if binary {
+                2 * (idx as u32 + 1)
+            } else {
+                parser.numbers::<1>()?[0]
+            };
+            define(&mut defs, lit, Node::Input(idx))?;
+            lits.insert((lit >> 1) as usize, aig.add_input(None));
+        }
+        let mut nexts = Vec::new();
+        for idx in 0..l {
+            let line = parser.line()?;
+            let mut numbers = line
+                .split_whitespace()
+                .map(|n| n.parse::<u32>().map_err(|e| e.to_string()));
+            let lit = if binary {
+                2 * (i + idx + 1) as u32
+            } else {
+                numbers.next().ok_or("Missing latch literal")??
+            };
+            let next = numbers.next().ok_or("Missing latch next state")??;
+            let init = match numbers.next().transpose()? {
+                None | Some(0) => Some(false),
+                Some(1) => Some(true),
+                Some(x) if x == lit => None,
+                Some(x) => return Err(format!("Invalid latch initial value {x}")),
+            };
+            define(&mut defs, lit, Node::Latch(idx))?;
+            lits.insert((lit >> 1) as usize, aig.add_latch(None, init));
+            nexts.push(next);
+        }
+        let mut outputs = Vec::new();
+        for _ in 0..o {
+            outputs.push(parser.numbers::<1>()?[0]);
+        }
+        for idx in 0..a {
+            let [lhs, a, b] = if binary {
+                let lhs = 2 * (i + l + idx + 1) as u32;
+                let a = lhs
+                    .checked_sub(parser.delta()?)
+                    .ok_or("Invalid AND delta")?;
+                let b = a.checked_sub(parser.delta()?).ok_or("Invalid AND delta")?;
+                [lhs, a, b]
+            } else {
+                parser.numbers::<3>()?
+            };
+            define(&mut defs, lhs, Node::And(Lit(a), Lit(b)))?;
+        }
+
+        // AND nodes may be listed in any order in the ASCII format
+        let mut resolve = |aig: &mut Aig, lit: u32| -> Result<Lit, String> {
+            let mut stack = vec![(lit >> 1) as usize];
+            // The variables whose fanins are being resolved
+            let mut visiting: HashSet<usize> = HashSet::new();
+            while let Some(&var) = stack.last() {
+                if lits.contains_key(&var) {
+                    stack.pop();
+                    continue;
+                }
+                let Some(Node::And(a, b)) = defs.get(&var) else {
+                    return Err(format!("Literal {} is undefined", 2 * var));
+                };
+                let pending: Vec<usize> = [a, b]
+                    .into_iter()
+                    .map(|x| x.var())
+                    .filter(|v| !lits.contains_key(v))
+                    .collect();
+                if pending.is_empty() {
+                    let fanin = |x: &Lit| Lit(lits[&x.var()].0 ^ x.is_inverted() as u32);
+                    let lit = aig.and(fanin(a), fanin(b));
+                    lits.insert(var, lit);
+                    stack.pop();
+                } else if visiting.insert(var) {
+                    stack.extend(pending);
+                } else {
+                    // The fanins were resolved first, unless one of them depends on this variable
+                    return Err(format!("Cycle detected through literal {}", 2 * var));
+                }
+            }
+            Ok(Lit(lits[&((lit >> 1) as usize)].0 ^ (lit & 1)))
+        };
+        for (idx, next) in nexts.into_iter().enumerate() {
+            let next = resolve(&mut aig, next)?;
+            let latch = aig.latches[idx].output;
+            aig.set_latch_next(latch, next)?;
+        }
+        for lit in outputs {
+            let lit = resolve(&mut aig, lit)?;
+            aig.add_output(lit, None)?;
+        }
+
+        // The optional symbol table, followed by comments
+        while parser.pos < parser.bytes.len() {
+            let line = parser.line()?;
+            if line == "c" {
+                break;
+            }
+            let (key, name) = line
+                .split_once(' ')
+                .ok_or(format!("Invalid symbol {line}"))?;
+            let idx: usize = key[1..]
+                .parse()
+                .map_err(|_| format!("Invalid symbol {line}"))?;
+            let name = Some(name.to_string());
+            let slot = match &key[..1] {
+                "i" => aig.inputs.get_mut(idx).map(|(_, n)| n),
+                "l" => aig.latches.get_mut(idx).map(|l| &mut l.name),
+                "o" => aig.outputs.get_mut(idx).map(|(_, n)| n),
+                _ => None,
+            };
+            *slot.ok_or(format!("Invalid symbol {line}"))? = name;
+        }
+        Ok(aig)
+    }
+}
+
+/// Builds the logic of `root` and the nodes driving it, visiting instances after their drivers without recursion.
+/// The literals of every output of the visited nodes are kept in `lits`.
+fn lower<I, F>(
+    aig: &mut Aig,
+    lits: &mut HashMap<NetRef<I>, Vec<Lit>>,
+    visiting: &mut HashSet<NetRef<I>>,
+    root: NetRef<I>,
+    decompose: &F,
+) -> Result<(), String>
+where
+    I: Instantiable,
+    F: Fn(&I, &mut Aig, &[Lit]) -> Result<Vec<Lit>, String>,
+{
+    let mut stack = vec![root];
+    while let Some(node) = stack.last().cloned() {
+        if lits.contains_key(&node) {
+            stack.pop();
+            continue;
+        }
+        let mut drivers = Vec::new();
+        for i in 0..node.get_num_input_ports() {
+            match (node.get_driver(i), node.get_driver_net(i)) {
+                (Some(d), Some((_, pin))) => drivers.push((d, pin)),
+                _ => {
+                    return Err(format!(
+                        "Input {i} of {} is unconnected",
+                        node.get_instance_name()
+                            .unwrap_or_else(|| node.get_identifier())
+                    ));
+                }
+            }
+        }
+        if visiting.insert(node.clone()) {
+            stack.extend(
+                drivers
+                    .into_iter()
+                    .map(|(d, _)| d)
+                    .filter(|d| !lits.contains_key(d)),
+            );
+            continue;
+        }
+        if let Some((d, _)) = drivers.iter().find(|(d, _)| !lits.contains_key(d)) {
+            return Err(format!(
+                "Cycle detected through {}",
+                d.get_instance_name().unwrap_or_else(|| d.get_identifier())
+            ));
+        }
+        let operands: Vec<Lit> = drivers.iter().map(|(d, pin)| lits[d][*pin]).collect();
+        // Aliases are plain wires
+        let outputs = match node.get_instance_type() {
+            Some(inst_type) => decompose(&inst_type, aig, &operands)?,
+            None => operands,
+        };
+        if outputs.len() != node.outputs().count() {
+            return Err(format!(
+                "Decomposition of {} returned {} outputs",
+                node.get_instance_name().unwrap(),
+                outputs.len()
+            ));
+        }
+        stack.pop();
+        lits.insert(node, outputs);
+    }
+    Ok(())
+}
+
+/// Returns the logic of the common [Gate]s by name.
+/// `AND`, `NAND`, `OR`, `NOR`, `XOR` and `XNOR` take any number of inputs, `INV`, `NOT` and `BUF` take one.
+/// `MUX` takes its inputs in the order `A`, `B`, `S` and selects `B` when `S` is true.
+pub fn decompose_gate(gate: &Gate, aig: &mut Aig, inputs: &[Lit]) -> Result<Vec<Lit>, String> {
+    let name = gate.get_gate_name().to_string();
+    let reduce = |aig: &mut Aig, init: Lit, f: fn(&mut Aig, Lit, Lit) -> Lit| {
+        inputs.iter().fold(init, |acc, x| f(aig, acc, *x))
+    };
+    let lit = match (name.as_str(), inputs) {
+        ("AND", [_, ..]) => reduce(aig, Lit::TRUE, Aig::and),
+        ("NAND", [_, ..]) => !reduce(aig, Lit::TRUE, Aig::and),
+        ("OR", [_, ..]) => reduce(aig, Lit::FALSE, Aig::or),
+        ("NOR", [_, ..]) => !reduce(aig, Lit::FALSE, Aig::or),
+        ("XOR", [_, ..]) => reduce(aig, Lit::FALSE, Aig::xor),
+        ("XNOR", [_, ..]) => !reduce(aig, Lit::FALSE, Aig::xor),
+        ("INV" | "NOT", [a]) => !*a,
+        ("BUF", [a]) => *a,
+        ("MUX", [a, b, s]) => aig.mux(*s, *b, *a),
+        _ => {
+            return Err(format!(
+                "No AIG decomposition of {name} with {} inputs",
+                inputs.len()
+            ));
+        }
+    };
+    Ok(vec![lit])
+}
+
+/// Returns the value of `lit` from the values of the nodes
+fn lit_value(values: &[bool], lit: Lit) -> bool {
+    values[lit.var()] ^ lit.is_inverted()
+}
+
+/// Returns the minterm of the values of `leaves`, with leaf `i` as bit `i`
+fn minterm(values: &[bool], leaves: &[Lit]) -> usize {
+    leaves
+        .iter()
+        .enumerate()
+        .fold(0, |m, (i, l)| m | (lit_value(values, *l) as usize) << i)
+}
+
+/// Returns the function of the one net that the instances named in `region` drive outside it, with its exact don't-cares,
+/// so a pass given to [Netlist::rewrite_region] can exploit them.
+/// The leaves are the nets driven from outside the region, returned by name in the order that the working netlist of [Netlist::rewrite_region] takes them as inputs.
+/// The don't-cares are found by simulating every value of the inputs of the combinational `netlist`, with its logic built by `decompose`,
+/// so a replacement that [LocalFunction::admits] leaves the outputs of the netlist unchanged.
+/// Returns an error if the region drives more than one net used outside it, if a net it drives also feeds one of its leaves,
+/// or if the netlist has more than [Aig::MAX_EXHAUSTIVE] inputs.
+pub fn region_function<I, F>(
+    netlist: &Netlist<I>,
+    region: &[Identifier],
+    decompose: F,
+) -> Result<(Vec<Identifier>, LocalFunction), String>
+where
+    I: Instantiable,
+    F: Fn(&I, &mut Aig, &[Lit]) -> Result<Vec<Lit>, String>,
+{
+    let nodes: Vec<NetRef<I>> = region
+        .iter()
+        .map(|name| {
+            netlist.find_instance(name).ok_or(format!(
+                "Instance {name} not found in netlist {}",
+                netlist.get_name()
+            ))
+        })
+        .collect::<Result<_, _>>()?;
+    let keep: HashSet<NetRef<I>> = nodes.iter().cloned().collect();
+    let inside = |d: &DrivenNet<I>| keep.contains(&d.clone().unwrap());
+
+    // The leaves are the nets driven from outside the region, and the root is the net of the region used outside it
+    let mut leaves: Vec<DrivenNet<I>> = Vec::new();
+    for driver in nodes
+        .iter()
+        .flat_map(|n| n.operands())
+        .filter_map(|(_, d)| d)
+    {
+        if !inside(&driver) && !leaves.iter().any(|l| *l.as_net() == *driver.as_net()) {
+            leaves.push(driver);
+        }
+    }
+    if leaves.len() > LocalFunction::MAX_LEAVES {
+        return Err(format!(
+            "Expected at most {} leaves, got {}",
+            LocalFunction::MAX_LEAVES,
+            leaves.len()
+        ));
+    }
+    let outside = netlist
+        .objects()
+        .filter(|n| !keep.contains(n))
+        .flat_map(|n| n.operands().filter_map(|(_, d)| d).collect::<Vec<_>>());
+    let mut roots: Vec<DrivenNet<I>> = Vec::new();
+    for driver in netlist.outputs().into_iter().map(|(d, _)| d).chain(outside) {
+        if inside(&driver) && !roots.iter().any(|r| *r.as_net() == *driver.as_net()) {
+            roots.push(driver);
+        }
+    }
+    let [root] = roots.as_slice() else {
+        return Err(format!(
+            "The region must drive one net used outside it, but drives {}",
+            roots.len()
+        ));
+    };
+    let inputs: Vec<DrivenNet<I>> = netlist.inputs().collect();
+    if inputs.len() > Aig::MAX_EXHAUSTIVE {
+        return Err(format!(
+            "Expected at most {} inputs, got {}",
+            Aig::MAX_EXHAUSTIVE,
+            inputs.len()
+        ));
+    }
+
+    // The root is cut into a free input, so inverting it changes only the net of the region
+    let mut aig = Aig::new();
+    let mut lits: HashMap<NetRef<I>, Vec<Lit>> = HashMap::new();
+    for input in &inputs {
+        let lit = aig.add_input(Some(input.get_identifier().to_string()));
+        lits.insert(input.clone().unwrap(), vec![lit]);
+    }
+    let free = aig.add_input(None);
+    let root_node = root.clone().unwrap();
+    let mut cut = vec![Lit::FALSE; root_node.nets().count()];
+    cut[root.get_position()] = free;
+    lits.insert(root_node.clone(), cut);
+    let mut visiting = HashSet::new();
+    for (driver, net) in netlist.outputs() {
+        let node = driver.clone().unwrap();
+        lower(&mut aig, &mut lits, &mut visiting, node.clone(), &decompose)?;
+        let lit = lits[&node][driver.get_position()];
+        aig.add_output(lit, Some(net.get_identifier().to_string()))?;
+    }
+    for leaf in &leaves {
+        lower(
+            &mut aig,
+            &mut lits,
+            &mut visiting,
+            leaf.clone().unwrap(),
+            &decompose,
+        )?;
+    }
+    for constraint in netlist.get_input_constraints() {
+        aig.add_constraint(constraint)?;
+    }
+    let leaf_lits: Vec<Lit> = leaves
+        .iter()
+        .map(|l| lits[&l.clone().unwrap()][l.get_position()])
+        .collect();
+
+    // The logic of the region is built again over the leaves
+    let mut inner: HashMap<NetRef<I>, Vec<Lit>> = leaves
+        .iter()
+        .map(|l| {
+            let node = l.clone().unwrap();
+            let lits = lits[&node].clone();
+            (node, lits)
+        })
+        .collect();
+    lower(
+        &mut aig,
+        &mut inner,
+        &mut HashSet::new(),
+        root_node.clone(),
+        &decompose,
+    )?;
+    let root_lit = inner[&root_node][root.get_position()];
+
+    let mut function = LocalFunction::new(leaves.len());
+    for m in 0..1usize << inputs.len() {
+        let mut values: Vec<bool> = (0..inputs.len()).map(|i| m >> i & 1 == 1).collect();
+        if !aig.satisfies(&values) {
+            continue;
+        }
+        values.push(false);
+        let value = lit_value(&aig.simulate(&values, &[], None), root_lit);
+        values[inputs.len()] = value;
+        let kept = aig.simulate(&values, &[], None);
+        let flipped = aig.simulate(&values, &[], Some(free.var()));
+        let minterm = minterm(&kept, &leaf_lits);
+        if minterm != self::minterm(&flipped, &leaf_lits) {
+            return Err(format!(
+                "The net {} of the region feeds one of its leaves",
+                root.as_net()
+            ));
+        }
+        function.record(minterm, value, aig.sinks(&kept) != aig.sinks(&flipped));
+    }
+    let names = leaves
+        .iter()
+        .map(|l| l.as_net().get_identifier().clone())
+        .collect();
+    Ok((names, function))
+}
+
+/// Writes a delta of a binary AIGER file, seven bits at a time
+fn write_delta(writer: &mut impl Write, mut x: u32) -> std::io::Result<()> {
+    while x & !0x7f != 0 {
+        writer.write_all(&[(x & 0x7f) as u8 | 0x80])?;
+        x >>= 7;
+    }
+    writer.write_all(&[x as u8])
+}
+
+/// A cursor over the bytes of an AIGER file
+struct Parser {
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Returns the next line of text
+    fn line(&mut self) -> Result<&str, String> {
+        let rest = &self.bytes[self.pos..];
+        let end = rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or("Unexpected end of AIGER file")?;
+        self.pos += end + 1;
+        std::str::from_utf8(&rest[..end]).map_err(|e| e.to_string())
+    }
+
+    /// Returns the numbers on the next line of text
+    fn numbers<const N: usize>(&mut self) -> Result<[u32; N], String> {
+        let line = self.line()?;
+        let numbers = line
+            .split_whitespace()
+            .map(|n| n.parse::<u32>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        numbers
+            .try_into()
+            .map_err(|_| format!("Expected {N} numbers on line {line}"))
+    }
+
+    /// Returns the next delta of a binary AND node
+    fn delta(&mut self) -> Result<u32, String> {
+        let mut x: u32 = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.pos)
+                .ok_or("Unexpected end of AIGER file")?;
+            self.pos += 1;
+            x |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(x);
+            }
+        }
+        Err("Invalid delta in binary AIGER file".to_string())
+    }
+}
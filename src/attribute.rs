@@ -17,6 +17,21 @@ pub type AttributeKey = String;
 /// A Verilog attribute can be assigned a string value: bitvec = (* dont_touch = true *)
 pub type AttributeValue = Option<String>;
 
+/// Separates the namespaces of an attribute key, like `xilinx.LOC`
+pub const NAMESPACE_SEPARATOR: char = '.';
+
+/// Returns the key `key` inside the namespace `namespace`, like `xilinx.LOC`
+pub fn namespaced_key(namespace: &str, key: &str) -> AttributeKey {
+    format!("{namespace}{NAMESPACE_SEPARATOR}{key}")
+}
+
+/// Returns `true` if `key` is inside `namespace`, directly or through nested namespaces.
+/// For example, `xilinx.place.LOC` is in both `xilinx` and `xilinx.place`.
+pub fn in_namespace(key: &str, namespace: &str) -> bool {
+    key.strip_prefix(namespace)
+        .is_some_and(|rest| rest.starts_with(NAMESPACE_SEPARATOR))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// An attribute can add information to instances and wires in string form, like 'dont_touch'
 pub struct Attribute {
@@ -40,6 +55,24 @@ impl Attribute {
         &self.v
     }
 
+    /// Returns the namespace of the key, like `xilinx` for `xilinx.LOC`, or [None] if the key has no namespace.
+    /// Nested namespaces are kept together, so the namespace of `xilinx.place.LOC` is `xilinx.place`.
+    pub fn namespace(&self) -> Option<&str> {
+        self.k.rsplit_once(NAMESPACE_SEPARATOR).map(|(ns, _)| ns)
+    }
+
+    /// Returns the key without its namespace, like `LOC` for `xilinx.LOC`
+    pub fn local_key(&self) -> &str {
+        self.k
+            .rsplit_once(NAMESPACE_SEPARATOR)
+            .map_or(self.k.as_str(), |(_, k)| k)
+    }
+
+    /// Returns `true` if the key is inside `namespace`, directly or through nested namespaces
+    pub fn in_namespace(&self, namespace: &str) -> bool {
+        in_namespace(&self.k, namespace)
+    }
+
     /// Map a attribute key-value pairs to the Attribute struct
     pub fn from_pairs(
         iter: impl Iterator<Item = (AttributeKey, AttributeValue)>,
@@ -59,6 +92,7 @@ impl std::fmt::Display for Attribute {
 }
 
 /// A dedicated type to parameters for instantiables
+#[derive(Debug, Clone, PartialEq)]
 pub enum Parameter {
     /// An integer parameter
     Integer(i32),
@@ -72,7 +106,9 @@ impl std::fmt::Display for Parameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Parameter::Integer(i) => write!(f, "{i}"),
-            Parameter::Real(_r) => todo!(),
+            // Verilog reads a number as a real only if it has a decimal point
+            Parameter::Real(r) if r.is_finite() && r.fract() == 0.0 => write!(f, "{r:.1}"),
+            Parameter::Real(r) => write!(f, "{r}"),
             Parameter::BitVec(bv) => write!(
                 f,
                 "{}'b{}",
@@ -86,6 +122,44 @@ impl std::fmt::Display for Parameter {
     }
 }
 
+impl std::str::FromStr for Parameter {
+    type Err = String;
+
+    /// Decodes a Verilog literal: sized literals become bit vectors and plain numbers integers or reals
+    fn from_str(lit: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("Unsupported literal {lit}");
+        let Some((width, value)) = lit.split_once('\'') else {
+            return match lit.contains('.') {
+                true => lit.parse().map(Parameter::Real).map_err(|_| bad()),
+                false => lit.parse().map(Parameter::Integer).map_err(|_| bad()),
+            };
+        };
+        let width: usize = width.parse().map_err(|_| bad())?;
+        let mut chars = value.chars();
+        let bits_per_digit = match chars.next().map(|c| c.to_ascii_lowercase()) {
+            Some('b') => 1,
+            Some('o') => 3,
+            Some('h') => 4,
+            Some('d') => {
+                let v: u64 = chars.as_str().replace('_', "").parse().map_err(|_| bad())?;
+                return Ok(Parameter::BitVec(
+                    (0..width).map(|i| i < 64 && (v >> i) & 1 == 1).collect(),
+                ));
+            }
+            _ => return Err(bad()),
+        };
+        let mut bits = BitVec::new();
+        for c in chars.rev().filter(|c| *c != '_') {
+            let digit = c.to_digit(1 << bits_per_digit).ok_or_else(bad)?;
+            for i in 0..bits_per_digit {
+                bits.push((digit >> i) & 1 == 1);
+            }
+        }
+        bits.resize(width, false);
+        Ok(Parameter::BitVec(bits))
+    }
+}
+
 /// Filter nodes/nets in the netlist by some attribute, like "dont_touch"
 pub struct AttributeFilter<'a, I: Instantiable> {
     // A reference to the underlying netlist
@@ -156,6 +230,22 @@ where
     AttributeFilter::new(netlist, vec!["dont_touch".to_string()])
 }
 
+/// Returns a filtering of nodes and nets with any attribute inside `namespace`, like `xilinx`
+pub fn namespace_filter<'a, I>(netlist: &'a Netlist<I>, namespace: &str) -> AttributeFilter<'a, I>
+where
+    I: Instantiable,
+{
+    let mut keys: Vec<AttributeKey> = netlist
+        .objects()
+        .flat_map(|n| n.attributes().collect::<Vec<_>>())
+        .filter(|a| a.in_namespace(namespace))
+        .map(|a| a.key().clone())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    AttributeFilter::new(netlist, keys)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,6 +271,22 @@ mod tests {
         assert!(real_attrs.last().unwrap().value().is_none());
     }
 
+    #[test]
+    fn attribute_namespaces() {
+        let loc = Attribute::new(namespaced_key("xilinx.place", "LOC"), None);
+        assert_eq!(loc.key(), "xilinx.place.LOC");
+        assert_eq!(loc.namespace(), Some("xilinx.place"));
+        assert_eq!(loc.local_key(), "LOC");
+        assert!(loc.in_namespace("xilinx"));
+        assert!(loc.in_namespace("xilinx.place"));
+        assert!(!loc.in_namespace("xil"));
+        assert!(!loc.in_namespace("xilinx.place.LOC"));
+
+        let plain = Attribute::new("dont_touch".to_string(), None);
+        assert_eq!(plain.namespace(), None);
+        assert_eq!(plain.local_key(), "dont_touch");
+    }
+
     #[test]
     fn test_parameter_fmt() {
         let p1 = Parameter::Integer(42);
@@ -0,0 +1,419 @@
+/*!
+
+  Cells with bus ports whose widths are resolved from parameters, like an N-bit adder macro.
+
+*/
+
+use crate::{
+    attribute::Parameter,
+    circuit::{Identifier, Instantiable, Net},
+    format_id,
+    netlist::{DrivenNet, NetRef, Netlist},
+};
+use std::rc::Rc;
+
+/// An expression for the width of a port in terms of the parameters of its cell, like `N+1` or `$clog2(DEPTH)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WidthExpr {
+    /// A constant width
+    Const(usize),
+    /// The value of an integer parameter
+    Param(Identifier),
+    /// The sum of two widths
+    Add(Box<WidthExpr>, Box<WidthExpr>),
+    /// The difference of two widths, which must not be negative
+    Sub(Box<WidthExpr>, Box<WidthExpr>),
+    /// The product of two widths
+    Mul(Box<WidthExpr>, Box<WidthExpr>),
+    /// The quotient of two widths, rounded down
+    Div(Box<WidthExpr>, Box<WidthExpr>),
+    /// The ceiling of the base-2 logarithm of a width, like the Verilog `$clog2`
+    Clog2(Box<WidthExpr>),
+}
+
+impl WidthExpr {
+    /// Evaluates the expression with the parameter values in `params`
+    pub fn eval(&self, params: &[(Identifier, Parameter)]) -> Result<usize, String> {
+        let binary =
+            |a: &WidthExpr, b: &WidthExpr| Ok::<_, String>((a.eval(params)?, b.eval(params)?));
+        match self {
+            WidthExpr::Const(c) => Ok(*c),
+            WidthExpr::Param(id) => match params.iter().find(|(k, _)| k == id) {
+                Some((_, Parameter::Integer(v))) => {
+                    usize::try_from(*v).map_err(|_| format!("Parameter {id} is negative"))
+                }
+                Some((_, p)) => Err(format!("Parameter {id} is not an integer: {p}")),
+                None => Err(format!("Unknown parameter {id}")),
+            },
+            WidthExpr::Add(a, b) => {
+                let (a, b) = binary(a, b)?;
+                a.checked_add(b).ok_or(format!("Width {self} overflows"))
+            }
+            WidthExpr::Sub(a, b) => {
+                let (a, b) = binary(a, b)?;
+                a.checked_sub(b).ok_or(format!("Width {self} is negative"))
+            }
+            WidthExpr::Mul(a, b) => {
+                let (a, b) = binary(a, b)?;
+                a.checked_mul(b).ok_or(format!("Width {self} overflows"))
+            }
+            WidthExpr::Div(a, b) => {
+                let (a, b) = binary(a, b)?;
+                a.checked_div(b)
+                    .ok_or(format!("Width {self} divides by zero"))
+            }
+            WidthExpr::Clog2(a) => {
+                let a = a.eval(params)?;
+                // Past the largest power of two, the next one is 2 to the number of bits
+                let bits = a
+                    .checked_next_power_of_two()
+                    .map_or(usize::BITS, |p| p.trailing_zeros());
+                Ok(bits as usize)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for WidthExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WidthExpr::Const(c) => write!(f, "{c}"),
+            WidthExpr::Param(id) => write!(f, "{id}"),
+            WidthExpr::Add(a, b) => write!(f, "({a}+{b})"),
+            WidthExpr::Sub(a, b) => write!(f, "({a}-{b})"),
+            WidthExpr::Mul(a, b) => write!(f, "({a}*{b})"),
+            WidthExpr::Div(a, b) => write!(f, "({a}/{b})"),
+            WidthExpr::Clog2(a) => write!(f, "$clog2({a})"),
+        }
+    }
+}
+
+impl From<usize> for WidthExpr {
+    fn from(width: usize) -> Self {
+        WidthExpr::Const(width)
+    }
+}
+
+impl std::str::FromStr for WidthExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut parser = ExprParser { tokens, pos: 0 };
+        let expr = parser.sum()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("Unexpected '{c}' in width {s}")),
+        }
+    }
+}
+
+/// A recursive descent parser for [WidthExpr]
+struct ExprParser {
+    /// The characters of the expression, without whitespace
+    tokens: Vec<char>,
+    /// The position of the next character
+    pos: usize,
+}
+
+impl ExprParser {
+    /// Returns the next character without consuming it
+    fn peek(&self) -> Option<char> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    /// Consumes the next character if it is `c`
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    /// Consumes characters while they satisfy `f`
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        self.tokens[start..self.pos].iter().collect()
+    }
+
+    /// Parses terms separated by `+` and `-`
+    fn sum(&mut self) -> Result<WidthExpr, String> {
+        let mut expr = self.product()?;
+        loop {
+            if self.eat('+') {
+                expr = WidthExpr::Add(Box::new(expr), Box::new(self.product()?));
+            } else if self.eat('-') {
+                expr = WidthExpr::Sub(Box::new(expr), Box::new(self.product()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Parses factors separated by `*` and `/`
+    fn product(&mut self) -> Result<WidthExpr, String> {
+        let mut expr = self.factor()?;
+        loop {
+            if self.eat('*') {
+                expr = WidthExpr::Mul(Box::new(expr), Box::new(self.factor()?));
+            } else if self.eat('/') {
+                expr = WidthExpr::Div(Box::new(expr), Box::new(self.factor()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Parses a constant, a parameter, a `$clog2` call or a parenthesized expression
+    fn factor(&mut self) -> Result<WidthExpr, String> {
+        let paren = |p: &mut Self| {
+            if !p.eat('(') {
+                return Err("Expected '('".to_string());
+            }
+            let expr = p.sum()?;
+            match p.eat(')') {
+                true => Ok(expr),
+                false => Err("Expected ')'".to_string()),
+            }
+        };
+        match self.peek() {
+            Some('(') => paren(self),
+            Some('$') => {
+                self.pos += 1;
+                match self.take_while(|c| c.is_ascii_alphanumeric()).as_str() {
+                    "clog2" => Ok(WidthExpr::Clog2(Box::new(paren(self)?))),
+                    f => Err(format!("Unknown function ${f}")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                digits
+                    .parse()
+                    .map(WidthExpr::Const)
+                    .map_err(|e| format!("Bad width {digits}: {e}"))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                Ok(WidthExpr::Param(name.into()))
+            }
+            Some(c) => Err(format!("Unexpected '{c}'")),
+            None => Err("Unexpected end of width".to_string()),
+        }
+    }
+}
+
+/// The definition of a cell with bus ports sized by its parameters.
+/// Each [BusCellDef::instantiate] resolves the widths and generates the bits of every port.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusCellDef {
+    /// The name of the cell
+    name: Identifier,
+    /// The parameters of the cell, with their default values
+    params: Vec<(Identifier, Parameter)>,
+    /// The input buses, with their widths
+    inputs: Vec<(Identifier, WidthExpr)>,
+    /// The output buses, with their widths
+    outputs: Vec<(Identifier, WidthExpr)>,
+}
+
+impl BusCellDef {
+    /// Creates the definition of a cell named `name` with no parameters or ports
+    pub fn new(name: Identifier) -> Self {
+        Self {
+            name,
+            params: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Adds the parameter `id` with the default value `default`
+    pub fn with_parameter(mut self, id: Identifier, default: Parameter) -> Self {
+        self.params.push((id, default));
+        self
+    }
+
+    /// Adds the input bus `name` that is `width` bits wide
+    pub fn with_input(mut self, name: Identifier, width: WidthExpr) -> Self {
+        self.inputs.push((name, width));
+        self
+    }
+
+    /// Adds the output bus `name` that is `width` bits wide
+    pub fn with_output(mut self, name: Identifier, width: WidthExpr) -> Self {
+        self.outputs.push((name, width));
+        self
+    }
+
+    /// Returns the name of the cell
+    pub fn get_name(&self) -> &Identifier {
+        &self.name
+    }
+
+    /// Returns the widths of the input and output buses under the parameter values in `params`
+    fn widths(
+        &self,
+        params: &[(Identifier, Parameter)],
+    ) -> Result<(Vec<usize>, Vec<usize>), String> {
+        let widths = |buses: &[(Identifier, WidthExpr)]| {
+            buses
+                .iter()
+                .map(|(name, w)| {
+                    w.eval(params)
+                        .map_err(|e| format!("Port {name} of {}: {e}", self.name))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok((widths(&self.inputs)?, widths(&self.outputs)?))
+    }
+
+    /// Creates an instance type with the parameters in `overrides` set and the rest at their defaults
+    pub fn instantiate(
+        self: &Rc<Self>,
+        overrides: &[(Identifier, Parameter)],
+    ) -> Result<BusCell, String> {
+        let mut params = self.params.clone();
+        for (id, val) in overrides {
+            match params.iter_mut().find(|(k, _)| k == id) {
+                Some((_, v)) => *v = val.clone(),
+                None => return Err(format!("{} has no parameter {id}", self.name)),
+            }
+        }
+        let (input_widths, output_widths) = self.widths(&params)?;
+        let bits = |buses: &[(Identifier, WidthExpr)], widths: &[usize]| {
+            buses
+                .iter()
+                .zip(widths)
+                .flat_map(|((name, _), w)| {
+                    (0..*w).map(move |i| Net::new_logic(format_id!("{name}[{i}]")))
+                })
+                .collect()
+        };
+        Ok(BusCell {
+            inputs: bits(&self.inputs, &input_widths),
+            outputs: bits(&self.outputs, &output_widths),
+            def: self.clone(),
+            params,
+            input_widths,
+            output_widths,
+        })
+    }
+}
+
+/// An instance type of a [BusCellDef], with its parameters set and its buses expanded to bits.
+/// Parameters can be overridden later only if the widths of the buses stay the same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusCell {
+    /// The definition of the cell
+    def: Rc<BusCellDef>,
+    /// The values of every parameter
+    params: Vec<(Identifier, Parameter)>,
+    /// The widths of the input buses
+    input_widths: Vec<usize>,
+    /// The widths of the output buses
+    output_widths: Vec<usize>,
+    /// The bits of the input buses, lsb first
+    inputs: Vec<Net>,
+    /// The bits of the output buses, lsb first
+    outputs: Vec<Net>,
+}
+
+impl BusCell {
+    /// Returns the definition of the cell
+    pub fn get_def(&self) -> &Rc<BusCellDef> {
+        &self.def
+    }
+
+    /// Returns the input buses with their resolved widths
+    pub fn input_buses(&self) -> impl Iterator<Item = (&Identifier, usize)> {
+        self.def
+            .inputs
+            .iter()
+            .map(|(n, _)| n)
+            .zip(self.input_widths.iter().copied())
+    }
+
+    /// Returns the output buses with their resolved widths
+    pub fn output_buses(&self) -> impl Iterator<Item = (&Identifier, usize)> {
+        self.def
+            .outputs
+            .iter()
+            .map(|(n, _)| n)
+            .zip(self.output_widths.iter().copied())
+    }
+}
+
+impl Instantiable for BusCell {
+    fn get_name(&self) -> &Identifier {
+        &self.def.name
+    }
+
+    fn get_input_ports(&self) -> impl IntoIterator<Item = &Net> {
+        &self.inputs
+    }
+
+    fn get_output_ports(&self) -> impl IntoIterator<Item = &Net> {
+        &self.outputs
+    }
+
+    fn has_parameter(&self, id: &Identifier) -> bool {
+        self.params.iter().any(|(k, _)| k == id)
+    }
+
+    fn get_parameter(&self, id: &Identifier) -> Option<Parameter> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == id)
+            .map(|(_, v)| v.clone())
+    }
+
+    fn parameters(&self) -> impl Iterator<Item = (Identifier, Parameter)> {
+        self.params.clone().into_iter()
+    }
+
+    fn set_parameter(&mut self, id: &Identifier, val: Parameter) -> Option<Parameter> {
+        let mut params = self.params.clone();
+        let old = params.iter_mut().find(|(k, _)| k == id)?;
+        let old = std::mem::replace(&mut old.1, val);
+        let widths = self.def.widths(&params).ok()?;
+        if widths != (self.input_widths.clone(), self.output_widths.clone()) {
+            return None;
+        }
+        self.params = params;
+        Some(old)
+    }
+}
+
+/// Instantiates `cell` in `netlist` with each input bus connected to the nets in `inputs`, lsb first.
+/// Returns an error if the number of buses or the width of any bus does not match the cell.
+pub fn insert_bus_cell<I>(
+    netlist: &Rc<Netlist<I>>,
+    cell: BusCell,
+    inst_name: Identifier,
+    inputs: &[Vec<DrivenNet<I>>],
+) -> Result<NetRef<I>, String>
+where
+    I: Instantiable + From<BusCell>,
+{
+    if inputs.len() != cell.input_widths.len() {
+        return Err(format!(
+            "{inst_name} has {} input buses, got {}",
+            cell.input_widths.len(),
+            inputs.len()
+        ));
+    }
+    for ((name, width), nets) in cell.input_buses().zip(inputs) {
+        if nets.len() != width {
+            return Err(format!(
+                "Input {name} of {inst_name} is {width} bits wide, got {} nets",
+                nets.len()
+            ));
+        }
+    }
+    let operands: Vec<DrivenNet<I>> = inputs.iter().flatten().cloned().collect();
+    Ok(netlist.insert_gate(cell.into(), inst_name, &operands)?)
+}
@@ -6,7 +6,7 @@
 
 use crate::attribute::Parameter;
 
-/// Signals in a circuit can be binary, tri-state, or four-state.
+/// Signals in a circuit can be binary, tri-state, four-state, constant supplies, or multi-bit vectors.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DataType {
@@ -16,6 +16,17 @@ pub enum DataType {
     ThreeState,
     /// A logical 0, 1, high-Z, or unknown (X)
     FourState,
+    /// A ground supply that is always a logical 0, like `supply0` in Verilog
+    Supply0,
+    /// A power supply that is always a logical 1, like `supply1` in Verilog
+    Supply1,
+    /// A vector of four-state bits, like `logic signed [7:0]`
+    Vector {
+        /// The number of bits
+        width: usize,
+        /// Whether the bits are a two's complement number
+        signed: bool,
+    },
 }
 
 impl DataType {
@@ -38,6 +49,146 @@ impl DataType {
     pub fn logic() -> Self {
         DataType::FourState
     }
+
+    /// Returns the data type for a supply that is always `value`
+    pub fn supply(value: bool) -> Self {
+        match value {
+            true => DataType::Supply1,
+            false => DataType::Supply0,
+        }
+    }
+
+    /// Returns the data type for an unsigned vector of `width` four-state bits
+    pub fn unsigned(width: usize) -> Self {
+        DataType::Vector {
+            width,
+            signed: false,
+        }
+    }
+
+    /// Returns the data type for a signed vector of `width` four-state bits
+    pub fn signed(width: usize) -> Self {
+        DataType::Vector {
+            width,
+            signed: true,
+        }
+    }
+
+    /// Returns the number of bits, which is 1 for every type but vectors
+    pub fn width(&self) -> usize {
+        match self {
+            DataType::Vector { width, .. } => *width,
+            _ => 1,
+        }
+    }
+
+    /// Returns `true` if the type is a signed vector
+    pub fn is_signed(&self) -> bool {
+        matches!(self, DataType::Vector { signed: true, .. })
+    }
+
+    /// Returns the value of the type if it is a supply
+    pub fn get_supply(&self) -> Option<bool> {
+        match self {
+            DataType::Supply0 => Some(false),
+            DataType::Supply1 => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if a net of this type can drive a port of type `sink`.
+    /// A driver must not produce values that the sink cannot represent, and must have the same width and signedness.
+    /// A supply can drive any port a two-state signal can, but a supply port must be driven by the same supply.
+    pub fn can_drive(&self, sink: &DataType) -> bool {
+        if sink.get_supply().is_some() {
+            return self == sink;
+        }
+        self.width() == sink.width()
+            && self.is_signed() == sink.is_signed()
+            && self.num_states() <= sink.num_states()
+    }
+
+    /// Returns the number of values a bit of this type can take
+    fn num_states(&self) -> usize {
+        match self {
+            DataType::Supply0 | DataType::Supply1 => 1,
+            DataType::TwoState => 2,
+            DataType::ThreeState => 3,
+            DataType::FourState | DataType::Vector { .. } => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataType::TwoState => write!(f, "bit"),
+            DataType::ThreeState => write!(f, "tri"),
+            DataType::FourState => write!(f, "logic"),
+            DataType::Supply0 => write!(f, "supply0"),
+            DataType::Supply1 => write!(f, "supply1"),
+            DataType::Vector { width, signed } => {
+                write!(f, "logic ")?;
+                if *signed {
+                    write!(f, "signed ")?;
+                }
+                write!(f, "[{}:0]", width.saturating_sub(1))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for DataType {
+    type Err = String;
+
+    /// Reads a data type as it is displayed, like `bit` or `logic signed [7:0]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bit" => return Ok(DataType::TwoState),
+            "tri" => return Ok(DataType::ThreeState),
+            "logic" => return Ok(DataType::FourState),
+            "supply0" => return Ok(DataType::Supply0),
+            "supply1" => return Ok(DataType::Supply1),
+            _ => (),
+        }
+        let bad = || format!("Unknown data type {s}");
+        let range = s.strip_prefix("logic ").ok_or_else(bad)?;
+        let (signed, range) = match range.strip_prefix("signed ") {
+            Some(range) => (true, range),
+            None => (false, range),
+        };
+        let msb: usize = range
+            .strip_prefix('[')
+            .and_then(|r| r.strip_suffix(":0]"))
+            .and_then(|msb| msb.parse().ok())
+            .ok_or_else(bad)?;
+        Ok(DataType::Vector {
+            width: msb + 1,
+            signed,
+        })
+    }
+}
+
+/// The direction of a port on a module boundary
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortDirection {
+    /// The port is driven from outside the module
+    Input,
+    /// The port is driven from inside the module
+    Output,
+    /// The port is bidirectional, like a tri-state pad, and can be driven from both sides
+    Inout,
+}
+
+impl std::fmt::Display for PortDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortDirection::Input => write!(f, "input"),
+            PortDirection::Output => write!(f, "output"),
+            PortDirection::Inout => write!(f, "inout"),
+        }
+    }
 }
 
 /// The type of identifier labelling a circuit node
@@ -63,7 +214,9 @@ pub struct Identifier {
 }
 
 impl Identifier {
-    /// Creates a new identifier with the given name
+    /// Creates a new identifier with the given name.
+    /// Names starting with `\` are escaped, names like `a[3]` are bit-slices,
+    /// and other names are escaped if they are not legal in Verilog (see [VerilogEscaping]).
     pub fn new(name: String) -> Self {
         if let Some(root) = name.strip_prefix('\\') {
             return Identifier {
@@ -72,27 +225,21 @@ impl Identifier {
             };
         }
 
-        // Certainly not an exhaustive list.
-        // TODO(matth2k): Implement a true isEscaped()
-        let esc_chars = [' ', '\\', '(', ')', ',', '+', '-'];
-        if name.chars().any(|c| esc_chars.contains(&c)) {
+        if let Some((rname, index)) = name.strip_suffix(']').and_then(|n| n.split_once('['))
+            && let Ok(s) = index.parse::<usize>()
+            && VerilogEscaping::is_simple(rname)
+        {
             return Identifier {
-                name,
-                id_type: IdentifierType::Escaped,
+                name: rname.to_string(),
+                id_type: IdentifierType::BitSlice(s),
             };
         }
 
-        if name.contains('[') && name.ends_with(']') {
-            let name_ind = name.find('[').unwrap();
-            let rname = &name[..name_ind];
-            let index_start = name_ind + 1;
-            let slice = name[index_start..name.len() - 1].parse::<usize>();
-            if let Ok(s) = slice {
-                return Identifier {
-                    name: rname.to_string(),
-                    id_type: IdentifierType::BitSlice(s),
-                };
-            }
+        if !VerilogEscaping::is_simple(&name) {
+            return Identifier {
+                name,
+                id_type: IdentifierType::Escaped,
+            };
         }
 
         Identifier {
@@ -126,14 +273,296 @@ impl Identifier {
 
     /// Emit the name as suitable for an HDL like Verilog. This takes into account bit-slicing and escaped identifiers
     pub fn emit_name(&self) -> String {
-        match &self.id_type {
-            IdentifierType::Normal => self.name.clone(),
-            IdentifierType::BitSlice(index) => format!("{}[{}]", self.name, index),
-            IdentifierType::Escaped => format!("\\{} ", self.name),
+        self.emit_with(&VerilogEscaping)
+    }
+
+    /// Emits the name as it is written in the format of `policy`, like [VhdlEscaping] for VHDL
+    pub fn emit_with(&self, policy: &dyn EscapePolicy) -> String {
+        policy.emit(self)
+    }
+}
+
+/// The rules an output format names identifiers by: which names are legal as they are, and how the others are escaped.
+/// Emitters write names through a policy, so each format gets legal names from the same [Identifier].
+pub trait EscapePolicy {
+    /// Returns `true` if `name` is not legal as it is and must be escaped
+    fn needs_escape(&self, name: &str) -> bool;
+
+    /// Returns `name` escaped for the format
+    fn escape(&self, name: &str) -> String;
+
+    /// Returns the bit `index` of the bus `name`, where `name` is already escaped if needed.
+    /// **The default implementation writes the index in brackets, like `a[3]`.**
+    fn bit(&self, name: &str, index: usize) -> String {
+        format!("{name}[{index}]")
+    }
+
+    /// Returns `id` as it is written in the format. Escaped identifiers stay escaped, and other names are escaped if they need to be.
+    fn emit(&self, id: &Identifier) -> String {
+        let name = match id.is_escaped() || self.needs_escape(id.get_name()) {
+            true => self.escape(id.get_name()),
+            false => id.get_name().to_string(),
+        };
+        match id.get_bit_index() {
+            Some(index) => self.bit(&name, index),
+            None => name,
         }
     }
 }
 
+/// Returns `true` if `name` starts with a letter or `_` and continues with letters, digits, `_` or any of `extra`
+fn is_simple_name(name: &str, extra: &[char]) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || extra.contains(&c))
+}
+
+/// The naming rules of Verilog: simple identifiers are letters, digits, `_` and `$` not starting with a digit or `$`
+/// that are not keywords, and other names are escaped like `\a+b ` or `\wire `
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerilogEscaping;
+
+impl VerilogEscaping {
+    /// Returns `true` if `name` is made of the characters of a simple identifier, even if it is a keyword.
+    /// Keywords are still plain names, which are only escaped when they are written.
+    fn is_simple(name: &str) -> bool {
+        is_simple_name(name, &['$'])
+    }
+
+    /// Returns `true` if `name` is a keyword, which is a plain name that is only escaped when it is written
+    pub(crate) fn is_keyword(name: &str) -> bool {
+        Self::RESERVED.contains(&name)
+    }
+
+    /// The keywords of Verilog-2005, which are case-sensitive
+    const RESERVED: [&str; 124] = [
+        "always",
+        "and",
+        "assign",
+        "automatic",
+        "begin",
+        "buf",
+        "bufif0",
+        "bufif1",
+        "case",
+        "casex",
+        "casez",
+        "cell",
+        "cmos",
+        "config",
+        "deassign",
+        "default",
+        "defparam",
+        "design",
+        "disable",
+        "edge",
+        "else",
+        "end",
+        "endcase",
+        "endconfig",
+        "endfunction",
+        "endgenerate",
+        "endmodule",
+        "endprimitive",
+        "endspecify",
+        "endtable",
+        "endtask",
+        "event",
+        "for",
+        "force",
+        "forever",
+        "fork",
+        "function",
+        "generate",
+        "genvar",
+        "highz0",
+        "highz1",
+        "if",
+        "ifnone",
+        "incdir",
+        "include",
+        "initial",
+        "inout",
+        "input",
+        "instance",
+        "integer",
+        "join",
+        "large",
+        "liblist",
+        "library",
+        "localparam",
+        "macromodule",
+        "medium",
+        "module",
+        "nand",
+        "negedge",
+        "nmos",
+        "nor",
+        "noshowcancelled",
+        "not",
+        "notif0",
+        "notif1",
+        "or",
+        "output",
+        "parameter",
+        "pmos",
+        "posedge",
+        "primitive",
+        "pull0",
+        "pull1",
+        "pulldown",
+        "pullup",
+        "pulsestyle_ondetect",
+        "pulsestyle_onevent",
+        "rcmos",
+        "real",
+        "realtime",
+        "reg",
+        "release",
+        "repeat",
+        "rnmos",
+        "rpmos",
+        "rtran",
+        "rtranif0",
+        "rtranif1",
+        "scalared",
+        "showcancelled",
+        "signed",
+        "small",
+        "specify",
+        "specparam",
+        "strong0",
+        "strong1",
+        "supply0",
+        "supply1",
+        "table",
+        "task",
+        "time",
+        "tran",
+        "tranif0",
+        "tranif1",
+        "tri",
+        "tri0",
+        "tri1",
+        "triand",
+        "trior",
+        "trireg",
+        "unsigned",
+        "use",
+        "uwire",
+        "vectored",
+        "wait",
+        "wand",
+        "weak0",
+        "weak1",
+        "while",
+        "wire",
+        "wor",
+        "xnor",
+        "xor",
+    ];
+}
+
+impl EscapePolicy for VerilogEscaping {
+    fn needs_escape(&self, name: &str) -> bool {
+        !Self::is_simple(name) || Self::RESERVED.contains(&name)
+    }
+
+    fn escape(&self, name: &str) -> String {
+        format!("\\{name} ")
+    }
+}
+
+/// The naming rules of VHDL: basic identifiers start with a letter, have no `__` and do not end in `_` or clash with a reserved word.
+/// Other names become extended identifiers like `\a+b\`, and bits are indexed like `a(3)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VhdlEscaping;
+
+impl VhdlEscaping {
+    /// The reserved words of VHDL most likely to clash with net names
+    const RESERVED: [&str; 36] = [
+        "abs",
+        "and",
+        "architecture",
+        "begin",
+        "block",
+        "buffer",
+        "bus",
+        "component",
+        "constant",
+        "downto",
+        "else",
+        "end",
+        "entity",
+        "for",
+        "function",
+        "if",
+        "in",
+        "inout",
+        "is",
+        "label",
+        "library",
+        "map",
+        "mod",
+        "nand",
+        "nor",
+        "not",
+        "of",
+        "or",
+        "out",
+        "port",
+        "process",
+        "signal",
+        "to",
+        "type",
+        "use",
+        "xor",
+    ];
+}
+
+impl EscapePolicy for VhdlEscaping {
+    fn needs_escape(&self, name: &str) -> bool {
+        !is_simple_name(name, &[])
+            || name.starts_with('_')
+            || name.ends_with('_')
+            || name.contains("__")
+            || Self::RESERVED.contains(&name.to_ascii_lowercase().as_str())
+    }
+
+    fn escape(&self, name: &str) -> String {
+        format!("\\{}\\", name.replace('\\', "\\\\"))
+    }
+
+    fn bit(&self, name: &str, index: usize) -> String {
+        format!("{name}({index})")
+    }
+}
+
+/// The naming rules of EDIF: names start with a letter and continue with letters, digits and `_`.
+/// Other names are declared with a legal name and the original, like `(rename &a_b "a+b")`, and bits are members like `(member a 3)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EdifEscaping;
+
+impl EscapePolicy for EdifEscaping {
+    fn needs_escape(&self, name: &str) -> bool {
+        !is_simple_name(name, &[]) || name.starts_with('_')
+    }
+
+    fn escape(&self, name: &str) -> String {
+        let legal: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("(rename &{legal} \"{}\")", name.replace('"', "%34%"))
+    }
+
+    fn bit(&self, name: &str, index: usize) -> String {
+        format!("(member {name} {index})")
+    }
+}
+
 impl From<&str> for Identifier {
     fn from(name: &str) -> Self {
         Identifier::new(name.to_string())
@@ -156,6 +585,94 @@ impl std::fmt::Display for Identifier {
     }
 }
 
+/// A hierarchical path to a net or instance, like `top/u_core/u_alu/add0/Y`.
+/// The first segment names the top-level module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HierPath {
+    segments: Vec<Identifier>,
+}
+
+impl HierPath {
+    /// The separator between hierarchy levels
+    pub const SEPARATOR: char = '/';
+
+    /// Creates a new path from its segments, ordered from the top-level module down
+    pub fn new(segments: Vec<Identifier>) -> Self {
+        Self { segments }
+    }
+
+    /// Returns the segments of the path, ordered from the top-level module down
+    pub fn segments(&self) -> &[Identifier] {
+        &self.segments
+    }
+
+    /// Returns the first segment of the path, which names the top-level module
+    pub fn root(&self) -> Option<&Identifier> {
+        self.segments.first()
+    }
+
+    /// Returns the last segment of the path
+    pub fn leaf(&self) -> Option<&Identifier> {
+        self.segments.last()
+    }
+
+    /// Returns the path one level up the hierarchy, if there is one
+    pub fn parent(&self) -> Option<Self> {
+        match self.segments.split_last() {
+            Some((_, rest)) if !rest.is_empty() => Some(Self::new(rest.to_vec())),
+            _ => None,
+        }
+    }
+
+    /// Returns a new path with `segment` appended one level down the hierarchy
+    pub fn join(&self, segment: Identifier) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push(segment);
+        Self::new(segments)
+    }
+
+    /// Returns the number of segments in the path
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns `true` if the path has no segments
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl std::fmt::Display for HierPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, seg) in self.segments.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", Self::SEPARATOR)?;
+            }
+            write!(f, "{seg}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for HierPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments: Vec<Identifier> = s
+            .split(Self::SEPARATOR)
+            .map(|seg| {
+                if seg.is_empty() {
+                    Err(format!("Empty segment in hierarchical path {s}"))
+                } else {
+                    Ok(Identifier::from(seg))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(segments))
+    }
+}
+
 /// A net in a circuit, which is identified with a name and data type.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -259,6 +776,41 @@ pub trait Instantiable: Clone {
     /// Returns an iterator over the parameters of the primitive.
     fn parameters(&self) -> impl Iterator<Item = (Identifier, Parameter)>;
 
+    /// Overrides the parameter `id` with `val`, returning the previous value.
+    /// Returns [None] if the primitive does not take the parameter or cannot be re-parameterized.
+    /// **The default implementation does not support overrides.**
+    fn set_parameter(&mut self, _id: &Identifier, _val: Parameter) -> Option<Parameter> {
+        None
+    }
+
+    /// Renames the port `old` to `new`, returning `true` if the port was renamed.
+    /// Returns `false` if there is no port `old`, `new` is already a port, or the primitive cannot rename ports.
+    /// **The default implementation does not support renaming.**
+    fn rename_port(&mut self, _old: &Identifier, _new: Identifier) -> bool {
+        false
+    }
+
+    /// Returns the constant value the `index`th input port should be tied to when left unconnected.
+    /// Returns [None] if the input has no default and must be driven.
+    /// **The default implementation gives no input a default.**
+    fn get_input_default(&self, _index: usize) -> Option<bool> {
+        None
+    }
+
+    /// Returns `true` if the `index`th input port is bidirectional, like the pad pin of an I/O buffer.
+    /// Bidirectional pins are connected like inputs, usually to a net of an `inout` port of the netlist.
+    /// **The default implementation makes every input port an input only.**
+    fn is_inout_port(&self, _index: usize) -> bool {
+        false
+    }
+
+    /// Returns the value a sequential primitive holds at time zero, like from an `INIT` parameter.
+    /// Returns [None] if the primitive is combinational or its initial value is unknown.
+    /// **The default implementation gives no primitive an initial value.**
+    fn get_initial_value(&self) -> Option<bool> {
+        None
+    }
+
     /// Returns `true` if the primitive is parameterized (has at least one parameter).
     fn is_parameterized(&self) -> bool {
         self.parameters().next().is_some()
@@ -279,21 +831,27 @@ pub trait Instantiable: Clone {
     ///
     /// If the index is out of bounds.
     fn get_output_port(&self, index: usize) -> &Net {
-        self.get_output_ports()
-            .into_iter()
-            .nth(index)
+        self.try_get_output_port(index)
             .expect("Index out of bounds for output ports")
     }
 
+    /// Returns the output port at the given index, or [None] if the index is out of bounds.
+    fn try_get_output_port(&self, index: usize) -> Option<&Net> {
+        self.get_output_ports().into_iter().nth(index)
+    }
+
     /// Returns the input port at the given index.
     /// # Panics
     ///
     /// If the index is out of bounds.
     fn get_input_port(&self, index: usize) -> &Net {
-        self.get_input_ports()
-            .into_iter()
-            .nth(index)
-            .expect("Index out of bounds for output ports")
+        self.try_get_input_port(index)
+            .expect("Index out of bounds for input ports")
+    }
+
+    /// Returns the input port at the given index, or [None] if the index is out of bounds.
+    fn try_get_input_port(&self, index: usize) -> Option<&Net> {
+        self.get_input_ports().into_iter().nth(index)
     }
 
     /// Returns the index of the input port with the given identifier, if it exists.
@@ -311,9 +869,83 @@ pub trait Instantiable: Clone {
             .into_iter()
             .position(|n| n.get_identifier() == id)
     }
+
+    /// Returns every port of the primitive with its direction, in the order the primitive declares them, like for emission.
+    /// The nets must be the ones returned by [Instantiable::get_input_ports] and [Instantiable::get_output_ports].
+    /// **The default implementation lists the input ports, then the output ports, with bidirectional inputs as [PortDirection::Inout].**
+    fn get_ports(&self) -> Vec<(&Net, PortDirection)> {
+        let inputs = self
+            .get_input_ports()
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| match self.is_inout_port(i) {
+                true => (n, PortDirection::Inout),
+                false => (n, PortDirection::Input),
+            });
+        let outputs = self
+            .get_output_ports()
+            .into_iter()
+            .map(|n| (n, PortDirection::Output));
+        inputs.chain(outputs).collect()
+    }
+
+    /// Returns the direction of the port with the given identifier, if it exists
+    fn get_port_direction(&self, id: &Identifier) -> Option<PortDirection> {
+        self.get_ports()
+            .into_iter()
+            .find(|(n, _)| n.get_identifier() == id)
+            .map(|(_, d)| d)
+    }
+}
+
+/// Classifies primitives as state elements, like flip-flops and latches, and identifies their control pins,
+/// so analyses can tell registers apart from combinational gates. Pins are given as input port indices.
+pub trait SequentialElement: Instantiable {
+    /// Returns `true` if the primitive holds state, like a flip-flop or latch
+    fn is_register(&self) -> bool;
+
+    /// Returns `true` if the register is level-sensitive, like a latch, instead of edge-triggered.
+    /// **The default implementation makes every register edge-triggered.**
+    fn is_latch(&self) -> bool {
+        false
+    }
+
+    /// Returns the index of the data input of the register.
+    /// **The default implementation finds the input port named `D`.**
+    fn get_data_input(&self) -> Option<usize> {
+        self.find_input(&"D".into()).filter(|_| self.is_register())
+    }
+
+    /// Returns the index of the clock input of the register, or the gate input of a latch.
+    /// **The default implementation gives no register a clock.**
+    fn get_clock_input(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the index of the reset input of the register, if it has one.
+    /// **The default implementation gives no register a reset.**
+    fn get_reset_input(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the index of the enable input of the register, if it has one.
+    /// **The default implementation gives no register an enable.**
+    fn get_enable_input(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns `true` if the `index`th input is a clock, reset or enable of the register
+    fn is_control_input(&self, index: usize) -> bool {
+        [
+            self.get_clock_input(),
+            self.get_reset_input(),
+            self.get_enable_input(),
+        ]
+        .contains(&Some(index))
+    }
 }
 
-/// A tagged union for objects in a digital circuit, which can be either an input net or an instance of a module or primitive.
+/// A tagged union for objects in a digital circuit, which can be an input net, an instance of a module or primitive, or an alias of another net.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Object<I>
@@ -324,6 +956,8 @@ where
     Input(Net),
     /// An instance of a module or primitive
     Instance(Vec<Net>, Identifier, I),
+    /// A net that aliases the net driving its single input, like `assign y = x;`
+    Alias(Net),
 }
 
 impl<I> Object<I>
@@ -333,7 +967,7 @@ where
     /// Returns the net driven by this object.
     pub fn get_single_net(&self) -> &Net {
         match self {
-            Object::Input(net) => net,
+            Object::Input(net) | Object::Alias(net) => net,
             Object::Instance(nets, _, _) => {
                 if nets.len() > 1 {
                     panic!("Instance has more than one output net");
@@ -347,7 +981,7 @@ where
     /// Returns the net driven by this object at the index
     pub fn get_net(&self, index: usize) -> &Net {
         match self {
-            Object::Input(net) => {
+            Object::Input(net) | Object::Alias(net) => {
                 if index > 0 {
                     panic!("Index out of bounds for input net.")
                 }
@@ -360,7 +994,7 @@ where
     /// Returns the instance within the object, if the object represents one
     pub fn get_instance_type(&self) -> Option<&I> {
         match self {
-            Object::Input(_) => None,
+            Object::Input(_) | Object::Alias(_) => None,
             Object::Instance(_, _, instance) => Some(instance),
         }
     }
@@ -368,7 +1002,7 @@ where
     /// Returns a mutable reference to the instance type within the object, if the object represents one
     pub fn get_instance_type_mut(&mut self) -> Option<&mut I> {
         match self {
-            Object::Input(_) => None,
+            Object::Input(_) | Object::Alias(_) => None,
             Object::Instance(_, _, instance) => Some(instance),
         }
     }
@@ -376,7 +1010,7 @@ where
     /// Returns all the nets driven at this circuit node.
     pub fn get_nets(&self) -> &[Net] {
         match self {
-            Object::Input(net) => std::slice::from_ref(net),
+            Object::Input(net) | Object::Alias(net) => std::slice::from_ref(net),
             Object::Instance(nets, _, _) => nets,
         }
     }
@@ -384,7 +1018,7 @@ where
     /// Returns a mutable reference to all the nets driven at this circuit node.
     pub fn get_nets_mut(&mut self) -> &mut [Net] {
         match self {
-            Object::Input(net) => std::slice::from_mut(net),
+            Object::Input(net) | Object::Alias(net) => std::slice::from_mut(net),
             Object::Instance(nets, _, _) => nets,
         }
     }
@@ -397,6 +1031,7 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Object::Input(net) => write!(f, "Input({net})"),
+            Object::Alias(net) => write!(f, "Alias({net})"),
             Object::Instance(_nets, name, instance) => {
                 write!(f, "{}({})", instance.get_name(), name)
             }
@@ -432,14 +1067,42 @@ mod tests {
     #[test]
     fn identifier_emission() {
         let id = Identifier::new("wire".to_string());
-        assert_eq!(id.emit_name(), "wire");
+        assert!(!id.is_escaped());
+        assert_eq!(id.emit_name(), "\\wire ");
         let id = Identifier::new("\\wire".to_string());
         assert!(id.is_escaped());
         assert_eq!(id.emit_name(), "\\wire ");
         assert_eq!(format!("{id}"), "\\wire ");
         let id = Identifier::new("wire[3]".to_string());
         assert!(id.is_sliced());
-        assert_eq!(id.emit_name(), "wire[3]");
+        assert_eq!(id.emit_name(), "\\wire [3]");
+        let id = Identifier::new("wire_3".to_string());
+        assert_eq!(id.emit_name(), "wire_3");
+    }
+
+    #[test]
+    fn escaping_policies() {
+        let id = Identifier::new("a.b".to_string());
+        assert!(id.is_escaped());
+        assert_eq!(id.emit_name(), "\\a.b ");
+        assert_eq!(id.emit_with(&VhdlEscaping), "\\a.b\\");
+        assert_eq!(id.emit_with(&EdifEscaping), "(rename &a_b \"a.b\")");
+
+        // Names legal in Verilog can still clash with the rules of another format
+        let id = Identifier::new("signal".to_string());
+        assert_eq!(id.emit_name(), "signal");
+        assert_eq!(id.emit_with(&VhdlEscaping), "\\signal\\");
+        let id = Identifier::new("n__1[2]".to_string());
+        assert_eq!(id.emit_name(), "n__1[2]");
+        assert_eq!(id.emit_with(&VhdlEscaping), "\\n__1\\(2)");
+        assert_eq!(id.emit_with(&EdifEscaping), "(member n__1 2)");
+
+        // Bits of a bus whose name is not legal are escaped as a whole
+        let id = Identifier::new("a-b[1]".to_string());
+        assert!(id.is_escaped());
+        assert_eq!(id.emit_name(), "\\a-b[1] ");
+        assert!(Identifier::new("$x".to_string()).is_escaped());
+        assert!(!Identifier::new("x$".to_string()).is_escaped());
     }
 
     #[test]
@@ -450,4 +1113,40 @@ mod tests {
         assert_eq!(*net.get_type(), DataType::logic());
         assert_eq!(*net.get_type(), DataType::fourstate());
     }
+
+    #[test]
+    fn hier_path_parsing() {
+        let path: HierPath = "top/u_core/u_alu/add0/Y".parse().unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!(path.root(), Some(&"top".into()));
+        assert_eq!(path.leaf(), Some(&"Y".into()));
+        assert_eq!(path.to_string(), "top/u_core/u_alu/add0/Y");
+        assert_eq!(path.parent().unwrap().to_string(), "top/u_core/u_alu/add0");
+        assert_eq!(path.parent().unwrap().join("Y".into()), path);
+        assert!("top//Y".parse::<HierPath>().is_err());
+    }
+
+    #[test]
+    fn data_type_compatibility() {
+        assert!(DataType::boolean().can_drive(&DataType::logic()));
+        assert!(DataType::tristate().can_drive(&DataType::tristate()));
+        assert!(!DataType::logic().can_drive(&DataType::tristate()));
+        assert!(!DataType::tristate().can_drive(&DataType::boolean()));
+    }
+
+    #[test]
+    fn supply_and_vector_compatibility() {
+        let vdd = DataType::supply(true);
+        assert!(vdd.can_drive(&DataType::boolean()));
+        assert!(vdd.can_drive(&vdd));
+        assert!(!DataType::supply(false).can_drive(&vdd));
+        assert!(!DataType::boolean().can_drive(&vdd));
+
+        assert!(DataType::signed(8).can_drive(&DataType::signed(8)));
+        assert!(!DataType::signed(8).can_drive(&DataType::unsigned(8)));
+        assert!(!DataType::unsigned(4).can_drive(&DataType::unsigned(8)));
+        assert!(DataType::boolean().can_drive(&DataType::unsigned(1)));
+        assert!(!DataType::unsigned(8).can_drive(&DataType::logic()));
+        assert_eq!(DataType::signed(8).to_string(), "logic signed [7:0]");
+    }
 }
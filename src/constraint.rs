@@ -0,0 +1,84 @@
+/*!
+
+  Constraints on the values of the inputs of a netlist, which simulation and verification may assume.
+
+*/
+
+use crate::circuit::Identifier;
+
+/// A constraint on the values of the inputs of a netlist, which simulation and verification may assume
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputConstraint {
+    /// The input always has the given value
+    Constant(Identifier, bool),
+    /// Exactly one of the inputs is true
+    OneHot(Vec<Identifier>),
+    /// At most one of the inputs is true, like mutually exclusive enables
+    Exclusive(Vec<Identifier>),
+}
+
+impl InputConstraint {
+    /// Returns the inputs the constraint is on
+    pub fn inputs(&self) -> &[Identifier] {
+        match self {
+            InputConstraint::Constant(input, _) => std::slice::from_ref(input),
+            InputConstraint::OneHot(inputs) | InputConstraint::Exclusive(inputs) => inputs,
+        }
+    }
+
+    /// Returns `true` if the constraint holds for `values`, given in the order of [InputConstraint::inputs]
+    pub fn holds(&self, values: &[bool]) -> bool {
+        let ones = values.iter().filter(|v| **v).count();
+        match self {
+            InputConstraint::Constant(_, value) => values[0] == *value,
+            InputConstraint::OneHot(_) => ones == 1,
+            InputConstraint::Exclusive(_) => ones <= 1,
+        }
+    }
+
+    /// Fills in the unknown `values` that the constraint implies from the known ones.
+    /// Values are given in the order of [InputConstraint::inputs].
+    /// Returns an error if the known values already violate the constraint, like two ones under [InputConstraint::OneHot].
+    pub fn imply(&self, values: &mut [Option<bool>]) -> Result<(), String> {
+        let ones = values.iter().filter(|v| **v == Some(true)).count();
+        let unknown = values.iter().filter(|v| v.is_none()).count();
+        let violated = match self {
+            InputConstraint::Constant(_, value) => values[0].is_some_and(|v| v != *value),
+            InputConstraint::OneHot(_) => ones > 1 || (ones == 0 && unknown == 0),
+            InputConstraint::Exclusive(_) => ones > 1,
+        };
+        if violated {
+            return Err(format!("The input values violate {self}"));
+        }
+        match self {
+            InputConstraint::Constant(_, value) => values[0] = Some(*value),
+            InputConstraint::OneHot(_) | InputConstraint::Exclusive(_) if ones == 1 => {
+                values
+                    .iter_mut()
+                    .for_each(|v| *v = Some(v.unwrap_or(false)));
+            }
+            InputConstraint::OneHot(_) if ones == 0 && unknown == 1 => {
+                values.iter_mut().for_each(|v| *v = Some(v.unwrap_or(true)));
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for InputConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |inputs: &[Identifier]| {
+            inputs
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            InputConstraint::Constant(input, value) => write!(f, "{input} == {}", *value as u8),
+            InputConstraint::OneHot(inputs) => write!(f, "onehot({})", list(inputs)),
+            InputConstraint::Exclusive(inputs) => write!(f, "exclusive({})", list(inputs)),
+        }
+    }
+}
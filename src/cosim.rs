@@ -0,0 +1,268 @@
+/*!
+
+  Cycle-by-cycle cosimulation of two circuits, like an original and an optimized netlist.
+
+*/
+
+use crate::aig::{Aig, Lit};
+use std::collections::HashMap;
+
+/// The outputs that differ between the two circuits at a cycle, as found by [Cosim::step]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The cycle the outputs differ at, starting from 0
+    cycle: usize,
+    /// The bound outputs that differ, with the values of the left and right circuits
+    outputs: Vec<(String, bool, bool)>,
+}
+
+impl Divergence {
+    /// Returns the cycle the outputs differ at, starting from 0
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    /// Returns the bound outputs that differ, named as in the left circuit, with the values of the left and right circuits
+    pub fn outputs(&self) -> &[(String, bool, bool)] {
+        &self.outputs
+    }
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cycle {}:", self.cycle)?;
+        for (name, left, right) in &self.outputs {
+            write!(f, " {name} {} != {}", *left as u8, *right as u8)?;
+        }
+        Ok(())
+    }
+}
+
+/// A harness that simulates two circuits side by side on shared inputs and compares their outputs every cycle.
+/// Ports are bound by name when the harness is created, and ports named differently can be bound with [Cosim::bind_input] and [Cosim::bind_output].
+/// Renamed outputs can also be paired automatically with [Cosim::bind_outputs_by_signature].
+/// Circuits are given as [Aig]s, like from [Aig::from_sequential_netlist], and start from their initial states.
+#[derive(Debug, Clone)]
+pub struct Cosim {
+    /// The left circuit
+    left: Aig,
+    /// The right circuit
+    right: Aig,
+    /// The bound inputs, as the positions of the inputs in the left and right circuits
+    inputs: Vec<(usize, usize)>,
+    /// The bound outputs, as the positions of the outputs in the left and right circuits
+    outputs: Vec<(usize, usize)>,
+    /// The current states of the left and right circuits
+    state: (Vec<bool>, Vec<bool>),
+    /// The number of cycles simulated so far
+    cycle: usize,
+}
+
+/// The ports of an [Aig], with their names
+type Ports = [(Lit, Option<String>)];
+
+/// Returns the position of the port named `name`
+fn find_port(ports: &Ports, name: &str) -> Option<usize> {
+    ports.iter().position(|(_, n)| n.as_deref() == Some(name))
+}
+
+/// Pairs the positions of the ports with the same names in `left` and `right`
+fn bind_by_name(left: &Ports, right: &Ports) -> Vec<(usize, usize)> {
+    left.iter()
+        .enumerate()
+        .filter_map(|(i, (_, name))| Some((i, find_port(right, name.as_deref()?)?)))
+        .collect()
+}
+
+impl Cosim {
+    /// Creates a harness for `left` and `right`, binding the inputs and outputs that have the same names
+    pub fn new(left: Aig, right: Aig) -> Self {
+        let inputs = bind_by_name(left.inputs(), right.inputs());
+        let outputs = bind_by_name(left.outputs(), right.outputs());
+        let state = (left.initial_state(), right.initial_state());
+        Self {
+            left,
+            right,
+            inputs,
+            outputs,
+            state,
+            cycle: 0,
+        }
+    }
+
+    /// Returns the positions of the ports named `left` and `right` in the two circuits
+    fn find_pair(left: &Ports, right: &Ports, l: &str, r: &str) -> Result<(usize, usize), String> {
+        let l = find_port(left, l).ok_or(format!("The left circuit has no port {l}"))?;
+        let r = find_port(right, r).ok_or(format!("The right circuit has no port {r}"))?;
+        Ok((l, r))
+    }
+
+    /// Binds the input `left` of the left circuit to the input `right` of the right circuit, so they are driven together
+    pub fn bind_input(&mut self, left: &str, right: &str) -> Result<(), String> {
+        let pair = Self::find_pair(self.left.inputs(), self.right.inputs(), left, right)?;
+        self.inputs.retain(|(l, r)| *l != pair.0 && *r != pair.1);
+        self.inputs.push(pair);
+        self.inputs.sort();
+        Ok(())
+    }
+
+    /// Binds the output `left` of the left circuit to the output `right` of the right circuit, so they are compared
+    pub fn bind_output(&mut self, left: &str, right: &str) -> Result<(), String> {
+        let pair = Self::find_pair(self.left.outputs(), self.right.outputs(), left, right)?;
+        self.outputs.retain(|(l, r)| *l != pair.0 && *r != pair.1);
+        self.outputs.push(pair);
+        self.outputs.sort();
+        Ok(())
+    }
+
+    /// Returns the names of the bound inputs in the left circuit, in the order [Cosim::step] takes their values
+    pub fn inputs(&self) -> Vec<&str> {
+        self.inputs
+            .iter()
+            .map(|(l, _)| self.left.inputs()[*l].1.as_deref().unwrap_or_default())
+            .collect()
+    }
+
+    /// Returns the number of cycles simulated so far
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    /// Returns the values of the inputs of the left and right circuits, given the values of the bound inputs
+    fn assign(&self, values: &[bool]) -> Result<(Vec<bool>, Vec<bool>), String> {
+        if values.len() != self.inputs.len() {
+            return Err(format!(
+                "Expected {} input values, got {}",
+                self.inputs.len(),
+                values.len()
+            ));
+        }
+        let mut left = vec![None; self.left.inputs().len()];
+        let mut right = vec![None; self.right.inputs().len()];
+        for ((l, r), v) in self.inputs.iter().zip(values) {
+            left[*l] = Some(*v);
+            right[*r] = Some(*v);
+        }
+        let require_bound = |side: &str, aig: &Aig, values: Vec<Option<bool>>| {
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    v.ok_or_else(|| {
+                        let name = aig.inputs()[i].1.as_deref().unwrap_or_default();
+                        format!("Input {i} ({name}) of the {side} circuit is unbound")
+                    })
+                })
+                .collect::<Result<Vec<bool>, String>>()
+        };
+        Ok((
+            require_bound("left", &self.left, left)?,
+            require_bound("right", &self.right, right)?,
+        ))
+    }
+
+    /// Binds the unbound outputs of the two circuits that behave the same, for circuits whose outputs were renamed.
+    /// Both circuits are simulated from their initial states for `cycles` cycles of pseudo-random stimulus,
+    /// and outputs are paired when their sequences of values match exactly one output of the other circuit.
+    /// Returns the names of the newly bound outputs. The state of the harness is not advanced.
+    pub fn bind_outputs_by_signature(
+        &mut self,
+        cycles: usize,
+    ) -> Result<Vec<(String, String)>, String> {
+        let mut left_sigs = vec![Vec::with_capacity(cycles); self.left.outputs().len()];
+        let mut right_sigs = vec![Vec::with_capacity(cycles); self.right.outputs().len()];
+        let mut state = (self.left.initial_state(), self.right.initial_state());
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        for _ in 0..cycles {
+            let values: Vec<bool> = (0..self.inputs.len())
+                .map(|_| {
+                    // xorshift64
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed & 1 == 1
+                })
+                .collect();
+            let (left, right) = self.assign(&values)?;
+            let (left_out, left_next) = self.left.evaluate(&left, &state.0)?;
+            let (right_out, right_next) = self.right.evaluate(&right, &state.1)?;
+            for (sig, v) in left_sigs.iter_mut().zip(left_out) {
+                sig.push(v);
+            }
+            for (sig, v) in right_sigs.iter_mut().zip(right_out) {
+                sig.push(v);
+            }
+            state = (left_next, right_next);
+        }
+
+        let unbound = |sigs: Vec<Vec<bool>>, bound: Vec<usize>| {
+            let mut by_sig: HashMap<Vec<bool>, Vec<usize>> = HashMap::new();
+            for (i, sig) in sigs.into_iter().enumerate() {
+                if !bound.contains(&i) {
+                    by_sig.entry(sig).or_default().push(i);
+                }
+            }
+            by_sig
+        };
+        let left = unbound(left_sigs, self.outputs.iter().map(|(l, _)| *l).collect());
+        let right = unbound(right_sigs, self.outputs.iter().map(|(_, r)| *r).collect());
+        let mut pairs: Vec<(usize, usize)> = left
+            .iter()
+            .filter_map(
+                |(sig, l)| match (l.as_slice(), right.get(sig)?.as_slice()) {
+                    ([l], [r]) => Some((*l, *r)),
+                    _ => None,
+                },
+            )
+            .collect();
+        pairs.sort();
+
+        let name = |aig: &Aig, i: usize| aig.outputs()[i].1.clone().unwrap_or_default();
+        let names = pairs
+            .iter()
+            .map(|(l, r)| (name(&self.left, *l), name(&self.right, *r)))
+            .collect();
+        self.outputs.extend(pairs);
+        self.outputs.sort();
+        Ok(names)
+    }
+
+    /// Simulates one cycle with the values of the bound inputs, in the order of [Cosim::inputs].
+    /// Returns the outputs that differ, if any. Both circuits advance to their next states either way.
+    pub fn step(&mut self, values: &[bool]) -> Result<Option<Divergence>, String> {
+        if self.outputs.is_empty() {
+            return Err("No outputs are bound".to_string());
+        }
+        let (left, right) = self.assign(values)?;
+
+        let (left_out, left_next) = self.left.evaluate(&left, &self.state.0)?;
+        let (right_out, right_next) = self.right.evaluate(&right, &self.state.1)?;
+        let outputs: Vec<(String, bool, bool)> = self
+            .outputs
+            .iter()
+            .filter(|(l, r)| left_out[*l] != right_out[*r])
+            .map(|(l, r)| {
+                let name = self.left.outputs()[*l].1.clone().unwrap_or_default();
+                (name, left_out[*l], right_out[*r])
+            })
+            .collect();
+
+        let cycle = self.cycle;
+        self.state = (left_next, right_next);
+        self.cycle += 1;
+        Ok((!outputs.is_empty()).then_some(Divergence { cycle, outputs }))
+    }
+
+    /// Simulates a cycle for each set of input values in `stimulus`, stopping at the first divergence
+    pub fn run(
+        &mut self,
+        stimulus: impl IntoIterator<Item = Vec<bool>>,
+    ) -> Result<Option<Divergence>, String> {
+        for values in stimulus {
+            if let Some(divergence) = self.step(&values)? {
+                return Ok(Some(divergence));
+            }
+        }
+        Ok(None)
+    }
+}
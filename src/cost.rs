@@ -0,0 +1,286 @@
+/*!
+
+  Cost models that optimization passes consult to decide whether a change is worth applying.
+
+*/
+
+use crate::{
+    circuit::Instantiable,
+    netlist::{NetRef, Netlist, NetlistError},
+};
+use std::ops::Deref;
+
+/// The cost of a set of instances: their total area and power, and the delay of the slowest one
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Cost {
+    /// The total area
+    pub area: f64,
+    /// The largest delay of a single instance
+    pub delay: f64,
+    /// The total power
+    pub power: f64,
+}
+
+impl Cost {
+    /// Returns the cost of a set of instances with this cost and the instances with `other`
+    pub fn combine(self, other: Cost) -> Cost {
+        Cost {
+            area: self.area + other.area,
+            delay: self.delay.max(other.delay),
+            power: self.power + other.power,
+        }
+    }
+}
+
+impl std::fmt::Display for Cost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "area {:.3}, delay {:.3}, power {:.3}",
+            self.area, self.delay, self.power
+        )
+    }
+}
+
+/// How much each part of a [Cost] counts towards the single number compared by [CostModel::accepts]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostWeights {
+    /// The weight of area
+    pub area: f64,
+    /// The weight of delay
+    pub delay: f64,
+    /// The weight of power
+    pub power: f64,
+}
+
+impl Default for CostWeights {
+    /// Only area counts
+    fn default() -> Self {
+        Self {
+            area: 1.0,
+            delay: 0.0,
+            power: 0.0,
+        }
+    }
+}
+
+/// A model of what instances cost, which optimization passes consult instead of hard-coding their goals.
+/// Any `Fn(&I) -> f64` is a cost model that gives the area of an instance type.
+pub trait CostModel<I: Instantiable> {
+    /// Returns the area of an instance of `inst_type`
+    fn area(&self, inst_type: &I) -> f64;
+
+    /// Returns the delay through an instance of `inst_type`.
+    /// **The default implementation gives every instance no delay.**
+    fn delay(&self, _inst_type: &I) -> f64 {
+        0.0
+    }
+
+    /// Returns the power of an instance of `inst_type`.
+    /// **The default implementation gives every instance no power.**
+    fn power(&self, _inst_type: &I) -> f64 {
+        0.0
+    }
+
+    /// Returns how much area, delay and power count when costs are compared.
+    /// **The default implementation only counts area.**
+    fn weights(&self) -> CostWeights {
+        CostWeights::default()
+    }
+
+    /// Returns the cost of an instance of `inst_type`
+    fn cost(&self, inst_type: &I) -> Cost {
+        Cost {
+            area: self.area(inst_type),
+            delay: self.delay(inst_type),
+            power: self.power(inst_type),
+        }
+    }
+
+    /// Returns the weighted sum of `cost`
+    fn weigh(&self, cost: &Cost) -> f64 {
+        let w = self.weights();
+        w.area * cost.area + w.delay * cost.delay + w.power * cost.power
+    }
+
+    /// Returns `true` if a change from instances costing `before` to instances costing `after` should be applied.
+    /// **The default implementation accepts changes that lower the weighted cost.**
+    fn accepts(&self, before: &Cost, after: &Cost) -> bool {
+        self.weigh(after) < self.weigh(before)
+    }
+}
+
+impl<I, F> CostModel<I> for F
+where
+    I: Instantiable,
+    F: Fn(&I) -> f64,
+{
+    fn area(&self, inst_type: &I) -> f64 {
+        self(inst_type)
+    }
+}
+
+/// A cost model where every instance has an area of one, so costs count instances
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitCost;
+
+impl<I: Instantiable> CostModel<I> for UnitCost {
+    fn area(&self, _inst_type: &I) -> f64 {
+        1.0
+    }
+}
+
+/// A cost model that accepts every change, for passes that edit the netlist for its structure rather than its cost, like buffering or scan insertion.
+/// Every instance has an area of one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptAll;
+
+impl<I: Instantiable> CostModel<I> for AcceptAll {
+    fn area(&self, _inst_type: &I) -> f64 {
+        1.0
+    }
+
+    fn accepts(&self, _before: &Cost, _after: &Cost) -> bool {
+        true
+    }
+}
+
+/// Returns the cost of the instances in `nodes` under `model`. Inputs cost nothing.
+pub fn cost_of<I, M>(model: &M, nodes: impl IntoIterator<Item = NetRef<I>>) -> Cost
+where
+    I: Instantiable,
+    M: CostModel<I> + ?Sized,
+{
+    nodes
+        .into_iter()
+        .filter_map(|n| n.get_instance_type().map(|t| model.cost(&t)))
+        .fold(Cost::default(), Cost::combine)
+}
+
+/// Returns the cost of every instance in `netlist` under `model`
+pub fn netlist_cost<I, M>(model: &M, netlist: &Netlist<I>) -> Cost
+where
+    I: Instantiable,
+    M: CostModel<I> + ?Sized,
+{
+    cost_of(model, netlist.objects())
+}
+
+/// A ceiling on the number of instances and their total area, as set by [Netlist::set_budget].
+/// The running counts are kept up to date as instances are inserted and cleaned from the netlist.
+pub struct Budget<I: Instantiable> {
+    /// Returns the area of an instance type
+    area_of: Box<dyn Fn(&I) -> f64>,
+    /// The maximum number of instances
+    max_instances: Option<usize>,
+    /// The maximum total area
+    max_area: Option<f64>,
+    /// The running number of instances
+    instances: usize,
+    /// The running total area
+    area: f64,
+}
+
+impl<I> Budget<I>
+where
+    I: Instantiable,
+{
+    /// Creates a budget without any ceilings, where `area_of` returns the area of an instance type
+    pub fn new(area_of: impl Fn(&I) -> f64 + 'static) -> Self {
+        Self {
+            area_of: Box::new(area_of),
+            max_instances: None,
+            max_area: None,
+            instances: 0,
+            area: 0.0,
+        }
+    }
+
+    /// Sets the maximum number of instances
+    pub fn with_max_instances(mut self, max: usize) -> Self {
+        self.max_instances = Some(max);
+        self
+    }
+
+    /// Sets the maximum total area
+    pub fn with_max_area(mut self, max: f64) -> Self {
+        self.max_area = Some(max);
+        self
+    }
+
+    /// Returns the running number of instances
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    /// Returns the running total area
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Restarts the running counts from the instances of `inst_types`
+    pub(crate) fn recount(&mut self, inst_types: impl IntoIterator<Item = impl Deref<Target = I>>) {
+        self.instances = 0;
+        self.area = 0.0;
+        for inst_type in inst_types {
+            self.instances += 1;
+            self.area += (self.area_of)(&inst_type);
+        }
+    }
+
+    /// Counts an instance of `inst_type`, unless it would exceed the budget
+    pub(crate) fn charge(&mut self, inst_type: &I) -> Result<(), NetlistError<I>> {
+        let instances = self.instances + 1;
+        let area = self.area + (self.area_of)(inst_type);
+        if self.max_instances.is_some_and(|m| instances > m)
+            || self.max_area.is_some_and(|m| area > m)
+        {
+            return Err(NetlistError::BudgetExceeded { instances, area });
+        }
+        self.instances = instances;
+        self.area = area;
+        Ok(())
+    }
+
+    /// Checks that instances of `inst_types` can all be counted, without counting them
+    pub(crate) fn fits<'a>(
+        &self,
+        inst_types: impl IntoIterator<Item = &'a I>,
+    ) -> Result<(), NetlistError<I>>
+    where
+        I: 'a,
+    {
+        let mut instances = self.instances;
+        let mut area = self.area;
+        for inst_type in inst_types {
+            instances += 1;
+            area += (self.area_of)(inst_type);
+        }
+        if self.max_instances.is_some_and(|m| instances > m)
+            || self.max_area.is_some_and(|m| area > m)
+        {
+            return Err(NetlistError::BudgetExceeded { instances, area });
+        }
+        Ok(())
+    }
+
+    /// Stops counting an instance of `inst_type`
+    pub(crate) fn refund(&mut self, inst_type: &I) {
+        self.instances -= 1;
+        self.area -= (self.area_of)(inst_type);
+    }
+}
+
+impl<I> std::fmt::Debug for Budget<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Budget")
+            .field("max_instances", &self.max_instances)
+            .field("max_area", &self.max_area)
+            .field("instances", &self.instances)
+            .field("area", &self.area)
+            .finish()
+    }
+}
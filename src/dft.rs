@@ -0,0 +1,147 @@
+/*!
+
+  Design-for-test transforms, like stitching the flip-flops of a netlist into a scan chain.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Net, SequentialElement},
+    cost::AcceptAll,
+    library::CellMap,
+    netlist::{NetRef, Netlist},
+};
+use std::rc::Rc;
+
+/// Inserts a scan chain into a netlist: flip-flops are replaced with their scan-equivalent cells and chained
+/// from a `scan_in` input to a `scan_out` output, shifting while `scan_enable` is high.
+/// Port and pin names follow common conventions by default and can be changed with the builder methods.
+#[derive(Debug, Clone)]
+pub struct ScanChain {
+    /// The name of the input that feeds the first flip-flop of the chain
+    scan_in: Identifier,
+    /// The name of the output driven by the last flip-flop of the chain
+    scan_out: Identifier,
+    /// The name of the input that selects the scan path on every flip-flop
+    scan_enable: Identifier,
+    /// The scan data input pin of the scan cells
+    si_pin: Identifier,
+    /// The scan enable pin of the scan cells
+    se_pin: Identifier,
+}
+
+impl Default for ScanChain {
+    fn default() -> Self {
+        Self {
+            scan_in: "scan_in".into(),
+            scan_out: "scan_out".into(),
+            scan_enable: "scan_enable".into(),
+            si_pin: "SI".into(),
+            se_pin: "SE".into(),
+        }
+    }
+}
+
+impl ScanChain {
+    /// Creates a scan chain with ports `scan_in`, `scan_out` and `scan_enable`, through scan cells with pins `SI` and `SE`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the names of the scan input, scan output and scan enable ports
+    pub fn with_ports(
+        mut self,
+        scan_in: Identifier,
+        scan_out: Identifier,
+        scan_enable: Identifier,
+    ) -> Self {
+        self.scan_in = scan_in;
+        self.scan_out = scan_out;
+        self.scan_enable = scan_enable;
+        self
+    }
+
+    /// Sets the names of the scan data input and scan enable pins of the scan cells
+    pub fn with_pins(mut self, si: Identifier, se: Identifier) -> Self {
+        self.si_pin = si;
+        self.se_pin = se;
+        self
+    }
+
+    /// Replaces every flip-flop of `netlist` with its scan-equivalent cell in `cells` and stitches them into a chain, in object order.
+    /// The pins of each flip-flop are mapped to the scan cell like [Netlist::substitute_cells], and the scan pins are left for the chain.
+    /// Each flip-flop feeds the next from its first output, and latches are not scanned. Returns the flip-flops in the order of the chain, from the scan input to the scan output.
+    /// The netlist is left unchanged if a flip-flop has no scan equivalent, a scan cell lacks a scan pin or a port name is taken.
+    pub fn insert<I>(
+        &self,
+        netlist: &Rc<Netlist<I>>,
+        cells: &CellMap<I>,
+    ) -> Result<Vec<NetRef<I>>, String>
+    where
+        I: SequentialElement,
+    {
+        let flops: Vec<Identifier> = netlist
+            .objects()
+            .filter(|n| {
+                n.get_instance_type()
+                    .is_some_and(|t| t.is_register() && !t.is_latch())
+            })
+            .filter_map(|n| n.get_instance_name())
+            .collect();
+        if flops.is_empty() {
+            return Err(format!(
+                "Netlist {} has no flip-flops to scan",
+                netlist.get_name()
+            ));
+        }
+        for name in &flops {
+            let inst = netlist.find_instance(name).unwrap();
+            let old = inst.get_instance_type().unwrap().get_name().clone();
+            let Some(cell) = cells.get(&old) else {
+                return Err(format!(
+                    "No scan equivalent for cell {old} of flip-flop {name}"
+                ));
+            };
+            for pin in [&self.si_pin, &self.se_pin] {
+                if cell.find_input(pin).is_none() {
+                    return Err(format!(
+                        "Scan cell {} has no input pin {pin}",
+                        cell.get_name()
+                    ));
+                }
+            }
+        }
+        for port in [&self.scan_in, &self.scan_out, &self.scan_enable] {
+            let taken = netlist.find_port(port).is_some()
+                || netlist
+                    .objects()
+                    .any(|n| n.nets().any(|net| net.get_identifier() == port));
+            if taken {
+                return Err(format!(
+                    "The name {port} is already taken in netlist {}",
+                    netlist.get_name()
+                ));
+            }
+        }
+
+        netlist.substitute_cells(cells, &AcceptAll)?;
+        let scan_enable = netlist.insert_input(Net::new_logic(self.scan_enable.clone()));
+        let mut scan_in = netlist.insert_input(Net::new_logic(self.scan_in.clone()));
+        let mut chain = Vec::with_capacity(flops.len());
+        for name in &flops {
+            let flop = netlist.find_instance(name).unwrap();
+            flop.find_input(&self.se_pin)
+                .unwrap()
+                .connect(scan_enable.clone());
+            flop.find_input(&self.si_pin).unwrap().connect(scan_in);
+            scan_in = flop.get_output(0);
+            chain.push(flop);
+        }
+        // The last flip-flop may already drive an output, which is kept by driving scan_out through an alias
+        let scan_out = match scan_in.is_top_level_output() {
+            true => netlist.insert_alias(scan_in, self.scan_out.clone())?,
+            false => scan_in,
+        };
+        scan_out.expose_with_name(self.scan_out.clone());
+        Ok(chain)
+    }
+}
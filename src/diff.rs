@@ -0,0 +1,345 @@
+/*!
+
+  Structural differences between two versions of a netlist, and an overlay view of them for review.
+
+*/
+
+use crate::{
+    circuit::Instantiable,
+    netlist::{NetRef, Netlist},
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// How a node changed between the left and right netlists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The node is only in the right netlist
+    Added,
+    /// The node is only in the left netlist
+    Removed,
+    /// The node is in both netlists, but its type, parameters or inputs differ
+    Changed,
+    /// The node is the same in both netlists
+    Unchanged,
+}
+
+impl Change {
+    /// Returns the fill color of nodes with this change in the overlay view
+    pub fn color(&self) -> &'static str {
+        match self {
+            Change::Added => "palegreen",
+            Change::Removed => "lightpink",
+            Change::Changed => "lightgoldenrod",
+            Change::Unchanged => "white",
+        }
+    }
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Removed => write!(f, "removed"),
+            Change::Changed => write!(f, "changed"),
+            Change::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+/// A node of the overlay, matched across the two netlists by instance name or input net name
+#[derive(Debug, Clone)]
+pub struct DiffNode<I: Instantiable> {
+    /// The name the node is matched by
+    name: String,
+    /// How the node changed
+    change: Change,
+    /// The node in the left netlist
+    left: Option<NetRef<I>>,
+    /// The node in the right netlist
+    right: Option<NetRef<I>>,
+}
+
+impl<I> DiffNode<I>
+where
+    I: Instantiable,
+{
+    /// Returns the name the node is matched by
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns how the node changed
+    pub fn change(&self) -> Change {
+        self.change
+    }
+
+    /// Returns the node in the left netlist, if it is there
+    pub fn left(&self) -> Option<&NetRef<I>> {
+        self.left.as_ref()
+    }
+
+    /// Returns the node in the right netlist, if it is there
+    pub fn right(&self) -> Option<&NetRef<I>> {
+        self.right.as_ref()
+    }
+
+    /// Returns the type label of the node, showing both types if they differ
+    fn label(&self) -> String {
+        let type_of = |n: &NetRef<I>| match n.get_instance_type() {
+            Some(t) => t.get_name().to_string(),
+            None if n.is_an_alias() => "alias".to_string(),
+            None => "input".to_string(),
+        };
+        let left = self.left.as_ref().map(type_of);
+        let right = self.right.as_ref().map(type_of);
+        match (left, right) {
+            (Some(l), Some(r)) if l != r => format!("{l} -> {r}"),
+            (Some(t), _) | (None, Some(t)) => t,
+            (None, None) => unreachable!("A diff node is in at least one netlist"),
+        }
+    }
+}
+
+/// A connection in the overlay from a driving node to a load node or top-level output
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct DiffEdge {
+    /// The name of the driving node
+    from: String,
+    /// The name of the load node, or of the top-level output
+    to: String,
+    /// The name of the input port of the load, if it is a node
+    port: Option<String>,
+}
+
+/// The differences between two netlists, as found by [diff]
+#[derive(Debug, Clone)]
+pub struct NetlistDiff<I: Instantiable> {
+    /// The nodes of both netlists, left ones first
+    nodes: Vec<DiffNode<I>>,
+    /// The connections of both netlists and whether they are in the left and right netlists
+    edges: BTreeMap<DiffEdge, (bool, bool)>,
+    /// The top-level outputs of both netlists and whether they are in the left and right netlists
+    outputs: BTreeMap<String, (bool, bool)>,
+}
+
+impl<I> NetlistDiff<I>
+where
+    I: Instantiable,
+{
+    /// Returns the nodes of both netlists, in the order of the left netlist followed by added nodes
+    pub fn nodes(&self) -> &[DiffNode<I>] {
+        &self.nodes
+    }
+
+    /// Returns the nodes with the given change
+    pub fn with_change(&self, change: Change) -> impl Iterator<Item = &DiffNode<I>> {
+        self.nodes.iter().filter(move |n| n.change == change)
+    }
+
+    /// Returns `true` if the two netlists have the same nodes, connections and outputs
+    pub fn is_empty(&self) -> bool {
+        self.nodes.iter().all(|n| n.change == Change::Unchanged)
+            && self.edges.values().all(|(l, r)| l == r)
+            && self.outputs.values().all(|(l, r)| l == r)
+    }
+
+    /// Returns a Graphviz DOT view that overlays the two netlists.
+    /// Nodes are filled by [Change::color], and connections only in the left or right netlist
+    /// are drawn as red dashed or green edges.
+    pub fn to_dot(&self) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let quote = |s: &str| format!("\"{}\"", escape(s));
+        let edge_style = |in_left: bool, in_right: bool| match (in_left, in_right) {
+            (true, false) => Some("color=red style=dashed".to_string()),
+            (false, true) => Some("color=green".to_string()),
+            _ => None,
+        };
+        let mut dot =
+            String::from("digraph diff {\n  rankdir=LR;\n  node [shape=box style=filled];\n");
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "  {} [label=\"{}\\n{}\" fillcolor={} tooltip={}];\n",
+                quote(&node.name),
+                escape(&node.name),
+                escape(&node.label()),
+                node.change.color(),
+                quote(&node.change.to_string())
+            ));
+        }
+        for (name, (in_left, in_right)) in &self.outputs {
+            let change = match (in_left, in_right) {
+                (true, false) => Change::Removed,
+                (false, true) => Change::Added,
+                _ => Change::Unchanged,
+            };
+            dot.push_str(&format!(
+                "  {} [shape=oval fillcolor={}];\n",
+                quote(&format!("output {name}")),
+                change.color()
+            ));
+        }
+        for (edge, (in_left, in_right)) in &self.edges {
+            let to = match &edge.port {
+                Some(_) => edge.to.clone(),
+                None => format!("output {}", edge.to),
+            };
+            let attrs: Vec<String> = edge
+                .port
+                .iter()
+                .map(|port| format!("label={}", quote(port)))
+                .chain(edge_style(*in_left, *in_right))
+                .collect();
+            dot.push_str(&format!(
+                "  {} -> {} [{}];\n",
+                quote(&edge.from),
+                quote(&to),
+                attrs.join(" ")
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<I> std::fmt::Display for NetlistDiff<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for node in self.nodes.iter().filter(|n| n.change != Change::Unchanged) {
+            writeln!(f, "{}: {} ({})", node.change, node.name, node.label())?;
+        }
+        for (name, (in_left, in_right)) in &self.outputs {
+            match (in_left, in_right) {
+                (true, false) => writeln!(f, "removed: output {name}")?,
+                (false, true) => writeln!(f, "added: output {name}")?,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name a node is matched by: its instance name, or the name of its net if it is an input or alias
+pub(crate) fn node_name<I: Instantiable>(node: &NetRef<I>) -> String {
+    match node.get_instance_name() {
+        Some(name) => name.to_string(),
+        None => node.get_identifier().to_string(),
+    }
+}
+
+/// Returns the connections into `node`, as the driving node name and the input port name
+fn fanin<I: Instantiable>(node: &NetRef<I>) -> Vec<(String, String)> {
+    let Some(inst_type) = node.get_instance_type().map(|t| t.clone()) else {
+        return Vec::new();
+    };
+    let ports: Vec<String> = inst_type
+        .get_input_ports()
+        .into_iter()
+        .map(|p| p.get_identifier().to_string())
+        .collect();
+    node.operands()
+        .filter_map(|(i, driver)| driver.map(|d| (node_name(&d.unwrap()), ports[i].clone())))
+        .collect()
+}
+
+/// Returns `true` if the instance types of `left` and `right` have the same name and parameters
+fn same_type<I: Instantiable>(left: &NetRef<I>, right: &NetRef<I>) -> bool {
+    match (left.get_instance_type(), right.get_instance_type()) {
+        (Some(l), Some(r)) => {
+            let params = |t: &I| {
+                t.parameters()
+                    .map(|(k, v)| format!("{k}={v}"))
+                    .collect::<Vec<_>>()
+            };
+            l.get_name() == r.get_name() && params(&l) == params(&r)
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Compares `left` against `right`, matching instances by name and inputs by net name.
+/// A matched node is changed if its type, parameters or the nodes driving its inputs differ.
+pub fn diff<I: Instantiable>(left: &Netlist<I>, right: &Netlist<I>) -> NetlistDiff<I> {
+    let mut right_nodes: HashMap<String, NetRef<I>> =
+        right.objects().map(|n| (node_name(&n), n)).collect();
+    let mut edges: BTreeMap<DiffEdge, (bool, bool)> = BTreeMap::new();
+    let mut nodes = Vec::new();
+
+    for (side, netlist) in [left, right].into_iter().enumerate() {
+        for node in netlist.objects() {
+            let to = node_name(&node);
+            for (from, port) in fanin(&node) {
+                let entry = edges
+                    .entry(DiffEdge {
+                        from,
+                        to: to.clone(),
+                        port: Some(port),
+                    })
+                    .or_default();
+                if side == 0 {
+                    entry.0 = true;
+                } else {
+                    entry.1 = true;
+                }
+            }
+        }
+    }
+
+    for node in left.objects() {
+        let name = node_name(&node);
+        let change = match right_nodes.get(&name) {
+            None => Change::Removed,
+            Some(other) if same_type(&node, other) && fanin(&node) == fanin(other) => {
+                Change::Unchanged
+            }
+            Some(_) => Change::Changed,
+        };
+        nodes.push(DiffNode {
+            right: right_nodes.remove(&name),
+            name,
+            change,
+            left: Some(node),
+        });
+    }
+    for node in right.objects() {
+        let name = node_name(&node);
+        if right_nodes.remove(&name).is_some() {
+            nodes.push(DiffNode {
+                name,
+                change: Change::Added,
+                left: None,
+                right: Some(node),
+            });
+        }
+    }
+
+    let mut outputs: BTreeMap<String, (bool, bool)> = BTreeMap::new();
+    for (side, netlist) in [left, right].into_iter().enumerate() {
+        for (driver, net) in netlist.outputs() {
+            let name = net.get_identifier().to_string();
+            let entry = outputs.entry(name.clone()).or_default();
+            let edge = edges
+                .entry(DiffEdge {
+                    from: node_name(&driver.unwrap()),
+                    to: name,
+                    port: None,
+                })
+                .or_default();
+            if side == 0 {
+                entry.0 = true;
+                edge.0 = true;
+            } else {
+                entry.1 = true;
+                edge.1 = true;
+            }
+        }
+    }
+
+    NetlistDiff {
+        nodes,
+        edges,
+        outputs,
+    }
+}
@@ -0,0 +1,852 @@
+/*!
+
+  Emitters that write netlists in an output format, like Verilog.
+
+*/
+
+use crate::{
+    attribute::{Attribute, Parameter},
+    circuit::{
+        DataType, EscapePolicy, Identifier, Instantiable, Net, PortDirection, VerilogEscaping,
+    },
+    netlist::Port,
+    supply::Supply,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+/// An error emitting a netlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmitError {
+    /// The output could not be written to
+    Write,
+    /// The netlist cannot be written in the format of the emitter, for the given reason
+    Unsupported(String),
+}
+
+impl std::fmt::Display for EmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmitError::Write => write!(f, "Could not write the output"),
+            EmitError::Unsupported(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for EmitError {}
+
+impl From<std::fmt::Error> for EmitError {
+    fn from(_: std::fmt::Error) -> Self {
+        EmitError::Write
+    }
+}
+
+/// The result of the methods of a [NetlistEmitter]
+pub type EmitResult = Result<(), EmitError>;
+
+/// An instance as it is passed to [NetlistEmitter::instance]
+#[derive(Debug)]
+pub struct InstanceView<'a, I: Instantiable> {
+    /// The name of the instance
+    name: &'a Identifier,
+    /// The type of the instance
+    inst_type: &'a I,
+    /// The attributes of the instance, except its initial value
+    attributes: Vec<Attribute>,
+    /// The initial value set on the instance
+    init: Option<bool>,
+    /// The connected input ports and the nets driving them
+    inputs: Vec<(&'a Net, Net)>,
+    /// The input ports that are not connected
+    unconnected: Vec<&'a Net>,
+    /// The output ports and the nets they drive
+    outputs: Vec<(&'a Net, &'a Net)>,
+    /// The supply nets of the netlist
+    supplies: &'a [(Net, Supply)],
+}
+
+impl<'a, I> InstanceView<'a, I>
+where
+    I: Instantiable,
+{
+    /// Creates a view of an instance for an emitter, with no attributes or connections yet
+    pub(crate) fn new(
+        name: &'a Identifier,
+        inst_type: &'a I,
+        supplies: &'a [(Net, Supply)],
+    ) -> Self {
+        Self {
+            name,
+            inst_type,
+            attributes: Vec::new(),
+            init: None,
+            inputs: Vec::new(),
+            unconnected: Vec::new(),
+            outputs: Vec::new(),
+            supplies,
+        }
+    }
+
+    /// Sets the attributes of the instance, except its initial value
+    pub(crate) fn with_attributes(mut self, attributes: Vec<Attribute>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Sets the initial value set on the instance
+    pub(crate) fn with_init(mut self, init: Option<bool>) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// Sets the connected input ports with the nets driving them, and the input ports that are not connected
+    pub(crate) fn with_inputs(
+        mut self,
+        inputs: Vec<(&'a Net, Net)>,
+        unconnected: Vec<&'a Net>,
+    ) -> Self {
+        self.inputs = inputs;
+        self.unconnected = unconnected;
+        self
+    }
+
+    /// Sets the output ports with the nets they drive
+    pub(crate) fn with_outputs(mut self, outputs: Vec<(&'a Net, &'a Net)>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+
+    /// Returns the name of the instance
+    pub fn get_name(&self) -> &Identifier {
+        self.name
+    }
+
+    /// Returns the type of the instance
+    pub fn get_instance_type(&self) -> &I {
+        self.inst_type
+    }
+
+    /// Returns the attributes of the instance, except its initial value
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// Returns the initial value set on the instance (see [crate::netlist::NetRef::set_initial_value])
+    pub fn get_init(&self) -> Option<bool> {
+        self.init
+    }
+
+    /// Returns the connected input ports and the nets driving them. Unconnected ports are skipped.
+    pub fn inputs(&self) -> &[(&'a Net, Net)] {
+        &self.inputs
+    }
+
+    /// Returns the input ports that are not connected, in the order of the ports
+    pub fn unconnected(&self) -> &[&'a Net] {
+        &self.unconnected
+    }
+
+    /// Returns the output ports and the nets they drive
+    pub fn outputs(&self) -> &[(&'a Net, &'a Net)] {
+        &self.outputs
+    }
+
+    /// Returns the supply nets of the netlist, for emitters that connect power and ground pins
+    pub fn supplies(&self) -> &[(Net, Supply)] {
+        self.supplies
+    }
+}
+
+/// A format to write netlists in, driven by [Netlist::emit](crate::netlist::Netlist::emit).
+/// The netlist is traversed in order: the header, the port declarations, the wire declarations, the instances, the assigns and the footer.
+pub trait NetlistEmitter<I: Instantiable> {
+    /// Emits the start of the module named `name` with its `ports`
+    fn header(&mut self, w: &mut dyn Write, name: &str, ports: &[Port]) -> EmitResult;
+
+    /// Takes note of the names of the instances before any net is declared, for emitters that make up net names.
+    /// Instances in arrays are given by their bit names, like `u_fa[0]`.
+    /// **The default implementation does nothing.**
+    fn instance_names(&mut self, names: &[Identifier]) {
+        let _ = names;
+    }
+
+    /// Emits the declaration of a port
+    fn port(&mut self, w: &mut dyn Write, port: &Port) -> EmitResult;
+
+    /// Emits the declaration of a net driven by an instance, which is not a port
+    fn wire(&mut self, w: &mut dyn Write, net: &Net) -> EmitResult;
+
+    /// Emits an instance and its connections
+    fn instance(&mut self, w: &mut dyn Write, inst: &InstanceView<'_, I>) -> EmitResult;
+
+    /// Emits the instances of the array `name` with indices from `range`, given as its most and least significant index.
+    /// The instances are given lsb first (see [Netlist::instance_arrays](crate::netlist::Netlist::instance_arrays)).
+    /// **The default implementation emits each instance on its own with [NetlistEmitter::instance].**
+    fn instance_array(
+        &mut self,
+        w: &mut dyn Write,
+        name: &Identifier,
+        range: (usize, usize),
+        insts: &[InstanceView<'_, I>],
+    ) -> EmitResult {
+        let _ = (name, range);
+        for inst in insts {
+            self.instance(w, inst)?;
+        }
+        Ok(())
+    }
+
+    /// Emits the connection of the top-level output or alias `output` to the net `driver`.
+    /// Outputs that are the driving net itself are not assigned.
+    fn assign(&mut self, w: &mut dyn Write, output: &Net, driver: &Net) -> EmitResult;
+
+    /// Emits the end of the module named `name`
+    fn footer(&mut self, w: &mut dyn Write, name: &str) -> EmitResult;
+}
+
+/// The radix bit vector parameters are written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamRadix {
+    /// Binary, like `8'b10000000`
+    #[default]
+    Binary,
+    /// Hexadecimal, like `8'h80`
+    Hex,
+}
+
+/// How the Verilog emitter writes input ports that are not connected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnconnectedInput {
+    /// Leave the port out of the connections
+    #[default]
+    Skip,
+    /// List the port with nothing connected, like `.A()`
+    Empty,
+    /// Tie the port to a constant, like `.A(1'b0)`
+    Tie(bool),
+    /// Tie the port to its default value (see [Instantiable::get_input_default]), failing if it has none
+    TieDefault,
+    /// Fail the emission with an [EmitError::Unsupported] naming the port
+    Error,
+}
+
+/// Writes a netlist as a structural Verilog module. This is the format used by the [std::fmt::Display] of a netlist.
+#[derive(Debug, Clone, Default)]
+pub struct VerilogEmitter {
+    /// Whether instance parameters are set with `defparam` statements instead of `#(...)`
+    defparams: bool,
+    /// The radix bit vector parameters are written in
+    radix: ParamRadix,
+    /// Whether the names of nets that are not ports are shortened
+    compress: bool,
+    /// The shortened names given so far, with the original names
+    names: Vec<(Identifier, Identifier)>,
+    /// The position of the shortened name of each original name in `names`
+    renamed: HashMap<Identifier, usize>,
+    /// The names of the ports and instances, which shortened names must not clash with
+    reserved: HashSet<Identifier>,
+    /// Whether bit-sliced wires are declared as vectors
+    buses: bool,
+    /// The wires not declared yet, when they are gathered into vectors
+    pending: Vec<Net>,
+    /// The bits of the vectors declared, as they are referenced
+    bus_bits: HashMap<Identifier, String>,
+    /// How input ports that are not connected are written
+    unconnected: UnconnectedInput,
+    /// The names of the power and ground pins connected on every instance
+    supply_pins: Option<(Identifier, Identifier)>,
+    /// Whether arrays of instances are written as one Verilog instance array
+    arrays: bool,
+    /// The values of each analysis written as trailing comments on instances, by instance name
+    annotations: Vec<(String, HashMap<Identifier, String>)>,
+}
+
+/// Returns the vector and the index a net name is a bit of, like `a` and `3` for `a[3]` or `\a[3] `
+fn bus_bit(id: &Identifier) -> Option<(String, usize)> {
+    if let Some(index) = id.get_bit_index() {
+        return Some((id.get_name().to_string(), index));
+    }
+    if !id.is_escaped() {
+        return None;
+    }
+    let (name, index) = id.get_name().strip_suffix(']')?.split_once('[')?;
+    let plain = name.chars().next()?.is_ascii_alphabetic()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    match plain {
+        true => Some((name.to_string(), index.parse().ok()?)),
+        false => None,
+    }
+}
+
+impl VerilogEmitter {
+    /// Creates an emitter that writes parameters inline in binary, like the [std::fmt::Display] of a netlist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether instance parameters are set with `defparam` statements after the instance instead of `#(...)`
+    pub fn with_defparams(mut self, defparams: bool) -> Self {
+        self.defparams = defparams;
+        self
+    }
+
+    /// Sets the radix bit vector parameters are written in
+    pub fn with_radix(mut self, radix: ParamRadix) -> Self {
+        self.radix = radix;
+        self
+    }
+
+    /// Sets whether the names of nets that are not ports are shortened to `n0`, `n1`, ... in the order they are declared.
+    /// The original names are kept in [VerilogEmitter::name_map] for tracing the output back to the netlist.
+    pub fn with_compressed_names(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Returns the shortened names of the last emitted module with their original names, in the order they were given
+    pub fn name_map(&self) -> &[(Identifier, Identifier)] {
+        &self.names
+    }
+
+    /// Writes [VerilogEmitter::name_map] with one shortened and original name per line
+    pub fn write_name_map(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+        for (short, original) in &self.names {
+            writeln!(w, "{} {}", short.emit_name(), original.emit_name())?;
+        }
+        Ok(())
+    }
+
+    /// Sets whether wires that are bits of a vector, like `a[0]` to `a[3]` or `\a[0] ` to `\a[3] `, are declared as one vector `wire [3:0] a;`
+    /// and referenced by index. Escaped bits are gathered only if the vector name is a plain identifier,
+    /// and bits are declared one by one if the vector name is taken by a port or another wire.
+    pub fn with_buses(mut self, buses: bool) -> Self {
+        self.buses = buses;
+        self
+    }
+
+    /// Sets how input ports that are not connected are written
+    pub fn with_unconnected_inputs(mut self, unconnected: UnconnectedInput) -> Self {
+        self.unconnected = unconnected;
+        self
+    }
+
+    /// Sets the names of the power and ground pins, like `VDD` and `VSS`, to connect on every instance for PG-aware netlists.
+    /// The pins are connected to the first power and ground nets of the netlist (see [Supply]), after the logical ports.
+    /// Instance types that already declare a pin are left as they are, and a missing supply fails the emission.
+    pub fn with_supply_pins(mut self, power: Identifier, ground: Identifier) -> Self {
+        self.supply_pins = Some((power, ground));
+        self
+    }
+
+    /// Sets whether arrays of instances, like `u_fa[0]` to `u_fa[31]`, are written as one Verilog instance array `FA u_fa [31:0] (...)`,
+    /// with each port connected to the concatenation of the nets of the instances, msb first, or to a net they all share.
+    /// Arrays whose instances differ in attributes, have an initial value or unconnected inputs, or need `defparam` statements are written one instance at a time.
+    /// Instances of an array are otherwise written with escaped names, like `\u_fa[0] `, which match the names of the elements of a Verilog instance array.
+    pub fn with_instance_arrays(mut self, arrays: bool) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// Annotates instances with the values of an analysis, written as a trailing comment like `// level=5` after the instance.
+    /// `values` gives the value of `key` for each instance by name, like the logic level from [crate::graph::SimpleCombDepth] or the slack from timing analysis.
+    /// Annotations are written in the order they were added, and instance arrays with annotated instances are written one instance at a time.
+    pub fn with_annotation(
+        mut self,
+        key: &str,
+        values: impl IntoIterator<Item = (Identifier, String)>,
+    ) -> Self {
+        self.annotations
+            .push((key.to_string(), values.into_iter().collect()));
+        self
+    }
+
+    /// Returns the trailing comment an instance is annotated with, like ` // level=5 slack=0.5`, or an empty string if it has none
+    fn annotation(&self, name: &Identifier) -> String {
+        self.annotations
+            .iter()
+            .filter_map(|(key, values)| Some(format!(" {key}={}", values.get(name)?)))
+            .fold(String::new(), |comment, value| match comment.is_empty() {
+                true => format!(" //{value}"),
+                false => comment + &value,
+            })
+    }
+
+    /// Returns the value an unconnected input is written with, or [None] if it is skipped.
+    /// Fails if the port must be connected.
+    fn unconnected_value<I: Instantiable>(
+        &self,
+        inst: &InstanceView<'_, I>,
+        port: &Net,
+    ) -> Result<Option<String>, EmitError> {
+        let tie = |v: bool| Ok(Some(format!("1'b{}", v as u8)));
+        let index = || {
+            inst.get_instance_type()
+                .get_input_ports()
+                .into_iter()
+                .position(|p| std::ptr::eq(p, port))
+        };
+        let default = index().and_then(|i| inst.get_instance_type().get_input_default(i));
+        match (self.unconnected, default) {
+            (UnconnectedInput::Skip, _) => Ok(None),
+            (UnconnectedInput::Empty, _) => Ok(Some(String::new())),
+            (UnconnectedInput::Tie(v), _) | (UnconnectedInput::TieDefault, Some(v)) => tie(v),
+            (UnconnectedInput::TieDefault, None) | (UnconnectedInput::Error, _) => {
+                Err(EmitError::Unsupported(format!(
+                    "Input {} of instance {} is not connected",
+                    port.get_identifier(),
+                    inst.get_name()
+                )))
+            }
+        }
+    }
+
+    /// Returns the name a net is written with, which is shortened or a vector bit if it was declared as a wire
+    fn net_name(&self, net: &Net) -> String {
+        if let Some(i) = self.renamed.get(net.get_identifier()) {
+            return self.names[*i].0.emit_name();
+        }
+        match self.bus_bits.get(net.get_identifier()) {
+            Some(bit) => bit.clone(),
+            None => net.get_identifier().emit_name(),
+        }
+    }
+
+    /// Declares the wires gathered for vectors, in the order of their first bits
+    fn flush_wires(&mut self, w: &mut dyn Write) -> EmitResult {
+        let pending = std::mem::take(&mut self.pending);
+        let scalars: HashSet<String> = pending
+            .iter()
+            .filter(|n| bus_bit(n.get_identifier()).is_none())
+            .map(|n| n.get_identifier().get_name().to_string())
+            .collect();
+        let mut order: Vec<String> = Vec::new();
+        let mut buses: HashMap<String, Vec<(usize, &Net)>> = HashMap::new();
+        for net in &pending {
+            let Some((name, index)) = bus_bit(net.get_identifier()) else {
+                continue;
+            };
+            if !buses.contains_key(&name) {
+                order.push(name.clone());
+            }
+            buses.entry(name).or_default().push((index, net));
+        }
+        let reserved: HashSet<String> = self
+            .reserved
+            .iter()
+            .map(|id| id.get_name().to_string())
+            .collect();
+        for name in order {
+            let bits = &buses[&name];
+            let distinct: HashSet<usize> = bits.iter().map(|(i, _)| *i).collect();
+            if scalars.contains(&name) || reserved.contains(&name) || distinct.len() != bits.len() {
+                continue;
+            }
+            let msb = bits.iter().map(|(i, _)| *i).max().unwrap();
+            let lsb = bits.iter().map(|(i, _)| *i).min().unwrap();
+            writeln!(w, "{}wire [{msb}:{lsb}] {name};", Self::INDENT)?;
+            for (index, net) in bits {
+                self.bus_bits
+                    .insert(net.get_identifier().clone(), format!("{name}[{index}]"));
+            }
+        }
+        for net in pending
+            .iter()
+            .filter(|n| !self.bus_bits.contains_key(n.get_identifier()))
+        {
+            writeln!(
+                w,
+                "{}{} {};",
+                Self::INDENT,
+                Self::net_kind(net),
+                self.net_name(net)
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Formats a parameter value in the radix of the emitter
+    fn format_param(&self, param: &Parameter) -> String {
+        match (param, self.radix) {
+            (Parameter::BitVec(bv), ParamRadix::Hex) => {
+                let digits: String = bv
+                    .chunks(4)
+                    .rev()
+                    .map(|nibble| {
+                        let v = nibble
+                            .iter()
+                            .enumerate()
+                            .fold(0, |acc, (i, b)| acc | ((*b as u32) << i));
+                        char::from_digit(v, 16).unwrap()
+                    })
+                    .collect();
+                format!("{}'h{digits}", bv.len())
+            }
+            _ => param.to_string(),
+        }
+    }
+
+    /// Fails if `inst_type` has a parameter Verilog cannot write, like an empty bit vector
+    fn check_params<I: Instantiable>(name: &Identifier, inst_type: &I) -> EmitResult {
+        let empty = |v: &Parameter| matches!(v, Parameter::BitVec(bv) if bv.is_empty());
+        if let Some((k, _)) = inst_type.parameters().find(|(_, v)| empty(v)) {
+            return Err(EmitError::Unsupported(format!(
+                "Parameter {k} of instance {name} is an empty bit vector, which has no Verilog literal"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the name an instance is written with. Bits of an array, like `u_fa[0]`, are escaped.
+    fn instance_name(id: &Identifier) -> String {
+        match id.is_sliced() {
+            true => format!("\\{id} "),
+            false => id.emit_name(),
+        }
+    }
+
+    /// Emits the type of an instance with its parameters, unless they are set with `defparam` statements
+    fn type_and_params<I: Instantiable>(&self, w: &mut dyn Write, inst_type: &I) -> EmitResult {
+        let (indent, inner) = (Self::INDENT, Self::INNER);
+        let params: Vec<_> = inst_type.parameters().collect();
+        write!(w, "{indent}{} ", inst_type.get_name())?;
+        if !params.is_empty() && !self.defparams {
+            writeln!(w, "#(")?;
+            for (i, (k, v)) in params.iter().enumerate() {
+                let v = self.format_param(v);
+                if i == params.len() - 1 {
+                    writeln!(w, "{inner}.{k}({v})")?;
+                } else {
+                    writeln!(w, "{inner}.{k}({v}),")?;
+                }
+            }
+            write!(w, "{indent}) ")?;
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the instances can be written as one instance array
+    fn can_group<I: Instantiable>(&self, insts: &[InstanceView<'_, I>]) -> bool {
+        let sorted = |inst: &InstanceView<'_, I>| {
+            let mut attributes = inst.attributes().to_vec();
+            attributes.sort_by(|a, b| a.key().cmp(b.key()));
+            attributes
+        };
+        let Some(first) = insts.first() else {
+            return false;
+        };
+        let attributes = sorted(first);
+        let defparams = self.defparams && first.get_instance_type().parameters().next().is_some();
+        !defparams
+            && insts.iter().all(|inst| {
+                inst.get_init().is_none()
+                    && inst.unconnected().is_empty()
+                    && sorted(inst) == attributes
+                    && self.annotation(inst.get_name()).is_empty()
+            })
+    }
+
+    /// The indentation of the module body
+    const INDENT: &str = "  ";
+    /// The indentation of instance connections and parameters
+    const INNER: &str = "    ";
+
+    /// Returns the keyword and range a net is declared with, like `wire`, `supply1` or `wire signed [7:0]`
+    fn net_kind(net: &Net) -> String {
+        match net.get_type() {
+            DataType::Supply0 => "supply0".to_string(),
+            DataType::Supply1 => "supply1".to_string(),
+            _ => format!("wire{}", Self::net_range(net)),
+        }
+    }
+
+    /// Returns the signedness and range a vector net is declared with, like ` signed [7:0]`, or nothing for a single bit
+    fn net_range(net: &Net) -> String {
+        match net.get_type() {
+            DataType::Vector { width, signed } => {
+                let signed = if *signed { " signed" } else { "" };
+                format!("{signed} [{}:0]", width.saturating_sub(1))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Emits an attribute on its own line
+    fn attribute(w: &mut dyn Write, attr: &Attribute) -> EmitResult {
+        let indent = Self::INDENT;
+        // Keys that are not simple identifiers, like the namespaced `xilinx.LOC`, are escaped
+        let key = match VerilogEscaping.needs_escape(attr.key()) {
+            true => VerilogEscaping.escape(attr.key()),
+            false => attr.key().clone(),
+        };
+        if let Some(value) = attr.value() {
+            Ok(writeln!(w, "{indent}(* {key} = \"{value}\" *)")?)
+        } else {
+            Ok(writeln!(w, "{indent}(* {key} *)")?)
+        }
+    }
+}
+
+impl<I> NetlistEmitter<I> for VerilogEmitter
+where
+    I: Instantiable,
+{
+    fn header(&mut self, w: &mut dyn Write, name: &str, ports: &[Port]) -> EmitResult {
+        self.names.clear();
+        self.renamed.clear();
+        self.pending.clear();
+        self.bus_bits.clear();
+        self.reserved = ports
+            .iter()
+            .flat_map(|p| p.get_nets().iter().map(|n| n.get_identifier().clone()))
+            .chain(ports.iter().map(|p| p.get_name().clone()))
+            .collect();
+        writeln!(w, "module {name} (")?;
+        let port_names: Vec<String> = ports
+            .iter()
+            .flat_map(|p| match p.is_vector() {
+                true => vec![p.get_name().emit_name()],
+                false => p
+                    .get_nets()
+                    .iter()
+                    .map(|n| n.get_identifier().emit_name())
+                    .collect(),
+            })
+            .collect();
+        let indent = Self::INDENT;
+        for (i, name) in port_names.iter().enumerate() {
+            if i == port_names.len() - 1 {
+                writeln!(w, "{indent}{name}")?;
+            } else {
+                writeln!(w, "{indent}{name},")?;
+            }
+        }
+        Ok(writeln!(w, ");")?)
+    }
+
+    fn instance_names(&mut self, names: &[Identifier]) {
+        // Verilog declares nets and instances in the same scope, and an array by the name of its bits
+        for name in names {
+            self.reserved.insert(name.clone());
+            if name.is_sliced() {
+                self.reserved
+                    .insert(Identifier::new(name.get_name().to_string()));
+            }
+        }
+    }
+
+    fn port(&mut self, w: &mut dyn Write, port: &Port) -> EmitResult {
+        let indent = Self::INDENT;
+        for attr in port.attributes() {
+            Self::attribute(w, attr)?;
+        }
+        if let Some((msb, lsb)) = port.get_range() {
+            let name = port.get_name().emit_name();
+            writeln!(w, "{indent}{} [{msb}:{lsb}] {name};", port.get_direction())?;
+            return Ok(writeln!(w, "{indent}wire [{msb}:{lsb}] {name};")?);
+        }
+        for net in port.get_nets() {
+            let name = net.get_identifier().emit_name();
+            let range = Self::net_range(net);
+            writeln!(w, "{indent}{}{range} {name};", port.get_direction())?;
+            writeln!(w, "{indent}{} {name};", Self::net_kind(net))?;
+        }
+        Ok(())
+    }
+
+    fn wire(&mut self, w: &mut dyn Write, net: &Net) -> EmitResult {
+        if self.compress {
+            let short = (self.names.len()..)
+                .map(|i| Identifier::new(format!("n{i}")))
+                .find(|id| !self.reserved.contains(id))
+                .unwrap();
+            self.reserved.insert(short.clone());
+            self.renamed
+                .insert(net.get_identifier().clone(), self.names.len());
+            self.names.push((short, net.get_identifier().clone()));
+        } else if self.buses {
+            self.pending.push(net.clone());
+            return Ok(());
+        }
+        Ok(writeln!(
+            w,
+            "{}{} {};",
+            Self::INDENT,
+            Self::net_kind(net),
+            self.net_name(net)
+        )?)
+    }
+
+    fn instance(&mut self, w: &mut dyn Write, inst: &InstanceView<'_, I>) -> EmitResult {
+        self.flush_wires(w)?;
+        let (indent, inner) = (Self::INDENT, Self::INNER);
+        for attr in inst.attributes() {
+            Self::attribute(w, attr)?;
+        }
+
+        let inst_type = inst.get_instance_type();
+        Self::check_params(inst.get_name(), inst_type)?;
+        // The initial value is written as a defparam of INIT, which would set the parameter of a type that has one twice
+        if inst.get_init().is_some() && inst_type.has_parameter(&"INIT".into()) {
+            return Err(EmitError::Unsupported(format!(
+                "Instance {} has an initial value, but its type already has an INIT parameter",
+                inst.get_name()
+            )));
+        }
+        let params: Vec<_> = inst_type.parameters().collect();
+        self.type_and_params(w, inst_type)?;
+        let name = Self::instance_name(inst.get_name());
+        writeln!(w, "{name} (")?;
+        // Ports are connected in the order the type declares them
+        let mut connections = Vec::new();
+        for (port, direction) in inst.get_instance_type().get_ports() {
+            let value = match direction {
+                PortDirection::Output => inst
+                    .outputs()
+                    .iter()
+                    .find(|(p, _)| std::ptr::eq(*p, port))
+                    .map(|(_, net)| self.net_name(net)),
+                _ => match inst.inputs().iter().find(|(p, _)| std::ptr::eq(*p, port)) {
+                    Some((_, net)) => Some(self.net_name(net)),
+                    None => self.unconnected_value(inst, port)?,
+                },
+            };
+            if let Some(value) = value {
+                let port = port.get_identifier().emit_name();
+                connections.push(format!("{inner}.{port}({value})"));
+            }
+        }
+        if let Some((power, ground)) = self.supply_pins.clone() {
+            for (pin, supply) in [(power, Supply::Power), (ground, Supply::Ground)] {
+                if inst_type
+                    .get_ports()
+                    .into_iter()
+                    .any(|(p, _)| *p.get_identifier() == pin)
+                {
+                    continue;
+                }
+                let Some((net, _)) = inst.supplies().iter().find(|(_, s)| *s == supply) else {
+                    return Err(EmitError::Unsupported(format!(
+                        "No {supply} net to connect to pin {pin} of instance {}",
+                        inst.get_name()
+                    )));
+                };
+                let value = self.net_name(net);
+                connections.push(format!("{inner}.{}({value})", pin.emit_name()));
+            }
+        }
+        if !connections.is_empty() {
+            writeln!(w, "{}", connections.join(",\n"))?;
+        }
+        writeln!(w, "{indent});{}", self.annotation(inst.get_name()))?;
+        if self.defparams {
+            for (k, v) in &params {
+                writeln!(w, "{indent}defparam {name}.{k} = {};", self.format_param(v))?;
+            }
+        }
+        if let Some(init) = inst.get_init() {
+            writeln!(w, "{indent}defparam {name}.INIT = 1'b{};", init as u8)?;
+        }
+        Ok(())
+    }
+
+    fn instance_array(
+        &mut self,
+        w: &mut dyn Write,
+        name: &Identifier,
+        (msb, lsb): (usize, usize),
+        insts: &[InstanceView<'_, I>],
+    ) -> EmitResult {
+        if !self.arrays || !self.can_group(insts) {
+            for inst in insts {
+                self.instance(w, inst)?;
+            }
+            return Ok(());
+        }
+        self.flush_wires(w)?;
+        let (indent, inner) = (Self::INDENT, Self::INNER);
+        let first = &insts[0];
+        for attr in first.attributes() {
+            Self::attribute(w, attr)?;
+        }
+        let inst_type = first.get_instance_type();
+        Self::check_params(name, inst_type)?;
+        self.type_and_params(w, inst_type)?;
+        writeln!(w, "{} [{msb}:{lsb}] (", name.emit_name())?;
+        // Each port is connected to the nets of the instances msb first, or to the net they all share
+        let mut connections = Vec::new();
+        let ports = inst_type.get_ports();
+        for (index, (port, direction)) in ports.iter().enumerate() {
+            let nets: Vec<String> = insts
+                .iter()
+                .rev()
+                .filter_map(|inst| {
+                    let (p, _) = inst
+                        .get_instance_type()
+                        .get_ports()
+                        .into_iter()
+                        .nth(index)?;
+                    let net = match direction {
+                        PortDirection::Output => inst
+                            .outputs()
+                            .iter()
+                            .find(|(q, _)| std::ptr::eq(*q, p))
+                            .map(|(_, net)| *net),
+                        _ => inst
+                            .inputs()
+                            .iter()
+                            .find(|(q, _)| std::ptr::eq(*q, p))
+                            .map(|(_, net)| net),
+                    };
+                    net.map(|n| self.net_name(n))
+                })
+                .collect();
+            let value = match nets.as_slice() {
+                [] => continue,
+                [net, rest @ ..]
+                    if rest.iter().all(|n| n == net) && *direction != PortDirection::Output =>
+                {
+                    net.clone()
+                }
+                _ => format!("{{{}}}", nets.join(", ")),
+            };
+            let port = port.get_identifier().emit_name();
+            connections.push(format!("{inner}.{port}({value})"));
+        }
+        if let Some((power, ground)) = self.supply_pins.clone() {
+            for (pin, supply) in [(power, Supply::Power), (ground, Supply::Ground)] {
+                if ports.iter().any(|(p, _)| *p.get_identifier() == pin) {
+                    continue;
+                }
+                let Some((net, _)) = first.supplies().iter().find(|(_, s)| *s == supply) else {
+                    return Err(EmitError::Unsupported(format!(
+                        "No {supply} net to connect to pin {pin} of instance array {name}"
+                    )));
+                };
+                let value = self.net_name(net);
+                connections.push(format!("{inner}.{}({value})", pin.emit_name()));
+            }
+        }
+        if !connections.is_empty() {
+            writeln!(w, "{}", connections.join(",\n"))?;
+        }
+        Ok(writeln!(w, "{indent});")?)
+    }
+
+    fn assign(&mut self, w: &mut dyn Write, output: &Net, driver: &Net) -> EmitResult {
+        self.flush_wires(w)?;
+        Ok(writeln!(
+            w,
+            "{}assign {} = {};",
+            Self::INDENT,
+            self.net_name(output),
+            self.net_name(driver)
+        )?)
+    }
+
+    fn footer(&mut self, w: &mut dyn Write, _name: &str) -> EmitResult {
+        self.flush_wires(w)?;
+        Ok(writeln!(w, "endmodule")?)
+    }
+}
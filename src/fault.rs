@@ -0,0 +1,533 @@
+/*!
+
+  Stuck-at fault models and fault simulation, for grading how well a set of test vectors covers a netlist,
+  and SCOAP testability measures, for finding the nets that are hard to test before any vectors exist.
+
+*/
+
+use crate::{
+    circuit::{Instantiable, SequentialElement},
+    graph::{Analysis, FanOutTable, topological_order},
+    netlist::{DrivenNet, Gate, InputPort, NetRef, Netlist},
+    sim::{Logic, PackedSimulator, Simulator, eval_gate},
+};
+use std::collections::HashMap;
+
+/// Where a stuck-at fault sits in a netlist
+#[derive(Debug, Clone)]
+pub enum FaultSite<I: Instantiable> {
+    /// A net, as seen by all of its loads
+    Net(DrivenNet<I>),
+    /// An input pin of an instance, as seen only by that instance
+    Pin(InputPort<I>),
+}
+
+/// A net or pin stuck at a constant value
+#[derive(Debug, Clone)]
+pub struct Fault<I: Instantiable> {
+    /// Where the fault sits
+    site: FaultSite<I>,
+    /// The value the site is stuck at
+    value: bool,
+}
+
+impl<I> Fault<I>
+where
+    I: Instantiable,
+{
+    /// Creates a fault that sticks `site` at `value`
+    pub fn new(site: FaultSite<I>, value: bool) -> Self {
+        Self { site, value }
+    }
+
+    /// Returns where the fault sits
+    pub fn site(&self) -> &FaultSite<I> {
+        &self.site
+    }
+
+    /// Returns the value the site is stuck at
+    pub fn value(&self) -> bool {
+        self.value
+    }
+
+    /// Injects the fault into `sim` for its following steps, until it is released with [Simulator::release]
+    pub fn inject(&self, sim: &mut Simulator<'_, I>)
+    where
+        I: SequentialElement,
+    {
+        match &self.site {
+            FaultSite::Net(net) => sim.force(net, self.value.into()),
+            FaultSite::Pin(pin) => sim.force_pin(pin, self.value.into()),
+        }
+    }
+
+    /// Injects the fault into every set of values of `sim`, until it is released with [PackedSimulator::release]
+    pub fn inject_packed(&self, sim: &mut PackedSimulator<'_, I>) {
+        match &self.site {
+            FaultSite::Net(net) => sim.force(net, self.value),
+            FaultSite::Pin(pin) => sim.force_pin(pin, self.value),
+        }
+    }
+}
+
+impl<I> std::fmt::Display for Fault<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.site {
+            FaultSite::Net(net) => write!(f, "{}", net.get_identifier())?,
+            FaultSite::Pin(pin) => {
+                let inst = pin.clone().unwrap().get_instance_name().unwrap();
+                write!(f, "{inst}.{pin}")?;
+            }
+        }
+        write!(f, " stuck-at-{}", u8::from(self.value))
+    }
+}
+
+/// Enumerates the stuck-at-0 and stuck-at-1 faults of `netlist`, in object order.
+/// Every net has both faults, and so does every connected input pin of a net with more than one load,
+/// since a fault on a branch of a net can be told apart from a fault on the net itself.
+/// Pins of nets with a single load are left out, as their faults are equivalent to those of the net.
+pub fn enumerate_faults<I: Instantiable>(netlist: &Netlist<I>) -> Vec<Fault<I>> {
+    let fan_out = FanOutTable::index(netlist);
+    let mut faults = Vec::new();
+    for node in netlist.objects() {
+        for net in node.outputs() {
+            for value in [false, true] {
+                faults.push(Fault::new(FaultSite::Net(net.clone()), value));
+            }
+        }
+        for pin in node.inputs() {
+            let branch = pin
+                .get_driver()
+                .is_some_and(|d| fan_out.get_net_fan_out(&d.as_net()) > 1);
+            if node.get_instance_type().is_some() && branch {
+                for value in [false, true] {
+                    faults.push(Fault::new(FaultSite::Pin(pin.clone()), value));
+                }
+            }
+        }
+    }
+    faults
+}
+
+/// The result of fault simulation: which faults a set of vectors detects
+#[derive(Debug, Clone)]
+pub struct FaultReport<I: Instantiable> {
+    /// The detected faults with the first vector that detects each
+    detected: Vec<(Fault<I>, usize)>,
+    /// The faults that no vector detects
+    undetected: Vec<Fault<I>>,
+}
+
+impl<I> FaultReport<I>
+where
+    I: Instantiable,
+{
+    /// Returns the detected faults with the index of the first vector that detects each
+    pub fn detected(&self) -> &[(Fault<I>, usize)] {
+        &self.detected
+    }
+
+    /// Returns the faults that no vector detects
+    pub fn undetected(&self) -> &[Fault<I>] {
+        &self.undetected
+    }
+
+    /// Returns the fraction of faults that are detected, or 1 if there are none
+    pub fn coverage(&self) -> f64 {
+        let total = self.detected.len() + self.undetected.len();
+        match total {
+            0 => 1.0,
+            _ => self.detected.len() as f64 / total as f64,
+        }
+    }
+}
+
+impl<I> std::fmt::Display for FaultReport<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} faults detected ({:.1}%)",
+            self.detected.len(),
+            self.detected.len() + self.undetected.len(),
+            self.coverage() * 100.0
+        )
+    }
+}
+
+/// Simulates `faults` one at a time against `vectors`, each giving the values of the inputs in the order of [Netlist::inputs].
+/// A fault is detected by a vector when some output is known in both the good and the faulty netlist and differs.
+/// Vectors are applied independently from the reset state, so registers output their initial values, and `eval` evaluates its primitives like in [Simulator::new].
+/// A combinational two-state netlist is faster to simulate 64 vectors at a time with [simulate_faults_packed].
+pub fn simulate_faults<'a, I>(
+    netlist: &'a Netlist<I>,
+    eval: impl Fn(&I, &[Logic]) -> Option<Vec<Logic>> + 'a,
+    faults: &[Fault<I>],
+    vectors: &[Vec<Logic>],
+) -> Result<FaultReport<I>, String>
+where
+    I: SequentialElement,
+{
+    let mut sim = Simulator::new(netlist, eval)?;
+    let good = vectors
+        .iter()
+        .map(|v| {
+            sim.reset();
+            sim.step(v)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut detected = Vec::new();
+    let mut undetected = Vec::new();
+    for fault in faults {
+        fault.inject(&mut sim);
+        let mut first = None;
+        for (i, vector) in vectors.iter().enumerate() {
+            sim.reset();
+            let outputs = sim.step(vector)?;
+            let differs = outputs
+                .iter()
+                .zip(&good[i])
+                .any(|(bad, good)| bad.is_known() && good.is_known() && bad != good);
+            if differs {
+                first = Some(i);
+                break;
+            }
+        }
+        sim.release();
+        match first {
+            Some(i) => detected.push((fault.clone(), i)),
+            None => undetected.push(fault.clone()),
+        }
+    }
+    Ok(FaultReport {
+        detected,
+        undetected,
+    })
+}
+
+/// Simulates `faults` like [simulate_faults], on a combinational two-state netlist (see [Netlist::set_two_state])
+/// with a [PackedSimulator], so each fault is simulated against 64 vectors at a time.
+/// Every output is known in two-state logic, so a fault is detected by a vector when some output differs.
+/// `eval` evaluates primitives with bitwise operations like in [PackedSimulator::new].
+/// Returns an error if the netlist is not declared two-state or cannot be simulated.
+pub fn simulate_faults_packed<'a, I>(
+    netlist: &'a Netlist<I>,
+    eval: impl Fn(&I, &[u64]) -> Option<Vec<u64>> + 'a,
+    faults: &[Fault<I>],
+    vectors: &[Vec<bool>],
+) -> Result<FaultReport<I>, String>
+where
+    I: Instantiable,
+{
+    let mut sim = PackedSimulator::new(netlist, eval)?;
+    let inputs = netlist.inputs().count();
+    if let Some(v) = vectors.iter().find(|v| v.len() != inputs) {
+        return Err(format!("Expected {inputs} input values, got {}", v.len()));
+    }
+    // The vectors in sets of 64, with each input packed into one word and a mask of the sets in use
+    let chunks: Vec<(Vec<u64>, u64)> = vectors
+        .chunks(64)
+        .map(|chunk| {
+            let words = (0..inputs)
+                .map(|i| {
+                    chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0, |w, (bit, v)| w | (v[i] as u64) << bit)
+                })
+                .collect();
+            (words, u64::MAX >> (64 - chunk.len()))
+        })
+        .collect();
+    let good = chunks
+        .iter()
+        .map(|(words, _)| sim.step(words))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut detected = Vec::new();
+    let mut undetected = Vec::new();
+    for fault in faults {
+        fault.inject_packed(&mut sim);
+        let mut first = None;
+        for (i, (words, mask)) in chunks.iter().enumerate() {
+            let outputs = sim.step(words)?;
+            let differs = outputs
+                .iter()
+                .zip(&good[i])
+                .fold(0, |d, (bad, good)| d | (bad ^ good))
+                & mask;
+            if differs != 0 {
+                first = Some(i * 64 + differs.trailing_zeros() as usize);
+                break;
+            }
+        }
+        sim.release();
+        match first {
+            Some(i) => detected.push((fault.clone(), i)),
+            None => undetected.push(fault.clone()),
+        }
+    }
+    Ok(FaultReport {
+        detected,
+        undetected,
+    })
+}
+
+/// The most inputs an instance can have for [Testability] to enumerate its input values
+const MAX_SCOAP_INPUTS: usize = 8;
+
+/// The values an input is set to when enumerating the input values of an instance, where `x` leaves it unset
+const SCOAP_VALUES: [Logic; 3] = [Logic::Zero, Logic::One, Logic::X];
+
+/// SCOAP-style testability measures of a netlist: for each net, the effort to set it to 0 and to 1
+/// (its controllability), and the effort to make its value visible at an output (its observability).
+/// Lower numbers are easier, and [None] means the net cannot be controlled or observed.
+/// Registers are treated as if they were scanned, so their outputs are controlled like inputs and their data inputs are observed like outputs.
+/// The measures of an instance are found by evaluating it over every combination of 0, 1 and unknown inputs,
+/// so instances that cannot be evaluated, or that have more than 8 inputs, are neither controlled nor observed through.
+pub struct Testability<'a, I: Instantiable> {
+    // A reference to the underlying netlist
+    _netlist: &'a Netlist<I>,
+    /// Maps each node to the 0- and 1-controllability of its outputs
+    controllability: HashMap<NetRef<I>, Vec<[Option<usize>; 2]>>,
+    /// Maps each node to the observability of its outputs
+    observability: HashMap<NetRef<I>, Vec<Option<usize>>>,
+}
+
+impl<'a, I> Testability<'a, I>
+where
+    I: SequentialElement,
+{
+    /// Computes the testability of `netlist`, where `eval` evaluates its primitives like in [Simulator::new].
+    /// Returns an error if the netlist has a combinational cycle.
+    pub fn new(
+        netlist: &'a Netlist<I>,
+        eval: impl Fn(&I, &[Logic]) -> Option<Vec<Logic>>,
+    ) -> Result<Self, String> {
+        let order = comb_order(netlist)?;
+
+        let mut controllability: HashMap<NetRef<I>, Vec<[Option<usize>; 2]>> = HashMap::new();
+        let mut tables = HashMap::new();
+        for node in &order {
+            let cc = if let Some(supply) = node.get_supply() {
+                let mut cc = [None; 2];
+                cc[supply.value() as usize] = Some(0);
+                vec![cc]
+            } else if node.is_an_input() || node.is_register() {
+                vec![[Some(1); 2]; node.outputs().count()]
+            } else if node.is_an_alias() {
+                vec![pin_controllability(node, 0, &controllability)]
+            } else {
+                let inputs: Vec<_> = (0..node.get_num_input_ports())
+                    .map(|i| pin_controllability(node, i, &controllability))
+                    .collect();
+                let table = eval_table(&*node.get_instance_type().unwrap(), inputs.len(), &eval);
+                let mut cc = vec![[None; 2]; node.outputs().count()];
+                for (code, outputs) in table.iter().enumerate() {
+                    let Some(outputs) = outputs else { continue };
+                    let Some(cost) = assignment_cost(code, &inputs) else {
+                        continue;
+                    };
+                    for (o, value) in outputs.iter().enumerate().take(cc.len()) {
+                        if let Some(v) = value.as_bool() {
+                            keep_min(&mut cc[o][v as usize], cost + 1);
+                        }
+                    }
+                }
+                tables.insert(node.clone(), (table, inputs));
+                cc
+            };
+            controllability.insert(node.clone(), cc);
+        }
+
+        let mut observability: HashMap<NetRef<I>, Vec<Option<usize>>> = order
+            .iter()
+            .map(|n| (n.clone(), vec![None; n.outputs().count()]))
+            .collect();
+        let observe = |observability: &mut HashMap<NetRef<I>, Vec<Option<usize>>>,
+                       net: DrivenNet<I>,
+                       co: usize| {
+            let node = observability.get_mut(&net.clone().unwrap()).unwrap();
+            keep_min(&mut node[net.get_position()], co);
+        };
+        for (driver, _) in netlist.outputs() {
+            observe(&mut observability, driver, 0);
+        }
+        for node in &order {
+            if node.is_register()
+                && let Some(data) = node.get_data()
+            {
+                observe(&mut observability, data, 0);
+            }
+        }
+        // Loads come after their drivers in the order, so the observability of a node is known once its loads are done
+        for node in order.iter().rev() {
+            if node.is_an_input() || node.is_register() {
+                continue;
+            }
+            let out = observability[node].clone();
+            let pins: Vec<Option<usize>> = match tables.get(node) {
+                None => vec![out[0]],
+                Some((table, inputs)) => (0..inputs.len())
+                    .map(|i| pin_observability(i, table, inputs, &out))
+                    .collect(),
+            };
+            for (i, co) in pins.into_iter().enumerate() {
+                if let (Some(co), Some(driver)) = (co, node.get_input(i).get_driver()) {
+                    observe(&mut observability, driver, co);
+                }
+            }
+        }
+
+        Ok(Testability {
+            _netlist: netlist,
+            controllability,
+            observability,
+        })
+    }
+}
+
+impl<I> Testability<'_, I>
+where
+    I: Instantiable,
+{
+    /// Returns the effort to set `net` to `value`, or [None] if it cannot be set or was not analyzed
+    pub fn get_controllability(&self, net: &DrivenNet<I>, value: bool) -> Option<usize> {
+        self.controllability.get(&net.clone().unwrap())?[net.get_position()][value as usize]
+    }
+
+    /// Returns the effort to observe the value of `net` at an output, or [None] if it cannot be observed or was not analyzed
+    pub fn get_observability(&self, net: &DrivenNet<I>) -> Option<usize> {
+        self.observability.get(&net.clone().unwrap())?[net.get_position()]
+    }
+
+    /// Returns the effort to detect `net` stuck at `value`: setting it to the opposite value and observing it.
+    /// Returns [None] if the fault cannot be detected.
+    pub fn get_detection_cost(&self, net: &DrivenNet<I>, value: bool) -> Option<usize> {
+        Some(self.get_controllability(net, !value)? + self.get_observability(net)?)
+    }
+}
+
+impl<'a> Analysis<'a, Gate> for Testability<'a, Gate> {
+    fn build(netlist: &'a Netlist<Gate>) -> Result<Self, String> {
+        Testability::new(netlist, eval_gate)
+    }
+}
+
+/// Orders the nodes of `netlist` so every node comes after the drivers of its inputs, with inputs and registers as sources
+fn comb_order<I: SequentialElement>(netlist: &Netlist<I>) -> Result<Vec<NetRef<I>>, String> {
+    topological_order(netlist.objects(), |node| {
+        if node.is_an_input() || node.is_register() {
+            return Vec::new();
+        }
+        node.drivers().flatten().collect()
+    })
+    .map_err(|node| format!("Combinational cycle detected through {node}"))
+}
+
+/// Lowers `best` to `cost`, if it is not already lower
+fn keep_min(best: &mut Option<usize>, cost: usize) {
+    *best = Some(best.map_or(cost, |b| b.min(cost)));
+}
+
+/// Returns the controllability of the `index`th input of `node`, from its driver or the value it is tied to when unconnected
+fn pin_controllability<I: Instantiable>(
+    node: &NetRef<I>,
+    index: usize,
+    controllability: &HashMap<NetRef<I>, Vec<[Option<usize>; 2]>>,
+) -> [Option<usize>; 2] {
+    match node.get_input(index).get_driver() {
+        Some(driver) => controllability[&driver.clone().unwrap()][driver.get_position()],
+        None => {
+            let mut cc = [None; 2];
+            if let Some(value) = node
+                .get_instance_type()
+                .and_then(|t| t.get_input_default(index))
+            {
+                cc[value as usize] = Some(0);
+            }
+            cc
+        }
+    }
+}
+
+/// Evaluates a primitive with `n` inputs over every combination of [SCOAP_VALUES], indexed by the base-3 code of the combination.
+/// The table is empty if the primitive has too many inputs.
+fn eval_table<I: Instantiable>(
+    inst: &I,
+    n: usize,
+    eval: &impl Fn(&I, &[Logic]) -> Option<Vec<Logic>>,
+) -> Vec<Option<Vec<Logic>>> {
+    if n > MAX_SCOAP_INPUTS {
+        return Vec::new();
+    }
+    (0..3usize.pow(n as u32))
+        .map(|code| eval(inst, &assignment(code, n)))
+        .collect()
+}
+
+/// Returns the input values of the combination with base-3 `code`, the first input being the least significant digit
+fn assignment(code: usize, n: usize) -> Vec<Logic> {
+    (0..n)
+        .map(|i| SCOAP_VALUES[code / 3usize.pow(i as u32) % 3])
+        .collect()
+}
+
+/// Returns the effort to set the inputs to the combination with base-3 `code`, skipping `skip`, or [None] if some value cannot be set
+fn assignment_cost_skipping(
+    code: usize,
+    inputs: &[[Option<usize>; 2]],
+    skip: Option<usize>,
+) -> Option<usize> {
+    let mut cost = 0;
+    for (i, value) in assignment(code, inputs.len()).into_iter().enumerate() {
+        if Some(i) == skip {
+            continue;
+        }
+        if let Some(v) = value.as_bool() {
+            cost += inputs[i][v as usize]?;
+        }
+    }
+    Some(cost)
+}
+
+/// Returns the effort to set the inputs to the combination with base-3 `code`, or [None] if some value cannot be set
+fn assignment_cost(code: usize, inputs: &[[Option<usize>; 2]]) -> Option<usize> {
+    assignment_cost_skipping(code, inputs, None)
+}
+
+/// Returns the effort to observe the `index`th input of an instance: setting the other inputs so that flipping it
+/// flips an output, plus the observability of that output
+fn pin_observability(
+    index: usize,
+    table: &[Option<Vec<Logic>>],
+    inputs: &[[Option<usize>; 2]],
+    outputs: &[Option<usize>],
+) -> Option<usize> {
+    let digit = 3usize.pow(index as u32);
+    let mut best: Option<usize> = None;
+    for code in (0..table.len()).filter(|c| SCOAP_VALUES[c / digit % 3] == Logic::Zero) {
+        let (Some(zero), Some(one)) = (&table[code], &table[code + digit]) else {
+            continue;
+        };
+        let Some(cost) = assignment_cost_skipping(code, inputs, Some(index)) else {
+            continue;
+        };
+        for (o, co) in outputs.iter().enumerate() {
+            let (Some(co), Some(a), Some(b)) = (co, zero.get(o), one.get(o)) else {
+                continue;
+            };
+            if a.is_known() && b.is_known() && a != b {
+                keep_min(&mut best, cost + co + 1);
+            }
+        }
+    }
+    best
+}
@@ -0,0 +1,229 @@
+/*!
+
+  Immutable views of netlists that can be shared across threads, as made by [Netlist::freeze](crate::netlist::Netlist::freeze).
+
+*/
+
+use crate::{
+    attribute::Attribute,
+    circuit::{Identifier, Instantiable, Net, Object},
+    netlist::Port,
+};
+
+/// An output net of a node in a [FrozenNetlist], addressed by the node index and the output position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FrozenNet {
+    /// The index of the driving node
+    node: usize,
+    /// The position of the output on the driving node
+    output: usize,
+}
+
+impl FrozenNet {
+    /// Creates an address of output `output` of the node at index `node`
+    pub(crate) fn new(node: usize, output: usize) -> Self {
+        Self { node, output }
+    }
+
+    /// Returns the index of the driving node
+    pub fn node(&self) -> usize {
+        self.node
+    }
+
+    /// Returns the position of the output on the driving node
+    pub fn output(&self) -> usize {
+        self.output
+    }
+}
+
+/// A node of a [FrozenNetlist], with the nets driving its inputs and its attributes
+#[derive(Debug, Clone)]
+pub struct FrozenObject<I: Instantiable> {
+    /// The input, instance or alias
+    object: Object<I>,
+    /// The nets driving the inputs, or [None] for unconnected ones
+    operands: Vec<Option<FrozenNet>>,
+    /// The attributes of the node
+    attributes: Vec<Attribute>,
+    /// The initial value set on the node
+    init: Option<bool>,
+}
+
+impl<I> FrozenObject<I>
+where
+    I: Instantiable,
+{
+    /// Creates a node from its object, the nets driving its inputs, its attributes and its initial value
+    pub(crate) fn new(
+        object: Object<I>,
+        operands: Vec<Option<FrozenNet>>,
+        attributes: Vec<Attribute>,
+        init: Option<bool>,
+    ) -> Self {
+        Self {
+            object,
+            operands,
+            attributes,
+            init,
+        }
+    }
+
+    /// Returns the input, instance or alias
+    pub fn get(&self) -> &Object<I> {
+        &self.object
+    }
+
+    /// Returns the type of the instance, if the node is one
+    pub fn get_instance_type(&self) -> Option<&I> {
+        self.object.get_instance_type()
+    }
+
+    /// Returns the name of the instance, if the node is one
+    pub fn get_instance_name(&self) -> Option<&Identifier> {
+        match &self.object {
+            Object::Instance(_, name, _) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the node is a principal input
+    pub fn is_an_input(&self) -> bool {
+        matches!(self.object, Object::Input(_))
+    }
+
+    /// Returns `true` if the node is an alias
+    pub fn is_an_alias(&self) -> bool {
+        matches!(self.object, Object::Alias(_))
+    }
+
+    /// Returns the output nets of the node
+    pub fn get_nets(&self) -> &[Net] {
+        self.object.get_nets()
+    }
+
+    /// Returns the nets driving the inputs, or [None] for unconnected ones
+    pub fn operands(&self) -> &[Option<FrozenNet>] {
+        &self.operands
+    }
+
+    /// Returns the attributes of the node
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+
+    /// Returns the initial value set on the node, without the one of its instance type
+    pub fn get_initial_value(&self) -> Option<bool> {
+        self.init
+    }
+}
+
+/// An immutable copy of a netlist in flat arrays, made by [Netlist::freeze](crate::netlist::Netlist::freeze).
+/// Unlike a netlist, it holds no reference-counted cells, so it is [Send] and [Sync] when the instance type is.
+/// Analyses and exporters can read it from many threads while the netlist it came from keeps being edited.
+#[derive(Debug, Clone)]
+pub struct FrozenNetlist<I: Instantiable> {
+    /// The name of the netlist
+    name: String,
+    /// The nodes in the order of the netlist
+    objects: Vec<FrozenObject<I>>,
+    /// The top-level outputs, in port order, with the nets driving them
+    outputs: Vec<(FrozenNet, Net)>,
+    /// The ports on the module boundary
+    ports: Vec<Port>,
+    /// The loads of every node, as the load index and its input position
+    users: Vec<Vec<(usize, usize)>>,
+}
+
+impl<I> FrozenNetlist<I>
+where
+    I: Instantiable,
+{
+    /// Builds a frozen netlist from its nodes, and computes the loads of every node
+    pub(crate) fn new(
+        name: String,
+        objects: Vec<FrozenObject<I>>,
+        outputs: Vec<(FrozenNet, Net)>,
+        ports: Vec<Port>,
+    ) -> Self {
+        let mut users = vec![Vec::new(); objects.len()];
+        for (index, object) in objects.iter().enumerate() {
+            for (pos, operand) in object.operands.iter().enumerate() {
+                if let Some(net) = operand {
+                    users[net.node].push((index, pos));
+                }
+            }
+        }
+        Self {
+            name,
+            objects,
+            outputs,
+            ports,
+            users,
+        }
+    }
+
+    /// Returns the name of the netlist
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the number of nodes
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns `true` if the netlist has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Returns the nodes in the order of the netlist. The position of a node is its index.
+    pub fn objects(&self) -> &[FrozenObject<I>] {
+        &self.objects
+    }
+
+    /// Returns the node at `index`
+    pub fn get(&self, index: usize) -> Option<&FrozenObject<I>> {
+        self.objects.get(index)
+    }
+
+    /// Returns the net addressed by `net`
+    pub fn net(&self, net: FrozenNet) -> &Net {
+        &self.objects[net.node].get_nets()[net.output]
+    }
+
+    /// Returns the indices of the principal inputs
+    pub fn inputs(&self) -> impl Iterator<Item = usize> {
+        (0..self.objects.len()).filter(|i| self.objects[*i].is_an_input())
+    }
+
+    /// Returns the top-level outputs in port order, with the nets driving them
+    pub fn outputs(&self) -> &[(FrozenNet, Net)] {
+        &self.outputs
+    }
+
+    /// Returns the ports on the module boundary
+    pub fn ports(&self) -> &[Port] {
+        &self.ports
+    }
+
+    /// Returns the loads of the node at `index`, as the load index and its input position
+    pub fn users(&self, index: usize) -> &[(usize, usize)] {
+        &self.users[index]
+    }
+
+    /// Returns the number of inputs `net` drives. Top-level outputs are not counted.
+    pub fn fanout(&self, net: FrozenNet) -> usize {
+        self.users[net.node]
+            .iter()
+            .filter(|(load, pos)| self.objects[*load].operands[*pos] == Some(net))
+            .count()
+    }
+
+    /// Returns the index of the instance named `name`. This operation is O(n).
+    pub fn find_instance(&self, name: &Identifier) -> Option<usize> {
+        self.objects
+            .iter()
+            .position(|o| o.get_instance_name() == Some(name))
+    }
+}
@@ -4,11 +4,11 @@
 
 */
 
-use crate::circuit::{Instantiable, Net};
-#[cfg(feature = "graph")]
-use crate::netlist::Connection;
+use crate::circuit::{Instantiable, Net, SequentialElement};
 use crate::netlist::iter::DFSIterator;
-use crate::netlist::{NetRef, Netlist};
+use crate::netlist::{Connection, InputPort, NetRef, Netlist};
+use crate::persist::PersistentAnalysis;
+use crate::timing::{DelayModel, Model};
 #[cfg(feature = "graph")]
 use petgraph::graph::DiGraph;
 use std::collections::hash_map::Entry;
@@ -28,8 +28,8 @@ where
 pub struct FanOutTable<'a, I: Instantiable> {
     // A reference to the underlying netlist
     _netlist: &'a Netlist<I>,
-    // Maps a net to the list of nodes it drives
-    net_fan_out: HashMap<Net, Vec<NetRef<I>>>,
+    // Maps a net to the list of input ports it drives
+    net_fan_out: HashMap<Net, Vec<InputPort<I>>>,
     /// Maps a node to the list of nodes it drives
     node_fan_out: HashMap<NetRef<I>, Vec<NetRef<I>>>,
     /// Contains nets which are outputs
@@ -42,10 +42,20 @@ where
 {
     /// Returns an iterator to the circuit nodes that use `net`.
     pub fn get_net_users(&self, net: &Net) -> impl Iterator<Item = NetRef<I>> {
+        self.get_net_loads(net).map(|load| load.unwrap())
+    }
+
+    /// Returns an iterator to the input ports that `net` drives.
+    pub fn get_net_loads(&self, net: &Net) -> impl Iterator<Item = InputPort<I>> {
         self.net_fan_out
             .get(net)
             .into_iter()
-            .flat_map(|users| users.iter().cloned())
+            .flat_map(|loads| loads.iter().cloned())
+    }
+
+    /// Returns the number of input ports that `net` drives.
+    pub fn get_net_fan_out(&self, net: &Net) -> usize {
+        self.net_fan_out.get(net).map_or(0, |loads| loads.len())
     }
 
     /// Returns an iterator to the circuit nodes that use `node`.
@@ -69,21 +79,27 @@ where
     I: Instantiable,
 {
     fn build(netlist: &'a Netlist<I>) -> Result<Self, String> {
-        let mut net_fan_out: HashMap<Net, Vec<NetRef<I>>> = HashMap::new();
-        let mut node_fan_out: HashMap<NetRef<I>, Vec<NetRef<I>>> = HashMap::new();
-        let mut is_an_output: HashSet<Net> = HashSet::new();
-
         // This can only be fully-correct on a verified netlist.
         netlist.verify()?;
+        Ok(Self::index(netlist))
+    }
+}
+
+impl<'a, I> FanOutTable<'a, I>
+where
+    I: Instantiable,
+{
+    /// Indexes the fan-out of `netlist` without verifying it first, for the analyses that tolerate an unverified netlist
+    pub(crate) fn index(netlist: &'a Netlist<I>) -> Self {
+        let mut net_fan_out: HashMap<Net, Vec<InputPort<I>>> = HashMap::new();
+        let mut node_fan_out: HashMap<NetRef<I>, Vec<NetRef<I>>> = HashMap::new();
+        let mut is_an_output: HashSet<Net> = HashSet::new();
 
         for c in netlist.connections() {
             if let Entry::Vacant(e) = net_fan_out.entry(c.net()) {
-                e.insert(vec![c.target().unwrap()]);
+                e.insert(vec![c.target()]);
             } else {
-                net_fan_out
-                    .get_mut(&c.net())
-                    .unwrap()
-                    .push(c.target().unwrap());
+                net_fan_out.get_mut(&c.net()).unwrap().push(c.target());
             }
 
             if let Entry::Vacant(e) = node_fan_out.entry(c.src().unwrap()) {
@@ -101,15 +117,200 @@ where
             is_an_output.insert(n);
         }
 
-        Ok(FanOutTable {
+        FanOutTable {
             _netlist: netlist,
             net_fan_out,
             node_fan_out,
             is_an_output,
-        })
+        }
     }
 }
 
+/// Orders `roots` and every node they depend on, so each node comes after the nodes `depends_on` gives for it.
+/// Returns a node on a cycle as the error, if the dependencies have one.
+pub(crate) fn topological_order<I, D>(
+    roots: impl IntoIterator<Item = NetRef<I>>,
+    depends_on: D,
+) -> Result<Vec<NetRef<I>>, NetRef<I>>
+where
+    I: Instantiable,
+    D: Fn(&NetRef<I>) -> Vec<NetRef<I>>,
+{
+    let mut order = Vec::new();
+    let mut done: HashSet<NetRef<I>> = HashSet::new();
+    // A depth-first search from every root, where nodes being expanded are on the stack
+    let mut expanding: HashSet<NetRef<I>> = HashSet::new();
+    for root in roots {
+        let mut stack = vec![(root, false)];
+        while let Some((node, expanded)) = stack.pop() {
+            if done.contains(&node) {
+                continue;
+            }
+            if expanded {
+                expanding.remove(&node);
+                done.insert(node.clone());
+                order.push(node);
+            } else {
+                if !expanding.insert(node.clone()) {
+                    return Err(node);
+                }
+                let dependencies = depends_on(&node);
+                stack.push((node, true));
+                stack.extend(dependencies.into_iter().map(|d| (d, false)));
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// The worst path to a node, from the node where it starts, as found by a depth or timing analysis
+#[derive(Debug, Clone)]
+pub struct CriticalPath<I: Instantiable> {
+    /// The nodes along the path, from where it starts to where it ends
+    nodes: Vec<NetRef<I>>,
+    /// The connections between consecutive nodes
+    connections: Vec<Connection<I>>,
+}
+
+impl<I> CriticalPath<I>
+where
+    I: Instantiable,
+{
+    /// Follows the path back from `end`, where `worst` picks the input of a node the path arrives through, until it picks none
+    pub(crate) fn trace(
+        end: NetRef<I>,
+        worst: impl Fn(&NetRef<I>) -> Option<InputPort<I>>,
+    ) -> Self {
+        let mut nodes = vec![end];
+        let mut connections = Vec::new();
+        while let Some(input) = worst(nodes.last().unwrap()) {
+            let driver = input
+                .get_driver()
+                .expect("The path arrives through an unconnected input");
+            nodes.push(driver.clone().unwrap());
+            connections.push(Connection::new(driver, input));
+        }
+        nodes.reverse();
+        connections.reverse();
+        Self { nodes, connections }
+    }
+
+    /// Extends the path with the connection from its last node to `input`
+    pub(crate) fn with_load(mut self, input: InputPort<I>) -> Self {
+        let driver = input.get_driver().expect("The load is not connected");
+        self.nodes.push(input.clone().unwrap());
+        self.connections.push(Connection::new(driver, input));
+        self
+    }
+
+    /// Returns the nodes along the path, from where it starts to where it ends
+    pub fn nodes(&self) -> &[NetRef<I>] {
+        &self.nodes
+    }
+
+    /// Returns the connections along the path, in order
+    pub fn connections(&self) -> &[Connection<I>] {
+        &self.connections
+    }
+
+    /// Returns the node where the path starts
+    pub fn startpoint(&self) -> &NetRef<I> {
+        self.nodes.first().unwrap()
+    }
+
+    /// Returns the node where the path ends
+    pub fn endpoint(&self) -> &NetRef<I> {
+        self.nodes.last().unwrap()
+    }
+
+    /// Returns the number of nodes along the path
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the path has no nodes, which never happens
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<I> std::fmt::Display for CriticalPath<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+            match node.get_instance_name() {
+                Some(name) => write!(f, "{name}")?,
+                None => write!(f, "{}", node.get_identifier())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the input of `node` whose driver has the largest depth, taking the first on ties
+fn deepest_input<I: Instantiable>(
+    node: &NetRef<I>,
+    depth: &HashMap<NetRef<I>, usize>,
+) -> Option<InputPort<I>> {
+    let mut deepest: Option<(usize, InputPort<I>)> = None;
+    for input in node.inputs() {
+        let Some(d) = input
+            .get_driver()
+            .and_then(|d| depth.get(&d.unwrap()).cloned())
+        else {
+            continue;
+        };
+        if deepest.as_ref().is_none_or(|(best, _)| d > *best) {
+            deepest = Some((d, input));
+        }
+    }
+    deepest.map(|(_, input)| input)
+}
+
+/// Computes the latest arrival at the output of every node in `order`, which lists drivers before their loads.
+/// Nodes that `starts` accepts, and nodes with no analyzed drivers, arrive after their own delay.
+/// Other nodes arrive after their latest input, through the wire to it, and then their own delay.
+fn comb_delays<I: Instantiable>(
+    order: impl IntoIterator<Item = NetRef<I>>,
+    model: &Model<'_, I>,
+    starts: impl Fn(&NetRef<I>) -> bool,
+) -> HashMap<NetRef<I>, f64> {
+    let mut delays: HashMap<NetRef<I>, f64> = HashMap::new();
+    for node in order {
+        let latest = match starts(&node) {
+            true => None,
+            false => node
+                .inputs()
+                .filter_map(|input| {
+                    let driver = input.get_driver()?;
+                    let arrival = delays.get(&driver.clone().unwrap())?;
+                    Some(arrival + model.wire_delay(&driver, &input))
+                })
+                .reduce(f64::max),
+        };
+        let delay = node
+            .get_instance_type()
+            .map_or(0.0, |t| model.cell_delay(&t));
+        delays.insert(node, latest.unwrap_or(0.0) + delay);
+    }
+    delays
+}
+
+/// Returns the arrival times of unit delays, which are the logic levels of `depth`
+fn unit_delays<I: Instantiable>(depth: &HashMap<NetRef<I>, usize>) -> HashMap<NetRef<I>, f64> {
+    depth.iter().map(|(n, d)| (n.clone(), *d as f64)).collect()
+}
+
+/// Returns the largest delay of `delays`, or zero if there are none
+fn max_delay<I: Instantiable>(delays: &HashMap<NetRef<I>, f64>) -> f64 {
+    delays.values().cloned().fold(0.0, f64::max)
+}
+
 /// An simple example to analyze the logic levels of a netlist.
 /// This analysis checks for cycles, but it doesn't check for registers.
 pub struct SimpleCombDepth<'a, I: Instantiable> {
@@ -119,12 +320,36 @@ pub struct SimpleCombDepth<'a, I: Instantiable> {
     comb_depth: HashMap<NetRef<I>, usize>,
     /// The maximum depth of the circuit
     max_depth: usize,
+    // Maps a node to the latest arrival at its output
+    comb_delay: HashMap<NetRef<I>, f64>,
 }
 
-impl<I> SimpleCombDepth<'_, I>
+impl<'a, I> SimpleCombDepth<'a, I>
 where
     I: Instantiable,
 {
+    /// Analyzes the logic levels of `netlist` like [Analysis::build], with arrival times from the cell and wire delays of `model`.
+    /// [Analysis::build] gives every instance a delay of 1 and wires none, so its arrival times are the logic levels.
+    pub fn with_delays(netlist: &'a Netlist<I>, model: &dyn DelayModel<I>) -> Result<Self, String> {
+        let mut depth = Self::build(netlist)?;
+        let order = topological_order(depth.comb_depth.keys().cloned(), |n| {
+            n.drivers().flatten().collect()
+        })
+        .map_err(|node| format!("Cycle detected in the netlist through {node}"))?;
+        depth.comb_delay = comb_delays(order, &Model::new(netlist, model), |_| false);
+        Ok(depth)
+    }
+
+    /// Returns the latest time the output of a node in the circuit arrives.
+    pub fn get_comb_delay(&self, node: &NetRef<I>) -> Option<f64> {
+        self.comb_delay.get(node).cloned()
+    }
+
+    /// Returns the latest arrival time in the circuit.
+    pub fn get_max_delay(&self) -> f64 {
+        max_delay(&self.comb_delay)
+    }
+
     /// Returns the logic level of a node in the circuit.
     pub fn get_comb_depth(&self, node: &NetRef<I>) -> Option<usize> {
         self.comb_depth.get(node).cloned()
@@ -134,6 +359,25 @@ where
     pub fn get_max_depth(&self) -> usize {
         self.max_depth
     }
+
+    /// Returns the deepest path from an input to `node`, or [None] if the node was not analyzed.
+    pub fn get_critical_path_to(&self, node: &NetRef<I>) -> Option<CriticalPath<I>> {
+        self.comb_depth.get(node)?;
+        Some(CriticalPath::trace(node.clone(), |n| {
+            deepest_input(n, &self.comb_depth)
+        }))
+    }
+
+    /// Returns the deepest path from an input to an output, taking the first output on ties.
+    pub fn get_critical_path(&self) -> Option<CriticalPath<I>> {
+        let end = self
+            ._netlist
+            .outputs()
+            .into_iter()
+            .map(|(driver, _)| driver.unwrap())
+            .find(|n| self.comb_depth.get(n) == Some(&self.max_depth))?;
+        self.get_critical_path_to(&end)
+    }
 }
 
 impl<'a, I> Analysis<'a, I> for SimpleCombDepth<'a, I>
@@ -167,20 +411,400 @@ where
                     .cloned()
                     .unwrap_or(usize::MAX);
 
-                comb_depth.insert(node, max_depth + 1);
+                // Aliases are wires, so they add no depth
+                let depth = match node.is_an_alias() {
+                    true => max_depth,
+                    false => max_depth + 1,
+                };
+                comb_depth.insert(node, depth);
             }
         }
 
         let max_depth = comb_depth.values().max().cloned().unwrap_or(0);
+        let comb_delay = unit_delays(&comb_depth);
 
         Ok(SimpleCombDepth {
             _netlist: netlist,
             comb_depth,
             max_depth,
+            comb_delay,
+        })
+    }
+}
+
+impl<'a, I> PersistentAnalysis<'a, I> for SimpleCombDepth<'a, I>
+where
+    I: Instantiable,
+{
+    const KEY: &'static str = "comb_depth";
+
+    fn save(&self) -> Vec<(NetRef<I>, String)> {
+        self.comb_depth
+            .iter()
+            .map(|(n, d)| (n.clone(), d.to_string()))
+            .collect()
+    }
+
+    fn restore(
+        netlist: &'a Netlist<I>,
+        values: HashMap<NetRef<I>, String>,
+    ) -> Result<Self, String> {
+        let mut comb_depth = HashMap::new();
+        for (node, depth) in values {
+            let depth = depth
+                .parse()
+                .map_err(|_| format!("Bad logic level {depth} for {node}"))?;
+            comb_depth.insert(node, depth);
+        }
+        let max_depth = comb_depth.values().max().cloned().unwrap_or(0);
+        let comb_delay = unit_delays(&comb_depth);
+        Ok(SimpleCombDepth {
+            _netlist: netlist,
+            comb_depth,
+            max_depth,
+            comb_delay,
+        })
+    }
+}
+
+/// The logic levels of a sequential netlist, where paths start at inputs and register outputs.
+/// Registers are found with [SequentialElement], and have a depth of 0, so loops through registers are allowed.
+/// The depth of the logic before a register is the depth of the driver of its data input.
+pub struct SequentialCombDepth<'a, I: Instantiable> {
+    // A reference to the underlying netlist
+    _netlist: &'a Netlist<I>,
+    // Maps a node to its logic level from the last register or input
+    comb_depth: HashMap<NetRef<I>, usize>,
+    /// The maximum depth of the circuit
+    max_depth: usize,
+    // Maps a node to the latest arrival at its output, from the last register or input
+    comb_delay: HashMap<NetRef<I>, f64>,
+}
+
+impl<'a, I> SequentialCombDepth<'a, I>
+where
+    I: SequentialElement,
+{
+    /// Analyzes the logic levels of `netlist` like [Analysis::build], with arrival times from the cell and wire delays of `model`.
+    /// Registers arrive after their clock-to-output delay.
+    /// [Analysis::build] gives every instance but a register a delay of 1 and wires none, so its arrival times are the logic levels.
+    pub fn with_delays(netlist: &'a Netlist<I>, model: &dyn DelayModel<I>) -> Result<Self, String> {
+        let mut depth = Self::build(netlist)?;
+        let starts = |n: &NetRef<I>| n.is_an_input() || n.is_register();
+        let order = topological_order(netlist.objects(), |n| match starts(n) {
+            true => Vec::new(),
+            false => n.drivers().flatten().collect(),
+        })
+        .map_err(|node| format!("Combinational cycle detected through {node}"))?;
+        depth.comb_delay = comb_delays(order, &Model::new(netlist, model), starts);
+        Ok(depth)
+    }
+
+    /// Returns the latest time the output of a node in the circuit arrives.
+    pub fn get_comb_delay(&self, node: &NetRef<I>) -> Option<f64> {
+        self.comb_delay.get(node).cloned()
+    }
+
+    /// Returns the latest arrival time in the circuit.
+    pub fn get_max_delay(&self) -> f64 {
+        max_delay(&self.comb_delay)
+    }
+
+    /// Returns the logic level of a node in the circuit.
+    pub fn get_comb_depth(&self, node: &NetRef<I>) -> Option<usize> {
+        self.comb_depth.get(node).cloned()
+    }
+
+    /// Returns the logic level of the data input of `register`, or [None] if it is not a connected register.
+    pub fn get_register_input_depth(&self, register: &NetRef<I>) -> Option<usize> {
+        let data = register.get_data()?;
+        self.get_comb_depth(&data.unwrap())
+    }
+
+    /// Returns the maximum logic level of the circuit.
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Returns the deepest path to `node` from an input or register, or [None] if the node was not analyzed.
+    pub fn get_critical_path_to(&self, node: &NetRef<I>) -> Option<CriticalPath<I>> {
+        self.comb_depth.get(node)?;
+        Some(CriticalPath::trace(node.clone(), |n| {
+            match n.is_register() {
+                true => None,
+                false => deepest_input(n, &self.comb_depth),
+            }
+        }))
+    }
+
+    /// Returns the deepest path from an input or register, taking the first node in the netlist on ties.
+    pub fn get_critical_path(&self) -> Option<CriticalPath<I>> {
+        let end = self
+            ._netlist
+            .objects()
+            .find(|n| self.comb_depth.get(n) == Some(&self.max_depth))?;
+        self.get_critical_path_to(&end)
+    }
+}
+
+impl<'a, I> Analysis<'a, I> for SequentialCombDepth<'a, I>
+where
+    I: SequentialElement,
+{
+    fn build(netlist: &'a Netlist<I>) -> Result<Self, String> {
+        let mut comb_depth: HashMap<NetRef<I>, usize> = HashMap::new();
+        for node in netlist.objects() {
+            if node.is_an_input() || node.is_register() {
+                comb_depth.insert(node, 0);
+            }
+        }
+
+        // A depth-first search from every node, where nodes being expanded are on the stack
+        let mut expanding: HashSet<NetRef<I>> = HashSet::new();
+        for root in netlist.objects() {
+            let mut stack = vec![(root, false)];
+            while let Some((node, expanded)) = stack.pop() {
+                if comb_depth.contains_key(&node) {
+                    continue;
+                }
+                let drivers = (0..node.get_num_input_ports())
+                    .filter_map(|i| netlist.get_driver(node.clone(), i));
+                if expanded {
+                    let max_depth = drivers.map(|d| comb_depth[&d]).max();
+                    // Aliases are wires, so they add no depth
+                    let depth = match node.is_an_alias() {
+                        true => max_depth.unwrap_or(0),
+                        false => max_depth.map_or(1, |d| d + 1),
+                    };
+                    expanding.remove(&node);
+                    comb_depth.insert(node, depth);
+                } else {
+                    if !expanding.insert(node.clone()) {
+                        return Err(format!("Combinational cycle detected through {node}"));
+                    }
+                    stack.push((node.clone(), true));
+                    stack.extend(drivers.map(|d| (d, false)));
+                }
+            }
+        }
+
+        let max_depth = comb_depth.values().max().cloned().unwrap_or(0);
+        let comb_delay = unit_delays(&comb_depth);
+
+        Ok(SequentialCombDepth {
+            _netlist: netlist,
+            comb_depth,
+            max_depth,
+            comb_delay,
+        })
+    }
+}
+
+impl<'a, I> PersistentAnalysis<'a, I> for SequentialCombDepth<'a, I>
+where
+    I: SequentialElement,
+{
+    const KEY: &'static str = "sequential_comb_depth";
+
+    fn save(&self) -> Vec<(NetRef<I>, String)> {
+        self.comb_depth
+            .iter()
+            .map(|(n, d)| (n.clone(), d.to_string()))
+            .collect()
+    }
+
+    fn restore(
+        netlist: &'a Netlist<I>,
+        values: HashMap<NetRef<I>, String>,
+    ) -> Result<Self, String> {
+        let mut comb_depth = HashMap::new();
+        for (node, depth) in values {
+            let depth = depth
+                .parse()
+                .map_err(|_| format!("Bad logic level {depth} for {node}"))?;
+            comb_depth.insert(node, depth);
+        }
+        let max_depth = comb_depth.values().max().cloned().unwrap_or(0);
+        let comb_delay = unit_delays(&comb_depth);
+        Ok(SequentialCombDepth {
+            _netlist: netlist,
+            comb_depth,
+            max_depth,
+            comb_delay,
         })
     }
 }
 
+/// Path queries between circuit nodes, like "how does `a` get to `y`?".
+/// This analysis checks for cycles, but it doesn't check for registers.
+pub struct PathAnalysis<'a, I: Instantiable> {
+    // A reference to the underlying netlist
+    _netlist: &'a Netlist<I>,
+    // Maps a node to the distinct nodes it drives
+    users: HashMap<NetRef<I>, Vec<NetRef<I>>>,
+    // The nodes in topological order
+    order: Vec<NetRef<I>>,
+}
+
+impl<I> PathAnalysis<'_, I>
+where
+    I: Instantiable,
+{
+    /// Returns the paths from `src` to `dst`, each starting at `src` and ending at `dst`
+    pub fn paths_between(&self, src: &NetRef<I>, dst: &NetRef<I>) -> Paths<'_, I> {
+        // Distances are kept for the nodes that are reachable from src
+        let mut distances: HashMap<NetRef<I>, (usize, usize)> = HashMap::new();
+        distances.insert(src.clone(), (0, 0));
+        for node in self.order.iter().skip_while(|n| *n != src) {
+            let Some((shortest, longest)) = distances.get(node).copied() else {
+                continue;
+            };
+            for user in self.users.get(node).into_iter().flatten() {
+                let entry = distances.entry(user.clone()).or_insert((usize::MAX, 0));
+                entry.0 = entry.0.min(shortest + 1);
+                entry.1 = entry.1.max(longest + 1);
+            }
+        }
+
+        // Nodes on a path are reachable from src and reach dst
+        let mut on_path: HashSet<NetRef<I>> = HashSet::new();
+        if distances.contains_key(dst) {
+            on_path.insert(dst.clone());
+        }
+        for node in self.order.iter().rev() {
+            if distances.contains_key(node)
+                && self
+                    .users
+                    .get(node)
+                    .into_iter()
+                    .flatten()
+                    .any(|u| on_path.contains(u))
+            {
+                on_path.insert(node.clone());
+            }
+        }
+
+        Paths {
+            analysis: self,
+            src: src.clone(),
+            dst: dst.clone(),
+            distances: distances.get(dst).copied(),
+            on_path,
+        }
+    }
+}
+
+impl<'a, I> Analysis<'a, I> for PathAnalysis<'a, I>
+where
+    I: Instantiable,
+{
+    fn build(netlist: &'a Netlist<I>) -> Result<Self, String> {
+        let fan_out = FanOutTable::index(netlist);
+        let mut users: HashMap<NetRef<I>, Vec<NetRef<I>>> = HashMap::new();
+        for node in netlist.objects() {
+            let mut targets: Vec<NetRef<I>> = Vec::new();
+            for user in fan_out.get_node_users(&node) {
+                if !targets.contains(&user) {
+                    targets.push(user);
+                }
+            }
+            if !targets.is_empty() {
+                users.insert(node, targets);
+            }
+        }
+
+        // Order the nodes topologically, so every node comes after its drivers
+        let order = topological_order(netlist.objects(), |n| n.drivers().flatten().collect())
+            .map_err(|_| "Cycle detected in the netlist".to_string())?;
+
+        Ok(PathAnalysis {
+            _netlist: netlist,
+            users,
+            order,
+        })
+    }
+}
+
+/// The paths between two circuit nodes, as found by [PathAnalysis::paths_between]
+pub struct Paths<'a, I: Instantiable> {
+    // The analysis the paths were found with
+    analysis: &'a PathAnalysis<'a, I>,
+    // The start of the paths
+    src: NetRef<I>,
+    // The end of the paths
+    dst: NetRef<I>,
+    // The shortest and longest distance to dst, if it is reachable
+    distances: Option<(usize, usize)>,
+    // The nodes that lie on some path
+    on_path: HashSet<NetRef<I>>,
+}
+
+impl<I> Paths<'_, I>
+where
+    I: Instantiable,
+{
+    /// Returns true if there is a path from the source to the destination
+    pub fn exists(&self) -> bool {
+        self.distances.is_some()
+    }
+
+    /// Returns the number of connections on the shortest path, if there is one
+    pub fn shortest(&self) -> Option<usize> {
+        self.distances.map(|(shortest, _)| shortest)
+    }
+
+    /// Returns the number of connections on the longest path, if there is one
+    pub fn longest(&self) -> Option<usize> {
+        self.distances.map(|(_, longest)| longest)
+    }
+
+    /// Returns the nodes that lie on some path, including the source and destination
+    pub fn nodes(&self) -> impl Iterator<Item = &NetRef<I>> {
+        self.on_path.iter()
+    }
+
+    /// Enumerates up to `bound` distinct paths, as the nodes from the source to the destination
+    pub fn enumerate(&self, bound: usize) -> Vec<Vec<NetRef<I>>> {
+        let mut paths = Vec::new();
+        if !self.exists() || bound == 0 {
+            return paths;
+        }
+
+        // Depth-first, where each stack entry is a node and the index of the next user to try
+        let mut stack: Vec<(NetRef<I>, usize)> = vec![(self.src.clone(), 0)];
+        while let Some((node, next)) = stack.last_mut() {
+            if *node == self.dst {
+                paths.push(stack.iter().map(|(n, _)| n.clone()).collect());
+                if paths.len() == bound {
+                    break;
+                }
+                stack.pop();
+                continue;
+            }
+            let user = self
+                .analysis
+                .users
+                .get(node)
+                .into_iter()
+                .flatten()
+                .skip(*next)
+                .position(|u| self.on_path.contains(u))
+                .map(|i| *next + i);
+            match user {
+                Some(i) => {
+                    *next = i + 1;
+                    let user = self.analysis.users[node][i].clone();
+                    stack.push((user, 0));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        paths
+    }
+}
+
 /// An enum to provide pseudo-nodes for any misc user-programmable behavior.
 #[cfg(feature = "graph")]
 #[derive(Debug, Clone)]
@@ -307,7 +931,7 @@ mod tests {
         let b = netlist.insert_input_escaped_logic_bus("b".to_string(), bitwidth);
         let mut carry: DrivenNet<Gate> = netlist.insert_input("cin".into());
 
-        for (i, (a, b)) in a.into_iter().zip(b.into_iter()).enumerate() {
+        for (i, (a, b)) in a.into_iter().zip(b).enumerate() {
             // Instantiate a full adder for each bit
             let fa = netlist
                 .insert_gate(full_adder(), format_id!("fa_{i}"), &[carry, a, b])
@@ -0,0 +1,796 @@
+/*!
+
+  Hierarchical netlists, where a netlist can be instantiated inside another.
+
+*/
+
+use crate::{
+    attribute::Parameter,
+    circuit::{HierPath, Identifier, Instantiable, Net, PortDirection},
+    netlist::{DrivenNet, NetRef, Netlist, PathTarget},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    rc::Rc,
+};
+
+/// A netlist whose instances are primitives or other netlists
+pub type HierNetlist<I> = Netlist<HierCell<I>>;
+
+/// A netlist instantiated as a cell of another netlist.
+/// The input and output ports of the netlist become the ports of the instance, in the order they are declared.
+/// `inout` ports become bidirectional input ports, see [Instantiable::is_inout_port].
+/// The ports are taken when the submodule is created, so later changes to the ports of the netlist are not reflected.
+#[derive(Debug)]
+pub struct Submodule<I: Instantiable> {
+    /// The name of the module
+    name: Identifier,
+    /// The netlist that is instantiated
+    netlist: Rc<HierNetlist<I>>,
+    /// The input ports of the module, including the `inout` ports
+    inputs: Vec<Net>,
+    /// Whether each input port is an `inout` port
+    inouts: Vec<bool>,
+    /// The output ports of the module
+    outputs: Vec<Net>,
+}
+
+impl<I> Clone for Submodule<I>
+where
+    I: Instantiable,
+{
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            netlist: self.netlist.clone(),
+            inputs: self.inputs.clone(),
+            inouts: self.inouts.clone(),
+            outputs: self.outputs.clone(),
+        }
+    }
+}
+
+impl<I> Submodule<I>
+where
+    I: Instantiable,
+{
+    /// Creates a submodule that instantiates `netlist`
+    pub fn new(netlist: Rc<HierNetlist<I>>) -> Self {
+        let ports = netlist.ports();
+        let (outputs, inputs): (Vec<_>, Vec<_>) = ports
+            .iter()
+            .flat_map(|p| p.get_nets().iter().map(move |n| (p, n.clone())))
+            .partition(|(p, _)| p.is_output());
+        Self {
+            name: netlist.get_name().into(),
+            inouts: inputs.iter().map(|(p, _)| p.is_inout()).collect(),
+            inputs: inputs.into_iter().map(|(_, n)| n).collect(),
+            outputs: outputs.into_iter().map(|(_, n)| n).collect(),
+            netlist,
+        }
+    }
+
+    /// Returns the netlist that is instantiated
+    pub fn get_netlist(&self) -> &Rc<HierNetlist<I>> {
+        &self.netlist
+    }
+}
+
+/// A cell of a [HierNetlist]: either a primitive or a [Submodule]
+#[derive(Debug, Clone)]
+pub enum HierCell<I: Instantiable> {
+    /// A leaf primitive
+    Primitive(I),
+    /// An instance of another netlist
+    Submodule(Submodule<I>),
+}
+
+impl<I> HierCell<I>
+where
+    I: Instantiable,
+{
+    /// Returns the primitive, if this cell is not a submodule
+    pub fn get_primitive(&self) -> Option<&I> {
+        match self {
+            HierCell::Primitive(p) => Some(p),
+            HierCell::Submodule(_) => None,
+        }
+    }
+
+    /// Returns the submodule, if this cell is one
+    pub fn get_submodule(&self) -> Option<&Submodule<I>> {
+        match self {
+            HierCell::Primitive(_) => None,
+            HierCell::Submodule(s) => Some(s),
+        }
+    }
+
+    /// Returns `true` if this cell is an instance of another netlist
+    pub fn is_submodule(&self) -> bool {
+        matches!(self, HierCell::Submodule(_))
+    }
+}
+
+impl<I> From<I> for HierCell<I>
+where
+    I: Instantiable,
+{
+    fn from(primitive: I) -> Self {
+        HierCell::Primitive(primitive)
+    }
+}
+
+impl<I> From<Submodule<I>> for HierCell<I>
+where
+    I: Instantiable,
+{
+    fn from(submodule: Submodule<I>) -> Self {
+        HierCell::Submodule(submodule)
+    }
+}
+
+impl<I> Instantiable for HierCell<I>
+where
+    I: Instantiable,
+{
+    fn get_name(&self) -> &Identifier {
+        match self {
+            HierCell::Primitive(p) => p.get_name(),
+            HierCell::Submodule(s) => &s.name,
+        }
+    }
+
+    fn get_input_ports(&self) -> impl IntoIterator<Item = &Net> {
+        match self {
+            HierCell::Primitive(p) => p.get_input_ports().into_iter().collect::<Vec<_>>(),
+            HierCell::Submodule(s) => s.inputs.iter().collect(),
+        }
+    }
+
+    fn get_output_ports(&self) -> impl IntoIterator<Item = &Net> {
+        match self {
+            HierCell::Primitive(p) => p.get_output_ports().into_iter().collect::<Vec<_>>(),
+            HierCell::Submodule(s) => s.outputs.iter().collect(),
+        }
+    }
+
+    fn has_parameter(&self, id: &Identifier) -> bool {
+        self.get_primitive().is_some_and(|p| p.has_parameter(id))
+    }
+
+    fn get_parameter(&self, id: &Identifier) -> Option<Parameter> {
+        self.get_primitive()?.get_parameter(id)
+    }
+
+    fn parameters(&self) -> impl Iterator<Item = (Identifier, Parameter)> {
+        self.get_primitive()
+            .into_iter()
+            .flat_map(|p| p.parameters().collect::<Vec<_>>())
+    }
+
+    fn set_parameter(&mut self, id: &Identifier, val: Parameter) -> Option<Parameter> {
+        match self {
+            HierCell::Primitive(p) => p.set_parameter(id, val),
+            HierCell::Submodule(_) => None,
+        }
+    }
+
+    fn rename_port(&mut self, old: &Identifier, new: Identifier) -> bool {
+        match self {
+            HierCell::Primitive(p) => p.rename_port(old, new),
+            HierCell::Submodule(_) => false,
+        }
+    }
+
+    fn get_input_default(&self, index: usize) -> Option<bool> {
+        self.get_primitive()?.get_input_default(index)
+    }
+
+    fn get_ports(&self) -> Vec<(&Net, PortDirection)> {
+        match self {
+            HierCell::Primitive(p) => p.get_ports(),
+            HierCell::Submodule(s) => {
+                let inputs = s
+                    .inputs
+                    .iter()
+                    .zip(&s.inouts)
+                    .map(|(n, inout)| match inout {
+                        true => (n, PortDirection::Inout),
+                        false => (n, PortDirection::Input),
+                    });
+                inputs
+                    .chain(s.outputs.iter().map(|n| (n, PortDirection::Output)))
+                    .collect()
+            }
+        }
+    }
+
+    fn is_inout_port(&self, index: usize) -> bool {
+        match self {
+            HierCell::Primitive(p) => p.is_inout_port(index),
+            HierCell::Submodule(s) => s.inouts.get(index).copied().unwrap_or(false),
+        }
+    }
+
+    fn get_initial_value(&self) -> Option<bool> {
+        self.get_primitive()?.get_initial_value()
+    }
+}
+
+/// Returns the netlists instantiated under `top`, at any depth, each once and before any netlist that instantiates it.
+/// Returns an error if two different netlists have the same name or a netlist instantiates itself.
+pub fn submodules<I: Instantiable>(
+    top: &HierNetlist<I>,
+) -> Result<Vec<Rc<HierNetlist<I>>>, String> {
+    /// Visits the submodules of `netlist` depth-first, adding each after its own submodules
+    fn visit<I: Instantiable>(
+        netlist: &HierNetlist<I>,
+        stack: &mut Vec<String>,
+        seen: &mut HashMap<String, Rc<HierNetlist<I>>>,
+        order: &mut Vec<Rc<HierNetlist<I>>>,
+    ) -> Result<(), String> {
+        stack.push(netlist.get_name().to_string());
+        for node in netlist.objects() {
+            let Some(child) = node
+                .get_instance_type()
+                .and_then(|t| t.get_submodule().map(|s| s.get_netlist().clone()))
+            else {
+                continue;
+            };
+            let name = child.get_name().to_string();
+            if stack.contains(&name) {
+                return Err(format!("Module {name} instantiates itself"));
+            }
+            match seen.get(&name) {
+                Some(other) if Rc::ptr_eq(other, &child) => continue,
+                Some(_) => return Err(format!("Two different modules are named {name}")),
+                None => {
+                    seen.insert(name, child.clone());
+                    visit(&child, stack, seen, order)?;
+                    order.push(child);
+                }
+            }
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    visit(top, &mut Vec::new(), &mut HashMap::new(), &mut order)?;
+    Ok(order)
+}
+
+/// Writes the definitions of `top` and every netlist under it as Verilog modules in one stream.
+/// Each module is defined once, after the modules it instantiates.
+pub fn write_verilog<I: Instantiable>(
+    top: &HierNetlist<I>,
+    mut writer: impl std::io::Write,
+) -> Result<(), String> {
+    for module in submodules(top)? {
+        writeln!(writer, "{module}").map_err(|e| e.to_string())?;
+    }
+    write!(writer, "{top}").map_err(|e| e.to_string())
+}
+
+/// Writes the definitions of `top` and every netlist under it to one file per module in `dir`, named after the module.
+/// Returns the paths of the files in dependency order.
+pub fn write_verilog_files<I: Instantiable>(
+    top: &HierNetlist<I>,
+    dir: &Path,
+) -> Result<Vec<std::path::PathBuf>, String> {
+    let mut paths = Vec::new();
+    let modules = submodules(top)?;
+    let top = std::iter::once(top);
+    for module in modules.iter().map(|m| m.as_ref()).chain(top) {
+        let path = dir.join(format!("{}.v", module.get_name()));
+        std::fs::write(&path, module.to_string())
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// The submodule instances a path descends through, the modules they instantiate, and the segments left in the last module
+type Descent<'p, I> = (
+    Vec<NetRef<HierCell<I>>>,
+    Vec<Rc<HierNetlist<I>>>,
+    &'p [Identifier],
+);
+
+/// Follows the segments of a path from `top` down through the submodule instances they name.
+/// A segment followed by more segments descends into the submodule instance it names.
+fn descend<'p, I: Instantiable>(
+    top: &Rc<HierNetlist<I>>,
+    segments: &'p [Identifier],
+) -> Descent<'p, I> {
+    let mut insts = Vec::new();
+    let mut levels: Vec<Rc<HierNetlist<I>>> = Vec::new();
+    let mut rest = segments;
+    while let Some((inst, tail)) = rest.split_first()
+        && !tail.is_empty()
+    {
+        let netlist = levels.last().unwrap_or(top);
+        let Some(inst) = netlist.find_instance(inst) else {
+            break;
+        };
+        let Some(child) = inst
+            .get_instance_type()
+            .and_then(|t| t.get_submodule().map(|s| s.get_netlist().clone()))
+        else {
+            break;
+        };
+        insts.push(inst);
+        levels.push(child);
+        rest = tail;
+    }
+    (insts, levels, rest)
+}
+
+/// Returns the path to the object named by `rest` within `netlist`, as [Netlist::resolve_path] takes it
+fn local_path<I: Instantiable>(netlist: &HierNetlist<I>, rest: &[Identifier]) -> HierPath {
+    HierPath::new(
+        std::iter::once(Identifier::from(netlist.get_name()))
+            .chain(rest.iter().cloned())
+            .collect(),
+    )
+}
+
+/// An object found by [resolve_path], along with the module it is in
+pub type HierTarget<I> = (Rc<HierNetlist<I>>, PathTarget<HierCell<I>>);
+
+/// Resolves a path across module boundaries, like `top/u_core/u_alu/add0/Y`, to an object in the module the path ends in.
+/// The path names `top`, the submodule instances down the hierarchy and then an object of the last module, as found by [Netlist::resolve_path].
+/// A segment after a submodule instance always descends into it, so `top/u_core/u_alu` names the instance `u_alu` in the module of `u_core`.
+/// Returns the module the object is in along with the object, or [None] if the path names nothing.
+pub fn resolve_path<I: Instantiable>(
+    top: &Rc<HierNetlist<I>>,
+    path: &HierPath,
+) -> Option<HierTarget<I>> {
+    let (root, rest) = path.segments().split_first()?;
+    if root.get_name() != top.get_name() {
+        return None;
+    }
+    let (_, levels, rest) = descend(top, rest);
+    let netlist = levels.last().unwrap_or(top).clone();
+    let target = netlist.resolve_path(&local_path(&netlist, rest))?;
+    Some((netlist, target))
+}
+
+/// Exposes the net at `path` as an output port named `name` of every module above it, up to `top`.
+/// The path names `top`, the submodule instances down the hierarchy and then the net, like `top/u_core/u_alu/sum`,
+/// or an instance output, like `top/u_core/u_alu/add0/Y`. A segment after a submodule instance always descends into it.
+/// Every instance of a module that gains the port gets the new output, but only the instances on the path are connected up.
+/// A net that already drives an output is exposed through an alias, so the existing output is kept.
+/// Returns the net driving the new output of `top`. The hierarchy is left unchanged if the port cannot be punched.
+pub fn punch_port<I: Instantiable>(
+    top: &Rc<HierNetlist<I>>,
+    path: &HierPath,
+    name: Identifier,
+) -> Result<DrivenNet<HierCell<I>>, String> {
+    let segments = path.segments();
+    match segments.first() {
+        Some(root) if root.get_name() == top.get_name() => (),
+        _ => return Err(format!("Path {path} does not start at {}", top.get_name())),
+    }
+
+    // Descend through the submodule instances, then find the net in the last module
+    let (insts, levels, rest) = descend(top, &segments[1..]);
+    let netlist = levels.last().unwrap_or(top);
+    let net = match netlist.resolve_path(&local_path(netlist, rest)) {
+        Some(PathTarget::Net(net)) => net,
+        _ => return Err(format!("Path {path} does not name a net")),
+    };
+    let on_path = std::iter::once(top).chain(levels.iter());
+    for netlist in on_path {
+        if netlist.find_port(&name).is_some() {
+            return Err(format!(
+                "Module {} already has a port {name}",
+                netlist.get_name()
+            ));
+        }
+    }
+
+    // Every module that may change is saved first, so a failure part way leaves the hierarchy as it was
+    let mut touched: Vec<Rc<HierNetlist<I>>> = submodules(top)?;
+    touched.push(top.clone());
+    if let Some(locked) = touched.iter().find(|n| n.is_locked()) {
+        return Err(format!(
+            "Netlist {} is locked against edits",
+            locked.get_name()
+        ));
+    }
+    let saved: Vec<_> = touched.iter().map(|n| n.snapshot()).collect();
+    let punched = punch_levels(top, &touched, &levels, &insts, net, &name);
+    if punched.is_err() {
+        for (netlist, snapshot) in touched.iter().zip(&saved) {
+            netlist.restore(snapshot)?;
+        }
+    }
+    punched
+}
+
+/// Exposes `net` as the output `name` of `netlist`, through an alias if the net already drives an output,
+/// so the existing output is kept
+fn expose_new_output<I: Instantiable>(
+    netlist: &Rc<HierNetlist<I>>,
+    net: DrivenNet<HierCell<I>>,
+    name: &Identifier,
+) -> Result<DrivenNet<HierCell<I>>, String> {
+    let net = match net.is_top_level_output() {
+        true => netlist.insert_alias(net, name.clone())?,
+        false => net,
+    };
+    Ok(netlist.expose_net_with_name(net, name.clone()))
+}
+
+/// Exposes `net` of the deepest of `levels` as the output `name`, and connects it up through `insts` to `top`.
+/// `modules` are every module that may instantiate a module on the path.
+fn punch_levels<I: Instantiable>(
+    top: &Rc<HierNetlist<I>>,
+    modules: &[Rc<HierNetlist<I>>],
+    levels: &[Rc<HierNetlist<I>>],
+    insts: &[NetRef<HierCell<I>>],
+    mut net: DrivenNet<HierCell<I>>,
+    name: &Identifier,
+) -> Result<DrivenNet<HierCell<I>>, String> {
+    for (depth, inst) in insts.iter().enumerate().rev() {
+        let child = &levels[depth];
+        let parent = match depth {
+            0 => top,
+            _ => &levels[depth - 1],
+        };
+        expose_new_output(child, net, name)?;
+        let cell = HierCell::Submodule(Submodule::new(child.clone()));
+        let mut punched = None;
+        for netlist in modules {
+            let users: Vec<_> = netlist
+                .objects()
+                .filter(|o| {
+                    o.get_instance_type()
+                        .and_then(|t| {
+                            t.get_submodule()
+                                .map(|s| Rc::ptr_eq(s.get_netlist(), child))
+                        })
+                        .unwrap_or(false)
+                })
+                .collect();
+            for user in users {
+                let new = netlist.extend_instance(&user, cell.clone())?;
+                if user == *inst && Rc::ptr_eq(netlist, parent) {
+                    punched = new.into_iter().last();
+                }
+            }
+        }
+        net = punched.ok_or(format!("Instance {inst} did not gain the port {name}"))?;
+    }
+    expose_new_output(top, net, name)
+}
+
+/// The parameters to set on each instance, keyed by instance name, as [elaborate] takes them
+pub type Overrides = HashMap<Identifier, Vec<(Identifier, Parameter)>>;
+
+/// The specialized copies of modules, keyed by the module and its sorted overrides
+type Specialized<I> = HashMap<(*const HierNetlist<I>, Vec<String>), Rc<HierNetlist<I>>>;
+
+/// Elaborates a hierarchical design by binding per-instance parameter overrides, like generics, so it is ready for flattening or emission.
+/// `overrides` maps the names of instances in `top` to the parameters to set on them.
+/// Parameters of a primitive instance are set on its type, as [Netlist::elaborate] does.
+/// Parameters of a submodule instance name an instance inside its module, like `add0.INIT`, or `u_alu.add0.INIT` for one further down, as a Verilog `defparam` would.
+/// Each distinct set of overrides on a module specializes a copy of it, named after the module with a numbered suffix like `alu_1`,
+/// and the instances are rebound to the copies. Instances with the same overrides share a copy, and the original modules are not changed.
+/// Every override is checked and every copy is made before `top` is changed, so the design is left untouched on error.
+pub fn elaborate<I: Instantiable>(
+    top: &Rc<HierNetlist<I>>,
+    overrides: &Overrides,
+) -> Result<(), String> {
+    let mut names: HashSet<String> = submodules(top)?
+        .iter()
+        .map(|m| m.get_name().to_string())
+        .collect();
+    names.insert(top.get_name().to_string());
+    specialize(top, overrides, &mut names, &mut HashMap::new())
+}
+
+/// Binds `overrides` in `netlist`, specializing a copy of a module for each distinct set of overrides on its instances.
+/// The copies are named to avoid `names`, and reused through `copies`.
+fn specialize<I: Instantiable>(
+    netlist: &Rc<HierNetlist<I>>,
+    overrides: &Overrides,
+    names: &mut HashSet<String>,
+    copies: &mut Specialized<I>,
+) -> Result<(), String> {
+    if netlist.is_locked() {
+        return Err(format!(
+            "Netlist {} is locked against edits",
+            netlist.get_name()
+        ));
+    }
+    let mut primitives = Overrides::new();
+    let mut rebinds = Vec::new();
+    for (inst_name, params) in overrides {
+        let inst = netlist
+            .find_instance(inst_name)
+            .ok_or(format!("Instance {inst_name} not found"))?;
+        let Some(module) = inst
+            .get_instance_type()
+            .and_then(|t| t.get_submodule().map(|s| s.get_netlist().clone()))
+        else {
+            primitives.insert(inst_name.clone(), params.clone());
+            continue;
+        };
+
+        // The overrides inside the module, grouped by the instance they name
+        let mut inner = Overrides::new();
+        for (id, val) in params {
+            let Some((first, rest)) = id.get_name().split_once('.') else {
+                return Err(format!(
+                    "Instance {inst_name} of module {} has no parameter {}",
+                    module.get_name(),
+                    id.get_name()
+                ));
+            };
+            inner
+                .entry(first.into())
+                .or_default()
+                .push((rest.into(), val.clone()));
+        }
+        let mut key: Vec<String> = params
+            .iter()
+            .map(|(id, val)| format!("{}={val}", id.get_name()))
+            .collect();
+        key.sort();
+        let key = (Rc::as_ptr(&module), key);
+        let copy = match copies.get(&key) {
+            Some(copy) => copy.clone(),
+            None => {
+                let name = (1..)
+                    .map(|n| format!("{}_{n}", module.get_name()))
+                    .find(|n| !names.contains(n))
+                    .unwrap();
+                names.insert(name.clone());
+                let copy = module.duplicate(name);
+                specialize(&copy, &inner, names, copies)?;
+                copies.insert(key, copy.clone());
+                copy
+            }
+        };
+        rebinds.push((inst, copy));
+    }
+
+    netlist.elaborate(&primitives)?;
+    for (inst, copy) in rebinds {
+        inst.with_instance_type_mut(|t| *t = HierCell::Submodule(Submodule::new(copy)));
+    }
+    Ok(())
+}
+
+/// The cell counts and area of an instance of a module and everything under it, as found by [hier_stats]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierStats {
+    /// The name of the instance, or of the top module
+    name: String,
+    /// The name of the module instantiated
+    module: String,
+    /// The number of primitives directly in the module
+    cells: usize,
+    /// The area of the primitives directly in the module
+    area: f64,
+    /// The submodule instances of the module, in object order
+    children: Vec<HierStats>,
+}
+
+impl HierStats {
+    /// Returns the name of the instance, or of the top module
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the module instantiated
+    pub fn get_module(&self) -> &str {
+        &self.module
+    }
+
+    /// Returns the number of primitives directly in the module
+    pub fn cells(&self) -> usize {
+        self.cells
+    }
+
+    /// Returns the area of the primitives directly in the module
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Returns the number of primitives in the module and everything under it
+    pub fn total_cells(&self) -> usize {
+        self.cells + self.children.iter().map(|c| c.total_cells()).sum::<usize>()
+    }
+
+    /// Returns the area of the module and everything under it
+    pub fn total_area(&self) -> f64 {
+        self.area + self.children.iter().map(|c| c.total_area()).sum::<f64>()
+    }
+
+    /// Returns the submodule instances of the module, in object order
+    pub fn children(&self) -> &[HierStats] {
+        &self.children
+    }
+
+    /// Returns the statistics of the instance at `path`, relative to this one, like `u_core/u_alu`
+    pub fn find(&self, path: &str) -> Option<&HierStats> {
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .try_fold(self, |stats, name| {
+                stats.children.iter().find(|c| c.name == name)
+            })
+    }
+
+    /// Returns the statistics of every instance with its hierarchical path, starting with this one, depth-first
+    pub fn paths(&self) -> Vec<(String, &HierStats)> {
+        let mut paths = vec![(self.name.clone(), self)];
+        for child in &self.children {
+            paths.extend(
+                child
+                    .paths()
+                    .into_iter()
+                    .map(|(p, s)| (format!("{}/{p}", self.name), s)),
+            );
+        }
+        paths
+    }
+
+    /// Rolls the statistics up per module: the number of instances of each module,
+    /// and the primitives and area of one instance including everything under it, sorted by total area, highest first
+    pub fn by_module(&self) -> Vec<ModuleStats> {
+        let mut modules: Vec<ModuleStats> = Vec::new();
+        for (_, stats) in self.paths() {
+            match modules.iter_mut().find(|m| m.module == stats.module) {
+                Some(m) => m.instances += 1,
+                None => modules.push(ModuleStats {
+                    module: stats.module.clone(),
+                    instances: 1,
+                    cells: stats.total_cells(),
+                    area: stats.total_area(),
+                }),
+            }
+        }
+        modules.sort_by(|a, b| b.total_area().total_cmp(&a.total_area()));
+        modules
+    }
+
+    /// Writes this node of the tree and its children, indented below `prefix`
+    fn fmt_tree(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        prefix: &str,
+        branch: &str,
+    ) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{branch}{} ({}): {} cells, area {:.3} (own {} cells, area {:.3})",
+            self.name,
+            self.module,
+            self.total_cells(),
+            self.total_area(),
+            self.cells,
+            self.area
+        )?;
+        for (i, child) in self.children.iter().enumerate() {
+            let last = i == self.children.len() - 1;
+            let (branch, indent) = match last {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+            child.fmt_tree(
+                f,
+                &format!("{prefix}{indent}"),
+                &format!("{prefix}{branch}"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for HierStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_tree(f, "", "")
+    }
+}
+
+/// The statistics of a module summed over its instances, as found by [HierStats::by_module]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleStats {
+    /// The name of the module
+    module: String,
+    /// The number of instances of the module, counting the top module once
+    instances: usize,
+    /// The number of primitives in one instance, including everything under it
+    cells: usize,
+    /// The area of one instance, including everything under it
+    area: f64,
+}
+
+impl ModuleStats {
+    /// Returns the name of the module
+    pub fn get_module(&self) -> &str {
+        &self.module
+    }
+
+    /// Returns the number of instances of the module, counting the top module once
+    pub fn instances(&self) -> usize {
+        self.instances
+    }
+
+    /// Returns the number of primitives in one instance, including everything under it
+    pub fn cells(&self) -> usize {
+        self.cells
+    }
+
+    /// Returns the area of one instance, including everything under it
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Returns the area of all the instances of the module
+    pub fn total_area(&self) -> f64 {
+        self.area * self.instances as f64
+    }
+}
+
+impl std::fmt::Display for ModuleStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} instances of {} cells, area {:.3} each, {:.3} total",
+            self.module,
+            self.instances,
+            self.cells,
+            self.area,
+            self.total_area()
+        )
+    }
+}
+
+/// Counts the primitives and sums their area, given by `area_of`, for `top` and every submodule instance under it.
+/// Returns an error if the hierarchy is not well-formed, as checked by [submodules].
+pub fn hier_stats<I, A>(top: &HierNetlist<I>, area_of: A) -> Result<HierStats, String>
+where
+    I: Instantiable,
+    A: Fn(&I) -> f64,
+{
+    /// Gathers the statistics of `netlist`, instantiated as `name`
+    fn visit<I: Instantiable>(
+        netlist: &HierNetlist<I>,
+        name: String,
+        area_of: &dyn Fn(&I) -> f64,
+    ) -> HierStats {
+        let mut stats = HierStats {
+            name,
+            module: netlist.get_name().to_string(),
+            cells: 0,
+            area: 0.0,
+            children: Vec::new(),
+        };
+        for node in netlist.objects() {
+            let (Some(inst_type), Some(inst)) =
+                (node.get_instance_type(), node.get_instance_name())
+            else {
+                continue;
+            };
+            match &*inst_type {
+                HierCell::Primitive(p) => {
+                    stats.cells += 1;
+                    stats.area += area_of(p);
+                }
+                HierCell::Submodule(s) => {
+                    let child = visit(s.get_netlist(), inst.to_string(), area_of);
+                    stats.children.push(child);
+                }
+            }
+        }
+        stats
+    }
+
+    submodules(top)?;
+    Ok(visit(top, top.get_name().to_string(), &area_of))
+}
@@ -0,0 +1,668 @@
+/*!
+
+  A time-stamped journal of the edits made to a netlist, for audit trails and replaying the edits on another copy.
+
+*/
+
+use crate::{
+    attribute::{AttributeKey, Parameter},
+    circuit::{DataType, Identifier, Instantiable, Net, Object, PortDirection},
+    netlist::Netlist,
+    verilog::InstanceSpec,
+};
+use std::{
+    io::Read,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A structural edit to a netlist, as recorded in a [Journal].
+/// Circuit nodes are named by their instance name, or by their net if they are inputs or aliases.
+/// Nets are named by their identifier.
+#[derive(Debug, Clone)]
+pub enum Edit<I: Instantiable> {
+    /// An input or inout net was inserted as a bit of the port `port`
+    InsertInput {
+        /// The net
+        net: Net,
+        /// The port the net is a bit of
+        port: Identifier,
+        /// The direction of the port
+        direction: PortDirection,
+    },
+    /// An instance was inserted, with the nets connected to its inputs
+    InsertInstance {
+        /// The name of the instance
+        name: Identifier,
+        /// The type of the instance
+        inst_type: I,
+        /// The nets driving the inputs, or [None] for unconnected ones
+        operands: Vec<Option<Identifier>>,
+    },
+    /// An alias was inserted
+    InsertAlias {
+        /// The net driven by the alias
+        net: Net,
+        /// The net the alias reads, if it is connected
+        driver: Option<Identifier>,
+    },
+    /// The net `driver` was connected to input `port` of `node`
+    Connect {
+        /// The driving net
+        driver: Identifier,
+        /// The load
+        node: Identifier,
+        /// The position of the input on the load
+        port: usize,
+    },
+    /// Input `port` of `node` was disconnected
+    Disconnect {
+        /// The load
+        node: Identifier,
+        /// The position of the input on the load
+        port: usize,
+    },
+    /// The net `driver` was exposed as the top-level output `name`
+    Expose {
+        /// The driving net
+        driver: Identifier,
+        /// The name of the output
+        name: Identifier,
+    },
+    /// The uses of `node` were deleted, with its top-level outputs moved to `rebind` if given
+    DeleteUses {
+        /// The node
+        node: Identifier,
+        /// The net its top-level outputs were moved to
+        rebind: Option<Identifier>,
+    },
+    /// The uses of `of` were replaced with `with`
+    ReplaceUses {
+        /// The node replaced
+        of: Identifier,
+        /// The node replacing it
+        with: Identifier,
+    },
+    /// The loads and top-level output of an alias were moved to the net it aliases
+    BypassAlias(Identifier),
+    /// The type of an instance was replaced with one with more outputs
+    ExtendInstance {
+        /// The name of the instance
+        name: Identifier,
+        /// The new type of the instance
+        inst_type: I,
+    },
+    /// A node was removed
+    Remove(Identifier),
+    /// An attribute was set on `node`, with `value` if it has one
+    SetAttribute {
+        /// The node
+        node: Identifier,
+        /// The key of the attribute
+        key: AttributeKey,
+        /// The value of the attribute
+        value: Option<String>,
+    },
+    /// An attribute was cleared from `node`
+    ClearAttribute {
+        /// The node
+        node: Identifier,
+        /// The key of the attribute
+        key: AttributeKey,
+    },
+    /// The instance `from` was renamed to `to`
+    RenameInstance {
+        /// The old name of the instance
+        from: Identifier,
+        /// The new name of the instance
+        to: Identifier,
+    },
+    /// The net `from` was renamed to `to`
+    RenameNet {
+        /// The old name of the net
+        from: Identifier,
+        /// The new name of the net
+        to: Identifier,
+    },
+}
+
+/// Returns the name of `object` in the journal: its instance name, or the name of its net if it is an input or alias
+pub(crate) fn object_name<I: Instantiable>(object: &Object<I>) -> Identifier {
+    match object {
+        Object::Instance(_, name, _) => name.clone(),
+        Object::Input(net) | Object::Alias(net) => net.get_identifier().clone(),
+    }
+}
+
+/// Quotes `field` if it is empty or has whitespace or quotes, so that it reads back as one field
+fn quote(field: &str) -> String {
+    if !field.is_empty() && !field.chars().any(|c| c.is_whitespace() || c == '"') {
+        return field.to_string();
+    }
+    let mut quoted = String::from('"');
+    for c in field.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Formats `id` as one field, without the space that ends an escaped identifier
+fn id_field(id: &Identifier) -> String {
+    quote(id.to_string().trim_end())
+}
+
+/// Formats an instance type with its parameters as one field, like `LUT2#(INIT=4'b1000)`
+fn type_field<I: Instantiable>(inst_type: &I) -> String {
+    let name = inst_type.get_name().to_string();
+    let params: Vec<String> = inst_type
+        .parameters()
+        .map(|(k, v)| format!("{}={v}", k.to_string().trim_end()))
+        .collect();
+    match params.is_empty() {
+        true => quote(name.trim_end()),
+        false => quote(&format!("{}#({})", name.trim_end(), params.join(","))),
+    }
+}
+
+/// Splits a line of an exported journal into its fields, unquoting the quoted ones
+fn split_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(fields);
+        }
+        let mut field = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            if c != '"' {
+                field.push(c);
+                continue;
+            }
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => field.push('\n'),
+                        Some(c) => field.push(c),
+                        None => return Err(format!("Unterminated quote in {line}")),
+                    },
+                    Some(c) => field.push(c),
+                    None => return Err(format!("Unterminated quote in {line}")),
+                }
+            }
+        }
+        fields.push(field);
+    }
+}
+
+/// Reads an instance type field as the name of the type and its parameters
+fn parse_type(field: &str) -> Result<(Identifier, Vec<(Identifier, Parameter)>), String> {
+    let Some((name, params)) = field.split_once("#(") else {
+        return Ok((field.into(), Vec::new()));
+    };
+    let params = params
+        .strip_suffix(')')
+        .ok_or(format!("Unterminated parameters in {field}"))?;
+    let params = params
+        .split(',')
+        .map(|p| match p.split_once('=') {
+            Some((k, v)) => Ok((k.into(), v.parse()?)),
+            None => Err(format!("Parameter {p} has no value")),
+        })
+        .collect::<Result<_, String>>()?;
+    Ok((name.into(), params))
+}
+
+/// Reads a field naming an input of a node, like `inst_0.1`
+fn parse_input(field: &str) -> Result<(Identifier, usize), String> {
+    field
+        .rsplit_once('.')
+        .and_then(|(node, port)| Some((node.into(), port.parse().ok()?)))
+        .ok_or(format!("{field} does not name an input"))
+}
+
+impl<I> std::fmt::Display for Edit<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // An escaped identifier is never `-`, so the dash cannot be read back as a net
+        let or_dash = |id: &Option<Identifier>| match id {
+            Some(id) => id_field(id),
+            None => "-".to_string(),
+        };
+        let net_fields = |net: &Net| {
+            let data_type = quote(&net.get_type().to_string());
+            format!("{} {data_type}", id_field(net.get_identifier()))
+        };
+        match self {
+            Edit::InsertInput {
+                net,
+                port,
+                direction,
+            } => write!(
+                f,
+                "insert_{direction} {} {}",
+                net_fields(net),
+                id_field(port)
+            ),
+            Edit::InsertInstance {
+                name,
+                inst_type,
+                operands,
+            } => {
+                write!(
+                    f,
+                    "insert_instance {} {}",
+                    id_field(name),
+                    type_field(inst_type)
+                )?;
+                for operand in operands {
+                    write!(f, " {}", or_dash(operand))?;
+                }
+                Ok(())
+            }
+            Edit::InsertAlias { net, driver } => {
+                write!(f, "insert_alias {} {}", net_fields(net), or_dash(driver))
+            }
+            Edit::Connect { driver, node, port } => {
+                let input = quote(&format!("{}.{port}", node.to_string().trim_end()));
+                write!(f, "connect {} {input}", id_field(driver))
+            }
+            Edit::Disconnect { node, port } => {
+                let input = quote(&format!("{}.{port}", node.to_string().trim_end()));
+                write!(f, "disconnect {input}")
+            }
+            Edit::Expose { driver, name } => {
+                write!(f, "expose {} {}", id_field(driver), id_field(name))
+            }
+            Edit::DeleteUses { node, rebind } => {
+                write!(f, "delete_uses {} {}", id_field(node), or_dash(rebind))
+            }
+            Edit::ReplaceUses { of, with } => {
+                write!(f, "replace_uses {} {}", id_field(of), id_field(with))
+            }
+            Edit::BypassAlias(alias) => write!(f, "bypass_alias {}", id_field(alias)),
+            Edit::ExtendInstance { name, inst_type } => write!(
+                f,
+                "extend_instance {} {}",
+                id_field(name),
+                type_field(inst_type)
+            ),
+            Edit::Remove(node) => write!(f, "remove {}", id_field(node)),
+            Edit::SetAttribute { node, key, value } => {
+                write!(f, "set_attribute {} {}", id_field(node), quote(key))?;
+                match value {
+                    Some(value) => write!(f, " {}", quote(value)),
+                    None => Ok(()),
+                }
+            }
+            Edit::ClearAttribute { node, key } => {
+                write!(f, "clear_attribute {} {}", id_field(node), quote(key))
+            }
+            Edit::RenameInstance { from, to } => {
+                write!(f, "rename_instance {} {}", id_field(from), id_field(to))
+            }
+            Edit::RenameNet { from, to } => {
+                write!(f, "rename_net {} {}", id_field(from), id_field(to))
+            }
+        }
+    }
+}
+
+impl<I> Edit<I>
+where
+    I: Instantiable,
+{
+    /// Reads an edit from the fields of an exported journal, mapping instance types with `cells`
+    fn parse<F>(fields: &[String], cells: &F) -> Result<Self, String>
+    where
+        F: Fn(&InstanceSpec) -> Result<I, String>,
+    {
+        let Some((kind, args)) = fields.split_first() else {
+            return Err("Missing edit".to_string());
+        };
+        let arity = |n: usize| match args.len() == n {
+            true => Ok(()),
+            false => Err(format!("{kind} takes {n} fields, not {}", args.len())),
+        };
+        let id = |i: usize| Identifier::from(args[i].as_str());
+        let or_dash = |i: usize| match args[i].as_str() {
+            "-" => None,
+            name => Some(Identifier::from(name)),
+        };
+        let net = || -> Result<Net, String> { Ok(Net::new(id(0), args[1].parse::<DataType>()?)) };
+        let inst_type = |name: Identifier, field: &str| {
+            let (cell_type, parameters) = parse_type(field)?;
+            let spec = InstanceSpec {
+                name: name.clone(),
+                cell_type,
+                parameters,
+                ports: Vec::new(),
+            };
+            cells(&spec).map_err(|e| format!("Instance {name}: {e}"))
+        };
+        match kind.as_str() {
+            "insert_input" | "insert_inout" => {
+                arity(3)?;
+                Ok(Edit::InsertInput {
+                    net: net()?,
+                    port: id(2),
+                    direction: match kind.as_str() {
+                        "insert_input" => PortDirection::Input,
+                        _ => PortDirection::Inout,
+                    },
+                })
+            }
+            "insert_instance" => {
+                if args.len() < 2 {
+                    return Err(format!("{kind} takes at least 2 fields"));
+                }
+                Ok(Edit::InsertInstance {
+                    name: id(0),
+                    inst_type: inst_type(id(0), &args[1])?,
+                    operands: (2..args.len()).map(or_dash).collect(),
+                })
+            }
+            "insert_alias" => {
+                arity(3)?;
+                Ok(Edit::InsertAlias {
+                    net: net()?,
+                    driver: or_dash(2),
+                })
+            }
+            "connect" => {
+                arity(2)?;
+                let (node, port) = parse_input(&args[1])?;
+                Ok(Edit::Connect {
+                    driver: id(0),
+                    node,
+                    port,
+                })
+            }
+            "disconnect" => {
+                arity(1)?;
+                let (node, port) = parse_input(&args[0])?;
+                Ok(Edit::Disconnect { node, port })
+            }
+            "expose" => {
+                arity(2)?;
+                Ok(Edit::Expose {
+                    driver: id(0),
+                    name: id(1),
+                })
+            }
+            "delete_uses" => {
+                arity(2)?;
+                Ok(Edit::DeleteUses {
+                    node: id(0),
+                    rebind: or_dash(1),
+                })
+            }
+            "replace_uses" => {
+                arity(2)?;
+                Ok(Edit::ReplaceUses {
+                    of: id(0),
+                    with: id(1),
+                })
+            }
+            "bypass_alias" => {
+                arity(1)?;
+                Ok(Edit::BypassAlias(id(0)))
+            }
+            "extend_instance" => {
+                arity(2)?;
+                Ok(Edit::ExtendInstance {
+                    name: id(0),
+                    inst_type: inst_type(id(0), &args[1])?,
+                })
+            }
+            "remove" => {
+                arity(1)?;
+                Ok(Edit::Remove(id(0)))
+            }
+            "set_attribute" => {
+                if args.len() != 2 {
+                    arity(3)?;
+                }
+                Ok(Edit::SetAttribute {
+                    node: id(0),
+                    key: args[1].clone(),
+                    value: args.get(2).cloned(),
+                })
+            }
+            "clear_attribute" => {
+                arity(2)?;
+                Ok(Edit::ClearAttribute {
+                    node: id(0),
+                    key: args[1].clone(),
+                })
+            }
+            "rename_instance" => {
+                arity(2)?;
+                Ok(Edit::RenameInstance {
+                    from: id(0),
+                    to: id(1),
+                })
+            }
+            "rename_net" => {
+                arity(2)?;
+                Ok(Edit::RenameNet {
+                    from: id(0),
+                    to: id(1),
+                })
+            }
+            _ => Err(format!("Unknown edit {kind}")),
+        }
+    }
+}
+
+/// An [Edit] with when and by whom it was made
+#[derive(Debug, Clone)]
+pub struct JournalEntry<I: Instantiable> {
+    /// The position of the entry in the journal
+    seq: usize,
+    /// When the edit was made
+    time: SystemTime,
+    /// Who made the edit
+    author: String,
+    /// The edit
+    edit: Edit<I>,
+}
+
+impl<I> JournalEntry<I>
+where
+    I: Instantiable,
+{
+    /// Returns the position of the entry in the journal
+    pub fn seq(&self) -> usize {
+        self.seq
+    }
+
+    /// Returns when the edit was made
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Returns who made the edit
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
+    /// Returns the edit
+    pub fn edit(&self) -> &Edit<I> {
+        &self.edit
+    }
+}
+
+impl<I> JournalEntry<I>
+where
+    I: Instantiable,
+{
+    /// Reads an entry from a line of an exported journal, mapping instance types with `cells`
+    fn parse<F>(line: &str, cells: &F) -> Result<Self, String>
+    where
+        F: Fn(&InstanceSpec) -> Result<I, String>,
+    {
+        let fields = split_fields(line)?;
+        if fields.len() < 4 {
+            return Err(format!("Missing fields in {line}"));
+        }
+        let seq = fields[0]
+            .parse()
+            .map_err(|_| format!("Bad sequence number {}", fields[0]))?;
+        let bad_time = || format!("Bad time {}", fields[1]);
+        let (secs, millis) = fields[1].split_once('.').ok_or_else(bad_time)?;
+        let secs: u64 = secs.parse().map_err(|_| bad_time())?;
+        let millis: u64 = millis.parse().map_err(|_| bad_time())?;
+        Ok(Self {
+            seq,
+            time: UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_millis(millis),
+            author: fields[2].clone(),
+            edit: Edit::parse(&fields[3..], cells)?,
+        })
+    }
+}
+
+impl<I> std::fmt::Display for JournalEntry<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let since = self.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        write!(
+            f,
+            "{} {}.{:03} {} {}",
+            self.seq,
+            since.as_secs(),
+            since.subsec_millis(),
+            quote(&self.author),
+            self.edit
+        )
+    }
+}
+
+/// The edits made to a netlist while it is journaled, as started by [Netlist::start_journal].
+/// Structural edits are recorded: insertions, connections, exposed outputs, deleted or replaced uses and removals.
+/// So are attributes set on circuit nodes and renames made with [crate::netlist::NetRef::set_identifier] and [crate::netlist::NetRef::set_instance_name].
+/// Renames through a borrowed [Net], cell substitutions, reordering and restoring a [crate::netlist::Snapshot] are not, so a journal spanning them does not replay exactly.
+#[derive(Debug, Clone)]
+pub struct Journal<I: Instantiable> {
+    /// Who the next edits are made by
+    author: String,
+    /// The edits in the order they were made
+    entries: Vec<JournalEntry<I>>,
+}
+
+impl<I> Journal<I>
+where
+    I: Instantiable,
+{
+    /// Creates an empty journal with edits made by `author`
+    pub fn new(author: String) -> Self {
+        Self {
+            author,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Sets who the next edits are made by
+    pub fn set_author(&mut self, author: String) {
+        self.author = author;
+    }
+
+    /// Returns who the next edits are made by
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+    /// Records `edit` as made now
+    pub(crate) fn push(&mut self, edit: Edit<I>) {
+        self.entries.push(JournalEntry {
+            seq: self.entries.len(),
+            time: SystemTime::now(),
+            author: self.author.clone(),
+            edit,
+        });
+    }
+
+    /// Returns the entries in the order the edits were made
+    pub fn entries(&self) -> &[JournalEntry<I>] {
+        &self.entries
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no edits were recorded
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the journal with one entry per line: the sequence number, the Unix time in seconds with milliseconds, the author and the edit.
+    /// Fields that are empty or have whitespace or quotes are quoted, so [Journal::import] reads the text back.
+    pub fn export(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+        for entry in &self.entries {
+            writeln!(w, "{entry}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a journal written by [Journal::export], with `cells` mapping each inserted or extended instance to its type,
+    /// as in [crate::verilog::read_verilog]. The [InstanceSpec] has no ports, as the journal connects instances by position.
+    /// The next edits are made by the author of the last entry.
+    pub fn import<F>(mut reader: impl Read, cells: F) -> Result<Self, String>
+    where
+        F: Fn(&InstanceSpec) -> Result<I, String>,
+    {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .map_err(|e| e.to_string())?;
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(n, line)| {
+                JournalEntry::parse(line, &cells).map_err(|e| format!("Line {}: {e}", n + 1))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            author: entries.last().map(|e| e.author.clone()).unwrap_or_default(),
+            entries,
+        })
+    }
+
+    /// Makes the recorded edits again on `netlist`, which should start as the journaled netlist did.
+    /// Nodes and nets are found by name, so this is O(n) per edit.
+    /// Returns an error naming the first edit that cannot be made.
+    pub fn replay(&self, netlist: &Rc<Netlist<I>>) -> Result<(), String> {
+        for entry in &self.entries {
+            netlist
+                .replay_edit(&entry.edit)
+                .map_err(|e| format!("Cannot replay edit {}: {e}", entry.seq))?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> std::fmt::Display for Journal<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
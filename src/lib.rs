@@ -7,13 +7,48 @@
 An experimental library for representing circuit netlists for EDA tool development.
 Take a look at some [examples](https://github.com/matth2k/safety-net/tree/main/examples) and the [documentation](https://matth2k.github.io/safety-net/).
 
+## Panic-free use
+
+The convenience methods for editing a netlist panic on misuse, like an out-of-bounds port, a multi-output node used as a single net, a dropped netlist or an edit while the netlist is locked.
+Each has a `try_` version that returns a [netlist::NetlistError] instead, like [netlist::NetRef::try_get_input], [netlist::DrivenNet::try_connect] and [netlist::Netlist::try_insert_input],
+so a long-running process can reject a malformed request without crashing.
+Methods that already return a [Result] report a dropped netlist as [netlist::NetlistError::Unlinked].
+
 */
 #![doc = "## Simple Example\n```"]
 #![doc = include_str!("../examples/simple.rs")]
 #![doc = "\n```"]
 
+pub mod aig;
 pub mod attribute;
+pub mod bus;
 pub mod circuit;
+pub mod constraint;
+pub mod cosim;
+pub mod cost;
+pub mod dft;
+pub mod diff;
+pub mod emit;
+pub mod fault;
+pub mod frozen;
 pub mod graph;
+pub mod hier;
+#[cfg(feature = "journal")]
+pub mod journal;
+pub mod library;
 pub mod netlist;
+pub mod persist;
+pub mod recognize;
+pub mod reduce;
+pub mod report;
+pub mod script;
+pub mod sdc;
+pub mod sim;
+pub mod spice;
+pub mod supply;
+pub mod timing;
 mod util;
+pub mod verilog;
+pub mod xref;
+#[cfg(feature = "yosys")]
+pub mod yosys;
@@ -0,0 +1,291 @@
+/*!
+
+  Libraries of standard cells, so netlists are built from one set of cell definitions and their characterization data.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable},
+    cost::CostModel,
+    netlist::{DrivenNet, NetRef, Netlist},
+    timing::WireLoad,
+};
+use std::{collections::HashMap, rc::Rc};
+
+/// A cell of a [CellLibrary], with its characterization data
+#[derive(Debug, Clone)]
+pub struct LibraryCell<I: Instantiable> {
+    /// The definition of the cell
+    cell: I,
+    /// The area of the cell
+    area: f64,
+    /// The delay through the cell
+    delay: f64,
+    /// The drive strength of the outputs, like 1 for an `X1` cell and 4 for an `X4` cell
+    drive: f64,
+}
+
+impl<I> LibraryCell<I>
+where
+    I: Instantiable,
+{
+    /// Creates a library cell for `cell`, with no area or delay and a drive strength of 1
+    pub fn new(cell: I) -> Self {
+        Self {
+            cell,
+            area: 0.0,
+            delay: 0.0,
+            drive: 1.0,
+        }
+    }
+
+    /// Sets the area of the cell
+    pub fn with_area(mut self, area: f64) -> Self {
+        self.area = area;
+        self
+    }
+
+    /// Sets the delay through the cell
+    pub fn with_delay(mut self, delay: f64) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the drive strength of the outputs of the cell
+    pub fn with_drive(mut self, drive: f64) -> Self {
+        self.drive = drive;
+        self
+    }
+
+    /// Returns the name of the cell
+    pub fn get_name(&self) -> &Identifier {
+        self.cell.get_name()
+    }
+
+    /// Returns the definition of the cell
+    pub fn get_cell(&self) -> &I {
+        &self.cell
+    }
+
+    /// Returns the area of the cell
+    pub fn area(&self) -> f64 {
+        self.area
+    }
+
+    /// Returns the delay through the cell
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+
+    /// Returns the drive strength of the outputs of the cell
+    pub fn drive(&self) -> f64 {
+        self.drive
+    }
+}
+
+/// A library of cells looked up by name, like the cells of a standard cell library or an FPGA family.
+/// The library is a [CostModel] that gives the area and delay of its cells, and nothing for other instance types.
+#[derive(Debug, Clone)]
+pub struct CellLibrary<I: Instantiable> {
+    /// The name of the library
+    name: String,
+    /// The cells in the order they were added
+    cells: Vec<LibraryCell<I>>,
+    /// The position of every cell by name
+    index: HashMap<Identifier, usize>,
+    /// The model of wire delays, if the library has one
+    wire_load: Option<WireLoad>,
+}
+
+impl<I> CellLibrary<I>
+where
+    I: Instantiable,
+{
+    /// Creates an empty library called `name`
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            cells: Vec::new(),
+            index: HashMap::new(),
+            wire_load: None,
+        }
+    }
+
+    /// Adds `cell` to the library. Returns an error if the library already has a cell with its name.
+    pub fn add(&mut self, cell: LibraryCell<I>) -> Result<(), String> {
+        let name = cell.get_name().clone();
+        if self.index.contains_key(&name) {
+            return Err(format!(
+                "Library {} already has a cell named {name}",
+                self.name
+            ));
+        }
+        self.index.insert(name, self.cells.len());
+        self.cells.push(cell);
+        Ok(())
+    }
+
+    /// Adds `cell` to the library, replacing any cell with its name
+    pub fn with_cell(mut self, cell: LibraryCell<I>) -> Self {
+        match self.index.get(cell.get_name()) {
+            Some(&i) => self.cells[i] = cell,
+            None => {
+                self.index.insert(cell.get_name().clone(), self.cells.len());
+                self.cells.push(cell);
+            }
+        }
+        self
+    }
+
+    /// Sets the model of wire delays, which timing analyses with the library use for interconnect
+    pub fn with_wire_load(mut self, wire_load: WireLoad) -> Self {
+        self.wire_load = Some(wire_load);
+        self
+    }
+
+    /// Returns the model of wire delays, if the library has one
+    pub fn get_wire_load(&self) -> Option<&WireLoad> {
+        self.wire_load.as_ref()
+    }
+
+    /// Returns the name of the library
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the cell named `name`
+    pub fn get(&self, name: &Identifier) -> Option<&LibraryCell<I>> {
+        self.index.get(name).map(|&i| &self.cells[i])
+    }
+
+    /// Returns a copy of the definition of the cell named `name`, to instantiate it
+    pub fn cell(&self, name: &Identifier) -> Option<I> {
+        self.get(name).map(|c| c.cell.clone())
+    }
+
+    /// Returns the cells in the order they were added
+    pub fn cells(&self) -> &[LibraryCell<I>] {
+        &self.cells
+    }
+
+    /// Returns the cells that `matches` accepts, ordered from the weakest to the strongest drive.
+    /// This finds the drive strengths of a function, like every cell named `BUF_X*`.
+    pub fn drives_of(&self, matches: impl Fn(&LibraryCell<I>) -> bool) -> Vec<&LibraryCell<I>> {
+        let mut cells: Vec<&LibraryCell<I>> = self.cells.iter().filter(|c| matches(c)).collect();
+        cells.sort_by(|a, b| a.drive.total_cmp(&b.drive));
+        cells
+    }
+
+    /// Returns the number of cells in the library
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns `true` if the library has no cells
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Inserts an instance of the cell named `cell` into `netlist`, like [Netlist::insert_gate].
+    /// Returns an error if the library has no such cell or the instance cannot be inserted.
+    pub fn insert(
+        &self,
+        netlist: &Rc<Netlist<I>>,
+        cell: &Identifier,
+        inst_name: Identifier,
+        operands: &[DrivenNet<I>],
+    ) -> Result<NetRef<I>, String> {
+        let inst_type = self
+            .cell(cell)
+            .ok_or_else(|| format!("Library {} has no cell named {cell}", self.name))?;
+        Ok(netlist.insert_gate(inst_type, inst_name, operands)?)
+    }
+
+    /// Returns the instance types of `netlist` that are not cells of the library, in object order without repeats
+    pub fn missing_cells(&self, netlist: &Netlist<I>) -> Vec<Identifier> {
+        let mut missing = Vec::new();
+        for node in netlist.objects() {
+            if let Some(inst_type) = node.get_instance_type()
+                && !self.index.contains_key(inst_type.get_name())
+                && !missing.contains(inst_type.get_name())
+            {
+                missing.push(inst_type.get_name().clone());
+            }
+        }
+        missing
+    }
+}
+
+impl<I> CostModel<I> for CellLibrary<I>
+where
+    I: Instantiable,
+{
+    fn area(&self, inst_type: &I) -> f64 {
+        self.get(inst_type.get_name()).map_or(0.0, |c| c.area)
+    }
+
+    fn delay(&self, inst_type: &I) -> f64 {
+        self.get(inst_type.get_name()).map_or(0.0, |c| c.delay)
+    }
+}
+
+/// A table of library cells to substitute, as applied by [Netlist::substitute_cells].
+/// Each rule maps the name of an old cell to the new cell that replaces it and the new names of its ports.
+/// Ports that are not renamed keep their name.
+#[derive(Debug, Clone)]
+pub struct CellMap<I: Instantiable> {
+    /// The new cell and the renamed ports, by the name of the old cell
+    rules: HashMap<Identifier, (I, HashMap<Identifier, Identifier>)>,
+}
+
+impl<I> Default for CellMap<I>
+where
+    I: Instantiable,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> CellMap<I>
+where
+    I: Instantiable,
+{
+    /// Creates an empty table
+    pub fn new() -> Self {
+        Self {
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Replaces the cells named `old` with `cell`, where `pins` maps old port names to new ones
+    pub fn with_cell(mut self, old: Identifier, cell: I, pins: &[(&str, &str)]) -> Self {
+        let pins = pins
+            .iter()
+            .map(|(from, to)| ((*from).into(), (*to).into()))
+            .collect();
+        self.rules.insert(old, (cell, pins));
+        self
+    }
+
+    /// Returns the cell that replaces the cells named `old`
+    pub fn get(&self, old: &Identifier) -> Option<&I> {
+        self.rules.get(old).map(|(cell, _)| cell)
+    }
+
+    /// Returns the new name of the port `pin` of the cells named `old`
+    pub fn get_pin(&self, old: &Identifier, pin: &Identifier) -> Option<Identifier> {
+        let (_, pins) = self.rules.get(old)?;
+        Some(pins.get(pin).unwrap_or(pin).clone())
+    }
+
+    /// Returns the number of cells in the table
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns `true` if the table has no cells
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
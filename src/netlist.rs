@@ -4,14 +4,27 @@
 
 */
 
+#[cfg(feature = "journal")]
+use crate::journal::{self, Edit, Journal};
 use crate::{
-    attribute::{Attribute, AttributeKey, AttributeValue, Parameter},
-    circuit::{Identifier, Instantiable, Net, Object},
+    attribute::{self, Attribute, AttributeKey, AttributeValue, Parameter},
+    circuit::{
+        DataType, HierPath, Identifier, Instantiable, Net, Object, PortDirection, SequentialElement,
+    },
+    constraint::InputConstraint,
+    cost::{Budget, Cost, CostModel, cost_of, netlist_cost},
+    emit::{EmitError, EmitResult, InstanceView, NetlistEmitter, VerilogEmitter},
+    frozen::{FrozenNet, FrozenNetlist, FrozenObject},
     graph::{Analysis, FanOutTable},
+    library::CellMap,
+    sim::Strength,
+    supply::Supply,
+    timing::Parasitics,
 };
 use std::{
-    cell::{Ref, RefCell, RefMut},
-    collections::{HashMap, HashSet},
+    any::{Any, TypeId},
+    cell::{Cell, Ref, RefCell, RefMut},
+    collections::{BTreeMap, HashMap, HashSet},
     num::ParseIntError,
     rc::{Rc, Weak},
 };
@@ -60,9 +73,75 @@ impl Instantiable for Gate {
     fn parameters(&self) -> impl Iterator<Item = (Identifier, Parameter)> {
         std::iter::empty()
     }
+
+    fn rename_port(&mut self, old: &Identifier, new: Identifier) -> bool {
+        let mut ports = self.inputs.iter_mut().chain(self.outputs.iter_mut());
+        if ports.any(|p| *p.get_identifier() == new) {
+            return false;
+        }
+        let mut ports = self.inputs.iter_mut().chain(self.outputs.iter_mut());
+        match ports.find(|p| p.get_identifier() == old) {
+            Some(port) => {
+                port.set_identifier(new);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Gates are classified by name: those named like `DFF*`, `SDFF*`, `LATCH*` or `DLATCH*` are registers,
+/// with their control pins found by conventional port names, like `C`/`CLK`, `R`/`RST` and `E`/`EN`.
+impl SequentialElement for Gate {
+    fn is_register(&self) -> bool {
+        let name = self.name.get_name().to_ascii_uppercase();
+        ["DFF", "SDFF", "LATCH", "DLATCH"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+    }
+
+    fn is_latch(&self) -> bool {
+        self.is_register() && self.name.get_name().to_ascii_uppercase().contains("LATCH")
+    }
+
+    fn get_clock_input(&self) -> Option<usize> {
+        self.find_register_pin(&["C", "CK", "CLK", "G", "GATE"])
+    }
+
+    fn get_reset_input(&self) -> Option<usize> {
+        self.find_register_pin(&["R", "RN", "RST", "RSTN", "RESET", "CLR"])
+    }
+
+    fn get_enable_input(&self) -> Option<usize> {
+        self.find_register_pin(&["E", "EN", "CE", "ENABLE"])
+    }
 }
 
 impl Gate {
+    /// Returns the index of the first input named one of `names`, ignoring case, if the gate is a register
+    fn find_register_pin(&self, names: &[&str]) -> Option<usize> {
+        if !self.is_register() {
+            return None;
+        }
+        self.inputs.iter().position(|p| {
+            let port = p.get_identifier().get_name();
+            names.iter().any(|n| port.eq_ignore_ascii_case(n))
+        })
+    }
+
+    /// Creates a new gate primitive with the given ports
+    pub fn new(name: Identifier, inputs: Vec<Net>, outputs: Vec<Net>) -> Self {
+        if name.is_sliced() {
+            panic!("Attempted to create a gate with a sliced identifier: {name}");
+        }
+
+        Self {
+            name,
+            inputs,
+            outputs,
+        }
+    }
+
     /// Creates a new gate primitive with four-state logic types
     pub fn new_logical(name: Identifier, inputs: Vec<Identifier>, output: Identifier) -> Self {
         if name.is_sliced() {
@@ -118,6 +197,70 @@ impl Gate {
     }
 }
 
+/// A built-in library of sequential primitives, with port names that [SequentialElement] recognizes.
+/// All of them are rising-edge or active-high, and drive their state on the output `Q`.
+impl Gate {
+    /// A D flip-flop with ports `D` and `C`
+    pub fn dff() -> Self {
+        Self::new_logical("DFF".into(), vec!["D".into(), "C".into()], "Q".into())
+    }
+
+    /// A D flip-flop with ports `D`, `C` and `R`, where `R` resets the state to 0 asynchronously
+    pub fn dff_async_reset() -> Self {
+        Self::new_logical(
+            "DFFR".into(),
+            vec!["D".into(), "C".into(), "R".into()],
+            "Q".into(),
+        )
+    }
+
+    /// A D flip-flop with ports `D`, `C` and `E`, which only loads `D` when `E` is high
+    pub fn dff_enable() -> Self {
+        Self::new_logical(
+            "DFFE".into(),
+            vec!["D".into(), "C".into(), "E".into()],
+            "Q".into(),
+        )
+    }
+
+    /// A D latch with ports `D` and `G`, which is transparent while `G` is high
+    pub fn latch() -> Self {
+        Self::new_logical("DLATCH".into(), vec!["D".into(), "G".into()], "Q".into())
+    }
+
+    /// Returns the inner and root gates of a tree of gates with `fanin` inputs that computes this gate,
+    /// like `AND` and `NAND` for a `NAND`. This works for `AND`, `OR` and `XOR` and their inversions,
+    /// and returns [None] for other gates or if `fanin` is more than the inputs of this gate.
+    pub fn with_fanin(&self, fanin: usize) -> Option<(Gate, Gate)> {
+        let name = self.name.to_string();
+        let inner = match name.as_str() {
+            "AND" | "NAND" => "AND",
+            "OR" | "NOR" => "OR",
+            "XOR" | "XNOR" => "XOR",
+            _ => return None,
+        };
+        if fanin > self.inputs.len() || self.outputs.len() != 1 {
+            return None;
+        }
+        let gate = |name: &str| Gate {
+            name: name.into(),
+            inputs: self.inputs[..fanin].to_vec(),
+            outputs: self.outputs.clone(),
+        };
+        Some((gate(inner), gate(&name)))
+    }
+
+    /// Returns every primitive of the built-in sequential library
+    pub fn sequential_library() -> Vec<Self> {
+        vec![
+            Self::dff(),
+            Self::dff_async_reset(),
+            Self::dff_enable(),
+            Self::latch(),
+        ]
+    }
+}
+
 /// An operand to an [Instantiable]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
@@ -189,15 +332,54 @@ where
     O: WeakIndex<usize, Output = Self>,
 {
     /// The object that is owned by the netlist
-    object: Object<I>,
+    object: Rc<Object<I>>,
     /// The weak reference to the owner netlist/module
     owner: Weak<O>,
     /// The list of operands for the object
-    operands: Vec<Option<Operand>>,
+    operands: Rc<Vec<Option<Operand>>>,
     /// A collection of attributes for the object
-    attributes: HashMap<AttributeKey, AttributeValue>,
+    attributes: Rc<HashMap<AttributeKey, AttributeValue>>,
+    /// The typed properties of the object, kept apart from its attributes
+    properties: Rc<Properties>,
     /// The index of the object within the netlist/module
     index: usize,
+    /// The epoch of the owner in which the object was last marked
+    mark: usize,
+}
+
+/// The properties of a node that the library gives a meaning to.
+/// They are kept apart from the attributes, so an attribute of the same name from a parsed netlist cannot be taken for one.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+struct Properties {
+    /// The initial value, as set by [NetRef::set_initial_value]
+    init: Option<bool>,
+    /// Whether the node is an observation point, as set by [NetRef::anchor]
+    anchored: bool,
+    /// The parasitics of the outputs by position, as set by [DrivenNet::set_parasitics]
+    parasitics: BTreeMap<usize, Parasitics>,
+    /// The drive strengths of the outputs by position, as set by [DrivenNet::set_drive_strength]
+    drive_strengths: BTreeMap<usize, Strength>,
+}
+
+impl Properties {
+    /// Returns the properties as `key=value` strings, in a fixed order, for hashing and comparing nodes
+    fn to_strings(&self) -> Vec<String> {
+        let mut strings = Vec::new();
+        if let Some(init) = self.init {
+            strings.push(format!("init={}", init as u8));
+        }
+        if self.anchored {
+            strings.push("anchor".to_string());
+        }
+        for (pos, p) in &self.parasitics {
+            strings.push(format!("parasitics_{pos}={p}"));
+        }
+        for (pos, s) in &self.drive_strengths {
+            strings.push(format!("drive_strength_{pos}={s}"));
+        }
+        strings
+    }
 }
 
 impl<I, O> OwnedObject<I, O>
@@ -205,11 +387,34 @@ where
     I: Instantiable,
     O: WeakIndex<usize, Output = Self>,
 {
-    /// Get an iterator to mutate the operand indices
-    fn inds_mut(&mut self) -> impl Iterator<Item = &mut Operand> {
-        self.operands
-            .iter_mut()
-            .filter_map(|operand| operand.as_mut())
+    /// Replaces every operand that `f` maps to a new one, which may be [None] to disconnect it.
+    /// The operands are only copied from a [Snapshot] sharing them if one of them changes.
+    fn map_operands(&mut self, f: impl Fn(&Operand) -> Option<Option<Operand>>) {
+        if !self.operands.iter().flatten().any(|op| f(op).is_some()) {
+            return;
+        }
+        for operand in Rc::make_mut(&mut self.operands) {
+            if let Some(op) = operand
+                && let Some(new) = f(op)
+            {
+                *operand = new;
+            }
+        }
+    }
+
+    /// Get the operands mutably, copying them first if a [Snapshot] shares them
+    fn operands_mut(&mut self) -> &mut Vec<Option<Operand>> {
+        Rc::make_mut(&mut self.operands)
+    }
+
+    /// Get the attributes mutably, copying them first if a [Snapshot] shares them
+    fn attributes_mut(&mut self) -> &mut HashMap<AttributeKey, AttributeValue> {
+        Rc::make_mut(&mut self.attributes)
+    }
+
+    /// Get the properties mutably, copying them first if a [Snapshot] shares them
+    fn properties_mut(&mut self) -> &mut Properties {
+        Rc::make_mut(&mut self.properties)
     }
 
     /// Get the driver to input `index`
@@ -234,38 +439,41 @@ where
         })
     }
 
-    /// Iterator to driving nets
-    fn driver_nets(&self) -> impl Iterator<Item = Option<Net>> {
+    /// Iterator to driving nets, along with the output pin of the driver that is used
+    fn driver_nets(&self) -> impl Iterator<Item = Option<(Net, usize)>> {
         self.operands.iter().map(|operand| {
-            operand.as_ref().map(|operand| match operand {
-                Operand::DirectIndex(idx) => self
-                    .owner
-                    .upgrade()
-                    .expect("Object is unlinked from netlist")
-                    .index_weak(idx)
-                    .borrow()
-                    .as_net()
-                    .clone(),
-                Operand::CellIndex(idx, j) => self
-                    .owner
-                    .upgrade()
-                    .expect("Object is unlinked from netlist")
-                    .index_weak(idx)
-                    .borrow()
-                    .get_net(*j)
-                    .clone(),
-            })
+            operand
+                .as_ref()
+                .map(|operand| (self.operand_net(operand), operand.secondary()))
         })
     }
 
+    /// Get the net referred to by `operand`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference to the netlist is lost.
+    fn operand_net(&self, operand: &Operand) -> Net {
+        let driver = self
+            .owner
+            .upgrade()
+            .expect("Object is unlinked from netlist")
+            .index_weak(&operand.root());
+        let driver = driver.borrow();
+        match operand {
+            Operand::DirectIndex(_) => driver.as_net().clone(),
+            Operand::CellIndex(_, j) => driver.get_net(*j).clone(),
+        }
+    }
+
     /// Get the underlying object
     fn get(&self) -> &Object<I> {
         &self.object
     }
 
-    /// Get the underlying object mutably
+    /// Get the underlying object mutably, copying it first if a [Snapshot] shares it
     fn get_mut(&mut self) -> &mut Object<I> {
-        &mut self.object
+        Rc::make_mut(&mut self.object)
     }
 
     /// Get the index of `self` relative to the owning module
@@ -275,8 +483,8 @@ where
 
     /// Get the net that is driven by this object
     fn as_net(&self) -> &Net {
-        match &self.object {
-            Object::Input(net) => net,
+        match &*self.object {
+            Object::Input(net) | Object::Alias(net) => net,
             Object::Instance(nets, _, _) => {
                 if nets.len() > 1 {
                     panic!("Attempt to grab the net of a multi-output instance");
@@ -289,8 +497,8 @@ where
 
     /// Get the net that is driven by this object
     fn as_net_mut(&mut self) -> &mut Net {
-        match &mut self.object {
-            Object::Input(net) => net,
+        match Rc::make_mut(&mut self.object) {
+            Object::Input(net) | Object::Alias(net) => net,
             Object::Instance(nets, _, _) => {
                 if nets.len() > 1 {
                     panic!("Attempt to grab the net of a multi-output instance");
@@ -304,8 +512,8 @@ where
 
     /// Get the net that is driven by this object at position `idx`
     fn get_net(&self, idx: usize) -> &Net {
-        match &self.object {
-            Object::Input(net) => {
+        match &*self.object {
+            Object::Input(net) | Object::Alias(net) => {
                 if idx != 0 {
                     panic!("Nonzero index on an input object");
                 }
@@ -317,8 +525,8 @@ where
 
     /// Get a mutable reference to the net that is driven by this object at position `idx`
     fn get_net_mut(&mut self, idx: usize) -> &mut Net {
-        match &mut self.object {
-            Object::Input(net) => {
+        match Rc::make_mut(&mut self.object) {
+            Object::Input(net) | Object::Alias(net) => {
                 if idx != 0 {
                     panic!("Nonzero index on an input object");
                 }
@@ -330,8 +538,8 @@ where
 
     /// Check if this object drives a specific net
     fn find_net(&self, net: &Net) -> Option<usize> {
-        match &self.object {
-            Object::Input(input_net) => {
+        match &*self.object {
+            Object::Input(input_net) | Object::Alias(input_net) => {
                 if input_net == net {
                     Some(0)
                 } else {
@@ -344,8 +552,8 @@ where
 
     /// Attempt to find a mutable reference to a net within this object
     fn find_net_mut(&mut self, net: &Net) -> Option<&mut Net> {
-        match &mut self.object {
-            Object::Input(input_net) => {
+        match Rc::make_mut(&mut self.object) {
+            Object::Input(input_net) | Object::Alias(input_net) => {
                 if input_net == net {
                     Some(input_net)
                 } else {
@@ -356,58 +564,100 @@ where
         }
     }
 
-    /// Get driving net using the weak reference
+    /// Get driving net using the weak reference, along with the output pin of the driver that is used
     ///
     /// # Panics
     ///
     /// Panics if the reference to the netlist is lost.
-    fn get_driver_net(&self, index: usize) -> Option<Net> {
-        let operand = &self.operands[index];
-        match operand {
-            Some(op) => match op {
-                Operand::DirectIndex(idx) => self
-                    .owner
-                    .upgrade()
-                    .expect("Object is unlinked from netlist")
-                    .index_weak(idx)
-                    .borrow()
-                    .as_net()
-                    .clone()
-                    .into(),
-                Operand::CellIndex(idx, j) => self
-                    .owner
-                    .upgrade()
-                    .expect("Object is unlinked from netlist")
-                    .index_weak(idx)
-                    .borrow()
-                    .get_net(*j)
-                    .clone()
-                    .into(),
-            },
-            None => None,
-        }
+    fn get_driver_net(&self, index: usize) -> Option<(Net, usize)> {
+        self.operands[index]
+            .as_ref()
+            .map(|op| (self.operand_net(op), op.secondary()))
     }
 
     fn clear_attribute(&mut self, k: &AttributeKey) -> Option<AttributeValue> {
-        self.attributes.remove(k)
+        self.attributes_mut().remove(k)
     }
 
     fn set_attribute(&mut self, k: AttributeKey) {
-        self.attributes.insert(k, None);
+        self.attributes_mut().insert(k, None);
     }
 
     fn insert_attribute(&mut self, k: AttributeKey, v: String) -> Option<AttributeValue> {
-        self.attributes.insert(k, Some(v))
+        self.attributes_mut().insert(k, Some(v))
     }
 
     fn attributes(&self) -> impl Iterator<Item = Attribute> {
-        Attribute::from_pairs(self.attributes.clone().into_iter())
+        Attribute::from_pairs((*self.attributes).clone().into_iter())
     }
 }
 
+/// The attribute marking the net of a node as a supply, as set by [NetRef::set_supply]
+const SUPPLY_ATTRIBUTE: &str = "supply";
+
+/// The name of the input port of an alias, as created by [Netlist::insert_alias]
+const ALIAS_INPUT: &str = "A";
+
+/// The name of the output port of an alias
+const ALIAS_OUTPUT: &str = "Y";
+
 /// This type exposes the interior mutability of elements in a netlist.
 type NetRefT<I> = Rc<RefCell<OwnedObject<I, Netlist<I>>>>;
 
+/// The structure of an instance: its type name, its parameters and its operands
+type StructKey = (Identifier, String, Vec<Operand>);
+
+/// An index from the structure of an instance to the instance
+type StructIndex<I> = HashMap<StructKey, Weak<RefCell<OwnedObject<I, Netlist<I>>>>>;
+
+/// An index from the name of an instance type to its instances
+type TypeIndex<I> = HashMap<Identifier, Vec<Weak<RefCell<OwnedObject<I, Netlist<I>>>>>>;
+
+/// A mutable borrow of the [Instantiable] type of an instance, returned by [NetRef::get_instance_type_mut]
+pub struct InstanceTypeMut<'a, I: Instantiable> {
+    /// The borrowed type
+    inst_type: RefMut<'a, I>,
+    /// The name of the type when it was borrowed
+    name: Identifier,
+    /// The netlist that owns the instance
+    owner: Weak<Netlist<I>>,
+}
+
+impl<I> std::ops::Deref for InstanceTypeMut<'_, I>
+where
+    I: Instantiable,
+{
+    type Target = I;
+
+    fn deref(&self) -> &I {
+        &self.inst_type
+    }
+}
+
+impl<I> std::ops::DerefMut for InstanceTypeMut<'_, I>
+where
+    I: Instantiable,
+{
+    fn deref_mut(&mut self) -> &mut I {
+        &mut self.inst_type
+    }
+}
+
+impl<I> Drop for InstanceTypeMut<'_, I>
+where
+    I: Instantiable,
+{
+    fn drop(&mut self) {
+        // A renamed type moves the instance in the index by type name, so the index is rebuilt on next use.
+        // An instance of an unlinked netlist has no index to update.
+        if *self.inst_type.get_name() != self.name
+            && let Some(owner) = self.owner.upgrade()
+        {
+            owner.type_index.replace(None);
+        }
+    }
+}
+
 /// Provides an idiomatic interface
 /// to the interior mutability of the netlist
 #[derive(Debug, Clone)]
@@ -457,7 +707,7 @@ where
     /// # Panics
     ///
     /// Panics if the circuit node has multiple outputs.
-    pub fn as_net(&self) -> Ref<Net> {
+    pub fn as_net(&self) -> Ref<'_, Net> {
         Ref::map(self.netref.borrow(), |f| f.as_net())
     }
 
@@ -466,17 +716,19 @@ where
     /// # Panics
     ///
     /// Panics if the circuit node has multiple outputs.
-    pub fn as_net_mut(&self) -> RefMut<Net> {
+    pub fn as_net_mut(&self) -> RefMut<'_, Net> {
+        self.assert_owner_unlocked();
         RefMut::map(self.netref.borrow_mut(), |f| f.as_net_mut())
     }
 
     /// Returns a borrow to the output [Net] as position `idx`
-    pub fn get_net(&self, idx: usize) -> Ref<Net> {
+    pub fn get_net(&self, idx: usize) -> Ref<'_, Net> {
         Ref::map(self.netref.borrow(), |f| f.get_net(idx))
     }
 
     /// Returns a mutable borrow to the output [Net] as position `idx`
-    pub fn get_net_mut(&self, idx: usize) -> RefMut<Net> {
+    pub fn get_net_mut(&self, idx: usize) -> RefMut<'_, Net> {
+        self.assert_owner_unlocked();
         RefMut::map(self.netref.borrow_mut(), |f| f.get_net_mut(idx))
     }
 
@@ -520,6 +772,11 @@ where
     ///
     /// Panics if the circuit node has multiple outputs.
     pub fn set_identifier(&self, identifier: Identifier) {
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::RenameNet {
+            from: self.get_identifier(),
+            to: identifier.clone(),
+        });
         self.as_net_mut().set_identifier(identifier)
     }
 
@@ -528,22 +785,111 @@ where
         matches!(self.netref.borrow().get(), Object::Input(_))
     }
 
+    /// Returns `true` if this circuit node is an alias of another net, see [Netlist::insert_alias]
+    pub fn is_an_alias(&self) -> bool {
+        matches!(self.netref.borrow().get(), Object::Alias(_))
+    }
+
     /// Returns a reference to the object at this node.
-    pub fn get_obj(&self) -> Ref<Object<I>> {
+    pub fn get_obj(&self) -> Ref<'_, Object<I>> {
         Ref::map(self.netref.borrow(), |f| f.get())
     }
 
+    /// Records the edit made by `edit` in the journal of the netlist that owns this circuit node, if one was started
+    #[cfg(feature = "journal")]
+    fn record(&self, edit: impl FnOnce() -> Edit<I>) {
+        let owner = self.netref.borrow().owner.upgrade();
+        if let Some(netlist) = owner {
+            netlist.record(edit);
+        }
+    }
+
+    /// Returns the name of this circuit node in a journal
+    #[cfg(feature = "journal")]
+    fn journal_name(&self) -> Identifier {
+        journal::object_name(self.netref.borrow().get())
+    }
+
+    /// Panics if the netlist that owns this circuit node is locked, for edits that cannot fail
+    fn assert_owner_unlocked(&self) {
+        let owner = self.netref.borrow().owner.upgrade();
+        if let Some(netlist) = owner {
+            netlist.assert_unlocked();
+        }
+    }
+
+    /// Returns [NetlistError::Locked] if the netlist that owns this circuit node is locked
+    fn check_owner_unlocked(&self) -> Result<(), NetlistError<I>> {
+        let owner = self.netref.borrow().owner.upgrade();
+        match owner {
+            Some(netlist) => netlist.check_unlocked(),
+            None => Ok(()),
+        }
+    }
+
     /// Returns the [Instantiable] type of the instance, if this circuit node is an instance
-    pub fn get_instance_type(&self) -> Option<Ref<I>> {
+    pub fn get_instance_type(&self) -> Option<Ref<'_, I>> {
         Ref::filter_map(self.netref.borrow(), |f| f.get().get_instance_type()).ok()
     }
 
-    /// Returns the [Instantiable] type of the instance, if this circuit node is an instance
-    pub fn get_instance_type_mut(&self) -> Option<RefMut<I>> {
-        RefMut::filter_map(self.netref.borrow_mut(), |f| {
+    /// Returns the [Instantiable] type of the instance, if this circuit node is an instance.
+    /// If the type is renamed through the borrow, the instance moves to its new name in [Netlist::instances_of] when the borrow is dropped.
+    pub fn get_instance_type_mut(&self) -> Option<InstanceTypeMut<'_, I>> {
+        self.assert_owner_unlocked();
+        let owner = self.netref.borrow().owner.clone();
+        let inst_type = RefMut::filter_map(self.netref.borrow_mut(), |f| {
             f.get_mut().get_instance_type_mut()
         })
-        .ok()
+        .ok()?;
+        Some(InstanceTypeMut {
+            name: inst_type.get_name().clone(),
+            inst_type,
+            owner,
+        })
+    }
+
+    /// Marks this circuit node, returning `true` if it was not already marked.
+    /// Marks are cleared all at once with [Netlist::clear_marks].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference to the netlist is lost.
+    pub fn mark(&self) -> bool {
+        let epoch = self.owner_epoch();
+        let mark = &mut self.netref.borrow_mut().mark;
+        std::mem::replace(mark, epoch) != epoch
+    }
+
+    /// Unmarks this circuit node
+    pub fn unmark(&self) {
+        self.netref.borrow_mut().mark = 0;
+    }
+
+    /// Returns `true` if this circuit node is marked
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference to the netlist is lost.
+    pub fn is_marked(&self) -> bool {
+        self.netref.borrow().mark == self.owner_epoch()
+    }
+
+    /// Returns the current marking epoch of the owning netlist
+    fn owner_epoch(&self) -> usize {
+        self.netref
+            .borrow()
+            .owner
+            .upgrade()
+            .expect("NetRef is unlinked from netlist")
+            .epoch
+            .get()
+    }
+
+    /// Applies `f` to the mutable [Instantiable] type of the instance, if this circuit node is an instance.
+    /// The mutable borrow ends when `f` returns, so it cannot outlive the edit.
+    pub fn with_instance_type_mut<R>(&self, f: impl FnOnce(&mut I) -> R) -> Option<R> {
+        let mut inst_type = self.get_instance_type_mut()?;
+        Some(f(&mut inst_type))
     }
 
     /// Returns a copy of the name of the instance, if the circuit node is a instance.
@@ -560,26 +906,49 @@ where
     ///
     /// Panics if the circuit node is a principal input.
     pub fn set_instance_name(&self, name: Identifier) {
+        self.assert_owner_unlocked();
+        #[cfg(feature = "journal")]
+        if let Some(from) = self.get_instance_name() {
+            self.record(|| Edit::RenameInstance {
+                from,
+                to: name.clone(),
+            });
+        }
         match self.netref.borrow_mut().get_mut() {
             Object::Instance(_, inst_name, _) => *inst_name = name,
             _ => panic!("Attempted to set instance name on a non-instance object"),
         }
     }
 
-    /// Exposes this circuit node as a top-level output in the netlist.
-    /// Returns an error if the circuit node is a principal input.
+    /// Returns the full hierarchical path to this circuit node.
+    /// Instances are named by their instance name, and principal inputs and aliases by their net.
     ///
     /// # Panics
     ///
-    /// Panics if cell is a multi-output circuit node.
     /// Panics if the reference to the netlist is lost.
-    pub fn expose_as_output(self) -> Result<Self, String> {
+    pub fn get_path(&self) -> HierPath {
         let netlist = self
             .netref
             .borrow()
             .owner
             .upgrade()
             .expect("NetRef is unlinked from netlist");
+        match self.get_instance_name() {
+            Some(name) => netlist.get_path().join(name),
+            None => netlist.get_path().join(self.get_identifier()),
+        }
+    }
+
+    /// Exposes this circuit node as a top-level output in the netlist.
+    /// Returns an error if the circuit node is a principal input.
+    ///
+    /// Returns [NetlistError::Unlinked] as a string if the reference to the netlist is lost.
+    ///
+    /// # Panics
+    ///
+    /// Panics if cell is a multi-output circuit node.
+    pub fn expose_as_output(self) -> Result<Self, String> {
+        let netlist = self.try_get_netlist()?;
         netlist.expose_net(self.clone().into())?;
         Ok(self)
     }
@@ -601,18 +970,25 @@ where
         self
     }
 
-    /// Exposes the `net` driven by this circuit node as a top-level output.
-    /// Errors if `net` is not driven by this circuit node.
+    /// Exposes every output net of this circuit node as a top-level output.
+    /// Each port name is derived from the output port it comes from, like `{prefix}_COUT`.
     ///
     /// # Panics
+    ///
     /// Panics if the reference to the netlist is lost.
+    pub fn expose_all_outputs(&self, prefix: &str) -> Vec<DrivenNet<I>> {
+        self.outputs()
+            .map(|output| {
+                let name = format!("{}_{}", prefix, output.get_port().get_identifier());
+                output.expose_with_name(name.into())
+            })
+            .collect()
+    }
+
+    /// Exposes the `net` driven by this circuit node as a top-level output.
+    /// Errors if `net` is not driven by this circuit node, or if the reference to the netlist is lost.
     pub fn expose_net(&self, net: &Net) -> Result<(), String> {
-        let netlist = self
-            .netref
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("NetRef is unlinked from netlist");
+        let netlist = self.try_get_netlist()?;
         let net_index = self.netref.borrow().find_net(net).ok_or(format!(
             "Net {} not found in circuit node",
             net.get_identifier()
@@ -627,12 +1003,12 @@ where
         self.netref.borrow().get_driver(index).map(NetRef::wrap)
     }
 
-    /// Returns the net that drives the `index`th input
+    /// Returns the net that drives the `index`th input, along with the output pin of the driver that is used
     ///
     /// # Panics
     ///
     /// Panics if the reference to the netlist is lost.
-    pub fn get_driver_net(&self, index: usize) -> Option<Net> {
+    pub fn get_driver_net(&self, index: usize) -> Option<(Net, usize)> {
         self.netref.borrow().get_driver_net(index)
     }
 
@@ -643,7 +1019,7 @@ where
     ///
     /// Panics if the reference to the netlist is lost.
     pub fn req_driver_net(&self, index: usize) -> Option<MutBorrowReq<I>> {
-        let net = self.get_driver_net(index)?;
+        let (net, _) = self.get_driver_net(index)?;
         let operand = self.get_driver(index).unwrap();
         Some(MutBorrowReq::new(operand, net))
     }
@@ -652,6 +1028,8 @@ where
     pub fn get_num_input_ports(&self) -> usize {
         if let Some(inst_type) = self.get_instance_type() {
             inst_type.get_input_ports().into_iter().count()
+        } else if self.is_an_alias() {
+            1
         } else {
             0
         }
@@ -677,12 +1055,36 @@ where
         drivers.into_iter()
     }
 
-    /// Returns an interator to the driving nets.
-    pub fn driver_nets(&self) -> impl Iterator<Item = Option<Net>> {
-        let vec: Vec<Option<Net>> = self.netref.borrow().driver_nets().collect();
+    /// Returns an interator to the driving nets, along with the output pin of each driver that is used.
+    pub fn driver_nets(&self) -> impl Iterator<Item = Option<(Net, usize)>> {
+        let vec: Vec<Option<(Net, usize)>> = self.netref.borrow().driver_nets().collect();
         vec.into_iter()
     }
 
+    /// Returns an iterator to the operands of this circuit node, along with the input position they drive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference to the netlist is lost.
+    pub fn operands(&self) -> impl Iterator<Item = (usize, Option<DrivenNet<I>>)> {
+        let operands: Vec<(usize, Option<DrivenNet<I>>)> = self
+            .netref
+            .borrow()
+            .operands
+            .iter()
+            .cloned()
+            .zip(self.netref.borrow().drivers())
+            .enumerate()
+            .map(|(i, (operand, driver))| {
+                let driven = operand
+                    .zip(driver)
+                    .map(|(o, d)| DrivenNet::new(o.secondary(), NetRef::wrap(d)));
+                (i, driven)
+            })
+            .collect();
+        operands.into_iter()
+    }
+
     /// Returns an iterator to the output nets of this circuit node.
     #[allow(clippy::unnecessary_to_owned)]
     pub fn nets(&self) -> impl Iterator<Item = Net> {
@@ -702,7 +1104,7 @@ where
     }
 
     /// Returns an iterator to mutate the output nets of this circuit node.
-    pub fn nets_mut(&self) -> impl Iterator<Item = RefMut<Net>> {
+    pub fn nets_mut(&self) -> impl Iterator<Item = RefMut<'_, Net>> {
         let nnets = self.netref.borrow().get().get_nets().len();
         (0..nnets).map(|i| self.get_net_mut(i))
     }
@@ -713,21 +1115,15 @@ where
     }
 
     /// Returns `true` if this circuit node drives a top-level output.
-    ///
-    /// # Panics
-    /// Panics if the weak reference to the netlist is lost.
+    /// A circuit node whose netlist was dropped drives no outputs.
     pub fn drives_a_top_output(&self) -> bool {
-        let netlist = self
-            .netref
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("NetRef is unlinked from netlist");
-        netlist.drives_an_output(self.clone())
+        self.try_get_netlist()
+            .is_ok_and(|netlist| netlist.drives_an_output(self.clone()))
     }
 
     /// Attempts to find a mutable reference to `net` within this circuit node.
-    pub fn find_net_mut(&self, net: &Net) -> Option<RefMut<Net>> {
+    pub fn find_net_mut(&self, net: &Net) -> Option<RefMut<'_, Net>> {
+        self.assert_owner_unlocked();
         RefMut::filter_map(self.netref.borrow_mut(), |f| f.find_net_mut(net)).ok()
     }
 
@@ -737,48 +1133,66 @@ where
     }
 
     /// Deletes the uses of this circuit node from the netlist.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the reference to the netlist is lost.
-    pub fn delete_uses(self) -> Result<Object<I>, String> {
-        let netlist = self
-            .netref
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("NetRef is unlinked from netlist");
+    /// Returns [NetlistError::Unlinked] if the reference to the netlist is lost.
+    pub fn delete_uses(self) -> Result<Object<I>, NetlistError<I>> {
+        let netlist = self.try_get_netlist()?;
         netlist.delete_net_uses(self)
     }
 
+    /// Deletes the uses of this circuit node from the netlist.
+    /// Top-level outputs driven by this node are handled according to `policy`.
+    /// Returns [NetlistError::Unlinked] if the reference to the netlist is lost.
+    pub fn delete_uses_with(self, policy: OutputPolicy<I>) -> Result<Object<I>, NetlistError<I>> {
+        let netlist = self.try_get_netlist()?;
+        netlist.delete_net_uses_with(self, policy)
+    }
+
     /// Replaces the uses of this circuit node in the netlist with another circuit node.
+    /// Returns [NetlistError::Unlinked] if the reference to the netlist is lost.
     ///
     /// # Panics
     ///
     /// Panics if either `self` or `other` is a multi-output circuit node.
-    /// Panics if the weak reference to the netlist is lost.
-    pub fn replace_uses_with(self, other: &Self) -> Result<Object<I>, String> {
-        let netlist = self
-            .netref
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("NetRef is unlinked from netlist");
+    pub fn replace_uses_with(self, other: &Self) -> Result<Object<I>, NetlistError<I>> {
+        let netlist = self.try_get_netlist()?;
         netlist.replace_net_uses(self, other)
     }
 
     /// Clears the attribute with the given key on this circuit node.
     pub fn clear_attribute(&self, k: &AttributeKey) -> Option<AttributeValue> {
-        self.netref.borrow_mut().clear_attribute(k)
+        self.assert_owner_unlocked();
+        let cleared = self.netref.borrow_mut().clear_attribute(k);
+        #[cfg(feature = "journal")]
+        if cleared.is_some() {
+            self.record(|| Edit::ClearAttribute {
+                node: self.journal_name(),
+                key: k.clone(),
+            });
+        }
+        cleared
     }
 
     /// Set an attribute without a value
     pub fn set_attribute(&self, k: AttributeKey) {
+        self.assert_owner_unlocked();
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::SetAttribute {
+            node: self.journal_name(),
+            key: k.clone(),
+            value: None,
+        });
         self.netref.borrow_mut().set_attribute(k);
     }
 
     /// Insert an attribute on this node with a value
     pub fn insert_attribute(&self, k: AttributeKey, v: String) -> Option<AttributeValue> {
+        self.assert_owner_unlocked();
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::SetAttribute {
+            node: self.journal_name(),
+            key: k.clone(),
+            value: Some(v.clone()),
+        });
         self.netref.borrow_mut().insert_attribute(k, v)
     }
 
@@ -787,357 +1201,2495 @@ where
         let v: Vec<_> = self.netref.borrow().attributes().collect();
         v.into_iter()
     }
-}
 
-impl<I> std::fmt::Display for NetRef<I>
-where
-    I: Instantiable,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.netref.borrow().object.fmt(f)
+    /// Returns the attributes at this circuit node inside `namespace`, directly or through nested namespaces
+    pub fn attributes_in(&self, namespace: &str) -> impl Iterator<Item = Attribute> {
+        let namespace = namespace.to_string();
+        self.attributes()
+            .filter(move |a| a.in_namespace(&namespace))
     }
-}
 
-impl<I> From<NetRef<I>> for DrivenNet<I>
-where
-    I: Instantiable,
-{
-    fn from(val: NetRef<I>) -> Self {
-        if val.is_multi_output() {
-            panic!("Cannot convert a multi-output netref to an output port");
+    /// Returns the value of the attribute `key` inside `namespace`, if it is set
+    pub fn get_namespaced_attribute(&self, namespace: &str, key: &str) -> Option<AttributeValue> {
+        let key = attribute::namespaced_key(namespace, key);
+        self.netref.borrow().attributes.get(&key).cloned()
+    }
+
+    /// Clears every attribute at this circuit node inside `namespace`, returning how many were cleared.
+    /// Attributes of other namespaces are untouched.
+    pub fn clear_namespace(&self, namespace: &str) -> usize {
+        self.assert_owner_unlocked();
+        let keys: Vec<AttributeKey> = self
+            .netref
+            .borrow()
+            .attributes
+            .keys()
+            .filter(|k| attribute::in_namespace(k, namespace))
+            .cloned()
+            .collect();
+        for k in &keys {
+            self.clear_attribute(k);
         }
-        DrivenNet::new(0, val)
+        keys.len()
     }
-}
 
-impl<I> From<&NetRef<I>> for DrivenNet<I>
-where
-    I: Instantiable,
-{
-    fn from(val: &NetRef<I>) -> Self {
-        if val.is_multi_output() {
-            panic!("Cannot convert a multi-output netref to an output port");
+    /// Returns the value this instance holds at time zero.
+    /// An initial value set with [NetRef::set_initial_value] overrides the one of the instance type.
+    pub fn get_initial_value(&self) -> Option<bool> {
+        let owned = self.netref.borrow();
+        match owned.properties.init {
+            Some(init) => Some(init),
+            None => owned.get().get_instance_type()?.get_initial_value(),
         }
-        DrivenNet::new(0, val.clone())
     }
-}
 
-/// Facilitates mutable borrows to driver nets
-pub struct MutBorrowReq<I: Instantiable> {
-    from: NetRef<I>,
-    ind: Net,
-}
+    /// Sets the value this instance holds at time zero, or clears it with [None].
+    /// The value is kept apart from the attributes, and is emitted in Verilog as a `defparam` of the `INIT` parameter.
+    pub fn set_initial_value(&self, value: Option<bool>) {
+        self.assert_owner_unlocked();
+        self.netref.borrow_mut().properties_mut().init = value;
+    }
 
-impl<I> MutBorrowReq<I>
-where
-    I: Instantiable,
-{
-    /// Creates a new mutable borrow request
-    fn new(from: NetRef<I>, ind: Net) -> Self {
-        Self { from, ind }
+    /// Returns the properties of this node as `key=value` strings, like its initial value and anchor, for hashing and comparing nodes
+    pub(crate) fn property_strings(&self) -> Vec<String> {
+        self.netref.borrow().properties.to_strings()
     }
 
-    /// Mutably borrows the requested net from the circuit node
-    pub fn borrow_mut(&self) -> RefMut<Net> {
-        self.from.find_net_mut(&self.ind).unwrap()
+    /// Gives this node the attributes and properties of `other`, like its initial value
+    fn copy_annotations_from(&self, other: &NetRef<I>) {
+        let (attributes, properties) = {
+            let other = other.netref.borrow();
+            (other.attributes.clone(), other.properties.clone())
+        };
+        let mut owned = self.netref.borrow_mut();
+        owned.attributes = attributes;
+        owned.properties = properties;
     }
 
-    /// Returns `true` if the circuit node is an input
-    pub fn is_an_input(&self) -> bool {
-        self.from.is_an_input()
+    /// Returns the supply the net of this node carries, if it is marked as power or ground
+    pub fn get_supply(&self) -> Option<Supply> {
+        match self.netref.borrow().attributes.get(SUPPLY_ATTRIBUTE) {
+            Some(Some(v)) => v.parse().ok(),
+            _ => None,
+        }
     }
 
-    /// Attempts to borrow the net mutably if the condition `f` is satisfied.
-    pub fn borrow_mut_if(&self, f: impl Fn(&NetRef<I>) -> bool) -> Option<RefMut<Net>> {
-        if f(&self.from) {
-            Some(self.borrow_mut())
-        } else {
-            None
+    /// Marks the net of this node, like a `VDD` input or a tie cell, as a power or ground supply, or clears the mark with [None].
+    /// Supply nets are kept by [Netlist::clean], are not buffered by [DrivenNet::split_loads], and are skipped by the fanout reports.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node has more than one output, see [NetRef::try_set_supply].
+    pub fn set_supply(&self, supply: Option<Supply>) {
+        if self.is_multi_output() {
+            panic!("Cannot mark the multi-output node {self} as a supply");
+        }
+        match supply {
+            Some(s) => {
+                self.insert_attribute(SUPPLY_ATTRIBUTE.to_string(), s.to_string());
+            }
+            None => {
+                self.clear_attribute(&SUPPLY_ATTRIBUTE.to_string());
+            }
         }
     }
+
+    /// Returns `true` if this node is an observation point, as set by [NetRef::anchor]
+    pub fn is_anchored(&self) -> bool {
+        self.netref.borrow().properties.anchored
+    }
+
+    /// Anchors this node as an observation point, so internal signals can be watched without exposing them as outputs.
+    /// Anchored nodes and the logic driving them are kept by [Netlist::clean], and are traced by the simulator.
+    pub fn anchor(&self) {
+        self.assert_owner_unlocked();
+        self.netref.borrow_mut().properties_mut().anchored = true;
+    }
+
+    /// Removes the anchor from this node, so [Netlist::clean] can remove it if it is unused
+    pub fn clear_anchor(&self) {
+        self.assert_owner_unlocked();
+        self.netref.borrow_mut().properties_mut().anchored = false;
+    }
 }
 
-/// A netlist data structure
-#[derive(Debug)]
-pub struct Netlist<I>
+impl<I> NetRef<I>
 where
-    I: Instantiable,
+    I: SequentialElement,
 {
-    /// The name of the netlist
-    name: String,
-    /// The list of objects in the netlist, such as inputs, modules, and primitives
-    objects: RefCell<Vec<NetRefT<I>>>,
-    /// The list of operands that point to objects which are outputs
-    outputs: RefCell<HashMap<Operand, Net>>,
-}
+    /// Returns `true` if this circuit node is an instance of a register (see [SequentialElement])
+    pub fn is_register(&self) -> bool {
+        self.get_instance_type().is_some_and(|t| t.is_register())
+    }
 
-/// Represent the input port of a primitive
-#[derive(Debug, Clone)]
-pub struct InputPort<I: Instantiable> {
-    pos: usize,
-    netref: NetRef<I>,
+    /// Returns the net driving the input at `index` of a register, found by `pin`
+    fn register_pin(&self, pin: impl Fn(&I) -> Option<usize>) -> Option<DrivenNet<I>> {
+        let index = pin(&*self.get_instance_type()?)?;
+        self.get_input(index).get_driver()
+    }
+
+    /// Returns the net driving the data input of this register
+    pub fn get_data(&self) -> Option<DrivenNet<I>> {
+        self.register_pin(I::get_data_input)
+    }
+
+    /// Returns the net driving the clock input of this register
+    pub fn get_clock(&self) -> Option<DrivenNet<I>> {
+        self.register_pin(I::get_clock_input)
+    }
+
+    /// Returns the net driving the reset input of this register
+    pub fn get_reset(&self) -> Option<DrivenNet<I>> {
+        self.register_pin(I::get_reset_input)
+    }
+
+    /// Returns the net driving the enable input of this register
+    pub fn get_enable(&self) -> Option<DrivenNet<I>> {
+        self.register_pin(I::get_enable_input)
+    }
 }
 
-impl<I> InputPort<I>
+/// Fallible versions of the accessors of [NetRef] that panic on misuse, for embedding the library where a malformed request must not bring down the process.
+/// Each returns the [NetlistError] that the panicking version would have panicked on.
+impl<I> NetRef<I>
 where
     I: Instantiable,
 {
-    fn new(pos: usize, netref: NetRef<I>) -> Self {
-        if pos >= netref.clone().unwrap().borrow().operands.len() {
-            panic!(
-                "Position {} out of bounds for netref with {} input nets",
-                pos,
-                netref.unwrap().borrow().get().get_nets().len()
-            );
-        }
-        Self { pos, netref }
+    /// Returns the netlist that owns this circuit node, or [NetlistError::Unlinked] if it was dropped
+    pub fn try_get_netlist(&self) -> Result<Rc<Netlist<I>>, NetlistError<I>> {
+        self.netref
+            .borrow()
+            .owner
+            .upgrade()
+            .ok_or(NetlistError::Unlinked)
     }
 
-    /// Returns the net that is driving this input port
-    pub fn get_driver(&self) -> Option<DrivenNet<I>> {
-        if self.netref.is_an_input() {
-            panic!("Input port is not driven by a primitive");
+    /// Returns an error if the circuit node has multiple outputs
+    fn check_single_output(&self) -> Result<(), NetlistError<I>> {
+        match self.is_multi_output() {
+            true => Err(NetlistError::MultiOutput(self.clone())),
+            false => Ok(()),
         }
-        if let Some(prev_operand) = self.netref.clone().unwrap().borrow().operands[self.pos].clone()
-        {
-            let netlist = self
-                .netref
-                .clone()
-                .unwrap()
-                .borrow()
-                .owner
-                .upgrade()
-                .expect("Input port is unlinked from netlist");
-            let driver_nr = netlist.index_weak(&prev_operand.root());
-            let nr = NetRef::wrap(driver_nr);
-            let pos = prev_operand.secondary();
-            Some(DrivenNet::new(pos, nr))
-        } else {
-            None
+    }
+
+    /// Returns an error if `index` is not less than `len`
+    fn check_bounds(&self, index: usize, len: usize) -> Result<(), NetlistError<I>> {
+        match index < len {
+            true => Ok(()),
+            false => Err(NetlistError::OutOfBounds {
+                node: self.clone(),
+                index,
+                len,
+            }),
         }
     }
 
-    /// Disconnects an input port and returns the previous [DrivenNet] if it was connected.
-    pub fn disconnect(&self) -> Option<DrivenNet<I>> {
-        let val = self.get_driver();
-        self.netref.clone().unwrap().borrow_mut().operands[self.pos] = None;
-        val
+    /// Returns a borrow to the [Net] at this circuit node, like [NetRef::as_net]
+    pub fn try_as_net(&self) -> Result<Ref<'_, Net>, NetlistError<I>> {
+        self.check_single_output()?;
+        Ok(self.as_net())
     }
 
-    /// Get the input port associated with this connection
-    pub fn get_port(&self) -> Net {
-        if self.netref.is_an_input() {
-            panic!("Net is not driven by a primitive");
-        }
-        self.netref
-            .get_instance_type()
-            .unwrap()
-            .get_input_port(self.pos)
-            .clone()
+    /// Returns the name of the net at this circuit node, like [NetRef::get_identifier]
+    pub fn try_get_identifier(&self) -> Result<Identifier, NetlistError<I>> {
+        Ok(self.try_as_net()?.get_identifier().clone())
     }
 
-    /// Connects this input port to a driven net.
-    pub fn connect(self, output: DrivenNet<I>) {
-        output.connect(self);
+    /// Changes the identifier of the net at this circuit node, like [NetRef::set_identifier]
+    pub fn try_set_identifier(&self, identifier: Identifier) -> Result<(), NetlistError<I>> {
+        self.check_single_output()?;
+        self.check_owner_unlocked()?;
+        self.set_identifier(identifier);
+        Ok(())
     }
 
-    /// Return the underlying circuit node
-    pub fn unwrap(self) -> NetRef<I> {
-        self.netref
+    /// Returns a borrow to the output [Net] at position `idx`, like [NetRef::get_net]
+    pub fn try_get_net(&self, idx: usize) -> Result<Ref<'_, Net>, NetlistError<I>> {
+        self.check_bounds(idx, self.netref.borrow().get().get_nets().len())?;
+        Ok(self.get_net(idx))
     }
-}
 
-impl<I> std::fmt::Display for InputPort<I>
-where
-    I: Instantiable,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.get_port().fmt(f)
+    /// Returns the output at position `idx`, like [NetRef::get_output]
+    pub fn try_get_output(&self, idx: usize) -> Result<DrivenNet<I>, NetlistError<I>> {
+        self.check_bounds(idx, self.netref.borrow().get().get_nets().len())?;
+        Ok(self.get_output(idx))
     }
-}
 
-/// Represent a net that is being driven by a [Instantiable]
-#[derive(Debug, Clone)]
-pub struct DrivenNet<I: Instantiable> {
-    pos: usize,
-    netref: NetRef<I>,
-}
+    /// Returns the input at position `idx`, like [NetRef::get_input]
+    pub fn try_get_input(&self, idx: usize) -> Result<InputPort<I>, NetlistError<I>> {
+        if self.is_an_input() {
+            return Err(NetlistError::NotAnInstance(self.clone()));
+        }
+        self.check_bounds(idx, self.get_num_input_ports())?;
+        Ok(self.get_input(idx))
+    }
 
-impl<I> DrivenNet<I>
-where
-    I: Instantiable,
-{
-    fn new(pos: usize, netref: NetRef<I>) -> Self {
-        if pos >= netref.clone().unwrap().borrow().get().get_nets().len() {
-            panic!(
-                "Position {} out of bounds for netref with {} outputted nets",
-                pos,
-                netref.unwrap().borrow().get().get_nets().len()
-            );
+    /// Returns the circuit node that drives the `index`th input, like [NetRef::get_driver]
+    pub fn try_get_driver(&self, index: usize) -> Result<Option<Self>, NetlistError<I>> {
+        self.check_bounds(index, self.get_num_input_ports())?;
+        if self.netref.borrow().operands[index].is_some() {
+            self.try_get_netlist()?;
         }
-        Self { pos, netref }
+        Ok(self.get_driver(index))
     }
 
-    /// Returns the index that can address this net in the netlist.
-    fn get_operand(&self) -> Operand {
-        if self.netref.is_multi_output() {
-            Operand::CellIndex(self.netref.clone().unwrap().borrow().get_index(), self.pos)
-        } else {
-            Operand::DirectIndex(self.netref.clone().unwrap().borrow().get_index())
+    /// Updates the name of the instance, like [NetRef::set_instance_name]
+    pub fn try_set_instance_name(&self, name: Identifier) -> Result<(), NetlistError<I>> {
+        self.check_owner_unlocked()?;
+        if self.get_instance_name().is_none() {
+            return Err(NetlistError::NotAnInstance(self.clone()));
         }
+        self.set_instance_name(name);
+        Ok(())
     }
 
-    /// Borrow the net being driven
-    pub fn as_net(&self) -> Ref<Net> {
-        self.netref.get_net(self.pos)
+    /// Returns the full hierarchical path to this circuit node, like [NetRef::get_path]
+    pub fn try_get_path(&self) -> Result<HierPath, NetlistError<I>> {
+        let netlist = self.try_get_netlist()?;
+        Ok(match self.get_instance_name() {
+            Some(name) => netlist.get_path().join(name),
+            None => netlist.get_path().join(self.try_get_identifier()?),
+        })
     }
 
-    /// Get a mutable reference to the net being driven
-    pub fn as_net_mut(&self) -> RefMut<Net> {
-        self.netref.get_net_mut(self.pos)
+    /// Returns a mutable borrow to the [Net] at this circuit node, like [NetRef::as_net_mut]
+    pub fn try_as_net_mut(&self) -> Result<RefMut<'_, Net>, NetlistError<I>> {
+        self.check_single_output()?;
+        self.check_owner_unlocked()?;
+        Ok(self.as_net_mut())
     }
 
-    /// Returns `true` if this net is a principal input
-    pub fn is_an_input(&self) -> bool {
-        self.netref.is_an_input()
+    /// Returns a mutable borrow to the output [Net] at position `idx`, like [NetRef::get_net_mut]
+    pub fn try_get_net_mut(&self, idx: usize) -> Result<RefMut<'_, Net>, NetlistError<I>> {
+        self.check_bounds(idx, self.netref.borrow().get().get_nets().len())?;
+        self.check_owner_unlocked()?;
+        Ok(self.get_net_mut(idx))
     }
 
-    /// Get the output port associated with this connection
-    pub fn get_port(&self) -> Net {
-        if self.netref.is_an_input() {
-            panic!("Net is not driven by a primitive");
-        }
-        self.netref
-            .get_instance_type()
-            .unwrap()
-            .get_output_port(self.pos)
-            .clone()
+    /// Sets an attribute without a value, like [NetRef::set_attribute]
+    pub fn try_set_attribute(&self, k: AttributeKey) -> Result<(), NetlistError<I>> {
+        self.check_owner_unlocked()?;
+        self.set_attribute(k);
+        Ok(())
     }
 
-    /// Connects the net driven by this output port to the given input port.
-    pub fn connect(&self, input: InputPort<I>) {
-        let operand = self.get_operand();
-        let index = input.netref.unwrap().borrow().get_index();
-        let netlist = self
-            .netref
-            .clone()
-            .unwrap()
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("Output port is unlinked from netlist");
-        let obj = netlist.index_weak(&index);
-        obj.borrow_mut().operands[input.pos] = Some(operand.clone());
+    /// Inserts an attribute with a value, like [NetRef::insert_attribute]
+    pub fn try_insert_attribute(
+        &self,
+        k: AttributeKey,
+        v: String,
+    ) -> Result<Option<AttributeValue>, NetlistError<I>> {
+        self.check_owner_unlocked()?;
+        Ok(self.insert_attribute(k, v))
     }
 
-    /// Returns `true` if this net is a top-level output in the netlist.
-    pub fn is_top_level_output(&self) -> bool {
-        let netlist = self
-            .netref
-            .clone()
-            .unwrap()
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("DrivenNet is unlinked from netlist");
-        let outputs = netlist.outputs.borrow();
-        outputs.contains_key(&self.get_operand())
+    /// Clears the attribute with the given key, like [NetRef::clear_attribute]
+    pub fn try_clear_attribute(
+        &self,
+        k: &AttributeKey,
+    ) -> Result<Option<AttributeValue>, NetlistError<I>> {
+        self.check_owner_unlocked()?;
+        Ok(self.clear_attribute(k))
     }
 
-    /// Return the underlying circuit node
-    pub fn unwrap(self) -> NetRef<I> {
-        self.netref
+    /// Sets the value this instance holds at time zero, like [NetRef::set_initial_value]
+    pub fn try_set_initial_value(&self, value: Option<bool>) -> Result<(), NetlistError<I>> {
+        self.check_owner_unlocked()?;
+        self.set_initial_value(value);
+        Ok(())
     }
 
-    /// Returns a copy of the identifier of the net being driven.
-    pub fn get_identifier(&self) -> Identifier {
-        self.as_net().get_identifier().clone()
+    /// Marks the net of this node as a power or ground supply, like [NetRef::set_supply]
+    pub fn try_set_supply(&self, supply: Option<Supply>) -> Result<(), NetlistError<I>> {
+        self.check_single_output()?;
+        self.check_owner_unlocked()?;
+        self.set_supply(supply);
+        Ok(())
     }
 
-    /// Expose this driven net as a module output
-    ///
-    /// # Panics
-    ///
-    /// Panics if the weak reference to the netlist is dead.
-    pub fn expose_with_name(self, name: Identifier) -> Self {
-        let netlist = self
-            .netref
-            .clone()
-            .unwrap()
-            .borrow()
-            .owner
-            .upgrade()
-            .expect("DrivenNet is unlinked from netlist");
-        netlist.expose_net_with_name(self.clone(), name);
-        self
+    /// Anchors this node as an observation point, like [NetRef::anchor]
+    pub fn try_anchor(&self) -> Result<(), NetlistError<I>> {
+        self.check_owner_unlocked()?;
+        self.anchor();
+        Ok(())
+    }
+
+    /// Exposes this circuit node as a top-level output with a specific port name, like [NetRef::expose_with_name]
+    pub fn try_expose_with_name(self, name: Identifier) -> Result<Self, NetlistError<I>> {
+        self.check_single_output()?;
+        let netlist = self.try_get_netlist()?;
+        netlist.try_expose_net_with_name(self.clone().into(), name)?;
+        Ok(self)
     }
 }
 
-impl<I> std::fmt::Display for DrivenNet<I>
+impl<I> std::fmt::Display for NetRef<I>
 where
     I: Instantiable,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.as_net().fmt(f)
+        self.netref.borrow().object.fmt(f)
     }
 }
 
-impl<I> WeakIndex<usize> for Netlist<I>
+impl<I> From<NetRef<I>> for DrivenNet<I>
 where
     I: Instantiable,
 {
-    type Output = OwnedObject<I, Self>;
-
-    fn index_weak(&self, index: &usize) -> Rc<RefCell<Self::Output>> {
-        self.objects.borrow()[*index].clone()
+    fn from(val: NetRef<I>) -> Self {
+        if val.is_multi_output() {
+            panic!("Cannot convert a multi-output netref to an output port");
+        }
+        DrivenNet::new(0, val)
     }
 }
 
-impl<I> Netlist<I>
+impl<I> From<&NetRef<I>> for DrivenNet<I>
 where
     I: Instantiable,
 {
-    /// Creates a new netlist with the given name
-    pub fn new(name: String) -> Rc<Self> {
-        Rc::new(Self {
-            name,
-            objects: RefCell::new(Vec::new()),
-            outputs: RefCell::new(HashMap::new()),
-        })
-    }
-
-    /// Attempts to reclaim the netlist, returning [Some] if successful.
-    pub fn reclaim(self: Rc<Self>) -> Option<Self> {
-        Rc::try_unwrap(self).ok()
+    fn from(val: &NetRef<I>) -> Self {
+        if val.is_multi_output() {
+            panic!("Cannot convert a multi-output netref to an output port");
+        }
+        DrivenNet::new(0, val.clone())
     }
+}
 
-    /// Use interior mutability to add an object to the netlist. Returns a mutable reference to the created object.
-    fn insert_object(
+/// Facilitates mutable borrows to driver nets
+pub struct MutBorrowReq<I: Instantiable> {
+    from: NetRef<I>,
+    ind: Net,
+}
+
+impl<I> MutBorrowReq<I>
+where
+    I: Instantiable,
+{
+    /// Creates a new mutable borrow request
+    fn new(from: NetRef<I>, ind: Net) -> Self {
+        Self { from, ind }
+    }
+
+    /// Mutably borrows the requested net from the circuit node
+    pub fn borrow_mut(&self) -> RefMut<'_, Net> {
+        self.from.find_net_mut(&self.ind).unwrap()
+    }
+
+    /// Returns `true` if the circuit node is an input
+    pub fn is_an_input(&self) -> bool {
+        self.from.is_an_input()
+    }
+
+    /// Attempts to borrow the net mutably if the condition `f` is satisfied.
+    pub fn borrow_mut_if(&self, f: impl Fn(&NetRef<I>) -> bool) -> Option<RefMut<'_, Net>> {
+        if f(&self.from) {
+            Some(self.borrow_mut())
+        } else {
+            None
+        }
+    }
+}
+
+/// Determines what happens to top-level outputs when the circuit node driving them is deleted
+#[derive(Debug, Clone)]
+pub enum OutputPolicy<I: Instantiable> {
+    /// Refuse to delete a node that drives a top-level output
+    Error,
+    /// Silently remove the top-level outputs driven by the node
+    Drop,
+    /// Rebind the top-level outputs to another driver, like a constant tie-off
+    Rebind(DrivenNet<I>),
+}
+
+/// What exposing a net under a new name does when it already drives a top-level output, as set by [Netlist::set_duplicate_output_policy]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DuplicateOutputPolicy {
+    /// The net drives only the new output, and the earlier one is removed from the module boundary
+    Rebind,
+    /// The new output is driven through an alias of the net, so both outputs are kept.
+    /// If the alias cannot be inserted, the new output is rejected.
+    /// This is the default, so no output is lost without asking for it.
+    #[default]
+    Alias,
+    /// The earlier output is kept, and the new one is reported by [Netlist::verify] and [Netlist::get_duplicate_outputs]
+    Reject,
+}
+
+/// The net `net` cannot drive the input port `port` of the instance `inst_name`, as reported by [NetlistError::TypeMismatch]
+#[derive(Debug, Clone)]
+pub struct TypeMismatch {
+    /// The name of the instance
+    pub inst_name: Identifier,
+    /// The driving net
+    pub net: Identifier,
+    /// The type of the driving net
+    pub driver: DataType,
+    /// The input port being driven
+    pub port: Identifier,
+    /// The type of the input port
+    pub sink: DataType,
+}
+
+/// An error from an operation that edits the netlist
+#[derive(Debug, Clone)]
+pub enum NetlistError<I: Instantiable> {
+    /// The instance `inst_name` was given the wrong number of operands
+    ArityMismatch {
+        /// The name of the instance being inserted
+        inst_name: Identifier,
+        /// The number of input ports on the instance
+        expected: usize,
+        /// The number of operands given
+        got: usize,
+    },
+    /// A net cannot drive the input port of an instance being inserted or connected
+    TypeMismatch(Box<TypeMismatch>),
+    /// The bus `operand` given for the instances `inst_name` is not as wide as the first bus
+    WidthMismatch {
+        /// The name the instances are inserted with
+        inst_name: Identifier,
+        /// The position of the bus among the operands
+        operand: usize,
+        /// The width of the first bus
+        expected: usize,
+        /// The width of the bus
+        got: usize,
+    },
+    /// The net is already driven by another circuit node in the netlist
+    DuplicateDriver(Identifier),
+    /// The instance name is already taken by another instance in the netlist
+    NameCollision(Identifier),
+    /// The instance name is escaped, or a bit-slice given to an array, so the names of its output nets cannot be derived from it
+    EscapedName(Identifier),
+    /// The circuit node cannot be removed, because references to it still exist
+    ReferencesExist(NetRef<I>),
+    /// The circuit node drives a top-level output
+    DrivesOutput(NetRef<I>),
+    /// The top-level outputs of a circuit node cannot be rebound to the node itself
+    RebindToSelf(NetRef<I>),
+    /// The top-level outputs of a circuit node cannot be rebound, because the new driver would drive more than one output
+    RebindTaken(NetRef<I>),
+    /// The circuit node is not an alias that can be collapsed into the net it aliases
+    CannotCollapse(NetRef<I>),
+    /// An analysis needed by the operation failed
+    Analysis(String),
+    /// Inserting an instance would exceed the [Budget] of the netlist
+    BudgetExceeded {
+        /// The number of instances there would be
+        instances: usize,
+        /// The total area there would be
+        area: f64,
+    },
+    /// The netlist that owned the circuit node was dropped
+    Unlinked,
+    /// The netlist was edited while locked with [Netlist::lock]
+    Locked(String),
+    /// The circuit node has several outputs, but the operation needs a single net
+    MultiOutput(NetRef<I>),
+    /// The circuit node is a principal input or alias, but the operation needs an instance
+    NotAnInstance(NetRef<I>),
+    /// A port of the circuit node was addressed past the number it has
+    OutOfBounds {
+        /// The circuit node
+        node: NetRef<I>,
+        /// The position addressed
+        index: usize,
+        /// The number of ports
+        len: usize,
+    },
+}
+
+impl<I> std::fmt::Display for NetlistError<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetlistError::ArityMismatch { expected, got, .. } => {
+                write!(f, "Expected {expected} operands, got {got}")
+            }
+            NetlistError::TypeMismatch(m) => write!(
+                f,
+                "Net {} of type {} cannot drive port {} of {} of type {}",
+                m.net, m.driver, m.port, m.inst_name, m.sink
+            ),
+            NetlistError::WidthMismatch {
+                operand,
+                expected,
+                got,
+                ..
+            } => write!(
+                f,
+                "Expected operand {operand} to be {expected} bits wide, got {got}"
+            ),
+            NetlistError::DuplicateDriver(id) => {
+                write!(f, "Net {id} is already driven in the netlist")
+            }
+            NetlistError::NameCollision(id) => {
+                write!(f, "Instance name {id} is already taken in the netlist")
+            }
+            NetlistError::EscapedName(id) => {
+                write!(f, "Cannot name the output nets of instance name {id}")
+            }
+            NetlistError::ReferencesExist(netref) => {
+                write!(
+                    f,
+                    "Cannot remove {netref}. References still exist on this node"
+                )
+            }
+            NetlistError::DrivesOutput(netref) => {
+                write!(f, "Cannot delete. {netref} drives a top-level output")
+            }
+            NetlistError::RebindToSelf(_) => {
+                write!(f, "Cannot rebind outputs to the node being deleted")
+            }
+            NetlistError::RebindTaken(netref) => write!(
+                f,
+                "Cannot rebind the outputs of {netref}. The new driver would drive more than one output"
+            ),
+            NetlistError::CannotCollapse(netref) => write!(
+                f,
+                "Cannot collapse {netref}. It is not a connected alias, or the net it aliases already drives an output"
+            ),
+            NetlistError::Analysis(e) => write!(f, "{e}"),
+            NetlistError::BudgetExceeded { instances, area } => write!(
+                f,
+                "Cannot insert. Budget exceeded with {instances} instances of area {area}"
+            ),
+            NetlistError::Unlinked => write!(f, "The netlist of the circuit node was dropped"),
+            NetlistError::Locked(name) => write!(f, "Netlist {name} is locked against edits"),
+            NetlistError::MultiOutput(netref) => {
+                write!(
+                    f,
+                    "{netref} has multiple outputs, but a single net is needed"
+                )
+            }
+            NetlistError::NotAnInstance(netref) => write!(f, "{netref} is not an instance"),
+            NetlistError::OutOfBounds { node, index, len } => {
+                write!(f, "Port {index} of {node} is out of bounds for {len} ports")
+            }
+        }
+    }
+}
+
+impl<I> std::error::Error for NetlistError<I> where I: Instantiable + std::fmt::Debug {}
+
+impl<I> From<NetlistError<I>> for String
+where
+    I: Instantiable,
+{
+    fn from(value: NetlistError<I>) -> Self {
+        value.to_string()
+    }
+}
+
+/// A bit of a port declared on the module boundary
+#[derive(Debug, Clone)]
+enum PortBit<I: Instantiable> {
+    /// The principal input object that provides this bit
+    Input(Weak<RefCell<OwnedObject<I, Netlist<I>>>>),
+    /// The name of the top-level output net
+    Output(Identifier),
+}
+
+/// A port declared on the module boundary
+#[derive(Debug, Clone)]
+struct PortDecl<I: Instantiable> {
+    /// The name of the port
+    name: Identifier,
+    /// The direction of the port
+    direction: PortDirection,
+    /// The bits of the port, lsb first
+    bits: Vec<PortBit<I>>,
+    /// A collection of attributes for the port
+    attributes: HashMap<AttributeKey, AttributeValue>,
+}
+
+impl<I> PortBit<I>
+where
+    I: Instantiable,
+{
+    /// Returns the bit index of the net behind this bit, if it is a bit-slice
+    fn get_bit_index(&self) -> Option<usize> {
+        match self {
+            PortBit::Input(weak) => weak
+                .upgrade()
+                .and_then(|o| o.borrow().as_net().get_identifier().get_bit_index()),
+            PortBit::Output(id) => id.get_bit_index(),
+        }
+    }
+}
+
+impl<I> PortDecl<I>
+where
+    I: Instantiable,
+{
+    /// Returns the name of the port that a net named `id` belongs to.
+    /// Bit-sliced nets like `a[3]` are grouped into the port `a`.
+    fn port_name(id: &Identifier) -> Identifier {
+        if id.is_sliced() {
+            id.get_name().into()
+        } else {
+            id.clone()
+        }
+    }
+}
+
+/// A port on the boundary of a netlist, with its bits resolved to nets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    name: Identifier,
+    direction: PortDirection,
+    nets: Vec<Net>,
+    attributes: Vec<Attribute>,
+}
+
+impl Port {
+    /// Returns the name of the port
+    pub fn get_name(&self) -> &Identifier {
+        &self.name
+    }
+
+    /// Returns the direction of the port
+    pub fn get_direction(&self) -> PortDirection {
+        self.direction
+    }
+
+    /// Returns the nets of the port, lsb first
+    pub fn get_nets(&self) -> &[Net] {
+        &self.nets
+    }
+
+    /// Returns the number of bits in the port
+    pub fn width(&self) -> usize {
+        self.nets.len()
+    }
+
+    /// Returns `true` if the port is a vector, i.e. every net is a bit-slice of the port name
+    pub fn is_vector(&self) -> bool {
+        self.nets.iter().all(|n| {
+            let id = n.get_identifier();
+            id.is_sliced() && id.get_name() == self.name.get_name()
+        })
+    }
+
+    /// Returns the most and least significant bit indices of a vector port
+    pub fn get_range(&self) -> Option<(usize, usize)> {
+        if !self.is_vector() {
+            return None;
+        }
+        let indices = self
+            .nets
+            .iter()
+            .filter_map(|n| n.get_identifier().get_bit_index());
+        Some((indices.clone().max()?, indices.min()?))
+    }
+
+    /// Returns an iterator to the attributes on the port
+    pub fn attributes(&self) -> impl Iterator<Item = &Attribute> {
+        self.attributes.iter()
+    }
+
+    /// Returns `true` if the port is an input
+    pub fn is_input(&self) -> bool {
+        self.direction == PortDirection::Input
+    }
+
+    /// Returns `true` if the port is an output
+    pub fn is_output(&self) -> bool {
+        self.direction == PortDirection::Output
+    }
+
+    /// Returns `true` if the port is bidirectional
+    pub fn is_inout(&self) -> bool {
+        self.direction == PortDirection::Inout
+    }
+}
+
+/// An array of instances of the same type named by index, like `u_fa[0]` to `u_fa[31]`, as found by [Netlist::instance_arrays]
+#[derive(Debug, Clone)]
+pub struct InstanceArray<I: Instantiable> {
+    /// The name of the array, like `u_fa`
+    name: Identifier,
+    /// The index of the first instance
+    lsb: usize,
+    /// The instances, lsb first
+    instances: Vec<NetRef<I>>,
+}
+
+impl<I> InstanceArray<I>
+where
+    I: Instantiable,
+{
+    /// Returns the name of the array, without an index
+    pub fn get_name(&self) -> &Identifier {
+        &self.name
+    }
+
+    /// Returns the instances of the array, lsb first
+    pub fn instances(&self) -> &[NetRef<I>] {
+        &self.instances
+    }
+
+    /// Returns the most and least significant indices of the array
+    pub fn get_range(&self) -> (usize, usize) {
+        (self.lsb + self.instances.len() - 1, self.lsb)
+    }
+
+    /// Returns the number of instances in the array
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Returns `true` if the array has no instances
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+}
+
+/// A difference between the ports of two netlists, as found by [Netlist::interface_matches]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The other netlist lacks the port
+    Missing(Identifier),
+    /// Only the other netlist has the port
+    Extra(Identifier),
+    /// The port has a different direction in the other netlist
+    Direction {
+        /// The name of the port
+        port: Identifier,
+        /// The direction in this netlist
+        expected: PortDirection,
+        /// The direction in the other netlist
+        found: PortDirection,
+    },
+    /// The port has a different width in the other netlist
+    Width {
+        /// The name of the port
+        port: Identifier,
+        /// The width in this netlist
+        expected: usize,
+        /// The width in the other netlist
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Missing(port) => write!(f, "Port {port} is missing"),
+            Mismatch::Extra(port) => write!(f, "Port {port} is unexpected"),
+            Mismatch::Direction {
+                port,
+                expected,
+                found,
+            } => write!(f, "Port {port} is an {found}, expected an {expected}"),
+            Mismatch::Width {
+                port,
+                expected,
+                found,
+            } => write!(f, "Port {port} has {found} bits, expected {expected}"),
+        }
+    }
+}
+
+/// The object found at the end of a [HierPath]
+#[derive(Debug, Clone)]
+pub enum PathTarget<I: Instantiable> {
+    /// An instance in the netlist
+    Instance(NetRef<I>),
+    /// A net driven in the netlist
+    Net(DrivenNet<I>),
+    /// The input port of an instance
+    InputPort(InputPort<I>),
+}
+
+/// Typed scratch data attached to the circuit nodes of a netlist, as returned by [Netlist::side_table].
+/// Entries are dropped when their circuit node is deleted from the netlist.
+pub struct SideTable<T> {
+    entries: HashMap<usize, T>,
+}
+
+impl<T> SideTable<T> {
+    /// Returns the data attached to `node`
+    pub fn get<I: Instantiable>(&self, node: &NetRef<I>) -> Option<&T> {
+        self.entries.get(&node.netref.borrow().get_index())
+    }
+
+    /// Returns the data attached to `node` mutably
+    pub fn get_mut<I: Instantiable>(&mut self, node: &NetRef<I>) -> Option<&mut T> {
+        self.entries.get_mut(&node.netref.borrow().get_index())
+    }
+
+    /// Attaches `value` to `node`, returning the data previously attached to it
+    pub fn insert<I: Instantiable>(&mut self, node: &NetRef<I>, value: T) -> Option<T> {
+        self.entries.insert(node.netref.borrow().get_index(), value)
+    }
+
+    /// Removes the data attached to `node`
+    pub fn remove<I: Instantiable>(&mut self, node: &NetRef<I>) -> Option<T> {
+        self.entries.remove(&node.netref.borrow().get_index())
+    }
+
+    /// Returns `true` if data is attached to `node`
+    pub fn contains<I: Instantiable>(&self, node: &NetRef<I>) -> bool {
+        self.entries.contains_key(&node.netref.borrow().get_index())
+    }
+
+    /// Returns the number of circuit nodes with data attached
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no circuit node has data attached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes the data attached to every circuit node
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for SideTable<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SideTable")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+/// A [SideTable] with its data type erased, so the netlist can keep it in sync with its objects
+trait AnySideTable: std::fmt::Debug {
+    /// Drops the entry for the object at `index`
+    fn forget(&mut self, index: usize);
+    /// Moves entries to the new object indices in `remap`, dropping those that are not in it
+    fn remap(&mut self, remap: &HashMap<usize, usize>);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: 'static> AnySideTable for SideTable<T> {
+    fn forget(&mut self, index: usize) {
+        self.entries.remove(&index);
+    }
+
+    fn remap(&mut self, remap: &HashMap<usize, usize>) {
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .filter_map(|(k, v)| remap.get(&k).map(|n| (*n, v)))
+            .collect();
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A guard taken by [Netlist::lock] that keeps the netlist from being edited until it is dropped
+#[derive(Debug)]
+#[must_use = "the netlist is unlocked as soon as the lock is dropped"]
+pub struct NetlistLock<'a, I: Instantiable> {
+    /// The locked netlist
+    netlist: &'a Netlist<I>,
+}
+
+impl<I> Drop for NetlistLock<'_, I>
+where
+    I: Instantiable,
+{
+    fn drop(&mut self) {
+        self.netlist.locks.set(self.netlist.locks.get() - 1);
+    }
+}
+
+/// A checkpoint of a netlist, taken by [Netlist::snapshot] and brought back with [Netlist::restore].
+/// Objects are held weakly, so a snapshot does not keep removed objects alive or stop [Netlist::clean].
+/// The state of each object is shared with the netlist until one of them is edited, so only edited objects are copied.
+#[derive(Debug)]
+pub struct Snapshot<I: Instantiable> {
+    /// The objects in order, with their state when the snapshot was taken
+    objects: Vec<SavedObject<I>>,
+    /// The outputs of the netlist
+    outputs: HashMap<Operand, Net>,
+    /// The ports on the module boundary
+    ports: Vec<PortDecl<I>>,
+    /// The outputs that were rejected, with the output that kept their net
+    rejected_outputs: Vec<(Identifier, Identifier)>,
+    /// The constraints on the values of the inputs
+    input_constraints: Vec<InputConstraint>,
+    /// Whether inserting an instance checks that its output nets are not already driven
+    check_drivers: bool,
+    /// What exposing a net that already drives a top-level output does
+    duplicate_outputs: DuplicateOutputPolicy,
+    /// Whether every net is declared to only carry 0 and 1
+    two_state: bool,
+}
+
+impl<I> Snapshot<I>
+where
+    I: Instantiable,
+{
+    /// Returns the number of objects in the snapshot
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns `true` if the snapshot has no objects
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// The state of an object saved in a [Snapshot]
+#[derive(Debug)]
+struct SavedObject<I: Instantiable> {
+    /// The object itself, if it is still alive
+    handle: Weak<RefCell<OwnedObject<I, Netlist<I>>>>,
+    object: Rc<Object<I>>,
+    owner: Weak<Netlist<I>>,
+    operands: Rc<Vec<Option<Operand>>>,
+    attributes: Rc<HashMap<AttributeKey, AttributeValue>>,
+    properties: Rc<Properties>,
+}
+
+/// Derives a plain name from `id` for naming new instances after it, like `a_3` for `a[3]`
+fn plain_name(id: &Identifier) -> String {
+    let name: String = id
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    name.trim_matches('_').to_string()
+}
+
+/// Returns the first name like `{stem}_0` for a new instance of `inst_type` that neither it nor its output nets share with a name in `used`,
+/// and adds them to `used`
+fn fresh_name<I: Instantiable>(
+    used: &mut HashSet<Identifier>,
+    stem: &str,
+    inst_type: &I,
+) -> Identifier {
+    let mut k = 0;
+    loop {
+        let name: Identifier = format!("{stem}_{k}").into();
+        k += 1;
+        let outputs: Vec<Identifier> = inst_type
+            .get_output_ports()
+            .into_iter()
+            .map(|p| format!("{name}_{}", p.get_identifier()).into())
+            .collect();
+        if !used.contains(&name) && outputs.iter().all(|o| !used.contains(o)) {
+            used.insert(name.clone());
+            used.extend(outputs);
+            return name;
+        }
+    }
+}
+
+/// A netlist data structure
+#[derive(Debug)]
+pub struct Netlist<I>
+where
+    I: Instantiable,
+{
+    /// The name of the netlist
+    name: String,
+    /// The list of objects in the netlist, such as inputs, modules, and primitives
+    objects: RefCell<Vec<NetRefT<I>>>,
+    /// The list of operands that point to objects which are outputs
+    outputs: RefCell<HashMap<Operand, Net>>,
+    /// Whether inserting an instance checks that its output nets are not already driven
+    check_drivers: Cell<bool>,
+    /// What exposing a net that already drives a top-level output does
+    duplicate_outputs: Cell<DuplicateOutputPolicy>,
+    /// The outputs that were rejected, with the output that kept their net
+    rejected_outputs: RefCell<Vec<(Identifier, Identifier)>>,
+    /// The ordered list of ports on the module boundary
+    ports: RefCell<Vec<PortDecl<I>>>,
+    /// The side tables of user data attached to objects, keyed by data type
+    side_tables: RefCell<HashMap<TypeId, Box<dyn AnySideTable>>>,
+    /// The current marking epoch. Objects are marked when their mark matches it.
+    epoch: Cell<usize>,
+    /// The optional ceiling on instance count and area
+    budget: RefCell<Option<Budget<I>>>,
+    /// The structural hash index of instances, built on first use
+    strash: RefCell<Option<StructIndex<I>>>,
+    /// The index of instances by type name, built on first use
+    type_index: RefCell<Option<TypeIndex<I>>>,
+    /// The constraints on the values of the inputs
+    input_constraints: RefCell<Vec<InputConstraint>>,
+    /// The number of [NetlistLock] guards held on the netlist
+    locks: Cell<usize>,
+    /// Whether every net is declared to only carry 0 and 1
+    two_state: Cell<bool>,
+    /// The journal of edits, if one was started
+    #[cfg(feature = "journal")]
+    journal: RefCell<Option<Journal<I>>>,
+}
+
+/// Represent the input port of a primitive
+#[derive(Debug, Clone)]
+pub struct InputPort<I: Instantiable> {
+    pos: usize,
+    netref: NetRef<I>,
+}
+
+impl<I> InputPort<I>
+where
+    I: Instantiable,
+{
+    fn new(pos: usize, netref: NetRef<I>) -> Self {
+        if pos >= netref.clone().unwrap().borrow().operands.len() {
+            panic!(
+                "Position {} out of bounds for netref with {} input nets",
+                pos,
+                netref.unwrap().borrow().get().get_nets().len()
+            );
+        }
+        Self { pos, netref }
+    }
+
+    /// Returns the net that is driving this input port
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference to the netlist is lost.
+    pub fn get_driver(&self) -> Option<DrivenNet<I>> {
+        match self.try_get_driver() {
+            Ok(driver) => driver,
+            Err(_) => panic!("Input port is unlinked from netlist"),
+        }
+    }
+
+    /// Returns the net that is driving this input port, or [NetlistError::Unlinked] if the netlist was dropped
+    pub fn try_get_driver(&self) -> Result<Option<DrivenNet<I>>, NetlistError<I>> {
+        if self.netref.is_an_input() {
+            panic!("Input port is not driven by a primitive");
+        }
+        if let Some(prev_operand) = self.netref.clone().unwrap().borrow().operands[self.pos].clone()
+        {
+            let netlist = self.netref.try_get_netlist()?;
+            let driver_nr = netlist.index_weak(&prev_operand.root());
+            let nr = NetRef::wrap(driver_nr);
+            let pos = prev_operand.secondary();
+            Ok(Some(DrivenNet::new(pos, nr)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns the index of this input port on the instance
+    pub fn get_position(&self) -> usize {
+        self.pos
+    }
+
+    /// Disconnects an input port and returns the previous [DrivenNet] if it was connected,
+    /// or returns [NetlistError::Locked] if the netlist is locked.
+    pub fn try_disconnect(&self) -> Result<Option<DrivenNet<I>>, NetlistError<I>> {
+        self.netref.check_owner_unlocked()?;
+        Ok(self.disconnect())
+    }
+
+    /// Disconnects an input port and returns the previous [DrivenNet] if it was connected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the netlist is locked, see [InputPort::try_disconnect].
+    pub fn disconnect(&self) -> Option<DrivenNet<I>> {
+        if let Some(netlist) = self.netref.netref.borrow().owner.upgrade() {
+            netlist.assert_unlocked();
+        }
+        let val = self.get_driver();
+        self.netref.clone().unwrap().borrow_mut().operands_mut()[self.pos] = None;
+        #[cfg(feature = "journal")]
+        if val.is_some() {
+            let owned = self.netref.clone().unwrap();
+            if let Some(netlist) = owned.borrow().owner.upgrade() {
+                netlist.record(|| Edit::Disconnect {
+                    node: journal::object_name(owned.borrow().get()),
+                    port: self.pos,
+                });
+            }
+        }
+        val
+    }
+
+    /// Get the input port associated with this connection.
+    /// The input port of an alias is named `A`.
+    pub fn get_port(&self) -> Net {
+        if self.netref.is_an_input() {
+            panic!("Net is not driven by a primitive");
+        }
+        if self.netref.is_an_alias() {
+            return Net::new_logic(ALIAS_INPUT.into());
+        }
+        self.netref
+            .get_instance_type()
+            .unwrap()
+            .get_input_port(self.pos)
+            .clone()
+    }
+
+    /// Connects this input port to a driven net.
+    pub fn connect(self, output: DrivenNet<I>) {
+        output.connect(self);
+    }
+
+    /// Return the underlying circuit node
+    pub fn unwrap(self) -> NetRef<I> {
+        self.netref
+    }
+}
+
+impl<I> std::fmt::Display for InputPort<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.get_port().fmt(f)
+    }
+}
+
+/// Represent a net that is being driven by a [Instantiable]
+#[derive(Debug, Clone)]
+pub struct DrivenNet<I: Instantiable> {
+    pos: usize,
+    netref: NetRef<I>,
+}
+
+impl<I> DrivenNet<I>
+where
+    I: Instantiable,
+{
+    fn new(pos: usize, netref: NetRef<I>) -> Self {
+        if pos >= netref.clone().unwrap().borrow().get().get_nets().len() {
+            panic!(
+                "Position {} out of bounds for netref with {} outputted nets",
+                pos,
+                netref.unwrap().borrow().get().get_nets().len()
+            );
+        }
+        Self { pos, netref }
+    }
+
+    /// Returns the index that can address this net in the netlist.
+    fn get_operand(&self) -> Operand {
+        if self.netref.is_multi_output() {
+            Operand::CellIndex(self.netref.clone().unwrap().borrow().get_index(), self.pos)
+        } else {
+            Operand::DirectIndex(self.netref.clone().unwrap().borrow().get_index())
+        }
+    }
+
+    /// Borrow the net being driven
+    pub fn as_net(&self) -> Ref<'_, Net> {
+        self.netref.get_net(self.pos)
+    }
+
+    /// Get a mutable reference to the net being driven
+    pub fn as_net_mut(&self) -> RefMut<'_, Net> {
+        self.netref.get_net_mut(self.pos)
+    }
+
+    /// Returns `true` if this net is a principal input
+    pub fn is_an_input(&self) -> bool {
+        self.netref.is_an_input()
+    }
+
+    /// Returns `true` if this net is driven by an alias
+    pub fn is_an_alias(&self) -> bool {
+        self.netref.is_an_alias()
+    }
+
+    /// Returns the supply this net carries, if its driver is marked as power or ground
+    pub fn get_supply(&self) -> Option<Supply> {
+        self.netref.get_supply()
+    }
+
+    /// Returns the wire resistance and capacitance of this net, if they were annotated
+    pub fn get_parasitics(&self) -> Option<Parasitics> {
+        let owned = self.netref.netref.borrow();
+        owned.properties.parasitics.get(&self.pos).copied()
+    }
+
+    /// Annotates this net with its wire resistance and capacitance, like from extraction after layout, or clears them with [None].
+    /// The parasitics are kept with the driving node, apart from its attributes.
+    pub fn set_parasitics(&self, parasitics: Option<Parasitics>) {
+        self.netref.assert_owner_unlocked();
+        let mut owned = self.netref.netref.borrow_mut();
+        match parasitics {
+            Some(p) => owned.properties_mut().parasitics.insert(self.pos, p),
+            None => owned.properties_mut().parasitics.remove(&self.pos),
+        };
+    }
+
+    /// Returns the strength this net is driven with, if it was set. Nets without a strength are driven strongly,
+    /// unless they are supplies (see [NetRef::set_supply]).
+    pub fn get_drive_strength(&self) -> Option<Strength> {
+        let owned = self.netref.netref.borrow();
+        owned.properties.drive_strengths.get(&self.pos).copied()
+    }
+
+    /// Returns the strength this net is driven with: the strength it was set to, else [Strength::Supply] for a supply and [Strength::Strong] otherwise
+    pub fn drive_strength(&self) -> Strength {
+        self.get_drive_strength()
+            .unwrap_or(match self.get_supply() {
+                Some(_) => Strength::Supply,
+                None => Strength::Strong,
+            })
+    }
+
+    /// Sets the strength this net is driven with, like [Strength::Pull] for a pull-up on a wired-AND, or clears it with [None].
+    /// Where a net of an `inout` port has many drivers (see [Netlist::drive_inout]), the strongest drivers set its value.
+    pub fn set_drive_strength(&self, strength: Option<Strength>) {
+        self.netref.assert_owner_unlocked();
+        let mut owned = self.netref.netref.borrow_mut();
+        match strength {
+            Some(s) => owned.properties_mut().drive_strengths.insert(self.pos, s),
+            None => owned.properties_mut().drive_strengths.remove(&self.pos),
+        };
+    }
+
+    /// Get the output port associated with this connection.
+    /// The output port of an alias is named `Y`.
+    pub fn get_port(&self) -> Net {
+        if self.netref.is_an_input() {
+            panic!("Net is not driven by a primitive");
+        }
+        if self.netref.is_an_alias() {
+            return Net::new_logic(ALIAS_OUTPUT.into());
+        }
+        self.netref
+            .get_instance_type()
+            .unwrap()
+            .get_output_port(self.pos)
+            .clone()
+    }
+
+    /// Connects the net driven by this output port to the given input port.
+    /// The types of the net and the port are not checked, so connecting cannot fail, and passes that move loads between nets
+    /// of the same type, like buffering or cloning, need not handle an error. Use [DrivenNet::try_connect] to check a connection
+    /// as it is made, and [Netlist::verify] to check every connection after an edit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the netlist is locked, see [DrivenNet::try_connect].
+    pub fn connect(&self, input: InputPort<I>) {
+        let operand = self.get_operand();
+        let index = input.netref.unwrap().borrow().get_index();
+        let netlist = self
+            .netref
+            .clone()
+            .unwrap()
+            .borrow()
+            .owner
+            .upgrade()
+            .expect("Output port is unlinked from netlist");
+        netlist.assert_unlocked();
+        let obj = netlist.index_weak(&index);
+        obj.borrow_mut().operands_mut()[input.pos] = Some(operand.clone());
+        #[cfg(feature = "journal")]
+        netlist.record(|| Edit::Connect {
+            driver: self.get_identifier(),
+            node: journal::object_name(obj.borrow().get()),
+            port: input.pos,
+        });
+    }
+
+    /// Connects the net driven by this output port to the given input port, like [DrivenNet::connect],
+    /// but first checks that the type of the net can drive the type of the port (see [DataType::can_drive]).
+    /// The input of an alias takes any type. Returns [NetlistError::Locked] if the netlist is locked.
+    pub fn try_connect(&self, input: InputPort<I>) -> Result<(), NetlistError<I>> {
+        self.netref.try_get_netlist()?.check_unlocked()?;
+        // The input of an alias has no instance name
+        if let Some(inst_name) = input.netref.get_instance_name() {
+            let driver = *self.as_net().get_type();
+            let port = input.get_port();
+            if !driver.can_drive(port.get_type()) {
+                return Err(NetlistError::TypeMismatch(Box::new(TypeMismatch {
+                    inst_name,
+                    net: self.get_identifier(),
+                    driver,
+                    port: port.get_identifier().clone(),
+                    sink: *port.get_type(),
+                })));
+            }
+        }
+        self.connect(input);
+        Ok(())
+    }
+
+    /// Returns `true` if this net is a top-level output in the netlist.
+    /// A net whose netlist was dropped is not an output.
+    pub fn is_top_level_output(&self) -> bool {
+        let Ok(netlist) = self.netref.try_get_netlist() else {
+            return false;
+        };
+        let outputs = netlist.outputs.borrow();
+        outputs.contains_key(&self.get_operand())
+    }
+
+    /// Return the underlying circuit node
+    pub fn unwrap(self) -> NetRef<I> {
+        self.netref
+    }
+
+    /// Returns the index of this output on the circuit node
+    pub fn get_position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns a copy of the identifier of the net being driven.
+    pub fn get_identifier(&self) -> Identifier {
+        self.as_net().get_identifier().clone()
+    }
+
+    /// Splits the loads of this net into groups, driving each group through its own buffer.
+    /// Each group is given as the instance name of its buffer and the input ports it should drive.
+    /// Returns the inserted buffers in the order of `groups`, or an error if the net is a supply.
+    /// The netlist is only changed if every buffer can be inserted.
+    pub fn split_loads(
+        &self,
+        buffer: I,
+        groups: Vec<(Identifier, Vec<InputPort<I>>)>,
+    ) -> Result<Vec<NetRef<I>>, String> {
+        let ninputs = buffer.get_input_ports().into_iter().count();
+        let noutputs = buffer.get_output_ports().into_iter().count();
+        if ninputs != 1 || noutputs != 1 {
+            return Err(format!(
+                "Buffer {} must have exactly one input and one output",
+                buffer.get_name()
+            ));
+        }
+        if let Some(supply) = self.get_supply() {
+            return Err(format!(
+                "Net {self} is a {supply} supply and is not buffered"
+            ));
+        }
+        let operand = self.get_operand();
+        for (_, ports) in groups.iter() {
+            for port in ports {
+                if port.get_driver().map(|d| d.get_operand()) != Some(operand.clone()) {
+                    return Err(format!("Input port {port} is not a load of net {self}"));
+                }
+            }
+        }
+
+        let netlist = self.netref.try_get_netlist()?;
+        netlist.check_unlocked()?;
+        let mut buffers = Vec::new();
+        for (name, _) in &groups {
+            match netlist.insert_gate(buffer.clone(), name.clone(), std::slice::from_ref(self)) {
+                Ok(buf) => buffers.push(buf),
+                Err(e) => {
+                    netlist.roll_back(buffers)?;
+                    return Err(e.into());
+                }
+            }
+        }
+        for (buf, (_, ports)) in buffers.iter().zip(groups) {
+            let buf_out = buf.get_output(0);
+            for port in ports {
+                buf_out.connect(port);
+            }
+        }
+        Ok(buffers)
+    }
+
+    /// Splits the loads of this net into groups, driving each group from its own copy of the instance that drives the net.
+    /// Each group is given as the instance name of its copy and the input ports it should drive. Copies read the same nets
+    /// and carry the same attributes as the instance, which keeps its other loads and the top-level outputs it drives.
+    /// Returns the copies in the order of `groups`, or an error if the net is not driven by a single-output instance.
+    /// The netlist is only changed if every copy can be inserted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weak reference to the netlist is dead.
+    pub fn clone_for_loads(
+        &self,
+        groups: Vec<(Identifier, Vec<InputPort<I>>)>,
+    ) -> Result<Vec<NetRef<I>>, String> {
+        let node = self.netref.clone();
+        let Some(inst_type) = node.get_instance_type().map(|t| t.clone()) else {
+            return Err(format!("Net {self} is not driven by an instance"));
+        };
+        if node.is_multi_output() {
+            return Err(format!("Net {self} is driven by a multi-output instance"));
+        }
+        let operand = self.get_operand();
+        for (_, ports) in groups.iter() {
+            for port in ports {
+                if port.get_driver().map(|d| d.get_operand()) != Some(operand.clone()) {
+                    return Err(format!("Input port {port} is not a load of net {self}"));
+                }
+            }
+        }
+        let mut operands = Vec::new();
+        for (i, driver) in node.operands() {
+            operands.push(
+                driver.ok_or_else(|| {
+                    format!("Input {} of {node} is unconnected", node.get_input(i))
+                })?,
+            );
+        }
+
+        let netlist = node.try_get_netlist()?;
+        netlist.check_unlocked()?;
+        let mut copies = Vec::new();
+        for (name, _) in &groups {
+            match netlist.insert_gate(inst_type.clone(), name.clone(), &operands) {
+                Ok(copy) => copies.push(copy),
+                Err(e) => {
+                    netlist.roll_back(copies)?;
+                    return Err(e.into());
+                }
+            }
+        }
+        for (copy, (_, ports)) in copies.iter().zip(groups) {
+            copy.copy_annotations_from(&node);
+            let copy_out = copy.get_output(0);
+            for port in ports {
+                copy_out.connect(port);
+            }
+        }
+        Ok(copies)
+    }
+
+    /// Returns the full hierarchical path to this net.
+    /// Nets driven by instances are named by their output port, like `top/inst/Y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weak reference to the netlist is dead.
+    pub fn get_path(&self) -> HierPath {
+        if self.is_an_input() || self.netref.is_an_alias() {
+            self.netref.get_path()
+        } else {
+            self.netref
+                .get_path()
+                .join(self.get_port().take_identifier())
+        }
+    }
+
+    /// Expose this driven net as a module output
+    ///
+    /// # Panics
+    ///
+    /// Panics if the weak reference to the netlist is dead.
+    pub fn expose_with_name(self, name: Identifier) -> Self {
+        let netlist = self
+            .netref
+            .clone()
+            .unwrap()
+            .borrow()
+            .owner
+            .upgrade()
+            .expect("DrivenNet is unlinked from netlist");
+        netlist.expose_net_with_name(self.clone(), name);
+        self
+    }
+
+    /// Exposes this driven net as a module output, like [DrivenNet::expose_with_name].
+    /// Returns [NetlistError::Unlinked] if the netlist was dropped, or [NetlistError::Locked] if it is locked.
+    pub fn try_expose_with_name(self, name: Identifier) -> Result<Self, NetlistError<I>> {
+        let netlist = self.netref.try_get_netlist()?;
+        netlist.try_expose_net_with_name(self.clone(), name)?;
+        Ok(self)
+    }
+
+    /// Changes the name of this driven net, which may be any output of its circuit node, like [NetRef::set_identifier].
+    /// Returns [NetlistError::Locked] if the netlist is locked.
+    pub fn try_set_identifier(&self, identifier: Identifier) -> Result<(), NetlistError<I>> {
+        self.netref.check_owner_unlocked()?;
+        #[cfg(feature = "journal")]
+        self.netref.record(|| Edit::RenameNet {
+            from: self.get_identifier(),
+            to: identifier.clone(),
+        });
+        self.as_net_mut().set_identifier(identifier);
+        Ok(())
+    }
+}
+
+impl<I> std::fmt::Display for DrivenNet<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_net().fmt(f)
+    }
+}
+
+impl<I> WeakIndex<usize> for Netlist<I>
+where
+    I: Instantiable,
+{
+    type Output = OwnedObject<I, Self>;
+
+    fn index_weak(&self, index: &usize) -> Rc<RefCell<Self::Output>> {
+        self.objects.borrow()[*index].clone()
+    }
+}
+
+impl<I> Netlist<I>
+where
+    I: Instantiable,
+{
+    /// Creates a new netlist with the given name
+    pub fn new(name: String) -> Rc<Self> {
+        Rc::new(Self {
+            name,
+            objects: RefCell::new(Vec::new()),
+            outputs: RefCell::new(HashMap::new()),
+            check_drivers: Cell::new(false),
+            duplicate_outputs: Cell::new(DuplicateOutputPolicy::default()),
+            rejected_outputs: RefCell::new(Vec::new()),
+            ports: RefCell::new(Vec::new()),
+            side_tables: RefCell::new(HashMap::new()),
+            epoch: Cell::new(1),
+            budget: RefCell::new(None),
+            strash: RefCell::new(None),
+            type_index: RefCell::new(None),
+            input_constraints: RefCell::new(Vec::new()),
+            locks: Cell::new(0),
+            two_state: Cell::new(false),
+            #[cfg(feature = "journal")]
+            journal: RefCell::new(None),
+        })
+    }
+
+    /// Returns the instances of the primitive named `type_name`, in insertion order.
+    /// The instances are found through an index, so this does not scan the netlist.
+    pub fn instances_of(&self, type_name: &Identifier) -> Vec<NetRef<I>> {
+        if self.type_index.borrow().is_none() {
+            let mut index = TypeIndex::new();
+            for obj in self.objects.borrow().iter() {
+                if let Some(inst_type) = obj.borrow().get().get_instance_type() {
+                    let entry: &mut Vec<_> = index.entry(inst_type.get_name().clone()).or_default();
+                    entry.push(Rc::downgrade(obj));
+                }
+            }
+            self.type_index.replace(Some(index));
+        }
+        self.type_index
+            .borrow()
+            .as_ref()
+            .and_then(|index| index.get(type_name))
+            .into_iter()
+            .flatten()
+            .filter_map(|weak| weak.upgrade().map(NetRef::wrap))
+            .collect()
+    }
+
+    /// Adds `obj` to the index of instances by type name, if the index has been built
+    fn index_type(&self, obj: &NetRefT<I>) {
+        if let Some(index) = self.type_index.borrow_mut().as_mut()
+            && let Some(inst_type) = obj.borrow().get().get_instance_type()
+        {
+            index
+                .entry(inst_type.get_name().clone())
+                .or_default()
+                .push(Rc::downgrade(obj));
+        }
+    }
+
+    /// Returns the structural key of an instance of `inst_type` driven by `operands`
+    fn struct_key(inst_type: &I, operands: Vec<Operand>) -> StructKey {
+        let params = inst_type
+            .parameters()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        (inst_type.get_name().clone(), params, operands)
+    }
+
+    /// Returns the structural key of `obj`, if it is a fully connected instance
+    fn struct_key_of(obj: &OwnedObject<I, Self>) -> Option<StructKey> {
+        let inst_type = obj.get().get_instance_type()?;
+        let operands = obj.operands.iter().cloned().collect::<Option<Vec<_>>>()?;
+        Some(Self::struct_key(inst_type, operands))
+    }
+
+    /// Adds `obj` to the structural hash index, if the index has been built
+    fn index_structure(&self, obj: &NetRefT<I>) {
+        if let Some(index) = self.strash.borrow_mut().as_mut()
+            && let Some(key) = Self::struct_key_of(&obj.borrow())
+        {
+            index.entry(key).or_insert_with(|| Rc::downgrade(obj));
+        }
+    }
+
+    /// Ties every unconnected input port that has a default value (see [Instantiable::get_input_default]) to a constant.
+    /// `tie` returns the constant cell for a value. Constant cells are shared and named like `tie_1`, skipping names already in use.
+    /// Returns the number of input ports that were tied.
+    pub fn tie_unconnected_inputs(
+        self: &Rc<Self>,
+        tie: impl Fn(bool) -> I,
+    ) -> Result<usize, NetlistError<I>> {
+        self.check_unlocked()?;
+        let mut unconnected = Vec::new();
+        for obj in self.objects() {
+            let Some(inst_type) = obj.get_instance_type() else {
+                continue;
+            };
+            for (i, driver) in obj.operands() {
+                if driver.is_none()
+                    && let Some(value) = inst_type.get_input_default(i)
+                {
+                    unconnected.push((obj.get_input(i), value));
+                }
+            }
+        }
+
+        let count = unconnected.len();
+        let mut constants: HashMap<bool, DrivenNet<I>> = HashMap::new();
+        for (input, value) in unconnected {
+            let constant = match constants.get(&value) {
+                Some(constant) => constant.clone(),
+                None => self.find_or_insert_tie(tie(value), value)?,
+            };
+            input.connect(constant.clone());
+            constants.insert(value, constant);
+        }
+        Ok(count)
+    }
+
+    /// Returns the output of the constant cell `inst_type` driving `value`, inserting it if the netlist has none.
+    /// A new cell is named like `tie_1`, or like `tie_1_0` if that name is already in use.
+    pub(crate) fn find_or_insert_tie(
+        self: &Rc<Self>,
+        inst_type: I,
+        value: bool,
+    ) -> Result<DrivenNet<I>, NetlistError<I>> {
+        let stem = format!("tie_{}", value as u8);
+        let mut used = self.used_names();
+        let taken = |name: &str| {
+            used.contains(&name.into())
+                || inst_type
+                    .get_output_ports()
+                    .into_iter()
+                    .any(|p| used.contains(&format!("{name}_{}", p.get_identifier()).into()))
+        };
+        let name = match taken(&stem) {
+            true => fresh_name(&mut used, &stem, &inst_type),
+            false => stem.into(),
+        };
+        Ok(self
+            .find_or_insert_gate(inst_type, name, &[])?
+            .get_output(0))
+    }
+
+    /// Returns an instance of `inst_type` driven by `operands` if one already exists, and otherwise inserts one named `inst_name`.
+    /// Existing instances are found through a structural hash index, so generators can share logic without searching the netlist.
+    pub fn find_or_insert_gate(
+        self: &Rc<Self>,
+        inst_type: I,
+        inst_name: Identifier,
+        operands: &[DrivenNet<I>],
+    ) -> Result<NetRef<I>, NetlistError<I>> {
+        let key = Self::struct_key(
+            &inst_type,
+            operands.iter().map(|o| o.get_operand()).collect(),
+        );
+        if self.strash.borrow().is_none() {
+            let mut index = StructIndex::new();
+            for obj in self.objects.borrow().iter() {
+                if let Some(key) = Self::struct_key_of(&obj.borrow()) {
+                    index.entry(key).or_insert_with(|| Rc::downgrade(obj));
+                }
+            }
+            self.strash.replace(Some(index));
+        }
+
+        // Entries may be stale if the instance was edited in place, so check the structure again
+        let found = self
+            .strash
+            .borrow()
+            .as_ref()
+            .and_then(|index| index.get(&key))
+            .and_then(|weak| weak.upgrade());
+        if let Some(obj) = found
+            && Self::struct_key_of(&obj.borrow()).as_ref() == Some(&key)
+        {
+            return Ok(NetRef::wrap(obj));
+        }
+
+        let netref = self.insert_gate(inst_type, inst_name, operands)?;
+        if let Some(index) = self.strash.borrow_mut().as_mut() {
+            index.insert(key, Rc::downgrade(&netref.netref));
+        }
+        Ok(netref)
+    }
+
+    /// Tracks the instances of the netlist against `budget`, starting from the instances already in it.
+    /// Inserting an instance that would exceed the budget fails with [NetlistError::BudgetExceeded].
+    /// Edits made in place to instance types are not tracked, so set the budget again to recount after them.
+    pub fn set_budget(&self, mut budget: Budget<I>) {
+        let nodes: Vec<NetRef<I>> = self.objects().collect();
+        budget.recount(nodes.iter().filter_map(|o| o.get_instance_type()));
+        self.budget.replace(Some(budget));
+    }
+
+    /// Declares a constraint on the values of the inputs, which [crate::aig::Aig::from_netlist] carries over.
+    /// Returns an error if the constraint is on a net that is not an input.
+    pub fn constrain_inputs(&self, constraint: InputConstraint) -> Result<(), String> {
+        for name in constraint.inputs() {
+            if !self.inputs().any(|i| i.get_identifier() == *name) {
+                return Err(format!("{name} is not an input of {}", self.get_name()));
+            }
+        }
+        self.input_constraints.borrow_mut().push(constraint);
+        Ok(())
+    }
+
+    /// Returns the constraints on the values of the inputs
+    pub fn get_input_constraints(&self) -> Vec<InputConstraint> {
+        self.input_constraints.borrow().clone()
+    }
+
+    /// Removes the constraints on the values of the inputs, returning them
+    pub fn clear_input_constraints(&self) -> Vec<InputConstraint> {
+        self.input_constraints.take()
+    }
+
+    /// Stops tracking the budget, returning it if one was set
+    pub fn take_budget(&self) -> Option<Budget<I>> {
+        self.budget.take()
+    }
+
+    /// Returns the budget being tracked, if one was set
+    pub fn get_budget(&self) -> Option<Ref<'_, Budget<I>>> {
+        Ref::filter_map(self.budget.borrow(), |b| b.as_ref()).ok()
+    }
+
+    /// Starts recording the edits made to the netlist by `author`, replacing any journal already started
+    #[cfg(feature = "journal")]
+    pub fn start_journal(&self, author: String) {
+        self.journal.replace(Some(Journal::new(author)));
+    }
+
+    /// Sets who the next edits are made by, if a journal was started
+    #[cfg(feature = "journal")]
+    pub fn set_journal_author(&self, author: String) {
+        if let Some(journal) = self.journal.borrow_mut().as_mut() {
+            journal.set_author(author);
+        }
+    }
+
+    /// Stops recording edits, returning the journal if one was started
+    #[cfg(feature = "journal")]
+    pub fn take_journal(&self) -> Option<Journal<I>> {
+        self.journal.take()
+    }
+
+    /// Returns the journal being recorded, if one was started
+    #[cfg(feature = "journal")]
+    pub fn get_journal(&self) -> Option<Ref<'_, Journal<I>>> {
+        Ref::filter_map(self.journal.borrow(), |j| j.as_ref()).ok()
+    }
+
+    /// Records the edit made by `edit` in the journal, if one was started
+    #[cfg(feature = "journal")]
+    fn record(&self, edit: impl FnOnce() -> Edit<I>) {
+        if let Some(journal) = self.journal.borrow_mut().as_mut() {
+            journal.push(edit());
+        }
+    }
+
+    /// Returns the node named `name` in a journal, for replaying edits
+    #[cfg(feature = "journal")]
+    fn journal_node(&self, name: &Identifier) -> Result<NetRef<I>, String> {
+        self.objects()
+            .find(|n| journal::object_name(n.netref.borrow().get()) == *name)
+            .ok_or(format!("No node named {name}"))
+    }
+
+    /// Returns the net named `name`, for replaying edits
+    #[cfg(feature = "journal")]
+    fn journal_net(&self, name: &Identifier) -> Result<DrivenNet<I>, String> {
+        self.objects()
+            .flat_map(|n| n.outputs().collect::<Vec<_>>())
+            .find(|n| n.get_identifier() == *name)
+            .ok_or(format!("No net named {name}"))
+    }
+
+    /// Returns input `port` of the node named `name`, for replaying edits
+    #[cfg(feature = "journal")]
+    fn journal_input(&self, name: &Identifier, port: usize) -> Result<InputPort<I>, String> {
+        let node = self.journal_node(name)?;
+        if port >= node.get_num_input_ports() {
+            return Err(format!("{name} has no input {port}"));
+        }
+        Ok(node.get_input(port))
+    }
+
+    /// Makes `edit` from a [Journal] on this netlist
+    #[cfg(feature = "journal")]
+    pub(crate) fn replay_edit(self: &Rc<Self>, edit: &Edit<I>) -> Result<(), String> {
+        match edit {
+            Edit::InsertInput {
+                net,
+                port,
+                direction,
+            } => {
+                self.insert_port_bit(net.clone(), port.clone(), *direction);
+            }
+            Edit::InsertInstance {
+                name,
+                inst_type,
+                operands,
+            } => {
+                let drivers = operands
+                    .iter()
+                    .flatten()
+                    .map(|op| self.journal_net(op))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if drivers.len() == operands.len() {
+                    self.insert_gate(inst_type.clone(), name.clone(), &drivers)?;
+                } else if drivers.is_empty() {
+                    self.insert_gate_disconnected(inst_type.clone(), name.clone())?;
+                } else {
+                    return Err(format!("{name} was inserted partially connected"));
+                }
+            }
+            Edit::InsertAlias { net, driver } => match driver {
+                Some(driver) => {
+                    let driver = self.journal_net(driver)?;
+                    self.insert_alias(driver, net.get_identifier().clone())?;
+                }
+                None => {
+                    self.insert_alias_disconnected(net.clone());
+                }
+            },
+            Edit::Connect { driver, node, port } => {
+                let input = self.journal_input(node, *port)?;
+                self.journal_net(driver)?.connect(input);
+            }
+            Edit::Disconnect { node, port } => {
+                self.journal_input(node, *port)?.disconnect();
+            }
+            Edit::Expose { driver, name } => {
+                self.expose_net_with_name(self.journal_net(driver)?, name.clone());
+            }
+            Edit::DeleteUses { node, rebind } => {
+                let policy = match rebind {
+                    Some(net) => OutputPolicy::Rebind(self.journal_net(net)?),
+                    None => OutputPolicy::Drop,
+                };
+                self.delete_net_uses_with(self.journal_node(node)?, policy)?;
+            }
+            Edit::ReplaceUses { of, with } => {
+                let with = self.journal_node(with)?;
+                self.replace_net_uses(self.journal_node(of)?, &with)?;
+            }
+            Edit::BypassAlias(alias) => {
+                self.bypass_alias(&self.journal_node(alias)?)
+                    .ok_or(format!("Cannot bypass alias {alias}"))?;
+            }
+            Edit::ExtendInstance { name, inst_type } => {
+                self.extend_instance(&self.journal_node(name)?, inst_type.clone())?;
+            }
+            Edit::Remove(name) => {
+                let index = self.journal_node(name)?.netref.borrow().index;
+                self.remove_objects(&HashSet::from([index]))?;
+            }
+            Edit::SetAttribute { node, key, value } => {
+                let node = self.journal_node(node)?;
+                match value {
+                    Some(value) => {
+                        node.try_insert_attribute(key.clone(), value.clone())?;
+                    }
+                    None => node.try_set_attribute(key.clone())?,
+                }
+            }
+            Edit::ClearAttribute { node, key } => {
+                self.journal_node(node)?.try_clear_attribute(key)?;
+            }
+            Edit::RenameInstance { from, to } => {
+                self.journal_node(from)?.try_set_instance_name(to.clone())?;
+            }
+            Edit::RenameNet { from, to } => {
+                self.journal_net(from)?.try_set_identifier(to.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a new instance of `inst_type` against the budget, if one is set
+    fn charge_budget(&self, inst_type: &I) -> Result<(), NetlistError<I>> {
+        match self.budget.borrow_mut().as_mut() {
+            Some(budget) => budget.charge(inst_type),
+            None => Ok(()),
+        }
+    }
+
+    /// Unmarks every circuit node in the netlist. This operation is O(1).
+    pub fn clear_marks(&self) {
+        self.epoch.set(self.epoch.get() + 1);
+    }
+
+    /// Returns the side table holding data of type `T` for the circuit nodes of this netlist, creating it if needed.
+    /// Only one side table can be borrowed at a time.
+    pub fn side_table<T: 'static>(&self) -> RefMut<'_, SideTable<T>> {
+        RefMut::map(self.side_tables.borrow_mut(), |tables| {
+            tables
+                .entry(TypeId::of::<T>())
+                .or_insert_with(|| Box::new(SideTable::<T>::default()))
+                .as_any_mut()
+                .downcast_mut::<SideTable<T>>()
+                .expect("Side table is keyed by its data type")
+        })
+    }
+
+    /// Removes the side table holding data of type `T`, returning it if it exists
+    pub fn take_side_table<T: 'static>(&self) -> Option<SideTable<T>> {
+        let table = self.side_tables.borrow_mut().remove(&TypeId::of::<T>())?;
+        table.into_any().downcast::<SideTable<T>>().ok().map(|t| *t)
+    }
+
+    /// Drops the side table entries for the object at `index`
+    fn forget_side_entries(&self, index: usize) {
+        for table in self.side_tables.borrow_mut().values_mut() {
+            table.forget(index);
+        }
+    }
+
+    /// Sets whether inserting an instance rejects instance names that are already taken and output nets whose names are already driven in the netlist.
+    /// This check is O(n) for every insertion, so it is disabled by default.
+    pub fn set_check_drivers(&self, check: bool) {
+        self.check_drivers.set(check);
+    }
+
+    /// Sets what exposing a net under a new name does when the net already drives a top-level output
+    pub fn set_duplicate_output_policy(&self, policy: DuplicateOutputPolicy) {
+        self.duplicate_outputs.set(policy);
+    }
+
+    /// Declares whether every net of the netlist only carries 0 and 1, never `x` or `z`.
+    /// In two-state mode, the netlist can be simulated 64 patterns at a time with [crate::sim::PackedSimulator],
+    /// and its faults with [crate::fault::simulate_faults_packed], while [crate::sim::Simulator] rejects unknown input values.
+    /// The flag is a declaration that is not checked against the netlist: packed simulation leaves out registers, drive strengths,
+    /// and the resolution of `inout` ports, and an uninitialized register still starts at `x` in [crate::sim::Simulator].
+    pub fn set_two_state(&self, two_state: bool) {
+        self.two_state.set(two_state);
+    }
+
+    /// Returns `true` if the netlist is declared two-state with [Netlist::set_two_state]
+    pub fn is_two_state(&self) -> bool {
+        self.two_state.get()
+    }
+
+    /// Returns the outputs rejected by [DuplicateOutputPolicy::Reject], each with the output that kept its net
+    pub fn get_duplicate_outputs(&self) -> Vec<(Identifier, Identifier)> {
+        self.rejected_outputs.borrow().clone()
+    }
+
+    /// Forgets the outputs rejected by [DuplicateOutputPolicy::Reject], and returns them
+    pub fn clear_duplicate_outputs(&self) -> Vec<(Identifier, Identifier)> {
+        self.rejected_outputs.take()
+    }
+
+    /// Checks that a new node named `inst_name`, if it is an instance, can drive `nets` without taking a name twice or creating multiple drivers
+    fn check_new_drivers(
+        &self,
+        inst_name: Option<&Identifier>,
+        nets: &[Net],
+    ) -> Result<(), NetlistError<I>> {
+        if !self.check_drivers.get() {
+            return Ok(());
+        }
+        if let Some(inst_name) = inst_name
+            && self.find_instance(inst_name).is_some()
+        {
+            return Err(NetlistError::NameCollision(inst_name.clone()));
+        }
+        for existing in self.into_iter() {
+            if let Some(net) = nets
+                .iter()
+                .find(|n| n.get_identifier() == existing.get_identifier())
+            {
+                return Err(NetlistError::DuplicateDriver(net.get_identifier().clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to reclaim the netlist, returning [Some] if successful.
+    pub fn reclaim(self: Rc<Self>) -> Option<Self> {
+        Rc::try_unwrap(self).ok()
+    }
+
+    /// Use interior mutability to add an object to the netlist. Returns a mutable reference to the created object.
+    fn insert_object(self: &Rc<Self>, object: Object<I>, operands: &[DrivenNet<I>]) -> NetRef<I> {
+        self.assert_unlocked();
+        let index = self.objects.borrow().len();
+        let weak = Rc::downgrade(self);
+        #[cfg(feature = "journal")]
+        match &object {
+            Object::Instance(_, name, inst_type) => self.record(|| Edit::InsertInstance {
+                name: name.clone(),
+                inst_type: inst_type.clone(),
+                operands: operands.iter().map(|n| Some(n.get_identifier())).collect(),
+            }),
+            Object::Alias(net) => self.record(|| Edit::InsertAlias {
+                net: net.clone(),
+                driver: operands.first().map(|n| n.get_identifier()),
+            }),
+            // Inputs are recorded with the port they are a bit of
+            Object::Input(_) => (),
+        }
+        let operands = operands
+            .iter()
+            .map(|net| Some(net.get_operand()))
+            .collect::<Vec<_>>();
+        let owned_object = Rc::new(RefCell::new(OwnedObject {
+            object: Rc::new(object),
+            owner: weak,
+            operands: Rc::new(operands),
+            attributes: Rc::default(),
+            properties: Rc::default(),
+            index,
+            mark: 0,
+        }));
+        self.objects.borrow_mut().push(owned_object.clone());
+        self.index_type(&owned_object);
+        NetRef::wrap(owned_object)
+    }
+
+    /// Inserts an alias named `name` of the net `driver`, which is emitted like `assign name = driver;`.
+    /// Loads and outputs connected to the alias keep its name, unlike those connected to `driver` directly.
+    pub fn insert_alias(
+        self: &Rc<Self>,
+        driver: DrivenNet<I>,
+        name: Identifier,
+    ) -> Result<DrivenNet<I>, NetlistError<I>> {
+        self.check_unlocked()?;
+        let net = driver.as_net().with_name(name);
+        self.check_new_drivers(None, std::slice::from_ref(&net))?;
+        let netref = self.insert_object(Object::Alias(net), &[driver]);
+        Ok(DrivenNet::new(0, netref))
+    }
+
+    /// Inserts an alias driving `net` whose input is not connected yet
+    fn insert_alias_disconnected(self: &Rc<Self>, net: Net) -> NetRef<I> {
+        let netref = self.insert_object(Object::Alias(net), &[]);
+        netref.netref.borrow_mut().operands = Rc::new(vec![None]);
+        netref
+    }
+
+    /// Returns the aliases in the netlist, in insertion order
+    pub fn aliases(&self) -> impl Iterator<Item = NetRef<I>> {
+        self.objects().filter(|n| n.is_an_alias())
+    }
+
+    /// Returns the nets marked as power or ground with [NetRef::set_supply], in object order
+    pub fn supplies(&self) -> impl Iterator<Item = (DrivenNet<I>, Supply)> {
+        self.objects()
+            .filter_map(|n| n.get_supply().map(|s| (n.get_output(0), s)))
+    }
+
+    /// Returns the nodes anchored as observation points with [NetRef::anchor], in object order
+    pub fn anchors(&self) -> impl Iterator<Item = NetRef<I>> {
+        self.objects().filter(|n| n.is_anchored())
+    }
+
+    /// Connects the loads and the top-level output of `alias` to the net it aliases, returning that net's operand.
+    /// Returns [None] without changes if the alias is unconnected, or it drives an output and the net it aliases already does.
+    fn bypass_alias(&self, alias: &NetRef<I>) -> Option<Operand> {
+        let (driver, index) = {
+            let owned = alias.netref.borrow();
+            (owned.operands[0].clone()?, owned.index)
+        };
+        let alias_op = Operand::DirectIndex(index);
+        let mut outputs = self.outputs.borrow_mut();
+        if outputs.contains_key(&alias_op) && outputs.contains_key(&driver) {
+            return None;
+        }
+        if let Some(net) = outputs.remove(&alias_op) {
+            outputs.insert(driver.clone(), net);
+        }
+        for oref in self.objects.borrow().iter() {
+            oref.borrow_mut()
+                .map_operands(|op| (*op == alias_op).then(|| Some(driver.clone())));
+        }
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::BypassAlias(journal::object_name(alias.netref.borrow().get())));
+        Some(driver)
+    }
+
+    /// Connects the loads of `alias` to the net it aliases and removes the alias, returning that net.
+    /// A top-level output driven by the alias moves to the aliased net, which must not already drive one.
+    pub fn collapse_alias(&self, alias: NetRef<I>) -> Result<DrivenNet<I>, NetlistError<I>> {
+        self.check_unlocked()?;
+        let driver = match alias.is_an_alias() {
+            true => self.bypass_alias(&alias),
+            false => None,
+        };
+        let Some(driver) = driver else {
+            return Err(NetlistError::CannotCollapse(alias));
+        };
+        let index = alias.netref.borrow().index;
+        drop(alias);
+        let driver = DrivenNet::new(
+            driver.secondary(),
+            NetRef::wrap(self.index_weak(&driver.root())),
+        );
+        self.remove_objects(&HashSet::from([index]))?;
+        Ok(driver)
+    }
+
+    /// Collapses every alias that can be, like [Netlist::collapse_alias], and returns how many were.
+    /// Aliases that are unconnected or needed to name a top-level output are kept.
+    pub fn collapse_aliases(&self) -> Result<usize, NetlistError<I>> {
+        let mut dead = HashSet::new();
+        for alias in self.aliases() {
+            if self.bypass_alias(&alias).is_some() {
+                dead.insert(alias.netref.borrow().index);
+            }
+        }
+        if !dead.is_empty() {
+            self.remove_objects(&dead)?;
+        }
+        Ok(dead.len())
+    }
+
+    /// Decomposes every instance with more than `max_fanin` inputs into a balanced tree of instances with at most `max_fanin` inputs.
+    /// `split` returns the types of the inner and root instances of the tree for a number of inputs, like [Gate::with_fanin],
+    /// or [None] if the instance type cannot be decomposed. The root keeps the name, output net, attributes and properties of the instance,
+    /// and the inner instances are named after it, like `inst_0`, skipping names already in use.
+    /// Instances whose tree `model` does not accept in place of the instance are left as they are.
+    /// The netlist is only changed if every instance can be decomposed. Returns the number of instances that were decomposed.
+    pub fn decompose_fanin(
+        self: &Rc<Self>,
+        max_fanin: usize,
+        split: impl Fn(&I, usize) -> Option<(I, I)>,
+        model: &dyn CostModel<I>,
+    ) -> Result<usize, String> {
+        if max_fanin < 2 {
+            return Err(format!("Cannot decompose gates to a fanin of {max_fanin}"));
+        }
+        self.check_unlocked()?;
+        let wide: Vec<NetRef<I>> = self
+            .objects()
+            .filter(|n| n.get_num_input_ports() > max_fanin && n.outputs().count() == 1)
+            .filter(|n| {
+                n.get_instance_type()
+                    .is_some_and(|t| split(&t, max_fanin).is_some())
+            })
+            .collect();
+
+        // Every tree is planned before the netlist is changed. The inputs of a planned gate index
+        // into the drivers of the instance, followed by the outputs of the gates planned before it.
+        let mut used = self.used_names();
+        let mut plans = Vec::new();
+        for inst in wide {
+            let inst_type = inst.get_instance_type().unwrap().clone();
+            let name = inst.get_instance_name().unwrap();
+            let mut nets = Vec::new();
+            for (i, driver) in inst.operands() {
+                match driver {
+                    Some(driver) => nets.push(driver),
+                    None => {
+                        return Err(format!(
+                            "Input {} of {name} is unconnected",
+                            inst.get_input(i)
+                        ));
+                    }
+                }
+            }
+            let tree_type = |fanin: usize| {
+                split(&inst_type, fanin).ok_or_else(|| {
+                    format!("Cannot decompose {name} into gates with {fanin} inputs")
+                })
+            };
+
+            let stem = plain_name(&name);
+            let mut gates: Vec<(I, Identifier, Vec<usize>)> = Vec::new();
+            let mut level: Vec<usize> = (0..nets.len()).collect();
+            while level.len() > max_fanin {
+                let mut next = Vec::new();
+                for chunk in level.chunks(max_fanin) {
+                    if chunk.len() == 1 {
+                        next.push(chunk[0]);
+                        continue;
+                    }
+                    let (inner, _) = tree_type(chunk.len())?;
+                    let gate_name = fresh_name(&mut used, &stem, &inner);
+                    gates.push((inner, gate_name, chunk.to_vec()));
+                    next.push(nets.len() + gates.len() - 1);
+                }
+                level = next;
+            }
+            let (_, root_type) = tree_type(level.len())?;
+            let root_name = fresh_name(&mut used, &stem, &root_type);
+            gates.push((root_type, root_name, level));
+            let tree = gates
+                .iter()
+                .map(|(t, _, _)| model.cost(t))
+                .fold(Cost::default(), Cost::combine);
+            if model.accepts(&model.cost(&inst_type), &tree) {
+                plans.push((inst, nets, gates));
+            }
+        }
+        if let Some(budget) = self.budget.borrow().as_ref() {
+            budget.fits(
+                plans
+                    .iter()
+                    .flat_map(|(_, _, gates)| gates.iter().map(|g| &g.0)),
+            )?;
+        }
+
+        let mut dead = HashSet::new();
+        let mut roots = Vec::new();
+        for (inst, mut nets, gates) in plans {
+            let mut root = None;
+            for (inst_type, gate_name, inputs) in gates {
+                let operands: Vec<DrivenNet<I>> = inputs.iter().map(|&i| nets[i].clone()).collect();
+                let gate = self.insert_gate(inst_type, gate_name, &operands)?;
+                nets.push(gate.get_output(0));
+                root = Some(gate);
+            }
+            let root = root.unwrap();
+            root.copy_annotations_from(&inst);
+            dead.insert(inst.netref.borrow().index);
+            let name = inst.get_instance_name().unwrap();
+            let net = inst.get_net(0).clone();
+            self.replace_net_uses(inst, &root)?;
+            roots.push((root, name, net));
+        }
+
+        let count = roots.len();
+        if !dead.is_empty() {
+            self.remove_objects(&dead)?;
+        }
+        for (root, name, net) in roots {
+            root.set_instance_name(name);
+            root.get_net_mut(0).set_identifier(net.take_identifier());
+        }
+        Ok(count)
+    }
+
+    /// Returns the input ports each net drives, in the order of [Netlist::connections]
+    fn loads_by_net(&self) -> HashMap<Net, Vec<InputPort<I>>> {
+        let mut loads: HashMap<Net, Vec<InputPort<I>>> = HashMap::new();
+        for c in self.connections() {
+            loads.entry(c.net()).or_default().push(c.target());
+        }
+        loads
+    }
+
+    /// Returns the names of every instance and net in the netlist
+    fn used_names(&self) -> HashSet<Identifier> {
+        self.objects()
+            .flat_map(|n| {
+                let mut names: Vec<Identifier> =
+                    n.nets().map(|net| net.take_identifier()).collect();
+                names.extend(n.get_instance_name());
+                names
+            })
+            .collect()
+    }
+
+    /// Buffers every net with more than `max_fanout` loads through a tree of `buffer` instances, so no net drives more than `max_fanout` loads.
+    /// Loads are grouped in the order of [Netlist::connections], and each level of the tree is split again with [DrivenNet::split_loads].
+    /// Buffers are named after the net they buffer, like `a_buf_0`, skipping names already in use.
+    /// Top-level outputs are not counted as loads and supply nets are not buffered. Nets whose tree `model` does not accept are left as they are.
+    /// Returns the number of buffers inserted.
+    pub fn buffer_fanout(
         self: &Rc<Self>,
-        object: Object<I>,
-        operands: &[DrivenNet<I>],
-    ) -> Result<NetRef<I>, String> {
-        let index = self.objects.borrow().len();
-        let weak = Rc::downgrade(self);
-        let operands = operands
-            .iter()
-            .map(|net| Some(net.get_operand()))
-            .collect::<Vec<_>>();
-        let owned_object = Rc::new(RefCell::new(OwnedObject {
-            object,
-            owner: weak,
-            operands,
-            attributes: HashMap::new(),
-            index,
-        }));
-        self.objects.borrow_mut().push(owned_object.clone());
-        Ok(NetRef::wrap(owned_object))
+        max_fanout: usize,
+        buffer: I,
+        model: &dyn CostModel<I>,
+    ) -> Result<usize, String> {
+        if max_fanout < 2 {
+            return Err(format!("Cannot buffer nets to a fanout of {max_fanout}"));
+        }
+        let mut loads = self.loads_by_net();
+        let wide: Vec<DrivenNet<I>> = self
+            .objects()
+            .flat_map(|n| n.outputs().collect::<Vec<_>>())
+            .filter(|net| net.get_supply().is_none())
+            .filter(|net| {
+                loads
+                    .get(&*net.as_net())
+                    .is_some_and(|l| l.len() > max_fanout)
+            })
+            .collect();
+
+        let mut used = self.used_names();
+        let mut count = 0;
+        for net in wide {
+            let stem = format!("{}_buf", plain_name(&net.get_identifier()));
+            let mut level = loads.remove(&*net.as_net()).unwrap_or_default();
+            let mut tree = Cost::default();
+            let mut width = level.len();
+            while width > max_fanout {
+                width = width.div_ceil(max_fanout);
+                tree = (0..width).fold(tree, |c, _| c.combine(model.cost(&buffer)));
+            }
+            if !model.accepts(&Cost::default(), &tree) {
+                continue;
+            }
+            while level.len() > max_fanout {
+                let groups: Vec<(Identifier, Vec<InputPort<I>>)> = level
+                    .chunks(max_fanout)
+                    .map(|chunk| (fresh_name(&mut used, &stem, &buffer), chunk.to_vec()))
+                    .collect();
+                let buffers = net.split_loads(buffer.clone(), groups)?;
+                count += buffers.len();
+                level = buffers.iter().map(|b| b.get_input(0)).collect();
+            }
+        }
+        Ok(count)
+    }
+
+    /// Clones every single-output instance with more than `max_fanout` loads, so each copy drives at most `max_fanout` of them.
+    /// Loads are grouped in the order of [Netlist::connections], and the instance keeps the first group with [DrivenNet::clone_for_loads].
+    /// Instances are visited in reverse object order, so the copies of a load count toward the fanout of the instances that drive it.
+    /// Copies are named after the instance, like `and_clone_0`, skipping names already in use.
+    /// Top-level outputs are not counted as loads and stay on the instance. Returns the number of copies inserted.
+    pub fn clone_fanout(self: &Rc<Self>, max_fanout: usize) -> Result<usize, String> {
+        if max_fanout == 0 {
+            return Err("Cannot clone instances to a fanout of 0".to_string());
+        }
+        let mut loads = self.loads_by_net();
+        let instances: Vec<NetRef<I>> = self
+            .objects()
+            .filter(|n| n.get_instance_type().is_some() && !n.is_multi_output())
+            .collect();
+
+        let mut used = self.used_names();
+        let mut count = 0;
+        for node in instances.into_iter().rev() {
+            let net = node.get_output(0);
+            let Some(level) = loads
+                .remove(&*net.as_net())
+                .filter(|l| l.len() > max_fanout)
+            else {
+                continue;
+            };
+            let inst_type = node.get_instance_type().unwrap().clone();
+            let stem = format!("{}_clone", node.get_instance_name().unwrap());
+            let groups: Vec<(Identifier, Vec<InputPort<I>>)> = level
+                .chunks(max_fanout)
+                .skip(1)
+                .map(|chunk| (fresh_name(&mut used, &stem, &inst_type), chunk.to_vec()))
+                .collect();
+            for copy in net.clone_for_loads(groups)? {
+                for input in copy.inputs() {
+                    if let Some(driver) = input.get_driver() {
+                        loads
+                            .entry(driver.as_net().clone())
+                            .or_default()
+                            .push(input);
+                    }
+                }
+                count += 1;
+            }
+        }
+        Ok(count)
     }
 
     /// Inserts an input net to the netlist
+    ///
+    /// # Panics
+    ///
+    /// Panics if the netlist is locked, see [Netlist::try_insert_input].
     pub fn insert_input(self: &Rc<Self>, net: Net) -> DrivenNet<I> {
+        let port = PortDecl::<I>::port_name(net.get_identifier());
+        self.insert_input_bit(net, port)
+    }
+
+    /// Inserts an input net to the netlist, like [Netlist::insert_input], or returns [NetlistError::Locked] if the netlist is locked
+    pub fn try_insert_input(self: &Rc<Self>, net: Net) -> Result<DrivenNet<I>, NetlistError<I>> {
+        self.check_unlocked()?;
+        Ok(self.insert_input(net))
+    }
+
+    /// Inserts an input net to the netlist as a bit of the port `port`
+    fn insert_input_bit(self: &Rc<Self>, net: Net, port: Identifier) -> DrivenNet<I> {
+        self.insert_port_bit(net, port, PortDirection::Input)
+    }
+
+    /// Inserts a net read from outside the netlist as a bit of the port `port` with the direction `direction`
+    fn insert_port_bit(
+        self: &Rc<Self>,
+        net: Net,
+        port: Identifier,
+        direction: PortDirection,
+    ) -> DrivenNet<I> {
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::InsertInput {
+            net: net.clone(),
+            port: port.clone(),
+            direction,
+        });
         let obj = Object::Input(net);
-        self.insert_object(obj, &[]).unwrap().into()
+        let netref = self.insert_object(obj, &[]);
+        let bit = PortBit::Input(Rc::downgrade(&netref.netref));
+        self.declare_port_bit(port, direction, bit);
+        netref.into()
+    }
+
+    /// Inserts a bidirectional net to the netlist, declared as an `inout` port.
+    /// The net is read inside the netlist like an input, and can be driven by instances with [Netlist::drive_inout].
+    pub fn insert_inout(self: &Rc<Self>, net: Net) -> DrivenNet<I> {
+        let port = PortDecl::<I>::port_name(net.get_identifier());
+        self.insert_port_bit(net, port, PortDirection::Inout)
+    }
+
+    /// Returns `true` if the net named `id` is a bit of an `inout` port
+    fn is_inout_net(&self, id: &Identifier) -> bool {
+        self.ports
+            .borrow()
+            .iter()
+            .filter(|p| p.direction == PortDirection::Inout)
+            .flat_map(|p| p.bits.iter())
+            .any(|b| match b {
+                PortBit::Input(w) => w
+                    .upgrade()
+                    .is_some_and(|o| o.borrow().as_net().get_identifier() == id),
+                PortBit::Output(_) => false,
+            })
+    }
+
+    /// Makes the instance output `driver` one of the drivers of the `inout` net `inout`, like a tri-state buffer driving a pad.
+    /// The output net takes the name of the `inout` net, so it is written as connected to the pad.
+    /// Returns an error if `inout` is not a bit of an `inout` port, or `driver` is a top-level input or drives a top-level output.
+    pub fn drive_inout(&self, driver: &DrivenNet<I>, inout: &DrivenNet<I>) -> Result<(), String> {
+        let id = inout.get_identifier();
+        if !inout.is_an_input() || !self.is_inout_net(&id) {
+            return Err(format!("Net {id} is not a bit of an inout port"));
+        }
+        if driver.is_an_input() {
+            return Err(format!(
+                "Net {} is not driven by an instance",
+                driver.get_identifier()
+            ));
+        }
+        if driver.is_top_level_output() {
+            return Err(format!(
+                "Net {} drives a top-level output",
+                driver.get_identifier()
+            ));
+        }
+        driver.as_net_mut().set_identifier(id);
+        Ok(())
     }
 
     /// Inserts a four-state logic input port to the netlist
@@ -1146,70 +3698,760 @@ where
         net: String,
         bw: usize,
     ) -> Vec<DrivenNet<I>> {
+        let port = Identifier::new(net.clone());
         Net::new_escaped_logic_bus(net, bw)
             .into_iter()
-            .map(|n| self.insert_input(n))
+            .map(|n| self.insert_input_bit(n, port.clone()))
+            .collect()
+    }
+
+    /// Inserts a gate to the netlist
+    pub fn insert_gate(
+        self: &Rc<Self>,
+        inst_type: I,
+        inst_name: Identifier,
+        operands: &[DrivenNet<I>],
+    ) -> Result<NetRef<I>, NetlistError<I>> {
+        // TODO(matth2k): Need to a more robust way to concat identifiers.
+        if inst_name.is_escaped() {
+            return Err(NetlistError::EscapedName(inst_name));
+        }
+        let nets = inst_type
+            .get_output_ports()
+            .into_iter()
+            .map(|pnet| pnet.with_name(format!("{}_{}", inst_name, pnet.get_identifier()).into()))
+            .collect::<Vec<_>>();
+        self.insert_gate_with_nets(inst_type, inst_name, nets, operands)
+    }
+
+    /// Inserts a gate named `inst_name` whose outputs drive `nets`
+    fn insert_gate_with_nets(
+        self: &Rc<Self>,
+        inst_type: I,
+        inst_name: Identifier,
+        nets: Vec<Net>,
+        operands: &[DrivenNet<I>],
+    ) -> Result<NetRef<I>, NetlistError<I>> {
+        self.check_unlocked()?;
+        let input_count = inst_type.get_input_ports().into_iter().count();
+        if operands.len() != input_count {
+            return Err(NetlistError::ArityMismatch {
+                inst_name,
+                expected: input_count,
+                got: operands.len(),
+            });
+        }
+        for (port, operand) in inst_type.get_input_ports().into_iter().zip(operands) {
+            let driver = *operand.as_net().get_type();
+            if !driver.can_drive(port.get_type()) {
+                return Err(NetlistError::TypeMismatch(Box::new(TypeMismatch {
+                    inst_name,
+                    net: operand.get_identifier(),
+                    driver,
+                    port: port.get_identifier().clone(),
+                    sink: *port.get_type(),
+                })));
+            }
+        }
+        self.check_new_drivers(Some(&inst_name), &nets)?;
+        self.charge_budget(&inst_type)?;
+        let obj = Object::Instance(nets, inst_name, inst_type);
+        let netref = self.insert_object(obj, operands);
+        self.index_structure(&netref.netref);
+        Ok(netref)
+    }
+
+    /// Inserts a copy of the single-bit gate `inst_type` for every bit of the buses in `operands`, which has one bus per input port.
+    /// The copy for bit `i` is named `{inst_name}_{i}` and takes bit `i` of every bus. Returns the copies, lsb first.
+    /// The buses must all be as wide. Copies inserted before a later copy fails to insert are kept.
+    pub fn insert_gate_bus(
+        self: &Rc<Self>,
+        inst_type: I,
+        inst_name: Identifier,
+        operands: &[Vec<DrivenNet<I>>],
+    ) -> Result<Vec<NetRef<I>>, NetlistError<I>> {
+        let input_count = inst_type.get_input_ports().into_iter().count();
+        if operands.len() != input_count {
+            return Err(NetlistError::ArityMismatch {
+                inst_name,
+                expected: input_count,
+                got: operands.len(),
+            });
+        }
+        let width = operands.first().map_or(0, |bus| bus.len());
+        if let Some((operand, bus)) = operands.iter().enumerate().find(|(_, b)| b.len() != width) {
+            return Err(NetlistError::WidthMismatch {
+                inst_name,
+                operand,
+                expected: width,
+                got: bus.len(),
+            });
+        }
+        (0..width)
+            .map(|i| {
+                let bits: Vec<DrivenNet<I>> = operands.iter().map(|bus| bus[i].clone()).collect();
+                self.insert_gate(inst_type.clone(), format!("{inst_name}_{i}").into(), &bits)
+            })
+            .collect()
+    }
+
+    /// Inserts an array of copies of the gate `inst_type`, one for every bit of the buses in `operands`, which has one bus per input port.
+    /// Copy `i` is named like `u_fa[i]` and its outputs drive escaped nets named after the array, port and index, like `\u_fa_S[i] `,
+    /// which are gathered into vectors by [VerilogEmitter::with_buses].
+    /// An operand that is a single net is used by every copy. The copies are found again by [Netlist::instance_arrays],
+    /// which only needs their names, and can be written as one instance array by an emitter (see [NetlistEmitter::instance_array]).
+    /// Returns the copies, lsb first, or [NetlistError::EscapedName] if `name` is escaped or a bit-slice.
+    pub fn insert_gate_array(
+        self: &Rc<Self>,
+        inst_type: I,
+        name: Identifier,
+        operands: &[Vec<DrivenNet<I>>],
+    ) -> Result<Vec<NetRef<I>>, NetlistError<I>> {
+        if name.is_escaped() || name.is_sliced() {
+            return Err(NetlistError::EscapedName(name));
+        }
+        let input_count = inst_type.get_input_ports().into_iter().count();
+        if operands.len() != input_count {
+            return Err(NetlistError::ArityMismatch {
+                inst_name: name,
+                expected: input_count,
+                got: operands.len(),
+            });
+        }
+        let width = operands.iter().map(|bus| bus.len()).max().unwrap_or(0);
+        if let Some((operand, bus)) = operands
+            .iter()
+            .enumerate()
+            .find(|(_, b)| b.len() != width && b.len() != 1)
+        {
+            return Err(NetlistError::WidthMismatch {
+                inst_name: name,
+                operand,
+                expected: width,
+                got: bus.len(),
+            });
+        }
+        (0..width)
+            .map(|i| {
+                let bits: Vec<DrivenNet<I>> = operands
+                    .iter()
+                    .map(|bus| bus[i.min(bus.len() - 1)].clone())
+                    .collect();
+                let nets = inst_type
+                    .get_output_ports()
+                    .into_iter()
+                    .map(|p| {
+                        let net = format!("\\{name}_{}[{i}]", p.get_identifier());
+                        p.with_name(Identifier::new(net))
+                    })
+                    .collect();
+                self.insert_gate_with_nets(
+                    inst_type.clone(),
+                    format!("{name}[{i}]").into(),
+                    nets,
+                    &bits,
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the arrays of instances in the netlist, in the order of their first instance.
+    /// Instances named like `u_fa[0]` to `u_fa[31]` form an array if their indices are contiguous and they all have the same type and parameters,
+    /// as inserted by [Netlist::insert_gate_array]. Names of instances are kept through serde and emission, so arrays are too.
+    pub fn instance_arrays(&self) -> Vec<InstanceArray<I>> {
+        let mut order: Vec<Identifier> = Vec::new();
+        let mut groups: HashMap<Identifier, Vec<(usize, NetRef<I>)>> = HashMap::new();
+        for node in self.objects() {
+            let Some(index) = node.get_instance_name().and_then(|n| n.get_bit_index()) else {
+                continue;
+            };
+            let name: Identifier = node.get_instance_name().unwrap().get_name().into();
+            if !groups.contains_key(&name) {
+                order.push(name.clone());
+            }
+            groups.entry(name).or_default().push((index, node));
+        }
+        let signature = |n: &NetRef<I>| {
+            let inst_type = n.get_instance_type().unwrap();
+            let params: Vec<String> = inst_type
+                .parameters()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect();
+            (inst_type.get_name().clone(), params)
+        };
+        order
+            .into_iter()
+            .filter_map(|name| {
+                let mut members = groups.remove(&name)?;
+                members.sort_by_key(|(i, _)| *i);
+                let lsb = members[0].0;
+                let contiguous = members.iter().enumerate().all(|(k, (i, _))| *i == lsb + k);
+                let first = signature(&members[0].1);
+                if !contiguous || members.iter().any(|(_, n)| signature(n) != first) {
+                    return None;
+                }
+                Some(InstanceArray {
+                    name,
+                    lsb,
+                    instances: members.into_iter().map(|(_, n)| n).collect(),
+                })
+            })
             .collect()
     }
 
-    /// Inserts a gate to the netlist
-    pub fn insert_gate(
-        self: &Rc<Self>,
-        inst_type: I,
-        inst_name: Identifier,
-        operands: &[DrivenNet<I>],
-    ) -> Result<NetRef<I>, String> {
-        // TODO(matth2k): Need to a more robust way to concat identifiers.
-        assert!(!inst_name.is_escaped());
-        let nets = inst_type
-            .get_output_ports()
+    /// Maps the single-bit gate `inst_type` over the buses in `operands`, like [Netlist::insert_gate_bus], and returns the output bus, lsb first.
+    /// An operand that is a single net is used by every copy, like the enable of a bus of ANDs.
+    /// The output of a copy is its first output.
+    pub fn map_gate_over_bus(
+        self: &Rc<Self>,
+        inst_type: I,
+        name_prefix: Identifier,
+        operands: &[Vec<DrivenNet<I>>],
+    ) -> Result<Vec<DrivenNet<I>>, NetlistError<I>> {
+        let width = operands.iter().map(|bus| bus.len()).max().unwrap_or(0);
+        let buses: Vec<Vec<DrivenNet<I>>> = operands
+            .iter()
+            .map(|bus| match bus.as_slice() {
+                [net] => vec![net.clone(); width],
+                _ => bus.clone(),
+            })
+            .collect();
+        Ok(self
+            .insert_gate_bus(inst_type, name_prefix, &buses)?
+            .into_iter()
+            .map(|copy| copy.get_output(0))
+            .collect())
+    }
+
+    /// Use interior mutability to add an object to the netlist. Returns a mutable reference to the created object.
+    pub fn insert_gate_disconnected(
+        self: &Rc<Self>,
+        inst_type: I,
+        inst_name: Identifier,
+    ) -> Result<NetRef<I>, NetlistError<I>> {
+        self.check_unlocked()?;
+        // TODO(matth2k): Need to a more robust way to concat identifiers.
+        if inst_name.is_escaped() {
+            return Err(NetlistError::EscapedName(inst_name));
+        }
+        let nets = inst_type
+            .get_output_ports()
+            .into_iter()
+            .map(|pnet| pnet.with_name(format!("{}_{}", inst_name, pnet.get_identifier()).into()))
+            .collect::<Vec<_>>();
+        self.check_new_drivers(Some(&inst_name), &nets)?;
+        self.charge_budget(&inst_type)?;
+        let object = Object::Instance(nets, inst_name, inst_type);
+        let index = self.objects.borrow().len();
+        let weak = Rc::downgrade(self);
+        let input_count = object
+            .get_instance_type()
+            .unwrap()
+            .get_input_ports()
+            .into_iter()
+            .count();
+        #[cfg(feature = "journal")]
+        if let Object::Instance(_, name, inst_type) = &object {
+            self.record(|| Edit::InsertInstance {
+                name: name.clone(),
+                inst_type: inst_type.clone(),
+                operands: vec![None; input_count],
+            });
+        }
+        let operands = vec![None; input_count];
+        let owned_object = Rc::new(RefCell::new(OwnedObject {
+            object: Rc::new(object),
+            owner: weak,
+            operands: Rc::new(operands),
+            attributes: Rc::default(),
+            properties: Rc::default(),
+            index,
+            mark: 0,
+        }));
+        self.objects.borrow_mut().push(owned_object.clone());
+        self.index_type(&owned_object);
+        Ok(NetRef::wrap(owned_object))
+    }
+
+    /// Copies the cone of logic driving `roots` in `other` into this netlist.
+    /// Instances and aliases are deep-copied with their attributes and renamed with `name_prefix`.
+    /// Inputs of `other` in the cone are bound to the input of this netlist with the same name, or inserted as new inputs.
+    /// Returns the nets in this netlist that correspond to `roots`. The netlist is only changed if every node can be copied.
+    pub fn copy_cone_from(
+        self: &Rc<Self>,
+        other: &Netlist<I>,
+        roots: &[DrivenNet<I>],
+        name_prefix: &str,
+    ) -> Result<Vec<DrivenNet<I>>, String> {
+        if std::ptr::eq(self.as_ref(), other) {
+            return Err("Cannot copy a cone of logic into the netlist it comes from".to_string());
+        }
+        for root in roots {
+            let owner = root.netref.netref.borrow().owner.as_ptr();
+            if !std::ptr::eq(owner, other) {
+                return Err(format!(
+                    "Net {} does not belong to netlist {}",
+                    root.get_identifier(),
+                    other.get_name()
+                ));
+            }
+        }
+
+        self.check_unlocked()?;
+
+        let mut copies: HashMap<NetRef<I>, NetRef<I>> = HashMap::new();
+        let mut instances = Vec::new();
+        let mut inserted = HashSet::new();
+        if let Err(e) = self.copy_cone_nodes(
+            roots,
+            name_prefix,
+            &mut copies,
+            &mut instances,
+            &mut inserted,
+        ) {
+            drop(copies);
+            self.remove_objects(&inserted)?;
+            return Err(e);
+        }
+
+        // Rebuild the operands once every node in the cone has a copy
+        for node in instances {
+            let copy = &copies[&node];
+            for (i, driver) in node.operands() {
+                if let Some(driver) = driver {
+                    let src = copies[&driver.netref].get_output(driver.pos);
+                    copy.get_input(i).connect(src);
+                }
+            }
+        }
+
+        Ok(roots
+            .iter()
+            .map(|r| copies[&r.netref].get_output(r.pos))
+            .collect())
+    }
+
+    /// Copies every node of the cone driving `roots` for [Netlist::copy_cone_from], without connecting the copies.
+    /// Copied instances and aliases are collected in `instances`, and the indices of the inserted objects in `inserted`.
+    fn copy_cone_nodes(
+        self: &Rc<Self>,
+        roots: &[DrivenNet<I>],
+        name_prefix: &str,
+        copies: &mut HashMap<NetRef<I>, NetRef<I>>,
+        instances: &mut Vec<NetRef<I>>,
+        inserted: &mut HashSet<usize>,
+    ) -> Result<(), String> {
+        let mut stack: Vec<NetRef<I>> = roots.iter().map(|r| r.clone().unwrap()).collect();
+        while let Some(node) = stack.pop() {
+            if copies.contains_key(&node) {
+                continue;
+            }
+            let copy = if node.is_an_input() {
+                let net = node.as_net().clone();
+                match self
+                    .inputs()
+                    .find(|i| i.get_identifier() == *net.get_identifier())
+                {
+                    Some(input) => input.unwrap(),
+                    None => {
+                        let input = self.insert_input(net).unwrap();
+                        inserted.insert(input.netref.borrow().index);
+                        input
+                    }
+                }
+            } else {
+                let copy = match node.get_instance_type() {
+                    Some(inst_type) => {
+                        let name: Identifier =
+                            format!("{}{}", name_prefix, node.get_instance_name().unwrap()).into();
+                        self.insert_gate_disconnected(inst_type.clone(), name)?
+                    }
+                    None => {
+                        let net = node.as_net().clone();
+                        let name = format!("{}{}", name_prefix, net.get_identifier());
+                        self.insert_alias_disconnected(net.with_name(name.into()))
+                    }
+                };
+                inserted.insert(copy.netref.borrow().index);
+                copy.copy_annotations_from(&node);
+                stack.extend(node.drivers().flatten());
+                instances.push(node.clone());
+                copy
+            };
+            copies.insert(node, copy);
+        }
+        Ok(())
+    }
+
+    /// Copies the cone of logic driving `roots` into a standalone netlist named like `top_cone`.
+    /// The inputs of the cone keep their names, and each root becomes an output named like the top-level output it drives,
+    /// or otherwise after its net. Roots that are inputs become outputs named like `a_out`.
+    pub fn extract_cone(&self, roots: &[DrivenNet<I>]) -> Result<Rc<Netlist<I>>, String> {
+        let cone = Netlist::new(format!("{}_cone", self.name));
+        let copies = cone.copy_cone_from(self, roots, "")?;
+        let outputs = self.outputs();
+        for (root, copy) in roots.iter().zip(copies) {
+            let output = outputs
+                .iter()
+                .find(|(d, _)| d.get_operand() == root.get_operand())
+                .map(|(_, net)| net.get_identifier().clone());
+            match output {
+                Some(name) => {
+                    copy.expose_with_name(name);
+                }
+                None if copy.is_an_input() => {
+                    let name = format!("{}_out", copy.get_identifier());
+                    copy.expose_with_name(name.into());
+                }
+                None => {
+                    cone.expose_net(copy)?;
+                }
+            }
+        }
+        Ok(cone)
+    }
+
+    /// Writes the cone of logic driving `roots` to `w` as a standalone Verilog module, like [Netlist::extract_cone].
+    /// This makes minimal reproducers of bugs without sharing the rest of the design.
+    pub fn emit_cone_verilog(
+        &self,
+        roots: &[DrivenNet<I>],
+        w: &mut dyn std::fmt::Write,
+    ) -> Result<(), String> {
+        let cone = self.extract_cone(roots)?;
+        cone.emit(&mut VerilogEmitter::new(), w)
+            .map_err(|e| format!("Could not write the cone of {}: {e}", self.name))
+    }
+
+    /// Extracts the nodes carrying the attribute `key` into a new netlist, along with the logic connecting them.
+    /// Connecting logic lies on a path from one node carrying the attribute to another.
+    /// Nets driven from outside the subset become inputs, and nets used outside it become outputs, named after the nets.
+    /// Top-level outputs driven within the subset keep their names.
+    pub fn extract_by_attribute(&self, key: &AttributeKey) -> Result<Rc<Netlist<I>>, String> {
+        let selected: Vec<NetRef<I>> = self
+            .objects()
+            .filter(|n| n.netref.borrow().attributes.contains_key(key))
+            .collect();
+        if selected.is_empty() {
+            return Err(format!("No node carries the attribute {key}"));
+        }
+
+        let mut users: HashMap<NetRef<I>, Vec<NetRef<I>>> = HashMap::new();
+        for c in self.connections() {
+            users
+                .entry(c.src().unwrap())
+                .or_default()
+                .push(c.target().unwrap());
+        }
+        let reach = |next: &dyn Fn(&NetRef<I>) -> Vec<NetRef<I>>| {
+            let mut seen: HashSet<NetRef<I>> = selected.iter().cloned().collect();
+            let mut stack = selected.clone();
+            while let Some(node) = stack.pop() {
+                for n in next(&node) {
+                    if seen.insert(n.clone()) {
+                        stack.push(n);
+                    }
+                }
+            }
+            seen
+        };
+        let forward = reach(&|n| users.get(n).cloned().unwrap_or_default());
+        let backward = reach(&|n| n.drivers().flatten().collect());
+        let keep: HashSet<NetRef<I>> = forward.intersection(&backward).cloned().collect();
+
+        let subset = Netlist::new(self.get_name().to_string());
+        let mut copies: HashMap<NetRef<I>, NetRef<I>> = HashMap::new();
+        for node in self.objects().filter(|n| keep.contains(n)) {
+            let copy = if node.is_an_input() {
+                subset.insert_input(node.as_net().clone()).unwrap()
+            } else if node.is_an_alias() {
+                subset.insert_alias_disconnected(node.as_net().clone())
+            } else {
+                let inst_type = node.get_instance_type().unwrap().clone();
+                subset.insert_gate_disconnected(inst_type, node.get_instance_name().unwrap())?
+            };
+            copy.copy_annotations_from(&node);
+            copies.insert(node, copy);
+        }
+
+        // Connect the copies, with a new input for each net driven from outside the subset
+        let mut boundary: HashMap<Net, DrivenNet<I>> = HashMap::new();
+        for node in self.objects().filter(|n| keep.contains(n)) {
+            let copy = &copies[&node];
+            for (i, driver) in node.operands() {
+                let Some(driver) = driver else {
+                    continue;
+                };
+                let src = match copies.get(&driver.netref) {
+                    Some(d) => d.get_output(driver.pos),
+                    None => {
+                        let net = driver.as_net().clone();
+                        boundary
+                            .entry(net.clone())
+                            .or_insert_with(|| subset.insert_input(net))
+                            .clone()
+                    }
+                };
+                copy.get_input(i).connect(src);
+            }
+        }
+
+        // Expose the nets that are used outside the subset
+        let top: Vec<(DrivenNet<I>, Net)> = self.outputs();
+        for node in self.objects().filter(|n| keep.contains(n)) {
+            for (pos, net) in node.nets().enumerate() {
+                let out = copies[&node].get_output(pos);
+                let names: Vec<Identifier> = top
+                    .iter()
+                    .filter(|(d, _)| *d.as_net() == net)
+                    .map(|(_, n)| n.get_identifier().clone())
+                    .collect();
+                for name in &names {
+                    out.clone().expose_with_name(name.clone());
+                }
+                let used_outside =
+                    users.get(&node).into_iter().flatten().any(|u| {
+                        !keep.contains(u) && u.driver_nets().flatten().any(|(n, _)| n == net)
+                    });
+                if used_outside && names.is_empty() {
+                    out.expose_with_name(net.get_identifier().clone());
+                }
+            }
+        }
+
+        Ok(subset)
+    }
+
+    /// Rewrites the instances named in `region` by extracting them into a working netlist named like `top_region`,
+    /// applying `pass` to it and splicing the result back in their place.
+    /// The inputs of the working netlist are the nets driven from outside the region, and its outputs are the nets of the region used outside it,
+    /// both named after the nets. `pass` may change the logic between them freely, but must keep every output and add no input.
+    /// Spliced instances and nets keep the names the pass leaves them with, unless those are taken outside the region.
+    /// Only the replaced instances are removed, even if they are anchored, so handles to them must be dropped beforehand.
+    /// Logic the pass leaves unused is not spliced back, but logic outside the region that becomes unused is kept, and can be removed with [Netlist::clean].
+    /// The netlist is left unchanged if `pass` or splicing fails, or if `model` does not accept the cost of the working netlist in place of the region.
+    /// A pass can find the don't-cares it is free to change with [crate::aig::region_function].
+    pub fn rewrite_region<F>(
+        self: &Rc<Self>,
+        region: &[Identifier],
+        model: &dyn CostModel<I>,
+        pass: F,
+    ) -> Result<(), String>
+    where
+        F: FnOnce(&Rc<Netlist<I>>) -> Result<(), String>,
+    {
+        let nodes: Vec<NetRef<I>> = region
+            .iter()
+            .map(|name| {
+                self.find_instance(name).ok_or(format!(
+                    "Instance {name} not found in netlist {}",
+                    self.name
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+        let keep: HashSet<NetRef<I>> = nodes.iter().cloned().collect();
+
+        let working = Netlist::new(format!("{}_region", self.name));
+        let mut copies: HashMap<NetRef<I>, NetRef<I>> = HashMap::new();
+        for node in &nodes {
+            let inst_type = node.get_instance_type().unwrap().clone();
+            let copy =
+                working.insert_gate_disconnected(inst_type, node.get_instance_name().unwrap())?;
+            for (mut net, orig) in copy.nets_mut().zip(node.nets()) {
+                net.set_identifier(orig.get_identifier().clone());
+            }
+            copy.copy_annotations_from(node);
+            copies.insert(node.clone(), copy);
+        }
+
+        // Connect the copies, with a new input for each net driven from outside the region
+        let mut inputs: HashMap<Identifier, DrivenNet<I>> = HashMap::new();
+        for node in &nodes {
+            for (i, driver) in node.operands() {
+                let Some(driver) = driver else {
+                    continue;
+                };
+                let src = match copies.get(&driver.netref) {
+                    Some(d) => d.get_output(driver.pos),
+                    None => {
+                        let net = driver.as_net().clone();
+                        if !inputs.contains_key(net.get_identifier()) {
+                            inputs.insert(net.get_identifier().clone(), driver.clone());
+                            working.insert_input(net.clone());
+                        }
+                        working
+                            .inputs()
+                            .find(|i| i.get_identifier() == *net.get_identifier())
+                            .unwrap()
+                    }
+                };
+                copies[node].get_input(i).connect(src);
+            }
+        }
+
+        // Expose the nets used outside the region, remembering their loads there
+        let mut loads = self.loads_by_net();
+        let top = self.outputs();
+        let mut outputs: Vec<(Identifier, DrivenNet<I>, Vec<InputPort<I>>)> = Vec::new();
+        for node in &nodes {
+            for (pos, net) in node.nets().enumerate() {
+                let outside: Vec<InputPort<I>> = loads
+                    .remove(&net)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|l| !keep.contains(&l.clone().unwrap()))
+                    .collect();
+                let exposed = top.iter().any(|(d, _)| *d.as_net() == net);
+                if outside.is_empty() && !exposed {
+                    continue;
+                }
+                copies[node]
+                    .get_output(pos)
+                    .expose_with_name(net.get_identifier().clone());
+                outputs.push((net.get_identifier().clone(), node.get_output(pos), outside));
+            }
+        }
+        drop(copies);
+
+        pass(&working)?;
+        let results: HashMap<Identifier, DrivenNet<I>> = working
+            .outputs()
             .into_iter()
-            .map(|pnet| pnet.with_name(format!("{}_{}", inst_name, pnet.get_identifier()).into()))
-            .collect::<Vec<_>>();
-        let input_count = inst_type.get_input_ports().into_iter().count();
-        if operands.len() != input_count {
+            .map(|(d, n)| (n.get_identifier().clone(), d))
+            .collect();
+        if let Some((name, _, _)) = outputs.iter().find(|(n, _, _)| !results.contains_key(n)) {
+            return Err(format!("The pass removed the output {name} of the region"));
+        }
+        if let Some(input) = working
+            .inputs()
+            .find(|i| !inputs.contains_key(&i.get_identifier()))
+        {
             return Err(format!(
-                "Expected {} operands, got {}",
-                input_count,
-                operands.len()
+                "The pass added the input {} to the region",
+                input.get_identifier()
             ));
         }
-        let obj = Object::Instance(nets, inst_name, inst_type);
-        self.insert_object(obj, operands)
+        let before = cost_of(model, nodes.iter().cloned());
+        let after = netlist_cost(model, &working);
+        if !model.accepts(&before, &after) {
+            return Err(format!(
+                "The cost model rejects the rewrite, from {before} to {after}"
+            ));
+        }
+
+        let snapshot = self.snapshot();
+        let spliced = self.splice_region(&nodes, &working, &inputs, &results, outputs);
+        // Only the old instances of the region are removed, so unrelated logic is left alone
+        let old: HashSet<usize> = nodes
+            .iter()
+            .map(|n| n.netref.borrow().get_index())
+            .collect();
+        drop((nodes, keep, inputs, results, top, loads));
+        let removed = spliced.and_then(|_| self.remove_objects(&old).map_err(|e| e.to_string()));
+        if removed.is_err() {
+            self.restore(&snapshot)?;
+        }
+        removed
     }
 
-    /// Use interior mutability to add an object to the netlist. Returns a mutable reference to the created object.
-    pub fn insert_gate_disconnected(
+    /// Moves the old instances of a region out of the way and splices in the logic of `working`,
+    /// rewiring the outside loads and top-level outputs of the region to it
+    fn splice_region(
         self: &Rc<Self>,
-        inst_type: I,
-        inst_name: Identifier,
-    ) -> Result<NetRef<I>, String> {
-        // TODO(matth2k): Need to a more robust way to concat identifiers.
-        assert!(!inst_name.is_escaped());
-        let nets = inst_type
-            .get_output_ports()
-            .into_iter()
-            .map(|pnet| pnet.with_name(format!("{}_{}", inst_name, pnet.get_identifier()).into()))
-            .collect::<Vec<_>>();
-        let object = Object::Instance(nets, inst_name, inst_type);
-        let index = self.objects.borrow().len();
-        let weak = Rc::downgrade(self);
-        let input_count = object
-            .get_instance_type()
-            .unwrap()
-            .get_input_ports()
-            .into_iter()
-            .count();
-        let operands = vec![None; input_count];
-        let owned_object = Rc::new(RefCell::new(OwnedObject {
-            object,
-            owner: weak,
-            operands,
-            attributes: HashMap::new(),
-            index,
-        }));
-        self.objects.borrow_mut().push(owned_object.clone());
-        Ok(NetRef::wrap(owned_object))
+        old: &[NetRef<I>],
+        working: &Netlist<I>,
+        inputs: &HashMap<Identifier, DrivenNet<I>>,
+        results: &HashMap<Identifier, DrivenNet<I>>,
+        outputs: Vec<(Identifier, DrivenNet<I>, Vec<InputPort<I>>)>,
+    ) -> Result<(), String> {
+        // Free the names of the old instances for the spliced ones
+        let mut used = self.used_names();
+        for node in old {
+            let inst_type = node.get_instance_type().unwrap().clone();
+            let name = fresh_name(&mut used, "region_old", &inst_type);
+            node.set_instance_name(name.clone());
+            for (mut net, port) in node.nets_mut().zip(inst_type.get_output_ports()) {
+                net.set_identifier(format!("{name}_{}", port.get_identifier()).into());
+            }
+        }
+        let mut used = self.used_names();
+
+        // Only the logic feeding the outputs of the region, or anchored in it, is spliced
+        let mut live: HashSet<NetRef<I>> = HashSet::new();
+        let mut stack: Vec<NetRef<I>> = results
+            .values()
+            .map(|d| d.netref.clone())
+            .chain(working.anchors())
+            .collect();
+        while let Some(node) = stack.pop() {
+            if !node.is_an_input() && live.insert(node.clone()) {
+                stack.extend(node.drivers().flatten());
+            }
+        }
+
+        let mut spliced: HashMap<NetRef<I>, NetRef<I>> = HashMap::new();
+        for node in working.objects().filter(|n| live.contains(n)) {
+            let copy = match node.get_instance_type() {
+                Some(inst_type) => {
+                    let name = node.get_instance_name().unwrap();
+                    let name = if used.contains(&name) {
+                        fresh_name(&mut used, &plain_name(&name), &*inst_type)
+                    } else {
+                        used.insert(name.clone());
+                        name
+                    };
+                    self.insert_gate_disconnected(inst_type.clone(), name)?
+                }
+                None => {
+                    let net = node.as_net().clone();
+                    let mut name = net.get_identifier().clone();
+                    let mut k = 0;
+                    while used.contains(&name) {
+                        name = format!("{}_{k}", plain_name(net.get_identifier())).into();
+                        k += 1;
+                    }
+                    used.insert(name.clone());
+                    self.insert_alias_disconnected(net.with_name(name))
+                }
+            };
+            if !copy.is_an_alias() {
+                for (mut net, want) in copy.nets_mut().zip(node.nets()) {
+                    if used.insert(want.get_identifier().clone()) {
+                        net.set_identifier(want.get_identifier().clone());
+                    }
+                }
+            }
+            copy.copy_annotations_from(&node);
+            spliced.insert(node, copy);
+        }
+
+        let resolve = |d: &DrivenNet<I>| match spliced.get(&d.netref) {
+            Some(copy) => copy.get_output(d.pos),
+            None => inputs[&d.get_identifier()].clone(),
+        };
+        for node in working.objects().filter(|n| live.contains(n)) {
+            for (i, driver) in node.operands() {
+                if let Some(driver) = driver {
+                    spliced[&node].get_input(i).connect(resolve(&driver));
+                }
+            }
+        }
+
+        let top = self.outputs();
+        for (name, old, loads) in outputs {
+            let new = resolve(&results[&name]);
+            for load in loads {
+                load.connect(new.clone());
+            }
+            for (_, net) in top
+                .iter()
+                .filter(|(d, _)| d.get_operand() == old.get_operand())
+            {
+                self.remove_output(&old.get_operand());
+                new.clone().expose_with_name(net.get_identifier().clone());
+            }
+        }
+        Ok(())
     }
 
     /// Returns the driving node at input position `index` for `netref`
@@ -1223,48 +4465,148 @@ where
     }
 
     /// Set an added object as a top-level output.
-    /// Panics if `net`` is a multi-output node.
+    /// If the net already drives an output with another name, the [DuplicateOutputPolicy] decides what happens.
+    /// Panics if `net`` is a multi-output node, or if the netlist is locked (see [Netlist::try_expose_net_with_name]).
     pub fn expose_net_with_name(&self, net: DrivenNet<I>, name: Identifier) -> DrivenNet<I> {
-        let mut outputs = self.outputs.borrow_mut();
-        outputs.insert(net.get_operand(), net.as_net().with_name(name));
+        self.assert_unlocked();
+        let existing = self.outputs.borrow().get(&net.get_operand()).cloned();
+        if let Some(old) = existing
+            && *old.get_identifier() != name
+        {
+            let policy = self.duplicate_outputs.get();
+            if policy == DuplicateOutputPolicy::Alias
+                && let Ok(netlist) = net.netref.try_get_netlist()
+                && let Ok(alias) = netlist.insert_alias(net.clone(), name.clone())
+            {
+                self.expose_net_with_name(alias, name);
+                return net;
+            }
+            if policy != DuplicateOutputPolicy::Rebind {
+                self.rejected_outputs
+                    .borrow_mut()
+                    .push((name, old.get_identifier().clone()));
+                return net;
+            }
+        }
+        let output = net.as_net().with_name(name);
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::Expose {
+            driver: net.get_identifier(),
+            name: output.get_identifier().clone(),
+        });
+        self.insert_output(net.get_operand(), output);
         net
     }
 
+    /// Sets an added object as a top-level output, like [Netlist::expose_net_with_name], or returns [NetlistError::Locked] if the netlist is locked
+    pub fn try_expose_net_with_name(
+        &self,
+        net: DrivenNet<I>,
+        name: Identifier,
+    ) -> Result<DrivenNet<I>, NetlistError<I>> {
+        self.check_unlocked()?;
+        Ok(self.expose_net_with_name(net, name))
+    }
+
     /// Set an added object as a top-level output.
     pub fn expose_net(&self, net: DrivenNet<I>) -> Result<DrivenNet<I>, String> {
+        self.check_unlocked()?;
         if net.is_an_input() {
             return Err(
                 "Cannot expose an input net as output without a new name to bind to".to_string(),
             );
         }
-        let mut outputs = self.outputs.borrow_mut();
-        outputs.insert(net.get_operand(), net.as_net().clone());
+        let output = net.as_net().clone();
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::Expose {
+            driver: net.get_identifier(),
+            name: net.get_identifier(),
+        });
+        self.insert_output(net.get_operand(), output);
         Ok(net)
     }
 
+    /// Maps `operand` to the top-level output `net` and declares it on the module boundary
+    fn insert_output(&self, operand: Operand, net: Net) {
+        let id = net.get_identifier().clone();
+        let old = self.outputs.borrow_mut().insert(operand, net);
+        if let Some(old) = old {
+            if *old.get_identifier() == id {
+                return;
+            }
+            self.remove_output_port_bit(old.get_identifier());
+        }
+        let port = PortDecl::<I>::port_name(&id);
+        self.declare_port_bit(port, PortDirection::Output, PortBit::Output(id));
+    }
+
+    /// Removes the top-level output `operand` from the module boundary
+    fn remove_output(&self, operand: &Operand) -> Option<Net> {
+        let net = self.outputs.borrow_mut().remove(operand)?;
+        self.remove_output_port_bit(net.get_identifier());
+        Some(net)
+    }
+
+    /// Adds a bit to the port `name`, declaring the port if it does not exist yet
+    fn declare_port_bit(&self, name: Identifier, direction: PortDirection, bit: PortBit<I>) {
+        let mut ports = self.ports.borrow_mut();
+        match ports
+            .iter_mut()
+            .find(|p| p.name == name && p.direction == direction)
+        {
+            Some(port) => match bit.get_bit_index() {
+                // Keep the bits of a vector lsb first, regardless of the order they are added in
+                Some(index) => {
+                    let pos = port
+                        .bits
+                        .iter()
+                        .position(|b| b.get_bit_index().is_some_and(|i| i > index))
+                        .unwrap_or(port.bits.len());
+                    port.bits.insert(pos, bit);
+                }
+                None => port.bits.push(bit),
+            },
+            None => ports.push(PortDecl {
+                name,
+                direction,
+                bits: vec![bit],
+                attributes: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Removes the output bit named `id` from the module boundary, removing the port if it becomes empty
+    fn remove_output_port_bit(&self, id: &Identifier) {
+        let mut ports = self.ports.borrow_mut();
+        for port in ports.iter_mut() {
+            port.bits
+                .retain(|b| !matches!(b, PortBit::Output(o) if o == id));
+        }
+        ports.retain(|p| !p.bits.is_empty());
+    }
+
+    /// Unlink a circuit node from the rest of the netlist. Return the object that was being stored.
+    /// Any top-level outputs driven by the node are dropped.
+    pub fn delete_net_uses(&self, netref: NetRef<I>) -> Result<Object<I>, NetlistError<I>> {
+        self.delete_net_uses_with(netref, OutputPolicy::Drop)
+    }
+
     /// Unlink a circuit node from the rest of the netlist. Return the object that was being stored.
-    pub fn delete_net_uses(&self, netref: NetRef<I>) -> Result<Object<I>, String> {
+    /// Top-level outputs driven by the node are handled according to `policy`.
+    /// A net drives at most one output, so [OutputPolicy::Rebind] fails with [NetlistError::RebindTaken],
+    /// leaving the netlist unchanged, if the new driver already drives an output or the node drives several.
+    pub fn delete_net_uses_with(
+        &self,
+        netref: NetRef<I>,
+        policy: OutputPolicy<I>,
+    ) -> Result<Object<I>, NetlistError<I>> {
+        self.check_unlocked()?;
         let unwrapped = netref.clone().unwrap();
         if Rc::strong_count(&unwrapped) > 3 {
-            return Err("Cannot delete. References still exist on this node".to_string());
+            drop(unwrapped);
+            return Err(NetlistError::ReferencesExist(netref));
         }
         let old_index = unwrapped.borrow().get_index();
-        let objects = self.objects.borrow();
-        for oref in objects.iter() {
-            let operands = &mut oref.borrow_mut().operands;
-            for operand in operands.iter_mut() {
-                if let Some(op) = operand {
-                    match op {
-                        Operand::DirectIndex(idx) | Operand::CellIndex(idx, _)
-                            if *idx == old_index =>
-                        {
-                            *operand = None;
-                        }
-                        _ => (),
-                    }
-                }
-            }
-        }
 
         let outputs: Vec<Operand> = self
             .outputs
@@ -1276,19 +4618,271 @@ where
             .cloned()
             .collect();
 
+        let rebind = match &policy {
+            OutputPolicy::Error if !outputs.is_empty() => {
+                drop(unwrapped);
+                return Err(NetlistError::DrivesOutput(netref));
+            }
+            OutputPolicy::Rebind(with) => {
+                let new_operand = with.get_operand();
+                if new_operand.root() == old_index {
+                    drop(unwrapped);
+                    return Err(NetlistError::RebindToSelf(netref));
+                }
+                let taken = self.outputs.borrow().contains_key(&new_operand);
+                if !outputs.is_empty() && (taken || outputs.len() > 1) {
+                    drop(unwrapped);
+                    return Err(NetlistError::RebindTaken(netref));
+                }
+                Some(new_operand)
+            }
+            _ => None,
+        };
+
+        let objects = self.objects.borrow();
+        for oref in objects.iter() {
+            oref.borrow_mut()
+                .map_operands(|op| (op.root() == old_index).then_some(None));
+        }
+
         for operand in outputs {
-            self.outputs.borrow_mut().remove(&operand);
+            match &rebind {
+                Some(new_operand) => {
+                    let net = self.outputs.borrow_mut().remove(&operand);
+                    if let Some(net) = net {
+                        self.outputs.borrow_mut().insert(new_operand.clone(), net);
+                    }
+                }
+                None => {
+                    self.remove_output(&operand);
+                }
+            }
         }
 
+        self.forget_side_entries(old_index);
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::DeleteUses {
+            node: journal::object_name(netref.netref.borrow().get()),
+            rebind: match &policy {
+                OutputPolicy::Rebind(with) => Some(with.get_identifier()),
+                _ => None,
+            },
+        });
         Ok(netref.unwrap().borrow().get().clone())
     }
 
+    /// Replaces the type of the instance `inst` with `inst_type`, which must have the same input ports
+    /// and the same output ports followed by new ones. Existing connections are kept.
+    /// Returns the nets driven by the new output ports, which are named like those of [Netlist::insert_gate].
+    pub fn extend_instance(
+        &self,
+        inst: &NetRef<I>,
+        inst_type: I,
+    ) -> Result<Vec<DrivenNet<I>>, String> {
+        self.check_unlocked()?;
+        let old = inst
+            .get_instance_type()
+            .ok_or(format!("{inst} is not an instance"))?
+            .clone();
+        let inst_name = inst.get_instance_name().unwrap();
+        let ids = |ports: Vec<&Net>| -> Vec<Identifier> {
+            ports.iter().map(|p| p.get_identifier().clone()).collect()
+        };
+        let old_outputs = ids(old.get_output_ports().into_iter().collect());
+        let new_outputs: Vec<Net> = inst_type.get_output_ports().into_iter().cloned().collect();
+        if ids(old.get_input_ports().into_iter().collect())
+            != ids(inst_type.get_input_ports().into_iter().collect())
+        {
+            return Err(format!("The input ports of {inst_name} must stay the same"));
+        }
+        if !ids(new_outputs.iter().collect()).starts_with(&old_outputs) {
+            return Err(format!(
+                "The output ports of {inst_name} must stay the same, with new ones after them"
+            ));
+        }
+        let nets: Vec<Net> = new_outputs[old_outputs.len()..]
+            .iter()
+            .map(|p| p.with_name(format!("{}_{}", inst_name, p.get_identifier()).into()))
+            .collect();
+        self.check_new_drivers(None, &nets)?;
+
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::ExtendInstance {
+            name: inst_name.clone(),
+            inst_type: inst_type.clone(),
+        });
+        let was_multi = inst.is_multi_output();
+        let index = {
+            let mut owned = inst.netref.borrow_mut();
+            if let Object::Instance(outputs, _, t) = owned.get_mut() {
+                outputs.extend(nets);
+                *t = inst_type;
+            }
+            owned.get_index()
+        };
+        if !was_multi && inst.is_multi_output() {
+            // Operands of single-output nodes do not name the output, so they are rewritten
+            let direct = Operand::DirectIndex(index);
+            let cell = Operand::CellIndex(index, 0);
+            for oref in self.objects.borrow().iter() {
+                oref.borrow_mut()
+                    .map_operands(|op| (*op == direct).then(|| Some(cell.clone())));
+            }
+            let mut outputs = self.outputs.borrow_mut();
+            if let Some(net) = outputs.remove(&direct) {
+                outputs.insert(cell, net);
+            }
+        }
+        self.strash.replace(None);
+        self.type_index.replace(None);
+        Ok((old_outputs.len()..new_outputs.len())
+            .map(|i| DrivenNet::new(i, inst.clone()))
+            .collect())
+    }
+
+    /// Replaces every instance of a cell in `map` with its new cell, keeping the connections of the renamed ports.
+    /// Input ports of the new cell without an old port are left unconnected, and every output port must map to one of the new cell.
+    /// No two ports of an old cell may map to the same port of the new cell.
+    /// The nets driven by the instances keep their names. Instances whose new cell `model` does not accept in place of the old one are kept.
+    /// The netlist is only changed if every instance can be replaced. Returns the number of instances that were replaced.
+    pub fn substitute_cells(
+        &self,
+        map: &CellMap<I>,
+        model: &dyn CostModel<I>,
+    ) -> Result<usize, String> {
+        self.check_unlocked()?;
+        let position = |ports: Vec<&Net>, name: &Identifier| {
+            ports.iter().position(|p| p.get_identifier() == name)
+        };
+
+        // The new positions of the inputs and the outputs of every instance
+        let mut plans = Vec::new();
+        for inst in self.objects() {
+            let Some(old) = inst.get_instance_type().map(|t| t.clone()) else {
+                continue;
+            };
+            let Some(cell) = map.get(old.get_name()) else {
+                continue;
+            };
+            if !model.accepts(&model.cost(&old), &model.cost(cell)) {
+                continue;
+            }
+            let inst_name = inst.get_instance_name().unwrap();
+            // Two old ports that land on the same new port would be merged
+            let mut pins = HashSet::new();
+            for port in old
+                .get_input_ports()
+                .into_iter()
+                .chain(old.get_output_ports())
+            {
+                let pin = map.get_pin(old.get_name(), port.get_identifier()).unwrap();
+                if !pins.insert(pin.clone()) {
+                    return Err(format!(
+                        "More than one port of {} maps to port {pin} of {}",
+                        old.get_name(),
+                        cell.get_name()
+                    ));
+                }
+            }
+            let mut inputs = Vec::new();
+            for (i, driver) in inst.operands() {
+                let port = old.get_input_port(i).get_identifier();
+                let pin = map.get_pin(old.get_name(), port).unwrap();
+                match position(cell.get_input_ports().into_iter().collect(), &pin) {
+                    Some(j) => inputs.push(Some(j)),
+                    None if driver.is_none() => inputs.push(None),
+                    None => {
+                        return Err(format!(
+                            "Input {port} of {inst_name} has no port {pin} on {}",
+                            cell.get_name()
+                        ));
+                    }
+                }
+            }
+            let new_outputs: Vec<&Net> = cell.get_output_ports().into_iter().collect();
+            let mut outputs = Vec::new();
+            for port in old.get_output_ports() {
+                let pin = map.get_pin(old.get_name(), port.get_identifier()).unwrap();
+                match position(new_outputs.clone(), &pin) {
+                    Some(j) => outputs.push(j),
+                    None => {
+                        return Err(format!(
+                            "Output {port} of {inst_name} has no port {pin} on {}",
+                            cell.get_name()
+                        ));
+                    }
+                }
+            }
+            if new_outputs.len() != outputs.len() {
+                return Err(format!(
+                    "{} has {} outputs, but {inst_name} has {}",
+                    cell.get_name(),
+                    new_outputs.len(),
+                    outputs.len()
+                ));
+            }
+            plans.push((inst, cell.clone(), inputs, outputs));
+        }
+
+        let count = plans.len();
+        for (inst, cell, inputs, outputs) in plans {
+            let multi = inst.is_multi_output();
+            let index = {
+                let mut owned = inst.netref.borrow_mut();
+                let old_operands = Rc::unwrap_or_clone(std::mem::take(&mut owned.operands));
+                let mut operands = vec![None; cell.get_input_ports().into_iter().count()];
+                for (operand, j) in old_operands.into_iter().zip(&inputs) {
+                    if let Some(j) = j {
+                        operands[*j] = operand;
+                    }
+                }
+                owned.operands = Rc::new(operands);
+                if let Object::Instance(nets, _, t) = owned.get_mut() {
+                    let mut new_nets = nets.clone();
+                    for (old_j, new_j) in outputs.iter().enumerate() {
+                        let port = cell.get_output_port(*new_j);
+                        new_nets[*new_j] = port.with_name(nets[old_j].get_identifier().clone());
+                    }
+                    *nets = new_nets;
+                    *t = cell;
+                }
+                owned.get_index()
+            };
+            if multi {
+                // Operands of multi-output nodes name the output, so they follow it to its new position
+                let remap = |op: &Operand| match op {
+                    Operand::CellIndex(i, j) if *i == index => {
+                        Some(Operand::CellIndex(index, outputs[*j]))
+                    }
+                    _ => None,
+                };
+                for oref in self.objects.borrow().iter() {
+                    oref.borrow_mut().map_operands(|op| remap(op).map(Some));
+                }
+                let pairs: Vec<_> = self.outputs.take().into_iter().collect();
+                for (op, net) in pairs {
+                    let op = remap(&op).unwrap_or(op);
+                    self.outputs.borrow_mut().insert(op, net);
+                }
+            }
+        }
+        self.strash.replace(None);
+        self.type_index.replace(None);
+        Ok(count)
+    }
+
     /// Replaces the uses of a circuit node with another circuit node. The [Object] stored at `of` is returned.
     /// Panics if `of` and  `with` are not single-output nodes.
-    pub fn replace_net_uses(&self, of: NetRef<I>, with: &NetRef<I>) -> Result<Object<I>, String> {
+    pub fn replace_net_uses(
+        &self,
+        of: NetRef<I>,
+        with: &NetRef<I>,
+    ) -> Result<Object<I>, NetlistError<I>> {
+        self.check_unlocked()?;
         let unwrapped = of.clone().unwrap();
         if Rc::strong_count(&unwrapped) > 3 {
-            return Err("Cannot replace. References still exist on this node".to_string());
+            drop(unwrapped);
+            return Err(NetlistError::ReferencesExist(of));
         }
 
         let old_tag: DrivenNet<I> = of.clone().into();
@@ -1297,25 +4891,27 @@ where
         let new_index = new_tag.get_operand();
         let objects = self.objects.borrow();
         for oref in objects.iter() {
-            let operands = &mut oref.borrow_mut().operands;
-            for operand in operands.iter_mut() {
-                if let Some(op) = operand {
-                    if *op == old_index {
-                        *operand = Some(new_index.clone());
-                    }
-                }
-            }
+            oref.borrow_mut()
+                .map_operands(|op| (*op == old_index).then(|| Some(new_index.clone())));
         }
 
         let already_mapped = self.outputs.borrow().contains_key(&new_index);
-        let old_mapping = self.outputs.borrow_mut().remove(&old_index);
 
         if already_mapped {
-            self.outputs.borrow_mut().remove(&old_index);
-        } else if let Some(v) = old_mapping {
-            self.outputs.borrow_mut().insert(new_index, v.clone());
+            self.remove_output(&old_index);
+        } else {
+            let old_mapping = self.outputs.borrow_mut().remove(&old_index);
+            if let Some(v) = old_mapping {
+                self.outputs.borrow_mut().insert(new_index, v);
+            }
         }
 
+        self.forget_side_entries(old_index.root());
+        #[cfg(feature = "journal")]
+        self.record(|| Edit::ReplaceUses {
+            of: journal::object_name(of.netref.borrow().get()),
+            with: journal::object_name(with.netref.borrow().get()),
+        });
         Ok(of.unwrap().borrow().get().clone())
     }
 }
@@ -1342,25 +4938,262 @@ where
 
     /// Returns a list of output nets
     pub fn get_output_ports(&self) -> Vec<Net> {
-        self.outputs.borrow().values().cloned().collect::<Vec<_>>()
+        self.outputs().into_iter().map(|(_, n)| n).collect()
+    }
+
+    /// Returns the ports on the module boundary in the order they were declared
+    pub fn ports(&self) -> Vec<Port> {
+        let outputs: HashMap<Identifier, Net> = self
+            .outputs
+            .borrow()
+            .values()
+            .map(|n| (n.get_identifier().clone(), n.clone()))
+            .collect();
+        self.ports
+            .borrow()
+            .iter()
+            .map(|decl| Port {
+                name: decl.name.clone(),
+                direction: decl.direction,
+                nets: decl
+                    .bits
+                    .iter()
+                    .filter_map(|b| match b {
+                        PortBit::Input(w) => w.upgrade().map(|o| o.borrow().as_net().clone()),
+                        PortBit::Output(id) => outputs.get(id).cloned(),
+                    })
+                    .collect(),
+                attributes: Attribute::from_pairs(decl.attributes.clone().into_iter()).collect(),
+            })
+            .collect()
+    }
+
+    /// Checks that `other` has the same interface, so one can be swapped for the other where the module is instantiated.
+    /// Ports are matched by name, and must agree in direction and width. The order of the ports does not matter.
+    /// Returns every mismatch, with the ports of this netlist first.
+    pub fn interface_matches<J: Instantiable>(
+        &self,
+        other: &Netlist<J>,
+    ) -> Result<(), Vec<Mismatch>> {
+        let theirs: HashMap<Identifier, Port> = other
+            .ports()
+            .into_iter()
+            .map(|p| (p.get_name().clone(), p))
+            .collect();
+        let ours = self.ports();
+        let mut mismatches = Vec::new();
+        for port in &ours {
+            let name = port.get_name().clone();
+            let Some(other) = theirs.get(&name) else {
+                mismatches.push(Mismatch::Missing(name));
+                continue;
+            };
+            if port.get_direction() != other.get_direction() {
+                mismatches.push(Mismatch::Direction {
+                    port: name.clone(),
+                    expected: port.get_direction(),
+                    found: other.get_direction(),
+                });
+            }
+            if port.width() != other.width() {
+                mismatches.push(Mismatch::Width {
+                    port: name,
+                    expected: port.width(),
+                    found: other.width(),
+                });
+            }
+        }
+        let names: HashSet<&Identifier> = ours.iter().map(|p| p.get_name()).collect();
+        mismatches.extend(
+            other
+                .ports()
+                .into_iter()
+                .filter(|p| !names.contains(p.get_name()))
+                .map(|p| Mismatch::Extra(p.get_name().clone())),
+        );
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(mismatches),
+        }
+    }
+
+    /// Returns the port with the name `name`, if it exists
+    pub fn find_port(&self, name: &Identifier) -> Option<Port> {
+        self.ports().into_iter().find(|p| p.get_name() == name)
+    }
+
+    /// Sets an attribute on the port `name`, returning the previous value of the attribute
+    pub fn set_port_attribute(
+        &self,
+        name: &Identifier,
+        k: AttributeKey,
+        v: AttributeValue,
+    ) -> Result<Option<AttributeValue>, String> {
+        self.check_unlocked()?;
+        let mut ports = self.ports.borrow_mut();
+        let port = ports
+            .iter_mut()
+            .find(|p| p.name == *name)
+            .ok_or(format!("Port {name} not found"))?;
+        Ok(port.attributes.insert(k, v))
+    }
+
+    /// Clears the attribute with the given key on the port `name`, like [Netlist::clear_port_attribute],
+    /// or returns [NetlistError::Locked] if the netlist is locked
+    pub fn try_clear_port_attribute(
+        &self,
+        name: &Identifier,
+        k: &AttributeKey,
+    ) -> Result<Option<AttributeValue>, NetlistError<I>> {
+        self.check_unlocked()?;
+        Ok(self.clear_port_attribute(name, k))
+    }
+
+    /// Clears the attribute with the given key on the port `name`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the netlist is locked, see [Netlist::try_clear_port_attribute].
+    pub fn clear_port_attribute(
+        &self,
+        name: &Identifier,
+        k: &AttributeKey,
+    ) -> Option<AttributeValue> {
+        self.assert_unlocked();
+        self.ports
+            .borrow_mut()
+            .iter_mut()
+            .find(|p| p.name == *name)?
+            .attributes
+            .remove(k)
+    }
+
+    /// Constructs an analysis of the netlist.
+    pub fn get_analysis<'a, A: Analysis<'a, I>>(&'a self) -> Result<A, String> {
+        A::build(self)
+    }
+
+    /// Finds the first circuit node that drives the `net`. This operation is O(n).
+    /// This should be unique provided the netlist is well-formed.
+    pub fn find_net(&self, net: &Net) -> Option<DrivenNet<I>> {
+        for obj in self.objects() {
+            for o in obj.outputs() {
+                if *o.as_net() == *net {
+                    return Some(o);
+                }
+            }
+        }
+        None
+    }
+
+    /// Finds the instance with the name `name`. This operation is O(n).
+    pub fn find_instance(&self, name: &Identifier) -> Option<NetRef<I>> {
+        self.objects()
+            .find(|o| o.get_instance_name().is_some_and(|n| n == *name))
+    }
+
+    /// Returns the hierarchical path to the top of this netlist
+    pub fn get_path(&self) -> HierPath {
+        HierPath::new(vec![self.name.as_str().into()])
+    }
+
+    /// Resolves a hierarchical path, like `top/inst/Y` or `top/net`, to an object in the netlist.
+    /// The first segment of the path must match the name of this netlist.
+    /// Paths into submodules are resolved by [crate::hier::resolve_path].
+    /// This operation is O(n).
+    pub fn resolve_path(&self, path: &HierPath) -> Option<PathTarget<I>> {
+        let (root, rest) = path.segments().split_first()?;
+        if root.get_name() != self.name {
+            return None;
+        }
+        match rest {
+            [name] => {
+                if let Some(inst) = self.find_instance(name) {
+                    return Some(PathTarget::Instance(inst));
+                }
+                self.objects()
+                    .flat_map(|o| o.outputs().collect::<Vec<_>>())
+                    .find(|o| o.get_identifier() == *name)
+                    .map(PathTarget::Net)
+            }
+            [inst, port] => {
+                let inst = self.find_instance(inst)?;
+                if let Some(output) = inst.find_output(port) {
+                    return Some(PathTarget::Net(output));
+                }
+                inst.find_input(port).map(PathTarget::InputPort)
+            }
+            _ => None,
+        }
     }
 
-    /// Constructs an analysis of the netlist.
-    pub fn get_analysis<'a, A: Analysis<'a, I>>(&'a self) -> Result<A, String> {
-        A::build(self)
+    /// Elaborates the netlist by binding per-instance parameter overrides.
+    /// `overrides` maps instance names to the parameters to set on them.
+    /// Only the types of the instances in this netlist are changed, so overriding an instance of a [crate::hier::Submodule] is an error.
+    /// Use [crate::hier::elaborate] to specialize submodules into copies of their modules.
+    /// All overrides are applied to copies of the instance types before any instance is changed, so the netlist is left untouched on error.
+    pub fn elaborate(
+        &self,
+        overrides: &HashMap<Identifier, Vec<(Identifier, Parameter)>>,
+    ) -> Result<(), String> {
+        self.check_unlocked()?;
+        let mut bindings = Vec::new();
+        for (inst_name, params) in overrides {
+            let inst = self
+                .find_instance(inst_name)
+                .ok_or(format!("Instance {inst_name} not found"))?;
+            let mut inst_type = inst.get_instance_type().unwrap().clone();
+            for (id, val) in params {
+                if !inst_type.has_parameter(id) {
+                    return Err(format!("Instance {inst_name} has no parameter {id}"));
+                }
+                if inst_type.set_parameter(id, val.clone()).is_none() {
+                    return Err(format!(
+                        "Instance type {} does not support overriding parameter {id}",
+                        inst_type.get_name()
+                    ));
+                }
+            }
+            bindings.push((inst, inst_type));
+        }
+
+        for (inst, inst_type) in bindings {
+            inst.with_instance_type_mut(|t| *t = inst_type);
+        }
+        Ok(())
     }
 
-    /// Finds the first circuit node that drives the `net`. This operation is O(n).
-    /// This should be unique provided the netlist is well-formed.
-    pub fn find_net(&self, net: &Net) -> Option<DrivenNet<I>> {
-        for obj in self.objects() {
-            for o in obj.outputs() {
-                if *o.as_net() == *net {
-                    return Some(o);
-                }
+    /// Renames the port `old` to `new` on every instance of the primitive `type_name`, returning the number of instances changed.
+    /// Connections are kept, so emission and lookups by port name pick up the new name.
+    /// All instances are checked before any are changed, so the netlist is left untouched on error.
+    pub fn rename_port(
+        &self,
+        type_name: &Identifier,
+        old: &Identifier,
+        new: Identifier,
+    ) -> Result<usize, String> {
+        self.check_unlocked()?;
+        let instances: Vec<NetRef<I>> = self
+            .objects()
+            .filter(|o| {
+                o.get_instance_type()
+                    .is_some_and(|t| t.get_name() == type_name)
+            })
+            .collect();
+        for inst in &instances {
+            let mut inst_type = inst.get_instance_type().unwrap().clone();
+            if !inst_type.rename_port(old, new.clone()) {
+                return Err(format!(
+                    "Cannot rename port {old} to {new} on instance {}",
+                    inst.get_instance_name().unwrap()
+                ));
             }
         }
-        None
+
+        for inst in &instances {
+            inst.with_instance_type_mut(|t| t.rename_port(old, new.clone()));
+        }
+        Ok(instances.len())
     }
 
     /// Returns a `NetRef` to the first circuit node
@@ -1391,10 +5224,13 @@ where
     }
 
     /// Cleans unused nodes from the netlist, returning `Ok(true)` if the netlist changed.
-    pub fn clean_once(&self) -> Result<bool, String> {
+    pub fn clean_once(&self) -> Result<bool, NetlistError<I>> {
+        self.check_unlocked()?;
         let mut dead_objs = HashSet::new();
         {
-            let fan_out = self.get_analysis::<FanOutTable<I>>()?;
+            let fan_out = self
+                .get_analysis::<FanOutTable<I>>()
+                .map_err(NetlistError::Analysis)?;
             for obj in self.objects() {
                 let mut is_dead = true;
                 for net in obj.nets() {
@@ -1404,7 +5240,8 @@ where
                         break;
                     }
                 }
-                if is_dead && !obj.is_an_input() {
+                if is_dead && !obj.is_an_input() && obj.get_supply().is_none() && !obj.is_anchored()
+                {
                     dead_objs.insert(obj.unwrap().borrow().index);
                 }
             }
@@ -1413,18 +5250,30 @@ where
         if dead_objs.is_empty() {
             return Ok(false);
         }
+        self.remove_objects(&dead_objs)?;
+        Ok(true)
+    }
+
+    /// Removes the objects at the indices in `dead`, which must not be used by other objects
+    fn remove_objects(&self, dead: &HashSet<usize>) -> Result<(), NetlistError<I>> {
+        // Nothing is removed unless every dead object can be
+        for (index, obj) in self.objects.borrow().iter().enumerate() {
+            if dead.contains(&index) && Rc::strong_count(obj) > 2 {
+                return Err(NetlistError::ReferencesExist(NetRef::wrap(obj.clone())));
+            }
+        }
 
         let old_objects = self.objects.take();
         let mut remap: HashMap<usize, usize> = HashMap::new();
         for (old_index, obj) in old_objects.into_iter().enumerate() {
-            if dead_objs.contains(&old_index) {
-                if Rc::strong_count(&obj) > 2 {
-                    return Err(format!(
-                        "Cannot delete object {} as a NetRef still exists, or it is an output. SC = {}",
-                        obj.borrow().get(),
-                        Rc::strong_count(&obj)
-                    ));
+            if dead.contains(&old_index) {
+                if let Some(budget) = self.budget.borrow_mut().as_mut()
+                    && let Some(inst_type) = obj.borrow().get().get_instance_type()
+                {
+                    budget.refund(inst_type);
                 }
+                #[cfg(feature = "journal")]
+                self.record(|| Edit::Remove(journal::object_name(obj.borrow().get())));
                 continue;
             }
             let new_index = self.objects.borrow().len();
@@ -1432,13 +5281,29 @@ where
             obj.borrow_mut().index = new_index;
             self.objects.borrow_mut().push(obj);
         }
+        self.apply_remap(&remap);
+        Ok(())
+    }
+
+    /// Removes the objects inserted by an edit that failed partway, whose handles in `inserted` are the only ones left
+    fn roll_back(&self, inserted: Vec<NetRef<I>>) -> Result<(), NetlistError<I>> {
+        let dead: HashSet<usize> = inserted.iter().map(|n| n.netref.borrow().index).collect();
+        drop(inserted);
+        self.remove_objects(&dead)
+    }
+
+    /// Fixes up everything that refers to objects by index, after the objects moved from the old to the new indices in `remap`
+    fn apply_remap(&self, remap: &HashMap<usize, usize>) {
+        // Object indices change, so the indices are rebuilt on next use
+        self.strash.replace(None);
+        self.type_index.replace(None);
 
         for obj in self.objects.borrow().iter() {
-            for operand in obj.borrow_mut().inds_mut() {
+            obj.borrow_mut().map_operands(|operand| {
                 let root = operand.root();
-                let root = *remap.get(&root).unwrap_or(&root);
-                *operand = operand.clone().remap(root);
-            }
+                let new_root = *remap.get(&root).unwrap_or(&root);
+                (new_root != root).then(|| Some(operand.clone().remap(new_root)))
+            });
         }
 
         let pairs: Vec<_> = self.outputs.take().into_iter().collect();
@@ -1449,12 +5314,270 @@ where
             self.outputs.borrow_mut().insert(new_operand, net);
         }
 
-        Ok(true)
+        for table in self.side_tables.borrow_mut().values_mut() {
+            table.remap(remap);
+        }
+    }
+
+    /// Reorders the objects of the netlist to `order`, which must list every object exactly once.
+    /// Connections, outputs and side tables are preserved, so only the storage and emission order changes.
+    pub fn permute_objects(&self, order: &[NetRef<I>]) -> Result<(), String> {
+        self.check_unlocked()?;
+        let len = self.objects.borrow().len();
+        if order.len() != len {
+            return Err(format!(
+                "Expected an order of {len} objects, got {}",
+                order.len()
+            ));
+        }
+        let mut remap: HashMap<usize, usize> = HashMap::new();
+        for (new_index, node) in order.iter().enumerate() {
+            let obj = node.netref.borrow();
+            if !std::ptr::eq(obj.owner.as_ptr(), self) {
+                return Err(format!(
+                    "{node} does not belong to netlist {}",
+                    self.get_name()
+                ));
+            }
+            if remap.insert(obj.index, new_index).is_some() {
+                return Err(format!("{node} appears more than once in the order"));
+            }
+        }
+
+        let mut objects = self.objects.take();
+        objects.sort_by_key(|obj| remap[&obj.borrow().index]);
+        for (index, obj) in objects.iter().enumerate() {
+            obj.borrow_mut().index = index;
+        }
+        self.objects.replace(objects);
+        self.apply_remap(&remap);
+        Ok(())
+    }
+
+    /// Takes a checkpoint of the objects, connections, outputs, ports, input constraints and settings of the netlist.
+    /// Restoring it with [Netlist::restore] keeps existing handles to surviving objects valid,
+    /// so optimizers can revert bad edits without a deep clone of the whole netlist.
+    /// The snapshot shares the state of each object with the netlist, and an edit copies the part of an object it changes.
+    pub fn snapshot(&self) -> Snapshot<I> {
+        let objects = self
+            .objects
+            .borrow()
+            .iter()
+            .map(|obj| {
+                let owned = obj.borrow();
+                SavedObject {
+                    handle: Rc::downgrade(obj),
+                    object: owned.object.clone(),
+                    owner: owned.owner.clone(),
+                    operands: owned.operands.clone(),
+                    attributes: owned.attributes.clone(),
+                    properties: owned.properties.clone(),
+                }
+            })
+            .collect();
+        Snapshot {
+            objects,
+            outputs: self.outputs.borrow().clone(),
+            ports: self.ports.borrow().clone(),
+            rejected_outputs: self.rejected_outputs.borrow().clone(),
+            input_constraints: self.input_constraints.borrow().clone(),
+            check_drivers: self.check_drivers.get(),
+            duplicate_outputs: self.duplicate_outputs.get(),
+            two_state: self.two_state.get(),
+        }
+    }
+
+    /// Returns a copy of the netlist named `name`, with its own objects, connections, outputs, ports, input constraints and settings.
+    /// Edits to the copy do not change this netlist, and the other way around.
+    /// Side tables, the budget, locks and the journal are not copied.
+    pub fn duplicate(&self, name: String) -> Rc<Self> {
+        let copy = Self::new(name);
+        let objects: Vec<NetRefT<I>> = self
+            .objects
+            .borrow()
+            .iter()
+            .map(|obj| {
+                let owned = obj.borrow();
+                Rc::new(RefCell::new(OwnedObject {
+                    object: owned.object.clone(),
+                    owner: Rc::downgrade(&copy),
+                    operands: owned.operands.clone(),
+                    attributes: owned.attributes.clone(),
+                    properties: owned.properties.clone(),
+                    index: owned.index,
+                    mark: 0,
+                }))
+            })
+            .collect();
+        let mut ports = self.ports.borrow().clone();
+        for bit in ports.iter_mut().flat_map(|p| p.bits.iter_mut()) {
+            // Input bits point at the objects of this netlist, so they are moved to the copies
+            if let PortBit::Input(weak) = bit
+                && let Some(obj) = weak.upgrade()
+            {
+                *weak = Rc::downgrade(&objects[obj.borrow().index]);
+            }
+        }
+        copy.objects.replace(objects);
+        copy.outputs.replace(self.outputs.borrow().clone());
+        copy.ports.replace(ports);
+        copy.rejected_outputs
+            .replace(self.rejected_outputs.borrow().clone());
+        copy.input_constraints
+            .replace(self.input_constraints.borrow().clone());
+        copy.check_drivers.set(self.check_drivers.get());
+        copy.duplicate_outputs.set(self.duplicate_outputs.get());
+        copy.two_state.set(self.two_state.get());
+        copy
+    }
+
+    /// Locks the netlist against edits until the returned guard is dropped, so a read-heavy phase cannot change it by accident.
+    /// While locked, edits that return a [Result] fail with [NetlistError::Locked], like [Netlist::try_insert_input] and [DrivenNet::try_connect].
+    /// The edits that cannot fail, like [Netlist::insert_input], [DrivenNet::connect], renames and attribute and parameter edits, panic.
+    /// Locks can be nested, and the netlist is unlocked when the last one is dropped.
+    pub fn lock(&self) -> NetlistLock<'_, I> {
+        self.locks.set(self.locks.get() + 1);
+        NetlistLock { netlist: self }
+    }
+
+    /// Returns `true` if the netlist is locked with [Netlist::lock]
+    pub fn is_locked(&self) -> bool {
+        self.locks.get() > 0
+    }
+
+    /// Returns [NetlistError::Locked] if the netlist is locked
+    fn check_unlocked(&self) -> Result<(), NetlistError<I>> {
+        match self.is_locked() {
+            true => Err(NetlistError::Locked(self.name.clone())),
+            false => Ok(()),
+        }
+    }
+
+    /// Panics if the netlist is locked, for edits that cannot fail
+    fn assert_unlocked(&self) {
+        if let Err(e) = self.check_unlocked() {
+            panic!("{e}");
+        }
+    }
+
+    /// Returns an immutable copy of the netlist in flat arrays, which can be shared across threads.
+    /// Later edits to the netlist do not change the copy.
+    pub fn freeze(&self) -> FrozenNetlist<I> {
+        let frozen = |op: &Operand| FrozenNet::new(op.root(), op.secondary());
+        let objects = self
+            .objects
+            .borrow()
+            .iter()
+            .map(|obj| {
+                let owned = obj.borrow();
+                let mut attributes: Vec<Attribute> = owned.attributes().collect();
+                attributes.sort_by(|a, b| a.key().cmp(b.key()));
+                let operands = owned
+                    .operands
+                    .iter()
+                    .map(|o| o.as_ref().map(frozen))
+                    .collect();
+                FrozenObject::new(
+                    (*owned.object).clone(),
+                    operands,
+                    attributes,
+                    owned.properties.init,
+                )
+            })
+            .collect();
+        let outputs = self
+            .outputs()
+            .into_iter()
+            .map(|(driver, net)| (frozen(&driver.get_operand()), net))
+            .collect();
+        FrozenNetlist::new(self.name.clone(), objects, outputs, self.ports())
+    }
+
+    /// Brings the netlist back to the state saved in `snapshot`.
+    /// Objects inserted since are dropped, objects removed since are rebuilt as new objects,
+    /// and side table entries follow the objects that survived. The budget, if set, is recounted.
+    /// Returns an error, and leaves the netlist untouched, if the snapshot was taken of another netlist.
+    pub fn restore(&self, snapshot: &Snapshot<I>) -> Result<(), String> {
+        self.check_unlocked()?;
+        if snapshot
+            .objects
+            .iter()
+            .any(|saved| !std::ptr::eq(saved.owner.as_ptr(), self))
+        {
+            return Err(format!(
+                "The snapshot does not belong to netlist {}",
+                self.get_name()
+            ));
+        }
+
+        let mut remap: HashMap<usize, usize> = HashMap::new();
+        let mut objects = Vec::with_capacity(snapshot.objects.len());
+        let mut rebuilt = Vec::new();
+        for (index, saved) in snapshot.objects.iter().enumerate() {
+            let obj = match saved.handle.upgrade() {
+                Some(obj) => {
+                    remap.insert(obj.borrow().index, index);
+                    obj
+                }
+                None => {
+                    let obj = Rc::new(RefCell::new(OwnedObject {
+                        object: saved.object.clone(),
+                        owner: saved.owner.clone(),
+                        operands: Rc::default(),
+                        attributes: Rc::default(),
+                        properties: Rc::default(),
+                        index,
+                        mark: 0,
+                    }));
+                    rebuilt.push((&saved.handle, Rc::downgrade(&obj)));
+                    obj
+                }
+            };
+            {
+                let mut owned = obj.borrow_mut();
+                owned.object = saved.object.clone();
+                owned.operands = saved.operands.clone();
+                owned.attributes = saved.attributes.clone();
+                owned.properties = saved.properties.clone();
+                owned.index = index;
+            }
+            objects.push(obj);
+        }
+
+        self.objects.replace(objects);
+        self.outputs.replace(snapshot.outputs.clone());
+        let mut ports = snapshot.ports.clone();
+        for bit in ports.iter_mut().flat_map(|p| p.bits.iter_mut()) {
+            // Input bits of rebuilt objects still point at the removed ones
+            if let PortBit::Input(weak) = bit
+                && let Some((_, new)) = rebuilt.iter().find(|(old, _)| old.ptr_eq(weak))
+            {
+                *weak = new.clone();
+            }
+        }
+        self.ports.replace(ports);
+        self.rejected_outputs
+            .replace(snapshot.rejected_outputs.clone());
+        self.input_constraints
+            .replace(snapshot.input_constraints.clone());
+        self.check_drivers.set(snapshot.check_drivers);
+        self.duplicate_outputs.set(snapshot.duplicate_outputs);
+        self.two_state.set(snapshot.two_state);
+        self.strash.replace(None);
+        self.type_index.replace(None);
+        for table in self.side_tables.borrow_mut().values_mut() {
+            table.remap(&remap);
+        }
+        if let Some(budget) = self.budget.take() {
+            self.set_budget(budget);
+        }
+        Ok(())
     }
 
     /// Greedly removes unused nodes from the netlist, until it stops changing.
+    /// Inputs, supply nets (see [NetRef::set_supply]) and anchored nodes (see [NetRef::anchor]) are kept even if unused.
     /// Returns true if the netlist was changed.
-    pub fn clean(&self) -> Result<bool, String> {
+    pub fn clean(&self) -> Result<bool, NetlistError<I>> {
         if !self.clean_once()? {
             Ok(false)
         } else {
@@ -1467,10 +5590,12 @@ where
     }
 
     /// Returns `true` if all the nets are uniquely named
+    /// Nets of `inout` ports may have many drivers, see [Netlist::drive_inout]
     fn nets_unique(&self) -> bool {
         let mut nets = HashSet::new();
         for net in self.into_iter() {
-            if !nets.insert(net.take_identifier()) {
+            let id = net.take_identifier();
+            if !nets.insert(id.clone()) && !self.is_inout_net(&id) {
                 return false;
             }
         }
@@ -1481,15 +5606,44 @@ where
     fn insts_unique(&self) -> bool {
         let mut insts = HashSet::new();
         for inst in self.objects() {
-            if let Some(name) = inst.get_instance_name() {
-                if !insts.insert(name) {
-                    return false;
-                }
+            if let Some(name) = inst.get_instance_name()
+                && !insts.insert(name)
+            {
+                return false;
             }
         }
         true
     }
 
+    /// Returns the nets of `inout` ports whose strongest drivers include supplies of both values, which always conflict.
+    /// Drivers are ranked by their strength (see [DrivenNet::set_drive_strength]), and weaker drivers are overridden.
+    pub fn drive_conflicts(&self) -> Vec<Identifier> {
+        let mut drivers: HashMap<Identifier, Vec<DrivenNet<I>>> = HashMap::new();
+        for net in self.objects().flat_map(|n| n.outputs().collect::<Vec<_>>()) {
+            let id = net.get_identifier();
+            if self.is_inout_net(&id) {
+                drivers.entry(id).or_default().push(net);
+            }
+        }
+        let mut conflicts: Vec<Identifier> = drivers
+            .into_iter()
+            .filter(|(_, nets)| {
+                let Some(strongest) = nets.iter().map(|n| n.drive_strength()).max() else {
+                    return false;
+                };
+                let values: HashSet<bool> = nets
+                    .iter()
+                    .filter(|n| n.drive_strength() == strongest)
+                    .filter_map(|n| n.get_supply().map(|s| s.value()))
+                    .collect();
+                values.len() > 1
+            })
+            .map(|(id, _)| id)
+            .collect();
+        conflicts.sort_by_key(|id| id.to_string());
+        conflicts
+    }
+
     /// Verifies that a netlist is well-formed.
     pub fn verify(&self) -> Result<(), String> {
         if self.outputs.borrow().is_empty() {
@@ -1503,6 +5657,35 @@ where
         if !self.insts_unique() {
             return Err("Netlist contains non-unique instances".to_string());
         }
+
+        if let Some(net) = self.drive_conflicts().first() {
+            return Err(format!("Net {net} is driven by supplies of both values"));
+        }
+
+        // Connections made with [DrivenNet::connect] are not checked when they are made
+        for c in self.connections() {
+            let input = c.target();
+            let Some(inst_name) = input.netref.get_instance_name() else {
+                continue;
+            };
+            let (net, port) = (c.net(), input.get_port());
+            if !net.get_type().can_drive(port.get_type()) {
+                return Err(NetlistError::<I>::TypeMismatch(Box::new(TypeMismatch {
+                    inst_name,
+                    net: net.get_identifier().clone(),
+                    driver: *net.get_type(),
+                    port: port.get_identifier().clone(),
+                    sink: *port.get_type(),
+                }))
+                .to_string());
+            }
+        }
+
+        if let Some((rejected, kept)) = self.rejected_outputs.borrow().first() {
+            return Err(format!(
+                "Output {rejected} is driven by the same net as output {kept}"
+            ));
+        }
         Ok(())
     }
 }
@@ -1518,7 +5701,7 @@ impl<I> Connection<I>
 where
     I: Instantiable,
 {
-    fn new(driver: DrivenNet<I>, input: InputPort<I>) -> Self {
+    pub(crate) fn new(driver: DrivenNet<I>, input: InputPort<I>) -> Self {
         Self { driver, input }
     }
 
@@ -1551,9 +5734,14 @@ where
 pub mod iter {
 
     use super::{
-        Connection, DrivenNet, InputPort, Instantiable, Net, NetRef, Netlist, Operand, WeakIndex,
+        Connection, DrivenNet, InputPort, Instantiable, Net, NetRef, Netlist, Operand, OwnedObject,
+        WeakIndex,
+    };
+    use std::{
+        cell::RefCell,
+        collections::{HashMap, HashSet, VecDeque, hash_map::Entry},
+        rc::{Rc, Weak},
     };
-    use std::collections::HashSet;
     /// An iterator over the nets in a netlist
     pub struct NetIterator<'a, I: Instantiable> {
         netlist: &'a Netlist<I>,
@@ -1630,6 +5818,102 @@ pub mod iter {
         }
     }
 
+    /// What an [ObjectCursor] does with the nodes inserted while it walks the netlist
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum InsertionPolicy {
+        /// Visit the inserted nodes after the nodes that existed when the cursor was made
+        Visit,
+        /// Only visit the nodes that existed when the cursor was made
+        Skip,
+    }
+
+    /// A weak handle to a node, which does not keep it from being removed
+    type WeakObject<I> = Weak<RefCell<OwnedObject<I, Netlist<I>>>>;
+
+    /// A cursor over the objects in a netlist that tolerates edits while it walks.
+    /// Unlike [ObjectIterator], it does not follow object indices, so cleaning or reordering the netlist
+    /// does not make it skip or repeat nodes. Nodes are visited in the order of the netlist when the cursor was made,
+    /// nodes removed before they are reached are skipped, and inserted nodes are visited or skipped by [InsertionPolicy].
+    pub struct ObjectCursor<'a, I: Instantiable> {
+        netlist: &'a Netlist<I>,
+        policy: InsertionPolicy,
+        /// The nodes left to visit
+        queue: VecDeque<WeakObject<I>>,
+        /// Every node queued so far. The handles keep their addresses from being reused by new nodes.
+        seen: HashMap<*const RefCell<OwnedObject<I, Netlist<I>>>, WeakObject<I>>,
+    }
+
+    impl<'a, I> ObjectCursor<'a, I>
+    where
+        I: Instantiable,
+    {
+        /// Creates a cursor over the objects in the netlist, with the inserted nodes visited or skipped by `policy`
+        pub fn new(netlist: &'a Netlist<I>, policy: InsertionPolicy) -> Self {
+            let mut cursor = Self {
+                netlist,
+                policy,
+                queue: VecDeque::new(),
+                seen: HashMap::new(),
+            };
+            cursor.enqueue_new();
+            cursor
+        }
+
+        /// Returns what the cursor does with inserted nodes
+        pub fn get_policy(&self) -> InsertionPolicy {
+            self.policy
+        }
+
+        /// Returns the number of nodes queued that have not been visited, including any that were removed since
+        pub fn pending(&self) -> usize {
+            self.queue.len()
+        }
+
+        /// Queues the nodes of the netlist that have not been queued yet, and returns `true` if there were any
+        fn enqueue_new(&mut self) -> bool {
+            let before = self.queue.len();
+            for obj in self.netlist.objects.borrow().iter() {
+                let weak = Rc::downgrade(obj);
+                if let Entry::Vacant(e) = self.seen.entry(weak.as_ptr()) {
+                    e.insert(weak.clone());
+                    self.queue.push_back(weak);
+                }
+            }
+            self.queue.len() > before
+        }
+    }
+
+    impl<I> Iterator for ObjectCursor<'_, I>
+    where
+        I: Instantiable,
+    {
+        type Item = NetRef<I>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                while let Some(weak) = self.queue.pop_front() {
+                    let Some(obj) = weak.upgrade() else {
+                        continue;
+                    };
+                    // Restoring a snapshot can drop a node from the netlist while something else holds it
+                    let index = obj.borrow().index;
+                    let live = self
+                        .netlist
+                        .objects
+                        .borrow()
+                        .get(index)
+                        .is_some_and(|o| Rc::ptr_eq(o, &obj));
+                    if live {
+                        return Some(NetRef::wrap(obj));
+                    }
+                }
+                if self.policy == InsertionPolicy::Skip || !self.enqueue_new() {
+                    return None;
+                }
+            }
+        }
+    }
+
     /// An iterator over the connections in a netlist
     pub struct ConnectionIterator<'a, I: Instantiable> {
         netlist: &'a Netlist<I>,
@@ -1809,10 +6093,18 @@ where
     I: Instantiable,
 {
     /// Returns an iterator over the circuit nodes in the netlist.
+    /// The iterator follows object indices: nodes inserted while iterating are visited,
+    /// but cleaning or reordering the netlist while iterating can skip or repeat nodes. Use [Netlist::cursor] for that.
     pub fn objects(&self) -> impl Iterator<Item = NetRef<I>> {
         iter::ObjectIterator::new(self)
     }
 
+    /// Returns a cursor over the circuit nodes in the netlist that tolerates edits while it walks,
+    /// with the nodes inserted along the way visited or skipped by `policy`.
+    pub fn cursor(&self, policy: iter::InsertionPolicy) -> iter::ObjectCursor<'_, I> {
+        iter::ObjectCursor::new(self, policy)
+    }
+
     /// Returns an iterator over the circuit nodes that match the instance type.
     pub fn matches<F>(&self, filter: F) -> impl Iterator<Item = NetRef<I>>
     where
@@ -1835,8 +6127,22 @@ where
     }
 
     /// Returns an iterator to circuit nodes that drive an output in the netlist.
+    /// The outputs are ordered as they are declared on the module boundary.
     pub fn outputs(&self) -> Vec<(DrivenNet<I>, Net)> {
-        self.outputs
+        let order: HashMap<Identifier, usize> = self
+            .ports
+            .borrow()
+            .iter()
+            .flat_map(|p| p.bits.iter())
+            .filter_map(|b| match b {
+                PortBit::Output(id) => Some(id.clone()),
+                PortBit::Input(_) => None,
+            })
+            .enumerate()
+            .map(|(i, id)| (id, i))
+            .collect();
+        let mut outputs: Vec<(DrivenNet<I>, Net)> = self
+            .outputs
             .borrow()
             .iter()
             .map(|(k, n)| {
@@ -1845,7 +6151,9 @@ where
                     n.clone(),
                 )
             })
-            .collect()
+            .collect();
+        outputs.sort_by_key(|(_, n)| order.get(n.get_identifier()).copied());
+        outputs
     }
 
     /// Returns an iterator over the wire connections in the netlist.
@@ -1868,162 +6176,182 @@ where
     }
 }
 
-impl<I> std::fmt::Display for Netlist<I>
+impl<I> Netlist<I>
 where
     I: Instantiable,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Returns the view of `owned` given to emitters, if it is an instance
+    fn instance_view<'a>(
+        owned: &'a OwnedObject<I, Self>,
+        objects: &[NetRefT<I>],
+        supplies: &'a [(Net, Supply)],
+    ) -> Option<InstanceView<'a, I>> {
+        let Object::Instance(nets, inst_name, inst_type) = owned.get() else {
+            return None;
+        };
+        let attributes = owned.attributes().collect();
+        let init = owned.properties.init;
+        let inputs = inst_type
+            .get_input_ports()
+            .into_iter()
+            .zip(owned.operands.iter())
+            .filter_map(|(port, operand)| {
+                let net = match operand.as_ref()? {
+                    Operand::DirectIndex(idx) => objects[*idx].borrow().as_net().clone(),
+                    Operand::CellIndex(idx, j) => objects[*idx].borrow().get_net(*j).clone(),
+                };
+                Some((port, net))
+            })
+            .collect();
+        let unconnected = inst_type
+            .get_input_ports()
+            .into_iter()
+            .zip(owned.operands.iter())
+            .filter(|(_, operand)| operand.is_none())
+            .map(|(port, _)| port)
+            .collect();
+        let outputs = inst_type
+            .get_output_ports()
+            .into_iter()
+            .zip(nets.iter())
+            .collect();
+        Some(
+            InstanceView::new(inst_name, inst_type, supplies)
+                .with_attributes(attributes)
+                .with_init(init)
+                .with_inputs(inputs, unconnected)
+                .with_outputs(outputs),
+        )
+    }
+
+    /// Writes the netlist to `w` in the format of `emitter`.
+    /// The emitter is given the ports, the nets driven by instances and aliases, the instances in order,
+    /// and then the assigns of aliases and outputs. Arrays of instances (see [Netlist::instance_arrays]) are given together.
+    /// Returns [EmitError::Unsupported] if the emitter cannot write the netlist in its format.
+    pub fn emit<E: NetlistEmitter<I>>(
+        &self,
+        emitter: &mut E,
+        w: &mut dyn std::fmt::Write,
+    ) -> EmitResult {
         // Borrow everything first
         let objects = self.objects.borrow();
-        let outputs = self.outputs.borrow();
 
-        writeln!(f, "module {} (", self.name)?;
+        let ports = self.ports();
+        emitter.header(w, &self.name, &ports)?;
+        // An object still borrowed mutably, like an instance type being edited, cannot be emitted
+        let instance_names: Vec<Identifier> = objects
+            .iter()
+            .filter_map(
+                |oref| match oref.try_borrow().expect("already mutably borrowed").get() {
+                    Object::Instance(_, name, _) => Some(name.clone()),
+                    _ => None,
+                },
+            )
+            .collect();
+        emitter.instance_names(&instance_names);
 
-        // Print inputs and outputs
-        let level = 2;
-        let indent = " ".repeat(level);
-        for oref in objects.iter() {
-            let owned = oref.borrow();
-            let obj = owned.get();
-            if let Object::Input(net) = obj {
-                writeln!(f, "{}{},", indent, net.get_identifier().emit_name())?;
-            }
-        }
-        for (i, (_, net)) in outputs.iter().enumerate() {
-            if i == outputs.len() - 1 {
-                writeln!(f, "{}{}", indent, net.get_identifier().emit_name())?;
-            } else {
-                writeln!(f, "{}{},", indent, net.get_identifier().emit_name())?;
-            }
-        }
-        writeln!(f, ");")?;
+        let supplies: Vec<(Net, Supply)> = objects
+            .iter()
+            .filter_map(|oref| {
+                let owned = oref.borrow();
+                let supply = owned
+                    .attributes
+                    .get(SUPPLY_ATTRIBUTE)?
+                    .as_ref()?
+                    .parse()
+                    .ok()?;
+                Some((owned.get().get_single_net().clone(), supply))
+            })
+            .collect();
 
         // Make wire decls
         let mut already_decl = HashSet::new();
+        for port in ports.iter() {
+            emitter.port(w, port)?;
+            already_decl.extend(port.get_nets().iter().cloned());
+        }
         for oref in objects.iter() {
             let owned = oref.borrow();
-            let obj = owned.get();
-            if let Object::Input(net) = obj {
-                writeln!(f, "{}input {};", indent, net.get_identifier().emit_name())?;
-                writeln!(f, "{}wire {};", indent, net.get_identifier().emit_name())?;
-                already_decl.insert(net.clone());
+            if let Object::Instance(..) | Object::Alias(_) = owned.get() {
+                for net in owned.get().get_nets() {
+                    if already_decl.insert(net.clone()) {
+                        emitter.wire(w, net)?;
+                    }
+                }
             }
         }
-        for (_, net) in outputs.iter() {
-            if !already_decl.contains(net) {
-                writeln!(f, "{}output {};", indent, net.get_identifier().emit_name())?;
-                writeln!(f, "{}wire {};", indent, net.get_identifier().emit_name())?;
-                already_decl.insert(net.clone());
-            }
+
+        // Arrays are emitted together where their first instance is
+        let arrays = self.instance_arrays();
+        let mut first_of: HashMap<usize, &InstanceArray<I>> = HashMap::new();
+        let mut in_array = HashSet::new();
+        for array in arrays.iter() {
+            let indices = array.instances().iter().map(|n| n.netref.borrow().index);
+            in_array.extend(indices.clone());
+            first_of.insert(indices.min().unwrap(), array);
         }
-        for oref in objects.iter() {
-            let owned = oref.borrow();
-            let obj = owned.get();
-            if let Object::Instance(nets, _, _) = obj {
-                for net in nets.iter() {
-                    if !already_decl.contains(net) {
-                        writeln!(f, "{}wire {};", indent, net.get_identifier().emit_name())?;
-                        already_decl.insert(net.clone());
-                    }
+        for (index, oref) in objects.iter().enumerate() {
+            if let Some(array) = first_of.get(&index) {
+                let owned: Vec<_> = array
+                    .instances()
+                    .iter()
+                    .map(|n| objects[n.netref.borrow().index].borrow())
+                    .collect();
+                let views: Vec<InstanceView<'_, I>> = owned
+                    .iter()
+                    .filter_map(|o| Self::instance_view(o, &objects, &supplies))
+                    .collect();
+                emitter.instance_array(w, array.get_name(), array.get_range(), &views)?;
+            } else if !in_array.contains(&index) {
+                let owned = oref.borrow();
+                if let Some(inst) = Self::instance_view(&owned, &objects, &supplies) {
+                    emitter.instance(w, &inst)?;
                 }
             }
         }
 
         for oref in objects.iter() {
             let owned = oref.borrow();
-            let obj = owned.get();
-            if let Object::Instance(nets, inst_name, inst_type) = obj {
-                for (k, v) in owned.attributes.iter() {
-                    if let Some(value) = v {
-                        writeln!(f, "{indent}(* {k} = \"{value}\" *)")?;
-                    } else {
-                        writeln!(f, "{indent}(* {k} *)")?;
-                    }
-                }
-
-                write!(f, "{}{} ", indent, inst_type.get_name())?;
-                if inst_type.is_parameterized() {
-                    writeln!(f, "#(")?;
-                    let level = 4;
-                    let indent = " ".repeat(level);
-                    let params: Vec<_> = inst_type.parameters().collect();
-                    for (i, (k, v)) in params.iter().enumerate() {
-                        if i == params.len() - 1 {
-                            writeln!(f, "{indent}.{k}({v})")?;
-                        } else {
-                            writeln!(f, "{indent}.{k}({v}),")?;
-                        }
-                    }
-                    let level = 2;
-                    let indent = " ".repeat(level);
-                    write!(f, "{indent}) ")?;
-                }
-                writeln!(f, "{} (", inst_name.emit_name())?;
-                let level = 4;
-                let indent = " ".repeat(level);
-                for (idx, port) in inst_type.get_input_ports().into_iter().enumerate() {
-                    let port_name = port.get_identifier().emit_name();
-                    if let Some(operand) = owned.operands[idx].as_ref() {
-                        let operand = match operand {
-                            Operand::DirectIndex(idx) => objects[*idx].borrow().as_net().clone(),
-                            Operand::CellIndex(idx, j) => {
-                                objects[*idx].borrow().get_net(*j).clone()
-                            }
-                        };
-                        writeln!(
-                            f,
-                            "{}.{}({}),",
-                            indent,
-                            port_name,
-                            operand.get_identifier().emit_name()
-                        )?;
-                    }
-                }
-
-                for (idx, net) in nets.iter().enumerate() {
-                    let port_name = inst_type.get_output_port(idx).get_identifier().emit_name();
-                    if idx == nets.len() - 1 {
-                        writeln!(
-                            f,
-                            "{}.{}({})",
-                            indent,
-                            port_name,
-                            net.get_identifier().emit_name()
-                        )?;
-                    } else {
-                        writeln!(
-                            f,
-                            "{}.{}({}),",
-                            indent,
-                            port_name,
-                            net.get_identifier().emit_name()
-                        )?;
-                    }
-                }
+            if let (Object::Alias(net), [Some(operand)]) = (owned.get(), owned.operands.as_slice())
+            {
+                let driver = objects[operand.root()]
+                    .borrow()
+                    .get_net(operand.secondary())
+                    .clone();
+                emitter.assign(w, net, &driver)?;
+            }
+        }
 
-                let level = 2;
-                let indent = " ".repeat(level);
-                writeln!(f, "{indent});")?;
+        for (driver, net) in self.outputs() {
+            let driver_net = driver.as_net().clone();
+            if net != driver_net {
+                emitter.assign(w, &net, &driver_net)?;
             }
         }
 
-        for (driver, net) in outputs.iter() {
-            let driver_net = match driver {
-                Operand::DirectIndex(idx) => self.index_weak(idx).borrow().as_net().clone(),
-                Operand::CellIndex(idx, j) => self.index_weak(idx).borrow().get_net(*j).clone(),
-            };
-            if *net != driver_net {
+        emitter.footer(w, &self.name)
+    }
+}
+
+impl<I> std::fmt::Display for Netlist<I>
+where
+    I: Instantiable,
+{
+    /// Writes the netlist as Verilog, or a comment with the reason it has no Verilog form.
+    /// Failing instead would make `to_string()` panic.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut verilog = String::new();
+        match self.emit(&mut VerilogEmitter::new(), &mut verilog) {
+            Ok(()) => f.write_str(&verilog),
+            Err(EmitError::Unsupported(reason)) => {
                 writeln!(
                     f,
-                    "{}assign {} = {};",
-                    indent,
-                    net.get_identifier().emit_name(),
-                    driver_net.get_identifier().emit_name()
-                )?;
+                    "// Cannot write module {} as Verilog: {reason}",
+                    self.name
+                )
             }
+            Err(EmitError::Write) => Err(std::fmt::Error),
         }
-
-        writeln!(f, "endmodule")
     }
 }
 
@@ -2098,10 +6426,10 @@ mod tests {
 #[cfg(feature = "serde")]
 /// Serde support for netlists
 pub mod serde {
-    use super::{Netlist, Operand, OwnedObject, WeakIndex};
+    use super::{Netlist, Operand, OwnedObject, PortBit, PortDecl, Properties, WeakIndex};
     use crate::{
         attribute::{AttributeKey, AttributeValue},
-        circuit::{Instantiable, Net, Object},
+        circuit::{Identifier, Instantiable, Net, Object, PortDirection},
     };
     use serde::{Deserialize, Serialize, de::DeserializeOwned};
     use std::cell::RefCell;
@@ -2118,6 +6446,9 @@ pub mod serde {
         operands: Vec<Option<Operand>>,
         /// A collection of attributes for the object
         attributes: HashMap<AttributeKey, AttributeValue>,
+        /// The typed properties of the object
+        #[serde(default)]
+        properties: Properties,
     }
 
     impl<I, O> From<OwnedObject<I, O>> for SerdeObject<I>
@@ -2127,9 +6458,10 @@ pub mod serde {
     {
         fn from(value: OwnedObject<I, O>) -> Self {
             SerdeObject {
-                object: value.object,
-                operands: value.operands,
-                attributes: value.attributes,
+                object: Rc::unwrap_or_clone(value.object),
+                operands: Rc::unwrap_or_clone(value.operands),
+                attributes: Rc::unwrap_or_clone(value.attributes),
+                properties: Rc::unwrap_or_clone(value.properties),
             }
         }
     }
@@ -2143,11 +6475,56 @@ pub mod serde {
             O: WeakIndex<usize, Output = OwnedObject<I, O>>,
         {
             OwnedObject {
-                object: self.object,
+                object: Rc::new(self.object),
                 owner: Rc::downgrade(owner),
-                operands: self.operands,
-                attributes: self.attributes,
+                operands: Rc::new(self.operands),
+                attributes: Rc::new(self.attributes),
+                properties: Rc::new(self.properties),
                 index,
+                mark: 0,
+            }
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    enum SerdePortBit {
+        /// The index of the principal input object
+        Input(usize),
+        /// The name of the top-level output net
+        Output(Identifier),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct SerdePort {
+        /// The name of the port
+        name: Identifier,
+        /// The direction of the port
+        direction: PortDirection,
+        /// The bits of the port, lsb first
+        bits: Vec<SerdePortBit>,
+        /// A collection of attributes for the port
+        attributes: HashMap<AttributeKey, AttributeValue>,
+    }
+
+    impl<I> From<&PortDecl<I>> for SerdePort
+    where
+        I: Instantiable,
+    {
+        fn from(value: &PortDecl<I>) -> Self {
+            SerdePort {
+                name: value.name.clone(),
+                direction: value.direction,
+                bits: value
+                    .bits
+                    .iter()
+                    .filter_map(|b| match b {
+                        PortBit::Input(w) => w
+                            .upgrade()
+                            .map(|o| SerdePortBit::Input(o.borrow().get_index())),
+                        PortBit::Output(id) => Some(SerdePortBit::Output(id.clone())),
+                    })
+                    .collect(),
+                attributes: value.attributes.clone(),
             }
         }
     }
@@ -2163,6 +6540,9 @@ pub mod serde {
         objects: Vec<SerdeObject<I>>,
         /// The list of operands that point to objects which are outputs
         outputs: HashMap<String, Net>,
+        /// The ordered list of ports on the module boundary
+        #[serde(default)]
+        ports: Vec<SerdePort>,
     }
 
     impl<I> From<Netlist<I>> for SerdeNetlist<I>
@@ -2170,8 +6550,10 @@ pub mod serde {
         I: Instantiable + Serialize,
     {
         fn from(value: Netlist<I>) -> Self {
+            let ports = value.ports.borrow().iter().map(SerdePort::from).collect();
             SerdeNetlist {
                 name: value.name,
+                ports,
                 objects: value
                     .objects
                     .into_inner()
@@ -2219,11 +6601,44 @@ pub mod serde {
                     Rc::new(RefCell::new(owned_object))
                 })
                 .collect::<Vec<_>>();
+            let ports = self
+                .ports
+                .into_iter()
+                .map(|p| PortDecl {
+                    name: p.name,
+                    direction: p.direction,
+                    bits: p
+                        .bits
+                        .into_iter()
+                        .map(|b| match b {
+                            SerdePortBit::Input(i) => PortBit::Input(Rc::downgrade(&objects[i])),
+                            SerdePortBit::Output(id) => PortBit::Output(id),
+                        })
+                        .collect(),
+                    attributes: p.attributes,
+                })
+                .collect();
             {
                 let mut objs_mut = netlist.objects.borrow_mut();
                 *objs_mut = objects;
                 let mut outputs_mut = netlist.outputs.borrow_mut();
                 *outputs_mut = outputs;
+                let mut ports_mut = netlist.ports.borrow_mut();
+                *ports_mut = ports;
+            }
+            if netlist.ports.borrow().is_empty() {
+                // Netlists serialized without a port map declare their inputs, then outputs
+                for input in netlist.objects().filter(|o| o.is_an_input()) {
+                    let port = PortDecl::<I>::port_name(&input.get_identifier());
+                    let bit = PortBit::Input(Rc::downgrade(&input.netref));
+                    netlist.declare_port_bit(port, PortDirection::Input, bit);
+                }
+                let outputs: Vec<Net> = netlist.outputs.borrow().values().cloned().collect();
+                for net in outputs {
+                    let id = net.get_identifier().clone();
+                    let port = PortDecl::<I>::port_name(&id);
+                    netlist.declare_port_bit(port, PortDirection::Output, PortBit::Output(id));
+                }
             }
             netlist
         }
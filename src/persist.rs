@@ -0,0 +1,247 @@
+/*!
+
+  Persistence of analysis results, keyed by a hash of the netlist contents, so expensive analyses are reloaded instead of recomputed.
+
+*/
+
+use crate::{
+    circuit::Instantiable,
+    diff::node_name,
+    graph::Analysis,
+    netlist::{NetRef, Netlist},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hasher,
+    str::FromStr,
+};
+
+/// A 64-bit FNV-1a hasher, which unlike the hasher of a [HashMap] gives the same hash on every run and platform
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Hashes `s` followed by a separator, so consecutive strings cannot run into each other
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+        self.write(&[0xff]);
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Returns a hash of the contents of `netlist`: its name, ports, nodes with their types, parameters, connections and attributes, and outputs.
+/// The hash is the same across runs, so it identifies a netlist read again from a file. Any edit is very likely to change it.
+pub fn content_hash<I: Instantiable>(netlist: &Netlist<I>) -> u64 {
+    let mut h = Fnv::new();
+    h.write_str(netlist.get_name());
+    for port in netlist.ports() {
+        h.write_str(&format!("{} {}", port.get_direction(), port.get_name()));
+        for net in port.get_nets() {
+            h.write_str(&net.get_identifier().to_string());
+        }
+    }
+    for node in netlist.objects() {
+        h.write_str(&node_name(&node));
+        for net in node.nets() {
+            h.write_str(&net.get_identifier().to_string());
+        }
+        if let Some(inst_type) = node.get_instance_type() {
+            h.write_str(&inst_type.get_name().to_string());
+            for (k, v) in inst_type.parameters() {
+                h.write_str(&format!("{k}={v}"));
+            }
+        }
+        for i in 0..node.get_num_input_ports() {
+            match node.get_driver_net(i) {
+                Some((net, _)) => h.write_str(&net.get_identifier().to_string()),
+                None => h.write_str(""),
+            }
+        }
+        let mut attributes: Vec<String> = node.attributes().map(|a| a.to_string()).collect();
+        attributes.sort();
+        for attribute in attributes {
+            h.write_str(&attribute);
+        }
+        for property in node.property_strings() {
+            h.write_str(&property);
+        }
+    }
+    for (driver, net) in netlist.outputs() {
+        h.write_str(&format!(
+            "{} {}",
+            driver.as_net().get_identifier(),
+            net.get_identifier()
+        ));
+    }
+    h.finish()
+}
+
+/// An [Analysis] whose results can be stored in an [AnalysisCache] and rebuilt from it
+pub trait PersistentAnalysis<'a, I: Instantiable>: Analysis<'a, I> {
+    /// The name the results are stored under
+    const KEY: &'static str;
+
+    /// Returns the results to store, as a value for each node. Values must not contain tabs or newlines.
+    fn save(&self) -> Vec<(NetRef<I>, String)>;
+
+    /// Rebuilds the analysis of `netlist` from the values stored for its nodes
+    fn restore(netlist: &'a Netlist<I>, values: HashMap<NetRef<I>, String>)
+    -> Result<Self, String>;
+}
+
+/// Stored analysis results of one netlist, as tables of values for nodes named like in [crate::diff].
+/// The cache is keyed by the [content_hash] of the netlist, so results are only reloaded while the netlist is unchanged.
+/// It can be written next to the netlist with [AnalysisCache::write] and read back with [AnalysisCache::read].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisCache {
+    /// The content hash of the netlist the results are for
+    hash: u64,
+    /// The tables of results by name, with a value for each named node
+    tables: BTreeMap<String, Vec<(String, String)>>,
+}
+
+/// Maps the names of the nodes of `netlist` to the nodes
+fn nodes_by_name<I: Instantiable>(netlist: &Netlist<I>) -> HashMap<String, NetRef<I>> {
+    netlist.objects().map(|n| (node_name(&n), n)).collect()
+}
+
+impl AnalysisCache {
+    /// Creates an empty cache for the current contents of `netlist`
+    pub fn new<I: Instantiable>(netlist: &Netlist<I>) -> Self {
+        Self {
+            hash: content_hash(netlist),
+            tables: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the content hash of the netlist the results are for
+    pub fn get_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns `true` if the results are for the current contents of `netlist`
+    pub fn is_valid_for<I: Instantiable>(&self, netlist: &Netlist<I>) -> bool {
+        self.hash == content_hash(netlist)
+    }
+
+    /// Returns the names of the stored tables, in sorted order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(|k| k.as_str())
+    }
+
+    /// Stores `values` as the table `key`, replacing any table with that name
+    pub fn store<I: Instantiable, V: std::fmt::Display>(
+        &mut self,
+        key: &str,
+        values: impl IntoIterator<Item = (NetRef<I>, V)>,
+    ) {
+        let table = values
+            .into_iter()
+            .map(|(n, v)| (node_name(&n), v.to_string()))
+            .collect();
+        self.tables.insert(key.to_string(), table);
+    }
+
+    /// Loads the table `key` for the nodes of `netlist`. Returns [None] if there is no such table or the cache is stale.
+    /// Returns an error if a value cannot be parsed or names a node `netlist` does not have.
+    pub fn load<I: Instantiable, V: FromStr>(
+        &self,
+        netlist: &Netlist<I>,
+        key: &str,
+    ) -> Result<Option<HashMap<NetRef<I>, V>>, String> {
+        let Some(table) = self.tables.get(key) else {
+            return Ok(None);
+        };
+        if !self.is_valid_for(netlist) {
+            return Ok(None);
+        }
+        let nodes = nodes_by_name(netlist);
+        let mut values = HashMap::new();
+        for (name, value) in table {
+            let node = nodes.get(name).ok_or_else(|| {
+                format!("Table {key} names node {name}, which is not in the netlist")
+            })?;
+            let value = value
+                .parse()
+                .map_err(|_| format!("Table {key} has a bad value {value} for node {name}"))?;
+            values.insert(node.clone(), value);
+        }
+        Ok(Some(values))
+    }
+
+    /// Returns the analysis `A` of `netlist`, rebuilt from the cache if it has results for the current netlist.
+    /// Otherwise the analysis is built and its results are stored, after the stale results of an edited netlist are dropped.
+    pub fn get_analysis<'a, I, A>(&mut self, netlist: &'a Netlist<I>) -> Result<A, String>
+    where
+        I: Instantiable,
+        A: PersistentAnalysis<'a, I>,
+    {
+        let hash = content_hash(netlist);
+        if hash != self.hash {
+            self.hash = hash;
+            self.tables.clear();
+        }
+        if let Some(values) = self.load::<I, String>(netlist, A::KEY)? {
+            return A::restore(netlist, values);
+        }
+        let analysis = A::build(netlist)?;
+        self.store(A::KEY, analysis.save());
+        Ok(analysis)
+    }
+
+    /// Writes the cache as text: the hash, then every table as a header line followed by one tab-separated line per node
+    pub fn write(&self, mut w: impl std::io::Write) -> std::io::Result<()> {
+        writeln!(w, "hash {:016x}", self.hash)?;
+        for (key, table) in &self.tables {
+            writeln!(w, "table {key} {}", table.len())?;
+            for (name, value) in table {
+                writeln!(w, "{name}\t{value}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a cache written by [AnalysisCache::write]
+    pub fn read(r: impl std::io::Read) -> Result<Self, String> {
+        let text = std::io::read_to_string(r).map_err(|e| e.to_string())?;
+        let mut lines = text.lines();
+        let hash = lines
+            .next()
+            .and_then(|l| l.strip_prefix("hash "))
+            .and_then(|h| u64::from_str_radix(h, 16).ok())
+            .ok_or("Expected the hash of the netlist on the first line")?;
+        let mut tables = BTreeMap::new();
+        while let Some(line) = lines.next() {
+            let (key, len) = line
+                .strip_prefix("table ")
+                .and_then(|l| l.rsplit_once(' '))
+                .and_then(|(k, n)| Some((k, n.parse::<usize>().ok()?)))
+                .ok_or_else(|| format!("Expected a table header, got {line}"))?;
+            let mut table = Vec::with_capacity(len);
+            for _ in 0..len {
+                let (name, value) = lines
+                    .next()
+                    .and_then(|l| l.split_once('\t'))
+                    .ok_or_else(|| format!("Table {key} is missing entries"))?;
+                table.push((name.to_string(), value.to_string()));
+            }
+            tables.insert(key.to_string(), table);
+        }
+        Ok(Self { hash, tables })
+    }
+}
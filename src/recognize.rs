@@ -0,0 +1,445 @@
+/*!
+
+  Recognition of common structures in a gate netlist, like mux trees, one-hot decoders and clock gates.
+
+*/
+
+use crate::{
+    circuit::{Instantiable, Net, SequentialElement},
+    graph::FanOutTable,
+    netlist::{DrivenNet, InputPort, NetRef, Netlist},
+};
+use std::collections::{HashMap, HashSet};
+
+/// A tree of multiplexers, where every non-root mux drives exactly one data input of its parent
+#[derive(Debug, Clone)]
+pub struct MuxTree<I: Instantiable> {
+    /// The mux at the output of the tree
+    root: NetRef<I>,
+    /// Every mux in the tree, starting at the root
+    muxes: Vec<NetRef<I>>,
+    /// The data inputs to the tree
+    leaves: Vec<DrivenNet<I>>,
+    /// The select signals used within the tree
+    selects: Vec<Net>,
+    /// The number of mux levels in the tree
+    depth: usize,
+}
+
+impl<I> MuxTree<I>
+where
+    I: Instantiable,
+{
+    /// Returns the mux at the output of the tree
+    pub fn root(&self) -> &NetRef<I> {
+        &self.root
+    }
+
+    /// Returns every mux in the tree, starting at the root
+    pub fn muxes(&self) -> &[NetRef<I>] {
+        &self.muxes
+    }
+
+    /// Returns the data inputs to the tree
+    pub fn leaves(&self) -> &[DrivenNet<I>] {
+        &self.leaves
+    }
+
+    /// Returns the distinct select signals used within the tree
+    pub fn selects(&self) -> &[Net] {
+        &self.selects
+    }
+
+    /// Returns the number of mux levels in the tree
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Tags every mux in the tree with a `mux_tree` attribute naming the root instance
+    pub fn tag(&self) {
+        let root = self.root.get_instance_name().unwrap().to_string();
+        for mux in &self.muxes {
+            mux.insert_attribute("mux_tree".to_string(), root.clone());
+        }
+    }
+}
+
+impl<I> std::fmt::Display for MuxTree<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "mux tree {}: {} muxes, {} leaves, {} selects, depth {}",
+            self.root.get_instance_name().unwrap(),
+            self.muxes.len(),
+            self.leaves.len(),
+            self.selects.len(),
+            self.depth
+        )
+    }
+}
+
+/// A decoded output alongside the polarity of each select signal
+pub type DecodedOutput<I> = (NetRef<I>, Vec<bool>);
+
+/// A group of AND gates that decode the same select signals into distinct minterms.
+/// At most one output of a decoder is high at a time.
+#[derive(Debug, Clone)]
+pub struct Decoder<I: Instantiable> {
+    /// The select signals being decoded
+    selects: Vec<Net>,
+    /// The decoded outputs alongside the polarity of each select signal, in the order of `selects`
+    outputs: Vec<DecodedOutput<I>>,
+}
+
+impl<I> Decoder<I>
+where
+    I: Instantiable,
+{
+    /// Returns the select signals being decoded
+    pub fn selects(&self) -> &[Net] {
+        &self.selects
+    }
+
+    /// Returns the decoded outputs alongside the polarity of each select signal
+    pub fn outputs(&self) -> &[DecodedOutput<I>] {
+        &self.outputs
+    }
+
+    /// Returns `true` if every minterm of the select signals is decoded
+    pub fn is_complete(&self) -> bool {
+        self.outputs.len() == 1 << self.selects.len()
+    }
+
+    /// Tags every decoded output with a `decoder` attribute listing the select signals
+    pub fn tag(&self) {
+        let selects = self
+            .selects
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        for (gate, _) in &self.outputs {
+            gate.insert_attribute("decoder".to_string(), selects.clone());
+        }
+    }
+}
+
+impl<I> std::fmt::Display for Decoder<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let selects = self
+            .selects
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            f,
+            "decoder of ({}): {} of {} outputs",
+            selects,
+            self.outputs.len(),
+            1usize << self.selects.len()
+        )
+    }
+}
+
+/// A clock gate: an AND gate that passes a clock to registers only while its enables are high.
+/// In an integrated clock gate (ICG), the enable is first held by a latch that is transparent while the clock is low.
+#[derive(Debug, Clone)]
+pub struct ClockGate<I: Instantiable> {
+    /// The AND gate driving the gated clock
+    gate: NetRef<I>,
+    /// The latch holding the enable, for latch-based clock gates
+    latch: Option<NetRef<I>>,
+    /// The clock being gated, if it could be told apart from the enables
+    clock: Option<Net>,
+    /// The enables of the clock gate
+    enables: Vec<Net>,
+    /// The registers clocked by the gated clock
+    registers: Vec<NetRef<I>>,
+}
+
+impl<I> ClockGate<I>
+where
+    I: Instantiable,
+{
+    /// Returns the AND gate driving the gated clock
+    pub fn gate(&self) -> &NetRef<I> {
+        &self.gate
+    }
+
+    /// Returns the latch holding the enable, for latch-based clock gates
+    pub fn latch(&self) -> Option<&NetRef<I>> {
+        self.latch.as_ref()
+    }
+
+    /// Returns `true` if the enable is held by a latch, as in an integrated clock gate
+    pub fn is_latch_based(&self) -> bool {
+        self.latch.is_some()
+    }
+
+    /// Returns the clock being gated, or [None] if it could not be told apart from the enables
+    pub fn clock(&self) -> Option<&Net> {
+        self.clock.as_ref()
+    }
+
+    /// Returns the enables of the clock gate, in front of the latch for latch-based clock gates.
+    /// The enables are empty when the clock is unknown.
+    pub fn enables(&self) -> &[Net] {
+        &self.enables
+    }
+
+    /// Returns the registers clocked by the gated clock
+    pub fn registers(&self) -> &[NetRef<I>] {
+        &self.registers
+    }
+
+    /// Tags every register behind the clock gate with a `clock_gate` attribute naming the AND gate
+    pub fn tag(&self) {
+        let gate = self.gate.get_instance_name().unwrap().to_string();
+        for reg in &self.registers {
+            reg.insert_attribute("clock_gate".to_string(), gate.clone());
+        }
+    }
+}
+
+impl<I> std::fmt::Display for ClockGate<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_latch_based() {
+            write!(f, "latch-based ")?;
+        }
+        write!(f, "clock gate {}: ", self.gate.get_instance_name().unwrap())?;
+        match &self.clock {
+            Some(clock) => {
+                let enables = self
+                    .enables
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{clock} gated by ({enables})")?;
+            }
+            None => write!(f, "unknown clock")?,
+        }
+        write!(f, ", {} registers", self.registers.len())
+    }
+}
+
+/// Finds the trees of multiplexers in `netlist` with at least two muxes.
+/// `select_of` returns the index of the select input if the cell is a mux, and [None] otherwise.
+pub fn find_mux_trees<I, F>(netlist: &Netlist<I>, select_of: F) -> Vec<MuxTree<I>>
+where
+    I: Instantiable,
+    F: Fn(&I) -> Option<usize>,
+{
+    let select: HashMap<NetRef<I>, usize> = netlist
+        .objects()
+        .filter_map(|o| {
+            let sel = select_of(&*o.get_instance_type()?)?;
+            (!o.is_multi_output()).then_some((o, sel))
+        })
+        .collect();
+
+    // A mux is interior to a tree when its only use is the data input of another mux
+    let loads = FanOutTable::index(netlist);
+    let interior = |m: &NetRef<I>| {
+        let uses: Vec<InputPort<I>> = loads.get_net_loads(&m.as_net()).collect();
+        let feeds_mux_data = |load: &InputPort<I>| {
+            select
+                .get(&load.clone().unwrap())
+                .is_some_and(|sel| *sel != load.get_position())
+        };
+        matches!(uses.as_slice(), [load] if feeds_mux_data(load)) && !m.drives_a_top_output()
+    };
+
+    let mut trees = Vec::new();
+    for root in netlist.objects().filter(|o| select.contains_key(o)) {
+        if interior(&root) {
+            continue;
+        }
+        let mut muxes = Vec::new();
+        let mut leaves = Vec::new();
+        let mut selects: Vec<Net> = Vec::new();
+        let mut depth = 0;
+        let mut stack = vec![(root.clone(), 1)];
+        while let Some((mux, level)) = stack.pop() {
+            depth = depth.max(level);
+            let sel = select[&mux];
+            for input in mux.inputs() {
+                let Some(driver) = input.get_driver() else {
+                    continue;
+                };
+                if input.get_position() == sel {
+                    let net = driver.as_net().clone();
+                    if !selects.contains(&net) {
+                        selects.push(net);
+                    }
+                } else if select.contains_key(&driver.clone().unwrap())
+                    && interior(&driver.clone().unwrap())
+                {
+                    stack.push((driver.unwrap(), level + 1));
+                } else {
+                    leaves.push(driver);
+                }
+            }
+            muxes.push(mux);
+        }
+        if muxes.len() > 1 {
+            trees.push(MuxTree {
+                root,
+                muxes,
+                leaves,
+                selects,
+                depth,
+            });
+        }
+    }
+    trees
+}
+
+/// Finds one-hot decoders in `netlist`: groups of AND gates over the same select signals with distinct polarities.
+/// Inverters in front of an AND gate are looked through to find the polarity of each select signal.
+pub fn find_decoders<I, A, N>(netlist: &Netlist<I>, is_and: A, is_inverter: N) -> Vec<Decoder<I>>
+where
+    I: Instantiable,
+    A: Fn(&I) -> bool,
+    N: Fn(&I) -> bool,
+{
+    let is_inv = |n: &NetRef<I>| {
+        n.get_instance_type().is_some_and(|t| is_inverter(&t)) && n.get_num_input_ports() == 1
+    };
+
+    let mut groups: HashMap<Vec<Net>, Vec<DecodedOutput<I>>> = HashMap::new();
+    'gates: for gate in netlist.matches(|t| is_and(t)) {
+        let mut literals: Vec<(Net, bool)> = Vec::new();
+        for driver in gate.driver_nets().zip(gate.drivers()) {
+            let (Some((net, _)), Some(node)) = driver else {
+                continue 'gates;
+            };
+            let literal = if is_inv(&node) {
+                match node.get_driver_net(0) {
+                    Some((n, _)) => (n, false),
+                    None => continue 'gates,
+                }
+            } else {
+                (net, true)
+            };
+            if literals.iter().any(|(n, _)| *n == literal.0) {
+                continue 'gates;
+            }
+            literals.push(literal);
+        }
+        literals.sort_by_key(|(n, _)| n.get_identifier().to_string());
+        let (signals, polarity): (Vec<Net>, Vec<bool>) = literals.into_iter().unzip();
+        groups.entry(signals).or_default().push((gate, polarity));
+    }
+
+    let mut decoders: Vec<Decoder<I>> = groups
+        .into_iter()
+        .filter(|(signals, outputs)| {
+            let patterns: HashSet<&Vec<bool>> = outputs.iter().map(|(_, p)| p).collect();
+            !signals.is_empty() && outputs.len() > 1 && patterns.len() == outputs.len()
+        })
+        .map(|(selects, outputs)| Decoder { selects, outputs })
+        .collect();
+    decoders.sort_by_key(|d| d.selects.iter().map(|n| n.to_string()).collect::<Vec<_>>());
+    decoders
+}
+
+/// Finds the clock gates in `netlist`: AND gates whose output drives the clock input of registers.
+///
+/// An input of the AND gate driven by a latch is the held enable of an integrated clock gate when the latch is gated by another input,
+/// possibly through an inverter. That other input is the clock, and the data input of the latch is the enable.
+/// Otherwise, the clock is the one input that also clocks registers directly or, failing that, the one input driven by a top-level input.
+pub fn find_clock_gates<I, A, N>(
+    netlist: &Netlist<I>,
+    is_and: A,
+    is_inverter: N,
+) -> Vec<ClockGate<I>>
+where
+    I: SequentialElement,
+    A: Fn(&I) -> bool,
+    N: Fn(&I) -> bool,
+{
+    let is_inv = |n: &NetRef<I>| {
+        n.get_instance_type().is_some_and(|t| is_inverter(&t)) && n.get_num_input_ports() == 1
+    };
+    let through_inv = |net: &DrivenNet<I>| {
+        let node = net.clone().unwrap();
+        match node.get_driver_net(0) {
+            Some((n, _)) if is_inv(&node) => n,
+            _ => net.as_net().clone(),
+        }
+    };
+
+    let mut clocked: HashMap<Net, Vec<NetRef<I>>> = HashMap::new();
+    for reg in netlist.objects().filter(|o| o.is_register()) {
+        if let Some(clock) = reg.get_clock() {
+            clocked.entry(clock.as_net().clone()).or_default().push(reg);
+        }
+    }
+
+    let mut gates = Vec::new();
+    for gate in netlist.matches(|t| is_and(t)) {
+        if gate.is_multi_output() {
+            continue;
+        }
+        let Some(registers) = clocked.get(&*gate.as_net()) else {
+            continue;
+        };
+        let Some(operands) = gate
+            .inputs()
+            .map(|i| i.get_driver())
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let nets: Vec<Net> = operands.iter().map(|o| o.as_net().clone()).collect();
+
+        let held = operands.iter().find_map(|o| {
+            let latch = o.clone().unwrap();
+            if !latch.get_instance_type().is_some_and(|t| t.is_latch()) {
+                return None;
+            }
+            let clock = through_inv(&latch.get_clock()?);
+            let enable = latch.get_data()?.as_net().clone();
+            nets.contains(&clock).then_some((latch, clock, enable))
+        });
+
+        let (latch, clock, enables) = match held {
+            Some((latch, clock, enable)) => (Some(latch), Some(clock), vec![enable]),
+            None => {
+                let only = |f: &dyn Fn(&DrivenNet<I>) -> bool| {
+                    let mut found = operands.iter().filter(|o| f(o));
+                    match (found.next(), found.next()) {
+                        (Some(o), None) => Some(o.as_net().clone()),
+                        _ => None,
+                    }
+                };
+                let clock = only(&|o| clocked.contains_key(&*o.as_net()))
+                    .or_else(|| only(&|o| o.is_an_input()));
+                let enables = match &clock {
+                    Some(clock) => nets.iter().filter(|n| *n != clock).cloned().collect(),
+                    None => Vec::new(),
+                };
+                (None, clock, enables)
+            }
+        };
+        gates.push(ClockGate {
+            gate,
+            latch,
+            clock,
+            enables,
+            registers: registers.clone(),
+        });
+    }
+    gates
+}
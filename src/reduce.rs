@@ -0,0 +1,134 @@
+/*!
+
+  Delta-debugging reduction of netlists, for shrinking a netlist that triggers a bug down to a small testcase.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable},
+    netlist::{DrivenNet, Netlist},
+};
+use std::rc::Rc;
+
+/// Removes chunks of `items`, halving the chunk size down to single items, and returns the items that could not be removed.
+/// `try_remove` attempts to remove a chunk and returns `true` if the removal was kept.
+fn reduce_items<T: Clone>(
+    mut items: Vec<T>,
+    mut try_remove: impl FnMut(&[T]) -> Result<bool, String>,
+) -> Result<Vec<T>, String> {
+    let mut size = items.len().div_ceil(2);
+    while size > 0 {
+        let before = items.len();
+        let mut kept = Vec::new();
+        for chunk in items.chunks(size) {
+            if !try_remove(chunk)? {
+                kept.extend_from_slice(chunk);
+            }
+        }
+        items = kept;
+        // Single items are retried until none can be removed, since removals can unblock each other
+        if size > 1 {
+            size /= 2;
+        } else if items.len() == before || items.is_empty() {
+            break;
+        }
+    }
+    Ok(items)
+}
+
+/// Copies the logic of `netlist` that drives the outputs named in `outputs` into a new netlist with the same name.
+/// Inputs that no longer drive anything are dropped.
+fn keep_outputs<I: Instantiable>(
+    netlist: &Netlist<I>,
+    outputs: &[Identifier],
+) -> Result<Rc<Netlist<I>>, String> {
+    let kept: Vec<(DrivenNet<I>, Identifier)> = netlist
+        .outputs()
+        .into_iter()
+        .filter(|(_, net)| outputs.contains(net.get_identifier()))
+        .map(|(driver, net)| (driver, net.get_identifier().clone()))
+        .collect();
+    let copy = Netlist::new(netlist.get_name().to_string());
+    let roots: Vec<DrivenNet<I>> = kept.iter().map(|(d, _)| d.clone()).collect();
+    let copies = copy.copy_cone_from(netlist, &roots, "")?;
+    for (net, (_, name)) in copies.into_iter().zip(kept) {
+        net.expose_with_name(name);
+    }
+    Ok(copy)
+}
+
+/// Reduces `netlist` to a smaller netlist on which `predicate` still holds, like "the emitted Verilog fails lint".
+/// The netlist is left unchanged, and the reduced copy is returned.
+///
+/// Reduction removes top-level outputs with the logic only they use, then cuts instances from the logic that drives them,
+/// replacing each cut instance with a new input of the same name and dropping the logic behind it.
+/// Chunks of outputs and instances are removed with delta debugging, halving the chunk size down to single ones,
+/// and a removal is kept only if `predicate` still holds. Inputs left unused are dropped at the end.
+/// Multi-output instances are not cut. Returns an error if `predicate` does not hold on `netlist` to begin with.
+pub fn reduce<I, P>(netlist: &Netlist<I>, mut predicate: P) -> Result<Rc<Netlist<I>>, String>
+where
+    I: Instantiable,
+    P: FnMut(&Netlist<I>) -> bool,
+{
+    let outputs: Vec<Identifier> = netlist
+        .outputs()
+        .into_iter()
+        .map(|(_, net)| net.get_identifier().clone())
+        .collect();
+    let mut current = keep_outputs(netlist, &outputs)?;
+    if !predicate(&current) {
+        return Err(format!(
+            "The predicate does not hold on netlist {}",
+            netlist.get_name()
+        ));
+    }
+
+    let outputs = reduce_items(outputs, |chunk| {
+        let rest: Vec<Identifier> = current
+            .outputs()
+            .into_iter()
+            .map(|(_, net)| net.get_identifier().clone())
+            .filter(|id| !chunk.contains(id))
+            .collect();
+        let candidate = keep_outputs(&current, &rest)?;
+        let holds = predicate(&candidate);
+        if holds {
+            current = candidate;
+        }
+        Ok(holds)
+    })?;
+
+    let instances: Vec<Identifier> = current
+        .objects()
+        .filter(|n| !n.is_multi_output())
+        .filter_map(|n| n.get_instance_name())
+        .collect();
+    let mut cuts = 0;
+    reduce_items(instances, |chunk| {
+        let snapshot = current.snapshot();
+        for name in chunk {
+            // Earlier cuts in the chunk may have dropped the instance
+            let Some(inst) = current.find_instance(name) else {
+                continue;
+            };
+            let net = inst.as_net().clone();
+            inst.as_net_mut()
+                .set_identifier(format!("reduce_cut_{cuts}").into());
+            cuts += 1;
+            let input = current.insert_input(net).unwrap();
+            current.replace_net_uses(inst, &input)?;
+        }
+        current.clean()?;
+        let holds = predicate(&current);
+        if !holds {
+            current.restore(&snapshot)?;
+        }
+        Ok(holds)
+    })?;
+
+    let candidate = keep_outputs(&current, &outputs)?;
+    if predicate(&candidate) {
+        current = candidate;
+    }
+    Ok(current)
+}
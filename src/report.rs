@@ -0,0 +1,662 @@
+/*!
+
+  Reports that summarize the structure of a netlist.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable, Net},
+    graph::FanOutTable,
+    netlist::{DrivenNet, InputPort, NetRef, Netlist},
+};
+use std::collections::{HashMap, HashSet};
+
+/// The distribution tree of a clock or reset signal, as found by [distribution_tree]
+#[derive(Debug, Clone)]
+pub struct DistributionTree<I: Instantiable> {
+    /// The net at the root of the tree
+    root: Net,
+    /// The buffers at each level of the tree. Level 0 is driven directly by the root.
+    levels: Vec<Vec<NetRef<I>>>,
+    /// The number of loads on the nets at each level. Index 0 counts the loads of the root.
+    fanout: Vec<usize>,
+    /// The non-buffer loads reached through the buffers of the tree
+    leaves: Vec<InputPort<I>>,
+    /// Sequential elements reached only through non-buffer logic
+    gated: Vec<NetRef<I>>,
+}
+
+impl<I> DistributionTree<I>
+where
+    I: Instantiable,
+{
+    /// Returns the net at the root of the tree
+    pub fn root(&self) -> &Net {
+        &self.root
+    }
+
+    /// Returns the buffers at each level of the tree. Level 0 is driven directly by the root.
+    pub fn levels(&self) -> &[Vec<NetRef<I>>] {
+        &self.levels
+    }
+
+    /// Returns the number of buffer levels in the tree
+    pub fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Returns the number of loads on the nets at each level. Index 0 counts the loads of the root.
+    pub fn fanout(&self) -> &[usize] {
+        &self.fanout
+    }
+
+    /// Returns the non-buffer loads reached through the buffers of the tree
+    pub fn leaves(&self) -> &[InputPort<I>] {
+        &self.leaves
+    }
+
+    /// Returns the sequential elements reached only through non-buffer logic, like clock gates
+    pub fn gated(&self) -> &[NetRef<I>] {
+        &self.gated
+    }
+}
+
+impl<I> std::fmt::Display for DistributionTree<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "tree {}:", self.root)?;
+        for (level, fanout) in self.fanout.iter().enumerate() {
+            let nbufs = self.levels.get(level).map(|l| l.len()).unwrap_or(0);
+            writeln!(f, "  level {level}: fanout {fanout}, buffers {nbufs}")?;
+        }
+        writeln!(f, "  leaves: {}", self.leaves.len())?;
+        for seq in &self.gated {
+            writeln!(f, "  through logic: {seq}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Traces the distribution tree of the clock or reset `root` through buffers.
+/// `is_buffer` identifies cells that pass the signal along unchanged, like buffers and inverters.
+/// `is_sequential` identifies state elements, which are reported when they are only reached through other logic.
+pub fn distribution_tree<I, B, S>(
+    netlist: &Netlist<I>,
+    root: &DrivenNet<I>,
+    is_buffer: B,
+    is_sequential: S,
+) -> DistributionTree<I>
+where
+    I: Instantiable,
+    B: Fn(&I) -> bool,
+    S: Fn(&I) -> bool,
+{
+    let loads = FanOutTable::index(netlist);
+    let kind_of =
+        |n: &NetRef<I>, f: &dyn Fn(&I) -> bool| n.get_instance_type().is_some_and(|t| f(&t));
+    let buffer_of = |n: &NetRef<I>| kind_of(n, &is_buffer) && !n.is_multi_output();
+
+    let mut levels = Vec::new();
+    let mut fanout = Vec::new();
+    let mut leaves = Vec::new();
+    let mut logic = Vec::new();
+    let mut seen: HashSet<NetRef<I>> = HashSet::new();
+    let mut frontier = vec![root.as_net().clone()];
+    while !frontier.is_empty() {
+        let mut buffers = Vec::new();
+        let mut count = 0;
+        for net in frontier {
+            for port in loads.get_net_loads(&net) {
+                count += 1;
+                let node = port.clone().unwrap();
+                if buffer_of(&node) {
+                    if seen.insert(node.clone()) {
+                        buffers.push(node);
+                    }
+                } else {
+                    if seen.insert(node.clone()) && !kind_of(&node, &is_sequential) {
+                        logic.push(node);
+                    }
+                    leaves.push(port);
+                }
+            }
+        }
+        fanout.push(count);
+        frontier = buffers.iter().map(|b| b.as_net().clone()).collect();
+        if !buffers.is_empty() {
+            levels.push(buffers);
+        }
+    }
+
+    // Walk forward through any other logic to find sequential elements fed by it
+    let mut gated = Vec::new();
+    while let Some(node) = logic.pop() {
+        for net in node.nets() {
+            for port in loads.get_net_loads(&net) {
+                let load = port.unwrap();
+                if !seen.insert(load.clone()) {
+                    continue;
+                }
+                if kind_of(&load, &is_sequential) {
+                    gated.push(load);
+                } else {
+                    logic.push(load);
+                }
+            }
+        }
+    }
+
+    DistributionTree {
+        root: root.as_net().clone(),
+        levels,
+        fanout,
+        leaves,
+        gated,
+    }
+}
+
+/// The estimated switching energy of a net, as ranked by [switching_report]
+#[derive(Debug, Clone)]
+pub struct NetActivity<I: Instantiable> {
+    /// The net
+    net: DrivenNet<I>,
+    /// The estimated toggle rate of the net
+    activity: f64,
+    /// The number of loads on the net
+    fanout: usize,
+    /// The total capacitance of the loads on the net
+    load: f64,
+}
+
+impl<I> NetActivity<I>
+where
+    I: Instantiable,
+{
+    /// Returns the net
+    pub fn net(&self) -> &DrivenNet<I> {
+        &self.net
+    }
+
+    /// Returns the estimated toggle rate of the net
+    pub fn activity(&self) -> f64 {
+        self.activity
+    }
+
+    /// Returns the number of loads on the net
+    pub fn fanout(&self) -> usize {
+        self.fanout
+    }
+
+    /// Returns the total capacitance of the loads on the net
+    pub fn load(&self) -> f64 {
+        self.load
+    }
+
+    /// Returns the estimated switching energy of the net, as activity × load
+    pub fn energy(&self) -> f64 {
+        self.activity * self.load
+    }
+}
+
+impl<I> std::fmt::Display for NetActivity<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: activity {:.3}, fanout {}, load {:.3}, energy {:.3}",
+            self.net.get_identifier(),
+            self.activity,
+            self.fanout,
+            self.load,
+            self.energy()
+        )
+    }
+}
+
+/// Ranks the nets of `netlist` by estimated switching energy, highest first.
+/// `activity` estimates the toggle rate of each net, like from simulation or signal probabilities.
+/// `load` gives the capacitance of each input port. Top-level outputs add no load, and supply nets are skipped.
+pub fn switching_report<I, A, L>(netlist: &Netlist<I>, activity: A, load: L) -> Vec<NetActivity<I>>
+where
+    I: Instantiable,
+    A: Fn(&DrivenNet<I>) -> f64,
+    L: Fn(&InputPort<I>) -> f64,
+{
+    let loads = FanOutTable::index(netlist);
+
+    let mut report: Vec<NetActivity<I>> = netlist
+        .objects()
+        .flat_map(|n| n.outputs().collect::<Vec<_>>())
+        .filter(|net| net.get_supply().is_none())
+        .map(|net| {
+            let ports: Vec<InputPort<I>> = loads.get_net_loads(&net.as_net()).collect();
+            NetActivity {
+                activity: activity(&net),
+                fanout: ports.len(),
+                load: ports.iter().map(&load).sum(),
+                net,
+            }
+        })
+        .collect();
+    report.sort_by(|a, b| b.energy().total_cmp(&a.energy()));
+    report
+}
+
+/// The register-to-register paths between two banks of registers, as found by [register_paths]
+#[derive(Debug, Clone)]
+pub struct BankPaths<I: Instantiable> {
+    /// The bank the paths start at
+    from: String,
+    /// The bank the paths end at
+    to: String,
+    /// The pairs of start and end registers that are connected
+    pairs: Vec<(NetRef<I>, NetRef<I>)>,
+    /// The most combinational cells on any of the paths
+    depth: usize,
+}
+
+impl<I> BankPaths<I>
+where
+    I: Instantiable,
+{
+    /// Returns the bank the paths start at
+    pub fn from_bank(&self) -> &str {
+        &self.from
+    }
+
+    /// Returns the bank the paths end at
+    pub fn to_bank(&self) -> &str {
+        &self.to
+    }
+
+    /// Returns the pairs of start and end registers that are connected
+    pub fn pairs(&self) -> &[(NetRef<I>, NetRef<I>)] {
+        &self.pairs
+    }
+
+    /// Returns the most combinational cells on any of the paths
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+impl<I> std::fmt::Display for BankPaths<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> {}: {} paths, depth {}",
+            self.from,
+            self.to,
+            self.pairs.len(),
+            self.depth
+        )
+    }
+}
+
+/// Finds the register-to-register paths of `netlist`, grouped by the banks of their start and end registers.
+/// `is_sequential` identifies the registers and `bank_of` names the bank of each register.
+/// The groups are sorted by the names of their banks.
+pub fn register_paths<I, S, B>(
+    netlist: &Netlist<I>,
+    is_sequential: S,
+    bank_of: B,
+) -> Result<Vec<BankPaths<I>>, String>
+where
+    I: Instantiable,
+    S: Fn(&I) -> bool,
+    B: Fn(&NetRef<I>) -> String,
+{
+    let loads = FanOutTable::index(netlist);
+    let is_register = |n: &NetRef<I>| n.get_instance_type().is_some_and(|t| is_sequential(&t));
+    let limit = netlist.objects().count();
+
+    let mut groups: HashMap<(String, String), BankPaths<I>> = HashMap::new();
+    for start in netlist.objects().filter(is_register) {
+        // The most combinational cells from start to each node and end register
+        let mut depths: HashMap<NetRef<I>, usize> = HashMap::new();
+        let mut ends: HashMap<NetRef<I>, usize> = HashMap::new();
+        let mut worklist = vec![(start.clone(), 0)];
+        while let Some((node, depth)) = worklist.pop() {
+            for load in node
+                .nets()
+                .flat_map(|n| loads.get_net_users(&n).collect::<Vec<_>>())
+            {
+                if is_register(&load) {
+                    let end = ends.entry(load).or_default();
+                    *end = (*end).max(depth);
+                } else if depths.get(&load).is_none_or(|d| *d < depth + 1) {
+                    if depth + 1 > limit {
+                        return Err("Cycle detected in the netlist".to_string());
+                    }
+                    depths.insert(load.clone(), depth + 1);
+                    worklist.push((load, depth + 1));
+                }
+            }
+        }
+
+        for (end, depth) in ends {
+            let (from, to) = (bank_of(&start), bank_of(&end));
+            let group = groups
+                .entry((from.clone(), to.clone()))
+                .or_insert_with(|| BankPaths {
+                    from,
+                    to,
+                    pairs: Vec::new(),
+                    depth: 0,
+                });
+            group.pairs.push((start.clone(), end));
+            group.depth = group.depth.max(depth);
+        }
+    }
+
+    let mut groups: Vec<BankPaths<I>> = groups.into_values().collect();
+    groups.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    Ok(groups)
+}
+
+/// The nets of a netlist that drive nothing, as found by [unused_report]
+#[derive(Debug, Clone)]
+pub struct UnusedReport<I: Instantiable> {
+    /// The top-level inputs that drive no instance or output
+    inputs: Vec<DrivenNet<I>>,
+    /// The instance outputs that drive no instance or output
+    outputs: Vec<DrivenNet<I>>,
+}
+
+impl<I> UnusedReport<I>
+where
+    I: Instantiable,
+{
+    /// Returns the top-level inputs that drive no instance or output, in the order they were added
+    pub fn inputs(&self) -> &[DrivenNet<I>] {
+        &self.inputs
+    }
+
+    /// Returns the instance outputs that drive no instance or output, in object order
+    pub fn outputs(&self) -> &[DrivenNet<I>] {
+        &self.outputs
+    }
+
+    /// Returns `true` if every net drives something
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty() && self.outputs.is_empty()
+    }
+}
+
+impl<I> std::fmt::Display for UnusedReport<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for input in &self.inputs {
+            writeln!(f, "unused input: {}", input.get_identifier())?;
+        }
+        for output in &self.outputs {
+            let inst = output.clone().unwrap().get_instance_name().unwrap();
+            writeln!(
+                f,
+                "unused output: {inst}/{} ({})",
+                output.get_port().get_identifier(),
+                output.get_identifier()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the top-level inputs and the instance outputs of `netlist` that drive nothing.
+/// Unlike dead instances, unused inputs are never removed by [Netlist::clean], so this is how interface bloat and forgotten connections are found.
+/// Supply nets and anchored nodes (see [NetRef::anchor]) are not listed, since they need not drive anything.
+pub fn unused_report<I: Instantiable>(netlist: &Netlist<I>) -> UnusedReport<I> {
+    let mut used: HashSet<Net> = netlist.connections().map(|c| c.net()).collect();
+    used.extend(
+        netlist
+            .outputs()
+            .into_iter()
+            .map(|(d, _)| d.as_net().clone()),
+    );
+    let (inputs, outputs) = netlist
+        .objects()
+        .filter(|n| !n.is_anchored())
+        .flat_map(|n| n.outputs().collect::<Vec<_>>())
+        .filter(|d| !d.is_an_alias() && d.get_supply().is_none() && !used.contains(&*d.as_net()))
+        .partition(|d| d.is_an_input());
+    UnusedReport { inputs, outputs }
+}
+
+/// The electrical limits of an output pin, from library data. A limit of [None] is not checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PinLimits {
+    /// The most capacitance the pin can drive
+    pub max_capacitance: Option<f64>,
+    /// The most input pins the pin can drive
+    pub max_fanout: Option<usize>,
+}
+
+/// A net that breaks the limits of its driver, as found by [erc_report]
+#[derive(Debug, Clone)]
+pub struct ErcViolation<I: Instantiable> {
+    /// The net
+    net: DrivenNet<I>,
+    /// The limits of the pin driving the net
+    limits: PinLimits,
+    /// The loads on the net, with their capacitance
+    loads: Vec<(InputPort<I>, f64)>,
+}
+
+impl<I> ErcViolation<I>
+where
+    I: Instantiable,
+{
+    /// Returns the net
+    pub fn net(&self) -> &DrivenNet<I> {
+        &self.net
+    }
+
+    /// Returns the limits of the pin driving the net
+    pub fn limits(&self) -> PinLimits {
+        self.limits
+    }
+
+    /// Returns the loads on the net
+    pub fn loads(&self) -> impl Iterator<Item = &InputPort<I>> {
+        self.loads.iter().map(|(p, _)| p)
+    }
+
+    /// Returns the number of loads on the net
+    pub fn fanout(&self) -> usize {
+        self.loads.len()
+    }
+
+    /// Returns the total capacitance of the loads on the net
+    pub fn capacitance(&self) -> f64 {
+        self.loads.iter().map(|(_, c)| c).sum()
+    }
+
+    /// Returns `true` if the net has more loads than its driver allows
+    pub fn exceeds_fanout(&self) -> bool {
+        self.limits.max_fanout.is_some_and(|m| self.fanout() > m)
+    }
+
+    /// Returns `true` if the loads on the net have more capacitance than its driver allows
+    pub fn exceeds_capacitance(&self) -> bool {
+        self.limits
+            .max_capacitance
+            .is_some_and(|m| self.capacitance() > m)
+    }
+
+    /// Splits the loads into groups that each fit the limits of the driver, in the order of the loads.
+    /// The groups can be given to [DrivenNet::split_loads], with one buffer per group.
+    /// A load that alone exceeds the capacitance limit gets a group of its own.
+    pub fn load_groups(&self) -> Vec<Vec<InputPort<I>>> {
+        let mut groups: Vec<Vec<InputPort<I>>> = Vec::new();
+        let mut cap = 0.0;
+        for (port, c) in &self.loads {
+            let full = groups.last().is_none_or(|g| {
+                self.limits.max_fanout.is_some_and(|m| g.len() >= m)
+                    || self.limits.max_capacitance.is_some_and(|m| cap + c > m)
+            });
+            if full {
+                groups.push(Vec::new());
+                cap = 0.0;
+            }
+            groups.last_mut().unwrap().push(port.clone());
+            cap += c;
+        }
+        groups
+    }
+}
+
+impl<I> std::fmt::Display for ErcViolation<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.net.get_identifier())?;
+        if let Some(max) = self.limits.max_fanout.filter(|_| self.exceeds_fanout()) {
+            write!(f, " fanout {} > {max}", self.fanout())?;
+        }
+        if let Some(max) = self
+            .limits
+            .max_capacitance
+            .filter(|_| self.exceeds_capacitance())
+        {
+            write!(f, " capacitance {:.3} > {max:.3}", self.capacitance())?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every net of `netlist` against the limits of its driver, given by `limits`, and returns the nets that break them in object order.
+/// `load` gives the capacitance of each input port. Top-level outputs are not counted as loads, and supply nets are skipped.
+pub fn erc_report<I, L, C>(netlist: &Netlist<I>, limits: L, load: C) -> Vec<ErcViolation<I>>
+where
+    I: Instantiable,
+    L: Fn(&DrivenNet<I>) -> PinLimits,
+    C: Fn(&InputPort<I>) -> f64,
+{
+    let loads = FanOutTable::index(netlist);
+    netlist
+        .objects()
+        .flat_map(|n| n.outputs().collect::<Vec<_>>())
+        .filter(|net| net.get_supply().is_none())
+        .map(|net| {
+            let ports: Vec<InputPort<I>> = loads.get_net_loads(&net.as_net()).collect();
+            ErcViolation {
+                limits: limits(&net),
+                loads: ports.into_iter().map(|p| (p.clone(), load(&p))).collect(),
+                net,
+            }
+        })
+        .filter(|v| v.exceeds_fanout() || v.exceeds_capacitance())
+        .collect()
+}
+
+/// The inputs of one pin of one cell type that are tied to constants, as found by [tie_off_report]
+#[derive(Debug, Clone)]
+pub struct TieOffGroup<I: Instantiable> {
+    /// The name of the cell type
+    cell: Identifier,
+    /// The name of the input pin
+    pin: Identifier,
+    /// The tied inputs in object order, with their constant
+    ties: Vec<(InputPort<I>, bool)>,
+}
+
+impl<I> TieOffGroup<I>
+where
+    I: Instantiable,
+{
+    /// Returns the name of the cell type
+    pub fn cell(&self) -> &Identifier {
+        &self.cell
+    }
+
+    /// Returns the name of the input pin
+    pub fn pin(&self) -> &Identifier {
+        &self.pin
+    }
+
+    /// Returns the tied inputs in object order, with their constant
+    pub fn ties(&self) -> &[(InputPort<I>, bool)] {
+        &self.ties
+    }
+
+    /// Returns the number of inputs tied to `value`
+    pub fn count(&self, value: bool) -> usize {
+        self.ties.iter().filter(|(_, v)| *v == value).count()
+    }
+}
+
+impl<I> std::fmt::Display for TieOffGroup<I>
+where
+    I: Instantiable,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}: {} tied to 0, {} tied to 1",
+            self.cell,
+            self.pin,
+            self.count(false),
+            self.count(true)
+        )?;
+        for (port, value) in &self.ties {
+            let inst = port.clone().unwrap().get_instance_name().unwrap();
+            write!(f, "\n  {inst} = {}", *value as u8)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists every instance input of `netlist` tied to a constant, grouped by cell type and pin and sorted by their names.
+/// Constants are supply nets (see [Supply](crate::supply::Supply)) and the outputs of instances that `constant` gives a value for, like tie cells,
+/// seen through any aliases.
+pub fn tie_off_report<I, C>(netlist: &Netlist<I>, constant: C) -> Vec<TieOffGroup<I>>
+where
+    I: Instantiable,
+    C: Fn(&I) -> Option<bool>,
+{
+    let value_of = |driver: &DrivenNet<I>| {
+        let mut node = driver.clone().unwrap();
+        while node.is_an_alias() {
+            node = node.get_driver(0)?;
+        }
+        match node.get_supply() {
+            Some(supply) => Some(supply.value()),
+            None => constant(&*node.get_instance_type()?),
+        }
+    };
+
+    let mut groups: HashMap<(Identifier, Identifier), TieOffGroup<I>> = HashMap::new();
+    for node in netlist.objects() {
+        let Some(cell) = node.get_instance_type().map(|t| t.get_name().clone()) else {
+            continue;
+        };
+        for (i, driver) in node.operands() {
+            if let Some(value) = driver.and_then(|d| value_of(&d)) {
+                let input = node.get_input(i);
+                let pin = input.get_port().get_identifier().clone();
+                groups
+                    .entry((cell.clone(), pin.clone()))
+                    .or_insert_with(|| TieOffGroup {
+                        cell: cell.clone(),
+                        pin,
+                        ties: Vec::new(),
+                    })
+                    .ties
+                    .push((input, value));
+            }
+        }
+    }
+    let mut groups: Vec<TieOffGroup<I>> = groups.into_values().collect();
+    groups.sort_by_key(|g| (g.cell.to_string(), g.pin.to_string()));
+    groups
+}
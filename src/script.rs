@@ -0,0 +1,204 @@
+/*!
+
+  A small command interpreter for scripting batch edits to a netlist.
+
+  Each line of a script is a single command. Blank lines and lines starting with `#` are ignored.
+
+  | Command | Description |
+  |---------|-------------|
+  | `rename_net <net> <new_name>` | Renames the net driven in the netlist |
+  | `disconnect <inst> <port>` | Disconnects the input port of an instance |
+  | `insert_buffer <net> <inst>` | Inserts a buffer named `<inst>` between the net and all its users |
+  | `set_attribute <inst_or_net> <key> [value]` | Sets an attribute on an instance or the node driving a net |
+  | `report_stats` | Reports the number of inputs, outputs, and instances |
+  | `report_instances` | Reports the hierarchical path to every instance and its type |
+  | `report_nets` | Reports every net in the netlist |
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable},
+    netlist::{DrivenNet, InputPort, NetRef, Netlist},
+};
+use std::{fmt::Write, rc::Rc};
+
+/// Executes text scripts of edit commands against a netlist
+pub struct CommandInterpreter<I: Instantiable> {
+    /// The netlist being edited
+    netlist: Rc<Netlist<I>>,
+    /// The primitive used by `insert_buffer`
+    buffer: Option<I>,
+}
+
+impl<I> CommandInterpreter<I>
+where
+    I: Instantiable,
+{
+    /// Create a new interpreter that edits `netlist`
+    pub fn new(netlist: Rc<Netlist<I>>) -> Self {
+        Self {
+            netlist,
+            buffer: None,
+        }
+    }
+
+    /// Sets the single-input, single-output primitive used by `insert_buffer`
+    pub fn with_buffer(mut self, buffer: I) -> Self {
+        self.buffer = Some(buffer);
+        self
+    }
+
+    /// Returns a reference to the netlist being edited
+    pub fn get_netlist(&self) -> &Rc<Netlist<I>> {
+        &self.netlist
+    }
+
+    /// Runs every command in `script`, returning the concatenated report output.
+    /// Execution stops at the first failing command and the error is tagged with its line number.
+    /// The edits of the commands before it are undone, so a script is applied entirely or not at all.
+    pub fn run(&self, script: &str) -> Result<String, String> {
+        let snapshot = self.netlist.snapshot();
+        let mut report = String::new();
+        for (lineno, line) in script.lines().enumerate() {
+            match self.execute(line) {
+                Ok(out) => report.push_str(&out),
+                Err(e) => {
+                    // A locked netlist cannot have been edited, and cannot be restored
+                    if !self.netlist.is_locked() {
+                        self.netlist.restore(&snapshot)?;
+                    }
+                    return Err(format!("Line {}: {}", lineno + 1, e));
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Executes a single command, returning any report output it produces.
+    pub fn execute(&self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(String::new());
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (cmd, args) = tokens.split_first().unwrap();
+        match (*cmd, args) {
+            ("rename_net", [net, new_name]) => self.rename_net(net, new_name),
+            ("disconnect", [inst, port]) => self.disconnect(inst, port),
+            ("insert_buffer", [net, inst]) => self.insert_buffer(net, inst),
+            ("set_attribute", [target, key]) => self.set_attribute(target, key, None),
+            ("set_attribute", [target, key, value]) => self.set_attribute(target, key, Some(value)),
+            ("report_stats", []) => self.report_stats(),
+            ("report_instances", []) => self.report_instances(),
+            ("report_nets", []) => self.report_nets(),
+            (
+                "rename_net" | "disconnect" | "insert_buffer" | "set_attribute" | "report_stats"
+                | "report_instances" | "report_nets",
+                _,
+            ) => Err(format!("Wrong number of arguments to {cmd}")),
+            _ => Err(format!("Unknown command {cmd}")),
+        }
+    }
+
+    /// Finds the net in the netlist with the name `name`
+    fn find_driven(&self, name: &str) -> Result<DrivenNet<I>, String> {
+        let id: Identifier = name.into();
+        self.netlist
+            .objects()
+            .flat_map(|o| o.outputs().collect::<Vec<_>>())
+            .find(|o| o.get_identifier() == id)
+            .ok_or(format!("Net {name} not found"))
+    }
+
+    /// Finds the instance in the netlist with the name `name`
+    fn find_instance(&self, name: &str) -> Option<NetRef<I>> {
+        self.netlist.find_instance(&name.into())
+    }
+
+    fn rename_net(&self, net: &str, new_name: &str) -> Result<String, String> {
+        let driven = self.find_driven(net)?;
+        if new_name != net && self.find_driven(new_name).is_ok() {
+            return Err(format!("Net {new_name} is already driven in the netlist"));
+        }
+        driven.try_set_identifier(new_name.into())?;
+        Ok(String::new())
+    }
+
+    fn disconnect(&self, inst: &str, port: &str) -> Result<String, String> {
+        let inst_ref = self
+            .find_instance(inst)
+            .ok_or(format!("Instance {inst} not found"))?;
+        let input = inst_ref
+            .find_input(&port.into())
+            .ok_or(format!("Instance {inst} has no input port {port}"))?;
+        input.disconnect();
+        Ok(String::new())
+    }
+
+    fn insert_buffer(&self, net: &str, inst: &str) -> Result<String, String> {
+        let buffer = self
+            .buffer
+            .clone()
+            .ok_or("No buffer primitive was provided to the interpreter".to_string())?;
+        let driven = self.find_driven(net)?;
+        let users: Vec<InputPort<I>> = self
+            .netlist
+            .connections()
+            .filter(|c| *c.src().as_net() == *driven.as_net())
+            .map(|c| c.target())
+            .collect();
+        driven.split_loads(buffer, vec![(inst.into(), users)])?;
+        Ok(String::new())
+    }
+
+    fn set_attribute(
+        &self,
+        target: &str,
+        key: &str,
+        value: Option<&str>,
+    ) -> Result<String, String> {
+        let node = match self.find_instance(target) {
+            Some(n) => n,
+            None => self.find_driven(target)?.unwrap(),
+        };
+        match value {
+            Some(v) => {
+                node.insert_attribute(key.to_string(), v.to_string());
+            }
+            None => node.set_attribute(key.to_string()),
+        }
+        Ok(String::new())
+    }
+
+    fn report_stats(&self) -> Result<String, String> {
+        let mut s = String::new();
+        let ninsts = self
+            .netlist
+            .objects()
+            .filter(|o| o.get_instance_type().is_some())
+            .count();
+        writeln!(s, "inputs: {}", self.netlist.inputs().count()).unwrap();
+        writeln!(s, "outputs: {}", self.netlist.outputs().len()).unwrap();
+        writeln!(s, "instances: {ninsts}").unwrap();
+        Ok(s)
+    }
+
+    fn report_instances(&self) -> Result<String, String> {
+        let mut s = String::new();
+        for obj in self.netlist.objects() {
+            if let Some(inst_type) = obj.get_instance_type().map(|t| t.get_name().clone()) {
+                writeln!(s, "{} {inst_type}", obj.get_path()).unwrap();
+            }
+        }
+        Ok(s)
+    }
+
+    fn report_nets(&self) -> Result<String, String> {
+        let mut s = String::new();
+        for net in self.netlist.as_ref() {
+            writeln!(s, "{net}").unwrap();
+        }
+        Ok(s)
+    }
+}
@@ -0,0 +1,486 @@
+/*!
+
+  A minimal reader of Synopsys Design Constraints (SDC), for the clocks, port delays and false paths of [crate::timing].
+
+*/
+
+use crate::circuit::Identifier;
+
+/// A clock made by `create_clock`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    /// The name of the clock
+    name: String,
+    /// The period of the clock
+    period: f64,
+    /// The ports the clock enters the design through, if it is not virtual
+    sources: Vec<String>,
+    /// The times the clock rises and falls within a period, if they are not at the start and middle of it
+    waveform: Option<(f64, f64)>,
+}
+
+impl Clock {
+    /// Creates a clock called `name` with period `period`, entering the design through the ports `sources`
+    pub fn new(name: String, period: f64, sources: Vec<String>) -> Self {
+        Self {
+            name,
+            period,
+            sources,
+            waveform: None,
+        }
+    }
+
+    /// Sets the times the clock rises and falls within a period, which are otherwise at the start and middle of it
+    pub fn with_waveform(self, rise: f64, fall: f64) -> Self {
+        Self {
+            waveform: Some((rise, fall)),
+            ..self
+        }
+    }
+
+    /// Returns the name of the clock
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the period of the clock
+    pub fn period(&self) -> f64 {
+        self.period
+    }
+
+    /// Returns the ports the clock enters the design through. A virtual clock has none.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Returns the time the clock is high within a period, for which the latches it gates are transparent
+    pub fn high_time(&self) -> f64 {
+        match self.waveform {
+            Some((rise, fall)) => (fall - rise).rem_euclid(self.period),
+            None => self.period / 2.0,
+        }
+    }
+}
+
+/// A delay outside the design on a port, relative to a clock, set by `set_input_delay` or `set_output_delay`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortDelay {
+    /// The name of the port
+    port: String,
+    /// The clock the delay is relative to
+    clock: Option<String>,
+    /// The delay
+    delay: f64,
+}
+
+impl PortDelay {
+    /// Returns the name of the port
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    /// Returns the clock the delay is relative to
+    pub fn clock(&self) -> Option<&str> {
+        self.clock.as_deref()
+    }
+
+    /// Returns the delay
+    pub fn delay(&self) -> f64 {
+        self.delay
+    }
+}
+
+/// Paths left out of timing by `set_false_path`, from the named ports or registers to others.
+/// An empty list of start or end points stands for all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FalsePath {
+    /// The start points: input ports or register instances
+    from: Vec<String>,
+    /// The end points: output ports or register instances
+    to: Vec<String>,
+}
+
+impl FalsePath {
+    /// Returns the start points, input ports or register instances. An empty list stands for all of them.
+    pub fn from(&self) -> &[String] {
+        &self.from
+    }
+
+    /// Returns the end points, output ports or register instances. An empty list stands for all of them.
+    pub fn to(&self) -> &[String] {
+        &self.to
+    }
+
+    /// Returns `true` if paths from the start point `from` to the end point `to` are false
+    pub fn covers(&self, from: &Identifier, to: &Identifier) -> bool {
+        let named = |names: &[String], id: &Identifier| {
+            names.is_empty() || names.iter().any(|n| names_object(n, id))
+        };
+        named(&self.from, from) && named(&self.to, to)
+    }
+}
+
+/// Returns `true` if `name`, as written in SDC, names the object `id`, like `a[3]` for the escaped bit `\a[3] `
+pub(crate) fn names_object(name: &str, id: &Identifier) -> bool {
+    id.get_name() == name || id.to_string() == name
+}
+
+/// The timing constraints of a design, as read from SDC by [Constraints::parse]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    /// The clocks in the order they were created
+    clocks: Vec<Clock>,
+    /// The input delays, the last one set on a port taking precedence
+    input_delays: Vec<PortDelay>,
+    /// The output delays, the last one set on a port taking precedence
+    output_delays: Vec<PortDelay>,
+    /// The false paths
+    false_paths: Vec<FalsePath>,
+    /// The commands that were not understood, in the order they were read
+    ignored: Vec<String>,
+}
+
+/// A word of a Tcl command
+#[derive(Debug, Clone)]
+enum Word {
+    /// A bare, quoted or braced word
+    Text(String),
+    /// A command in brackets, evaluated to a list of object names
+    Command(Vec<Word>),
+}
+
+/// Splits the commands of `src` into words, following Tcl quoting closely enough for SDC
+fn parse_commands(src: &str) -> Result<Vec<Vec<Word>>, String> {
+    let chars: Vec<char> = src.replace("\\\n", " ").chars().collect();
+    let mut i = 0;
+    let mut commands = Vec::new();
+    while i < chars.len() {
+        let words = parse_words(&chars, &mut i, None)?;
+        if !words.is_empty() {
+            commands.push(words);
+        }
+    }
+    Ok(commands)
+}
+
+/// Reads the words of one command from `chars` at `i`, up to the end of the command or the bracket `close`
+fn parse_words(chars: &[char], i: &mut usize, close: Option<char>) -> Result<Vec<Word>, String> {
+    let mut words = Vec::new();
+    while *i < chars.len() {
+        let c = chars[*i];
+        match c {
+            '\n' | ';' if close.is_none() => {
+                *i += 1;
+                return Ok(words);
+            }
+            c if c.is_whitespace() => *i += 1,
+            '#' if words.is_empty() && close.is_none() => {
+                while *i < chars.len() && chars[*i] != '\n' {
+                    *i += 1;
+                }
+            }
+            ']' if close == Some(']') => {
+                *i += 1;
+                return Ok(words);
+            }
+            '[' => {
+                *i += 1;
+                words.push(Word::Command(parse_words(chars, i, Some(']'))?));
+            }
+            '{' => {
+                let start = *i + 1;
+                let mut depth = 0;
+                loop {
+                    match chars.get(*i) {
+                        Some('{') => depth += 1,
+                        Some('}') => depth -= 1,
+                        Some(_) => (),
+                        None => return Err("Unterminated braces".to_string()),
+                    }
+                    *i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                words.push(Word::Text(chars[start..*i - 1].iter().collect()));
+            }
+            '"' => {
+                let start = *i + 1;
+                *i = start;
+                while *i < chars.len() && chars[*i] != '"' {
+                    *i += 1;
+                }
+                if *i == chars.len() {
+                    return Err("Unterminated string".to_string());
+                }
+                words.push(Word::Text(chars[start..*i].iter().collect()));
+                *i += 1;
+            }
+            _ => {
+                let start = *i;
+                while *i < chars.len() && !chars[*i].is_whitespace() && !"[];".contains(chars[*i]) {
+                    *i += 1;
+                }
+                words.push(Word::Text(chars[start..*i].iter().collect()));
+            }
+        }
+    }
+    match close {
+        Some(c) => Err(format!("Missing {c}")),
+        None => Ok(words),
+    }
+}
+
+/// Evaluates `word` to a list of object names, like `{a b}` or `[get_ports a]`
+fn object_names(word: &Word) -> Result<Vec<String>, String> {
+    match word {
+        Word::Text(text) => Ok(text.split_whitespace().map(|s| s.to_string()).collect()),
+        Word::Command(words) => {
+            let Some(Word::Text(cmd)) = words.first() else {
+                return Err("Expected a command in brackets".to_string());
+            };
+            match cmd.as_str() {
+                "get_ports" | "get_pins" | "get_cells" | "get_clocks" | "get_nets" => {
+                    let mut names = Vec::new();
+                    for word in words[1..].iter() {
+                        if matches!(word, Word::Text(t) if t.starts_with('-')) {
+                            continue;
+                        }
+                        names.extend(object_names(word)?);
+                    }
+                    Ok(names)
+                }
+                _ => Err(format!("Unsupported command {cmd} in brackets")),
+            }
+        }
+    }
+}
+
+/// The options of a command with their values, if they take one
+type Options<'a> = Vec<(&'a str, Option<&'a Word>)>;
+
+/// Splits the arguments of a command into its options with their values and its positional arguments.
+/// `flags` are the options that take no value.
+fn split_options<'a>(
+    args: &'a [Word],
+    flags: &[&str],
+) -> Result<(Options<'a>, Vec<&'a Word>), String> {
+    let mut options = Vec::new();
+    let mut positional = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match &args[i] {
+            Word::Text(t) if t.starts_with('-') && t.parse::<f64>().is_err() => {
+                if flags.contains(&t.as_str()) {
+                    options.push((t.as_str(), None));
+                    i += 1;
+                } else {
+                    let value = args.get(i + 1).ok_or(format!("Missing value for {t}"))?;
+                    options.push((t.as_str(), Some(value)));
+                    i += 2;
+                }
+            }
+            word => {
+                positional.push(word);
+                i += 1;
+            }
+        }
+    }
+    Ok((options, positional))
+}
+
+/// Returns the value of a word as a number
+fn number(word: &Word) -> Result<f64, String> {
+    match word {
+        Word::Text(t) => t.parse().map_err(|_| format!("Expected a number, got {t}")),
+        Word::Command(_) => Err("Expected a number, got a command".to_string()),
+    }
+}
+
+impl Constraints {
+    /// Creates an empty set of constraints
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the constraints in the SDC `src`. The commands read are `create_clock`, `set_input_delay`,
+    /// `set_output_delay` and `set_false_path`, with objects given by name or with `get_ports`, `get_cells` and the like.
+    /// Other commands are skipped and listed by [Constraints::ignored]. Minimum delays are skipped, since only setup is analyzed.
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut constraints = Self::new();
+        for (n, words) in parse_commands(src)?.into_iter().enumerate() {
+            constraints
+                .apply(&words)
+                .map_err(|e| format!("Command {}: {e}", n + 1))?;
+        }
+        Ok(constraints)
+    }
+
+    /// Applies one command
+    fn apply(&mut self, words: &[Word]) -> Result<(), String> {
+        let Word::Text(cmd) = &words[0] else {
+            return Err("Expected a command".to_string());
+        };
+        let args = &words[1..];
+        match cmd.as_str() {
+            "create_clock" => {
+                let (options, positional) = split_options(args, &["-add"])?;
+                let mut name = None;
+                let mut period = None;
+                let mut waveform = None;
+                for (option, value) in options {
+                    match (option, value) {
+                        ("-name", Some(Word::Text(n))) => name = Some(n.clone()),
+                        ("-period", Some(value)) => period = Some(number(value)?),
+                        ("-waveform", Some(Word::Text(w))) => {
+                            let edges: Vec<f64> = w
+                                .split_whitespace()
+                                .map(|e| {
+                                    e.parse().map_err(|_| format!("Expected a number, got {e}"))
+                                })
+                                .collect::<Result<_, _>>()?;
+                            let [rise, fall] = edges.as_slice() else {
+                                return Err(format!(
+                                    "Expected a rise and a fall in -waveform, got {w}"
+                                ));
+                            };
+                            waveform = Some((*rise, *fall));
+                        }
+                        ("-comment" | "-add", _) => (),
+                        _ => return Err(format!("Unsupported option {option} of {cmd}")),
+                    }
+                }
+                let mut sources = Vec::new();
+                for word in positional {
+                    sources.extend(object_names(word)?);
+                }
+                let name = name
+                    .or(sources.first().cloned())
+                    .ok_or("A clock needs a name or a source")?;
+                let period = period.ok_or(format!("Clock {name} has no period"))?;
+                let clock = Clock::new(name, period, sources);
+                self.add_clock(match waveform {
+                    Some((rise, fall)) => clock.with_waveform(rise, fall),
+                    None => clock,
+                });
+            }
+            "set_input_delay" | "set_output_delay" => {
+                let flags = ["-max", "-min", "-rise", "-fall", "-add_delay"];
+                let (options, positional) = split_options(args, &flags)?;
+                let mut clock = None;
+                let mut min_only = false;
+                let mut max = false;
+                for (option, value) in options {
+                    match (option, value) {
+                        ("-clock", Some(value)) => clock = object_names(value)?.into_iter().next(),
+                        ("-min", _) => min_only = true,
+                        ("-max", _) => max = true,
+                        ("-rise" | "-fall" | "-add_delay" | "-clock_fall", _) => (),
+                        _ => return Err(format!("Unsupported option {option} of {cmd}")),
+                    }
+                }
+                let [delay, ports @ ..] = positional.as_slice() else {
+                    return Err(format!("{cmd} needs a delay"));
+                };
+                if min_only && !max {
+                    return Ok(());
+                }
+                let delay = number(delay)?;
+                for word in ports {
+                    for port in object_names(word)? {
+                        let port_delay = PortDelay {
+                            port,
+                            clock: clock.clone(),
+                            delay,
+                        };
+                        match cmd.as_str() {
+                            "set_input_delay" => self.input_delays.push(port_delay),
+                            _ => self.output_delays.push(port_delay),
+                        }
+                    }
+                }
+            }
+            "set_false_path" => {
+                let (options, positional) = split_options(args, &["-setup", "-hold"])?;
+                if !positional.is_empty() {
+                    return Err(format!("Unexpected arguments to {cmd}"));
+                }
+                let mut from = Vec::new();
+                let mut to = Vec::new();
+                for (option, value) in options {
+                    match (option, value) {
+                        ("-from", Some(value)) => from.extend(object_names(value)?),
+                        ("-to", Some(value)) => to.extend(object_names(value)?),
+                        ("-setup" | "-hold", _) => (),
+                        _ => return Err(format!("Unsupported option {option} of {cmd}")),
+                    }
+                }
+                self.false_paths.push(FalsePath { from, to });
+            }
+            _ => self.ignored.push(cmd.clone()),
+        }
+        Ok(())
+    }
+
+    /// Adds `clock`, replacing any clock with its name
+    pub fn add_clock(&mut self, clock: Clock) {
+        self.clocks.retain(|c| c.name != clock.name);
+        self.clocks.push(clock);
+    }
+
+    /// Returns the clocks in the order they were created
+    pub fn clocks(&self) -> &[Clock] {
+        &self.clocks
+    }
+
+    /// Returns the clock called `name`
+    pub fn get_clock(&self, name: &str) -> Option<&Clock> {
+        self.clocks.iter().find(|c| c.name == name)
+    }
+
+    /// Returns the clock entering the design through the port `port`
+    pub fn clock_of_port(&self, port: &Identifier) -> Option<&Clock> {
+        self.clocks
+            .iter()
+            .find(|c| c.sources.iter().any(|s| names_object(s, port)))
+    }
+
+    /// Returns the input delay last set on the port `port`
+    pub fn input_delay(&self, port: &Identifier) -> Option<&PortDelay> {
+        self.input_delays
+            .iter()
+            .rev()
+            .find(|d| names_object(&d.port, port))
+    }
+
+    /// Returns the output delay last set on the port `port`
+    pub fn output_delay(&self, port: &Identifier) -> Option<&PortDelay> {
+        self.output_delays
+            .iter()
+            .rev()
+            .find(|d| names_object(&d.port, port))
+    }
+
+    /// Returns the false paths
+    pub fn false_paths(&self) -> &[FalsePath] {
+        &self.false_paths
+    }
+
+    /// Returns `true` if paths from the start point `from` to the end point `to` are false
+    pub fn is_false_path(&self, from: &Identifier, to: &Identifier) -> bool {
+        self.false_paths.iter().any(|p| p.covers(from, to))
+    }
+
+    /// Returns the names of the commands that were skipped, in the order they were read
+    pub fn ignored(&self) -> &[String] {
+        &self.ignored
+    }
+}
+
+impl std::str::FromStr for Constraints {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
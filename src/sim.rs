@@ -0,0 +1,829 @@
+/*!
+
+  Four-state simulation of netlists, with gray-box models standing in for instances whose structure is not available,
+  and bitwise simulation of two-state netlists, 64 patterns at a time.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable, SequentialElement},
+    constraint::InputConstraint,
+    graph::topological_order,
+    netlist::{DrivenNet, Gate, InputPort, NetRef, Netlist},
+};
+use std::collections::HashMap;
+
+/// A four-state logic value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Logic {
+    /// Logical 0
+    Zero,
+    /// Logical 1
+    One,
+    /// An unknown value
+    #[default]
+    X,
+    /// High impedance
+    Z,
+}
+
+impl Logic {
+    /// Returns the value as a bool, if it is 0 or 1
+    pub fn as_bool(self) -> Option<bool> {
+        match self {
+            Logic::Zero => Some(false),
+            Logic::One => Some(true),
+            Logic::X | Logic::Z => None,
+        }
+    }
+
+    /// Returns `true` if the value is 0 or 1
+    pub fn is_known(self) -> bool {
+        self.as_bool().is_some()
+    }
+}
+
+impl From<bool> for Logic {
+    fn from(value: bool) -> Self {
+        match value {
+            true => Logic::One,
+            false => Logic::Zero,
+        }
+    }
+}
+
+impl std::ops::Not for Logic {
+    type Output = Logic;
+
+    fn not(self) -> Self::Output {
+        match self.as_bool() {
+            Some(b) => (!b).into(),
+            None => Logic::X,
+        }
+    }
+}
+
+impl std::ops::BitAnd for Logic {
+    type Output = Logic;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        match (self.as_bool(), rhs.as_bool()) {
+            (Some(false), _) | (_, Some(false)) => Logic::Zero,
+            (Some(true), Some(true)) => Logic::One,
+            _ => Logic::X,
+        }
+    }
+}
+
+impl std::ops::BitOr for Logic {
+    type Output = Logic;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        !(!self & !rhs)
+    }
+}
+
+impl std::ops::BitXor for Logic {
+    type Output = Logic;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        match (self.as_bool(), rhs.as_bool()) {
+            (Some(a), Some(b)) => (a ^ b).into(),
+            _ => Logic::X,
+        }
+    }
+}
+
+impl std::fmt::Display for Logic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Logic::Zero => write!(f, "0"),
+            Logic::One => write!(f, "1"),
+            Logic::X => write!(f, "x"),
+            Logic::Z => write!(f, "z"),
+        }
+    }
+}
+
+/// The strength a net is driven with, from weakest to strongest, like the strengths of Verilog.
+/// Where a net has many drivers, the strongest drivers set its value (see [resolve]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Strength {
+    /// The driver is disconnected
+    HighZ,
+    /// A weak driver, like a keeper
+    Weak,
+    /// A resistive driver, like a pull-up
+    Pull,
+    /// An ordinary gate output
+    #[default]
+    Strong,
+    /// A power or ground supply
+    Supply,
+}
+
+impl std::fmt::Display for Strength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strength::HighZ => write!(f, "highz"),
+            Strength::Weak => write!(f, "weak"),
+            Strength::Pull => write!(f, "pull"),
+            Strength::Strong => write!(f, "strong"),
+            Strength::Supply => write!(f, "supply"),
+        }
+    }
+}
+
+impl std::str::FromStr for Strength {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "highz" => Ok(Strength::HighZ),
+            "weak" => Ok(Strength::Weak),
+            "pull" => Ok(Strength::Pull),
+            "strong" => Ok(Strength::Strong),
+            "supply" => Ok(Strength::Supply),
+            _ => Err(format!("Unknown drive strength {s}")),
+        }
+    }
+}
+
+/// Returns the values driven by the strongest of `drivers`, skipping drivers that are disconnected or drive `z`
+fn strongest(drivers: &[(Logic, Strength)]) -> Vec<Logic> {
+    let driving = drivers
+        .iter()
+        .filter(|(v, s)| *v != Logic::Z && *s != Strength::HighZ);
+    let Some(max) = driving.clone().map(|(_, s)| *s).max() else {
+        return Vec::new();
+    };
+    driving
+        .filter(|(_, s)| *s == max)
+        .map(|(v, _)| *v)
+        .collect()
+}
+
+/// Returns the value of a net with many `drivers`, each with the value and strength it drives.
+/// The strongest drivers set the value, so a pull-up only holds a wired-AND high while no gate drives it low.
+/// The value is `z` if nothing drives the net and `x` if the strongest drivers disagree.
+pub fn resolve(drivers: &[(Logic, Strength)]) -> Logic {
+    let values = strongest(drivers);
+    match values.split_first() {
+        None => Logic::Z,
+        Some((first, rest)) if rest.iter().all(|v| v == first) => *first,
+        Some(_) => Logic::X,
+    }
+}
+
+/// Returns `true` if the strongest of `drivers` drive both 0 and 1, see [resolve]
+pub fn is_conflict(drivers: &[(Logic, Strength)]) -> bool {
+    let values = strongest(drivers);
+    values.contains(&Logic::Zero) && values.contains(&Logic::One)
+}
+
+/// Returns the outputs of the common [Gate]s by name, like [crate::aig::decompose_gate], or [None] for other gates
+pub fn eval_gate(gate: &Gate, inputs: &[Logic]) -> Option<Vec<Logic>> {
+    let name = gate.get_gate_name().to_string();
+    let fold = |init: Logic, f: fn(Logic, Logic) -> Logic| inputs.iter().copied().fold(init, f);
+    let output = match name.as_str() {
+        "AND" => fold(Logic::One, |a, b| a & b),
+        "NAND" => !fold(Logic::One, |a, b| a & b),
+        "OR" => fold(Logic::Zero, |a, b| a | b),
+        "NOR" => !fold(Logic::Zero, |a, b| a | b),
+        "XOR" => fold(Logic::Zero, |a, b| a ^ b),
+        "XNOR" => !fold(Logic::Zero, |a, b| a ^ b),
+        "INV" | "NOT" if inputs.len() == 1 => !inputs[0],
+        "BUF" if inputs.len() == 1 => inputs[0] & Logic::One,
+        "MUX" if inputs.len() == 3 => match inputs[2].as_bool() {
+            Some(true) => inputs[1] & Logic::One,
+            Some(false) => inputs[0] & Logic::One,
+            None if inputs[0] == inputs[1] && inputs[0].is_known() => inputs[0],
+            None => Logic::X,
+        },
+        _ => return None,
+    };
+    Some(vec![output])
+}
+
+/// Returns the outputs of the common [Gate]s by name like [eval_gate], for 64 two-state patterns at a time, one per bit.
+/// Returns [None] for other gates.
+pub fn eval_gate_packed(gate: &Gate, inputs: &[u64]) -> Option<Vec<u64>> {
+    let name = gate.get_gate_name().to_string();
+    let fold = |init: u64, f: fn(u64, u64) -> u64| inputs.iter().copied().fold(init, f);
+    let output = match name.as_str() {
+        "AND" => fold(!0, |a, b| a & b),
+        "NAND" => !fold(!0, |a, b| a & b),
+        "OR" => fold(0, |a, b| a | b),
+        "NOR" => !fold(0, |a, b| a | b),
+        "XOR" => fold(0, |a, b| a ^ b),
+        "XNOR" => !fold(0, |a, b| a ^ b),
+        "INV" | "NOT" if inputs.len() == 1 => !inputs[0],
+        "BUF" if inputs.len() == 1 => inputs[0],
+        "MUX" if inputs.len() == 3 => (inputs[2] & inputs[1]) | (!inputs[2] & inputs[0]),
+        _ => return None,
+    };
+    Some(vec![output])
+}
+
+/// Returns the state of a primitive of the built-in sequential library, like [Gate::dff], after a clock edge,
+/// from its inputs and its state before the edge. Latches are evaluated with their gate held at its input value.
+/// Returns [None] for other gates. This gives the next state of registers with [Simulator::with_next_state], one edge per step.
+pub fn next_state(gate: &Gate, inputs: &[Logic], state: Logic) -> Option<Logic> {
+    // Loads `d` when `load` is high, and keeps the state when it is low
+    let load = |load: Logic, d: Logic| match load.as_bool() {
+        Some(true) => d & Logic::One,
+        Some(false) => state,
+        None if d == state => state,
+        None => Logic::X,
+    };
+    let name = gate.get_gate_name().to_string();
+    match name.as_str() {
+        "DFF" if inputs.len() == 2 => Some(inputs[0] & Logic::One),
+        "DFFR" if inputs.len() == 3 => Some(match inputs[2].as_bool() {
+            Some(true) => Logic::Zero,
+            Some(false) => inputs[0] & Logic::One,
+            None if inputs[0] == Logic::Zero => Logic::Zero,
+            None => Logic::X,
+        }),
+        "DFFE" if inputs.len() == 3 => Some(load(inputs[2], inputs[0])),
+        "DLATCH" if inputs.len() == 2 => Some(load(inputs[1], inputs[0])),
+        _ => None,
+    }
+}
+
+/// The instance outputs that drive each `inout` port, by the input node of the port
+type Wired<I> = HashMap<NetRef<I>, Vec<DrivenNet<I>>>;
+
+/// Orders the nodes of `netlist` so every node comes after the nodes it depends on, where an `inout` port depends on every output that drives it.
+/// Nodes for which `holds` is `true`, like registers, hold their values through a step, so they do not depend on their inputs.
+/// Also returns the instance outputs that drive each `inout` port.
+/// Returns an error if the netlist has a combinational cycle.
+fn simulation_order<I: Instantiable>(
+    netlist: &Netlist<I>,
+    holds: impl Fn(&NetRef<I>) -> bool,
+) -> Result<(Vec<NetRef<I>>, Wired<I>), String> {
+    // The value of an inout port depends on every output that drives it
+    let pads: HashMap<Identifier, NetRef<I>> = netlist
+        .objects()
+        .filter(|n| n.is_an_input())
+        .map(|n| (n.get_identifier(), n))
+        .collect();
+    let mut wired: Wired<I> = HashMap::new();
+    for node in netlist.objects().filter(|n| !n.is_an_input()) {
+        for net in node.outputs() {
+            if let Some(pad) = pads.get(&net.get_identifier()) {
+                wired.entry(pad.clone()).or_default().push(net);
+            }
+        }
+    }
+    let order = topological_order(netlist.objects(), |node| {
+        let mut dependencies: Vec<NetRef<I>> = wired
+            .get(node)
+            .into_iter()
+            .flatten()
+            .map(|net| net.clone().unwrap())
+            .collect();
+        if !holds(node) {
+            dependencies.extend(node.drivers().flatten());
+        }
+        dependencies
+    })
+    .map_err(|_| "Cycle detected in the netlist".to_string())?;
+    Ok((order, wired))
+}
+
+/// Returns the input nodes `constraint` is on, in the order of [InputConstraint::inputs]
+fn constrained_inputs<I: Instantiable>(
+    netlist: &Netlist<I>,
+    constraint: &InputConstraint,
+) -> Result<Vec<NetRef<I>>, String> {
+    constraint
+        .inputs()
+        .iter()
+        .map(|name| {
+            netlist
+                .inputs()
+                .find(|i| i.get_identifier() == *name)
+                .map(|i| i.unwrap())
+                .ok_or_else(|| format!("{constraint} is on {name}, which is not an input"))
+        })
+        .collect()
+}
+
+/// Returns the sets of two-state input values that violate `constraint`, as a mask of bits.
+/// `values` are given in the order of [InputConstraint::inputs].
+fn violations(constraint: &InputConstraint, values: &[u64]) -> u64 {
+    // The bits where at least one and at least two of the values are one
+    let (mut one, mut two) = (0, 0);
+    for v in values {
+        two |= one & v;
+        one |= v;
+    }
+    match constraint {
+        InputConstraint::Constant(_, true) => !values[0],
+        InputConstraint::Constant(_, false) => values[0],
+        InputConstraint::OneHot(_) => !one | two,
+        InputConstraint::Exclusive(_) => two,
+    }
+}
+
+/// The behavior of a gray-box instance: its outputs from its inputs and its state, which it may update
+type Model = Box<dyn Fn(&[Logic], &mut Vec<Logic>) -> Vec<Logic>>;
+
+/// Evaluates a primitive from its inputs, or returns [None] if it is not known
+type Eval<'a, I> = Box<dyn Fn(&I, &[Logic]) -> Option<Vec<Logic>> + 'a>;
+
+/// Gives the state of a register after a clock edge from its inputs and its state before the edge, or [None] if it is not known
+type NextState<'a, I> = Box<dyn Fn(&I, &[Logic], Logic) -> Option<Logic> + 'a>;
+
+/// Evaluates a primitive from its inputs for 64 two-state patterns at a time, or returns [None] if it is not known
+type PackedEval<'a, I> = Box<dyn Fn(&I, &[u64]) -> Option<Vec<u64>> + 'a>;
+
+/// A gray-box model, with the state each instance it models starts in
+struct GrayBox {
+    model: Model,
+    init: Vec<Logic>,
+}
+
+/// Simulates a netlist one set of input values at a time, with one clock edge per step.
+/// Primitives are evaluated by a closure, like [eval_gate]. Instances it does not know, like IP blocks,
+/// can be given a gray-box model with [Simulator::with_model], and otherwise drive unknown values.
+///
+/// Registers (see [SequentialElement]) hold their state through a step, so feedback through them is not a cycle.
+/// They start from their initial value (see [NetRef::get_initial_value]), or `x` if they have none,
+/// and load their next state at the end of each step, as given by [Simulator::with_next_state].
+/// Registers with a gray-box model are evaluated by the model in place instead, like any other instance.
+/// Models and their state live in the simulator, so the netlist is left unchanged.
+pub struct Simulator<'a, I: Instantiable> {
+    /// The netlist being simulated
+    netlist: &'a Netlist<I>,
+    /// Evaluates a primitive, returning [None] if it is not known
+    eval: Eval<'a, I>,
+    /// Gives the next state of a register
+    next: NextState<'a, I>,
+    /// The gray-box models by instance type name
+    models: HashMap<Identifier, GrayBox>,
+    /// The state of every gray-box instance and register that has been simulated
+    state: HashMap<NetRef<I>, Vec<Logic>>,
+    /// The nodes of the netlist in topological order, found again when a model is added, or the cycle that prevents it
+    order: Result<Vec<NetRef<I>>, String>,
+    /// The values of the nets in the last step
+    values: HashMap<NetRef<I>, Vec<Logic>>,
+    /// The instance outputs that also drive a net of an `inout` port, by the input node of the port
+    wired: Wired<I>,
+    /// The nets of `inout` ports whose drivers conflicted in the last step
+    conflicts: Vec<DrivenNet<I>>,
+    /// The values forced on nets, by their driving node and output position
+    forced: HashMap<(NetRef<I>, usize), Logic>,
+    /// The values forced on input pins, by their instance and input position
+    forced_pins: HashMap<(NetRef<I>, usize), Logic>,
+}
+
+/// Returns the value at the data input of a register, the default next state of [Simulator]
+fn load_data<I: SequentialElement>(inst: &I, inputs: &[Logic], _state: Logic) -> Option<Logic> {
+    inputs.get(inst.get_data_input()?).map(|d| *d & Logic::One)
+}
+
+impl<'a, I> Simulator<'a, I>
+where
+    I: SequentialElement,
+{
+    /// Creates a simulator for `netlist`, where `eval` gives the outputs of a primitive from its inputs.
+    /// Registers load the value at their data input on every step, unless changed with [Simulator::with_next_state].
+    /// Returns an error if the netlist has a combinational cycle.
+    pub fn new(
+        netlist: &'a Netlist<I>,
+        eval: impl Fn(&I, &[Logic]) -> Option<Vec<Logic>> + 'a,
+    ) -> Result<Self, String> {
+        let (order, wired) = simulation_order(netlist, |n| n.is_register())?;
+
+        Ok(Self {
+            netlist,
+            eval: Box::new(eval),
+            next: Box::new(load_data),
+            models: HashMap::new(),
+            state: HashMap::new(),
+            order: Ok(order),
+            values: HashMap::new(),
+            wired,
+            conflicts: Vec::new(),
+            forced: HashMap::new(),
+            forced_pins: HashMap::new(),
+        })
+    }
+
+    /// Models every instance of the type named `type_name` with `model`, which returns the outputs of an instance
+    /// from its inputs and state. Each instance starts with the state `init`, and the model may update it.
+    /// Models take precedence over the primitive evaluator, and over the next state of registers.
+    /// If modeling a register in place closes a combinational cycle, [Simulator::step] returns an error.
+    pub fn with_model(
+        mut self,
+        type_name: Identifier,
+        init: Vec<Logic>,
+        model: impl Fn(&[Logic], &mut Vec<Logic>) -> Vec<Logic> + 'static,
+    ) -> Self {
+        self.models.insert(
+            type_name,
+            GrayBox {
+                model: Box::new(model),
+                init,
+            },
+        );
+        self.order = simulation_order(self.netlist, |n| self.holds(n)).map(|(order, _)| order);
+        self
+    }
+
+    /// Sets how registers load their next state, from their inputs and their state before the clock edge,
+    /// like [next_state] for the built-in sequential library. Registers whose next state is not known load `x`.
+    pub fn with_next_state(
+        mut self,
+        next: impl Fn(&I, &[Logic], Logic) -> Option<Logic> + 'a,
+    ) -> Self {
+        self.next = Box::new(next);
+        self
+    }
+
+    /// Returns `true` if `node` is a register that holds its state through a step, rather than being modeled in place
+    fn holds(&self, node: &NetRef<I>) -> bool {
+        node.get_instance_type()
+            .is_some_and(|t| t.is_register() && !self.models.contains_key(t.get_name()))
+    }
+
+    /// Returns the state of the register `node`, starting from its initial value
+    fn register_state(&mut self, node: &NetRef<I>) -> &mut Vec<Logic> {
+        self.state.entry(node.clone()).or_insert_with(|| {
+            let init = node.get_initial_value();
+            let mut state = vec![Logic::X; node.outputs().count()];
+            state[0] = init.map_or(Logic::X, Logic::from);
+            state
+        })
+    }
+
+    /// Simulates the netlist with the values of its inputs, in the order of [Netlist::inputs].
+    /// Returns the values of its outputs, in the order of [Netlist::outputs].
+    ///
+    /// The value of an `inout` port is resolved from the value it is given, which is `z` if it is not driven from outside,
+    /// and the outputs that drive it (see [Netlist::drive_inout]), by their strengths (see [resolve]).
+    /// Loads of the port see the resolved value, and conflicts are kept in [Simulator::conflicts].
+    ///
+    /// Unknown inputs take the values implied by the input constraints of the netlist (see [Netlist::constrain_inputs]).
+    /// Returns an error if the known inputs violate a constraint, or if an input is still unknown in a two-state netlist (see [Netlist::set_two_state]).
+    pub fn step(&mut self, inputs: &[Logic]) -> Result<Vec<Logic>, String> {
+        let names: Vec<DrivenNet<I>> = self.netlist.inputs().collect();
+        if names.len() != inputs.len() {
+            return Err(format!(
+                "Expected {} input values, got {}",
+                names.len(),
+                inputs.len()
+            ));
+        }
+        let mut values: HashMap<NetRef<I>, Vec<Logic>> = names
+            .into_iter()
+            .zip(inputs)
+            .map(|(input, value)| (input.unwrap(), vec![*value]))
+            .collect();
+        for constraint in self.netlist.get_input_constraints() {
+            let nodes = constrained_inputs(self.netlist, &constraint)?;
+            let mut known: Vec<Option<bool>> =
+                nodes.iter().map(|n| values[n][0].as_bool()).collect();
+            constraint.imply(&mut known)?;
+            for (node, value) in nodes.iter().zip(known) {
+                if let Some(value) = value {
+                    values.insert(node.clone(), vec![Logic::from(value)]);
+                }
+            }
+        }
+        if self.netlist.is_two_state()
+            && let Some(input) = self
+                .netlist
+                .inputs()
+                .find(|i| !values[&i.clone().unwrap()][0].is_known())
+        {
+            return Err(format!(
+                "Input {} is unknown, but {} is declared two-state",
+                input.get_identifier(),
+                self.netlist.get_name()
+            ));
+        }
+        if let Err(e) = &self.order {
+            return Err(e.clone());
+        }
+        let registers: Vec<NetRef<I>> = self
+            .order
+            .iter()
+            .flatten()
+            .filter(|n| self.holds(n))
+            .cloned()
+            .collect();
+        for register in &registers {
+            let state = self.register_state(register).clone();
+            values.insert(register.clone(), state);
+        }
+        for ((node, pos), value) in &self.forced {
+            if let Some(v) = values.get_mut(node) {
+                v[*pos] = *value;
+            }
+        }
+
+        let mut conflicts = Vec::new();
+        for node in self.order.iter().flatten() {
+            if node.is_an_input() {
+                let Some(outputs) = self.wired.get(node) else {
+                    continue;
+                };
+                let pad = node.get_output(0);
+                let drivers: Vec<(Logic, Strength)> =
+                    std::iter::once((values[node][0], pad.drive_strength()))
+                        .chain(outputs.iter().map(|d| {
+                            (
+                                values[&d.clone().unwrap()][d.get_position()],
+                                d.drive_strength(),
+                            )
+                        }))
+                        .collect();
+                if is_conflict(&drivers) {
+                    conflicts.push(pad);
+                }
+                let value = self.forced.get(&(node.clone(), 0)).copied();
+                values.insert(node.clone(), vec![value.unwrap_or(resolve(&drivers))]);
+                continue;
+            }
+            if self.holds(node) {
+                continue;
+            }
+            let operands = self.operands(node, &values);
+            let nouts = node.outputs().count();
+            let mut outputs = match node.get_instance_type() {
+                None => operands,
+                Some(inst_type) => match self.models.get(inst_type.get_name()) {
+                    Some(gray) => {
+                        let state = self
+                            .state
+                            .entry(node.clone())
+                            .or_insert_with(|| gray.init.clone());
+                        (gray.model)(&operands, state)
+                    }
+                    None => (self.eval)(&inst_type, &operands).unwrap_or(vec![Logic::X; nouts]),
+                },
+            };
+            if outputs.len() != nouts {
+                return Err(format!(
+                    "The model of {node} returned {} outputs, expected {nouts}",
+                    outputs.len()
+                ));
+            }
+            for (pos, value) in outputs.iter_mut().enumerate() {
+                if let Some(forced) = self.forced.get(&(node.clone(), pos)) {
+                    *value = *forced;
+                }
+            }
+            values.insert(node.clone(), outputs);
+        }
+
+        // The clock edge at the end of the step
+        for register in &registers {
+            let operands = self.operands(register, &values);
+            let inst_type = register.get_instance_type().unwrap().clone();
+            let state = self.register_state(register)[0];
+            let next = (self.next)(&inst_type, &operands, state).unwrap_or(Logic::X);
+            self.register_state(register)[0] = next;
+        }
+
+        let outputs = self
+            .netlist
+            .outputs()
+            .into_iter()
+            .map(|(driver, _)| values[&driver.clone().unwrap()][driver.get_position()])
+            .collect();
+        self.values = values;
+        self.conflicts = conflicts;
+        Ok(outputs)
+    }
+
+    /// Returns the values at the inputs of `node`, from the values of their drivers or the values forced on them
+    fn operands(&self, node: &NetRef<I>, values: &HashMap<NetRef<I>, Vec<Logic>>) -> Vec<Logic> {
+        node.operands()
+            .map(
+                |(i, driver)| match self.forced_pins.get(&(node.clone(), i)) {
+                    Some(value) => *value,
+                    None => match driver {
+                        Some(d) => values[&d.clone().unwrap()][d.get_position()],
+                        None => Logic::Z,
+                    },
+                },
+            )
+            .collect()
+    }
+
+    /// Returns the value of `net` in the last step, if it has been simulated
+    pub fn get_value(&self, net: &DrivenNet<I>) -> Option<Logic> {
+        self.values
+            .get(&net.clone().unwrap())
+            .map(|v| v[net.get_position()])
+    }
+
+    /// Returns the values in the last step of the outputs of the anchored nodes (see [NetRef::anchor]), in object order
+    pub fn trace(&self) -> Vec<(DrivenNet<I>, Logic)> {
+        self.netlist
+            .anchors()
+            .flat_map(|n| n.outputs().collect::<Vec<_>>())
+            .filter_map(|d| self.get_value(&d).map(|v| (d, v)))
+            .collect()
+    }
+
+    /// Returns the nets of `inout` ports whose strongest drivers drove both 0 and 1 in the last step
+    pub fn conflicts(&self) -> &[DrivenNet<I>] {
+        &self.conflicts
+    }
+
+    /// Returns the state of the gray-box instance or register `inst`, if it has been simulated.
+    /// The state of a register is the value of its outputs in the next step.
+    pub fn get_state(&self, inst: &NetRef<I>) -> Option<&[Logic]> {
+        self.state.get(inst).map(|s| s.as_slice())
+    }
+
+    /// Forces `net` to `value` in the following steps, as seen by all of its loads, like a stuck-at fault on the net
+    pub fn force(&mut self, net: &DrivenNet<I>, value: Logic) {
+        self.forced
+            .insert((net.clone().unwrap(), net.get_position()), value);
+    }
+
+    /// Forces the input pin `pin` to `value` in the following steps, as seen only by its instance, like a stuck-at fault on the pin
+    pub fn force_pin(&mut self, pin: &InputPort<I>, value: Logic) {
+        self.forced_pins
+            .insert((pin.clone().unwrap(), pin.get_position()), value);
+    }
+
+    /// Releases every net and pin forced with [Simulator::force] and [Simulator::force_pin]
+    pub fn release(&mut self) {
+        self.forced.clear();
+        self.forced_pins.clear();
+    }
+
+    /// Returns every gray-box instance and register to the state it started in
+    pub fn reset(&mut self) {
+        self.state.clear();
+        self.values.clear();
+        self.conflicts.clear();
+    }
+}
+
+/// Simulates a combinational two-state netlist (see [Netlist::set_two_state]) on 64 sets of input values at a time,
+/// where the `i`th bit of every value belongs to the `i`th set. Primitives are evaluated with bitwise operations by a closure, like [eval_gate_packed].
+/// Without `x` and `z` there is nothing to resolve, so `inout` ports are simulated like inputs,
+/// unconnected inputs take their default value (see [Instantiable::get_input_default]), and every primitive must be known.
+pub struct PackedSimulator<'a, I: Instantiable> {
+    /// The netlist being simulated
+    netlist: &'a Netlist<I>,
+    /// Evaluates a primitive, returning [None] if it is not known
+    eval: PackedEval<'a, I>,
+    /// The nodes of the netlist in topological order
+    order: Vec<NetRef<I>>,
+    /// The values of the nets in the last step
+    values: HashMap<NetRef<I>, Vec<u64>>,
+    /// The values forced on nets, by their driving node and output position
+    forced: HashMap<(NetRef<I>, usize), bool>,
+    /// The values forced on input pins, by their instance and input position
+    forced_pins: HashMap<(NetRef<I>, usize), bool>,
+}
+
+impl<'a, I> PackedSimulator<'a, I>
+where
+    I: Instantiable,
+{
+    /// Creates a simulator for `netlist`, where `eval` gives the outputs of a primitive from its inputs.
+    /// Returns an error if the netlist is not declared two-state or has a combinational cycle.
+    pub fn new(
+        netlist: &'a Netlist<I>,
+        eval: impl Fn(&I, &[u64]) -> Option<Vec<u64>> + 'a,
+    ) -> Result<Self, String> {
+        if !netlist.is_two_state() {
+            return Err(format!(
+                "Netlist {} is not declared two-state",
+                netlist.get_name()
+            ));
+        }
+        let (order, _) = simulation_order(netlist, |_| false)?;
+        Ok(Self {
+            netlist,
+            eval: Box::new(eval),
+            order,
+            values: HashMap::new(),
+            forced: HashMap::new(),
+            forced_pins: HashMap::new(),
+        })
+    }
+
+    /// Simulates the netlist with the values of its inputs, in the order of [Netlist::inputs].
+    /// Returns the values of its outputs, in the order of [Netlist::outputs].
+    /// Returns an error if a primitive cannot be evaluated or an input without a default is unconnected,
+    /// or if a set of input values violates an input constraint of the netlist (see [Netlist::constrain_inputs]).
+    pub fn step(&mut self, inputs: &[u64]) -> Result<Vec<u64>, String> {
+        let names: Vec<DrivenNet<I>> = self.netlist.inputs().collect();
+        if names.len() != inputs.len() {
+            return Err(format!(
+                "Expected {} input values, got {}",
+                names.len(),
+                inputs.len()
+            ));
+        }
+        let mut values: HashMap<NetRef<I>, Vec<u64>> = names
+            .into_iter()
+            .zip(inputs)
+            .map(|(input, value)| (input.unwrap(), vec![*value]))
+            .collect();
+        for constraint in self.netlist.get_input_constraints() {
+            let nodes = constrained_inputs(self.netlist, &constraint)?;
+            let packed: Vec<u64> = nodes.iter().map(|n| values[n][0]).collect();
+            let violated = violations(&constraint, &packed);
+            if violated != 0 {
+                return Err(format!(
+                    "Set {} of the input values violates {constraint}",
+                    violated.trailing_zeros()
+                ));
+            }
+        }
+        for ((node, pos), value) in &self.forced {
+            if let Some(v) = values.get_mut(node) {
+                v[*pos] = splat(*value);
+            }
+        }
+
+        for node in self.order.iter().filter(|n| !n.is_an_input()) {
+            let inst_type = node.get_instance_type();
+            let operands = node
+                .operands()
+                .map(|(i, driver)| {
+                    if let Some(value) = self.forced_pins.get(&(node.clone(), i)) {
+                        return Ok(splat(*value));
+                    }
+                    match driver {
+                        Some(d) => Ok(values[&d.clone().unwrap()][d.get_position()]),
+                        None => match inst_type.as_ref().and_then(|t| t.get_input_default(i)) {
+                            Some(value) => Ok(splat(value)),
+                            None => Err(format!("Input {i} of {node} is unconnected")),
+                        },
+                    }
+                })
+                .collect::<Result<Vec<u64>, String>>()?;
+            let mut outputs = match &inst_type {
+                None => operands,
+                Some(inst_type) => (self.eval)(inst_type, &operands)
+                    .ok_or_else(|| format!("Cannot evaluate {node} with two-state logic"))?,
+            };
+            let nouts = node.outputs().count();
+            if outputs.len() != nouts {
+                return Err(format!(
+                    "The evaluation of {node} returned {} outputs, expected {nouts}",
+                    outputs.len()
+                ));
+            }
+            for (pos, value) in outputs.iter_mut().enumerate() {
+                if let Some(forced) = self.forced.get(&(node.clone(), pos)) {
+                    *value = splat(*forced);
+                }
+            }
+            values.insert(node.clone(), outputs);
+        }
+
+        let outputs = self
+            .netlist
+            .outputs()
+            .into_iter()
+            .map(|(driver, _)| values[&driver.clone().unwrap()][driver.get_position()])
+            .collect();
+        self.values = values;
+        Ok(outputs)
+    }
+
+    /// Returns the values of `net` in the last step, if it has been simulated
+    pub fn get_value(&self, net: &DrivenNet<I>) -> Option<u64> {
+        self.values
+            .get(&net.clone().unwrap())
+            .map(|v| v[net.get_position()])
+    }
+
+    /// Forces `net` to `value` in every set of the following steps, as seen by all of its loads, like a stuck-at fault on the net
+    pub fn force(&mut self, net: &DrivenNet<I>, value: bool) {
+        self.forced
+            .insert((net.clone().unwrap(), net.get_position()), value);
+    }
+
+    /// Forces the input pin `pin` to `value` in every set of the following steps, as seen only by its instance
+    pub fn force_pin(&mut self, pin: &InputPort<I>, value: bool) {
+        self.forced_pins
+            .insert((pin.clone().unwrap(), pin.get_position()), value);
+    }
+
+    /// Releases every net and pin forced with [PackedSimulator::force] and [PackedSimulator::force_pin]
+    pub fn release(&mut self) {
+        self.forced.clear();
+        self.forced_pins.clear();
+    }
+}
+
+/// Returns `value` in all 64 sets of a packed value
+fn splat(value: bool) -> u64 {
+    if value { !0 } else { 0 }
+}
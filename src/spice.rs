@@ -0,0 +1,266 @@
+/*!
+
+  Emitting netlists as SPICE decks of subcircuit instances.
+
+*/
+
+use crate::{
+    circuit::{EscapePolicy, Identifier, Instantiable, Net},
+    netlist::{DrivenNet, Netlist},
+};
+use std::{collections::HashMap, io::Write};
+
+/// A pin of a [Subckt], in the order the subcircuit declares them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubcktPin {
+    /// A port of the cell, connected to the net on that port
+    Port(Identifier),
+    /// A global net, like a supply
+    Global(String),
+}
+
+/// The SPICE subcircuit a cell is instantiated as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subckt {
+    /// The name of the subcircuit
+    name: String,
+    /// The pins of the subcircuit, in order
+    pins: Vec<SubcktPin>,
+}
+
+impl Subckt {
+    /// Creates a subcircuit named `name` with no pins
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            pins: Vec::new(),
+        }
+    }
+
+    /// Creates a subcircuit with the name of `cell` and its inputs followed by its outputs as pins
+    pub fn from_cell<I: Instantiable>(cell: &I) -> Self {
+        let ports = cell
+            .get_input_ports()
+            .into_iter()
+            .chain(cell.get_output_ports())
+            .map(|p| SubcktPin::Port(p.get_identifier().clone()))
+            .collect();
+        Self {
+            name: spice_name(cell.get_name()),
+            pins: ports,
+        }
+    }
+
+    /// Appends the port `port` of the cell as the next pin
+    pub fn with_port(mut self, port: Identifier) -> Self {
+        self.pins.push(SubcktPin::Port(port));
+        self
+    }
+
+    /// Appends the global net `net` as the next pin
+    pub fn with_global(mut self, net: String) -> Self {
+        self.pins.push(SubcktPin::Global(net));
+        self
+    }
+
+    /// Returns the name of the subcircuit
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the pins of the subcircuit, in order
+    pub fn pins(&self) -> &[SubcktPin] {
+        &self.pins
+    }
+}
+
+/// The order of the ports of the top-level subcircuit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PortOrder {
+    /// The order the ports are declared in the netlist, bit by bit
+    #[default]
+    Declared,
+    /// Sorted by name
+    Alphabetical,
+    /// The given order, which must list every port bit exactly once
+    Custom(Vec<Identifier>),
+}
+
+/// Options for [write_spice]
+#[derive(Debug, Clone, Default)]
+pub struct SpiceOptions {
+    /// The order of the ports of the top-level subcircuit
+    port_order: PortOrder,
+    /// Global nets appended to the ports of the top-level subcircuit, like supplies
+    globals: Vec<String>,
+}
+
+impl SpiceOptions {
+    /// Creates the default options: ports in declared order and no global nets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the order of the ports of the top-level subcircuit
+    pub fn with_port_order(mut self, order: PortOrder) -> Self {
+        self.port_order = order;
+        self
+    }
+
+    /// Appends the global net `net` to the ports of the top-level subcircuit
+    pub fn with_global(mut self, net: String) -> Self {
+        self.globals.push(net);
+        self
+    }
+}
+
+/// The naming rules of SPICE, which has no escaped identifiers: names are written as they are,
+/// with whitespace and the separators `(`, `)`, `,` and `=` replaced by `_`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpiceEscaping;
+
+impl EscapePolicy for SpiceEscaping {
+    fn needs_escape(&self, name: &str) -> bool {
+        name.chars()
+            .any(|c| c.is_whitespace() || "(),=".contains(c))
+    }
+
+    fn escape(&self, name: &str) -> String {
+        name.chars()
+            .map(|c| match c.is_whitespace() || "(),=".contains(c) {
+                true => '_',
+                false => c,
+            })
+            .collect()
+    }
+}
+
+/// Returns the name of `id` in a SPICE deck
+fn spice_name(id: &Identifier) -> String {
+    id.emit_with(&SpiceEscaping)
+}
+
+/// Writes `netlist` as a SPICE subcircuit, with each instance mapped to a subcircuit by `cells`.
+/// Nets driving a top-level output take the name of the output.
+/// Other outputs driven by the same net, or driven directly by an input, are connected through 0V sources.
+pub fn write_spice<I, F>(
+    netlist: &Netlist<I>,
+    mut writer: impl Write,
+    cells: F,
+    options: &SpiceOptions,
+) -> Result<(), String>
+where
+    I: Instantiable,
+    F: Fn(&I) -> Result<Subckt, String>,
+{
+    let ports: Vec<Identifier> = netlist
+        .ports()
+        .iter()
+        .flat_map(|p| p.get_nets().iter().map(|n| n.get_identifier().clone()))
+        .collect();
+    let ports = match &options.port_order {
+        PortOrder::Declared => ports,
+        PortOrder::Alphabetical => {
+            let mut ports = ports;
+            ports.sort_by_key(spice_name);
+            ports
+        }
+        PortOrder::Custom(order) => {
+            let mut sorted = order.clone();
+            sorted.sort_by_key(spice_name);
+            let mut expected = ports;
+            expected.sort_by_key(spice_name);
+            if sorted != expected {
+                return Err("The port order must list every port bit exactly once".to_string());
+            }
+            order.clone()
+        }
+    };
+
+    // Alias objects are wires, so their nets are written as the nets they alias
+    let alias_of: HashMap<Net, DrivenNet<I>> = netlist
+        .aliases()
+        .filter_map(|a| Some((a.as_net().clone(), a.get_input(0).get_driver()?)))
+        .collect();
+    let source = |driver: DrivenNet<I>| {
+        let mut driver = driver;
+        loop {
+            let net = driver.as_net().clone();
+            match alias_of.get(&net) {
+                Some(d) => driver = d.clone(),
+                None => return driver,
+            }
+        }
+    };
+
+    // Name the nets driving outputs after the first output they drive
+    let mut names: HashMap<Net, String> = HashMap::new();
+    let mut aliases: Vec<(String, String)> = Vec::new();
+    for (driver, output) in netlist.outputs() {
+        let driver = source(driver);
+        let net = driver.as_net().clone();
+        let output = spice_name(output.get_identifier());
+        match names.get(&net) {
+            Some(name) => aliases.push((output, name.clone())),
+            None if driver.is_an_input() => {
+                aliases.push((output, spice_name(net.get_identifier())))
+            }
+            None => {
+                names.insert(net, output);
+            }
+        }
+    }
+    let name_of = |net: &Net| {
+        names
+            .get(net)
+            .cloned()
+            .unwrap_or_else(|| spice_name(net.get_identifier()))
+    };
+
+    let write = |writer: &mut dyn Write, line: String| {
+        writeln!(writer, "{line}").map_err(|e| e.to_string())
+    };
+    let top = netlist.get_name();
+    let header: Vec<String> = ports
+        .iter()
+        .map(spice_name)
+        .chain(options.globals.iter().cloned())
+        .collect();
+    write(&mut writer, format!("* {top}"))?;
+    write(&mut writer, format!(".SUBCKT {top} {}", header.join(" ")))?;
+    for node in netlist.objects() {
+        let (Some(inst_name), Some(inst_type)) =
+            (node.get_instance_name(), node.get_instance_type())
+        else {
+            continue;
+        };
+        let subckt = cells(&inst_type)?;
+        let mut line = format!("X{}", spice_name(&inst_name));
+        for pin in subckt.pins() {
+            let net = match pin {
+                SubcktPin::Global(net) => net.clone(),
+                SubcktPin::Port(port) => {
+                    if let Some(input) = node.find_input(port) {
+                        let driver = input
+                            .get_driver()
+                            .ok_or(format!("Input {port} of {inst_name} is unconnected"))?;
+                        name_of(&source(driver).as_net())
+                    } else if let Some(output) = node.find_output(port) {
+                        name_of(&output.as_net())
+                    } else {
+                        return Err(format!("{inst_name} has no port {port}"));
+                    }
+                }
+            };
+            line.push(' ');
+            line.push_str(&net);
+        }
+        line.push(' ');
+        line.push_str(subckt.get_name());
+        write(&mut writer, line)?;
+    }
+    for (output, net) in aliases {
+        write(&mut writer, format!("V{output} {output} {net} 0"))?;
+    }
+    write(&mut writer, format!(".ENDS {top}"))
+}
@@ -0,0 +1,42 @@
+/*!
+
+  The power and ground supplies that nets can carry.
+
+*/
+
+/// The kind of supply a power or ground net carries, as marked by [crate::netlist::NetRef::set_supply]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Supply {
+    /// A power net, like `VDD`
+    Power,
+    /// A ground net, like `VSS`
+    Ground,
+}
+
+impl Supply {
+    /// Returns the value the supply holds as a logic level
+    pub fn value(&self) -> bool {
+        matches!(self, Supply::Power)
+    }
+}
+
+impl std::fmt::Display for Supply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Supply::Power => write!(f, "power"),
+            Supply::Ground => write!(f, "ground"),
+        }
+    }
+}
+
+impl std::str::FromStr for Supply {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "power" => Ok(Supply::Power),
+            "ground" => Ok(Supply::Ground),
+            _ => Err(format!("Unknown supply {s}")),
+        }
+    }
+}
@@ -0,0 +1,791 @@
+/*!
+
+  Static timing analysis of the paths between inputs, registers and outputs, against the constraints of [crate::sdc].
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable, SequentialElement},
+    cost::CostModel,
+    graph::{CriticalPath, FanOutTable, topological_order},
+    library::CellLibrary,
+    netlist::{DrivenNet, InputPort, NetRef, Netlist},
+    sdc::Constraints,
+};
+use std::collections::{HashMap, HashSet};
+
+/// A model of the delays along a path: through instances and along the wires between them.
+/// Any `Fn(&I) -> f64` is a delay model that gives the delay through an instance type and no wire delay.
+/// A [WireLoad] adds interconnect estimates to a model with [WithWireLoad].
+/// [TimingAnalysis] and the `with_delays` constructors of [crate::graph::SimpleCombDepth] and [crate::graph::SequentialCombDepth] take a model.
+pub trait DelayModel<I: Instantiable> {
+    /// Returns the delay through an instance of `inst_type`, or from the clock to the output of a register
+    fn cell_delay(&self, inst_type: &I) -> f64;
+
+    /// Returns the delay along the wire from `driver` to the input `load`, where the net of `driver` has `fanout` loads.
+    /// **The default implementation gives every wire no delay.**
+    fn wire_delay(&self, _driver: &DrivenNet<I>, _load: &InputPort<I>, _fanout: usize) -> f64 {
+        0.0
+    }
+}
+
+impl<I, F> DelayModel<I> for F
+where
+    I: Instantiable,
+    F: Fn(&I) -> f64,
+{
+    fn cell_delay(&self, inst_type: &I) -> f64 {
+        self(inst_type)
+    }
+}
+
+/// A library gives the delays of its cells, and wire delays from its [WireLoad] if it has one
+impl<I> DelayModel<I> for CellLibrary<I>
+where
+    I: Instantiable,
+{
+    fn cell_delay(&self, inst_type: &I) -> f64 {
+        self.delay(inst_type)
+    }
+
+    fn wire_delay(&self, _driver: &DrivenNet<I>, _load: &InputPort<I>, fanout: usize) -> f64 {
+        self.get_wire_load().map_or(0.0, |w| w.delay(fanout))
+    }
+}
+
+/// A wire-load model, which estimates the delay of a net from its fanout before placement, like the wire-load tables of a Liberty library.
+/// The delay of a net with `n` loads is the `n`th entry of the table, extrapolated by the slope past the end of the table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WireLoad {
+    /// The delay of a net with one load, two loads, and so on
+    table: Vec<f64>,
+    /// The delay added by every load past the end of the table
+    slope: f64,
+}
+
+impl WireLoad {
+    /// Creates a model where every load adds `slope` to the delay of a net
+    pub fn new(slope: f64) -> Self {
+        Self {
+            table: Vec::new(),
+            slope,
+        }
+    }
+
+    /// Sets the delays of nets with one load, two loads, and so on, with the slope used past the last entry
+    pub fn with_table(mut self, table: Vec<f64>) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Returns the delay of a net with `fanout` loads
+    pub fn delay(&self, fanout: usize) -> f64 {
+        match fanout {
+            0 => 0.0,
+            n if n <= self.table.len() => self.table[n - 1],
+            n => {
+                self.table.last().cloned().unwrap_or(0.0)
+                    + self.slope * (n - self.table.len()) as f64
+            }
+        }
+    }
+}
+
+/// A delay model with wire delays estimated by a [WireLoad], and cell delays from another model
+#[derive(Debug, Clone)]
+pub struct WithWireLoad<M> {
+    /// The model of the delays through instances
+    cells: M,
+    /// The model of the delays along nets
+    wire_load: WireLoad,
+}
+
+impl<M> WithWireLoad<M> {
+    /// Estimates the wire delays of `cells` with `wire_load`
+    pub fn new(cells: M, wire_load: WireLoad) -> Self {
+        Self { cells, wire_load }
+    }
+}
+
+impl<I, M> DelayModel<I> for WithWireLoad<M>
+where
+    I: Instantiable,
+    M: DelayModel<I>,
+{
+    fn cell_delay(&self, inst_type: &I) -> f64 {
+        self.cells.cell_delay(inst_type)
+    }
+
+    fn wire_delay(&self, _driver: &DrivenNet<I>, _load: &InputPort<I>, fanout: usize) -> f64 {
+        self.wire_load.delay(fanout)
+    }
+}
+
+/// The wire resistance and capacitance of a net, lumped into a single segment between the driver and its loads
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parasitics {
+    /// The resistance of the wire
+    pub resistance: f64,
+    /// The capacitance of the wire, not counting the input pins of the loads
+    pub capacitance: f64,
+}
+
+impl Parasitics {
+    /// Creates the parasitics of a wire with resistance `resistance` and capacitance `capacitance`
+    pub fn new(resistance: f64, capacitance: f64) -> Self {
+        Self {
+            resistance,
+            capacitance,
+        }
+    }
+}
+
+impl std::fmt::Display for Parasitics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.resistance, self.capacitance)
+    }
+}
+
+impl std::str::FromStr for Parasitics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s
+            .split_whitespace()
+            .map(|v| v.parse().map_err(|_| format!("Expected a number, got {v}")))
+            .collect::<Result<_, _>>()?;
+        match values.as_slice() {
+            [r, c] => Ok(Self::new(*r, *c)),
+            _ => Err(format!("Expected a resistance and a capacitance, got {s}")),
+        }
+    }
+}
+
+/// Returns the Elmore delay through a wire with `parasitics`, from a driver with output resistance `drive_resistance`
+/// to loads with `load_capacitance` in total on the far end of the wire.
+/// The wire is a single pi segment, so the driver charges every capacitance and the wire resistance half of its own.
+pub fn elmore_delay(parasitics: Parasitics, drive_resistance: f64, load_capacitance: f64) -> f64 {
+    let Parasitics {
+        resistance,
+        capacitance,
+    } = parasitics;
+    drive_resistance * (capacitance + load_capacitance)
+        + resistance * (capacitance / 2.0 + load_capacitance)
+}
+
+/// Annotates the nets of `netlist` with the parasitics in `src`, and returns the number of nets annotated.
+/// Every line names a net and gives its wire resistance and capacitance, like `n42 12.5 0.003`. Text after `//` is a comment.
+/// Returns an error naming the line of a net that is not in the netlist or a value that is not a number.
+pub fn read_parasitics<I: Instantiable>(netlist: &Netlist<I>, src: &str) -> Result<usize, String> {
+    let nets: HashMap<String, DrivenNet<I>> = netlist
+        .objects()
+        .flat_map(|n| n.outputs().collect::<Vec<_>>())
+        .map(|net| (net.get_identifier().to_string().trim().to_string(), net))
+        .collect();
+    let mut annotated = 0;
+    for (n, line) in src.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let Some((name, values)) = line.split_once(char::is_whitespace) else {
+            if line.is_empty() {
+                continue;
+            }
+            return Err(format!("Line {}: expected a net and its parasitics", n + 1));
+        };
+        let net = nets
+            .get(name)
+            .ok_or_else(|| format!("Line {}: net {name} is not in the netlist", n + 1))?;
+        let parasitics = values.parse().map_err(|e| format!("Line {}: {e}", n + 1))?;
+        net.set_parasitics(Some(parasitics));
+        annotated += 1;
+    }
+    Ok(annotated)
+}
+
+/// A delay model with wire delays from the [Parasitics] annotated on nets, by [elmore_delay], and cell delays from another model.
+/// Every load pin has the same capacitance, and each cell type drives its outputs through a resistance given by a function.
+/// Nets without parasitics get the wire delays of the other model, and principal inputs are driven ideally.
+#[derive(Debug, Clone)]
+pub struct ElmoreDelay<M, R> {
+    /// The model of the delays through instances
+    cells: M,
+    /// The output resistance of a cell type
+    drive_resistance: R,
+    /// The capacitance of an input pin
+    pin_capacitance: f64,
+}
+
+impl<M, R> ElmoreDelay<M, R> {
+    /// Computes the wire delays of `cells` from the parasitics of nets, driven through `drive_resistance` into pins of `pin_capacitance`
+    pub fn new(cells: M, drive_resistance: R, pin_capacitance: f64) -> Self {
+        Self {
+            cells,
+            drive_resistance,
+            pin_capacitance,
+        }
+    }
+}
+
+impl<I, M, R> DelayModel<I> for ElmoreDelay<M, R>
+where
+    I: Instantiable,
+    M: DelayModel<I>,
+    R: Fn(&I) -> f64,
+{
+    fn cell_delay(&self, inst_type: &I) -> f64 {
+        self.cells.cell_delay(inst_type)
+    }
+
+    fn wire_delay(&self, driver: &DrivenNet<I>, load: &InputPort<I>, fanout: usize) -> f64 {
+        let Some(parasitics) = driver.get_parasitics() else {
+            return self.cells.wire_delay(driver, load, fanout);
+        };
+        let drive_resistance = driver
+            .clone()
+            .unwrap()
+            .get_instance_type()
+            .map_or(0.0, |t| (self.drive_resistance)(&t));
+        elmore_delay(
+            parasitics,
+            drive_resistance,
+            self.pin_capacitance * fanout as f64,
+        )
+    }
+}
+
+/// A point where timing paths end: a top-level output or an input of a register
+#[derive(Debug, Clone)]
+pub struct TimingEndpoint<I: Instantiable> {
+    /// The name of the output, or the name of the register
+    name: Identifier,
+    /// The net arriving at the endpoint
+    driver: DrivenNet<I>,
+    /// The register input, or [None] for a top-level output
+    load: Option<InputPort<I>>,
+    /// The latest time the signal arrives
+    arrival: f64,
+    /// The latest time the signal may arrive, or [None] if no clock constrains it
+    required: Option<f64>,
+    /// How long the latch stays transparent, for the data input of a latch
+    transparent: Option<f64>,
+    /// The time borrowed from the next cycle at the data input of a latch, while the latch is transparent
+    borrow: f64,
+}
+
+impl<I> TimingEndpoint<I>
+where
+    I: Instantiable,
+{
+    /// Returns the name of the output, or the name of the register
+    pub fn get_name(&self) -> &Identifier {
+        &self.name
+    }
+
+    /// Returns the net arriving at the endpoint
+    pub fn get_driver(&self) -> &DrivenNet<I> {
+        &self.driver
+    }
+
+    /// Returns the register input, or [None] for a top-level output
+    pub fn get_load(&self) -> Option<&InputPort<I>> {
+        self.load.as_ref()
+    }
+
+    /// Returns the latest time the signal arrives
+    pub fn arrival(&self) -> f64 {
+        self.arrival
+    }
+
+    /// Returns the latest time the signal may arrive, or [None] if no clock constrains it
+    pub fn required(&self) -> Option<f64> {
+        self.required
+    }
+
+    /// Returns the time borrowed from the next cycle at the data input of a latch, which is zero for other endpoints.
+    /// The required time includes the borrowed time, so borrowing is not a violation.
+    pub fn borrowed(&self) -> f64 {
+        self.borrow
+    }
+
+    /// Returns the required time less the arrival time, which is negative for a violation
+    pub fn slack(&self) -> Option<f64> {
+        self.required.map(|r| r - self.arrival)
+    }
+}
+
+/// Returns `true` if timing paths start at `node`: an input, a register, or an instance with no connected inputs
+fn is_startpoint<I: SequentialElement>(node: &NetRef<I>) -> bool {
+    node.is_an_input()
+        || node.is_register()
+        || (!node.is_an_alias() && node.inputs().all(|p| p.get_driver().is_none()))
+}
+
+/// Returns the name of a startpoint in constraints: the net of an input, or the name of an instance
+fn startpoint_name<I: Instantiable>(node: &NetRef<I>) -> Identifier {
+    node.get_instance_name()
+        .unwrap_or_else(|| node.get_identifier())
+}
+
+/// A delay model with the fanout of every net, which wire delays depend on
+pub(crate) struct Model<'m, I: Instantiable> {
+    /// The delay model
+    delays: &'m dyn DelayModel<I>,
+    /// The loads of every net
+    fanout: FanOutTable<'m, I>,
+}
+
+impl<'m, I> Model<'m, I>
+where
+    I: Instantiable,
+{
+    /// Indexes the fanout of `netlist` for the wire delays of `delays`
+    pub(crate) fn new(netlist: &'m Netlist<I>, delays: &'m dyn DelayModel<I>) -> Self {
+        Self {
+            delays,
+            fanout: FanOutTable::index(netlist),
+        }
+    }
+
+    /// Returns the delay through an instance of `inst_type`
+    pub(crate) fn cell_delay(&self, inst_type: &I) -> f64 {
+        self.delays.cell_delay(inst_type)
+    }
+
+    /// Returns the delay along the wire from `driver` to `load`
+    pub(crate) fn wire_delay(&self, driver: &DrivenNet<I>, load: &InputPort<I>) -> f64 {
+        let fanout = self.fanout.get_net_fan_out(&driver.as_net());
+        self.delays.wire_delay(driver, load, fanout)
+    }
+}
+
+/// The latest arrival time of every node, with the position of the input the latest signal arrives through, as computed by [propagate]
+type Arrivals<I> = HashMap<NetRef<I>, Option<(f64, Option<usize>)>>;
+
+/// Computes the latest arrival time at the outputs of the nodes that `roots` depend on.
+/// Latches in `borrowed` launch after the time borrowed at their data inputs.
+/// Paths from the startpoints that `blocked` accepts are left out, so nodes only reached from them arrive at [None].
+fn propagate<I: SequentialElement>(
+    roots: impl IntoIterator<Item = NetRef<I>>,
+    constraints: &Constraints,
+    model: &Model<'_, I>,
+    borrowed: &HashMap<NetRef<I>, f64>,
+    blocked: &dyn Fn(&NetRef<I>) -> bool,
+) -> Result<Arrivals<I>, String> {
+    let order = topological_order(roots, |node| {
+        if is_startpoint(node) {
+            return Vec::new();
+        }
+        node.drivers().flatten().collect()
+    })
+    .map_err(|node| format!("Combinational cycle detected through {node}"))?;
+    let mut arrivals: Arrivals<I> = HashMap::new();
+    for node in order {
+        let arrival = if is_startpoint(&node) {
+            match node.get_instance_type() {
+                _ if blocked(&node) => None,
+                Some(inst_type) => Some((
+                    borrowed.get(&node).unwrap_or(&0.0) + model.cell_delay(&inst_type),
+                    None,
+                )),
+                None => Some((
+                    constraints
+                        .input_delay(&node.get_identifier())
+                        .map_or(0.0, |d| d.delay()),
+                    None,
+                )),
+            }
+        } else {
+            let mut latest: Option<(f64, Option<usize>)> = None;
+            for input in node.inputs() {
+                let Some(driver) = input.get_driver() else {
+                    continue;
+                };
+                if let Some((arrival, _)) = arrivals[&driver.clone().unwrap()] {
+                    let arrival = arrival + model.wire_delay(&driver, &input);
+                    if latest.is_none_or(|(l, _)| arrival > l) {
+                        latest = Some((arrival, Some(input.get_position())));
+                    }
+                }
+            }
+            let delay = node
+                .get_instance_type()
+                .map_or(0.0, |t| model.cell_delay(&t));
+            latest.map(|(l, from)| (l + delay, from))
+        };
+        arrivals.insert(node, arrival);
+    }
+    Ok(arrivals)
+}
+
+/// The endpoints that paths reach, with the arrival times without false paths for those that have some, by their position among them
+type TimedEndpoints<I> = (Vec<TimingEndpoint<I>>, HashMap<usize, Arrivals<I>>);
+
+/// Times `endpoints` with the arrivals from [propagate], leaving out those no path reaches
+fn time_endpoints<I: SequentialElement>(
+    endpoints: &[TimingEndpoint<I>],
+    arrivals: &Arrivals<I>,
+    constraints: &Constraints,
+    model: &Model<'_, I>,
+    borrowed: &HashMap<NetRef<I>, f64>,
+) -> Result<TimedEndpoints<I>, String> {
+    let startpoints: Vec<NetRef<I>> = arrivals
+        .keys()
+        .filter(|n| is_startpoint(n))
+        .cloned()
+        .collect();
+    let mut timed = Vec::new();
+    let mut false_arrivals = HashMap::new();
+    for endpoint in endpoints {
+        let node = endpoint.driver.clone().unwrap();
+        let false_from: HashSet<NetRef<I>> = startpoints
+            .iter()
+            .filter(|s| constraints.is_false_path(&startpoint_name(s), &endpoint.name))
+            .cloned()
+            .collect();
+        let arrival = match false_from.is_empty() {
+            true => arrivals[&node],
+            false => {
+                let restricted = propagate([node.clone()], constraints, model, borrowed, &|s| {
+                    false_from.contains(s)
+                })?;
+                let arrival = restricted[&node];
+                false_arrivals.insert(timed.len(), restricted);
+                arrival
+            }
+        };
+        let Some((arrival, _)) = arrival else {
+            continue;
+        };
+        let wire = endpoint
+            .load
+            .as_ref()
+            .map_or(0.0, |l| model.wire_delay(&endpoint.driver, l));
+        let mut endpoint = endpoint.clone();
+        endpoint.arrival = arrival + wire;
+        timed.push(endpoint);
+    }
+    Ok((timed, false_arrivals))
+}
+
+/// Static timing analysis of a netlist under SDC [Constraints]. Clocks are ideal, so registers launch at time zero.
+///
+/// Paths start at inputs, after their input delay, and at registers, after the clock-to-output delay of the register.
+/// Paths end at top-level outputs, which must arrive a period less their output delay after the clock edge,
+/// and at the data and enable inputs of registers, which must arrive within a period of the clock of the register.
+/// The clock of an output delay is the one it names. The clock of a register is the one defined on the input driving its clock.
+/// Otherwise the first clock created is used, and an endpoint is unconstrained when there are no clocks.
+/// Endpoints reached only through false paths are left out.
+///
+/// Latches are transparent while their gate is high, so a signal arriving at a latch after the period borrows time from the next cycle,
+/// up to the time the clock is high, and the paths from the latch launch that much later.
+/// Borrowing is not a violation. Only signals arriving after the latch closes are.
+pub struct TimingAnalysis<'a, I: Instantiable> {
+    // A reference to the underlying netlist
+    netlist: &'a Netlist<I>,
+    // The latest arrival time at the output of every node
+    arrivals: Arrivals<I>,
+    // The endpoints in the order of the outputs and then the registers
+    endpoints: Vec<TimingEndpoint<I>>,
+    // The arrival times without false paths, for the endpoints that have some
+    false_arrivals: HashMap<usize, Arrivals<I>>,
+}
+
+impl<'a, I> TimingAnalysis<'a, I>
+where
+    I: SequentialElement,
+{
+    /// Analyzes the timing of `netlist` under `constraints`, with delays from `model`.
+    /// Returns an error if the netlist has a combinational cycle.
+    pub fn new(
+        netlist: &'a Netlist<I>,
+        constraints: &Constraints,
+        model: &dyn DelayModel<I>,
+    ) -> Result<Self, String> {
+        let model = &Model::new(netlist, model);
+        let default_clock = constraints.clocks().first();
+        let default_period = default_clock.map(|c| c.period());
+
+        // The endpoints with their required times, to be timed once they are all found
+        let mut endpoints: Vec<TimingEndpoint<I>> = Vec::new();
+        for (driver, net) in netlist.outputs() {
+            let name = net.get_identifier().clone();
+            let required = match constraints.output_delay(&name) {
+                Some(d) => d
+                    .clock()
+                    .and_then(|c| constraints.get_clock(c))
+                    .map(|c| c.period())
+                    .or(default_period)
+                    .map(|p| p - d.delay()),
+                None => default_period,
+            };
+            endpoints.push(TimingEndpoint {
+                name,
+                driver,
+                load: None,
+                arrival: 0.0,
+                required,
+                transparent: None,
+                borrow: 0.0,
+            });
+        }
+        for node in netlist.objects().filter(|n| n.is_register()) {
+            let name = startpoint_name(&node);
+            let inst_type = node.get_instance_type().unwrap().clone();
+            let mut clock = node.get_clock().map(|c| c.unwrap());
+            while let Some(alias) = clock.clone().filter(|c| c.is_an_alias()) {
+                clock = alias.get_driver(0);
+            }
+            let clock = clock
+                .filter(|c| c.is_an_input())
+                .and_then(|c| constraints.clock_of_port(&c.get_identifier()))
+                .or(default_clock);
+            for input in node.inputs() {
+                let index = input.get_position();
+                if [inst_type.get_clock_input(), inst_type.get_reset_input()].contains(&Some(index))
+                {
+                    continue;
+                }
+                let transparent = clock
+                    .filter(|_| inst_type.is_latch() && inst_type.get_data_input() == Some(index))
+                    .map(|c| c.high_time());
+                if let Some(driver) = input.get_driver() {
+                    endpoints.push(TimingEndpoint {
+                        name: name.clone(),
+                        driver,
+                        load: Some(input),
+                        arrival: 0.0,
+                        required: clock.map(|c| c.period()),
+                        transparent,
+                        borrow: 0.0,
+                    });
+                }
+            }
+        }
+
+        // Each pass times the latches from the time they borrowed in the last one, until no borrow changes.
+        // A chain of latches settles within a pass per latch, and loops through latches are cut off there.
+        let latches = endpoints.iter().filter(|e| e.transparent.is_some()).count();
+        let mut borrowed: HashMap<NetRef<I>, f64> = HashMap::new();
+        let mut pass = 0;
+        let (arrivals, timed, false_arrivals) = loop {
+            let arrivals = propagate(netlist.objects(), constraints, model, &borrowed, &|_| false)?;
+            let (mut timed, false_arrivals) =
+                time_endpoints(&endpoints, &arrivals, constraints, model, &borrowed)?;
+            let mut borrows = HashMap::new();
+            for endpoint in timed.iter_mut() {
+                if let Some(high) = endpoint.transparent
+                    && let Some(required) = endpoint.required
+                {
+                    endpoint.borrow = (endpoint.arrival - required).clamp(0.0, high);
+                    endpoint.required = Some(required + endpoint.borrow);
+                    let latch = endpoint.load.as_ref().unwrap().clone().unwrap();
+                    borrows.insert(latch, endpoint.borrow);
+                }
+            }
+            pass += 1;
+            if borrows == borrowed || pass > latches {
+                break (arrivals, timed, false_arrivals);
+            }
+            borrowed = borrows;
+        };
+
+        Ok(Self {
+            netlist,
+            arrivals,
+            endpoints: timed,
+            false_arrivals,
+        })
+    }
+
+    /// Returns the latest time the outputs of `node` arrive, or [None] if no path reaches it
+    pub fn arrival(&self, node: &NetRef<I>) -> Option<f64> {
+        self.arrivals.get(node).cloned().flatten().map(|(t, _)| t)
+    }
+
+    /// Returns the latest arrival time at the outputs of every node a path reaches, like to store them in an [crate::persist::AnalysisCache]
+    pub fn arrivals(&self) -> impl Iterator<Item = (NetRef<I>, f64)> {
+        self.arrivals
+            .iter()
+            .filter_map(|(n, a)| a.map(|(t, _)| (n.clone(), t)))
+    }
+
+    /// Returns the latest path to the `index`th endpoint, from the startpoint it is launched at, or [None] if there is no such endpoint.
+    /// A path to a register ends at the register, through the input it is timed at. False paths are not taken.
+    pub fn path_to(&self, index: usize) -> Option<CriticalPath<I>> {
+        let endpoint = self.endpoints.get(index)?;
+        let arrivals = self.false_arrivals.get(&index).unwrap_or(&self.arrivals);
+        let path = CriticalPath::trace(endpoint.driver.clone().unwrap(), |n| {
+            let (_, from) = arrivals.get(n).cloned().flatten()?;
+            Some(n.get_input(from?))
+        });
+        Some(match &endpoint.load {
+            Some(load) => path.with_load(load.clone()),
+            None => path,
+        })
+    }
+
+    /// Returns the path to the endpoint with the worst slack, or the latest arrival if no endpoint is constrained, taking the first on ties
+    pub fn critical_path(&self) -> Option<CriticalPath<I>> {
+        let constrained = self.worst_slack().is_some();
+        let key = |e: &TimingEndpoint<I>| match constrained {
+            true => e.slack(),
+            false => Some(-e.arrival),
+        };
+        let mut worst: Option<(f64, usize)> = None;
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            if let Some(k) = key(endpoint)
+                && worst.is_none_or(|(w, _)| k < w)
+            {
+                worst = Some((k, i));
+            }
+        }
+        let (_, worst) = worst?;
+        self.path_to(worst)
+    }
+
+    /// Returns the names of the instances on the worst paths to the endpoints with negative slack,
+    /// along with the instances within `neighborhood` connections of those paths, in netlist order.
+    /// Registers are left out of the region and not looked through.
+    /// This is the region to fix timing in locally with [Netlist::rewrite_region].
+    pub fn critical_region(&self, neighborhood: usize) -> Vec<Identifier> {
+        let is_logic = |n: &NetRef<I>| n.get_instance_type().is_some_and(|t| !t.is_register());
+        let mut region: HashSet<NetRef<I>> = HashSet::new();
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            if endpoint.slack().is_some_and(|s| s < 0.0)
+                && let Some(path) = self.path_to(i)
+            {
+                region.extend(path.nodes().iter().filter(|n| is_logic(n)).cloned());
+            }
+        }
+
+        let users = FanOutTable::index(self.netlist);
+        let mut frontier: Vec<NetRef<I>> = region.iter().cloned().collect();
+        for _ in 0..neighborhood {
+            let mut next = Vec::new();
+            for node in &frontier {
+                let near = node.drivers().flatten().chain(users.get_node_users(node));
+                for n in near {
+                    if is_logic(&n) && region.insert(n.clone()) {
+                        next.push(n);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        self.netlist
+            .objects()
+            .filter(|n| region.contains(n))
+            .filter_map(|n| n.get_instance_name())
+            .collect()
+    }
+
+    /// Returns the endpoints, the top-level outputs in port order and then the register inputs
+    pub fn endpoints(&self) -> &[TimingEndpoint<I>] {
+        &self.endpoints
+    }
+
+    /// Returns the smallest slack of the constrained endpoints, or [None] if none are constrained
+    pub fn worst_slack(&self) -> Option<f64> {
+        self.endpoints
+            .iter()
+            .filter_map(|e| e.slack())
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Returns the sum of the negative slacks of the endpoints, which is zero when timing is met
+    pub fn total_negative_slack(&self) -> f64 {
+        self.endpoints
+            .iter()
+            .filter_map(|e| e.slack())
+            .filter(|s| *s < 0.0)
+            .sum()
+    }
+}
+
+/// Static timing analysis of a netlist at several delay corners, like the slow, typical and fast libraries of a process.
+/// Every corner is a [TimingAnalysis] under the same constraints, so the corners have the same endpoints in the same order.
+pub struct MultiCornerAnalysis<'a, I: Instantiable> {
+    // The analysis of every corner with its name, in the order the corners were given
+    corners: Vec<(String, TimingAnalysis<'a, I>)>,
+}
+
+impl<'a, I> MultiCornerAnalysis<'a, I>
+where
+    I: SequentialElement,
+{
+    /// Analyzes the timing of `netlist` under `constraints` at every corner, given by its name and delay model.
+    /// Returns an error if two corners share a name or the netlist has a combinational cycle.
+    pub fn new(
+        netlist: &'a Netlist<I>,
+        constraints: &Constraints,
+        corners: &[(&str, &dyn DelayModel<I>)],
+    ) -> Result<Self, String> {
+        let mut analyses: Vec<(String, TimingAnalysis<'a, I>)> = Vec::new();
+        for (name, model) in corners {
+            if analyses.iter().any(|(n, _)| n == name) {
+                return Err(format!("Corner {name} is given twice"));
+            }
+            let analysis = TimingAnalysis::new(netlist, constraints, *model)?;
+            analyses.push((name.to_string(), analysis));
+        }
+        Ok(Self { corners: analyses })
+    }
+
+    /// Returns the analysis of the corner `name`
+    pub fn corner(&self, name: &str) -> Option<&TimingAnalysis<'a, I>> {
+        self.corners
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, analysis)| analysis)
+    }
+
+    /// Returns the corners by name with their analyses, in the order they were given
+    pub fn corners(&self) -> impl Iterator<Item = (&str, &TimingAnalysis<'a, I>)> {
+        self.corners
+            .iter()
+            .map(|(name, analysis)| (name.as_str(), analysis))
+    }
+
+    /// Returns the smallest of `value` over the corners it is defined at, with the corner, taking the first corner on ties
+    fn worst_of(
+        &self,
+        value: impl Fn(&TimingAnalysis<'a, I>) -> Option<f64>,
+    ) -> Option<(&str, f64)> {
+        let mut worst: Option<(&str, f64)> = None;
+        for (name, analysis) in self.corners() {
+            if let Some(v) = value(analysis)
+                && worst.is_none_or(|(_, w)| v < w)
+            {
+                worst = Some((name, v));
+            }
+        }
+        worst
+    }
+
+    /// Returns the smallest slack of the constrained endpoints at any corner, with the corner it is at,
+    /// or [None] if no endpoint is constrained
+    pub fn worst_slack(&self) -> Option<(&str, f64)> {
+        self.worst_of(|a| a.worst_slack())
+    }
+
+    /// Returns the smallest slack of the `index`th endpoint across the corners, with the corner it is at,
+    /// or [None] if there is no such endpoint or it is not constrained
+    pub fn endpoint_slack(&self, index: usize) -> Option<(&str, f64)> {
+        self.worst_of(|a| a.endpoints().get(index)?.slack())
+    }
+
+    /// Returns the smallest total negative slack of the corners, with the corner it is at, or [None] if there are no corners
+    pub fn total_negative_slack(&self) -> Option<(&str, f64)> {
+        self.worst_of(|a| Some(a.total_negative_slack()))
+    }
+
+    /// Returns the critical path at the corner with the worst slack, or at the first corner if no endpoint is constrained
+    pub fn critical_path(&self) -> Option<CriticalPath<I>> {
+        let corner = match self.worst_slack() {
+            Some((name, _)) => self.corner(name)?,
+            None => &self.corners.first()?.1,
+        };
+        corner.critical_path()
+    }
+}
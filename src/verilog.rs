@@ -0,0 +1,716 @@
+/*!
+
+  Reading back structural Verilog, like the modules written by the [std::fmt::Display] of a netlist.
+
+*/
+
+use crate::{
+    attribute::Parameter,
+    circuit::{Identifier, Instantiable, Net, PortDirection, VerilogEscaping},
+    netlist::{DrivenNet, Netlist},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    rc::Rc,
+};
+
+/// An instance read from a Verilog module, to be mapped to an [Instantiable] type
+#[derive(Debug, Clone)]
+pub struct InstanceSpec {
+    /// The name of the instance
+    pub name: Identifier,
+    /// The type of the instance, like a library cell name
+    pub cell_type: Identifier,
+    /// The parameters set on the instance, inline or with `defparam`
+    pub parameters: Vec<(Identifier, Parameter)>,
+    /// The names of the connected ports, in the order they are listed
+    pub ports: Vec<Identifier>,
+}
+
+/// A token of structural Verilog
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An identifier or keyword, with escaped identifiers keeping their leading `\`
+    Ident(String),
+    /// A number or sized literal, like `42` or `4'b1010`
+    Literal(String),
+    /// A string, without its quotes
+    Str(String),
+    /// The start of an attribute, `(*`
+    AttrOpen,
+    /// The end of an attribute, `*)`
+    AttrClose,
+    /// Any other character
+    Symbol(char),
+}
+
+/// The value connected to a port or parameter
+#[derive(Debug, Clone)]
+enum Value {
+    /// A net, or a bit of a vector
+    Net(Identifier),
+    /// A literal
+    Literal(String),
+}
+
+/// Splits `src` into tokens, skipping whitespace and comments
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let take = |i: &mut usize, f: &dyn Fn(char) -> bool| {
+        let start = *i;
+        while *i < chars.len() && f(chars[*i]) {
+            *i += 1;
+        }
+        chars[start..*i].iter().collect::<String>()
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '/' if next == Some('/') => {
+                take(&mut i, &|c| c != '\n');
+            }
+            '/' if next == Some('*') => {
+                let end = src_find(&chars, i + 2, "*/").ok_or("Unterminated comment")?;
+                i = end + 2;
+            }
+            '(' if next == Some('*') => {
+                tokens.push(Token::AttrOpen);
+                i += 2;
+            }
+            '*' if next == Some(')') => {
+                tokens.push(Token::AttrClose);
+                i += 2;
+            }
+            '"' => {
+                i += 1;
+                let s = take(&mut i, &|c| c != '"');
+                if i == chars.len() {
+                    return Err("Unterminated string".to_string());
+                }
+                i += 1;
+                tokens.push(Token::Str(s));
+            }
+            '\\' => {
+                i += 1;
+                let s = take(&mut i, &|c| !c.is_whitespace());
+                tokens.push(Token::Ident(format!("\\{s}")));
+            }
+            c if c.is_ascii_digit() || c == '\'' => {
+                let s = take(&mut i, &|c| c.is_ascii_alphanumeric() || "'_.".contains(c));
+                tokens.push(Token::Literal(s));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == '$' => {
+                let s = take(&mut i, &|c| {
+                    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+                });
+                tokens.push(Token::Ident(s));
+            }
+            c => {
+                tokens.push(Token::Symbol(c));
+                i += 1;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the name of an identifier as it is read. Escaped names keep their backslash,
+/// except for escaped keywords like `\wire `, which are plain names that are only escaped when they are written.
+fn unescape_keyword(name: String) -> String {
+    match name.strip_prefix('\\') {
+        Some(root) if VerilogEscaping::is_keyword(root) => root.to_string(),
+        _ => name,
+    }
+}
+
+/// Returns the identifier of an instance name as it is read. Instances of a netlist are never escaped,
+/// so escaped names are those written escaped, like the bits of an array `\u_fa[3] `.
+fn instance_name(name: String) -> Identifier {
+    match name.strip_prefix('\\') {
+        Some(root) => Identifier::new(root.to_string()),
+        None => Identifier::new(name),
+    }
+}
+
+/// Returns the position of `pat` in `chars` at or after `from`
+fn src_find(chars: &[char], from: usize, pat: &str) -> Option<usize> {
+    let pat: Vec<char> = pat.chars().collect();
+    (from..chars.len()).find(|i| chars[*i..].starts_with(&pat))
+}
+
+/// A port declaration of a module
+struct PortDecl {
+    /// The name of the port
+    name: String,
+    /// The direction of the port
+    direction: PortDirection,
+    /// The range of a vector port, msb first
+    range: Option<(usize, usize)>,
+    /// The attributes on the port
+    attributes: Vec<(String, Option<String>)>,
+}
+
+impl PortDecl {
+    /// Returns the identifiers of the bits of the port, lsb first
+    fn bits(&self) -> Vec<Identifier> {
+        match self.range {
+            None => vec![Identifier::new(self.name.clone())],
+            Some((msb, lsb)) => (lsb.min(msb)..=lsb.max(msb))
+                .map(|i| Identifier::new(format!("{}[{i}]", self.name)))
+                .collect(),
+        }
+    }
+}
+
+/// An instance of a module
+struct InstDecl {
+    /// The instance as passed to the cell mapping
+    spec: InstanceSpec,
+    /// The connections of the instance, as port and net names
+    connections: Vec<(Identifier, Identifier)>,
+    /// The attributes on the instance
+    attributes: Vec<(String, Option<String>)>,
+}
+
+/// A module read from structural Verilog
+struct Module {
+    /// The name of the module
+    name: String,
+    /// The port declarations, in order
+    ports: Vec<PortDecl>,
+    /// The instances, in order
+    instances: Vec<InstDecl>,
+    /// The `assign` statements, in order
+    assigns: Vec<(Identifier, Identifier)>,
+}
+
+/// A recursive descent parser for a structural Verilog module
+struct Parser {
+    /// The tokens of the source
+    tokens: Vec<Token>,
+    /// The position of the next token
+    pos: usize,
+}
+
+impl Parser {
+    /// Returns the next token without consuming it
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Consumes the next token
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("Unexpected end of file")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is the symbol `c`
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.peek() == Some(&Token::Symbol(c));
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    /// Consumes the symbol `c`, or returns an error
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.next()? {
+            Token::Symbol(s) if s == c => Ok(()),
+            t => Err(format!("Expected '{c}', got {t:?}")),
+        }
+    }
+
+    /// Consumes an identifier, or returns an error
+    fn ident(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Ident(s) => Ok(unescape_keyword(s)),
+            t => Err(format!("Expected an identifier, got {t:?}")),
+        }
+    }
+
+    /// Consumes a number, or returns an error
+    fn number(&mut self) -> Result<usize, String> {
+        match self.next()? {
+            Token::Literal(s) => s.parse().map_err(|_| format!("Expected a number, got {s}")),
+            t => Err(format!("Expected a number, got {t:?}")),
+        }
+    }
+
+    /// Consumes a net name, which may be a bit of a vector like `a[3]`
+    fn net(&mut self) -> Result<Identifier, String> {
+        let name = self.ident()?;
+        if self.eat('[') {
+            let index = self.number()?;
+            self.expect(']')?;
+            return Ok(Identifier::new(format!("{name}[{index}]")));
+        }
+        Ok(Identifier::new(name))
+    }
+
+    /// Consumes an optional range, like `[3:0]`
+    fn range(&mut self) -> Result<Option<(usize, usize)>, String> {
+        if !self.eat('[') {
+            return Ok(None);
+        }
+        let msb = self.number()?;
+        self.expect(':')?;
+        let lsb = self.number()?;
+        self.expect(']')?;
+        Ok(Some((msb, lsb)))
+    }
+
+    /// Consumes the rest of an attribute after `(*`
+    fn attribute(&mut self) -> Result<(String, Option<String>), String> {
+        // An escaped key, like the namespaced `\\xilinx.LOC `, is read without its escape
+        let mut key = self.ident()?;
+        if let Some(unescaped) = key.strip_prefix('\\') {
+            key = unescaped.to_string();
+        }
+        while self.eat('.') {
+            key = format!("{key}.{}", self.ident()?);
+        }
+        let value = match self.eat('=') {
+            true => Some(match self.next()? {
+                Token::Str(s) | Token::Literal(s) | Token::Ident(s) => s,
+                t => return Err(format!("Bad value for attribute {key}: {t:?}")),
+            }),
+            false => None,
+        };
+        match self.next()? {
+            Token::AttrClose => Ok((key, value)),
+            t => Err(format!("Expected '*)', got {t:?}")),
+        }
+    }
+
+    /// Consumes a list of named connections, like `(.A(a), .Y(y))`
+    fn connections(&mut self) -> Result<Vec<(Identifier, Option<Value>)>, String> {
+        self.expect('(')?;
+        let mut connections = Vec::new();
+        while !self.eat(')') {
+            self.expect('.')?;
+            let port = self.net()?;
+            self.expect('(')?;
+            let value = match self.peek() {
+                Some(Token::Symbol(')')) => None,
+                Some(Token::Literal(l)) => {
+                    let l = l.clone();
+                    self.pos += 1;
+                    Some(Value::Literal(l))
+                }
+                _ => Some(Value::Net(self.net()?)),
+            };
+            self.expect(')')?;
+            connections.push((port, value));
+            if !self.eat(',') {
+                self.expect(')')?;
+                break;
+            }
+        }
+        Ok(connections)
+    }
+
+    /// Parses a whole module
+    fn module(&mut self) -> Result<Module, String> {
+        while self.peek() != Some(&Token::Ident("module".to_string())) {
+            self.next()?;
+        }
+        self.next()?;
+        let name = self.ident()?;
+        self.expect('(')?;
+        while !self.eat(')') {
+            self.next()?;
+        }
+        self.expect(';')?;
+
+        let mut module = Module {
+            name,
+            ports: Vec::new(),
+            instances: Vec::new(),
+            assigns: Vec::new(),
+        };
+        let mut attributes = Vec::new();
+        let mut defparams: Vec<(Identifier, Identifier, Parameter)> = Vec::new();
+        loop {
+            let token = self.next()?;
+            let keyword = match &token {
+                Token::AttrOpen => {
+                    attributes.push(self.attribute()?);
+                    continue;
+                }
+                Token::Ident(s) => s.as_str(),
+                t => return Err(format!("Unexpected {t:?}")),
+            };
+            match keyword {
+                "endmodule" => break,
+                "input" | "output" | "inout" => {
+                    let range = self.range()?;
+                    let name = self.ident()?;
+                    self.expect(';')?;
+                    module.ports.push(PortDecl {
+                        name,
+                        direction: match keyword {
+                            "input" => PortDirection::Input,
+                            "output" => PortDirection::Output,
+                            _ => PortDirection::Inout,
+                        },
+                        range,
+                        attributes: std::mem::take(&mut attributes),
+                    });
+                }
+                "wire" => {
+                    self.range()?;
+                    self.ident()?;
+                    self.expect(';')?;
+                }
+                "assign" => {
+                    let output = self.net()?;
+                    self.expect('=')?;
+                    let net = self.net()?;
+                    self.expect(';')?;
+                    module.assigns.push((output, net));
+                }
+                "defparam" => {
+                    let inst = instance_name(self.ident()?);
+                    self.expect('.')?;
+                    let param = Identifier::new(self.ident()?);
+                    self.expect('=')?;
+                    let value = match self.next()? {
+                        Token::Literal(l) => l.parse()?,
+                        t => return Err(format!("Bad value for defparam {inst}.{param}: {t:?}")),
+                    };
+                    self.expect(';')?;
+                    defparams.push((inst, param, value));
+                }
+                _ => {
+                    let cell_type = Identifier::new(unescape_keyword(keyword.to_string()));
+                    let mut parameters = Vec::new();
+                    if self.eat('#') {
+                        for (k, v) in self.connections()? {
+                            match v {
+                                Some(Value::Literal(l)) => parameters.push((k, l.parse()?)),
+                                v => return Err(format!("Bad value for parameter {k}: {v:?}")),
+                            }
+                        }
+                    }
+                    let name = instance_name(self.ident()?);
+                    let connections = self.connections()?;
+                    self.expect(';')?;
+                    let ports = connections.iter().map(|(p, _)| p.clone()).collect();
+                    let connections = connections
+                        .into_iter()
+                        .filter_map(|(p, v)| match v {
+                            Some(Value::Net(n)) => Some(Ok((p, n))),
+                            Some(Value::Literal(l)) => Some(Err(format!(
+                                "Unsupported connection of {l} to {p} of {name}"
+                            ))),
+                            None => None,
+                        })
+                        .collect::<Result<_, String>>()?;
+                    module.instances.push(InstDecl {
+                        spec: InstanceSpec {
+                            name,
+                            cell_type,
+                            parameters,
+                            ports,
+                        },
+                        connections,
+                        attributes: std::mem::take(&mut attributes),
+                    });
+                }
+            }
+        }
+        for (inst, param, value) in defparams {
+            let decl = module
+                .instances
+                .iter_mut()
+                .find(|i| i.spec.name == inst)
+                .ok_or(format!("defparam of unknown instance {inst}"))?;
+            decl.spec.parameters.retain(|(k, _)| *k != param);
+            decl.spec.parameters.push((param, value));
+        }
+        Ok(module)
+    }
+}
+
+/// The parameter that holds the initial value of an instance, as written by the emitter
+const INIT_PARAMETER: &str = "INIT";
+
+/// Reads the first module of a structural Verilog file, like one written by the [std::fmt::Display] of a netlist.
+/// `cells` maps each instance to an [Instantiable] type, whose ports are connected by name.
+/// A `defparam` of `INIT` on a type without that parameter sets the initial value of the instance.
+pub fn read_verilog<I, F>(mut reader: impl Read, cells: F) -> Result<Rc<Netlist<I>>, String>
+where
+    I: Instantiable,
+    F: Fn(&InstanceSpec) -> Result<I, String>,
+{
+    let mut src = String::new();
+    reader.read_to_string(&mut src).map_err(|e| e.to_string())?;
+    let module = Parser {
+        tokens: tokenize(&src)?,
+        pos: 0,
+    }
+    .module()?;
+
+    let netlist = Netlist::new(module.name);
+    let mut drivers: HashMap<Identifier, DrivenNet<I>> = HashMap::new();
+    for port in &module.ports {
+        for bit in port.bits() {
+            let net = Net::new_logic(bit.clone());
+            let driver = match port.direction {
+                PortDirection::Input => netlist.insert_input(net),
+                PortDirection::Inout => netlist.insert_inout(net),
+                PortDirection::Output => continue,
+            };
+            drivers.insert(bit, driver);
+        }
+    }
+
+    let mut instances = Vec::new();
+    for decl in &module.instances {
+        let name = &decl.spec.name;
+        let inst_type = cells(&decl.spec).map_err(|e| format!("Instance {name}: {e}"))?;
+        let init = decl
+            .spec
+            .parameters
+            .iter()
+            .filter(|(k, _)| !inst_type.has_parameter(k))
+            .map(|(k, v)| match (k.to_string().as_str(), v) {
+                (INIT_PARAMETER, Parameter::BitVec(bv)) if bv.len() == 1 => Ok(bv[0]),
+                _ => Err(format!("Instance {name} has no parameter {k}")),
+            })
+            .next()
+            .transpose()?;
+        let inst = netlist.insert_gate_disconnected(inst_type, name.clone())?;
+        if init.is_some() {
+            inst.set_initial_value(init);
+        }
+        for (k, v) in &decl.attributes {
+            match v {
+                Some(v) => {
+                    inst.insert_attribute(k.clone(), v.clone());
+                }
+                None => inst.set_attribute(k.clone()),
+            }
+        }
+        for (port, net) in &decl.connections {
+            if let Some(output) = inst.find_output(port) {
+                // Outputs connected to an inout port drive the pad, which is read through the port
+                match drivers.get(net) {
+                    Some(inout) if inout.is_an_input() => netlist.drive_inout(&output, inout)?,
+                    Some(_) => return Err(format!("Multiple drivers for net {net}")),
+                    None => {
+                        output.as_net_mut().set_identifier(net.clone());
+                        drivers.insert(net.clone(), output);
+                    }
+                }
+            } else if inst.find_input(port).is_none() {
+                return Err(format!("Instance {name} has no port {port}"));
+            }
+        }
+        instances.push((inst, &decl.connections));
+    }
+
+    // Assigns to nets that are not outputs are aliases, which may alias each other in any order
+    let outputs: HashSet<Identifier> = module
+        .ports
+        .iter()
+        .filter(|p| p.direction == PortDirection::Output)
+        .flat_map(|p| p.bits())
+        .collect();
+    let (output_assigns, mut pending): (Vec<_>, Vec<_>) = module
+        .assigns
+        .into_iter()
+        .partition(|(lhs, _)| outputs.contains(lhs));
+    while !pending.is_empty() {
+        let (ready, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, rhs)| drivers.contains_key(rhs));
+        if ready.is_empty() {
+            return Err(format!("Net {} is not driven", rest[0].1));
+        }
+        for (lhs, rhs) in ready {
+            if drivers.contains_key(&lhs) {
+                return Err(format!("Multiple drivers for net {lhs}"));
+            }
+            let alias = netlist.insert_alias(drivers[&rhs].clone(), lhs.clone())?;
+            drivers.insert(lhs, alias);
+        }
+        pending = rest;
+    }
+    let mut assigned: HashMap<Identifier, Identifier> = HashMap::new();
+    for (lhs, rhs) in output_assigns {
+        if assigned.insert(lhs.clone(), rhs).is_some() {
+            return Err(format!("Multiple drivers for net {lhs}"));
+        }
+    }
+    let output_assigns = assigned;
+
+    for (inst, connections) in instances {
+        for (port, net) in connections {
+            if let Some(input) = inst.find_input(port) {
+                let driver = drivers.get(net).ok_or(format!("Net {net} is not driven"))?;
+                input.connect(driver.clone());
+            }
+        }
+    }
+
+    for port in module
+        .ports
+        .iter()
+        .filter(|p| p.direction == PortDirection::Output)
+    {
+        for bit in port.bits() {
+            let net = output_assigns.get(&bit).unwrap_or(&bit);
+            let driver = drivers
+                .get(net)
+                .ok_or(format!("Output {bit} is not driven"))?;
+            netlist.expose_net_with_name(driver.clone(), bit);
+        }
+    }
+    for port in &module.ports {
+        for (k, v) in &port.attributes {
+            netlist.set_port_attribute(
+                &Identifier::new(port.name.clone()),
+                k.clone(),
+                v.clone(),
+            )?;
+        }
+    }
+    Ok(netlist)
+}
+
+/// Returns a description of the structure of `netlist` that does not depend on the order of objects
+fn describe<I: Instantiable>(netlist: &Netlist<I>) -> Vec<String> {
+    let sorted = |mut v: Vec<String>| {
+        v.sort();
+        v.join(" ")
+    };
+    let mut lines = vec![format!("module {}", netlist.get_name())];
+    // Ports that are not vectors are written as one port per bit, with the attributes on the first
+    for port in netlist.ports() {
+        let attrs = sorted(port.attributes().map(|a| a.to_string()).collect());
+        let names: Vec<String> = match port.get_range() {
+            Some(_) => vec![port.get_name().to_string()],
+            None => port.get_nets().iter().map(|n| n.to_string()).collect(),
+        };
+        for (i, name) in names.into_iter().enumerate() {
+            let attrs = if i == 0 { attrs.as_str() } else { "" };
+            let width = port.get_range().map_or(1, |_| port.width());
+            lines.push(format!(
+                "port {} {name} {width} {attrs}",
+                port.get_direction()
+            ));
+        }
+    }
+    for alias in netlist.aliases() {
+        let driver = alias.get_input(0).get_driver();
+        lines.push(format!(
+            "alias {} = {}",
+            alias.get_identifier(),
+            driver.map_or("?".to_string(), |d| d.get_identifier().to_string())
+        ));
+    }
+    for node in netlist.objects() {
+        let Some(inst_type) = node.get_instance_type() else {
+            continue;
+        };
+        let params = sorted(
+            inst_type
+                .parameters()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect(),
+        );
+        let attrs = sorted(
+            node.attributes()
+                .map(|a| a.to_string())
+                .chain(node.property_strings())
+                .collect(),
+        );
+        let inputs = sorted(
+            node.inputs()
+                .filter_map(|i| Some(format!("{}={}", i.get_port(), i.get_driver()?)))
+                .collect(),
+        );
+        let outputs = sorted(
+            node.outputs()
+                .map(|o| format!("{}={}", o.get_port(), o.get_identifier()))
+                .collect(),
+        );
+        lines.push(format!(
+            "instance {} {} #({params}) {attrs} in({inputs}) out({outputs})",
+            node.get_instance_name().unwrap(),
+            inst_type.get_name()
+        ));
+    }
+    for (driver, output) in netlist.outputs() {
+        lines.push(format!("output {output} = {driver}"));
+    }
+    lines.sort();
+    lines
+}
+
+/// Emits `netlist` as Verilog, reads it back with [read_verilog] and compares the structure of the two netlists.
+/// Instances are mapped back to the types they have in `netlist`.
+/// Returns an error listing the differences, if any.
+pub fn check_roundtrip<I: Instantiable>(netlist: &Netlist<I>) -> Result<(), String> {
+    let verilog = netlist.to_string();
+    let types: HashMap<Identifier, I> = netlist
+        .objects()
+        .filter_map(|n| Some((n.get_instance_name()?, n.get_instance_type()?.clone())))
+        .collect();
+    let read = read_verilog(verilog.as_bytes(), |spec: &InstanceSpec| {
+        let mut inst_type = types
+            .get(&spec.name)
+            .cloned()
+            .ok_or(format!("Unknown instance {}", spec.name))?;
+        if *inst_type.get_name() != spec.cell_type {
+            return Err(format!("Expected type {}", inst_type.get_name()));
+        }
+        for (k, v) in &spec.parameters {
+            if inst_type.get_parameter(k).is_some_and(|old| old != *v)
+                && inst_type.set_parameter(k, v.clone()).is_none()
+            {
+                return Err(format!("Cannot set parameter {k} to {v}"));
+            }
+        }
+        Ok(inst_type)
+    })
+    .map_err(|e| format!("Emitted Verilog does not read back: {e}\n{verilog}"))?;
+
+    let (before, after) = (describe(netlist), describe(&read));
+    let missing: Vec<&String> = before.iter().filter(|l| !after.contains(l)).collect();
+    let extra: Vec<&String> = after.iter().filter(|l| !before.contains(l)).collect();
+    if missing.is_empty() && extra.is_empty() {
+        return Ok(());
+    }
+    let mut msg = "The netlist read back differs from the original".to_string();
+    for l in missing {
+        msg.push_str(&format!("\n- {l}"));
+    }
+    for l in extra {
+        msg.push_str(&format!("\n+ {l}"));
+    }
+    Err(msg)
+}
+
+/// Asserts that `netlist` survives a round trip through Verilog, as checked by [check_roundtrip]
+///
+/// # Panics
+///
+/// Panics with the differences if the netlist read back differs from `netlist`.
+pub fn assert_roundtrip<I: Instantiable>(netlist: &Netlist<I>) {
+    if let Err(e) = check_roundtrip(netlist) {
+        panic!("{e}");
+    }
+}
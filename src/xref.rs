@@ -0,0 +1,263 @@
+/*!
+
+  Cross-reference tables of where every net is driven and used, for querying large designs with standard tools.
+
+*/
+
+use crate::{
+    circuit::{Identifier, Instantiable, PortDirection},
+    netlist::Netlist,
+};
+use std::{collections::HashMap, io::Write};
+
+/// A net of a [Xref]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XrefNet {
+    /// The name of the net
+    pub net: String,
+    /// The instance driving the net, or [None] if it is a top-level input
+    pub driver: Option<String>,
+    /// The output pin of the instance driving the net
+    pub driver_pin: Option<String>,
+    /// The number of instance pins and top-level outputs the net drives
+    pub fanout: usize,
+}
+
+/// A pin of a [Xref]. Top-level ports are listed as pins without an instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XrefPin {
+    /// The instance the pin belongs to, or [None] for a top-level port
+    pub instance: Option<String>,
+    /// The type of the instance
+    pub inst_type: Option<String>,
+    /// The name of the pin
+    pub pin: String,
+    /// The direction of the pin
+    pub direction: PortDirection,
+    /// The net connected to the pin, or [None] if it is unconnected
+    pub net: Option<String>,
+}
+
+/// The cross-reference of a netlist: every net with its driver, and every pin with its net
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xref {
+    /// The nets, in the order of the objects driving them
+    nets: Vec<XrefNet>,
+    /// The pins of the instances, in object order, followed by the top-level ports
+    pins: Vec<XrefPin>,
+}
+
+/// Returns the name of `id` without the escaping of Verilog
+fn plain_name(id: &Identifier) -> String {
+    if id.is_escaped() {
+        id.get_name().to_string()
+    } else {
+        id.to_string()
+    }
+}
+
+/// Quotes `field` for a CSV file if it contains a separator, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Returns `field` as an SQL literal
+fn sql_field(field: Option<&str>) -> String {
+    match field {
+        Some(f) => format!("'{}'", f.replace('\'', "''")),
+        None => "NULL".to_string(),
+    }
+}
+
+impl Xref {
+    /// Builds the cross-reference of `netlist`
+    pub fn new<I: Instantiable>(netlist: &Netlist<I>) -> Self {
+        let outputs = netlist.outputs();
+        let mut fanout: HashMap<String, usize> = HashMap::new();
+        for (driver, _) in &outputs {
+            *fanout
+                .entry(plain_name(&driver.get_identifier()))
+                .or_default() += 1;
+        }
+        let inouts: Vec<_> = netlist
+            .ports()
+            .into_iter()
+            .filter(|p| p.is_inout())
+            .flat_map(|p| p.get_nets().to_vec())
+            .collect();
+        let mut pins = Vec::new();
+        for node in netlist.objects().filter(|n| n.is_an_alias()) {
+            if let Some(driver) = node.get_input(0).get_driver() {
+                *fanout
+                    .entry(plain_name(&driver.get_identifier()))
+                    .or_default() += 1;
+            }
+        }
+        for node in netlist.objects() {
+            let Some(inst_ty) = node.get_instance_type().map(|t| t.clone()) else {
+                continue;
+            };
+            let instance = node.get_instance_name().map(|n| plain_name(&n));
+            let inst_type = Some(plain_name(inst_ty.get_name()));
+            for (i, input) in node.inputs().enumerate() {
+                let net = input.get_driver().map(|d| plain_name(&d.get_identifier()));
+                if let Some(net) = &net {
+                    *fanout.entry(net.clone()).or_default() += 1;
+                }
+                pins.push(XrefPin {
+                    instance: instance.clone(),
+                    inst_type: inst_type.clone(),
+                    pin: plain_name(input.get_port().get_identifier()),
+                    direction: match inst_ty.is_inout_port(i) {
+                        true => PortDirection::Inout,
+                        false => PortDirection::Input,
+                    },
+                    net,
+                });
+            }
+            for output in node.outputs() {
+                pins.push(XrefPin {
+                    instance: instance.clone(),
+                    inst_type: inst_type.clone(),
+                    pin: plain_name(output.get_port().get_identifier()),
+                    direction: PortDirection::Output,
+                    net: Some(plain_name(&output.get_identifier())),
+                });
+            }
+        }
+        for input in netlist.inputs() {
+            let name = plain_name(&input.get_identifier());
+            pins.push(XrefPin {
+                instance: None,
+                inst_type: None,
+                pin: name.clone(),
+                direction: match inouts.contains(&input.as_net()) {
+                    true => PortDirection::Inout,
+                    false => PortDirection::Input,
+                },
+                net: Some(name),
+            });
+        }
+        for (driver, output) in &outputs {
+            pins.push(XrefPin {
+                instance: None,
+                inst_type: None,
+                pin: plain_name(output.get_identifier()),
+                direction: PortDirection::Output,
+                net: Some(plain_name(&driver.get_identifier())),
+            });
+        }
+
+        let nets = netlist
+            .objects()
+            .flat_map(|n| n.outputs().collect::<Vec<_>>())
+            .map(|output| {
+                let net = plain_name(&output.get_identifier());
+                let (driver, driver_pin) = match output.is_an_input() || output.is_an_alias() {
+                    true => (None, None),
+                    false => (
+                        output
+                            .clone()
+                            .unwrap()
+                            .get_instance_name()
+                            .map(|n| plain_name(&n)),
+                        Some(plain_name(output.get_port().get_identifier())),
+                    ),
+                };
+                XrefNet {
+                    fanout: fanout.get(&net).copied().unwrap_or_default(),
+                    net,
+                    driver,
+                    driver_pin,
+                }
+            })
+            .collect();
+        Self { nets, pins }
+    }
+
+    /// Returns the nets, in the order of the objects driving them
+    pub fn nets(&self) -> &[XrefNet] {
+        &self.nets
+    }
+
+    /// Returns the pins of the instances, in object order, followed by the top-level ports
+    pub fn pins(&self) -> &[XrefPin] {
+        &self.pins
+    }
+
+    /// Returns the pins connected to the net named `net`, i.e. where the net is used and driven
+    pub fn where_used(&self, net: &str) -> impl Iterator<Item = &XrefPin> {
+        self.pins
+            .iter()
+            .filter(move |p| p.net.as_deref() == Some(net))
+    }
+
+    /// Writes the nets and the pins as two CSV files with headers
+    pub fn write_csv(&self, mut nets: impl Write, mut pins: impl Write) -> std::io::Result<()> {
+        writeln!(nets, "net,driver,driver_pin,fanout")?;
+        for n in &self.nets {
+            writeln!(
+                nets,
+                "{},{},{},{}",
+                csv_field(&n.net),
+                csv_field(n.driver.as_deref().unwrap_or_default()),
+                csv_field(n.driver_pin.as_deref().unwrap_or_default()),
+                n.fanout
+            )?;
+        }
+        writeln!(pins, "instance,type,pin,direction,net")?;
+        for p in &self.pins {
+            writeln!(
+                pins,
+                "{},{},{},{},{}",
+                csv_field(p.instance.as_deref().unwrap_or_default()),
+                csv_field(p.inst_type.as_deref().unwrap_or_default()),
+                csv_field(&p.pin),
+                p.direction,
+                csv_field(p.net.as_deref().unwrap_or_default())
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the nets and the pins as an SQL script that creates and fills the tables `nets` and `pins`,
+    /// which can be loaded into SQLite with `sqlite3 xref.db < xref.sql`
+    pub fn write_sql(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "BEGIN TRANSACTION;")?;
+        writeln!(
+            writer,
+            "CREATE TABLE nets (net TEXT PRIMARY KEY, driver TEXT, driver_pin TEXT, fanout INTEGER);"
+        )?;
+        writeln!(
+            writer,
+            "CREATE TABLE pins (instance TEXT, type TEXT, pin TEXT, direction TEXT, net TEXT);"
+        )?;
+        for n in &self.nets {
+            writeln!(
+                writer,
+                "INSERT INTO nets VALUES ({}, {}, {}, {});",
+                sql_field(Some(&n.net)),
+                sql_field(n.driver.as_deref()),
+                sql_field(n.driver_pin.as_deref()),
+                n.fanout
+            )?;
+        }
+        for p in &self.pins {
+            writeln!(
+                writer,
+                "INSERT INTO pins VALUES ({}, {}, {}, '{}', {});",
+                sql_field(p.instance.as_deref()),
+                sql_field(p.inst_type.as_deref()),
+                sql_field(Some(&p.pin)),
+                p.direction,
+                sql_field(p.net.as_deref())
+            )?;
+        }
+        writeln!(writer, "CREATE INDEX pins_net ON pins (net);")?;
+        writeln!(writer, "COMMIT;")
+    }
+}
@@ -0,0 +1,565 @@
+/*!
+
+  Reading and writing netlists in the JSON format of Yosys `write_json`.
+
+*/
+
+use crate::{
+    attribute::{AttributeValue, Parameter},
+    circuit::{Identifier, Instantiable, Net},
+    netlist::{DrivenNet, Netlist},
+};
+use bitvec::vec::BitVec;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+};
+use serde_json::Value;
+use std::{cell::RefCell, collections::HashMap, io::Read, io::Write, marker::PhantomData, rc::Rc};
+
+/// The value Yosys uses for attributes that are set without a value
+const FLAG: &str = "00000000000000000000000000000001";
+
+/// A JSON object that keeps the order of its keys
+#[derive(Debug)]
+struct Ordered<T>(Vec<(String, T)>);
+
+impl<T> Default for Ordered<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T: Serialize> Serialize for Ordered<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in &self.0 {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Ordered<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for OrderedVisitor<T> {
+            type Value = Ordered<T>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(Ordered(entries))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedVisitor(PhantomData))
+    }
+}
+
+/// A bit of a signal: either a net number or a constant like `"0"`, `"1"` or `"x"`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Bit {
+    Net(usize),
+    Const(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Design {
+    #[serde(default)]
+    creator: String,
+    modules: Ordered<Module>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Module {
+    #[serde(default)]
+    attributes: Ordered<Value>,
+    #[serde(default)]
+    ports: Ordered<Port>,
+    #[serde(default)]
+    cells: Ordered<Cell>,
+    #[serde(default)]
+    netnames: Ordered<Netname>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Port {
+    direction: String,
+    bits: Vec<Bit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cell {
+    #[serde(default)]
+    hide_name: u8,
+    #[serde(rename = "type")]
+    cell_type: String,
+    #[serde(default)]
+    parameters: Ordered<Value>,
+    #[serde(default)]
+    attributes: Ordered<Value>,
+    #[serde(default)]
+    port_directions: Ordered<String>,
+    connections: Ordered<Vec<Bit>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Netname {
+    #[serde(default)]
+    hide_name: u8,
+    bits: Vec<Bit>,
+    #[serde(default)]
+    attributes: Ordered<Value>,
+}
+
+/// A cell read from a Yosys netlist, to be mapped to an [Instantiable] type
+#[derive(Debug, Clone)]
+pub struct CellSpec {
+    /// The type of the cell, like `$_AND_` or a library cell name
+    pub cell_type: String,
+    /// The parameters of the cell
+    pub parameters: Vec<(Identifier, Parameter)>,
+    /// The names of the input ports, in the order they are listed
+    pub inputs: Vec<String>,
+    /// The names of the output ports, in the order they are listed
+    pub outputs: Vec<String>,
+}
+
+impl CellSpec {
+    /// Returns the spec of a cell that drives the constant `value` from its output `Y`.
+    /// Its type is the Verilog literal of the value, `1'b0` or `1'b1`.
+    pub fn constant(value: bool) -> Self {
+        Self {
+            cell_type: format!("1'b{}", value as u8),
+            parameters: Vec::new(),
+            inputs: Vec::new(),
+            outputs: vec!["Y".to_string()],
+        }
+    }
+}
+
+/// Returns the name of `id` as it appears in Yosys JSON
+fn json_name(id: &Identifier) -> String {
+    match id.get_bit_index() {
+        Some(i) => format!("{}[{i}]", id.get_name()),
+        None => id.get_name().to_string(),
+    }
+}
+
+/// Returns the identifier of the `index`th bit of the signal `name` with `width` bits
+fn bit_name(name: &str, index: usize, width: usize) -> Identifier {
+    if width == 1 {
+        name.into()
+    } else {
+        format!("{name}[{index}]").into()
+    }
+}
+
+/// Replaces the characters of a Yosys cell name that cannot be used in an instance name,
+/// like the `$` that private names start with
+fn instance_name(name: &str) -> Identifier {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{name}").into(),
+        false => name.into(),
+    }
+}
+
+/// Encodes a parameter like Yosys does: bit vectors as binary strings, msb first.
+/// Integers are written as numbers, which Yosys also reads, so they are not read back as bit vectors.
+fn encode_parameter(param: &Parameter) -> Value {
+    match param {
+        Parameter::Integer(i) => Value::from(*i),
+        Parameter::Real(r) => Value::String(format!("{r:?}")),
+        Parameter::BitVec(bv) => Value::String(
+            bv.iter()
+                .rev()
+                .map(|b| if *b { '1' } else { '0' })
+                .collect(),
+        ),
+    }
+}
+
+/// Decodes a parameter written by Yosys
+fn decode_parameter(value: &Value) -> Option<Parameter> {
+    match value {
+        Value::Number(n) => n.as_i64().map(|i| Parameter::Integer(i as i32)),
+        Value::String(s) if !s.is_empty() && s.chars().all(|c| c == '0' || c == '1') => Some(
+            Parameter::BitVec(s.chars().rev().map(|c| c == '1').collect::<BitVec>()),
+        ),
+        Value::String(s) => s.trim().parse::<f32>().ok().map(Parameter::Real),
+        _ => None,
+    }
+}
+
+/// Encodes an attribute value like Yosys does
+fn encode_attribute(value: &AttributeValue) -> Value {
+    Value::String(value.clone().unwrap_or_else(|| FLAG.to_string()))
+}
+
+/// Decodes an attribute value written by Yosys
+fn decode_attribute(value: &Value) -> AttributeValue {
+    match value {
+        Value::String(s) if s == FLAG => None,
+        Value::String(s) => Some(s.trim_end().to_string()),
+        v => Some(v.to_string()),
+    }
+}
+
+/// Adds the bit `bit` of the signal `name` to the connections of a cell, lsb first.
+/// Ports of an instance type that are bits of the same bus, like `A[0]` and `A[1]`, become one multi-bit connection `A`.
+fn connect_bit(
+    port_directions: &mut Vec<(String, String)>,
+    connections: &mut Vec<(String, Vec<Bit>)>,
+    port: &Identifier,
+    direction: &str,
+    bit: Bit,
+) {
+    let name = port.get_name().to_string();
+    let index = port.get_bit_index().unwrap_or(0);
+    let pos = match connections.iter().position(|(n, _)| *n == name) {
+        Some(pos) => pos,
+        None => {
+            port_directions.push((name.clone(), direction.to_string()));
+            connections.push((name, Vec::new()));
+            connections.len() - 1
+        }
+    };
+    let bits = &mut connections[pos].1;
+    if bits.len() <= index {
+        bits.resize(index + 1, Bit::Const("x".to_string()));
+    }
+    bits[index] = bit;
+}
+
+/// Writes `netlist` as a Yosys JSON design with a single top module.
+/// Ports of an instance type that are bits of a bus, like `A[0]`, are written as one multi-bit connection.
+pub fn write_yosys_json<I: Instantiable>(
+    netlist: &Netlist<I>,
+    writer: impl Write,
+) -> Result<(), serde_json::Error> {
+    // Number the driven nets, starting at 2 like Yosys
+    let mut bits: HashMap<Net, usize> = HashMap::new();
+    for obj in netlist.objects().filter(|o| !o.is_an_alias()) {
+        for net in obj.nets() {
+            let next = bits.len() + 2;
+            bits.entry(net).or_insert(next);
+        }
+    }
+    // Aliases are other names for the bits of the nets they alias
+    for alias in netlist.aliases() {
+        let mut driver = alias.get_input(0).get_driver();
+        while let Some(d) = driver.clone().filter(|d| d.is_an_alias()) {
+            driver = d.unwrap().get_input(0).get_driver();
+        }
+        if let Some(bit) = driver.and_then(|d| bits.get(&*d.as_net()).copied()) {
+            bits.insert(alias.as_net().clone(), bit);
+        }
+    }
+    let outputs: HashMap<Net, Net> = netlist
+        .outputs()
+        .into_iter()
+        .map(|(driver, net)| (net, driver.as_net().clone()))
+        .collect();
+    let bit_of = |net: &Net| {
+        let driver = outputs.get(net).unwrap_or(net);
+        bits.get(driver)
+            .map(|b| Bit::Net(*b))
+            .unwrap_or(Bit::Const("x".to_string()))
+    };
+
+    let mut module = Module::default();
+    module
+        .attributes
+        .0
+        .push(("top".to_string(), Value::String(FLAG.to_string())));
+    let mut port_nets = Vec::new();
+    for port in netlist.ports() {
+        let name = json_name(port.get_name());
+        let port_bits: Vec<Bit> = port.get_nets().iter().map(bit_of).collect();
+        module.ports.0.push((
+            name.clone(),
+            Port {
+                direction: port.get_direction().to_string(),
+                bits: port_bits.clone(),
+            },
+        ));
+        let attributes = port
+            .attributes()
+            .map(|a| (a.key().clone(), encode_attribute(a.value())))
+            .collect();
+        module.netnames.0.push((
+            name,
+            Netname {
+                hide_name: 0,
+                bits: port_bits,
+                attributes: Ordered(attributes),
+            },
+        ));
+        port_nets.extend(port.get_nets().iter().cloned());
+    }
+
+    for obj in netlist.objects() {
+        if obj.is_an_alias() && !port_nets.contains(&obj.as_net()) {
+            let net = obj.as_net().clone();
+            module.netnames.0.push((
+                json_name(net.get_identifier()),
+                Netname {
+                    hide_name: 0,
+                    bits: vec![bit_of(&net)],
+                    attributes: Ordered::default(),
+                },
+            ));
+        }
+        let Some(inst_type) = obj.get_instance_type() else {
+            continue;
+        };
+        let mut port_directions = Vec::new();
+        let mut connections = Vec::new();
+        for (i, port) in inst_type.get_input_ports().into_iter().enumerate() {
+            let bit = match obj.get_driver_net(i) {
+                Some((net, _)) => bit_of(&net),
+                None => Bit::Const("x".to_string()),
+            };
+            connect_bit(
+                &mut port_directions,
+                &mut connections,
+                port.get_identifier(),
+                "input",
+                bit,
+            );
+        }
+        for (port, net) in inst_type.get_output_ports().into_iter().zip(obj.nets()) {
+            connect_bit(
+                &mut port_directions,
+                &mut connections,
+                port.get_identifier(),
+                "output",
+                bit_of(&net),
+            );
+            if !port_nets.contains(&net) {
+                module.netnames.0.push((
+                    json_name(net.get_identifier()),
+                    Netname {
+                        hide_name: 0,
+                        bits: vec![bit_of(&net)],
+                        attributes: Ordered::default(),
+                    },
+                ));
+            }
+        }
+        let cell = Cell {
+            hide_name: 0,
+            cell_type: json_name(inst_type.get_name()),
+            parameters: Ordered(
+                inst_type
+                    .parameters()
+                    .map(|(k, v)| (json_name(&k), encode_parameter(&v)))
+                    .collect(),
+            ),
+            attributes: Ordered(
+                obj.attributes()
+                    .map(|a| (a.key().clone(), encode_attribute(a.value())))
+                    .collect(),
+            ),
+            port_directions: Ordered(port_directions),
+            connections: Ordered(connections),
+        };
+        module
+            .cells
+            .0
+            .push((json_name(&obj.get_instance_name().unwrap()), cell));
+    }
+
+    let design = Design {
+        creator: "safety-net".to_string(),
+        modules: Ordered(vec![(netlist.get_name().to_string(), module)]),
+    };
+    serde_json::to_writer_pretty(writer, &design)
+}
+
+/// Reads a netlist from a Yosys JSON design.
+/// The module `top` is read, or the module marked as top if [None] is given.
+/// `cells` maps each cell to an [Instantiable] type, whose ports are connected by name.
+/// The bits of a multi-bit cell port `A` are listed in the [CellSpec] as `A[0]`, `A[1]` and so on.
+/// Constant bits are driven by cells mapped from [CellSpec::constant], which are shared and named like `tie_1`, skipping names already in use.
+pub fn read_yosys_json<I, F>(
+    reader: impl Read,
+    top: Option<&str>,
+    cells: F,
+) -> Result<Rc<Netlist<I>>, String>
+where
+    I: Instantiable,
+    F: Fn(&CellSpec) -> Result<I, String>,
+{
+    let design: Design = serde_json::from_reader(reader).map_err(|e| e.to_string())?;
+    let mut modules = design.modules.0;
+    let pos = match top {
+        Some(top) => modules.iter().position(|(n, _)| n == top),
+        None => modules
+            .iter()
+            .position(|(_, m)| m.attributes.0.iter().any(|(k, _)| k == "top"))
+            .or((modules.len() == 1).then_some(0)),
+    }
+    .ok_or("Top module not found".to_string())?;
+    let (name, module) = modules.swap_remove(pos);
+
+    let netlist = Netlist::new(name);
+    let constants: RefCell<HashMap<bool, DrivenNet<I>>> = RefCell::new(HashMap::new());
+    let tie = |value: bool| -> Result<DrivenNet<I>, String> {
+        if let Some(constant) = constants.borrow().get(&value) {
+            return Ok(constant.clone());
+        }
+        let inst_type = cells(&CellSpec::constant(value))?;
+        let constant = netlist.find_or_insert_tie(inst_type, value)?;
+        constants.borrow_mut().insert(value, constant.clone());
+        Ok(constant)
+    };
+    let mut drivers: HashMap<usize, DrivenNet<I>> = HashMap::new();
+    for (port_name, port) in &module.ports.0 {
+        match port.direction.as_str() {
+            "input" => {
+                for (i, bit) in port.bits.iter().enumerate() {
+                    let Bit::Net(b) = bit else {
+                        return Err(format!("Input port {port_name} is driven by a constant"));
+                    };
+                    let id = bit_name(port_name, i, port.bits.len());
+                    drivers.insert(*b, netlist.insert_input(Net::new_logic(id)));
+                }
+            }
+            "output" => (),
+            dir => return Err(format!("Port {port_name} has unsupported direction {dir}")),
+        }
+    }
+
+    let mut instances = Vec::new();
+    for (cell_name, cell) in &module.cells.0 {
+        let mut parameters = Vec::new();
+        for (k, v) in &cell.parameters.0 {
+            let param = decode_parameter(v).ok_or(format!(
+                "Parameter {k} of cell {cell_name} has an unsupported value {v}"
+            ))?;
+            parameters.push((k.as_str().into(), param));
+        }
+        let ports = |dir: &str| {
+            cell.port_directions
+                .0
+                .iter()
+                .filter(|(_, d)| d == dir)
+                .flat_map(|(p, _)| {
+                    let width = cell
+                        .connections
+                        .0
+                        .iter()
+                        .find(|(c, _)| c == p)
+                        .map_or(1, |(_, bits)| bits.len());
+                    (0..width).map(move |i| json_name(&bit_name(p, i, width)))
+                })
+                .collect()
+        };
+        let spec = CellSpec {
+            cell_type: cell.cell_type.clone(),
+            parameters,
+            inputs: ports("input"),
+            outputs: ports("output"),
+        };
+        let inst_type = cells(&spec).map_err(|e| format!("Cell {cell_name}: {e}"))?;
+        let inst = netlist.insert_gate_disconnected(inst_type, instance_name(cell_name))?;
+        for (k, v) in &cell.attributes.0 {
+            match decode_attribute(v) {
+                Some(v) => {
+                    inst.insert_attribute(k.clone(), v);
+                }
+                None => inst.set_attribute(k.clone()),
+            }
+        }
+
+        // A port like `A[1]` is a bit of the multi-bit connection `A`, unless the cell has a connection named `A[1]`
+        let bit_of = |port: &Net| -> Result<Option<Bit>, String> {
+            let id = port.get_identifier();
+            let name = json_name(id);
+            let find = |name: &str| cell.connections.0.iter().find(|(p, _)| p == name);
+            match (find(&name), id.get_bit_index()) {
+                (Some((_, bits)), _) if bits.len() == 1 => Ok(Some(bits[0].clone())),
+                (Some(_), _) => Err(format!(
+                    "Port {name} of cell {cell_name} is not a single bit"
+                )),
+                (None, Some(i)) => match find(id.get_name()) {
+                    Some((_, bits)) => bits.get(i).cloned().map(Some).ok_or(format!(
+                        "Port {} of cell {cell_name} has no bit {i}",
+                        id.get_name()
+                    )),
+                    None => Ok(None),
+                },
+                (None, None) => Ok(None),
+            }
+        };
+        for output in inst.outputs() {
+            if let Some(Bit::Net(b)) = bit_of(&output.get_port())? {
+                drivers.insert(b, output);
+            }
+        }
+        let mut inputs = Vec::new();
+        for input in inst.inputs() {
+            inputs.push((input.clone(), bit_of(&input.get_port())?));
+        }
+        instances.push(inputs);
+    }
+
+    let driver_of = |bit: &Bit| -> Result<Option<DrivenNet<I>>, String> {
+        match bit {
+            Bit::Net(b) => Ok(drivers.get(b).cloned()),
+            Bit::Const(c) if c == "0" || c == "1" => tie(c == "1").map(Some),
+            Bit::Const(_) => Ok(None),
+        }
+    };
+    for (input, bit) in instances.into_iter().flatten() {
+        if let Some(bit) = bit
+            && let Some(driver) = driver_of(&bit)?
+        {
+            input.connect(driver);
+        }
+    }
+
+    for (port_name, port) in &module.ports.0 {
+        if port.direction != "output" {
+            continue;
+        }
+        for (i, bit) in port.bits.iter().enumerate() {
+            let driver = driver_of(bit)?
+                .ok_or(format!("Output port {port_name} has an undriven bit {i}"))?;
+            netlist.expose_net_with_name(driver, bit_name(port_name, i, port.bits.len()));
+        }
+    }
+
+    for (port_name, _) in &module.ports.0 {
+        let attributes = module
+            .netnames
+            .0
+            .iter()
+            .find(|(n, _)| n == port_name)
+            .map(|(_, n)| &n.attributes.0);
+        for (k, v) in attributes.into_iter().flatten() {
+            netlist.set_port_attribute(
+                &port_name.as_str().into(),
+                k.clone(),
+                decode_attribute(v),
+            )?;
+        }
+    }
+
+    Ok(netlist)
+}
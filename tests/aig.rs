@@ -0,0 +1,468 @@
+use safety_net::aig::{Aig, Lit, XMode, decompose_gate, region_function};
+use safety_net::constraint::InputConstraint;
+use safety_net::cost::AcceptAll;
+use safety_net::format_id;
+use safety_net::netlist::{DrivenNet, Gate, GateNetlist, Netlist};
+use std::rc::Rc;
+
+fn gate(name: &str, inputs: &[&str]) -> Gate {
+    Gate::new_logical(
+        name.into(),
+        inputs.iter().map(|i| (*i).into()).collect(),
+        "Y".into(),
+    )
+}
+
+fn ripple_adder(bitwidth: usize) -> Rc<GateNetlist> {
+    let netlist = Netlist::new("ripple_adder".to_string());
+    let a = netlist.insert_input_escaped_logic_bus("a".to_string(), bitwidth);
+    let b = netlist.insert_input_escaped_logic_bus("b".to_string(), bitwidth);
+    let mut carry: DrivenNet<Gate> = netlist.insert_input("cin".into());
+
+    for (i, (a, b)) in a.into_iter().zip(b).enumerate() {
+        let xor = gate("XOR", &["A", "B", "C"]);
+        let sum = netlist
+            .insert_gate(
+                xor,
+                format_id!("sum_{i}"),
+                &[a.clone(), b.clone(), carry.clone()],
+            )
+            .unwrap();
+        sum.expose_with_name(format_id!("s_{i}"));
+
+        let ab = netlist
+            .insert_gate(
+                gate("AND", &["A", "B"]),
+                format_id!("ab_{i}"),
+                &[a.clone(), b.clone()],
+            )
+            .unwrap();
+        let either = netlist
+            .insert_gate(gate("OR", &["A", "B"]), format_id!("either_{i}"), &[a, b])
+            .unwrap();
+        // carry = ab | (either & carry), as a mux on the carry
+        let mux = gate("MUX", &["A", "B", "S"]);
+        carry = netlist
+            .insert_gate(
+                mux,
+                format_id!("carry_{i}"),
+                &[ab.get_output(0), either.get_output(0), carry],
+            )
+            .unwrap()
+            .get_output(0);
+    }
+    carry.expose_with_name("cout".into());
+    netlist
+}
+
+#[test]
+fn test_from_netlist() {
+    let netlist = ripple_adder(2);
+    let aig = Aig::from_gate_netlist(&netlist).unwrap();
+    assert_eq!(aig.inputs().len(), 5);
+    assert_eq!(aig.outputs().len(), 3);
+    assert_eq!(aig.outputs()[2].1.as_deref(), Some("cout"));
+
+    // Inputs are a[0], a[1], b[0], b[1], cin and outputs are s_0, s_1, cout
+    for x in 0..32u32 {
+        let bits: Vec<bool> = (0..5).map(|i| x >> i & 1 == 1).collect();
+        let (outputs, _) = aig.evaluate(&bits, &[]).unwrap();
+        let sum = (x & 3) + (x >> 2 & 3) + (x >> 4);
+        let expected: Vec<bool> = (0..3).map(|i| sum >> i & 1 == 1).collect();
+        assert_eq!(outputs, expected);
+    }
+}
+
+#[test]
+fn test_from_netlist_errors() {
+    let netlist = GateNetlist::new("bad".to_string());
+    let a = netlist.insert_input("a".into());
+    let lut = netlist
+        .insert_gate(
+            gate("LUT", &["A"]),
+            "lut_0".into(),
+            std::slice::from_ref(&a),
+        )
+        .unwrap();
+    lut.expose_with_name("y".into());
+    assert!(Aig::from_gate_netlist(&netlist).is_err());
+
+    // Combinational loops can't be converted
+    let netlist = GateNetlist::new("loop".to_string());
+    let a = netlist.insert_input("a".into());
+    let and = netlist
+        .insert_gate_disconnected(gate("AND", &["A", "B"]), "and_0".into())
+        .unwrap();
+    a.connect(and.get_input(0));
+    and.get_output(0).connect(and.get_input(1));
+    and.expose_with_name("y".into());
+    assert!(Aig::from_gate_netlist(&netlist).is_err());
+}
+
+#[test]
+fn test_strash() {
+    let mut aig = Aig::new();
+    let a = aig.add_input(None);
+    let b = aig.add_input(None);
+    let ab = aig.and(a, b);
+    assert_eq!(aig.and(b, a), ab);
+    assert_eq!(aig.and(a, !a), Lit::FALSE);
+    assert_eq!(aig.and(a, Lit::TRUE), a);
+    assert_eq!(aig.fanins(!ab), Some((b, a)));
+    assert_eq!(aig.num_ands(), 1);
+}
+
+#[test]
+fn test_evaluate_ternary() {
+    // A register that starts unknown and is muxed by its own value between two copies of d
+    let mut aig = Aig::new();
+    let d = aig.add_input(Some("d".to_string()));
+    let q = aig.add_latch(Some("q".to_string()), None);
+    let next = aig.mux(q, d, d);
+    aig.set_latch_next(q, next).unwrap();
+    aig.add_output(q, Some("q".to_string())).unwrap();
+    let and = aig.and(q, d);
+    aig.add_output(and, Some("and".to_string())).unwrap();
+
+    let state = aig.initial_ternary_state();
+    assert_eq!(state, vec![None]);
+    let (outputs, next) = aig
+        .evaluate_ternary(&[Some(true)], &state, XMode::Pessimistic)
+        .unwrap();
+    assert_eq!(outputs, vec![None, None]);
+    assert_eq!(next, vec![None]);
+    let (outputs, next) = aig
+        .evaluate_ternary(&[Some(true)], &state, XMode::Resolve)
+        .unwrap();
+    assert_eq!(outputs, vec![None, None]);
+    assert_eq!(next, vec![Some(true)]);
+
+    // Known values are unaffected by the mode
+    let (outputs, next) = aig
+        .evaluate_ternary(&[Some(false)], &state, XMode::Pessimistic)
+        .unwrap();
+    assert_eq!(outputs, vec![None, Some(false)]);
+    assert_eq!(next, vec![Some(false)]);
+    let (_, next) = aig
+        .evaluate_ternary(&[None], &state, XMode::Resolve)
+        .unwrap();
+    assert_eq!(next, vec![None]);
+    assert!(aig.evaluate_ternary(&[], &state, XMode::Resolve).is_err());
+}
+
+#[test]
+fn test_write_aiger() {
+    let mut aig = Aig::new();
+    let a = aig.add_input(Some("a".to_string()));
+    let q = aig.add_latch(Some("q".to_string()), None);
+    let next = aig.xor(a, q);
+    aig.set_latch_next(q, next).unwrap();
+    aig.add_output(!q, Some("y".to_string())).unwrap();
+
+    let mut ascii = Vec::new();
+    aig.write_aiger(&mut ascii, false).unwrap();
+    assert_eq!(
+        String::from_utf8(ascii.clone()).unwrap(),
+        "aag 5 1 1 1 3\n2\n4 10 4\n5\n6 4 2\n8 5 3\n10 9 7\ni0 a\nl0 q\no0 y\n"
+    );
+
+    let mut binary = Vec::new();
+    aig.write_aiger(&mut binary, true).unwrap();
+    assert!(binary.starts_with(b"aig 5 1 1 1 3\n10 4\n5\n"));
+
+    // Both formats read back to the same graph
+    for bytes in [ascii.clone(), binary] {
+        let read = Aig::read_aiger(bytes.as_slice()).unwrap();
+        let mut written = Vec::new();
+        read.write_aiger(&mut written, false).unwrap();
+        assert_eq!(written, ascii);
+    }
+}
+
+#[test]
+fn test_read_aiger() {
+    // ASCII AND nodes may be listed in any order
+    let toggle = "aag 5 1 1 1 3\n2\n4 10\n4\n10 9 7\n6 2 4\n8 3 5\no0 q\nc\ncomment\n";
+    let aig = Aig::read_aiger(toggle.as_bytes()).unwrap();
+    assert_eq!(aig.num_ands(), 3);
+    assert_eq!(aig.latches()[0].init(), Some(false));
+    assert_eq!(aig.outputs()[0].1.as_deref(), Some("q"));
+
+    // The latch toggles when the input is set
+    let mut state = vec![false];
+    for input in [true, false, true, true] {
+        let (outputs, next) = aig.evaluate(&[input], &state).unwrap();
+        assert_eq!(outputs, state);
+        assert_eq!(next[0], state[0] ^ input);
+        state = next;
+    }
+
+    assert!(Aig::read_aiger("aag 2 1 0 1 1\n2\n4\n4 4 2\n".as_bytes()).is_err());
+    assert!(Aig::read_aiger("aag 1 0 0 1 0\n2\n".as_bytes()).is_err());
+    assert!(Aig::read_aiger("aig 1 0 0 0 0\n".as_bytes()).is_err());
+
+    // Reconvergent fanout is not a cycle
+    let reconvergent = "aag 5 2 0 1 3\n2\n4\n10\n6 2 4\n8 6 2\n10 6 8\n";
+    let aig = Aig::read_aiger(reconvergent.as_bytes()).unwrap();
+    assert_eq!(aig.outputs().len(), 1);
+    assert!(Aig::read_aiger("aag 2 0 0 1 2\n4\n2 4 4\n4 2 2\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_read_aiger_header() {
+    // Counts the file cannot hold are rejected before anything is allocated for them
+    let huge = [
+        "aag 0 0 1000000000000000000 0 0\n",
+        "aag 0 0 0 1000000000000000000 0\n",
+    ];
+    for header in huge {
+        assert!(Aig::read_aiger(header.as_bytes()).is_err());
+    }
+    let overflow = format!("aig {} {} 1 0 {}\n", usize::MAX, usize::MAX, usize::MAX);
+    assert!(Aig::read_aiger(overflow.as_bytes()).is_err());
+    assert!(Aig::read_aiger("aag 1 2 0 0 0\n2\n4\n".as_bytes()).is_err());
+    assert!(Aig::read_aiger("aig 8589934592 8589934592 0 0 0\n".as_bytes()).is_err());
+}
+
+#[test]
+fn test_from_sequential_netlist() {
+    // A flop that toggles when en is set, starting at one
+    let netlist = GateNetlist::new("toggle".to_string());
+    let en = netlist.insert_input("en".into());
+    let clk = netlist.insert_input("clk".into());
+    let dff = Gate::new_logical("DFF".into(), vec!["D".into(), "C".into()], "Q".into());
+    let q = netlist
+        .insert_gate_disconnected(dff, "q_reg".into())
+        .unwrap();
+    clk.connect(q.get_input(1));
+    let next = netlist
+        .insert_gate(
+            gate("XOR", &["A", "B"]),
+            "next".into(),
+            &[en, q.get_output(0)],
+        )
+        .unwrap();
+    next.get_output(0).connect(q.get_input(0));
+    q.set_initial_value(Some(true));
+    q.expose_with_name("q".into());
+
+    let is_dff = |g: &Gate| (g.get_gate_name().to_string() == "DFF").then_some(0);
+    let aig = Aig::from_sequential_netlist(&netlist, is_dff, decompose_gate).unwrap();
+    assert_eq!(aig.latches()[0].name(), Some("q_reg"));
+    assert_eq!(aig.initial_state(), vec![true]);
+
+    let mut state = aig.initial_state();
+    for en in [false, true, true, false] {
+        let (outputs, next) = aig.evaluate(&[en, false], &state).unwrap();
+        assert_eq!(outputs, state);
+        assert_eq!(next[0], state[0] ^ en);
+        state = next;
+    }
+
+    // The flop has no combinational decomposition
+    assert!(Aig::from_gate_netlist(&netlist).is_err());
+}
+
+#[test]
+fn test_input_constraints() {
+    // y = (en0 & a) | (en1 & b), with a test pin that must stay low
+    let netlist = Netlist::new("constrained".to_string());
+    let en0 = netlist.insert_input("en0".into());
+    let en1 = netlist.insert_input("en1".into());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let test = netlist.insert_input("test".into());
+    let x = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "inst_0".into(), &[en0, a])
+        .unwrap();
+    let y = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "inst_1".into(), &[en1, b])
+        .unwrap();
+    let z = netlist
+        .insert_gate(
+            gate("OR", &["A", "B"]),
+            "inst_2".into(),
+            &[x.get_output(0), y.get_output(0)],
+        )
+        .unwrap();
+    netlist
+        .insert_gate(
+            gate("OR", &["A", "B"]),
+            "inst_3".into(),
+            &[z.get_output(0), test],
+        )
+        .unwrap()
+        .expose_with_name("y".into());
+
+    let onehot = InputConstraint::OneHot(vec!["en0".into(), "en1".into()]);
+    assert!(
+        netlist
+            .constrain_inputs(InputConstraint::Constant("y".into(), false))
+            .is_err()
+    );
+    netlist.constrain_inputs(onehot.clone()).unwrap();
+    netlist
+        .constrain_inputs(InputConstraint::Constant("test".into(), false))
+        .unwrap();
+    assert_eq!(netlist.get_input_constraints().len(), 2);
+    assert_eq!(onehot.to_string(), "onehot(en0, en1)");
+
+    let mut aig = Aig::from_gate_netlist(&netlist).unwrap();
+    assert_eq!(aig.constraints().count(), 2);
+    assert!(aig.satisfies(&[true, false, true, false, false]));
+    assert!(!aig.satisfies(&[true, true, true, false, false]));
+    assert!(!aig.satisfies(&[true, false, true, false, true]));
+    let (outputs, _) = aig
+        .evaluate(&[false, true, true, false, false], &[])
+        .unwrap();
+    assert_eq!(outputs, vec![false]);
+    assert!(
+        aig.evaluate(&[false, false, true, true, false], &[])
+            .is_err()
+    );
+
+    // The unknown test pin and the unknown enable are implied by the constraints
+    let (outputs, _) = aig
+        .evaluate_ternary(
+            &[Some(false), None, Some(true), Some(true), None],
+            &[],
+            XMode::Pessimistic,
+        )
+        .unwrap();
+    assert_eq!(outputs, vec![Some(true)]);
+
+    // Known values that already violate a constraint are an error, not a guess
+    let mut values = vec![Some(true), Some(true)];
+    assert!(onehot.imply(&mut values).is_err());
+    assert!(
+        aig.evaluate_ternary(
+            &[Some(false), None, Some(true), Some(true), Some(true)],
+            &[],
+            XMode::Pessimistic,
+        )
+        .is_err()
+    );
+
+    // The constraint literal is true exactly for the allowed input values
+    let lit = aig.constraint_lit();
+    aig.add_output(lit, Some("valid".to_string())).unwrap();
+    let allowed: Vec<bool> = (0..32u32)
+        .map(|bits| aig.satisfies(&(0..5).map(|i| bits >> i & 1 == 1).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(allowed.iter().filter(|a| **a).count(), 8);
+    assert_eq!(aig.clear_constraints().len(), 2);
+    for (bits, allowed) in allowed.into_iter().enumerate() {
+        let inputs: Vec<bool> = (0..5).map(|i| bits >> i & 1 == 1).collect();
+        let (outputs, _) = aig.evaluate(&inputs, &[]).unwrap();
+        assert_eq!(outputs[1], allowed);
+    }
+
+    netlist.clear_input_constraints();
+    let mut free = Aig::from_gate_netlist(&netlist).unwrap();
+    assert_eq!(free.constraint_lit(), Lit::TRUE);
+}
+
+#[test]
+fn test_local_function() {
+    let mut aig = Aig::new();
+    let a = aig.add_input(Some("a".into()));
+    let b = aig.add_input(Some("b".into()));
+    let ab = aig.and(a, b);
+    let y = aig.or(ab, a);
+    aig.add_output(y, Some("y".into())).unwrap();
+
+    // When a is true, y is true whatever the AND is
+    let patterns = aig.exhaustive_patterns().unwrap();
+    let f = aig.local_function(ab, &[a, b], patterns).unwrap();
+    assert_eq!(f.num_leaves(), 2);
+    let values: Vec<_> = (0..4).map(|m| f.value(m)).collect();
+    assert_eq!(values, vec![Some(false), None, Some(false), None]);
+    assert!(f.is_observability_dont_care(1) && !f.is_satisfiability_dont_care(1));
+    assert_eq!(f.dont_cares().collect::<Vec<_>>(), vec![1, 3]);
+    assert!(f.admits(|_| false));
+    assert!(!f.admits(|v| v[1]));
+
+    // Both fanins of the OR are never true together
+    let mut aig = Aig::new();
+    let a = aig.add_input(Some("a".into()));
+    let b = aig.add_input(Some("b".into()));
+    let x = aig.and(a, b);
+    let z = aig.and(a, !b);
+    let w = aig.or(x, z);
+    aig.add_output(w, Some("w".into())).unwrap();
+    let patterns = aig.exhaustive_patterns().unwrap();
+    let f = aig.local_function(w, &[x, z], patterns).unwrap();
+    let values: Vec<_> = (0..4).map(|m| f.value(m)).collect();
+    assert_eq!(values, vec![Some(false), Some(true), Some(true), None]);
+    assert!(f.is_satisfiability_dont_care(3));
+    assert!(f.admits(|v| v[0] ^ v[1]));
+
+    // The leaves must determine the node
+    let patterns = aig.exhaustive_patterns().unwrap();
+    assert!(aig.local_function(x, &[a], patterns).is_err());
+    let leaves = vec![a; 17];
+    assert!(aig.local_function(x, &leaves, std::iter::empty()).is_err());
+    for _ in 0..20 {
+        aig.add_input(None);
+    }
+    assert!(aig.exhaustive_patterns().is_err());
+}
+
+#[test]
+fn test_region_function() {
+    let netlist = Netlist::new("odc".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let ab = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "ab".into(), &[a.clone(), b])
+        .unwrap();
+    netlist
+        .insert_gate(
+            gate("OR", &["A", "B"]),
+            "y".into(),
+            &[ab.get_output(0), a.clone()],
+        )
+        .unwrap()
+        .expose_with_name("y".into());
+    drop(ab);
+
+    assert!(region_function(&netlist, &["nope".into()], decompose_gate).is_err());
+    // The region of both gates drives nothing used outside it but the output
+    let both = ["ab".into(), "y".into()];
+    assert!(region_function(&netlist, &both, decompose_gate).is_ok());
+
+    // The AND only matters when a is false, where it is false, so it can be replaced by a
+    let (leaves, f) = region_function(&netlist, &["ab".into()], decompose_gate).unwrap();
+    assert_eq!(leaves, vec!["a".into(), "b".into()]);
+    assert!(f.admits(|_| false));
+    assert!(f.admits(|v| v[0]));
+    assert!(!f.admits(|v| v[1]));
+    netlist
+        .rewrite_region(&["ab".into()], &AcceptAll, |working| {
+            let old = working.find_instance(&"ab".into()).unwrap();
+            let a = working.inputs().find(|i| i.get_identifier() == "a".into());
+            let buf = working.insert_gate(gate("BUF", &["A"]), "buf".into(), &[a.unwrap()])?;
+            working.replace_net_uses(old, &buf)?;
+            working.clean()?;
+            Ok(())
+        })
+        .unwrap();
+    assert!(netlist.find_instance(&"ab".into()).is_none());
+    let aig = Aig::from_gate_netlist(&netlist).unwrap();
+    for (inputs, state) in aig.exhaustive_patterns().unwrap() {
+        let (outputs, _) = aig.evaluate(&inputs, &state).unwrap();
+        assert_eq!(outputs, vec![inputs[0]]);
+    }
+
+    // A region with two nets used outside it has no single function
+    let c = netlist.insert_input("c".into());
+    let and = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "and".into(), &[a, c])
+        .unwrap();
+    netlist
+        .insert_gate(gate("INV", &["A"]), "inv".into(), &[and.get_output(0)])
+        .unwrap()
+        .expose_with_name("q".into());
+    and.expose_with_name("p".into());
+    assert!(region_function(&netlist, &["and".into(), "inv".into()], decompose_gate).is_err());
+}
@@ -1,12 +1,23 @@
-use safety_net::attribute::dont_touch_filter;
-use safety_net::circuit::Net;
+use safety_net::attribute::{dont_touch_filter, namespace_filter};
+use safety_net::circuit::{Net, SequentialElement};
+use safety_net::diff::{Change, diff};
 use safety_net::format_id;
 use safety_net::graph::FanOutTable;
+use safety_net::graph::PathAnalysis;
+use safety_net::graph::SequentialCombDepth;
 use safety_net::graph::SimpleCombDepth;
+use safety_net::netlist::DrivenNet;
 use safety_net::netlist::Gate;
 use safety_net::netlist::GateNetlist;
 use safety_net::netlist::Netlist;
 use safety_net::netlist::iter::DFSIterator;
+use safety_net::persist::{AnalysisCache, content_hash};
+use safety_net::recognize::{find_clock_gates, find_decoders, find_mux_trees};
+use safety_net::report::{
+    PinLimits, distribution_tree, erc_report, register_paths, switching_report, tie_off_report,
+    unused_report,
+};
+use safety_net::supply::Supply;
 use std::rc::Rc;
 
 fn and_gate() -> Gate {
@@ -39,7 +50,7 @@ fn test_detect_cycles() {
     let input = netlist.inputs().next().unwrap();
     let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
     let inverted = netlist
-        .insert_gate(inverter, "inst_0".into(), &[input.clone()])
+        .insert_gate(inverter, "inst_0".into(), std::slice::from_ref(&input))
         .unwrap();
     assert!(netlist.replace_net_uses(input.unwrap(), &inverted).is_ok());
 
@@ -92,6 +103,36 @@ fn test_attr_filter() {
     assert_eq!(filter.keys().len(), 1)
 }
 
+#[test]
+fn test_namespace_filter() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    let inst_0 = netlist.last().unwrap();
+
+    // Two flows set the same local key without colliding
+    inst_0.insert_attribute("xilinx.LOC".into(), "SLICE_X0Y0".into());
+    inst_0.insert_attribute("sn.level".into(), "1".into());
+    inst_0.insert_attribute("sn.opt.keep".into(), "true".into());
+    a.insert_attribute("sn.level".into(), "0".into());
+    assert_eq!(
+        inst_0.get_namespaced_attribute("xilinx", "LOC"),
+        Some(Some("SLICE_X0Y0".to_string()))
+    );
+    assert_eq!(inst_0.get_namespaced_attribute("sn", "LOC"), None);
+    assert_eq!(inst_0.attributes_in("sn").count(), 2);
+    assert_eq!(inst_0.attributes_in("sn.opt").count(), 1);
+
+    let filter = namespace_filter(&netlist, "sn");
+    assert!(filter.has(&a) && filter.has(&inst_0));
+    assert_eq!(filter.keys(), ["sn.level", "sn.opt.keep"]);
+    let filter = namespace_filter(&netlist, "xilinx");
+    assert!(!filter.has(&a) && filter.has(&inst_0));
+
+    assert_eq!(inst_0.clear_namespace("sn"), 2);
+    let keys: Vec<String> = inst_0.attributes().map(|a| a.key().clone()).collect();
+    assert_eq!(keys, vec!["xilinx.LOC"]);
+}
+
 #[cfg(feature = "graph")]
 #[test]
 fn test_petgraph() {
@@ -119,6 +160,116 @@ fn test_comb_depth() {
 
     assert_eq!(depth_info.get_comb_depth(&gate), Some(1));
     assert_eq!(depth_info.get_max_depth(), 1);
+
+    let path = depth_info.get_critical_path().unwrap();
+    assert_eq!(path.len(), 2);
+    assert_eq!(*path.endpoint(), gate);
+    assert!(path.startpoint().is_an_input());
+    assert_eq!(path.connections()[0].target().unwrap(), gate);
+}
+
+#[test]
+fn test_sequential_comb_depth() {
+    // A one-bit counter with an enable: q <= q ^ en
+    let netlist = Netlist::new("counter".to_string());
+    let clk = netlist.insert_input("clk".into());
+    let en = netlist.insert_input("en".into());
+    let dffe = Gate::new_logical(
+        "DFFE".into(),
+        vec!["D".into(), "C".into(), "E".into()],
+        "Q".into(),
+    );
+    assert!(dffe.is_register() && !dffe.is_latch());
+    assert_eq!(dffe.get_data_input(), Some(0));
+    assert_eq!(dffe.get_clock_input(), Some(1));
+    assert_eq!(dffe.get_enable_input(), Some(2));
+    assert_eq!(dffe.get_reset_input(), None);
+    assert!(dffe.is_control_input(1) && !dffe.is_control_input(0));
+    assert!(!and_gate().is_register());
+    assert_eq!(and_gate().get_data_input(), None);
+
+    let reg = netlist
+        .insert_gate_disconnected(dffe, "q_reg".into())
+        .unwrap();
+    let xor = Gate::new_logical("XOR".into(), vec!["A".into(), "B".into()], "Y".into());
+    let xor = netlist
+        .insert_gate(xor, "next".into(), &[reg.get_output(0), en.clone()])
+        .unwrap();
+    xor.get_output(0).connect(reg.get_input(0));
+    clk.connect(reg.get_input(1));
+    en.connect(reg.get_input(2));
+    reg.clone().expose_with_name("q".into());
+    assert!(netlist.verify().is_ok());
+
+    assert!(reg.is_register() && !xor.is_register());
+    assert_eq!(reg.get_data().unwrap().unwrap(), xor);
+    assert_eq!(reg.get_clock().unwrap().as_net().to_string(), "clk");
+    assert_eq!(reg.get_enable().unwrap().as_net().to_string(), "en");
+    assert!(reg.get_reset().is_none());
+
+    // The loop goes through the register, so it is not a combinational cycle
+    let depth_info = netlist.get_analysis::<SequentialCombDepth<_>>().unwrap();
+    assert_eq!(depth_info.get_comb_depth(&reg), Some(0));
+    assert_eq!(depth_info.get_comb_depth(&xor), Some(1));
+    assert_eq!(depth_info.get_register_input_depth(&reg), Some(1));
+    assert_eq!(depth_info.get_max_depth(), 1);
+
+    // Paths start at registers
+    let path = depth_info.get_critical_path().unwrap();
+    assert_eq!(path.to_string(), "q_reg -> next");
+    assert_eq!(depth_info.get_critical_path_to(&reg).unwrap().len(), 1);
+}
+
+#[test]
+fn test_analysis_cache() {
+    let netlist = get_simple_example();
+    let inst = netlist.last().unwrap();
+    assert_eq!(content_hash(&netlist), content_hash(&get_simple_example()));
+
+    let mut cache = AnalysisCache::new(&netlist);
+    let depth: SimpleCombDepth<_> = cache.get_analysis(&netlist).unwrap();
+    assert_eq!(depth.get_comb_depth(&inst), Some(1));
+    cache.store("signature", [(inst.clone(), 0xbeefu64)]);
+    assert_eq!(
+        cache.keys().collect::<Vec<_>>(),
+        vec!["comb_depth", "signature"]
+    );
+
+    // Results survive a round trip through text, and are reloaded instead of recomputed
+    let mut text = Vec::new();
+    cache.write(&mut text).unwrap();
+    let text = String::from_utf8(text)
+        .unwrap()
+        .replace("inst_0\t1", "inst_0\t7");
+    let mut cache = AnalysisCache::read(text.as_bytes()).unwrap();
+    assert!(cache.is_valid_for(&netlist));
+    let depth: SimpleCombDepth<_> = cache.get_analysis(&netlist).unwrap();
+    assert_eq!(depth.get_comb_depth(&inst), Some(7));
+    assert_eq!(depth.get_max_depth(), 7);
+    let signatures = cache
+        .load::<_, u64>(&netlist, "signature")
+        .unwrap()
+        .unwrap();
+    assert_eq!(signatures[&inst], 0xbeef);
+    assert!(cache.load::<_, u64>(&netlist, "levels").unwrap().is_none());
+
+    // Editing the netlist makes the results stale
+    let a = netlist.first().unwrap();
+    netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[a.clone().into(), a.into()])
+        .unwrap();
+    assert!(!cache.is_valid_for(&netlist));
+    assert!(
+        cache
+            .load::<_, u64>(&netlist, "signature")
+            .unwrap()
+            .is_none()
+    );
+    let depth: SimpleCombDepth<_> = cache.get_analysis(&netlist).unwrap();
+    assert_eq!(depth.get_comb_depth(&inst), Some(1));
+    assert_eq!(cache.keys().collect::<Vec<_>>(), vec!["comb_depth"]);
+
+    assert!(AnalysisCache::read("table x 1\n".as_bytes()).is_err());
 }
 
 #[test]
@@ -131,3 +282,577 @@ fn test_fanout_table() {
     // Outputs don't have users that are nodes
     assert_eq!(fanout_table.get_node_users(&gate).count(), 0);
 }
+
+#[test]
+fn test_paths_between() {
+    let netlist = GateNetlist::new("paths".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let and_0 = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.clone(), b.clone()])
+        .unwrap();
+    let and_1 = netlist
+        .insert_gate(
+            and_gate(),
+            "inst_1".into(),
+            &[and_0.get_output(0), b.clone()],
+        )
+        .unwrap();
+    let and_2 = netlist
+        .insert_gate(
+            and_gate(),
+            "inst_2".into(),
+            &[and_1.get_output(0), a.clone()],
+        )
+        .unwrap();
+    let and_2 = and_2.expose_with_name("y".into());
+
+    let analysis = netlist.get_analysis::<PathAnalysis<_>>().unwrap();
+    let a = a.unwrap();
+    let paths = analysis.paths_between(&a, &and_2);
+    assert_eq!(paths.shortest(), Some(1));
+    assert_eq!(paths.longest(), Some(3));
+    assert_eq!(paths.nodes().count(), 4);
+    let all = paths.enumerate(10);
+    assert_eq!(all.len(), 2);
+    assert!(all.contains(&vec![
+        a.clone(),
+        and_0.clone(),
+        and_1.clone(),
+        and_2.clone()
+    ]));
+    assert!(all.contains(&vec![a.clone(), and_2.clone()]));
+    assert_eq!(paths.enumerate(1).len(), 1);
+
+    // There is no path against the direction of the connections
+    let back = analysis.paths_between(&and_1, &and_0);
+    assert!(!back.exists());
+    assert!(back.enumerate(10).is_empty());
+    let from_b = analysis.paths_between(&b.unwrap(), &and_2);
+    assert_eq!((from_b.shortest(), from_b.longest()), (Some(2), Some(3)));
+}
+
+fn mux_gate() -> Gate {
+    Gate::new_logical(
+        "MUX2".into(),
+        vec!["A".into(), "B".into(), "S".into()],
+        "Y".into(),
+    )
+}
+
+fn inv_gate() -> Gate {
+    Gate::new_logical("INV".into(), vec!["I".into()], "O".into())
+}
+
+fn mux_select(g: &Gate) -> Option<usize> {
+    (g.get_gate_name().to_string() == "MUX2").then_some(2)
+}
+
+#[test]
+fn test_mux_tree_recognition() {
+    let netlist = GateNetlist::new("mux4".to_string());
+    let [a, b, c, d, s0, s1] =
+        ["a", "b", "c", "d", "s0", "s1"].map(|n| netlist.insert_input(n.into()));
+    let m0 = netlist
+        .insert_gate(mux_gate(), "m0".into(), &[a, b, s0.clone()])
+        .unwrap();
+    let m1 = netlist
+        .insert_gate(mux_gate(), "m1".into(), &[c, d, s0])
+        .unwrap();
+    let m2 = netlist
+        .insert_gate(mux_gate(), "m2".into(), &[m0.into(), m1.into(), s1])
+        .unwrap();
+    m2.expose_with_name("y".into());
+
+    let trees = find_mux_trees(&netlist, mux_select);
+    assert_eq!(trees.len(), 1);
+    let tree = &trees[0];
+    assert_eq!(tree.root().get_instance_name(), Some("m2".into()));
+    assert_eq!(tree.muxes().len(), 3);
+    assert_eq!(tree.leaves().len(), 4);
+    assert_eq!(tree.selects().len(), 2);
+    assert_eq!(tree.depth(), 2);
+    assert_eq!(
+        tree.to_string(),
+        "mux tree m2: 3 muxes, 4 leaves, 2 selects, depth 2"
+    );
+    tree.tag();
+    assert_eq!(
+        netlist
+            .find_instance(&"m0".into())
+            .unwrap()
+            .attributes()
+            .next()
+            .unwrap()
+            .value()
+            .as_deref(),
+        Some("m2")
+    );
+}
+
+#[test]
+fn test_decoder_recognition() {
+    let netlist = GateNetlist::new("dec2".to_string());
+    let s0 = netlist.insert_input("s0".into());
+    let s1 = netlist.insert_input("s1".into());
+    let n0: DrivenNet<Gate> = netlist
+        .insert_gate(inv_gate(), "n0".into(), std::slice::from_ref(&s0))
+        .unwrap()
+        .into();
+    let n1: DrivenNet<Gate> = netlist
+        .insert_gate(inv_gate(), "n1".into(), std::slice::from_ref(&s1))
+        .unwrap()
+        .into();
+    let terms = [
+        [n0.clone(), n1.clone()],
+        [s0.clone(), n1],
+        [n0, s1.clone()],
+        [s0, s1],
+    ];
+    for (i, ops) in terms.iter().enumerate() {
+        netlist
+            .insert_gate(and_gate(), format_id!("y{i}"), ops)
+            .unwrap()
+            .expose_with_name(format_id!("o{i}"));
+    }
+
+    let decoders = find_decoders(
+        &netlist,
+        |g| g.get_gate_name().to_string() == "AND",
+        |g| g.get_gate_name().to_string() == "INV",
+    );
+    assert_eq!(decoders.len(), 1);
+    let decoder = &decoders[0];
+    assert!(decoder.is_complete());
+    assert_eq!(decoder.to_string(), "decoder of (s0, s1): 4 of 4 outputs");
+    let (first, polarity) = &decoder.outputs()[0];
+    assert_eq!(first.get_instance_name(), Some("y0".into()));
+    assert_eq!(*polarity, vec![false, false]);
+}
+
+#[test]
+fn test_find_clock_gates() {
+    let netlist = GateNetlist::new("gated".to_string());
+    let clk = netlist.insert_input("clk".into());
+    let en0 = netlist.insert_input("en0".into());
+    let en1 = netlist.insert_input("en1".into());
+    let d = netlist.insert_input("d".into());
+    let latch_gate = Gate::new_logical("DLATCH".into(), vec!["D".into(), "G".into()], "Q".into());
+
+    // An integrated clock gate holds en0 in a latch while the clock is high
+    let nclk: DrivenNet<Gate> = netlist
+        .insert_gate(inv_gate(), "nclk".into(), std::slice::from_ref(&clk))
+        .unwrap()
+        .into();
+    let held: DrivenNet<Gate> = netlist
+        .insert_gate(latch_gate, "held".into(), &[en0.clone(), nclk])
+        .unwrap()
+        .into();
+    let icg: DrivenNet<Gate> = netlist
+        .insert_gate(and_gate(), "icg".into(), &[held, clk.clone()])
+        .unwrap()
+        .into();
+    let plain: DrivenNet<Gate> = netlist
+        .insert_gate(and_gate(), "plain".into(), &[clk.clone(), en1.clone()])
+        .unwrap()
+        .into();
+    let odd: DrivenNet<Gate> = netlist
+        .insert_gate(and_gate(), "odd".into(), &[en0, en1])
+        .unwrap()
+        .into();
+    for (i, c) in [icg.clone(), icg, plain, clk, odd].into_iter().enumerate() {
+        netlist
+            .insert_gate(dff_gate(), format_id!("r{i}"), &[d.clone(), c])
+            .unwrap()
+            .expose_with_name(format_id!("q{i}"));
+    }
+
+    let gates = find_clock_gates(
+        &netlist,
+        |g| g.get_gate_name().to_string() == "AND",
+        |g| g.get_gate_name().to_string() == "INV",
+    );
+    let reports: Vec<String> = gates.iter().map(|g| g.to_string()).collect();
+    assert_eq!(
+        reports,
+        vec![
+            "latch-based clock gate icg: clk gated by (en0), 2 registers",
+            "clock gate plain: clk gated by (en1), 1 registers",
+            "clock gate odd: unknown clock, 1 registers",
+        ]
+    );
+    let icg = &gates[0];
+    assert_eq!(
+        icg.latch().unwrap().get_instance_name(),
+        Some("held".into())
+    );
+    assert!(!gates[1].is_latch_based());
+    assert!(gates[2].enables().is_empty());
+
+    icg.tag();
+    let r1 = netlist.find_instance(&"r1".into()).unwrap();
+    assert!(r1.attributes().any(|a| a.key() == "clock_gate"));
+}
+
+fn dff_gate() -> Gate {
+    Gate::new_logical("DFF".into(), vec!["D".into(), "CLK".into()], "Q".into())
+}
+
+fn buf_gate() -> Gate {
+    Gate::new_logical("BUF".into(), vec!["I".into()], "O".into())
+}
+
+#[test]
+fn test_clock_tree_report() {
+    let netlist = GateNetlist::new("clocks".to_string());
+    let clk = netlist.insert_input("clk".into());
+    let en = netlist.insert_input("en".into());
+    let d = netlist.insert_input("d".into());
+
+    let b0: DrivenNet<Gate> = netlist
+        .insert_gate(buf_gate(), "b0".into(), std::slice::from_ref(&clk))
+        .unwrap()
+        .into();
+    let gclk: DrivenNet<Gate> = netlist
+        .insert_gate(and_gate(), "icg".into(), &[clk.clone(), en])
+        .unwrap()
+        .into();
+    let b1: DrivenNet<Gate> = netlist
+        .insert_gate(buf_gate(), "b1".into(), std::slice::from_ref(&b0))
+        .unwrap()
+        .into();
+    for (i, c) in [b0, b1.clone(), b1, gclk].into_iter().enumerate() {
+        netlist
+            .insert_gate(dff_gate(), format_id!("r{i}"), &[d.clone(), c])
+            .unwrap()
+            .expose_with_name(format_id!("q{i}"));
+    }
+
+    let tree = distribution_tree(
+        &netlist,
+        &clk,
+        |g| g.get_gate_name().to_string() == "BUF",
+        |g| g.get_gate_name().to_string() == "DFF",
+    );
+    assert_eq!(tree.depth(), 2);
+    assert_eq!(tree.fanout(), &[2, 2, 2]);
+    assert_eq!(tree.leaves().len(), 4);
+    assert_eq!(tree.gated().len(), 1);
+    assert_eq!(tree.gated()[0].get_instance_name(), Some("r3".into()));
+    assert_eq!(
+        tree.to_string(),
+        "tree clk:
+  level 0: fanout 2, buffers 1
+  level 1: fanout 2, buffers 1
+  level 2: fanout 2, buffers 0
+  leaves: 4
+  through logic: DFF(r3)
+"
+    );
+}
+
+#[test]
+fn test_switching_report() {
+    let netlist = get_simple_example();
+    let a = netlist.inputs().next().unwrap();
+    let _ = netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[a.clone(), a])
+        .unwrap();
+
+    // a toggles less often than b, but it has three loads
+    let report = switching_report(
+        &netlist,
+        |net| {
+            if net.get_identifier() == "a".into() {
+                0.3
+            } else {
+                0.5
+            }
+        },
+        |port| if port.get_position() == 0 { 1.0 } else { 2.0 },
+    );
+    assert_eq!(report.len(), 4);
+    assert_eq!(report[0].net().get_identifier(), "a".into());
+    assert_eq!(report[0].fanout(), 3);
+    assert_eq!(report[0].load(), 4.0);
+    assert_eq!(report[1].net().get_identifier(), "b".into());
+    assert_eq!(report[1].energy(), 1.0);
+    assert_eq!(
+        report[0].to_string(),
+        "a: activity 0.300, fanout 3, load 4.000, energy 1.200"
+    );
+    assert!(report[2..].iter().all(|n| n.energy() == 0.0));
+}
+
+#[test]
+fn test_unused_report() {
+    let netlist = get_simple_example();
+    assert!(unused_report(&netlist).is_empty());
+
+    let a = netlist.inputs().next().unwrap();
+    let _ = netlist.insert_input("c".into());
+    let _ = netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[a.clone(), a])
+        .unwrap();
+
+    let report = unused_report(&netlist);
+    assert_eq!(report.inputs().len(), 1);
+    assert_eq!(report.inputs()[0].get_identifier(), "c".into());
+    assert_eq!(report.outputs().len(), 1);
+    assert_eq!(
+        report.to_string(),
+        "unused input: c\nunused output: inst_1/Y (inst_1_Y)\n"
+    );
+
+    // Cleaning removes the dead instance, but not the unused input
+    netlist.clean().unwrap();
+    let report = unused_report(&netlist);
+    assert_eq!(report.inputs().len(), 1);
+    assert!(report.outputs().is_empty());
+}
+
+#[test]
+fn test_erc_report() {
+    let netlist = get_simple_example();
+    let a = netlist.inputs().next().unwrap();
+    for i in 1..5 {
+        netlist
+            .insert_gate(
+                and_gate(),
+                format!("inst_{i}").into(),
+                &[a.clone(), a.clone()],
+            )
+            .unwrap()
+            .expose_with_name(format!("y{i}").into());
+    }
+
+    // a drives nine input pins, and pin B loads more than pin A
+    let limits = |_: &DrivenNet<Gate>| PinLimits {
+        max_capacitance: Some(8.0),
+        max_fanout: Some(4),
+    };
+    let load = |p: &safety_net::netlist::InputPort<Gate>| (p.get_position() + 1) as f64;
+    let report = erc_report(&netlist, limits, load);
+    assert_eq!(report.len(), 1);
+    let violation = &report[0];
+    assert_eq!(violation.net().get_identifier(), "a".into());
+    assert_eq!(violation.fanout(), 9);
+    assert_eq!(violation.capacitance(), 13.0);
+    assert!(violation.exceeds_fanout() && violation.exceeds_capacitance());
+    assert_eq!(
+        violation.to_string(),
+        "a: fanout 9 > 4 capacitance 13.000 > 8.000"
+    );
+
+    // Buffering the groups clears the violation
+    let groups = violation.load_groups();
+    assert!(groups.iter().all(|g| g.len() <= 4));
+    let named = groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, g)| (format!("buf_{i}").into(), g))
+        .collect();
+    let buffers = a.split_loads(buf_gate(), named).unwrap();
+    assert_eq!(buffers.len(), 3);
+    assert!(erc_report(&netlist, limits, load).is_empty());
+}
+
+#[test]
+fn test_tie_off_report() {
+    let netlist = get_simple_example();
+    let a = netlist.inputs().next().unwrap();
+    let tie = |v: u8| Gate::new_logical(format!("TIE{v}").as_str().into(), vec![], "Y".into());
+    let zero = netlist.insert_gate(tie(0), "tie_0".into(), &[]).unwrap();
+    let one = netlist.insert_gate(tie(1), "tie_1".into(), &[]).unwrap();
+    let vdd = netlist.insert_input("vdd".into());
+    vdd.clone().unwrap().set_supply(Some(Supply::Power));
+    let one_alias = netlist
+        .insert_alias(one.get_output(0), "one".into())
+        .unwrap();
+
+    // Configuration pins of a few AND gates, through a tie cell, an alias of one and a supply
+    let ands = [
+        ("cfg_0", [a.clone(), zero.get_output(0)]),
+        ("cfg_1", [one_alias, a.clone()]),
+        ("cfg_2", [vdd, zero.get_output(0)]),
+    ];
+    for (name, operands) in ands {
+        netlist
+            .insert_gate(and_gate(), name.into(), &operands)
+            .unwrap()
+            .expose_with_name(format!("{name}_y").as_str().into());
+    }
+
+    let constant = |g: &Gate| match g.get_gate_name().to_string().as_str() {
+        "TIE0" => Some(false),
+        "TIE1" => Some(true),
+        _ => None,
+    };
+    let report = tie_off_report(&netlist, constant);
+    assert_eq!(report.len(), 2);
+    assert_eq!(report[0].pin(), &"A".into());
+    assert_eq!(report[0].count(true), 2);
+    assert_eq!(report[1].cell(), &"AND".into());
+    assert_eq!(report[1].pin(), &"B".into());
+    assert_eq!(report[1].count(false), 2);
+    assert_eq!(
+        report[1].to_string(),
+        "AND/B: 2 tied to 0, 0 tied to 1\n  cfg_0 = 0\n  cfg_2 = 0"
+    );
+}
+
+#[test]
+fn test_register_paths() {
+    let netlist = GateNetlist::new("pipeline".to_string());
+    let clk = netlist.insert_input("clk".into());
+    let d = netlist.insert_input("d".into());
+    let inv = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+
+    // Two registers in stage a feed one in stage b through an AND, which feeds stage c through two inverters
+    let a: Vec<DrivenNet<Gate>> = (0..2)
+        .map(|i| {
+            netlist
+                .insert_gate(dff_gate(), format_id!("a_{i}"), &[d.clone(), clk.clone()])
+                .unwrap()
+                .into()
+        })
+        .collect();
+    let and = netlist.insert_gate(and_gate(), "and_0".into(), &a).unwrap();
+    let b = netlist
+        .insert_gate(dff_gate(), "b_0".into(), &[and.into(), clk.clone()])
+        .unwrap();
+    let inv_0 = netlist
+        .insert_gate(inv.clone(), "inv_0".into(), &[b.get_output(0)])
+        .unwrap();
+    let inv_1 = netlist
+        .insert_gate(inv, "inv_1".into(), &[inv_0.into()])
+        .unwrap();
+    let c = netlist
+        .insert_gate(dff_gate(), "c_0".into(), &[inv_1.into(), clk])
+        .unwrap();
+    let c = c.expose_with_name("q".into());
+
+    let groups = register_paths(
+        &netlist,
+        |g| g.get_gate_name().to_string() == "DFF",
+        |r| {
+            let name = r.get_instance_name().unwrap().to_string();
+            name.split('_').next().unwrap().to_string()
+        },
+    )
+    .unwrap();
+    let lines: Vec<String> = groups.iter().map(|g| g.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec!["a -> b: 2 paths, depth 1", "b -> c: 1 paths, depth 2"]
+    );
+    assert_eq!(groups[1].pairs()[0].1, c);
+}
+
+#[test]
+fn test_diff_overlay() {
+    let left = get_simple_example();
+    assert!(diff(&left, &left).is_empty());
+
+    // Swap the AND gate for an OR gate and add an inverter on a new output
+    let right = Netlist::new("example".to_string());
+    let a = right.insert_input("a".into());
+    let b = right.insert_input("b".into());
+    let or_gate = Gate::new_logical("OR".into(), vec!["A".into(), "B".into()], "Y".into());
+    right
+        .insert_gate(or_gate, "inst_0".into(), &[a.clone(), b])
+        .unwrap()
+        .expose_with_name("y".into());
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    right
+        .insert_gate(inverter, "inst_1".into(), &[a])
+        .unwrap()
+        .expose_with_name("z".into());
+
+    let changes = diff(&left, &right);
+    assert!(!changes.is_empty());
+    let names = |change| {
+        changes
+            .with_change(change)
+            .map(|n| n.get_name().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(Change::Unchanged), vec!["a", "b"]);
+    assert_eq!(names(Change::Changed), vec!["inst_0"]);
+    assert_eq!(names(Change::Added), vec!["inst_1"]);
+    assert!(names(Change::Removed).is_empty());
+    assert_eq!(
+        changes.to_string(),
+        "changed: inst_0 (AND -> OR)\nadded: inst_1 (INV)\nadded: output z\n"
+    );
+
+    let dot = changes.to_dot();
+    assert!(dot.starts_with("digraph diff {"));
+    assert!(dot.contains("\"inst_0\" [label=\"inst_0\\nAND -> OR\" fillcolor=lightgoldenrod"));
+    assert!(dot.contains("\"a\" -> \"inst_1\" [label=\"I\" color=green];"));
+    assert!(dot.contains("\"b\" -> \"inst_0\" [label=\"B\"];"));
+    assert!(dot.contains("\"output z\" [shape=oval fillcolor=palegreen];"));
+
+    // The other way around, the inverter is removed
+    let changes = diff(&right, &left);
+    assert_eq!(
+        changes
+            .with_change(Change::Removed)
+            .map(|n| n.get_name().to_string())
+            .collect::<Vec<_>>(),
+        vec!["inst_1"]
+    );
+}
+
+#[test]
+fn test_freeze() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let netlist = get_simple_example();
+    let and = netlist.last().unwrap();
+    netlist
+        .insert_gate(
+            and_gate(),
+            "inst_1".into(),
+            &[and.get_output(0), and.get_output(0)],
+        )
+        .unwrap()
+        .expose_with_name("z".into());
+    let frozen = netlist.freeze();
+    assert_send_sync(&frozen);
+
+    // Edits after freezing do not show up in the copy
+    netlist.insert_input("c".into());
+    assert_eq!(frozen.len(), 4);
+    assert_eq!(frozen.inputs().count(), 2);
+
+    let inst_0 = frozen.find_instance(&"inst_0".into()).unwrap();
+    let out = frozen.outputs()[0].0;
+    assert_eq!(out.node(), inst_0);
+    assert_eq!(*frozen.net(out).get_identifier(), "inst_0_Y".into());
+    assert_eq!(frozen.fanout(out), 2);
+    assert_eq!(frozen.users(inst_0), &[(3, 0), (3, 1)]);
+    assert_eq!(frozen.ports().len(), 4);
+
+    // Each thread counts the instances of a type
+    let counts: Vec<usize> = std::thread::scope(|scope| {
+        let handles: Vec<_> = ["AND", "OR"]
+            .into_iter()
+            .map(|name| {
+                let frozen = &frozen;
+                scope.spawn(move || {
+                    frozen
+                        .objects()
+                        .iter()
+                        .filter(|o| {
+                            o.get_instance_type()
+                                .is_some_and(|t| t.get_gate_name().to_string() == name)
+                        })
+                        .count()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(counts, vec![2, 0]);
+}
@@ -1,10 +1,16 @@
 use safety_net::assert_verilog_eq;
+use safety_net::circuit::Identifier;
+use safety_net::circuit::Instantiable;
 use safety_net::circuit::Net;
+use safety_net::circuit::PortDirection;
 use safety_net::format_id;
 use safety_net::netlist::DrivenNet;
 use safety_net::netlist::Gate;
 use safety_net::netlist::GateNetlist;
+use safety_net::netlist::Mismatch;
 use safety_net::netlist::Netlist;
+use safety_net::netlist::NetlistError;
+use safety_net::netlist::PathTarget;
 use std::rc::Rc;
 
 fn and_gate() -> Gate {
@@ -28,7 +34,7 @@ fn ripple_adder() -> Rc<GateNetlist> {
     let b = netlist.insert_input_escaped_logic_bus("b".to_string(), bitwidth);
     let mut carry: DrivenNet<Gate> = netlist.insert_input("cin".into());
 
-    for (i, (a, b)) in a.into_iter().zip(b.into_iter()).enumerate() {
+    for (i, (a, b)) in a.into_iter().zip(b).enumerate() {
         // Instantiate a full adder for each bit
         let fa = netlist
             .insert_gate(full_adder(), format_id!("fa_{i}"), &[carry, a, b])
@@ -140,6 +146,84 @@ fn test_unlinked_2() {
     last_fa.expose_with_name("no".into());
 }
 
+#[test]
+fn test_panic_free() {
+    let netlist = ripple_adder();
+    let last_fa = netlist.last().unwrap();
+    assert!(matches!(
+        last_fa.try_as_net(),
+        Err(NetlistError::MultiOutput(_))
+    ));
+    assert!(last_fa.try_get_identifier().is_err());
+    assert!(matches!(
+        last_fa.try_get_input(3),
+        Err(NetlistError::OutOfBounds {
+            index: 3,
+            len: 3,
+            ..
+        })
+    ));
+    assert!(last_fa.try_get_output(2).is_err());
+    assert_eq!(last_fa.try_get_output(1).unwrap().get_position(), 1);
+    assert!(last_fa.clone().try_expose_with_name("no".into()).is_err());
+
+    let a = netlist.first().unwrap();
+    assert!(matches!(
+        a.try_get_input(0),
+        Err(NetlistError::NotAnInstance(_))
+    ));
+    assert!(a.try_set_instance_name("no".into()).is_err());
+    assert!(a.try_get_path().is_ok());
+
+    let netlist = ripple_adder().reclaim().unwrap();
+    let last_fa = netlist.last().unwrap();
+    assert!(matches!(
+        last_fa.try_get_driver(0),
+        Err(NetlistError::Unlinked)
+    ));
+    assert!(matches!(
+        last_fa.get_input(0).try_get_driver(),
+        Err(NetlistError::Unlinked)
+    ));
+    assert!(last_fa.try_get_netlist().is_err());
+    assert!(last_fa.try_get_path().is_err());
+    assert!(!last_fa.drives_a_top_output());
+    assert!(!last_fa.get_output(0).is_top_level_output());
+    assert!(matches!(
+        last_fa.clone().delete_uses(),
+        Err(NetlistError::Unlinked)
+    ));
+    assert!(
+        last_fa
+            .get_output(0)
+            .try_expose_with_name("no".into())
+            .is_err()
+    );
+}
+
+#[test]
+fn test_panic_free_misuse() {
+    let netlist = ripple_adder();
+    let last_fa = netlist.last().unwrap();
+    assert!(matches!(
+        last_fa.try_set_supply(None),
+        Err(NetlistError::MultiOutput(_))
+    ));
+    let fa = last_fa.get_instance_type().unwrap().clone();
+    assert!(fa.try_get_input_port(3).is_none());
+    assert!(fa.try_get_output_port(1).is_some());
+    let inputs: Vec<_> = netlist.inputs().take(2).collect();
+    let err = netlist
+        .insert_gate(and_gate(), Identifier::new("\\and[0]".to_string()), &inputs)
+        .unwrap_err();
+    assert!(matches!(err, NetlistError::EscapedName(_)));
+    assert!(
+        netlist
+            .insert_gate_disconnected(and_gate(), Identifier::new("\\and[0]".to_string()))
+            .is_err()
+    );
+}
+
 #[test]
 fn test_get_net_from_obj() {
     let netlist = get_simple_example();
@@ -195,6 +279,145 @@ fn test_change_gate_correct() {
     );
 }
 
+#[test]
+fn test_change_gate_closure() {
+    let netlist = get_simple_example();
+    let gate = netlist.last().unwrap();
+    assert!(
+        gate.with_instance_type_mut(|t| t.set_gate_name("OR".into()))
+            .is_some()
+    );
+    // The borrow has already ended
+    assert!(netlist.to_string().contains("OR inst_0"));
+
+    let input = netlist.first().unwrap();
+    assert!(
+        input
+            .with_instance_type_mut(|t| t.set_gate_name("OR".into()))
+            .is_none()
+    );
+}
+
+#[test]
+fn test_marks() {
+    let netlist = ripple_adder();
+    let gate = netlist.last().unwrap();
+    assert!(!gate.is_marked());
+    assert!(gate.mark());
+    assert!(!gate.mark());
+    assert!(gate.is_marked());
+
+    // Count the nodes in the fan-in cone with a worklist
+    let mut stack = vec![gate.clone()];
+    let mut count = 0;
+    netlist.clear_marks();
+    while let Some(node) = stack.pop() {
+        if node.mark() {
+            count += 1;
+            stack.extend(node.drivers().flatten());
+        }
+    }
+    assert_eq!(count, netlist.objects().count());
+
+    netlist.clear_marks();
+    assert!(netlist.objects().all(|o| !o.is_marked()));
+    gate.mark();
+    gate.unmark();
+    assert!(!gate.is_marked());
+}
+
+#[test]
+fn test_expose_all_outputs() {
+    let netlist = Netlist::new("adder".to_string());
+    let inputs: Vec<DrivenNet<Gate>> = ["cin", "a", "b"]
+        .into_iter()
+        .map(|n| netlist.insert_input(n.into()))
+        .collect();
+    let fa = netlist
+        .insert_gate(full_adder(), "fa_0".into(), &inputs)
+        .unwrap();
+    let outputs = fa.expose_all_outputs("sum");
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs.iter().all(|o| o.is_top_level_output()));
+    let ports: Vec<String> = netlist
+        .get_output_ports()
+        .iter()
+        .map(|n| n.to_string())
+        .collect();
+    assert_eq!(ports, vec!["sum_S", "sum_COUT"]);
+    assert!(netlist.verify().is_ok());
+}
+
+#[test]
+fn test_rename_port() {
+    let netlist = ripple_adder();
+    let fa: Identifier = "FA".into();
+
+    // Colliding names are rejected without changing any instance
+    assert!(netlist.rename_port(&fa, &"CIN".into(), "A".into()).is_err());
+    assert!(netlist.rename_port(&fa, &"CI".into(), "C".into()).is_err());
+    assert!(netlist.to_string().contains(".CIN("));
+
+    assert_eq!(netlist.rename_port(&fa, &"CIN".into(), "CI".into()), Ok(4));
+    assert_eq!(
+        netlist.rename_port(&"HA".into(), &"A".into(), "X".into()),
+        Ok(0)
+    );
+    let verilog = netlist.to_string();
+    assert!(verilog.contains(".CI(") && !verilog.contains(".CIN("));
+    let gate = netlist.last().unwrap();
+    assert_eq!(gate.find_input(&"CI".into()).unwrap().get_position(), 0);
+    assert!(gate.find_input(&"CIN".into()).is_none());
+}
+
+#[test]
+fn test_instances_of() {
+    let netlist = ripple_adder();
+    let fa: Identifier = "FA".into();
+    let fas = netlist.instances_of(&fa);
+    assert_eq!(fas.len(), 4);
+    assert_eq!(fas[0].get_instance_name(), Some("fa_0".into()));
+    assert!(netlist.instances_of(&"AND".into()).is_empty());
+
+    // New instances are indexed as they are inserted
+    let a = netlist.first().unwrap();
+    let and = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.clone().into(), a.into()])
+        .unwrap();
+    assert_eq!(netlist.instances_of(&"AND".into()), vec![and.clone()]);
+
+    // Retyped instances move to their new type
+    and.get_instance_type_mut()
+        .unwrap()
+        .set_gate_name("NAND".into());
+    assert!(netlist.instances_of(&"AND".into()).is_empty());
+    assert_eq!(netlist.instances_of(&"NAND".into()).len(), 1);
+
+    // Cleaned instances are dropped from the index
+    drop(and);
+    drop(fas);
+    assert!(netlist.clean().unwrap());
+    assert!(netlist.instances_of(&"NAND".into()).is_empty());
+    assert_eq!(netlist.instances_of(&fa).len(), 4);
+
+    // Editing the type in place keeps the index as it is
+    let fa_0 = netlist.instances_of(&fa).remove(0);
+    fa_0.get_instance_type_mut()
+        .unwrap()
+        .set_gate_name("FA".into());
+    assert_eq!(netlist.instances_of(&fa)[0], fa_0);
+
+    // The type of an instance can still be edited once its netlist is gone
+    drop(netlist);
+    fa_0.get_instance_type_mut()
+        .unwrap()
+        .set_gate_name("HA".into());
+    assert_eq!(
+        fa_0.get_instance_type().unwrap().get_gate_name(),
+        &"HA".into()
+    );
+}
+
 #[test]
 fn test_find_net_mut() {
     let netlist = get_simple_example();
@@ -256,8 +479,30 @@ fn test_driver_net() {
 fn test_get_driver_net() {
     let netlist = ripple_adder();
     let gate = netlist.last().unwrap();
-    let driver_net = gate.get_driver_net(0).unwrap();
+    let (driver_net, pin) = gate.get_driver_net(0).unwrap();
     assert_eq!(driver_net.get_identifier(), &"fa_2_COUT".into());
+    assert_eq!(pin, 1);
+    let pins: Vec<usize> = gate.driver_nets().flatten().map(|(_, pin)| pin).collect();
+    assert_eq!(pins, vec![1, 0, 0]);
+}
+
+#[test]
+fn test_operands() {
+    let netlist = ripple_adder();
+    let gate = netlist.last().unwrap();
+    let operands: Vec<_> = gate.operands().collect();
+    assert_eq!(operands.len(), 3);
+
+    // The carry in comes from the second output of the previous adder
+    let (pos, carry) = operands[0].clone();
+    assert_eq!(pos, 0);
+    let carry = carry.unwrap();
+    assert_eq!(carry.get_port().get_identifier(), &"COUT".into());
+    assert_eq!(carry.get_identifier(), "fa_2_COUT".into());
+    assert_eq!(
+        operands[2].1.clone().unwrap().get_identifier().get_name(),
+        "b[3]"
+    );
 }
 
 #[test]
@@ -408,3 +653,85 @@ fn test_simple_example() {
     let objects: Vec<_> = netlist.objects().collect();
     assert_eq!(objects.len(), 3); // 2 inputs + 1 gate
 }
+
+#[test]
+fn test_hier_paths() {
+    let netlist = ripple_adder();
+    let fa = netlist.find_instance(&"fa_1".into()).unwrap();
+    assert_eq!(fa.get_path().to_string(), "ripple_adder/fa_1");
+    assert_eq!(
+        fa.get_output(1).get_path().to_string(),
+        "ripple_adder/fa_1/COUT"
+    );
+    let cin = netlist.inputs().last().unwrap();
+    assert_eq!(cin.get_path().to_string(), "ripple_adder/cin");
+
+    let path = "ripple_adder/fa_1/COUT".parse().unwrap();
+    match netlist.resolve_path(&path) {
+        Some(PathTarget::Net(n)) => assert_eq!(n.get_identifier(), "fa_1_COUT".into()),
+        _ => panic!("Expected a net"),
+    }
+    let path = "ripple_adder/fa_1/CIN".parse().unwrap();
+    match netlist.resolve_path(&path) {
+        Some(PathTarget::InputPort(p)) => {
+            assert_eq!(p.get_driver().unwrap().get_identifier(), "fa_0_COUT".into())
+        }
+        _ => panic!("Expected an input port"),
+    }
+    let path = "ripple_adder/fa_1".parse().unwrap();
+    assert!(matches!(
+        netlist.resolve_path(&path),
+        Some(PathTarget::Instance(_))
+    ));
+    let path = "ripple_adder/cin".parse().unwrap();
+    assert!(matches!(
+        netlist.resolve_path(&path),
+        Some(PathTarget::Net(_))
+    ));
+    let path = "other/fa_1".parse().unwrap();
+    assert!(netlist.resolve_path(&path).is_none());
+}
+
+#[test]
+fn test_interface_matches() {
+    let netlist = get_simple_example();
+    assert_eq!(netlist.interface_matches(&get_simple_example()), Ok(()));
+
+    // The same ports in another order still match
+    let other: Rc<GateNetlist> = Netlist::new("other".to_string());
+    let b = other.insert_input("b".into());
+    let a = other.insert_input("a".into());
+    other
+        .insert_gate(and_gate(), "inst_0".into(), &[b, a])
+        .unwrap()
+        .expose_with_name("y".into());
+    assert_eq!(netlist.interface_matches(&other), Ok(()));
+
+    let other: Rc<GateNetlist> = Netlist::new("other".to_string());
+    other.insert_input_escaped_logic_bus("a".to_string(), 4);
+    other.insert_input("y".into());
+    other.insert_input("en".into());
+    let errors = netlist.interface_matches(&other).unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            Mismatch::Width {
+                port: "a".into(),
+                expected: 1,
+                found: 4
+            },
+            Mismatch::Missing("b".into()),
+            Mismatch::Direction {
+                port: "y".into(),
+                expected: PortDirection::Output,
+                found: PortDirection::Input
+            },
+            Mismatch::Extra("en".into()),
+        ]
+    );
+    assert_eq!(errors[0].to_string(), "Port a has 4 bits, expected 1");
+    assert_eq!(
+        errors[2].to_string(),
+        "Port y is an input, expected an output"
+    );
+}
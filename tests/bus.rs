@@ -0,0 +1,118 @@
+use safety_net::attribute::Parameter;
+use safety_net::bus::{BusCell, BusCellDef, WidthExpr, insert_bus_cell};
+use safety_net::circuit::{Identifier, Instantiable};
+use safety_net::netlist::{DrivenNet, Netlist};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+fn adder() -> Rc<BusCellDef> {
+    Rc::new(
+        BusCellDef::new("ADD".into())
+            .with_parameter("N".into(), Parameter::Integer(4))
+            .with_input("A".into(), "N".parse().unwrap())
+            .with_input("B".into(), "N".parse().unwrap())
+            .with_output("S".into(), "N+1".parse().unwrap()),
+    )
+}
+
+#[test]
+fn test_width_expr() {
+    let params = vec![
+        (Identifier::from("N"), Parameter::Integer(8)),
+        (Identifier::from("DEPTH"), Parameter::Integer(5)),
+    ];
+    let eval = |s: &str| s.parse::<WidthExpr>().unwrap().eval(&params);
+    assert_eq!(eval("N"), Ok(8));
+    assert_eq!(eval("2 * (N + 1) - 3"), Ok(15));
+    assert_eq!(eval("N / 3"), Ok(2));
+    assert_eq!(eval("$clog2(DEPTH)"), Ok(3));
+    assert!(eval("N - 9").is_err());
+    assert!(eval("N / 0").is_err());
+    assert!(eval("M").is_err());
+    // Widths that overflow are errors rather than wrapping or panicking
+    let max = || Box::new(WidthExpr::Const(usize::MAX));
+    let two = || Box::new(WidthExpr::Const(2));
+    assert!(WidthExpr::Add(max(), two()).eval(&params).is_err());
+    assert!(WidthExpr::Mul(max(), two()).eval(&params).is_err());
+    assert_eq!(
+        WidthExpr::Clog2(max()).eval(&params),
+        Ok(usize::BITS as usize)
+    );
+    assert!("N +".parse::<WidthExpr>().is_err());
+    assert!("(N".parse::<WidthExpr>().is_err());
+    assert!("$log(N)".parse::<WidthExpr>().is_err());
+    assert_eq!(
+        "N*2+1".parse::<WidthExpr>().unwrap().to_string(),
+        "((N*2)+1)"
+    );
+}
+
+#[test]
+fn test_instantiate() {
+    let def = adder();
+    let cell = def.instantiate(&[]).unwrap();
+    assert_eq!(cell.get_input_ports().into_iter().count(), 8);
+    let cell = def
+        .instantiate(&[("N".into(), Parameter::Integer(2))])
+        .unwrap();
+    let outputs: Vec<String> = cell
+        .get_output_ports()
+        .into_iter()
+        .map(|n| n.get_identifier().to_string())
+        .collect();
+    assert_eq!(outputs, vec!["S[0]", "S[1]", "S[2]"]);
+    assert_eq!(cell.input_buses().collect::<Vec<_>>().len(), 2);
+    assert!(
+        def.instantiate(&[("M".into(), Parameter::Integer(2))])
+            .is_err()
+    );
+    assert!(
+        def.instantiate(&[("N".into(), Parameter::Integer(-1))])
+            .is_err()
+    );
+}
+
+#[test]
+fn test_insert_bus_cell() {
+    let netlist = Netlist::<BusCell>::new("top".to_string());
+    let a: Vec<DrivenNet<BusCell>> = (0..2)
+        .map(|i| netlist.insert_input(format!("a[{i}]").as_str().into()))
+        .collect();
+    let b: Vec<DrivenNet<BusCell>> = (0..2)
+        .map(|i| netlist.insert_input(format!("b[{i}]").as_str().into()))
+        .collect();
+    let def = adder();
+    let cell = def
+        .instantiate(&[("N".into(), Parameter::Integer(2))])
+        .unwrap();
+
+    let err = insert_bus_cell(
+        &netlist,
+        cell.clone(),
+        "add0".into(),
+        std::slice::from_ref(&a),
+    );
+    assert!(err.is_err());
+    let short = vec![a.clone(), b[..1].to_vec()];
+    let err = insert_bus_cell(&netlist, cell.clone(), "add0".into(), &short).unwrap_err();
+    assert_eq!(err, "Input B of add0 is 2 bits wide, got 1 nets");
+
+    let add = insert_bus_cell(&netlist, cell, "add0".into(), &[a, b]).unwrap();
+    let sum: Vec<String> = add
+        .outputs()
+        .map(|o| o.get_identifier().to_string())
+        .collect();
+    assert_eq!(sum, vec!["add0_S[0]", "add0_S[1]", "add0_S[2]"]);
+
+    // The width is fixed once the cell is instantiated
+    let overrides = HashMap::from([(
+        Identifier::from("add0"),
+        vec![(Identifier::from("N"), Parameter::Integer(4))],
+    )]);
+    assert!(netlist.elaborate(&overrides).is_err());
+    let overrides = HashMap::from([(
+        Identifier::from("add0"),
+        vec![(Identifier::from("N"), Parameter::Integer(2))],
+    )]);
+    assert!(netlist.elaborate(&overrides).is_ok());
+}
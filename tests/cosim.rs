@@ -0,0 +1,143 @@
+use safety_net::aig::{Aig, decompose_gate};
+use safety_net::cosim::Cosim;
+use safety_net::netlist::{Gate, GateNetlist};
+use std::rc::Rc;
+
+fn gate(name: &str, inputs: &[&str]) -> Gate {
+    Gate::new_logical(
+        name.into(),
+        inputs.iter().map(|i| (*i).into()).collect(),
+        "Y".into(),
+    )
+}
+
+/// A toggle flop, built with an XOR of `en` and the state or with the equivalent gates named in `xor`
+fn toggle(xor: &[&str], init: bool, output: &str) -> Rc<GateNetlist> {
+    let netlist = GateNetlist::new("toggle".to_string());
+    let en = netlist.insert_input("en".into());
+    let clk = netlist.insert_input("clk".into());
+    let dff = Gate::new_logical("DFF".into(), vec!["D".into(), "C".into()], "Q".into());
+    let q = netlist
+        .insert_gate_disconnected(dff, "q_reg".into())
+        .unwrap();
+    clk.connect(q.get_input(1));
+    let next = match xor {
+        [name] => netlist
+            .insert_gate(
+                gate(name, &["A", "B"]),
+                "next".into(),
+                &[en, q.get_output(0)],
+            )
+            .unwrap(),
+        [or, nand, and] => {
+            let operands = [en, q.get_output(0)];
+            let or = netlist
+                .insert_gate(gate(or, &["A", "B"]), "or".into(), &operands)
+                .unwrap();
+            let nand = netlist
+                .insert_gate(gate(nand, &["A", "B"]), "nand".into(), &operands)
+                .unwrap();
+            netlist
+                .insert_gate(
+                    gate(and, &["A", "B"]),
+                    "next".into(),
+                    &[or.into(), nand.into()],
+                )
+                .unwrap()
+        }
+        _ => unreachable!(),
+    };
+    next.get_output(0).connect(q.get_input(0));
+    q.set_initial_value(Some(init));
+    q.expose_with_name(output.into());
+    netlist
+}
+
+fn to_aig(netlist: &GateNetlist) -> Aig {
+    let is_dff = |g: &Gate| (g.get_gate_name().to_string() == "DFF").then_some(0);
+    Aig::from_sequential_netlist(netlist, is_dff, decompose_gate).unwrap()
+}
+
+fn stimulus() -> Vec<Vec<bool>> {
+    [true, false, true, true, false, true]
+        .into_iter()
+        .map(|en| vec![en, false])
+        .collect()
+}
+
+#[test]
+fn test_equivalent() {
+    let original = to_aig(&toggle(&["XOR"], false, "q"));
+    let optimized = to_aig(&toggle(&["OR", "NAND", "AND"], false, "q"));
+    let mut cosim = Cosim::new(original, optimized);
+    assert_eq!(cosim.inputs(), vec!["en", "clk"]);
+    assert_eq!(cosim.run(stimulus()).unwrap(), None);
+    assert_eq!(cosim.cycle(), 6);
+    assert!(cosim.step(&[true]).is_err());
+}
+
+#[test]
+fn test_divergence() {
+    // The bug only shows once en and the state are both set
+    let original = to_aig(&toggle(&["XOR"], false, "q"));
+    let buggy = to_aig(&toggle(&["OR"], false, "state"));
+    let mut cosim = Cosim::new(original.clone(), buggy.clone());
+    assert!(cosim.step(&[true, false]).is_err());
+
+    let mut cosim = Cosim::new(original.clone(), buggy);
+    assert!(cosim.bind_output("q", "y").is_err());
+    cosim.bind_output("q", "state").unwrap();
+    let divergence = cosim.run(stimulus()).unwrap().unwrap();
+    assert_eq!(divergence.cycle(), 3);
+    assert_eq!(divergence.to_string(), "cycle 3: q 0 != 1");
+
+    // Initial values are honored at time zero
+    let mut cosim = Cosim::new(original, to_aig(&toggle(&["XOR"], true, "q")));
+    let divergence = cosim.step(&[false, false]).unwrap().unwrap();
+    assert_eq!(divergence.outputs(), &[("q".to_string(), false, true)]);
+}
+
+/// Two gates on inputs a and b, exposed with the names in `outputs`
+fn and_or(outputs: [&str; 2], or: &str) -> Rc<GateNetlist> {
+    let netlist = GateNetlist::new("and_or".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let and = netlist
+        .insert_gate(
+            gate("AND", &["A", "B"]),
+            "and".into(),
+            &[a.clone(), b.clone()],
+        )
+        .unwrap();
+    let or = netlist
+        .insert_gate(gate(or, &["A", "B"]), "or".into(), &[a, b])
+        .unwrap();
+    and.expose_with_name(outputs[0].into());
+    or.expose_with_name(outputs[1].into());
+    netlist
+}
+
+#[test]
+fn test_bind_by_signature() {
+    let original = Aig::from_gate_netlist(&and_or(["x", "y"], "OR")).unwrap();
+    let renamed = Aig::from_gate_netlist(&and_or(["o1", "o0"], "OR")).unwrap();
+    let mut cosim = Cosim::new(original.clone(), renamed);
+    assert!(cosim.step(&[true, true]).is_err());
+    let pairs = cosim.bind_outputs_by_signature(16).unwrap();
+    assert_eq!(
+        pairs,
+        vec![
+            ("x".to_string(), "o1".to_string()),
+            ("y".to_string(), "o0".to_string())
+        ]
+    );
+    assert_eq!(cosim.cycle(), 0);
+    let all = (0..4).map(|i| vec![i & 1 == 1, i & 2 == 2]);
+    assert_eq!(cosim.run(all).unwrap(), None);
+
+    // Outputs without a unique match are left unbound
+    let different = Aig::from_gate_netlist(&and_or(["o1", "o0"], "XOR")).unwrap();
+    let mut cosim = Cosim::new(original, different);
+    let pairs = cosim.bind_outputs_by_signature(16).unwrap();
+    assert_eq!(pairs, vec![("x".to_string(), "o1".to_string())]);
+}
@@ -0,0 +1,92 @@
+use safety_net::circuit::Instantiable;
+use safety_net::dft::ScanChain;
+use safety_net::library::CellMap;
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use std::rc::Rc;
+
+fn scan_dff() -> Gate {
+    Gate::new_logical(
+        "SDFF".into(),
+        vec!["D".into(), "SI".into(), "SE".into(), "C".into()],
+        "Q".into(),
+    )
+}
+
+fn design() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("top".to_string());
+    let d = netlist.insert_input("d".into());
+    let clk = netlist.insert_input("clk".into());
+    let r0 = netlist
+        .insert_gate(Gate::dff(), "r0".into(), &[d.clone(), clk.clone()])
+        .unwrap();
+    let and = netlist
+        .insert_gate(
+            Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into()),
+            "and".into(),
+            &[r0.into(), d],
+        )
+        .unwrap();
+    let r1 = netlist
+        .insert_gate(Gate::dff(), "r1".into(), &[and.into(), clk])
+        .unwrap();
+    r1.expose_with_name("q".into());
+    netlist
+}
+
+#[test]
+fn test_scan_chain() {
+    let netlist = design();
+    let cells = CellMap::new().with_cell("DFF".into(), scan_dff(), &[]);
+
+    // Nothing changes when a flip-flop has no scan equivalent or a port name is taken
+    assert!(ScanChain::new().insert(&netlist, &CellMap::new()).is_err());
+    let taken = ScanChain::new().with_ports("scan_in".into(), "q".into(), "se".into());
+    assert!(taken.insert(&netlist, &cells).is_err());
+    let unpinned = ScanChain::new().with_pins("TI".into(), "SE".into());
+    assert!(unpinned.insert(&netlist, &cells).is_err());
+    assert!(netlist.find_port(&"scan_in".into()).is_none());
+
+    let chain = ScanChain::new().insert(&netlist, &cells).unwrap();
+    let names: Vec<String> = chain
+        .iter()
+        .map(|f| f.get_instance_name().unwrap().to_string())
+        .collect();
+    assert_eq!(names, vec!["r0", "r1"]);
+    assert!(
+        chain
+            .iter()
+            .all(|f| *f.get_instance_type().unwrap().get_name() == "SDFF".into())
+    );
+    assert!(netlist.verify().is_ok());
+
+    // Each flip-flop shifts in from the one before it, and the last drives scan_out
+    let si = |f: &safety_net::netlist::NetRef<Gate>| {
+        f.find_input(&"SI".into())
+            .unwrap()
+            .get_driver()
+            .unwrap()
+            .get_identifier()
+            .to_string()
+    };
+    assert_eq!(si(&chain[0]), "scan_in");
+    assert_eq!(si(&chain[1]), "r0_Q");
+    let se = chain[1]
+        .find_input(&"SE".into())
+        .unwrap()
+        .get_driver()
+        .unwrap();
+    assert_eq!(se.get_identifier(), "scan_enable".into());
+    let d = chain[1]
+        .find_input(&"D".into())
+        .unwrap()
+        .get_driver()
+        .unwrap();
+    assert_eq!(d.get_identifier(), "and_Y".into());
+    let outputs: Vec<String> = netlist
+        .get_output_ports()
+        .iter()
+        .map(|n| n.get_identifier().to_string())
+        .collect();
+    assert!(outputs.contains(&"scan_out".to_string()));
+    assert!(outputs.contains(&"q".to_string()));
+}
@@ -1,7 +1,17 @@
 use safety_net::assert_verilog_eq;
+use safety_net::circuit::Instantiable;
+use safety_net::constraint::InputConstraint;
+use safety_net::cost::Budget;
+use safety_net::cost::{AcceptAll, Cost, CostModel, CostWeights, UnitCost, cost_of, netlist_cost};
+use safety_net::emit::VerilogEmitter;
+use safety_net::library::CellMap;
 use safety_net::netlist::Gate;
 use safety_net::netlist::GateNetlist;
 use safety_net::netlist::Netlist;
+use safety_net::netlist::NetlistError;
+use safety_net::netlist::iter::InsertionPolicy;
+use safety_net::report;
+use safety_net::supply::Supply;
 use std::rc::Rc;
 
 fn and_gate() -> Gate {
@@ -46,7 +56,7 @@ fn test_replace() {
     let input = netlist.inputs().next().unwrap();
     let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
     let inverted = netlist
-        .insert_gate(inverter, "inst_0".into(), &[input.clone()])
+        .insert_gate(inverter, "inst_0".into(), std::slice::from_ref(&input))
         .unwrap();
     assert!(netlist.replace_net_uses(input.unwrap(), &inverted).is_ok());
     assert_verilog_eq!(
@@ -121,3 +131,1180 @@ fn test_replace2() {
          endmodule\n"
     );
 }
+
+#[test]
+fn test_split_loads() {
+    let netlist = get_simple_example();
+    let a = netlist.inputs().next().unwrap();
+    let b = netlist.inputs().nth(1).unwrap();
+    let and_1 = netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[a.clone(), b.clone()])
+        .unwrap();
+    let and_1 = and_1.expose_with_name("z".into());
+    let and_0 = netlist.find_instance(&"inst_0".into()).unwrap();
+
+    let buffer = Gate::new_logical("BUF".into(), vec!["I".into()], "O".into());
+    // Input B of inst_0 is not a load of a
+    assert!(
+        a.split_loads(
+            buffer.clone(),
+            vec![("buf_0".into(), vec![and_0.get_input(1)])]
+        )
+        .is_err()
+    );
+    // A buffer that cannot be inserted leaves the loads as they were
+    netlist.set_check_drivers(true);
+    assert!(
+        a.split_loads(
+            buffer.clone(),
+            vec![
+                ("buf_0".into(), vec![and_0.get_input(0)]),
+                ("inst_1".into(), vec![and_1.get_input(0)]),
+            ]
+        )
+        .is_err()
+    );
+    assert_eq!(netlist.objects().count(), 4);
+    assert_eq!(
+        and_0.get_input(0).get_driver().unwrap().get_identifier(),
+        "a".into()
+    );
+    let buffers = a
+        .split_loads(
+            buffer,
+            vec![
+                ("buf_0".into(), vec![and_0.get_input(0)]),
+                ("buf_1".into(), vec![and_1.get_input(0)]),
+            ],
+        )
+        .unwrap();
+    assert_eq!(buffers.len(), 2);
+    assert!(netlist.verify().is_ok());
+    assert_eq!(
+        and_0.get_input(0).get_driver().unwrap().get_identifier(),
+        "buf_0_O".into()
+    );
+    assert_eq!(
+        and_1.get_input(0).get_driver().unwrap().get_identifier(),
+        "buf_1_O".into()
+    );
+    assert_eq!(
+        buffers[1]
+            .get_input(0)
+            .get_driver()
+            .unwrap()
+            .get_identifier(),
+        "a".into()
+    );
+}
+
+#[test]
+fn test_copy_cone() {
+    let library = get_simple_example();
+    let root = library
+        .find_instance(&"inst_0".into())
+        .unwrap()
+        .get_output(0);
+
+    let netlist = GateNetlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let c = netlist.insert_input("c".into());
+
+    // The cone has to come from the other netlist
+    assert!(
+        netlist
+            .copy_cone_from(&netlist, std::slice::from_ref(&a), "lib_")
+            .is_err()
+    );
+    assert!(
+        netlist
+            .copy_cone_from(&library, std::slice::from_ref(&c), "lib_")
+            .is_err()
+    );
+
+    let copied = netlist.copy_cone_from(&library, &[root], "lib_").unwrap();
+    assert_eq!(copied.len(), 1);
+    let and = copied[0].clone().unwrap();
+    assert_eq!(and.get_instance_name(), Some("lib_inst_0".into()));
+    assert_eq!(and.get_driver(0).unwrap(), a.unwrap());
+    assert_eq!(netlist.inputs().count(), 3);
+
+    // A cone that does not fit the budget is not copied at all
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    let inv = library
+        .insert_gate(inverter, "inst_1".into(), &[library.last().unwrap().into()])
+        .unwrap();
+    let before = netlist.objects().count();
+    netlist.set_budget(Budget::new(|_: &Gate| 1.0).with_max_instances(2));
+    assert!(
+        netlist
+            .copy_cone_from(&library, &[inv.get_output(0)], "big_")
+            .is_err()
+    );
+    assert_eq!(netlist.objects().count(), before);
+    assert_eq!(netlist.get_budget().unwrap().instances(), 1);
+    netlist.take_budget();
+
+    let and = and.expose_with_name("y".into());
+    c.expose_with_name("z".into());
+    assert!(netlist.verify().is_ok());
+    assert_eq!(library.objects().count(), 4);
+    assert_eq!(
+        and.get_instance_type().unwrap().get_gate_name(),
+        &"AND".into()
+    );
+}
+
+#[test]
+fn test_emit_cone() {
+    let netlist = get_simple_example();
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    let and = netlist.find_instance(&"inst_0".into()).unwrap();
+    let inv = netlist
+        .insert_gate(inverter, "inst_1".into(), &[and.get_output(0)])
+        .unwrap();
+    let b = netlist.inputs().nth(1).unwrap();
+    let c = netlist.insert_input("c".into());
+    netlist
+        .insert_gate(and_gate(), "inst_2".into(), &[inv.get_output(0), c])
+        .unwrap()
+        .expose_with_name("z".into());
+
+    // The cone of the inverter leaves out c and the gate it drives
+    let mut verilog = String::new();
+    netlist
+        .emit_cone_verilog(&[inv.get_output(0), and.get_output(0), b], &mut verilog)
+        .unwrap();
+    assert_verilog_eq!(
+        verilog,
+        "module example_cone (
+           b,
+           a,
+           inst_1_O,
+           y,
+           b_out
+         );
+           input b;
+           wire b;
+           input a;
+           wire a;
+           output inst_1_O;
+           wire inst_1_O;
+           output y;
+           wire y;
+           output b_out;
+           wire b_out;
+           wire inst_0_Y;
+           AND inst_0 (
+             .A(a),
+             .B(b),
+             .Y(inst_0_Y)
+           );
+           INV inst_1 (
+             .I(inst_0_Y),
+             .O(inst_1_O)
+           );
+           assign y = inst_0_Y;
+           assign b_out = b;
+         endmodule\n"
+    );
+    assert!(netlist.extract_cone(&[]).unwrap().verify().is_err());
+}
+
+#[test]
+fn test_side_table() {
+    let netlist = GateNetlist::new("example".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let dead = netlist
+        .insert_gate(and_gate(), "inst_dead".into(), &[a.clone(), b.clone()])
+        .unwrap();
+    let live = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.clone(), b])
+        .unwrap();
+    let live = live.expose_with_name("y".into());
+
+    {
+        let mut levels = netlist.side_table::<usize>();
+        levels.insert(&a.clone().unwrap(), 0);
+        levels.insert(&dead, 1);
+        levels.insert(&live, 1);
+        assert_eq!(levels.len(), 3);
+    }
+    netlist.side_table::<&str>().insert(&live, "critical");
+
+    // Entries follow their nodes when the netlist is cleaned
+    drop(dead);
+    drop(live);
+    assert!(netlist.clean().unwrap());
+    let live = netlist.find_instance(&"inst_0".into()).unwrap();
+    {
+        let levels = netlist.side_table::<usize>();
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels.get(&live), Some(&1));
+    }
+
+    // Entries are dropped when their node is deleted
+    netlist.side_table::<usize>().insert(&live, 2);
+    live.delete_uses().unwrap();
+    assert_eq!(netlist.side_table::<usize>().len(), 1);
+    assert_eq!(netlist.take_side_table::<&str>().map(|t| t.len()), Some(0));
+    assert!(netlist.take_side_table::<&str>().is_none());
+}
+
+#[test]
+fn test_budget() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    let buffer = Gate::new_logical("BUF".into(), vec!["I".into()], "O".into());
+    netlist.set_budget(
+        Budget::new(|g: &Gate| match g.get_gate_name().get_name() {
+            "AND" => 2.0,
+            _ => 1.0,
+        })
+        .with_max_instances(2)
+        .with_max_area(3.5),
+    );
+    assert_eq!(netlist.get_budget().unwrap().instances(), 1);
+
+    assert!(matches!(
+        netlist.insert_gate(
+            and_gate(),
+            "inst_1".into(),
+            &[a.clone().into(), a.clone().into()]
+        ),
+        Err(NetlistError::BudgetExceeded { instances: 2, .. })
+    ));
+    let buf = netlist
+        .insert_gate(buffer.clone(), "buf_0".into(), &[a.clone().into()])
+        .unwrap();
+    assert!(matches!(
+        netlist.insert_gate_disconnected(buffer, "buf_1".into()),
+        Err(NetlistError::BudgetExceeded { instances: 3, .. })
+    ));
+    assert_eq!(netlist.get_budget().unwrap().area(), 3.0);
+
+    // Cleaned instances are refunded
+    drop(buf);
+    drop(a);
+    assert!(netlist.clean().unwrap());
+    assert_eq!(netlist.get_budget().unwrap().instances(), 1);
+    assert_eq!(netlist.take_budget().unwrap().area(), 2.0);
+    assert!(netlist.get_budget().is_none());
+}
+
+#[test]
+fn test_insert_gate_bus() {
+    let netlist = GateNetlist::new("bitwise".to_string());
+    let a = netlist.insert_input_escaped_logic_bus("a".to_string(), 4);
+    let b = netlist.insert_input_escaped_logic_bus("b".to_string(), 4);
+
+    let ands = netlist
+        .insert_gate_bus(and_gate(), "u_and".into(), &[a.clone(), b.clone()])
+        .unwrap();
+    assert_eq!(ands.len(), 4);
+    for (i, and) in ands.iter().enumerate() {
+        assert_eq!(
+            and.get_instance_name().unwrap().to_string(),
+            format!("u_and_{i}")
+        );
+        assert_eq!(and.get_driver(0).unwrap(), a[i].clone().unwrap());
+        assert_eq!(and.get_driver(1).unwrap(), b[i].clone().unwrap());
+        and.clone().expose_with_name(format!("y{i}").into());
+    }
+    assert!(netlist.verify().is_ok());
+
+    assert!(matches!(
+        netlist.insert_gate_bus(and_gate(), "u_bad".into(), &[a.clone(), b[..3].to_vec()]),
+        Err(NetlistError::WidthMismatch {
+            operand: 1,
+            expected: 4,
+            got: 3,
+            ..
+        })
+    ));
+    assert!(matches!(
+        netlist.insert_gate_bus(and_gate(), "u_bad".into(), &[a]),
+        Err(NetlistError::ArityMismatch { .. })
+    ));
+    assert_eq!(netlist.objects().count(), 12);
+}
+
+#[test]
+fn test_map_gate_over_bus() {
+    let netlist = GateNetlist::new("masked".to_string());
+    let a = netlist.insert_input_escaped_logic_bus("a".to_string(), 4);
+    let en = netlist.insert_input("en".into());
+
+    // The enable is broadcast to every bit
+    let y = netlist
+        .map_gate_over_bus(and_gate(), "u_mask".into(), &[a.clone(), vec![en.clone()]])
+        .unwrap();
+    assert_eq!(y.len(), 4);
+    for (i, bit) in y.iter().enumerate() {
+        let and = bit.clone().unwrap();
+        assert_eq!(
+            and.get_instance_name().unwrap().to_string(),
+            format!("u_mask_{i}")
+        );
+        assert_eq!(and.get_driver(0).unwrap(), a[i].clone().unwrap());
+        assert_eq!(and.get_driver(1).unwrap(), en.clone().unwrap());
+        bit.clone().expose_with_name(format!("y{i}").into());
+    }
+    assert!(netlist.verify().is_ok());
+
+    assert!(matches!(
+        netlist.map_gate_over_bus(and_gate(), "u_bad".into(), &[a.clone(), a[..2].to_vec()]),
+        Err(NetlistError::WidthMismatch {
+            operand: 1,
+            expected: 4,
+            got: 2,
+            ..
+        })
+    ));
+}
+
+/// NANDs are small but slow
+struct NandFavoring;
+
+impl CostModel<Gate> for NandFavoring {
+    fn area(&self, inst_type: &Gate) -> f64 {
+        match inst_type.get_name().to_string().as_str() {
+            "NAND" => 1.0,
+            _ => 2.0,
+        }
+    }
+
+    fn delay(&self, inst_type: &Gate) -> f64 {
+        match inst_type.get_name().to_string().as_str() {
+            "NAND" => 3.0,
+            _ => 1.0,
+        }
+    }
+
+    fn weights(&self) -> CostWeights {
+        CostWeights {
+            area: 1.0,
+            delay: 0.5,
+            power: 0.0,
+        }
+    }
+}
+
+#[test]
+fn test_cost_model() {
+    let netlist = get_simple_example();
+    let and = netlist.find_instance(&"inst_0".into()).unwrap();
+    assert_eq!(netlist_cost(&UnitCost, &netlist).area, 1.0);
+    assert_eq!(netlist_cost(&|_: &Gate| 2.5, &netlist).area, 2.5);
+
+    // An AND as a NAND followed by a NAND used as an inverter
+    let nand = Gate::new_logical("NAND".into(), vec!["A".into(), "B".into()], "Y".into());
+    let before = cost_of(&NandFavoring, [and]);
+    let after = NandFavoring.cost(&nand).combine(NandFavoring.cost(&nand));
+    assert_eq!(
+        after,
+        Cost {
+            area: 2.0,
+            delay: 3.0,
+            power: 0.0
+        }
+    );
+    assert_eq!(before.to_string(), "area 2.000, delay 1.000, power 0.000");
+    // Same area, but slower
+    assert!(!NandFavoring.accepts(&before, &after));
+    // Counting instances, two gates never replace one
+    let unit: &dyn CostModel<Gate> = &UnitCost;
+    let (before, after) = (
+        unit.cost(&and_gate()),
+        unit.cost(&nand).combine(unit.cost(&nand)),
+    );
+    assert!(!unit.accepts(&before, &after));
+    assert!(unit.accepts(&after, &before));
+}
+
+#[test]
+fn test_find_or_insert_gate() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    let b = netlist.find_net(&"b".into()).unwrap();
+    let and_0 = netlist.find_instance(&"inst_0".into()).unwrap();
+
+    // An existing instance is shared
+    let found = netlist
+        .find_or_insert_gate(and_gate(), "inst_1".into(), &[a.clone().into(), b.clone()])
+        .unwrap();
+    assert_eq!(found, and_0);
+
+    // Swapped operands are a different structure
+    let swapped = netlist
+        .find_or_insert_gate(and_gate(), "inst_1".into(), &[b.clone(), a.clone().into()])
+        .unwrap();
+    assert_ne!(swapped, and_0);
+    assert_eq!(netlist.objects().count(), 4);
+    let again = netlist
+        .find_or_insert_gate(and_gate(), "inst_2".into(), &[b.clone(), a.clone().into()])
+        .unwrap();
+    assert_eq!(again, swapped);
+
+    // Instances edited in place are no longer matched
+    and_0.get_input(0).connect(b.clone());
+    let fresh = netlist
+        .find_or_insert_gate(and_gate(), "inst_3".into(), &[a.into(), b])
+        .unwrap();
+    assert_ne!(fresh, and_0);
+    assert_eq!(netlist.objects().count(), 5);
+}
+
+#[test]
+fn test_extract_by_attribute() {
+    let netlist = GateNetlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let c = netlist.insert_input("c".into());
+    let and_0 = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.clone(), b])
+        .unwrap();
+    let and_1 = netlist
+        .insert_gate(
+            and_gate(),
+            "inst_1".into(),
+            &[and_0.get_output(0), c.clone()],
+        )
+        .unwrap();
+    let and_2 = netlist
+        .insert_gate(and_gate(), "inst_2".into(), &[and_1.into(), a])
+        .unwrap();
+    let and_3 = netlist
+        .insert_gate(and_gate(), "inst_3".into(), &[and_0.get_output(0), c])
+        .unwrap();
+    and_2.clone().expose_with_name("y".into());
+    and_3.expose_with_name("z".into());
+    and_0.set_attribute("dbg".to_string());
+    and_2.set_attribute("dbg".to_string());
+
+    assert!(netlist.extract_by_attribute(&"dft".to_string()).is_err());
+    let subset = netlist.extract_by_attribute(&"dbg".to_string()).unwrap();
+    assert!(subset.verify().is_ok());
+    assert_verilog_eq!(
+        subset.to_string(),
+        "module top (
+           a,
+           b,
+           c,
+           inst_0_Y,
+           y
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           input c;
+           wire c;
+           output inst_0_Y;
+           wire inst_0_Y;
+           output y;
+           wire y;
+           wire inst_1_Y;
+           wire inst_2_Y;
+           (* dbg *)
+           AND inst_0 (
+             .A(a),
+             .B(b),
+             .Y(inst_0_Y)
+           );
+           AND inst_1 (
+             .A(inst_0_Y),
+             .B(c),
+             .Y(inst_1_Y)
+           );
+           (* dbg *)
+           AND inst_2 (
+             .A(inst_1_Y),
+             .B(a),
+             .Y(inst_2_Y)
+           );
+           assign y = inst_2_Y;
+         endmodule\n"
+    );
+}
+
+#[test]
+fn test_permute_objects() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    let inv = netlist
+        .insert_gate(inverter, "inst_1".into(), &[a.clone().into()])
+        .unwrap();
+    let inv = inv.expose_with_name("z".into());
+    netlist.side_table::<&str>().insert(&inv, "inv");
+
+    let mut order: Vec<_> = netlist.objects().collect();
+    assert!(netlist.permute_objects(&order[1..]).is_err());
+    order[0] = order[1].clone();
+    assert!(netlist.permute_objects(&order).is_err());
+
+    // Put the instances in reverse, ahead of the inputs
+    let mut order: Vec<_> = netlist.objects().collect();
+    order.reverse();
+    netlist.permute_objects(&order).unwrap();
+    assert!(netlist.verify().is_ok());
+    assert_eq!(netlist.first().unwrap(), inv);
+    assert_eq!(netlist.side_table::<&str>().get(&inv), Some(&"inv"));
+    assert_eq!(inv.get_driver(0).unwrap(), a);
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module example (
+           a,
+           b,
+           y,
+           z
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           output z;
+           wire z;
+           wire inst_1_O;
+           wire inst_0_Y;
+           INV inst_1 (
+             .I(a),
+             .O(inst_1_O)
+           );
+           AND inst_0 (
+             .A(a),
+             .B(b),
+             .Y(inst_0_Y)
+           );
+           assign y = inst_0_Y;
+           assign z = inst_1_O;
+         endmodule\n"
+    );
+}
+
+#[test]
+fn test_object_cursor() {
+    let netlist = get_simple_example();
+    let inverter = || Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+
+    // Inserting while walking only visits the original nodes when they are skipped
+    let mut visited = Vec::new();
+    for node in netlist.cursor(InsertionPolicy::Skip) {
+        if node.get_instance_type().is_some() {
+            netlist
+                .insert_gate(inverter(), "inv_0".into(), &[node.get_output(0)])
+                .unwrap();
+        }
+        visited.push(node.get_identifier().to_string());
+    }
+    assert_eq!(visited.len(), 3);
+    assert_eq!(netlist.objects().count(), 4);
+
+    // Otherwise the inserted nodes are visited after them
+    let mut visited = Vec::new();
+    for node in netlist.cursor(InsertionPolicy::Visit) {
+        if node
+            .get_instance_type()
+            .is_some_and(|t| t.get_name() == &"AND".into())
+        {
+            netlist
+                .insert_gate(inverter(), "inv_1".into(), &[node.get_output(0)])
+                .unwrap();
+        }
+        visited.push(node.get_identifier().to_string());
+    }
+    assert_eq!(visited.len(), 5);
+    assert_eq!(visited.last().unwrap(), "inv_1_O");
+
+    // Cleaning while walking neither repeats nor visits the removed nodes
+    let mut cursor = netlist.cursor(InsertionPolicy::Skip);
+    assert_eq!(cursor.pending(), 5);
+    assert_eq!(cursor.next().unwrap().get_identifier().to_string(), "a");
+    assert!(netlist.clean().unwrap());
+    let rest: Vec<String> = cursor.map(|n| n.get_identifier().to_string()).collect();
+    assert_eq!(rest, vec!["b", "inst_0_Y"]);
+}
+
+#[test]
+fn test_decompose_fanin() {
+    let netlist = Netlist::new("wide".to_string());
+    let inputs: Vec<_> = (0..8)
+        .map(|i| netlist.insert_input(format!("i{i}").as_str().into()))
+        .collect();
+    let wide = |name: &str, n: usize| {
+        Gate::new_logical(
+            name.into(),
+            (0..n).map(|i| format!("A{i}").as_str().into()).collect(),
+            "Y".into(),
+        )
+    };
+    let nand = netlist
+        .insert_gate(wide("NAND", 8), "nand".into(), &inputs)
+        .unwrap();
+    nand.set_attribute("keep".into());
+    nand.expose_with_name("y".into());
+    let and = netlist
+        .insert_gate(wide("AND", 5), "and".into(), &inputs[..5])
+        .unwrap();
+    and.expose_with_name("z".into());
+    let mux = Gate::new_logical(
+        "MUX".into(),
+        vec!["A".into(), "B".into(), "S".into()],
+        "Y".into(),
+    );
+    let mux = netlist
+        .insert_gate(mux, "mux".into(), &inputs[..3])
+        .unwrap();
+    mux.clone().expose_with_name("w".into());
+    // An input already named like an inner instance is skipped over
+    netlist.insert_input("nand_0".into());
+
+    assert!(
+        netlist
+            .decompose_fanin(1, Gate::with_fanin, &AcceptAll)
+            .is_err()
+    );
+    // A tree has more instances than the gate it replaces
+    assert_eq!(
+        netlist
+            .decompose_fanin(4, Gate::with_fanin, &UnitCost)
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        netlist
+            .decompose_fanin(4, Gate::with_fanin, &AcceptAll)
+            .unwrap(),
+        2
+    );
+    assert!(netlist.verify().is_ok());
+
+    // NAND8 becomes NAND2(AND4, AND4), and AND5 becomes AND2(AND4, i4)
+    let root = netlist.find_instance(&"nand".into()).unwrap();
+    assert_eq!(root.get_instance_type().unwrap().get_name(), &"NAND".into());
+    assert_eq!(root.get_num_input_ports(), 2);
+    assert_eq!(
+        root.get_driver(0).unwrap().get_instance_name(),
+        Some("nand_1".into())
+    );
+    assert_eq!(root.get_net(0).get_identifier(), &"nand_Y".into());
+    assert!(root.attributes().any(|a| a.key() == "keep"));
+    let root = netlist.find_instance(&"and".into()).unwrap();
+    assert_eq!(root.get_num_input_ports(), 2);
+    assert_eq!(root.get_driver(0).unwrap().get_num_input_ports(), 4);
+    assert!(root.get_driver(1).unwrap().is_an_input());
+    assert_eq!(netlist.find_instance(&"mux".into()), Some(mux));
+
+    // Every gate now has at most 4 inputs
+    assert!(netlist.objects().all(|n| n.get_num_input_ports() <= 4));
+    assert_eq!(
+        netlist
+            .decompose_fanin(2, Gate::with_fanin, &AcceptAll)
+            .unwrap(),
+        3
+    );
+    assert!(netlist.objects().all(|n| n.get_num_input_ports() <= 3));
+    assert!(netlist.verify().is_ok());
+}
+
+#[test]
+fn test_substitute_cells() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    let half_adder = Gate::new_logical_multi(
+        "HA".into(),
+        vec!["A".into(), "B".into()],
+        vec!["S".into(), "C".into()],
+    );
+    let ha = netlist
+        .insert_gate(half_adder, "ha".into(), &[a.clone().into(), a.into()])
+        .unwrap();
+    ha.get_output(1).expose_with_name("c".into());
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    netlist
+        .insert_gate(inverter, "inv".into(), &[ha.get_output(0)])
+        .unwrap()
+        .expose_with_name("s".into());
+
+    // The new half adder has its outputs the other way around
+    let and2 = Gate::new_logical(
+        "AND2_X1".into(),
+        vec!["A1".into(), "A2".into()],
+        "ZN".into(),
+    );
+    let ha_x1 = Gate::new_logical_multi(
+        "HA_X1".into(),
+        vec!["A".into(), "B".into()],
+        vec!["CO".into(), "S".into()],
+    );
+    let map = CellMap::new()
+        .with_cell("AND".into(), and2, &[("A", "A1"), ("B", "A2"), ("Y", "ZN")])
+        .with_cell("HA".into(), ha_x1, &[("C", "CO")]);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get_pin(&"HA".into(), &"S".into()), Some("S".into()));
+
+    // A missing port leaves the netlist as it was
+    let bad = CellMap::new().with_cell("AND".into(), and_gate(), &[("A", "X")]);
+    assert!(netlist.substitute_cells(&bad, &AcceptAll).is_err());
+    assert!(netlist.to_string().contains("AND inst_0"));
+    // So does a pin map that merges two ports
+    let merged = CellMap::new().with_cell("AND".into(), and_gate(), &[("B", "A")]);
+    assert!(netlist.substitute_cells(&merged, &AcceptAll).is_err());
+    assert!(netlist.to_string().contains("AND inst_0"));
+
+    // Cells are only substituted where the model finds the new cell cheaper
+    assert_eq!(netlist.substitute_cells(&map, &UnitCost).unwrap(), 0);
+    let area = |g: &Gate| match g.get_name().to_string().ends_with("_X1") {
+        true => 0.5,
+        false => 1.0,
+    };
+    assert_eq!(netlist.substitute_cells(&map, &area).unwrap(), 2);
+    assert!(netlist.verify().is_ok());
+    assert_eq!(ha.get_output(1).get_port().get_identifier(), &"S".into());
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module example (
+           a,
+           b,
+           y,
+           c,
+           s
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           output c;
+           wire c;
+           output s;
+           wire s;
+           wire inst_0_Y;
+           wire ha_C;
+           wire ha_S;
+           wire inv_O;
+           AND2_X1 inst_0 (
+             .A1(a),
+             .A2(b),
+             .ZN(inst_0_Y)
+           );
+           HA_X1 ha (
+             .A(a),
+             .B(a),
+             .CO(ha_C),
+             .S(ha_S)
+           );
+           INV inv (
+             .I(ha_S),
+             .O(inv_O)
+           );
+           assign y = inst_0_Y;
+           assign c = ha_C;
+           assign s = inv_O;
+         endmodule\n"
+    );
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let netlist = get_simple_example();
+    let original = netlist.to_string();
+    let a = netlist.first().unwrap();
+    netlist.side_table::<&str>().insert(&a, "a");
+    let snapshot = netlist.snapshot();
+    assert_eq!(snapshot.len(), 3);
+
+    // Add an inverter, then drop the original AND gate
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    let inv = netlist
+        .insert_gate(inverter, "inst_1".into(), &[a.clone().into()])
+        .unwrap();
+    let inv = inv.expose_with_name("z".into());
+    assert_eq!(netlist.last().unwrap(), inv);
+    let and = netlist.objects().nth(2).unwrap();
+    and.delete_uses().unwrap();
+    netlist.clean().unwrap();
+    assert_ne!(netlist.to_string(), original);
+
+    // Edits to surviving objects and the settings of the netlist are undone too
+    a.insert_attribute("keep".to_string(), "1".to_string());
+    netlist.set_two_state(true);
+    netlist
+        .constrain_inputs(InputConstraint::Constant("a".into(), true))
+        .unwrap();
+
+    // A snapshot of another netlist is rejected before anything is restored
+    let other = get_simple_example();
+    assert!(netlist.restore(&other.snapshot()).is_err());
+    assert!(netlist.is_two_state());
+
+    netlist.restore(&snapshot).unwrap();
+    assert!(netlist.verify().is_ok());
+    assert_eq!(netlist.to_string(), original);
+    assert_eq!(netlist.first().unwrap(), a);
+    assert_eq!(a.attributes().count(), 0);
+    assert!(!netlist.is_two_state());
+    assert!(netlist.get_input_constraints().is_empty());
+    assert_eq!(netlist.side_table::<&str>().get(&a), Some(&"a"));
+    assert_eq!(
+        netlist
+            .last()
+            .unwrap()
+            .get_instance_name()
+            .unwrap()
+            .to_string(),
+        "inst_0"
+    );
+
+    // The snapshot can be restored again after more edits
+    let b = netlist.objects().nth(1).unwrap();
+    netlist
+        .insert_gate(and_gate(), "inst_2".into(), &[a.into(), b.into()])
+        .unwrap()
+        .expose_with_name("w".into());
+    netlist.restore(&snapshot).unwrap();
+    assert_eq!(netlist.to_string(), original);
+}
+
+#[test]
+fn test_aliases() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    let and = netlist.last().unwrap();
+
+    // An inverter reads the AND gate through a named alias, and a second alias names another output
+    let and_out = netlist
+        .insert_alias(and.get_output(0), "and_out".into())
+        .unwrap();
+    assert!(and_out.is_an_alias());
+    let inverter = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    netlist
+        .insert_gate(inverter, "inst_1".into(), std::slice::from_ref(&and_out))
+        .unwrap()
+        .expose_with_name("z".into());
+    netlist
+        .insert_alias(and_out.clone(), "and_buf".into())
+        .unwrap()
+        .expose_with_name("w".into());
+    let alias_of_input = netlist.insert_alias(a.into(), "a_buf".into()).unwrap();
+    drop(and_out);
+    assert!(netlist.verify().is_ok());
+    assert_eq!(netlist.aliases().count(), 3);
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module example (
+           a,
+           b,
+           y,
+           z,
+           w
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           output z;
+           wire z;
+           output w;
+           wire w;
+           wire inst_0_Y;
+           wire and_out;
+           wire inst_1_O;
+           wire and_buf;
+           wire a_buf;
+           AND inst_0 (
+             .A(a),
+             .B(b),
+             .Y(inst_0_Y)
+           );
+           INV inst_1 (
+             .I(and_out),
+             .O(inst_1_O)
+           );
+           assign and_out = inst_0_Y;
+           assign and_buf = and_out;
+           assign a_buf = a;
+           assign y = inst_0_Y;
+           assign z = inst_1_O;
+           assign w = and_buf;
+         endmodule\n"
+    );
+
+    // Collapsing and_buf moves w to and_out, which is then kept since the AND gate already drives y
+    let and_buf = netlist.aliases().nth(1).unwrap();
+    assert_eq!(
+        netlist.collapse_alias(and_buf).unwrap().get_identifier(),
+        "and_out".into()
+    );
+    let a_buf = alias_of_input.unwrap();
+    assert_eq!(
+        netlist.collapse_alias(a_buf).unwrap().get_identifier(),
+        "a".into()
+    );
+    assert_eq!(netlist.collapse_aliases().unwrap(), 0);
+    assert!(netlist.verify().is_ok());
+    let and_out = netlist.aliases().next().unwrap();
+    assert!(netlist.collapse_alias(and_out).is_err());
+    assert_eq!(netlist.aliases().count(), 1);
+    assert!(netlist.to_string().contains("assign w = and_out;"));
+
+    let inv = netlist.find_instance(&"inst_1".into()).unwrap();
+    assert_eq!(
+        inv.get_input(0).get_driver().unwrap().get_identifier(),
+        "and_out".into()
+    );
+}
+
+#[test]
+fn test_supplies() {
+    let netlist = get_simple_example();
+    let vdd = netlist.insert_input("vdd".into());
+    vdd.clone().unwrap().set_supply(Some(Supply::Power));
+    let tie = Gate::new_logical("TIELO".into(), vec![], "Y".into());
+    let vss = netlist.insert_gate(tie, "vss_tie".into(), &[]).unwrap();
+    vss.set_supply(Some(Supply::Ground));
+    assert_eq!(vss.get_output(0).get_supply(), Some(Supply::Ground));
+    let supplies: Vec<_> = netlist.supplies().map(|(_, s)| s).collect();
+    assert_eq!(supplies, vec![Supply::Power, Supply::Ground]);
+
+    // The unused tie cell is kept, and neither supply is reported as unused
+    assert!(!netlist.clean().unwrap());
+    assert_eq!(netlist.objects().count(), 5);
+    assert!(report::unused_report(&netlist).is_empty());
+
+    // Supply nets are not buffered
+    let load = netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[vdd.clone(), vdd.clone()])
+        .unwrap();
+    let load = load.expose_with_name("z".into());
+    let ports = vec![load.get_input(0), load.get_input(1)];
+    let buffer = Gate::new_logical("BUF".into(), vec!["A".into()], "Y".into());
+    assert!(
+        vdd.split_loads(buffer, vec![("buf".into(), ports)])
+            .is_err()
+    );
+
+    // PG-aware emission connects the supply pins of every instance
+    let mut emitter = VerilogEmitter::new().with_supply_pins("VDD".into(), "VSS".into());
+    let mut out = String::new();
+    netlist.emit(&mut emitter, &mut out).unwrap();
+    assert_eq!(out.matches(".VDD(vdd)").count(), 3);
+    assert_eq!(out.matches(".VSS(vss_tie_Y)").count(), 3);
+    assert!(out.contains("(* supply = \"ground\" *)"));
+    assert!(!netlist.to_string().contains(".VDD"));
+
+    vss.set_supply(None);
+    let err = netlist.emit(&mut emitter, &mut String::new()).unwrap_err();
+    assert!(err.to_string().contains("No ground net"));
+    assert!(netlist.clean().unwrap());
+}
+
+#[test]
+fn test_buffer_fanout() {
+    let netlist: Rc<GateNetlist> = Netlist::new("fanout".to_string());
+    let a = netlist.insert_input("a[3]".into());
+    let b = netlist.insert_input("b".into());
+    let buffer = Gate::new_logical("BUF".into(), vec!["I".into()], "O".into());
+    // The first buffer name is taken, so the tree starts at a_3_buf_1
+    netlist
+        .insert_gate(buffer.clone(), "a_3_buf_0".into(), std::slice::from_ref(&b))
+        .unwrap()
+        .expose_with_name("z".into());
+    let ands: Vec<_> = (0..7)
+        .map(|i| {
+            let and = netlist
+                .insert_gate(
+                    and_gate(),
+                    format!("and_{i}").into(),
+                    &[a.clone(), b.clone()],
+                )
+                .unwrap();
+            and.clone().expose_with_name(format!("y_{i}").into());
+            and
+        })
+        .collect();
+    a.clone().expose_with_name("a_out".into());
+
+    assert!(
+        netlist
+            .buffer_fanout(1, buffer.clone(), &AcceptAll)
+            .is_err()
+    );
+    // Buffers only add area, so a model that wants less area rejects every tree
+    assert_eq!(
+        netlist.buffer_fanout(2, buffer.clone(), &UnitCost).unwrap(),
+        0
+    );
+    // Seven loads of a need four buffers, which need two more, and likewise for the eight loads of b
+    assert_eq!(
+        netlist
+            .buffer_fanout(2, buffer.clone(), &AcceptAll)
+            .unwrap(),
+        6 + 6
+    );
+    assert!(netlist.verify().is_ok());
+    assert!(netlist.find_instance(&"a_3_buf_1".into()).is_some());
+    assert!(netlist.find_instance(&"b_buf_0".into()).is_some());
+    for net in netlist
+        .objects()
+        .flat_map(|n| n.outputs().collect::<Vec<_>>())
+    {
+        let fanout = netlist
+            .connections()
+            .filter(|c| c.net() == *net.as_net())
+            .count();
+        assert!(fanout <= 2, "{net} drives {fanout} loads");
+    }
+    // The loads still see a, through two levels of buffers
+    let driver = ands[6].get_input(0).get_driver().unwrap().unwrap();
+    let root = driver.get_input(0).get_driver().unwrap().unwrap();
+    assert_eq!(
+        root.get_input(0).get_driver().unwrap().get_identifier(),
+        a.get_identifier()
+    );
+    assert_eq!(netlist.buffer_fanout(2, buffer, &AcceptAll).unwrap(), 0);
+}
+
+#[test]
+fn test_clone_fanout() {
+    let netlist: Rc<GateNetlist> = Netlist::new("fanout".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let clk = netlist.insert_input("clk".into());
+    let and = netlist
+        .insert_gate(and_gate(), "and".into(), &[a.clone(), b])
+        .unwrap();
+    and.insert_attribute("keep".to_string(), "1".to_string());
+    let reg = netlist
+        .insert_gate(Gate::dff(), "reg".into(), &[and.get_output(0), clk])
+        .unwrap();
+    let inv = Gate::new_logical("INV".into(), vec!["A".into()], "Y".into());
+    for i in 0..5 {
+        netlist
+            .insert_gate(inv.clone(), format!("inv_{i}").into(), &[reg.get_output(0)])
+            .unwrap()
+            .expose_with_name(format!("y_{i}").into());
+    }
+    and.clone().expose_with_name("z".into());
+
+    // The input port of a is not a load of the AND
+    let port = and.get_input(0);
+    assert!(
+        and.get_output(0)
+            .clone_for_loads(vec![("and_copy".into(), vec![port])])
+            .is_err()
+    );
+    assert!(a.clone_for_loads(vec![]).is_err());
+
+    // Five loads of the register need two copies, and the AND then drives three registers
+    assert_eq!(netlist.clone_fanout(2).unwrap(), 2 + 1);
+    assert!(netlist.verify().is_ok());
+    let copy = netlist.find_instance(&"reg_clone_1".into()).unwrap();
+    assert!(copy.is_register());
+    assert_eq!(
+        copy.get_input(1).get_driver().unwrap().get_identifier(),
+        "clk".into()
+    );
+    let and_copy = netlist.find_instance(&"and_clone_0".into()).unwrap();
+    assert!(and_copy.attributes().any(|a| a.key() == "keep"));
+    assert!(and.drives_a_top_output());
+    assert!(!and_copy.drives_a_top_output());
+    // Inputs cannot be cloned, so only clk is left driving three registers
+    let instances = netlist.objects().filter(|n| !n.is_an_input());
+    for net in instances.flat_map(|n| n.outputs().collect::<Vec<_>>()) {
+        let fanout = netlist
+            .connections()
+            .filter(|c| c.net() == *net.as_net())
+            .count();
+        assert!(fanout <= 2, "{net} drives {fanout} loads");
+    }
+    assert_eq!(netlist.clone_fanout(2).unwrap(), 0);
+}
+
+#[test]
+fn test_lock() {
+    let netlist = get_simple_example();
+    let inputs: Vec<_> = netlist.inputs().collect();
+    {
+        let _outer = netlist.lock();
+        let inner = netlist.lock();
+        assert!(netlist.is_locked());
+        let err = netlist
+            .insert_gate(and_gate(), "inst_1".into(), &inputs)
+            .unwrap_err();
+        assert!(matches!(err, NetlistError::Locked(_)));
+        assert_eq!(err.to_string(), "Netlist example is locked against edits");
+        assert!(netlist.clean().is_err());
+        let inst = netlist.find_instance(&"inst_0".into()).unwrap();
+        assert!(netlist.replace_net_uses(inst.clone(), &inst).is_err());
+        // Edits that cannot fail have fallible versions
+        assert!(matches!(
+            netlist.try_insert_input("c".into()),
+            Err(NetlistError::Locked(_))
+        ));
+        assert!(matches!(
+            netlist.try_expose_net_with_name(inputs[0].clone(), "z".into()),
+            Err(NetlistError::Locked(_))
+        ));
+        assert!(matches!(
+            inputs[1].try_connect(inst.get_input(0)),
+            Err(NetlistError::Locked(_))
+        ));
+        assert!(matches!(
+            inst.try_set_instance_name("inst_2".into()),
+            Err(NetlistError::Locked(_))
+        ));
+        assert!(matches!(
+            inst.try_insert_attribute("keep".to_string(), "1".to_string()),
+            Err(NetlistError::Locked(_))
+        ));
+        assert!(inst.try_as_net_mut().is_err());
+        assert!(inst.get_input(0).try_disconnect().is_err());
+        assert!(inst.try_set_supply(Some(Supply::Ground)).is_err());
+        assert!(
+            netlist
+                .set_port_attribute(&"a".into(), "keep".to_string(), None)
+                .is_err()
+        );
+        assert!(
+            netlist
+                .try_clear_port_attribute(&"a".into(), &"keep".to_string())
+                .is_err()
+        );
+        assert_eq!(netlist.inputs().count(), 2);
+        assert!(inst.get_input(0).get_driver().is_some());
+        assert!(inst.get_supply().is_none());
+        drop(inner);
+        assert!(netlist.is_locked());
+    }
+    assert!(!netlist.is_locked());
+    assert!(
+        netlist
+            .insert_gate(and_gate(), "inst_1".into(), &inputs)
+            .is_ok()
+    );
+}
+
+#[test]
+#[should_panic(expected = "Netlist example is locked against edits")]
+fn test_lock_panics() {
+    let netlist = get_simple_example();
+    let _lock = netlist.lock();
+    netlist.insert_input("c".into());
+}
+
+#[test]
+#[should_panic(expected = "Netlist example is locked against edits")]
+fn test_lock_guards_attributes() {
+    let netlist = get_simple_example();
+    let inst = netlist.find_instance(&"inst_0".into()).unwrap();
+    let _lock = netlist.lock();
+    inst.insert_attribute("keep".to_string(), "1".to_string());
+}
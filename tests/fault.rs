@@ -0,0 +1,146 @@
+use safety_net::fault::{
+    FaultSite, Testability, enumerate_faults, simulate_faults, simulate_faults_packed,
+};
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::sim::{Logic, Simulator, eval_gate, eval_gate_packed};
+use std::rc::Rc;
+
+fn gate(name: &str) -> Gate {
+    Gate::new_logical(name.into(), vec!["A".into(), "B".into()], "Y".into())
+}
+
+fn design() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let c = netlist.insert_input("c".into());
+    netlist
+        .insert_gate(gate("AND"), "and".into(), &[a.clone(), b])
+        .unwrap()
+        .expose_with_name("y".into());
+    netlist
+        .insert_gate(gate("OR"), "or".into(), &[a, c])
+        .unwrap()
+        .expose_with_name("z".into());
+    netlist
+}
+
+fn vector(bits: &[u8]) -> Vec<Logic> {
+    bits.iter().map(|b| (*b == 1).into()).collect()
+}
+
+#[test]
+fn test_fault_simulation() {
+    let netlist = design();
+    let faults = enumerate_faults(&netlist);
+    // Both faults on each of the five nets, and on the two branches of a
+    assert_eq!(faults.len(), 14);
+    let names: Vec<String> = faults.iter().map(|f| f.to_string()).collect();
+    assert_eq!(names[0], "a stuck-at-0");
+    assert!(names.contains(&"and.A stuck-at-1".to_string()));
+    assert!(names.contains(&"or_Y stuck-at-1".to_string()));
+    assert!(!names.iter().any(|n| n.starts_with("and.B")));
+
+    // Forcing a branch of a only changes the instance it feeds
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    let pin = faults
+        .iter()
+        .find(|f| matches!(f.site(), FaultSite::Pin(_)) && !f.value())
+        .unwrap();
+    pin.inject(&mut sim);
+    assert_eq!(sim.step(&vector(&[1, 1, 0])).unwrap(), vector(&[0, 1]));
+    sim.release();
+    assert_eq!(sim.step(&vector(&[1, 1, 0])).unwrap(), vector(&[1, 1]));
+
+    let report = simulate_faults(&netlist, eval_gate, &faults, &[vector(&[1, 1, 0])]).unwrap();
+    assert_eq!(report.detected().len(), 6);
+    assert_eq!(report.to_string(), "6 of 14 faults detected (42.9%)");
+    assert!(
+        report
+            .undetected()
+            .iter()
+            .all(|f| f.value() || f.to_string().starts_with("c "))
+    );
+
+    let exhaustive: Vec<Vec<Logic>> = (0..8)
+        .map(|i| vector(&[i & 1, (i >> 1) & 1, (i >> 2) & 1]))
+        .collect();
+    let report = simulate_faults(&netlist, eval_gate, &faults, &exhaustive).unwrap();
+    assert_eq!(report.coverage(), 1.0);
+    let (first, index) = &report.detected()[1];
+    assert_eq!(first.to_string(), "a stuck-at-1");
+    assert_eq!(*index, 0);
+}
+
+#[test]
+fn test_packed_fault_simulation() {
+    let netlist = design();
+    let faults = enumerate_faults(&netlist);
+    // Seventy vectors, so the last ones fall in a second set of 64, with the detecting ones late
+    let bits: Vec<[u8; 3]> = (0..70)
+        .map(|i| match i {
+            0..66 => [0, 0, 0],
+            _ => [i & 1, (i >> 1) & 1, 1],
+        })
+        .collect();
+    let packed: Vec<Vec<bool>> = bits.iter().map(|b| b.map(|b| b == 1).to_vec()).collect();
+    assert!(simulate_faults_packed(&netlist, eval_gate_packed, &faults, &packed).is_err());
+
+    netlist.set_two_state(true);
+    let vectors: Vec<Vec<Logic>> = bits.iter().map(|b| vector(b)).collect();
+    let expected = simulate_faults(&netlist, eval_gate, &faults, &vectors).unwrap();
+    let report = simulate_faults_packed(&netlist, eval_gate_packed, &faults, &packed).unwrap();
+    assert_eq!(report.to_string(), expected.to_string());
+    let firsts = |r: &safety_net::fault::FaultReport<Gate>| -> Vec<(String, usize)> {
+        r.detected()
+            .iter()
+            .map(|(f, i)| (f.to_string(), *i))
+            .collect()
+    };
+    assert_eq!(firsts(&report), firsts(&expected));
+    assert_eq!(firsts(&report)[0], ("a stuck-at-0".to_string(), 67));
+
+    // The scalar simulator does not accept unknown values in a two-state netlist either
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    assert!(sim.step(&[Logic::X, Logic::One, Logic::Zero]).is_err());
+}
+
+#[test]
+fn test_testability() {
+    let netlist = design();
+    let scoap = netlist.get_analysis::<Testability<_>>().unwrap();
+    let and = netlist.find_instance(&"and".into()).unwrap();
+    let a = and.get_input(0).get_driver().unwrap();
+    let and = and.get_output(0);
+    let or = netlist.find_instance(&"or".into()).unwrap().get_output(0);
+    assert_eq!(scoap.get_controllability(&a, false), Some(1));
+    assert_eq!(scoap.get_controllability(&and, false), Some(2));
+    assert_eq!(scoap.get_controllability(&and, true), Some(3));
+    assert_eq!(scoap.get_controllability(&or, false), Some(3));
+    assert_eq!(scoap.get_observability(&or), Some(0));
+    // a is observed through either gate by setting the other input to its non-controlling value
+    assert_eq!(scoap.get_observability(&a), Some(2));
+    assert_eq!(scoap.get_detection_cost(&and, false), Some(3));
+
+    // Registers are scanned: their outputs are controlled like inputs and their data inputs observed like outputs
+    let netlist = Netlist::new("seq".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let clk = netlist.insert_input("clk".into());
+    let and = netlist
+        .insert_gate(gate("AND"), "and".into(), &[a, b])
+        .unwrap();
+    let q = netlist
+        .insert_gate(Gate::dff(), "r".into(), &[and.clone().into(), clk.clone()])
+        .unwrap();
+    let inv = Gate::new_logical("INV".into(), vec!["A".into()], "Y".into());
+    netlist
+        .insert_gate(inv, "inv".into(), &[q.clone().into()])
+        .unwrap()
+        .expose_with_name("y".into());
+    let scoap = netlist.get_analysis::<Testability<_>>().unwrap();
+    assert_eq!(scoap.get_observability(&and.into()), Some(0));
+    assert_eq!(scoap.get_controllability(&q.clone().into(), true), Some(1));
+    assert_eq!(scoap.get_observability(&q.into()), Some(1));
+    assert_eq!(scoap.get_observability(&clk), None);
+}
@@ -0,0 +1,397 @@
+use safety_net::circuit::{HierPath, Instantiable};
+use safety_net::hier::{
+    HierCell, HierNetlist, Submodule, hier_stats, punch_port, resolve_path, submodules,
+    write_verilog, write_verilog_files,
+};
+use safety_net::netlist::{Gate, Netlist, PathTarget};
+use std::rc::Rc;
+
+fn and_gate() -> HierCell<Gate> {
+    Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into()).into()
+}
+
+fn get_child() -> Rc<HierNetlist<Gate>> {
+    let netlist = Netlist::new("and2".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let instance = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+    instance.expose_with_name("y".into());
+    netlist
+}
+
+#[test]
+fn test_submodule() {
+    let child = get_child();
+    let submodule = Submodule::new(child.clone());
+    assert!(Rc::ptr_eq(submodule.get_netlist(), &child));
+
+    let cell: HierCell<Gate> = submodule.into();
+    assert!(cell.is_submodule());
+    assert_eq!(cell.get_name().to_string(), "and2");
+    let inputs: Vec<String> = cell
+        .get_input_ports()
+        .into_iter()
+        .map(|n| n.get_identifier().to_string())
+        .collect();
+    assert_eq!(inputs, vec!["a", "b"]);
+    assert_eq!(
+        cell.get_single_output_port().get_identifier().to_string(),
+        "y"
+    );
+    assert!(!cell.is_parameterized());
+
+    let top = Netlist::new("top".to_string());
+    let a = top.insert_input("a".into());
+    let b = top.insert_input("b".into());
+    let c = top.insert_input("c".into());
+    let u0 = top.insert_gate(cell.clone(), "u0".into(), &[a, b]).unwrap();
+    let u1 = top
+        .insert_gate(cell, "u1".into(), &[u0.get_output(0), c])
+        .unwrap();
+    u1.expose_with_name("y".into());
+    assert!(top.verify().is_ok());
+    assert_eq!(top.instances_of(&"and2".into()).len(), 2);
+    assert_eq!(
+        top.to_string(),
+        "module top (
+  a,
+  b,
+  c,
+  y
+);
+  input a;
+  wire a;
+  input b;
+  wire b;
+  input c;
+  wire c;
+  output y;
+  wire y;
+  wire u0_y;
+  wire u1_y;
+  and2 u0 (
+    .a(a),
+    .b(b),
+    .y(u0_y)
+  );
+  and2 u1 (
+    .a(u0_y),
+    .b(c),
+    .y(u1_y)
+  );
+  assign y = u1_y;
+endmodule
+"
+    );
+}
+
+/// Instantiates `cell` twice in a chain inside a new netlist named `name`
+fn get_chain(name: &str, cell: HierCell<Gate>) -> Rc<HierNetlist<Gate>> {
+    let netlist = Netlist::new(name.to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let c = netlist.insert_input("c".into());
+    let u0 = netlist
+        .insert_gate(cell.clone(), "u0".into(), &[a, b])
+        .unwrap();
+    let u1 = netlist
+        .insert_gate(cell, "u1".into(), &[u0.get_output(0), c])
+        .unwrap();
+    u1.expose_with_name("y".into());
+    netlist
+}
+
+#[test]
+fn test_multi_module_emission() {
+    let leaf = get_child();
+    let mid = get_chain("mid", Submodule::new(leaf.clone()).into());
+    let top = Netlist::new("top".to_string());
+    let inputs: Vec<_> = ["a", "b", "c", "d"]
+        .iter()
+        .map(|n| top.insert_input((*n).into()))
+        .collect();
+    let m = top
+        .insert_gate(Submodule::new(mid.clone()).into(), "m".into(), &inputs[..3])
+        .unwrap();
+    let l = top
+        .insert_gate(
+            Submodule::new(leaf.clone()).into(),
+            "l".into(),
+            &[m.get_output(0), inputs[3].clone()],
+        )
+        .unwrap();
+    l.expose_with_name("y".into());
+
+    let names: Vec<String> = submodules(&top)
+        .unwrap()
+        .iter()
+        .map(|m| m.get_name().to_string())
+        .collect();
+    assert_eq!(names, vec!["and2", "mid"]);
+
+    let mut verilog = Vec::new();
+    write_verilog(&top, &mut verilog).unwrap();
+    let verilog = String::from_utf8(verilog).unwrap();
+    let modules: Vec<&str> = verilog
+        .lines()
+        .filter_map(|l| l.strip_prefix("module "))
+        .collect();
+    assert_eq!(modules, vec!["and2 (", "mid (", "top ("]);
+    assert!(verilog.starts_with(&leaf.to_string()));
+
+    let dir = std::env::temp_dir().join(format!("safety_net_hier_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let paths = write_verilog_files(&top, &dir).unwrap();
+    assert_eq!(paths.last().unwrap(), &dir.join("top.v"));
+    assert_eq!(
+        std::fs::read_to_string(dir.join("mid.v")).unwrap(),
+        mid.to_string()
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Two different definitions cannot share a name
+    let other = get_chain("and2", and_gate());
+    let cells = [Submodule::new(leaf), Submodule::new(other)];
+    let top = Netlist::new("top".to_string());
+    let a = top.insert_input("a".into());
+    let b = top.insert_input("b".into());
+    let c = top.insert_input("c".into());
+    top.insert_gate(
+        cells[0].clone().into(),
+        "u0".into(),
+        &[a.clone(), b.clone()],
+    )
+    .unwrap();
+    top.insert_gate(cells[1].clone().into(), "u1".into(), &[a, b, c])
+        .unwrap();
+    assert!(submodules(&top).is_err());
+}
+
+#[test]
+fn test_hier_stats() {
+    let leaf = get_child();
+    let mid = get_chain("mid", Submodule::new(leaf.clone()).into());
+    let top = Netlist::new("top".to_string());
+    let inputs: Vec<_> = ["a", "b", "c", "d"]
+        .iter()
+        .map(|n| top.insert_input((*n).into()))
+        .collect();
+    let m = top
+        .insert_gate(Submodule::new(mid.clone()).into(), "m".into(), &inputs[..3])
+        .unwrap();
+    let and = top
+        .insert_gate(
+            and_gate(),
+            "inst_0".into(),
+            &[m.get_output(0), inputs[3].clone()],
+        )
+        .unwrap();
+    and.expose_with_name("y".into());
+
+    let stats = hier_stats(&top, |_| 1.5).unwrap();
+    assert_eq!(stats.total_cells(), 3);
+    assert_eq!(stats.cells(), 1);
+    assert_eq!(stats.total_area(), 4.5);
+    let u1 = stats.find("m/u1").unwrap();
+    assert_eq!(u1.get_module(), "and2");
+    assert_eq!(u1.total_cells(), 1);
+    let paths: Vec<String> = stats.paths().into_iter().map(|(p, _)| p).collect();
+    assert_eq!(paths, vec!["top", "top/m", "top/m/u0", "top/m/u1"]);
+
+    let modules = stats.by_module();
+    assert_eq!(modules[0].get_module(), "top");
+    let and2 = modules.iter().find(|m| m.get_module() == "and2").unwrap();
+    assert_eq!(and2.instances(), 2);
+    assert_eq!(and2.total_area(), 3.0);
+    assert_eq!(
+        and2.to_string(),
+        "and2: 2 instances of 1 cells, area 1.500 each, 3.000 total"
+    );
+
+    assert_eq!(
+        stats.to_string(),
+        "top (top): 3 cells, area 4.500 (own 1 cells, area 1.500)
+└── m (mid): 2 cells, area 3.000 (own 0 cells, area 0.000)
+    ├── u0 (and2): 1 cells, area 1.500 (own 1 cells, area 1.500)
+    └── u1 (and2): 1 cells, area 1.500 (own 1 cells, area 1.500)
+"
+    );
+}
+
+#[test]
+fn test_inout_submodule() {
+    let pad_cell = Netlist::new("pad_cell".to_string());
+    let a = pad_cell.insert_input("a".into());
+    let pad = pad_cell.insert_inout("pad".into());
+    let drv = pad_cell
+        .insert_gate(and_gate(), "drv".into(), &[a, pad.clone()])
+        .unwrap();
+    pad_cell.drive_inout(&drv.get_output(0), &pad).unwrap();
+    pad.expose_with_name("y".into());
+
+    let cell: HierCell<Gate> = Submodule::new(pad_cell).into();
+    let inputs: Vec<String> = cell
+        .get_input_ports()
+        .into_iter()
+        .map(|n| n.get_identifier().to_string())
+        .collect();
+    assert_eq!(inputs, vec!["a", "pad"]);
+    assert!(!cell.is_inout_port(0));
+    assert!(cell.is_inout_port(1));
+}
+
+#[test]
+fn test_resolve_path() {
+    let alu = Netlist::new("alu".to_string());
+    let a = alu.insert_input("a".into());
+    let b = alu.insert_input("b".into());
+    alu.insert_gate(and_gate(), "add0".into(), &[a, b])
+        .unwrap()
+        .expose_with_name("y".into());
+    let core = get_chain("core", Submodule::new(alu.clone()).into());
+    let top = Netlist::new("top".to_string());
+    let inputs: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .map(|n| top.insert_input((*n).into()))
+        .collect();
+    let cell: HierCell<Gate> = Submodule::new(core.clone()).into();
+    top.insert_gate(cell, "u_core".into(), &inputs)
+        .unwrap()
+        .expose_with_name("y".into());
+    // The chain of the core names its two ALUs u0 and u1
+    let resolve = |path: &str| resolve_path(&top, &path.parse().unwrap());
+
+    let (module, target) = resolve("top/u_core/u1/add0/Y").unwrap();
+    assert!(Rc::ptr_eq(&module, &alu));
+    match target {
+        PathTarget::Net(net) => assert_eq!(net.get_identifier(), "add0_Y".into()),
+        _ => panic!("Expected a net"),
+    }
+    match resolve("top/u_core/u1/add0/B") {
+        Some((_, PathTarget::InputPort(port))) => {
+            assert_eq!(port.get_driver().unwrap().get_identifier(), "b".into())
+        }
+        _ => panic!("Expected an input port"),
+    }
+    match resolve("top/u_core/u1/add0") {
+        Some((_, PathTarget::Instance(inst))) => {
+            assert_eq!(inst.get_instance_name(), Some("add0".into()))
+        }
+        _ => panic!("Expected an instance"),
+    }
+    // A path ending at a submodule instance names the instance in its parent
+    match resolve("top/u_core/u1") {
+        Some((module, PathTarget::Instance(inst))) => {
+            assert!(Rc::ptr_eq(&module, &core));
+            assert!(inst.get_instance_type().unwrap().is_submodule());
+        }
+        _ => panic!("Expected an instance"),
+    }
+    assert!(resolve("top/u_core/u2/add0/Y").is_none());
+    assert!(resolve("top/u_core/u1/nope").is_none());
+    assert!(resolve("core/u1/add0/Y").is_none());
+}
+
+#[test]
+fn test_punch_port() {
+    // A NAND with the AND output inside it
+    let leaf = Netlist::new("nand2".to_string());
+    let a = leaf.insert_input("a".into());
+    let b = leaf.insert_input("b".into());
+    let and = leaf
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+    let inv: HierCell<Gate> = Gate::new_logical("INV".into(), vec!["A".into()], "Y".into()).into();
+    let inv = leaf
+        .insert_gate(inv, "inst_1".into(), &[and.get_output(0)])
+        .unwrap();
+    inv.expose_with_name("y".into());
+
+    let mid = get_chain("mid", Submodule::new(leaf.clone()).into());
+    let top = Netlist::new("top".to_string());
+    let inputs: Vec<_> = ["a", "b", "c", "d", "e"]
+        .iter()
+        .map(|n| top.insert_input((*n).into()))
+        .collect();
+    let cell: HierCell<Gate> = Submodule::new(mid.clone()).into();
+    let u0 = top
+        .insert_gate(cell.clone(), "u0".into(), &inputs[..3])
+        .unwrap();
+    let u1 = top
+        .insert_gate(
+            cell,
+            "u1".into(),
+            &[u0.get_output(0), inputs[3].clone(), inputs[4].clone()],
+        )
+        .unwrap();
+    u1.expose_with_name("y".into());
+
+    let path: HierPath = "top/u0/u1/inst_0/Y".parse().unwrap();
+    assert!(punch_port(&top, &"top/u0/u1/nope".parse().unwrap(), "dbg".into()).is_err());
+    assert!(punch_port(&top, &path, "y".into()).is_err());
+    let punched = punch_port(&top, &path, "dbg".into()).unwrap();
+    assert_eq!(punched.get_identifier().to_string(), "u0_dbg");
+
+    for netlist in [&leaf, &mid, &top] {
+        assert!(netlist.verify().is_ok());
+        assert!(netlist.find_port(&"dbg".into()).is_some());
+    }
+    // Both instances of each module gain the port, but only the one on the path is connected up
+    assert!(mid.to_string().contains(
+        "  nand2 u1 (
+    .a(u0_y),
+    .b(c),
+    .y(u1_y),
+    .dbg(u1_dbg)
+  );"
+    ));
+    assert!(mid.to_string().contains("assign dbg = u1_dbg;"));
+    assert!(top.to_string().contains(
+        "  mid u1 (
+    .a(u0_y),
+    .b(d),
+    .c(e),
+    .y(u1_y),
+    .dbg(u1_dbg)
+  );"
+    ));
+    assert!(top.to_string().contains("assign dbg = u0_dbg;"));
+}
+
+#[test]
+fn test_punch_port_keeps_outputs() {
+    let leaf = Netlist::new("leaf".to_string());
+    let a = leaf.insert_input("a".into());
+    let inv: HierCell<Gate> = Gate::new_logical("INV".into(), vec!["A".into()], "Y".into()).into();
+    leaf.insert_gate(inv, "inv".into(), &[a])
+        .unwrap()
+        .expose_with_name("y".into());
+    let top = Netlist::new("top".to_string());
+    let x = top.insert_input("x".into());
+    let cell: HierCell<Gate> = Submodule::new(leaf.clone()).into();
+    top.insert_gate(cell, "u".into(), &[x])
+        .unwrap()
+        .expose_with_name("y".into());
+
+    // The punched net is already the output y of the leaf, so it is exposed again through an alias
+    let path: HierPath = "top/u/inv/Y".parse().unwrap();
+    punch_port(&top, &path, "dbg".into()).unwrap();
+    assert_eq!(leaf.ports().len(), 3);
+    assert!(leaf.find_port(&"y".into()).is_some());
+    assert!(leaf.to_string().contains("assign dbg = inv_Y;"));
+    for netlist in [&leaf, &top] {
+        assert!(netlist.verify().is_ok());
+    }
+    assert!(top.to_string().contains(".y(u_y),"));
+
+    // A failure in the parent, after the leaf gained the port, leaves every module unchanged
+    top.set_check_drivers(true);
+    top.insert_input("u_late".into());
+    let before = (leaf.to_string(), top.to_string());
+    assert!(punch_port(&top, &path, "late".into()).is_err());
+    assert!(leaf.find_port(&"late".into()).is_none());
+    assert_eq!(before, (leaf.to_string(), top.to_string()));
+    assert!(leaf.verify().is_ok());
+}
@@ -0,0 +1,168 @@
+#![cfg(feature = "journal")]
+
+use safety_net::journal::{Edit, Journal};
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::verilog::InstanceSpec;
+use std::rc::Rc;
+
+fn and_gate() -> Gate {
+    Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into())
+}
+
+fn inverter() -> Gate {
+    Gate::new_logical("INV".into(), vec!["I".into()], "O".into())
+}
+
+fn cells(spec: &InstanceSpec) -> Result<Gate, String> {
+    match spec.cell_type.to_string().as_str() {
+        "AND" => Ok(and_gate()),
+        "INV" => Ok(inverter()),
+        t => Err(format!("Unknown cell {t}")),
+    }
+}
+
+fn inputs_only() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("example".to_string());
+    netlist.insert_input("a".into());
+    netlist.insert_input("b".into());
+    netlist
+}
+
+#[test]
+fn test_journal_replay() {
+    let netlist = inputs_only();
+    assert!(netlist.get_journal().is_none());
+    netlist.start_journal("alice".to_string());
+
+    let a = netlist.first().unwrap();
+    let b = netlist.last().unwrap();
+    let and = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.into(), b.clone().into()])
+        .unwrap();
+    let inv = netlist
+        .insert_gate(inverter(), "inst_1".into(), &[and.clone().into()])
+        .unwrap();
+    let inv = inv.expose_with_name("y".into());
+
+    netlist.set_journal_author("bob".to_string());
+    b.get_output(0).connect(and.get_input(0));
+    netlist.replace_net_uses(inv, &and).unwrap();
+    assert!(netlist.clean().unwrap());
+    assert!(netlist.verify().is_ok());
+
+    let journal = netlist.take_journal().unwrap();
+    assert!(netlist.get_journal().is_none());
+    let kinds: Vec<String> = journal
+        .entries()
+        .iter()
+        .map(|e| e.edit().to_string())
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "insert_instance inst_0 AND a b",
+            "insert_instance inst_1 INV inst_0_Y",
+            "expose inst_1_O y",
+            "connect b inst_0.0",
+            "replace_uses inst_1 inst_0",
+            "remove inst_1",
+        ]
+    );
+    assert_eq!(journal.entries()[2].author(), "alice");
+    assert_eq!(journal.entries()[3].author(), "bob");
+    assert!(matches!(journal.entries()[5].edit(), Edit::Remove(_)));
+
+    // Every line of the export has the sequence number, the time, the author and the edit
+    let mut log = Vec::new();
+    journal.export(&mut log).unwrap();
+    let log = String::from_utf8(log).unwrap();
+    assert_eq!(log.lines().count(), 6);
+    let fields: Vec<&str> = log.lines().nth(3).unwrap().splitn(4, ' ').collect();
+    assert_eq!(fields[0], "3");
+    assert!(fields[1].parse::<f64>().is_ok());
+    assert_eq!(fields[2], "bob");
+    assert_eq!(fields[3], "connect b inst_0.0");
+
+    // Replaying the journal on a fresh copy gives the same netlist
+    let copy = inputs_only();
+    journal.replay(&copy).unwrap();
+    assert_eq!(copy.to_string(), netlist.to_string());
+
+    // Replaying on a netlist that does not match fails on the first edit that cannot be made
+    let empty = Netlist::new("example".to_string());
+    let err = journal.replay(&empty).unwrap_err();
+    assert!(err.starts_with("Cannot replay edit 0"));
+}
+
+#[test]
+fn test_journal_import() {
+    let netlist = Netlist::new("example".to_string());
+    netlist.start_journal("Alice Smith".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let and = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+    and.insert_attribute("keep".to_string(), "yes please".to_string());
+    and.set_attribute("dont_touch".to_string());
+    and.clear_attribute(&"dont_touch".to_string());
+    and.set_instance_name("my and".into());
+    and.set_identifier("sum".into());
+    and.expose_with_name("y".into());
+
+    let journal = netlist.take_journal().unwrap();
+    let kinds: Vec<String> = journal
+        .entries()
+        .iter()
+        .map(|e| e.edit().to_string())
+        .collect();
+    assert_eq!(
+        kinds[3..8],
+        [
+            "set_attribute inst_0 keep \"yes please\"",
+            "set_attribute inst_0 dont_touch",
+            "clear_attribute inst_0 dont_touch",
+            r#"rename_instance inst_0 "\\my and""#,
+            "rename_net inst_0_Y sum",
+        ]
+    );
+
+    // The export reads back, with the author quoted and the time in milliseconds
+    let mut log = Vec::new();
+    journal.export(&mut log).unwrap();
+    let log = String::from_utf8(log).unwrap();
+    let line = log.lines().next().unwrap();
+    assert!(line.starts_with("0 "));
+    assert!(line.contains(" \"Alice Smith\" insert_input a logic a"));
+    let (_, time) = line.split_once(' ').unwrap();
+    let (_, millis) = time.split_once(' ').unwrap().0.split_once('.').unwrap();
+    assert_eq!(millis.len(), 3);
+
+    let imported = Journal::import(log.as_bytes(), cells).unwrap();
+    assert_eq!(imported.len(), journal.len());
+    assert_eq!(imported.get_author(), "Alice Smith");
+    assert_eq!(imported.to_string(), journal.to_string());
+    for (i, e) in imported.entries().iter().zip(journal.entries()) {
+        assert_eq!(i.author(), "Alice Smith");
+        assert_eq!(
+            i.time()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis(),
+            e.time()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis()
+        );
+    }
+
+    let copy = Netlist::new("example".to_string());
+    imported.replay(&copy).unwrap();
+    assert_eq!(copy.to_string(), netlist.to_string());
+
+    // Unknown cells and malformed lines are reported with their line
+    let err = Journal::import("0 1.000 bob insert_instance x XOR a b".as_bytes(), cells);
+    assert_eq!(err.unwrap_err(), "Line 1: Instance x: Unknown cell XOR");
+    let err = Journal::import("\n0 1.000 \"bob remove x".as_bytes(), cells);
+    assert!(err.unwrap_err().starts_with("Line 2: Unterminated quote"));
+}
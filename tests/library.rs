@@ -0,0 +1,79 @@
+use safety_net::cost::{CostModel, netlist_cost};
+use safety_net::library::{CellLibrary, LibraryCell};
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use std::rc::Rc;
+
+fn nangate() -> CellLibrary<Gate> {
+    let and2 = Gate::new_logical(
+        "AND2_X1".into(),
+        vec!["A1".into(), "A2".into()],
+        "ZN".into(),
+    );
+    let buf = |drive: usize| {
+        let name = format!("BUF_X{drive}");
+        LibraryCell::new(Gate::new_logical(
+            name.as_str().into(),
+            vec!["A".into()],
+            "Z".into(),
+        ))
+        .with_area(0.5 * drive as f64)
+        .with_delay(2.0 / drive as f64)
+        .with_drive(drive as f64)
+    };
+    CellLibrary::new("nangate".to_string())
+        .with_cell(LibraryCell::new(and2).with_area(1.0).with_delay(1.5))
+        .with_cell(buf(4))
+        .with_cell(buf(1))
+        .with_cell(buf(2))
+}
+
+#[test]
+fn test_cell_library() {
+    let mut library = nangate();
+    assert_eq!(library.get_name(), "nangate");
+    assert_eq!(library.len(), 4);
+    let and2 = library.get(&"AND2_X1".into()).unwrap();
+    assert_eq!(and2.area(), 1.0);
+    assert_eq!(and2.drive(), 1.0);
+    assert!(library.get(&"OR2_X1".into()).is_none());
+
+    // Cells are unique by name
+    let dup = LibraryCell::new(library.cell(&"BUF_X1".into()).unwrap());
+    assert!(library.add(dup).is_err());
+
+    let bufs: Vec<String> = library
+        .drives_of(|c| c.get_name().get_name().starts_with("BUF_X"))
+        .iter()
+        .map(|c| c.get_name().to_string())
+        .collect();
+    assert_eq!(bufs, vec!["BUF_X1", "BUF_X2", "BUF_X4"]);
+
+    // Instances are inserted from the library, which also costs them
+    let netlist: Rc<GateNetlist> = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let and = library
+        .insert(&netlist, &"AND2_X1".into(), "and".into(), &[a, b])
+        .unwrap();
+    let buf = library
+        .insert(&netlist, &"BUF_X4".into(), "buf".into(), &[and.into()])
+        .unwrap();
+    buf.expose_with_name("y".into());
+    assert!(netlist.verify().is_ok());
+    assert!(
+        library
+            .insert(&netlist, &"OR2_X1".into(), "or".into(), &[])
+            .is_err()
+    );
+
+    let cost = netlist_cost(&library, &netlist);
+    assert_eq!(cost.area, 3.0);
+    assert_eq!(cost.delay, 1.5);
+    assert!(library.missing_cells(&netlist).is_empty());
+
+    let inv = Gate::new_logical("INV".into(), vec!["I".into()], "O".into());
+    assert_eq!(library.area(&inv), 0.0);
+    let a = netlist.first().unwrap();
+    netlist.insert_gate(inv, "inv".into(), &[a.into()]).unwrap();
+    assert_eq!(library.missing_cells(&netlist), vec!["INV".into()]);
+}
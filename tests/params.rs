@@ -1,11 +1,15 @@
-use bitvec::vec::BitVec;
+use bitvec::prelude::*;
 use safety_net::{
     assert_verilog_eq,
     attribute::Parameter,
     circuit::{Identifier, Instantiable, Net},
+    emit::{ParamRadix, VerilogEmitter},
     format_id,
+    hier::{HierCell, HierNetlist, Submodule, elaborate, submodules},
     netlist::Netlist,
+    verilog::{InstanceSpec, assert_roundtrip, read_verilog},
 };
+use std::{collections::HashMap, rc::Rc};
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -64,6 +68,22 @@ impl Instantiable for Lut {
             Parameter::BitVec(self.lookup_table.clone()),
         ))
     }
+
+    fn get_input_default(&self, _index: usize) -> Option<bool> {
+        Some(false)
+    }
+
+    fn set_parameter(&mut self, id: &Identifier, val: Parameter) -> Option<Parameter> {
+        match val {
+            Parameter::BitVec(bv)
+                if self.has_parameter(id) && bv.len() == self.lookup_table.len() =>
+            {
+                let old = std::mem::replace(&mut self.lookup_table, bv);
+                Some(Parameter::BitVec(old))
+            }
+            _ => None,
+        }
+    }
 }
 
 #[test]
@@ -112,3 +132,299 @@ fn param_verilog() {
          endmodule\n"
     );
 }
+
+#[test]
+fn param_roundtrip() {
+    let netlist = Netlist::new("example".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let nand = netlist
+        .insert_gate(Lut::new(2, 7), "inst_0".into(), &[a.clone(), b])
+        .unwrap();
+    let xor = netlist
+        .insert_gate(Lut::new(2, 6), "inst_1".into(), &[nand.get_output(0), a])
+        .unwrap();
+    xor.expose_with_name("y".into());
+    assert_roundtrip(&netlist);
+
+    // Cells can also be rebuilt from the parameters that were read back
+    let read = read_verilog(netlist.to_string().as_bytes(), |spec: &InstanceSpec| {
+        let mut lut = Lut::new(spec.ports.len() - 1, 0);
+        for (k, v) in &spec.parameters {
+            lut.set_parameter(k, v.clone())
+                .ok_or(format!("Bad parameter {k}"))?;
+        }
+        Ok(lut)
+    })
+    .unwrap();
+    assert_verilog_eq!(read.to_string(), netlist.to_string());
+}
+
+#[test]
+fn real_params() {
+    // Reals are written with a decimal point, so they are read back as reals rather than integers
+    let reals = [1.5, 2.0, -3.0, 0.0000001, 1e20];
+    let written: Vec<String> = reals
+        .iter()
+        .map(|r| Parameter::Real(*r).to_string())
+        .collect();
+    assert_eq!(written[0], "1.5");
+    assert_eq!(written[1], "2.0");
+    assert_eq!(written[2], "-3.0");
+    for (r, w) in reals.iter().zip(&written) {
+        assert!(w.contains('.'));
+        assert_eq!(w.parse::<f32>().unwrap(), *r);
+    }
+}
+
+#[test]
+fn param_emission_options() {
+    let netlist = Netlist::new("example".to_string());
+    let inputs: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .map(|n| netlist.insert_input((*n).into()))
+        .collect();
+    let instance = netlist
+        .insert_gate(Lut::new(3, 0xe8), "inst_0".into(), &inputs)
+        .unwrap();
+    instance.expose_with_name("y".into());
+
+    let emit = |emitter: &mut VerilogEmitter| {
+        let mut s = String::new();
+        netlist.emit(emitter, &mut s).unwrap();
+        s
+    };
+    let inline = emit(&mut VerilogEmitter::new().with_radix(ParamRadix::Hex));
+    assert!(inline.contains(".INIT(8'he8)"));
+    assert_eq!(emit(&mut VerilogEmitter::new()), netlist.to_string());
+
+    let defparams = emit(
+        &mut VerilogEmitter::new()
+            .with_defparams(true)
+            .with_radix(ParamRadix::Hex),
+    );
+    assert!(!defparams.contains("#("));
+    assert!(defparams.contains(
+        "  LUT3 inst_0 (
+    .I0(a),
+    .I1(b),
+    .I2(c),
+    .O(inst_0_O)
+  );
+  defparam inst_0.INIT = 8'he8;
+"
+    ));
+
+    // A partial top digit only holds the remaining bits
+    let instance = netlist
+        .insert_gate(Lut::new(1, 0b10), "inst_1".into(), &inputs[..1])
+        .unwrap()
+        .expose_with_name("z".into());
+    let inline = emit(&mut VerilogEmitter::new().with_radix(ParamRadix::Hex));
+    assert!(inline.contains(".INIT(2'h2)"));
+
+    // An initial value would set INIT a second time
+    instance.set_initial_value(Some(true));
+    let err = netlist.emit(&mut VerilogEmitter::new(), &mut String::new());
+    assert!(err.unwrap_err().to_string().contains("INIT parameter"));
+    // Display writes the reason instead of failing, so to_string does not panic
+    assert!(netlist.to_string().starts_with("// Cannot write module"));
+    instance.set_initial_value(None);
+
+    // An empty table has no literal in either radix
+    let mut empty = Lut::new(0, 0);
+    empty.lookup_table.clear();
+    netlist
+        .insert_gate(empty, "inst_2".into(), &[])
+        .unwrap()
+        .expose_with_name("w".into());
+    for radix in [ParamRadix::Binary, ParamRadix::Hex] {
+        let mut emitter = VerilogEmitter::new().with_radix(radix);
+        let err = netlist.emit(&mut emitter, &mut String::new()).unwrap_err();
+        assert!(err.to_string().contains("empty bit vector"));
+    }
+}
+
+#[test]
+fn param_elaboration() {
+    let netlist = Netlist::new("example".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let instance = netlist
+        .insert_gate(Lut::new(2, 7), "inst_0".into(), &[a.clone(), b])
+        .unwrap();
+    let instance = instance.expose_with_name("y".into());
+
+    let init: Identifier = "INIT".into();
+    let and_table = Parameter::BitVec(bitvec![0, 0, 0, 1]);
+
+    // Unknown instances and parameters are rejected
+    let overrides = HashMap::from([("inst_1".into(), vec![(init.clone(), and_table.clone())])]);
+    assert!(netlist.elaborate(&overrides).is_err());
+    let overrides = HashMap::from([(
+        "inst_0".into(),
+        vec![("WIDTH".into(), Parameter::Integer(2))],
+    )]);
+    assert!(netlist.elaborate(&overrides).is_err());
+
+    // A table of the wrong width on one instance leaves the other untouched
+    let other = netlist
+        .insert_gate(Lut::new(1, 1), "inst_2".into(), &[a])
+        .unwrap();
+    other.expose_with_name("z".into());
+    let before = instance.get_instance_type().unwrap().get_parameter(&init);
+    let overrides = HashMap::from([
+        ("inst_0".into(), vec![(init.clone(), and_table.clone())]),
+        ("inst_2".into(), vec![(init.clone(), and_table.clone())]),
+    ]);
+    assert!(netlist.elaborate(&overrides).is_err());
+    assert_eq!(
+        instance.get_instance_type().unwrap().get_parameter(&init),
+        before
+    );
+
+    let overrides = HashMap::from([("inst_0".into(), vec![(init.clone(), and_table.clone())])]);
+    assert!(netlist.elaborate(&overrides).is_ok());
+    assert_eq!(
+        instance.get_instance_type().unwrap().get_parameter(&init),
+        Some(and_table)
+    );
+}
+
+/// Returns the module instantiated by `inst` in `netlist`
+fn module_of(netlist: &HierNetlist<Lut>, inst: &str) -> Rc<HierNetlist<Lut>> {
+    let inst = netlist.find_instance(&inst.into()).unwrap();
+    let cell = inst.get_instance_type().unwrap();
+    cell.get_submodule().unwrap().get_netlist().clone()
+}
+
+/// Returns the INIT table of the LUT `inst` in `netlist`
+fn init_of(netlist: &HierNetlist<Lut>, inst: &str) -> Option<Parameter> {
+    let inst = netlist.find_instance(&inst.into()).unwrap();
+    inst.get_instance_type()
+        .unwrap()
+        .get_parameter(&"INIT".into())
+}
+
+#[test]
+fn hier_param_elaboration() {
+    let alu = Netlist::new("alu".to_string());
+    let a = alu.insert_input("a".into());
+    let b = alu.insert_input("b".into());
+    let add0 = alu
+        .insert_gate(HierCell::from(Lut::new(2, 7)), "add0".into(), &[a, b])
+        .unwrap();
+    add0.expose_with_name("y".into());
+
+    let core = Netlist::new("core".to_string());
+    let a = core.insert_input("a".into());
+    let b = core.insert_input("b".into());
+    let cell = HierCell::from(Submodule::new(alu.clone()));
+    let u0 = core
+        .insert_gate(cell.clone(), "u0".into(), &[a, b.clone()])
+        .unwrap();
+    let u1 = core
+        .insert_gate(cell, "u1".into(), &[u0.get_output(0), b])
+        .unwrap();
+    u1.expose_with_name("y".into());
+
+    let top = Netlist::new("top".to_string());
+    let a = top.insert_input("a".into());
+    let b = top.insert_input("b".into());
+    let cell = HierCell::from(Submodule::new(core.clone()));
+    for name in ["u_core", "u_same", "u_other"] {
+        top.insert_gate(cell.clone(), name.into(), &[a.clone(), b.clone()])
+            .unwrap()
+            .expose_with_name(format_id!("y_{name}"));
+    }
+
+    let nand_table = init_of(&alu, "add0");
+    let and_table = Parameter::BitVec(bitvec![0, 0, 0, 1]);
+    let or_table = Parameter::BitVec(bitvec![0, 1, 1, 1]);
+
+    // A parameter that names no instance in the module, or an instance that does not exist, changes nothing
+    let overrides = HashMap::from([("u_core".into(), vec![("INIT".into(), and_table.clone())])]);
+    assert!(elaborate(&top, &overrides).is_err());
+    let overrides = HashMap::from([(
+        "u_core".into(),
+        vec![("u0.add1.INIT".into(), and_table.clone())],
+    )]);
+    assert!(elaborate(&top, &overrides).is_err());
+    assert!(Rc::ptr_eq(&module_of(&top, "u_core"), &core));
+
+    // Instances with the same overrides share a copy, and the original modules keep their parameters
+    let overrides = HashMap::from([
+        (
+            "u_core".into(),
+            vec![("u0.add0.INIT".into(), and_table.clone())],
+        ),
+        (
+            "u_same".into(),
+            vec![("u0.add0.INIT".into(), and_table.clone())],
+        ),
+        (
+            "u_other".into(),
+            vec![("u1.add0.INIT".into(), or_table.clone())],
+        ),
+    ]);
+    assert!(elaborate(&top, &overrides).is_ok());
+    let core_1 = module_of(&top, "u_core");
+    assert!(Rc::ptr_eq(&core_1, &module_of(&top, "u_same")));
+    let core_2 = module_of(&top, "u_other");
+    assert!(!Rc::ptr_eq(&core_1, &core_2) && !Rc::ptr_eq(&core_1, &core));
+    let mut names = vec![core_1.get_name(), core_2.get_name()];
+    names.sort();
+    assert_eq!(names, vec!["core_1", "core_2"]);
+
+    assert_eq!(init_of(&module_of(&core_1, "u0"), "add0"), Some(and_table));
+    assert!(Rc::ptr_eq(&module_of(&core_1, "u1"), &alu));
+    assert_eq!(init_of(&module_of(&core_2, "u1"), "add0"), Some(or_table));
+    assert!(Rc::ptr_eq(&module_of(&core_2, "u0"), &alu));
+    assert!(Rc::ptr_eq(&module_of(&core, "u0"), &alu));
+    assert_eq!(init_of(&alu, "add0"), nand_table);
+
+    // The copies are complete modules, so the bound design verifies and emits each of them
+    for module in submodules(&top).unwrap() {
+        assert!(module.verify().is_ok());
+    }
+    assert!(top.verify().is_ok());
+    assert_eq!(submodules(&top).unwrap().len(), 5);
+}
+
+#[test]
+fn tie_unconnected_inputs() {
+    let netlist = Netlist::new("example".to_string());
+    // The input takes the name the constant would get
+    let a = netlist.insert_input("tie_0".into());
+    for i in 0..2 {
+        let lut = netlist
+            .insert_gate_disconnected(Lut::new(2, 8), format_id!("inst_{i}"))
+            .unwrap();
+        lut.get_input(0).connect(a.clone());
+        lut.expose_with_name(format_id!("y{i}"));
+    }
+
+    // Both LUTs share the same constant
+    let tied = netlist
+        .tie_unconnected_inputs(|v| Lut::new(0, v as usize))
+        .unwrap();
+    assert_eq!(tied, 2);
+    assert_eq!(netlist.objects().count(), 4);
+    let tie = netlist.find_instance(&"tie_0_0".into()).unwrap();
+    assert!(
+        netlist
+            .objects()
+            .all(|o| o.is_an_input() || o.is_fully_connected())
+    );
+    assert_eq!(
+        netlist
+            .find_instance(&"inst_1".into())
+            .unwrap()
+            .get_driver(1),
+        Some(tie)
+    );
+    assert_eq!(
+        netlist.tie_unconnected_inputs(|_| unreachable!()).unwrap(),
+        0
+    );
+}
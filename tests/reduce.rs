@@ -0,0 +1,84 @@
+use safety_net::circuit::Instantiable;
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::reduce::reduce;
+use std::rc::Rc;
+
+fn gate(name: &str, inputs: &[&str]) -> Gate {
+    Gate::new_logical(
+        name.into(),
+        inputs.iter().map(|i| (*i).into()).collect(),
+        "Y".into(),
+    )
+}
+
+fn design() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let c = netlist.insert_input("c".into());
+    let and = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "and".into(), &[a.clone(), b])
+        .unwrap();
+    let xor = netlist
+        .insert_gate(
+            gate("XOR", &["A", "B"]),
+            "xor".into(),
+            &[and.get_output(0), c.clone()],
+        )
+        .unwrap();
+    let or = netlist
+        .insert_gate(gate("OR", &["A", "B"]), "or".into(), &[xor.into(), a])
+        .unwrap();
+    let inv = netlist
+        .insert_gate(gate("INV", &["A"]), "inv".into(), &[c])
+        .unwrap();
+    and.expose_with_name("y0".into());
+    or.expose_with_name("y1".into());
+    inv.expose_with_name("y2".into());
+    netlist
+}
+
+fn has_xor(netlist: &GateNetlist) -> bool {
+    netlist
+        .objects()
+        .filter_map(|n| n.get_instance_type().map(|t| t.get_name().clone()))
+        .any(|name| name == "XOR".into())
+}
+
+#[test]
+fn test_reduce() {
+    let netlist = design();
+    let mut calls = 0;
+    let reduced = reduce(&netlist, |n| {
+        calls += 1;
+        n.verify().is_ok() && has_xor(n)
+    })
+    .unwrap();
+    assert!(calls > 1);
+    assert!(has_xor(&reduced));
+    assert!(reduced.verify().is_ok());
+    assert_eq!(reduced.get_name(), "top");
+
+    // Only the output through the XOR is left, with the AND behind it cut to an input
+    let outputs: Vec<String> = reduced
+        .get_output_ports()
+        .iter()
+        .map(|n| n.get_identifier().to_string())
+        .collect();
+    assert_eq!(outputs, vec!["y1"]);
+    assert!(reduced.find_instance(&"and".into()).is_none());
+    assert!(reduced.find_instance(&"inv".into()).is_none());
+    assert_eq!(reduced.objects().filter(|n| !n.is_an_input()).count(), 2);
+    let inputs: Vec<String> = reduced
+        .inputs()
+        .map(|i| i.get_identifier().to_string())
+        .collect();
+    assert_eq!(inputs.len(), 3);
+    assert!(inputs.contains(&"and_Y".to_string()));
+
+    // The original netlist is left unchanged
+    assert_eq!(netlist.outputs().len(), 3);
+    assert!(netlist.find_instance(&"and".into()).is_some());
+
+    assert!(reduce(&netlist, |_| false).is_err());
+}
@@ -0,0 +1,125 @@
+use safety_net::assert_verilog_eq;
+use safety_net::netlist::Gate;
+use safety_net::netlist::GateNetlist;
+use safety_net::netlist::Netlist;
+use safety_net::script::CommandInterpreter;
+use std::rc::Rc;
+
+fn and_gate() -> Gate {
+    Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into())
+}
+
+fn buf_gate() -> Gate {
+    Gate::new_logical("BUF".into(), vec!["I".into()], "O".into())
+}
+
+fn get_simple_example() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("example".to_string());
+
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+
+    let instance = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+
+    instance.expose_with_name("y".into());
+
+    netlist
+}
+
+#[test]
+fn test_script_edits() {
+    let netlist = get_simple_example();
+    let interp = CommandInterpreter::new(netlist.clone()).with_buffer(buf_gate());
+    let script = "
+        # Buffer input a and tag the AND gate
+        insert_buffer a buf_0
+        set_attribute inst_0 dont_touch
+        rename_net inst_0_Y and_out
+    ";
+    assert!(interp.run(script).is_ok());
+    assert!(netlist.verify().is_ok());
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module example (
+           a,
+           b,
+           y
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           wire and_out;
+           wire buf_0_O;
+           (* dont_touch *)
+           AND inst_0 (
+             .A(buf_0_O),
+             .B(b),
+             .Y(and_out)
+           );
+           BUF buf_0 (
+             .I(a),
+             .O(buf_0_O)
+           );
+           assign y = and_out;
+         endmodule\n"
+    );
+}
+
+#[test]
+fn test_script_reports() {
+    let netlist = get_simple_example();
+    let interp = CommandInterpreter::new(netlist);
+    let report = interp.run("report_stats\nreport_instances").unwrap();
+    assert_eq!(
+        report,
+        "inputs: 2\noutputs: 1\ninstances: 1\nexample/inst_0 AND\n"
+    );
+    let report = interp.run("disconnect inst_0 B\nreport_nets").unwrap();
+    assert_eq!(report, "a\nb\ninst_0_Y\n");
+    assert!(interp.get_netlist().last().unwrap().get_driver(1).is_none());
+}
+
+#[test]
+fn test_script_errors() {
+    let netlist = get_simple_example();
+    let interp = CommandInterpreter::new(netlist);
+    assert_eq!(
+        interp.run("report_stats\nfrobnicate").unwrap_err(),
+        "Line 2: Unknown command frobnicate"
+    );
+    assert!(interp.execute("rename_net missing x").is_err());
+    assert!(interp.execute("rename_net a").is_err());
+    assert!(interp.execute("rename_net a b").is_err());
+    assert!(interp.execute("disconnect inst_0 Z").is_err());
+    // No buffer was provided
+    assert!(interp.execute("insert_buffer a buf_0").is_err());
+
+    // A failing command undoes the edits of the commands before it
+    let err = interp
+        .run("rename_net inst_0_Y and_out\nfrobnicate")
+        .unwrap_err();
+    assert_eq!(err, "Line 2: Unknown command frobnicate");
+    let netlist = interp.get_netlist();
+    assert_eq!(netlist.last().unwrap().get_identifier(), "inst_0_Y".into());
+
+    // Nets are not renamed in a locked netlist
+    let lock = netlist.lock();
+    assert!(interp.run("rename_net inst_0_Y and_out").is_err());
+    drop(lock);
+    assert_eq!(netlist.last().unwrap().get_identifier(), "inst_0_Y".into());
+
+    // Renames are journaled like any other edit
+    #[cfg(feature = "journal")]
+    {
+        netlist.start_journal("script".to_string());
+        interp.run("rename_net inst_0_Y and_out").unwrap();
+        let journal = netlist.take_journal().unwrap();
+        let edit = journal.entries().last().unwrap().edit().to_string();
+        assert_eq!(edit, "rename_net inst_0_Y and_out");
+    }
+}
@@ -0,0 +1,408 @@
+use safety_net::circuit::SequentialElement;
+use safety_net::constraint::InputConstraint;
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::sim::{
+    Logic, PackedSimulator, Simulator, Strength, eval_gate, eval_gate_packed, is_conflict,
+    next_state, resolve,
+};
+use safety_net::supply::Supply;
+use std::rc::Rc;
+
+fn gate(name: &str, inputs: &[&str]) -> Gate {
+    Gate::new_logical(
+        name.into(),
+        inputs.iter().map(|i| (*i).into()).collect(),
+        "Y".into(),
+    )
+}
+
+/// An accumulator IP block whose structure is not available, feeding an AND and an INV
+fn graybox_design() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let en = netlist.insert_input("en".into());
+    let ip = Gate::new_logical_multi(
+        "ACC_IP".into(),
+        vec!["D".into(), "EN".into()],
+        vec!["Q".into(), "P".into()],
+    );
+    let acc = netlist
+        .insert_gate(ip, "acc".into(), &[a.clone(), en])
+        .unwrap();
+    let and = netlist
+        .insert_gate(
+            gate("AND", &["A", "B"]),
+            "and".into(),
+            &[a, acc.get_output(0)],
+        )
+        .unwrap();
+    let inv = netlist
+        .insert_gate(gate("INV", &["A"]), "inv".into(), &[acc.get_output(1)])
+        .unwrap();
+    and.expose_with_name("y".into());
+    inv.expose_with_name("z".into());
+    netlist
+}
+
+#[test]
+fn test_logic_ops() {
+    use Logic::*;
+    assert_eq!(Zero & X, Zero);
+    assert_eq!(One & X, X);
+    assert_eq!(One | Z, One);
+    assert_eq!(Zero ^ One, One);
+    assert_eq!(!Z, X);
+    assert_eq!(Logic::from(true).to_string(), "1");
+    let mux = gate("MUX", &["A", "B", "S"]);
+    assert_eq!(eval_gate(&mux, &[One, One, X]), Some(vec![One]));
+    assert_eq!(eval_gate(&mux, &[Zero, One, X]), Some(vec![X]));
+    assert_eq!(eval_gate(&gate("FOO", &["A"]), &[One]), None);
+}
+
+#[test]
+fn test_graybox_model() {
+    use Logic::*;
+    let netlist = graybox_design();
+
+    // Without a model the IP block drives unknowns
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    assert_eq!(sim.step(&[One, One]).unwrap(), vec![X, X]);
+    assert_eq!(sim.step(&[Zero, One]).unwrap(), vec![Zero, X]);
+
+    // Q is the stored bit, P is its parity with D, and EN loads D into the state
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap().with_model(
+        "ACC_IP".into(),
+        vec![Zero],
+        |inputs, state| {
+            let outputs = vec![state[0], state[0] ^ inputs[0]];
+            if inputs[1] == One {
+                state[0] = inputs[0];
+            }
+            outputs
+        },
+    );
+    assert_eq!(sim.step(&[One, One]).unwrap(), vec![Zero, Zero]);
+    assert_eq!(sim.step(&[One, Zero]).unwrap(), vec![One, One]);
+    assert_eq!(sim.step(&[Zero, One]).unwrap(), vec![Zero, Zero]);
+
+    let inst = netlist
+        .objects()
+        .find(|n| {
+            n.get_instance_name()
+                .is_some_and(|i| i.to_string() == "acc")
+        })
+        .unwrap();
+    assert_eq!(sim.get_state(&inst), Some([Zero].as_slice()));
+    assert_eq!(sim.get_value(&inst.get_output(0)), Some(One));
+    sim.reset();
+    assert_eq!(sim.get_state(&inst), None);
+
+    // The model sees only the simulator, so the netlist is untouched
+    assert!(netlist.verify().is_ok());
+    assert!(netlist.to_string().contains("ACC_IP"));
+
+    // Models returning the wrong number of outputs are an error
+    let mut sim =
+        Simulator::new(&netlist, eval_gate)
+            .unwrap()
+            .with_model("ACC_IP".into(), vec![], |_, _| vec![One]);
+    assert!(sim.step(&[One, One]).is_err());
+    assert!(sim.step(&[One]).is_err());
+}
+
+#[test]
+fn test_sequential_library() {
+    use Logic::*;
+    for gate in Gate::sequential_library() {
+        assert!(gate.is_register());
+        assert_eq!(gate.get_data_input(), Some(0));
+        assert_eq!(gate.get_clock_input(), Some(1));
+    }
+    assert!(Gate::latch().is_latch() && !Gate::dff().is_latch());
+    assert_eq!(Gate::dff_async_reset().get_reset_input(), Some(2));
+    assert_eq!(Gate::dff_enable().get_enable_input(), Some(2));
+
+    assert_eq!(next_state(&Gate::dff(), &[One, X], Zero), Some(One));
+    assert_eq!(
+        next_state(&Gate::dff_async_reset(), &[One, X, One], One),
+        Some(Zero)
+    );
+    assert_eq!(
+        next_state(&Gate::dff_async_reset(), &[Zero, X, X], One),
+        Some(Zero)
+    );
+    assert_eq!(
+        next_state(&Gate::dff_enable(), &[One, X, Zero], Zero),
+        Some(Zero)
+    );
+    assert_eq!(
+        next_state(&Gate::dff_enable(), &[One, X, X], One),
+        Some(One)
+    );
+    assert_eq!(next_state(&Gate::latch(), &[Z, One], Zero), Some(X));
+    assert_eq!(
+        next_state(&gate("AND", &["A", "B"]), &[One, One], Zero),
+        None
+    );
+
+    // A register with an enable, simulated one clock edge per step
+    let netlist = Netlist::new("top".to_string());
+    let d = netlist.insert_input("d".into());
+    let clk = netlist.insert_input("clk".into());
+    let en = netlist.insert_input("en".into());
+    let q = netlist
+        .insert_gate(Gate::dff_enable(), "q_reg".into(), &[d, clk, en])
+        .unwrap();
+    q.expose_with_name("q".into());
+    assert!(netlist.verify().is_ok());
+
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap().with_model(
+        "DFFE".into(),
+        vec![Zero],
+        |inputs, state| {
+            let q = state[0];
+            state[0] = next_state(&Gate::dff_enable(), inputs, q).unwrap();
+            vec![q]
+        },
+    );
+    assert_eq!(sim.step(&[One, One, Zero]).unwrap(), vec![Zero]);
+    assert_eq!(sim.step(&[One, One, One]).unwrap(), vec![Zero]);
+    assert_eq!(sim.step(&[Zero, One, Zero]).unwrap(), vec![One]);
+    assert_eq!(sim.step(&[Zero, One, Zero]).unwrap(), vec![One]);
+}
+
+#[test]
+fn test_anchors() {
+    use Logic::*;
+    let netlist = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let and = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "and".into(), &[a.clone(), b])
+        .unwrap();
+    and.expose_with_name("y".into());
+
+    // An inverter that only exists to be watched
+    let probe = netlist
+        .insert_gate(gate("INV", &["A"]), "probe".into(), &[a])
+        .unwrap();
+    probe.anchor();
+    assert!(probe.is_anchored());
+    assert_eq!(netlist.anchors().count(), 1);
+    assert!(!netlist.clean().unwrap());
+    assert!(safety_net::report::unused_report(&netlist).is_empty());
+    assert_eq!(netlist.outputs().len(), 1);
+
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    assert_eq!(sim.step(&[One, One]).unwrap(), vec![One]);
+    let trace = sim.trace();
+    assert_eq!(trace.len(), 1);
+    assert_eq!(trace[0].0.get_identifier(), "probe_Y".into());
+    assert_eq!(trace[0].1, Zero);
+    drop(sim);
+
+    probe.clear_anchor();
+    drop(probe);
+    drop(trace);
+    assert!(netlist.clean().unwrap());
+    assert_eq!(netlist.objects().count(), 3);
+}
+
+#[test]
+fn test_drive_strength() {
+    use Logic::*;
+    assert_eq!(resolve(&[]), Z);
+    assert_eq!(
+        resolve(&[(One, Strength::Pull), (Zero, Strength::Strong)]),
+        Zero
+    );
+    assert_eq!(
+        resolve(&[(One, Strength::Pull), (Z, Strength::Strong)]),
+        One
+    );
+    assert_eq!(
+        resolve(&[(One, Strength::Strong), (Zero, Strength::Strong)]),
+        X
+    );
+    assert!(is_conflict(&[
+        (One, Strength::Weak),
+        (Zero, Strength::Weak)
+    ]));
+    assert!(!is_conflict(&[
+        (X, Strength::Strong),
+        (Zero, Strength::Strong)
+    ]));
+    assert_eq!("pull".parse(), Ok(Strength::Pull));
+
+    // A wired-AND of two open-drain outputs with a pull-up
+    let netlist: Rc<GateNetlist> = Netlist::new("wired_and".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let pad = netlist.insert_inout("pad".into());
+    for (name, input) in [("od_a", a), ("od_b", b)] {
+        let od = netlist
+            .insert_gate(gate("OD", &["A"]), name.into(), &[input])
+            .unwrap();
+        netlist.drive_inout(&od.get_output(0), &pad).unwrap();
+    }
+    let pullup = netlist
+        .insert_gate(gate("TIE1", &[]), "pullup".into(), &[])
+        .unwrap();
+    pullup.set_supply(Some(Supply::Power));
+    pullup
+        .get_output(0)
+        .set_drive_strength(Some(Strength::Pull));
+    assert_eq!(pullup.get_output(0).drive_strength(), Strength::Pull);
+    netlist.drive_inout(&pullup.get_output(0), &pad).unwrap();
+    netlist
+        .insert_gate(gate("BUF", &["A"]), "rx".into(), std::slice::from_ref(&pad))
+        .unwrap()
+        .expose_with_name("y".into());
+
+    let eval = |g: &Gate, inputs: &[Logic]| match g.get_gate_name().to_string().as_str() {
+        "OD" => Some(vec![if inputs[0] == Zero { Zero } else { Z }]),
+        "TIE0" => Some(vec![Zero]),
+        "TIE1" => Some(vec![One]),
+        _ => eval_gate(g, inputs),
+    };
+    let mut sim = Simulator::new(&netlist, eval).unwrap();
+    assert_eq!(sim.step(&[One, One, Z]).unwrap(), vec![One]);
+    assert_eq!(sim.step(&[One, Zero, Z]).unwrap(), vec![Zero]);
+    assert!(sim.conflicts().is_empty());
+
+    // Driving the pad high from outside fights the open-drain output pulling it low
+    assert_eq!(sim.step(&[Zero, One, One]).unwrap(), vec![X]);
+    assert_eq!(sim.conflicts().len(), 1);
+    assert_eq!(sim.conflicts()[0].get_identifier(), "pad".into());
+
+    // A ground tie overrides the pull-up, but not another supply
+    assert!(netlist.verify().is_ok());
+    let tie = netlist
+        .insert_gate(gate("TIE0", &[]), "tie".into(), &[])
+        .unwrap();
+    tie.set_supply(Some(Supply::Ground));
+    netlist.drive_inout(&tie.get_output(0), &pad).unwrap();
+    assert!(netlist.drive_conflicts().is_empty());
+    pullup.get_output(0).set_drive_strength(None);
+    assert_eq!(netlist.drive_conflicts(), vec!["pad".into()]);
+    assert!(netlist.verify().is_err());
+}
+
+#[test]
+fn test_two_state_packed() {
+    let netlist = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let s = netlist.insert_input("s".into());
+    let xor = netlist
+        .insert_gate(
+            gate("XOR", &["A", "B"]),
+            "xor".into(),
+            &[a.clone(), b.clone()],
+        )
+        .unwrap();
+    let nand = netlist
+        .insert_gate(gate("NAND", &["A", "B"]), "nand".into(), &[a, b])
+        .unwrap();
+    netlist
+        .insert_gate(
+            gate("MUX", &["A", "B", "S"]),
+            "mux".into(),
+            &[xor.into(), nand.into(), s],
+        )
+        .unwrap()
+        .expose_with_name("y".into());
+
+    assert!(PackedSimulator::new(&netlist, eval_gate_packed).is_err());
+    netlist.set_two_state(true);
+    assert!(netlist.is_two_state());
+
+    // The eight input patterns, one per bit
+    let words: Vec<u64> = (0..3)
+        .map(|i| (0..8).filter(|p| p >> i & 1 == 1).map(|p| 1 << p).sum())
+        .collect();
+    let mut packed = PackedSimulator::new(&netlist, eval_gate_packed).unwrap();
+    let y = packed.step(&words).unwrap()[0];
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    for p in 0..8 {
+        let inputs: Vec<Logic> = (0..3).map(|i| (p >> i & 1 == 1).into()).collect();
+        let expected = sim.step(&inputs).unwrap()[0];
+        assert_eq!(Logic::from(y >> p & 1 == 1), expected);
+    }
+    let nand = netlist.find_instance(&"nand".into()).unwrap().get_output(0);
+    assert_eq!(packed.get_value(&nand).unwrap() & 0xff, 0x77);
+
+    // Unknown primitives have no two-state value
+    netlist
+        .insert_gate(gate("FOO", &["A"]), "foo".into(), &[nand])
+        .unwrap()
+        .expose_with_name("z".into());
+    let mut packed = PackedSimulator::new(&netlist, eval_gate_packed).unwrap();
+    assert!(packed.step(&words).is_err());
+}
+
+#[test]
+fn test_register_feedback() {
+    use Logic::*;
+    // A toggle flip-flop, whose output feeds back through an inverter
+    let netlist = Netlist::new("toggle".to_string());
+    let clk = netlist.insert_input("clk".into());
+    let q = netlist
+        .insert_gate_disconnected(Gate::dff(), "q_reg".into())
+        .unwrap();
+    let inv = netlist
+        .insert_gate(gate("INV", &["A"]), "inv".into(), &[q.get_output(0)])
+        .unwrap();
+    inv.get_output(0).connect(q.get_input(0));
+    clk.connect(q.get_input(1));
+    q.get_output(0).expose_with_name("q".into());
+
+    // Without an initial value the register is unknown
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    assert_eq!(sim.step(&[One]).unwrap(), vec![X]);
+    assert_eq!(sim.get_state(&q), Some([X].as_slice()));
+
+    q.set_initial_value(Some(true));
+    let mut sim = Simulator::new(&netlist, eval_gate)
+        .unwrap()
+        .with_next_state(next_state);
+    let trace: Vec<Logic> = (0..4).map(|_| sim.step(&[One]).unwrap()[0]).collect();
+    assert_eq!(trace, vec![One, Zero, One, Zero]);
+    sim.reset();
+    assert_eq!(sim.step(&[One]).unwrap(), vec![One]);
+
+    // Modeling the register in place closes the loop
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap().with_model(
+        "DFF".into(),
+        vec![Zero],
+        |_, state| state.clone(),
+    );
+    assert!(sim.step(&[One]).is_err());
+}
+
+#[test]
+fn test_input_constraints() {
+    use Logic::*;
+    let netlist = Netlist::new("top".to_string());
+    let en0 = netlist.insert_input("en0".into());
+    let en1 = netlist.insert_input("en1".into());
+    netlist
+        .insert_gate(gate("OR", &["A", "B"]), "or".into(), &[en0, en1])
+        .unwrap()
+        .expose_with_name("y".into());
+    netlist
+        .constrain_inputs(InputConstraint::OneHot(vec!["en0".into(), "en1".into()]))
+        .unwrap();
+
+    // The unknown enable is implied high, and two high enables are rejected
+    let mut sim = Simulator::new(&netlist, eval_gate).unwrap();
+    assert_eq!(sim.step(&[X, Zero]).unwrap(), vec![One]);
+    assert!(sim.step(&[One, One]).is_err());
+
+    netlist.set_two_state(true);
+    let mut packed = PackedSimulator::new(&netlist, eval_gate_packed).unwrap();
+    let en0 = 0x5555_5555_5555_5555;
+    assert_eq!(packed.step(&[en0, !en0]).unwrap(), vec![u64::MAX]);
+    let err = packed.step(&[en0 | 2, !en0]).unwrap_err();
+    assert_eq!(err, "Set 1 of the input values violates onehot(en0, en1)");
+}
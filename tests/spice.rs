@@ -0,0 +1,100 @@
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::spice::{PortOrder, SpiceOptions, Subckt, write_spice};
+use std::rc::Rc;
+
+fn and_gate() -> Gate {
+    Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into())
+}
+
+fn get_simple_example() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("example".to_string());
+
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+
+    let instance = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+
+    instance.expose_with_name("y".into());
+
+    netlist
+}
+
+fn to_spice(netlist: &GateNetlist, options: &SpiceOptions) -> Result<String, String> {
+    let mut deck = Vec::new();
+    write_spice(
+        netlist,
+        &mut deck,
+        |g: &Gate| {
+            Ok(Subckt::from_cell(g)
+                .with_global("VDD".to_string())
+                .with_global("VSS".to_string()))
+        },
+        options,
+    )?;
+    Ok(String::from_utf8(deck).unwrap())
+}
+
+#[test]
+fn test_write_spice() {
+    let netlist = get_simple_example();
+    let options = SpiceOptions::new()
+        .with_global("VDD".to_string())
+        .with_global("VSS".to_string());
+    assert_eq!(
+        to_spice(&netlist, &options).unwrap(),
+        "* example
+.SUBCKT example a b y VDD VSS
+Xinst_0 a b y VDD VSS AND
+.ENDS example
+"
+    );
+}
+
+#[test]
+fn test_spice_port_order() {
+    let netlist = get_simple_example();
+    let a = netlist.first().unwrap();
+    a.expose_with_name("z".into());
+
+    let options = SpiceOptions::new().with_port_order(PortOrder::Custom(vec![
+        "z".into(),
+        "y".into(),
+        "b".into(),
+        "a".into(),
+    ]));
+    assert_eq!(
+        to_spice(&netlist, &options).unwrap(),
+        "* example
+.SUBCKT example z y b a
+Xinst_0 a b y VDD VSS AND
+Vz z a 0
+.ENDS example
+"
+    );
+
+    let options = SpiceOptions::new().with_port_order(PortOrder::Custom(vec!["a".into()]));
+    assert!(to_spice(&netlist, &options).is_err());
+
+    // Cells are mapped with their own pin order
+    let mut deck = Vec::new();
+    let options = SpiceOptions::new().with_port_order(PortOrder::Alphabetical);
+    write_spice(
+        &netlist,
+        &mut deck,
+        |_: &Gate| {
+            Ok(Subckt::new("and2".to_string())
+                .with_port("Y".into())
+                .with_port("B".into())
+                .with_port("A".into()))
+        },
+        &options,
+    )
+    .unwrap();
+    assert!(String::from_utf8(deck).unwrap().contains(
+        ".SUBCKT example a b y z
+Xinst_0 y b a and2
+"
+    ));
+}
@@ -0,0 +1,446 @@
+use safety_net::circuit::Instantiable;
+use safety_net::cost::{AcceptAll, UnitCost};
+use safety_net::graph::{Analysis, SequentialCombDepth, SimpleCombDepth};
+use safety_net::library::{CellLibrary, LibraryCell};
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::persist::AnalysisCache;
+use safety_net::sdc::Constraints;
+use safety_net::timing::{
+    ElmoreDelay, MultiCornerAnalysis, Parasitics, TimingAnalysis, WireLoad, WithWireLoad,
+    elmore_delay, read_parasitics,
+};
+use std::rc::Rc;
+
+fn gate(name: &str, inputs: &[&str]) -> Gate {
+    Gate::new_logical(
+        name.into(),
+        inputs.iter().map(|i| (*i).into()).collect(),
+        "Y".into(),
+    )
+}
+
+fn delay(g: &Gate) -> f64 {
+    match g.get_name().to_string().as_str() {
+        "DFF" => 0.5,
+        _ => 1.0,
+    }
+}
+
+const SDC: &str = r#"
+# A 10ns clock
+create_clock -name clk -period 10 -waveform {0 5} [get_ports clk]
+set_input_delay -clock clk 2 [get_ports {a b}]
+set_input_delay -clock [get_clocks clk] -max 5 \
+    [get_ports a]
+set_input_delay -clock clk -min 9 [get_ports b]; set_output_delay -clock clk 3 [get_ports y]
+set_false_path -from [get_ports a] -to [get_cells reg]
+set_load 3 [get_ports y]
+"#;
+
+fn design() -> Rc<GateNetlist> {
+    let netlist: Rc<GateNetlist> = Netlist::new("top".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let clk = netlist.insert_input("clk".into());
+    let and1 = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "and1".into(), &[a, b.clone()])
+        .unwrap();
+    let inv = netlist
+        .insert_gate(gate("INV", &["A"]), "inv".into(), &[and1.into()])
+        .unwrap();
+    let reg = netlist
+        .insert_gate(Gate::dff(), "reg".into(), &[inv.into(), clk])
+        .unwrap();
+    let and2 = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "and2".into(), &[reg.into(), b])
+        .unwrap();
+    and2.expose_with_name("y".into());
+    netlist
+}
+
+#[test]
+fn test_sdc() {
+    let constraints: Constraints = SDC.parse().unwrap();
+    assert_eq!(constraints.clocks().len(), 1);
+    let clk = constraints.get_clock("clk").unwrap();
+    assert_eq!(clk.period(), 10.0);
+    assert_eq!(clk.high_time(), 5.0);
+    assert_eq!(clk.sources(), ["clk"]);
+    assert_eq!(constraints.input_delay(&"a".into()).unwrap().delay(), 5.0);
+    assert_eq!(constraints.input_delay(&"b".into()).unwrap().delay(), 2.0);
+    let y = constraints.output_delay(&"y".into()).unwrap();
+    assert_eq!((y.clock(), y.delay()), (Some("clk"), 3.0));
+    assert!(constraints.is_false_path(&"a".into(), &"reg".into()));
+    assert!(!constraints.is_false_path(&"b".into(), &"reg".into()));
+    assert_eq!(constraints.ignored(), ["set_load"]);
+
+    assert!(Constraints::parse("create_clock -name clk").is_err());
+    assert!(Constraints::parse("set_input_delay 1 [get_ports a").is_err());
+    assert!(Constraints::parse("set_false_path -through x").is_err());
+    assert!(Constraints::parse("create_clock -period 2 -waveform {0} clk").is_err());
+}
+
+#[test]
+fn test_timing_analysis() {
+    let netlist = design();
+    let constraints: Constraints = SDC.parse().unwrap();
+    let timing = TimingAnalysis::new(&netlist, &constraints, &delay).unwrap();
+
+    let inv = netlist.find_instance(&"inv".into()).unwrap();
+    assert_eq!(timing.arrival(&inv), Some(7.0));
+
+    // Arrival times can be stored with the netlist and loaded while it is unchanged
+    let mut cache = AnalysisCache::new(&netlist);
+    cache.store("arrival", timing.arrivals());
+    let arrivals = cache.load::<_, f64>(&netlist, "arrival").unwrap().unwrap();
+    assert_eq!(arrivals[&inv], 7.0);
+
+    let names: Vec<String> = timing
+        .endpoints()
+        .iter()
+        .map(|e| e.get_name().to_string())
+        .collect();
+    assert_eq!(names, vec!["y", "reg"]);
+
+    // The path from y is clk-to-q, then AND2 behind the input delay of b
+    let y = &timing.endpoints()[0];
+    assert_eq!(y.arrival(), 3.0);
+    assert_eq!(y.slack(), Some(4.0));
+
+    // The late path from a to the register is false, so b sets the arrival
+    let reg = &timing.endpoints()[1];
+    assert!(reg.get_load().is_some());
+    assert_eq!(reg.arrival(), 4.0);
+    assert_eq!(reg.slack(), Some(6.0));
+    assert_eq!(timing.worst_slack(), Some(4.0));
+    assert_eq!(timing.total_negative_slack(), 0.0);
+
+    let fast = SDC.replace("-period 10", "-period 2");
+    let constraints: Constraints = fast.parse().unwrap();
+    let timing = TimingAnalysis::new(&netlist, &constraints, &delay).unwrap();
+    assert_eq!(timing.worst_slack(), Some(-4.0));
+    assert_eq!(timing.total_negative_slack(), -6.0);
+
+    // Without clocks, nothing is constrained
+    let timing = TimingAnalysis::new(&netlist, &Constraints::new(), &delay).unwrap();
+    assert_eq!(timing.worst_slack(), None);
+    assert_eq!(timing.endpoints()[1].arrival(), 2.0);
+}
+
+#[test]
+fn test_latch_borrowing() {
+    let netlist: Rc<GateNetlist> = Netlist::new("top".to_string());
+    let d = netlist.insert_input("d".into());
+    let clk = netlist.insert_input("clk".into());
+    let mut net = d;
+    for name in ["inv1", "inv2", "inv3"] {
+        net = netlist
+            .insert_gate(gate("INV", &["A"]), name.into(), &[net])
+            .unwrap()
+            .into();
+    }
+    let l1 = netlist
+        .insert_gate(Gate::latch(), "l1".into(), &[net, clk.clone()])
+        .unwrap();
+    let mut net = l1.into();
+    for name in ["inv4", "inv5"] {
+        net = netlist
+            .insert_gate(gate("INV", &["A"]), name.into(), &[net])
+            .unwrap()
+            .into();
+    }
+    let l2 = netlist
+        .insert_gate(Gate::latch(), "l2".into(), &[net, clk])
+        .unwrap();
+    l2.expose_with_name("y".into());
+
+    // l1 borrows 0.5 and launches late, so l2 borrows 1.0, and neither is a violation
+    let constraints: Constraints = "create_clock -period 2.5 [get_ports clk]".parse().unwrap();
+    let analysis = TimingAnalysis::new(&netlist, &constraints, &delay).unwrap();
+    let endpoints = analysis.endpoints();
+    let names: Vec<String> = endpoints.iter().map(|e| e.get_name().to_string()).collect();
+    assert_eq!(names, vec!["y", "l1", "l2"]);
+    assert_eq!(endpoints[1].arrival(), 3.0);
+    assert_eq!(endpoints[1].borrowed(), 0.5);
+    assert_eq!(endpoints[1].slack(), Some(0.0));
+    assert_eq!(endpoints[2].arrival(), 3.5);
+    assert_eq!(endpoints[2].borrowed(), 1.0);
+    assert_eq!(endpoints[2].slack(), Some(0.0));
+    assert_eq!(endpoints[0].borrowed(), 0.0);
+    assert_eq!(endpoints[0].slack(), Some(0.5));
+    assert_eq!(analysis.worst_slack(), Some(0.0));
+
+    // With a shorter high phase, l2 closes before its data arrives
+    let constraints: Constraints = "create_clock -period 2.5 -waveform {0 0.5} [get_ports clk]"
+        .parse()
+        .unwrap();
+    let analysis = TimingAnalysis::new(&netlist, &constraints, &delay).unwrap();
+    let endpoints = analysis.endpoints();
+    assert_eq!(endpoints[1].slack(), Some(0.0));
+    assert_eq!(endpoints[2].borrowed(), 0.5);
+    assert_eq!(endpoints[2].slack(), Some(-0.5));
+    assert_eq!(endpoints[0].slack(), Some(1.0));
+    assert_eq!(analysis.worst_slack(), Some(-0.5));
+}
+
+#[test]
+fn test_multi_corner() {
+    let netlist = design();
+    let constraints: Constraints = SDC.parse().unwrap();
+    let slow = |g: &Gate| 3.0 * delay(g);
+    let corners =
+        MultiCornerAnalysis::new(&netlist, &constraints, &[("typ", &delay), ("slow", &slow)])
+            .unwrap();
+    let names: Vec<&str> = corners.corners().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["typ", "slow"]);
+    assert_eq!(corners.corner("typ").unwrap().worst_slack(), Some(4.0));
+    assert!(corners.corner("fast").is_none());
+
+    // The slow corner is worse at both endpoints, so it sets the worst slack
+    assert_eq!(corners.worst_slack(), Some(("slow", 2.0)));
+    assert_eq!(corners.endpoint_slack(0), Some(("slow", 2.0)));
+    assert_eq!(corners.endpoint_slack(1), Some(("slow", 2.0)));
+    assert_eq!(corners.endpoint_slack(2), None);
+    assert_eq!(corners.total_negative_slack(), Some(("typ", 0.0)));
+    let slow_path = corners.corner("slow").unwrap().critical_path().unwrap();
+    assert_eq!(
+        corners.critical_path().unwrap().to_string(),
+        slow_path.to_string()
+    );
+
+    let tight = SDC.replace("-period 10", "-period 4");
+    let constraints: Constraints = tight.parse().unwrap();
+    let corners =
+        MultiCornerAnalysis::new(&netlist, &constraints, &[("typ", &delay), ("slow", &slow)])
+            .unwrap();
+    assert_eq!(corners.total_negative_slack(), Some(("slow", -8.0)));
+
+    let err = MultiCornerAnalysis::new(&netlist, &constraints, &[("typ", &delay), ("typ", &slow)]);
+    assert_eq!(err.err(), Some("Corner typ is given twice".to_string()));
+}
+
+#[test]
+fn test_critical_path() {
+    let netlist = design();
+    let constraints: Constraints = SDC.parse().unwrap();
+    let timing = TimingAnalysis::new(&netlist, &constraints, &delay).unwrap();
+
+    let path = timing.critical_path().unwrap();
+    assert_eq!(path.to_string(), "b -> and2");
+    assert_eq!(path.connections().len(), 1);
+
+    // The path to the register avoids the false path from a
+    let path = timing.path_to(1).unwrap();
+    assert_eq!(path.to_string(), "b -> and1 -> inv -> reg");
+    assert_eq!(path.startpoint().get_identifier(), "b".into());
+    let d = path.connections().last().unwrap();
+    assert_eq!(d.target().get_position(), 0);
+    assert!(timing.path_to(2).is_none());
+
+    // Without constraints, the latest arrival is critical
+    let timing = TimingAnalysis::new(&netlist, &Constraints::new(), &delay).unwrap();
+    let path = timing.critical_path().unwrap();
+    assert_eq!(path.to_string(), "a -> and1 -> inv -> reg");
+}
+
+#[test]
+fn test_wire_load() {
+    let wire_load = WireLoad::new(0.5).with_table(vec![0.1, 0.2]);
+    assert_eq!(wire_load.delay(0), 0.0);
+    assert_eq!(wire_load.delay(1), 0.1);
+    assert_eq!(wire_load.delay(2), 0.2);
+    assert_eq!(wire_load.delay(4), 1.2);
+
+    // Every load adds a unit of delay, so b is slower with its two loads
+    let netlist = design();
+    let constraints: Constraints = SDC.parse().unwrap();
+    let model = WithWireLoad::new(delay, WireLoad::new(1.0));
+    let timing = TimingAnalysis::new(&netlist, &constraints, &model).unwrap();
+    let inv = netlist.find_instance(&"inv".into()).unwrap();
+    assert_eq!(timing.arrival(&inv), Some(9.0));
+    let arrivals: Vec<f64> = timing.endpoints().iter().map(|e| e.arrival()).collect();
+    assert_eq!(arrivals, vec![5.0, 8.0]);
+
+    // A library estimates wire delays with its own wire-load model
+    let library = CellLibrary::new("lib".to_string())
+        .with_cell(LibraryCell::new(gate("AND", &["A", "B"])).with_delay(1.0))
+        .with_cell(LibraryCell::new(gate("INV", &["A"])).with_delay(1.0))
+        .with_cell(LibraryCell::new(Gate::dff()).with_delay(0.5))
+        .with_wire_load(WireLoad::new(1.0));
+    let timing = TimingAnalysis::new(&netlist, &constraints, &library).unwrap();
+    assert_eq!(timing.arrival(&inv), Some(9.0));
+    assert_eq!(timing.worst_slack(), Some(2.0));
+}
+
+#[test]
+fn test_wire_load_depth() {
+    // Every load adds a unit of delay, so b is slower with its two loads
+    let netlist = design();
+    let model = WithWireLoad::new(delay, WireLoad::new(1.0));
+    let inv = netlist.find_instance(&"inv".into()).unwrap();
+    let and2 = netlist.find_instance(&"and2".into()).unwrap();
+
+    let depth = SequentialCombDepth::with_delays(&netlist, &model).unwrap();
+    assert_eq!(depth.get_comb_depth(&inv), Some(2));
+    assert_eq!(depth.get_comb_delay(&inv), Some(5.0));
+    assert_eq!(depth.get_comb_delay(&and2), Some(3.0));
+    assert_eq!(depth.get_max_delay(), 5.0);
+
+    // Without a model, arrival times are logic levels
+    let depth = SequentialCombDepth::build(&netlist).unwrap();
+    assert_eq!(depth.get_comb_delay(&inv), Some(2.0));
+    assert_eq!(depth.get_max_delay(), 2.0);
+
+    // The same logic without the register, where b also drives another output
+    let netlist: Rc<GateNetlist> = Netlist::new("comb".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let c = netlist.insert_input("c".into());
+    let and1 = netlist
+        .insert_gate(gate("AND", &["A", "B"]), "and1".into(), &[a, b.clone()])
+        .unwrap();
+    let inv = netlist
+        .insert_gate(gate("INV", &["A"]), "inv".into(), &[and1.into()])
+        .unwrap();
+    let and2 = netlist
+        .insert_gate(
+            gate("AND", &["A", "B"]),
+            "and2".into(),
+            &[inv.clone().into(), c],
+        )
+        .unwrap();
+    and2.expose_with_name("y".into());
+    let inv2 = netlist
+        .insert_gate(gate("INV", &["A"]), "inv2".into(), &[b])
+        .unwrap();
+    inv2.expose_with_name("z".into());
+    let depth = SimpleCombDepth::with_delays(&netlist, &model).unwrap();
+    assert_eq!(depth.get_comb_delay(&inv), Some(5.0));
+    assert_eq!(depth.get_max_delay(), 7.0);
+    assert_eq!(depth.get_max_depth(), 3);
+}
+
+#[test]
+fn test_elmore_delay() {
+    let wire = Parasitics::new(2.0, 1.0);
+    assert_eq!(elmore_delay(wire, 0.0, 0.0), 1.0);
+    assert_eq!(elmore_delay(wire, 1.0, 2.0), 3.0 + 5.0);
+
+    let netlist = design();
+    let inv = netlist.find_instance(&"inv".into()).unwrap();
+    let name = inv.get_output(0).get_identifier().to_string();
+    let src = format!("// Extracted parasitics\n{name} 2 1\n\nb 1 0.5 // two loads\n");
+    assert_eq!(read_parasitics(&netlist, &src), Ok(2));
+    assert_eq!(
+        inv.get_output(0).get_parasitics(),
+        Some(Parasitics::new(2.0, 1.0))
+    );
+    // The parasitics are not attributes, so an attribute of the same name is not read as one
+    assert_eq!(inv.attributes().count(), 0);
+    let and1 = netlist.find_instance(&"and1".into()).unwrap();
+    and1.insert_attribute("parasitics".to_string(), "2 1".to_string());
+    assert_eq!(and1.get_output(0).get_parasitics(), None);
+    and1.clear_attribute(&"parasitics".to_string());
+    assert!(read_parasitics(&netlist, "missing 1 1").is_err());
+    assert!(read_parasitics(&netlist, "b 1").is_err());
+
+    // INV drives through a resistance of 1 and b ideally, into pins of 0.5, so b is late by 1 * (0.25 + 2 * 0.5)
+    let model = ElmoreDelay::new(
+        delay,
+        |g: &Gate| match g.get_name().to_string().as_str() {
+            "INV" => 1.0,
+            _ => 0.0,
+        },
+        0.5,
+    );
+    let timing = TimingAnalysis::new(&netlist, &Constraints::new(), &model).unwrap();
+    let and1 = netlist.find_instance(&"and1".into()).unwrap();
+    assert_eq!(timing.arrival(&and1), Some(2.25));
+    let reg = &timing.endpoints()[1];
+    // The output of INV adds 1 * (1 + 0.5) + 2 * (0.5 + 0.5)
+    assert_eq!(reg.arrival(), 3.25 + 3.5);
+
+    inv.get_output(0).set_parasitics(None);
+    assert_eq!(inv.get_output(0).get_parasitics(), None);
+}
+
+#[test]
+fn test_critical_region() {
+    let netlist = design();
+    let and2 = netlist.find_instance(&"and2".into()).unwrap();
+    netlist
+        .insert_gate(gate("INV", &["A"]), "tap".into(), &[and2.into()])
+        .unwrap()
+        .expose_with_name("z".into());
+
+    // With a 5ns clock only y fails, and tap is a neighbor of its path
+    let slow = SDC.replace("-period 10", "-period 5");
+    let constraints: Constraints = slow.parse().unwrap();
+    let region = {
+        let timing = TimingAnalysis::new(&netlist, &constraints, &delay).unwrap();
+        assert_eq!(timing.critical_region(0), vec!["and2".into()]);
+        timing.critical_region(1)
+    };
+    assert_eq!(region, vec!["and2".into(), "tap".into()]);
+
+    // A pass that drops an output of the region is rejected
+    let dropped = netlist.rewrite_region(&region, &AcceptAll, |working| {
+        assert_eq!(working.get_name(), "top_region");
+        let tap = working.find_instance(&"tap".into()).unwrap();
+        working.delete_net_uses(tap).unwrap();
+        working.clean().unwrap();
+        Ok(())
+    });
+    assert!(dropped.is_err());
+    assert!(netlist.find_instance(&"tap".into()).is_some());
+    assert!(
+        netlist
+            .rewrite_region(&["nope".into()], &AcceptAll, |_| Ok(()))
+            .is_err()
+    );
+    // A rewrite that saves nothing is rejected by a model that wants less area
+    assert!(
+        netlist
+            .rewrite_region(&region, &UnitCost, |_| Ok(()))
+            .is_err()
+    );
+    assert!(netlist.find_instance(&"and2".into()).is_some());
+
+    // Unrelated dead logic is left alone, and anchored instances of the region are still replaced
+    let a = netlist.inputs().next().unwrap();
+    netlist
+        .insert_gate(gate("INV", &["A"]), "spare".into(), &[a])
+        .unwrap();
+    netlist.find_instance(&"tap".into()).unwrap().anchor();
+
+    // Upsize the AND gate, which is spliced back under its new name
+    netlist
+        .rewrite_region(&region, &AcceptAll, |working| {
+            let old = working.find_instance(&"and2".into()).unwrap();
+            let operands: Vec<_> = old.drivers().map(|d| d.unwrap().into()).collect();
+            let fast =
+                working.insert_gate(gate("AND_X2", &["A", "B"]), "fast".into(), &operands)?;
+            working.replace_net_uses(old, &fast)?;
+            working.clean()?;
+            Ok(())
+        })
+        .unwrap();
+    assert!(netlist.verify().is_ok());
+    assert!(netlist.find_instance(&"and2".into()).is_none());
+    let fast = netlist.find_instance(&"fast".into()).unwrap();
+    assert_eq!(
+        fast.get_instance_type().unwrap().get_name(),
+        &"AND_X2".into()
+    );
+    let tap = netlist.find_instance(&"tap".into()).unwrap();
+    assert_eq!(tap.get_driver(0), Some(fast.clone()));
+    let outputs: Vec<String> = netlist
+        .outputs()
+        .iter()
+        .map(|(d, n)| format!("{} {}", d.get_identifier(), n.get_identifier()))
+        .collect();
+    assert!(outputs.contains(&"fast_Y y".to_string()));
+    assert!(tap.is_anchored());
+    assert!(netlist.find_instance(&"spare".into()).is_some());
+    assert_eq!(netlist.objects().filter(|n| !n.is_an_input()).count(), 6);
+}
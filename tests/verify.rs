@@ -1,9 +1,13 @@
+use safety_net::circuit::DataType;
 use safety_net::circuit::Identifier;
 use safety_net::circuit::Instantiable;
 #[allow(unused_imports)]
 use safety_net::circuit::Net;
+use safety_net::netlist::DuplicateOutputPolicy;
 use safety_net::netlist::Gate;
 use safety_net::netlist::GateNetlist;
+use safety_net::netlist::NetlistError;
+use safety_net::netlist::OutputPolicy;
 
 #[test]
 fn inputs_w_same_name() {
@@ -91,6 +95,11 @@ fn test_basic_serialize() {
     let inst = netlist.last().unwrap();
     assert!(inst.get_instance_type().is_none());
     assert_eq!(*inst.as_net(), "in".into());
+
+    let ports = netlist.ports();
+    assert_eq!(ports.len(), 2);
+    assert!(ports[0].is_input());
+    assert_eq!(*ports[1].get_name(), "out".into());
 }
 
 #[test]
@@ -104,3 +113,280 @@ fn test_empty_netlist() {
     netlist.delete_net_uses(a.unwrap()).unwrap();
     assert!(netlist.outputs().is_empty());
 }
+
+#[test]
+fn test_delete_output_policy() {
+    let netlist = GateNetlist::new("min_module".to_string());
+    let a = netlist.insert_input("a".into());
+
+    // Refuses to delete a node that is still referenced
+    assert!(matches!(
+        netlist.delete_net_uses_with(a.clone().unwrap(), OutputPolicy::Error),
+        Err(NetlistError::ReferencesExist(_))
+    ));
+
+    // Refuses to delete a node that drives an output
+    let a = a.expose_with_name("y".into());
+    assert!(matches!(
+        netlist.delete_net_uses_with(a.unwrap(), OutputPolicy::Error),
+        Err(NetlistError::DrivesOutput(_))
+    ));
+    let a = netlist.first().unwrap();
+    assert_eq!(netlist.outputs().len(), 1);
+
+    // Rebinds the output to a constant
+    let tie = Gate::new_logical_multi("TIE0".into(), vec![], vec!["Y".into()]);
+    let zero = netlist.insert_gate(tie, "tie_0".into(), &[]).unwrap();
+    a.delete_uses_with(OutputPolicy::Rebind(zero.get_output(0)))
+        .unwrap();
+    let outputs = netlist.outputs();
+    assert_eq!(outputs.len(), 1);
+    let (driver, net) = outputs.first().unwrap();
+    assert_eq!(driver.get_identifier(), "tie_0_Y".into());
+    assert_eq!(*net.get_identifier(), "y".into());
+
+    // Refuses to rebind onto a net that already drives an output, which would drop one of them
+    let b = netlist.insert_input("b".into());
+    let inv = Gate::new_logical("INV".into(), vec!["A".into()], "Y".into());
+    let buf = Gate::new_logical("BUF".into(), vec!["A".into()], "Y".into());
+    let inv = netlist
+        .insert_gate(inv, "inv".into(), std::slice::from_ref(&b))
+        .unwrap();
+    let buf = netlist.insert_gate(buf, "buf".into(), &[b]).unwrap();
+    inv.clone().expose_with_name("v".into());
+    let buf = buf.expose_with_name("w".into());
+    assert!(matches!(
+        inv.delete_uses_with(OutputPolicy::Rebind(buf.into())),
+        Err(NetlistError::RebindTaken(_))
+    ));
+    assert_eq!(netlist.outputs().len(), 3);
+    assert!(netlist.verify().is_ok());
+}
+
+#[test]
+fn test_clean_referenced() {
+    let netlist = GateNetlist::new("min_module".to_string());
+    let a = netlist.insert_input("a".into());
+    let inv = Gate::new_logical("INV".into(), vec!["A".into()], "Y".into());
+    let first = netlist
+        .insert_gate(inv.clone(), "inv_0".into(), std::slice::from_ref(&a))
+        .unwrap();
+    netlist.insert_gate(inv, "inv_1".into(), &[a]).unwrap();
+    let handles = [first.clone(), first];
+    netlist
+        .insert_input("b".into())
+        .expose_with_name("y".into());
+
+    // A failed clean leaves every object in place
+    assert!(matches!(
+        netlist.clean(),
+        Err(NetlistError::ReferencesExist(_))
+    ));
+    assert_eq!(netlist.objects().count(), 4);
+    assert!(netlist.verify().is_ok());
+    drop(handles);
+    assert!(netlist.clean().unwrap());
+    assert_eq!(netlist.objects().count(), 2);
+}
+
+#[test]
+fn test_duplicate_outputs() {
+    let expose_twice = |policy| {
+        let netlist = GateNetlist::new("dup".to_string());
+        netlist.set_duplicate_output_policy(policy);
+        let a = netlist.insert_input("a".into());
+        let a = a.expose_with_name("y".into());
+        a.expose_with_name("z".into());
+        netlist
+    };
+    let names = |netlist: &GateNetlist| -> Vec<String> {
+        netlist
+            .outputs()
+            .iter()
+            .map(|(_, n)| n.get_identifier().to_string())
+            .collect()
+    };
+
+    // The net can be rebound to the new output, dropping the earlier one
+    let netlist = expose_twice(DuplicateOutputPolicy::Rebind);
+    assert_eq!(names(&netlist), vec!["z"]);
+    assert!(netlist.verify().is_ok());
+
+    // By default nothing is dropped
+    let netlist = expose_twice(DuplicateOutputPolicy::default());
+    assert_eq!(names(&netlist), vec!["y", "z"]);
+
+    // Both outputs are kept through an alias
+    let netlist = expose_twice(DuplicateOutputPolicy::Alias);
+    assert_eq!(names(&netlist), vec!["y", "z"]);
+    assert_eq!(netlist.aliases().count(), 1);
+    assert!(netlist.verify().is_ok());
+
+    // The new output is reported until the duplicates are cleared
+    let netlist = expose_twice(DuplicateOutputPolicy::Reject);
+    assert_eq!(names(&netlist), vec!["y"]);
+    assert_eq!(
+        netlist.get_duplicate_outputs(),
+        vec![("z".into(), "y".into())]
+    );
+    assert_eq!(
+        netlist.verify().unwrap_err(),
+        "Output z is driven by the same net as output y"
+    );
+    assert_eq!(netlist.clear_duplicate_outputs().len(), 1);
+    assert!(netlist.verify().is_ok());
+}
+
+#[test]
+fn test_insertion_checks() {
+    let netlist = GateNetlist::new("min_module".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input(Net::new("b".into(), DataType::boolean()));
+    let and = Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into());
+
+    // Arity mismatch
+    assert!(matches!(
+        netlist.insert_gate(and.clone(), "inst_0".into(), std::slice::from_ref(&a)),
+        Err(NetlistError::ArityMismatch {
+            expected: 2,
+            got: 1,
+            ..
+        })
+    ));
+
+    // A four-state net cannot drive a two-state port
+    let bool_inv = Gate::new(
+        "INV".into(),
+        vec![Net::new("I".into(), DataType::boolean())],
+        vec![Net::new("O".into(), DataType::boolean())],
+    );
+    assert!(matches!(
+        netlist.insert_gate(bool_inv.clone(), "inst_1".into(), std::slice::from_ref(&a)),
+        Err(NetlistError::TypeMismatch(ref m)) if m.inst_name == "inst_1".into()
+    ));
+    assert!(
+        netlist
+            .insert_gate(bool_inv, "inst_1".into(), std::slice::from_ref(&b))
+            .is_ok()
+    );
+
+    // Duplicate drivers are only rejected once enabled
+    assert!(
+        netlist
+            .insert_gate(and.clone(), "inst_1".into(), &[a.clone(), b.clone()])
+            .is_ok()
+    );
+    netlist.set_check_drivers(true);
+    let err = netlist
+        .insert_gate(and.clone(), "inst_1".into(), &[a.clone(), b.clone()])
+        .unwrap_err();
+    assert!(matches!(err, NetlistError::NameCollision(ref id) if *id == "inst_1".into()));
+    netlist.insert_input("inst_3_Y".into());
+    let err = netlist
+        .insert_gate(and.clone(), "inst_3".into(), &[a.clone(), b.clone()])
+        .unwrap_err();
+    assert!(matches!(err, NetlistError::DuplicateDriver(ref id) if *id == "inst_3_Y".into()));
+
+    // Callers can recover from a name collision by renaming
+    assert!(
+        netlist
+            .insert_gate(and.clone(), "inst_2".into(), &[a, b])
+            .is_ok()
+    );
+    assert!(
+        netlist
+            .insert_gate_disconnected(and, "inst_1".into())
+            .is_err()
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize_vector_ports() {
+    use safety_net::netlist::serde::netlist_deserialize;
+    use std::{io::Cursor, rc::Rc};
+
+    let netlist = GateNetlist::new("top".to_string());
+    {
+        let a0 = netlist.insert_input("a[0]".into());
+        let a1 = netlist.insert_input("a[1]".into());
+        a1.expose_with_name("y[1]".into());
+        a0.expose_with_name("y[0]".into());
+    }
+    let verilog = netlist.to_string();
+
+    let mut buf: Vec<u8> = Vec::new();
+    let netlist = netlist.reclaim().unwrap();
+    assert!(netlist.serialize(&mut buf).is_ok());
+    let netlist: Rc<GateNetlist> = netlist_deserialize(Cursor::new(buf)).unwrap();
+
+    let ports = netlist.ports();
+    assert_eq!(ports.len(), 2);
+    assert_eq!(*ports[1].get_name(), "y".into());
+    assert_eq!(ports[1].get_range(), Some((1, 0)));
+    assert_eq!(netlist.to_string(), verilog);
+}
+
+#[test]
+fn test_supply_and_vector_types() {
+    let netlist = GateNetlist::new("typed".to_string());
+    let vdd = netlist.insert_input(Net::new("vdd".into(), DataType::supply(true)));
+    let vss = netlist.insert_input(Net::new("vss".into(), DataType::supply(false)));
+    let word = netlist.insert_input(Net::new("word".into(), DataType::signed(8)));
+
+    // A cell with a power pin and a signed data pin
+    let cell = Gate::new(
+        "PGCELL".into(),
+        vec![
+            Net::new("VDD".into(), DataType::supply(true)),
+            Net::new("D".into(), DataType::signed(8)),
+        ],
+        vec![Net::new("Q".into(), DataType::unsigned(8))],
+    );
+    let inst = netlist
+        .insert_gate(cell.clone(), "inst_0".into(), &[vdd.clone(), word.clone()])
+        .unwrap();
+    assert!(matches!(
+        netlist.insert_gate(cell, "inst_1".into(), &[vss.clone(), word.clone()]),
+        Err(NetlistError::TypeMismatch(_))
+    ));
+
+    // Connecting checks the types the same way
+    let err = vss.try_connect(inst.get_input(0)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Net vss of type supply0 cannot drive port VDD of inst_0 of type supply1"
+    );
+    assert!(inst.get_output(0).try_connect(inst.get_input(1)).is_err());
+    assert!(vdd.try_connect(inst.get_input(0)).is_ok());
+    // Connecting without the check leaves the mismatch to verification
+    vss.connect(inst.get_input(0));
+    inst.clone().expose_with_name("q".into());
+    assert!(
+        netlist
+            .verify()
+            .unwrap_err()
+            .contains("cannot drive port VDD")
+    );
+    vdd.connect(inst.get_input(0));
+
+    // Supplies drive two-state ports
+    let bool_inv = Gate::new(
+        "INV".into(),
+        vec![Net::new("I".into(), DataType::boolean())],
+        vec![Net::new("O".into(), DataType::boolean())],
+    );
+    let inv = netlist
+        .insert_gate(bool_inv, "inst_2".into(), std::slice::from_ref(&vss))
+        .unwrap();
+    assert!(vdd.try_connect(inv.get_input(0)).is_ok());
+    inv.expose_with_name("y".into());
+    assert!(netlist.verify().is_ok());
+    // Ports are declared with their kind, like internal nets
+    let verilog = netlist.to_string();
+    assert!(verilog.contains("wire [7:0] inst_0_Q;"));
+    assert!(verilog.contains("input signed [7:0] word;"));
+    assert!(verilog.contains("wire signed [7:0] word;"));
+    assert!(verilog.contains("supply1 vdd;"));
+    assert!(verilog.contains("supply0 vss;"));
+}
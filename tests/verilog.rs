@@ -1,7 +1,13 @@
 use safety_net::{
     assert_verilog_eq,
-    netlist::{Gate, GateNetlist, Netlist},
+    attribute::Parameter,
+    circuit::{Identifier, Instantiable, Net, PortDirection},
+    emit::{EmitError, EmitResult, InstanceView, NetlistEmitter, UnconnectedInput, VerilogEmitter},
+    graph::SimpleCombDepth,
+    netlist::{DuplicateOutputPolicy, Gate, GateNetlist, Netlist, NetlistError, Port},
+    verilog::{InstanceSpec, assert_roundtrip, read_verilog},
 };
+use std::fmt::Write;
 
 fn and_gate() -> Gate {
     Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into())
@@ -154,3 +160,942 @@ fn simple_gate_attribute() {
          endmodule\n"
     );
 }
+
+#[test]
+fn port_map() {
+    let netlist = GateNetlist::new("port_map".to_string());
+    let a = netlist.insert_input_escaped_logic_bus("a".to_string(), 2);
+    let c = netlist.insert_input("c".into());
+    c.clone().expose_with_name("z".into());
+    a[1].clone().expose_with_name("y".into());
+    a[0].clone().expose_with_name("x".into());
+
+    let ports = netlist.ports();
+    let names: Vec<String> = ports.iter().map(|p| p.get_name().to_string()).collect();
+    assert_eq!(names, vec!["a", "c", "z", "y", "x"]);
+    assert_eq!(ports[0].width(), 2);
+    assert!(ports[0].is_input());
+    assert!(ports[2].is_output());
+    let outputs: Vec<String> = netlist
+        .get_output_ports()
+        .iter()
+        .map(|n| n.to_string())
+        .collect();
+    assert_eq!(outputs, vec!["z", "y", "x"]);
+
+    // Re-exposing under a new name replaces the port, when asked to rebind
+    netlist.set_duplicate_output_policy(DuplicateOutputPolicy::Rebind);
+    a[0].clone().expose_with_name("w".into());
+    assert!(netlist.find_port(&"x".into()).is_none());
+    assert!(
+        netlist
+            .set_port_attribute(&"w".into(), "keep".to_string(), None)
+            .is_ok()
+    );
+    assert!(
+        netlist
+            .set_port_attribute(&"x".into(), "keep".to_string(), None)
+            .is_err()
+    );
+
+    // Deleting the driver removes the port
+    netlist.delete_net_uses(c.unwrap()).unwrap();
+    assert!(netlist.find_port(&"z".into()).is_none());
+    assert!(netlist.find_port(&"c".into()).is_some());
+
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module port_map (
+           \\a[0] ,
+           \\a[1] ,
+           c,
+           y,
+           w
+         );
+           input \\a[0] ;
+           wire \\a[0] ;
+           input \\a[1] ;
+           wire \\a[1] ;
+           input c;
+           wire c;
+           output y;
+           wire y;
+           (* keep *)
+           output w;
+           wire w;
+           assign y = \\a[1] ;
+           assign w = \\a[0] ;
+         endmodule\n"
+    );
+}
+
+#[test]
+fn vector_ports() {
+    let netlist = GateNetlist::new("vector_ports".to_string());
+    let a1 = netlist.insert_input("a[1]".into());
+    let a0 = netlist.insert_input("a[0]".into());
+    let and = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a0.clone(), a1])
+        .unwrap();
+    and.expose_with_name("y[1]".into());
+    a0.expose_with_name("y[0]".into());
+
+    // Bits are kept lsb first, regardless of the order they were added in
+    let ports = netlist.ports();
+    assert_eq!(ports.len(), 2);
+    assert!(ports.iter().all(|p| p.is_vector()));
+    assert_eq!(ports[1].get_range(), Some((1, 0)));
+    let outputs: Vec<String> = netlist
+        .get_output_ports()
+        .iter()
+        .map(|n| n.to_string())
+        .collect();
+    assert_eq!(outputs, vec!["y[0]", "y[1]"]);
+
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module vector_ports (
+           a,
+           y
+         );
+           input [1:0] a;
+           wire [1:0] a;
+           output [1:0] y;
+           wire [1:0] y;
+           wire inst_0_Y;
+           AND inst_0 (
+             .A(a[0]),
+             .B(a[1]),
+             .Y(inst_0_Y)
+           );
+           assign y[0] = a[0];
+           assign y[1] = inst_0_Y;
+         endmodule\n"
+    );
+}
+
+#[test]
+fn initial_value() {
+    let netlist = GateNetlist::new("initial_value".to_string());
+    let d = netlist.insert_input("d".into());
+    let clk = netlist.insert_input("clk".into());
+    let dff = Gate::new_logical("DFF".into(), vec!["D".into(), "C".into()], "Q".into());
+    let q = netlist.insert_gate(dff, "q_reg".into(), &[d, clk]).unwrap();
+    let q = q.expose_with_name("q".into());
+
+    assert_eq!(q.get_initial_value(), None);
+    q.set_initial_value(Some(true));
+    assert_eq!(q.get_initial_value(), Some(true));
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module initial_value (
+           d,
+           clk,
+           q
+         );
+           input d;
+           wire d;
+           input clk;
+           wire clk;
+           output q;
+           wire q;
+           wire q_reg_Q;
+           DFF q_reg (
+             .D(d),
+             .C(clk),
+             .Q(q_reg_Q)
+           );
+           defparam q_reg.INIT = 1'b1;
+           assign q = q_reg_Q;
+         endmodule\n"
+    );
+
+    q.set_initial_value(None);
+    assert_eq!(q.get_initial_value(), None);
+    assert!(!netlist.to_string().contains("defparam"));
+
+    // An attribute named like the initial value is left alone
+    q.insert_attribute("init".to_string(), "1'b1".to_string());
+    assert_eq!(q.get_initial_value(), None);
+    assert!(!netlist.to_string().contains("defparam"));
+    q.set_initial_value(Some(false));
+    assert_eq!(q.get_initial_value(), Some(false));
+    assert_eq!(q.attributes().count(), 1);
+}
+
+#[test]
+fn compressed_names() {
+    let netlist = Netlist::new("compressed".to_string());
+    let a = netlist.insert_input("a".into());
+    let n0 = netlist.insert_input("n0".into());
+    let first = netlist
+        .insert_gate(and_gate(), "fa_123456".into(), &[a.clone(), n0])
+        .unwrap();
+    let second = netlist
+        .insert_gate(and_gate(), "fa_123457".into(), &[first.get_output(0), a])
+        .unwrap();
+    second.expose_with_name("y".into());
+
+    let mut emitter = VerilogEmitter::new().with_compressed_names(true);
+    let mut verilog = String::new();
+    netlist.emit(&mut emitter, &mut verilog).unwrap();
+    // Shortened names skip the names of ports
+    assert_verilog_eq!(
+        verilog,
+        "module compressed (
+           a,
+           n0,
+           y
+         );
+           input a;
+           wire a;
+           input n0;
+           wire n0;
+           output y;
+           wire y;
+           wire n1;
+           wire n2;
+           AND fa_123456 (
+             .A(a),
+             .B(n0),
+             .Y(n1)
+           );
+           AND fa_123457 (
+             .A(n1),
+             .B(a),
+             .Y(n2)
+           );
+           assign y = n2;
+         endmodule\n"
+    );
+
+    let mut map = Vec::new();
+    emitter.write_name_map(&mut map).unwrap();
+    assert_eq!(
+        String::from_utf8(map).unwrap(),
+        "n1 fa_123456_Y\nn2 fa_123457_Y\n"
+    );
+    assert_eq!(emitter.name_map().len(), 2);
+
+    // Shortened names also skip the names of instances
+    first.set_instance_name("n1".into());
+    netlist.emit(&mut emitter, &mut String::new()).unwrap();
+    let shortened: Vec<String> = emitter
+        .name_map()
+        .iter()
+        .map(|(short, _)| short.to_string())
+        .collect();
+    assert_eq!(shortened, vec!["n2", "n3"]);
+}
+
+#[test]
+fn bus_wires() {
+    let netlist = Netlist::new("bus_wires".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let first = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.clone(), b])
+        .unwrap();
+    let second = netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[first.get_output(0), a])
+        .unwrap();
+    first.as_net_mut().set_identifier("\\s[0]".into());
+    second.as_net_mut().set_identifier("\\s[1]".into());
+    second.clone().expose_with_name("y".into());
+    assert!(netlist.to_string().contains("wire \\s[1] ;"));
+
+    let mut verilog = String::new();
+    netlist
+        .emit(&mut VerilogEmitter::new().with_buses(true), &mut verilog)
+        .unwrap();
+    assert_verilog_eq!(
+        verilog,
+        "module bus_wires (
+           a,
+           b,
+           y
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           wire [1:0] s;
+           AND inst_0 (
+             .A(a),
+             .B(b),
+             .Y(s[0])
+           );
+           AND inst_1 (
+             .A(s[0]),
+             .B(a),
+             .Y(s[1])
+           );
+           assign y = s[1];
+         endmodule\n"
+    );
+
+    // Bits whose vector name is taken by a port stay scalar
+    second.as_net_mut().set_identifier("\\b[1]".into());
+    let mut verilog = String::new();
+    netlist
+        .emit(&mut VerilogEmitter::new().with_buses(true), &mut verilog)
+        .unwrap();
+    assert!(verilog.contains("wire [0:0] s;"));
+    assert!(verilog.contains("wire \\b[1] ;"));
+}
+
+#[test]
+fn unconnected_inputs() {
+    let netlist = Netlist::new("unconnected".to_string());
+    let a = netlist.insert_input("a".into());
+    let and = netlist
+        .insert_gate_disconnected(and_gate(), "inst_0".into())
+        .unwrap();
+    and.get_input(1).connect(a);
+    and.expose_with_name("y".into());
+
+    let emit = |emitter: &mut VerilogEmitter| {
+        let mut s = String::new();
+        netlist.emit(emitter, &mut s).map(|_| s)
+    };
+    let skipped = emit(&mut VerilogEmitter::new()).unwrap();
+    assert!(!skipped.contains(".A("));
+    let empty = emit(&mut VerilogEmitter::new().with_unconnected_inputs(UnconnectedInput::Empty));
+    assert!(empty.unwrap().contains(".A(),\n    .B(a),"));
+    let tied =
+        emit(&mut VerilogEmitter::new().with_unconnected_inputs(UnconnectedInput::Tie(true)));
+    assert!(tied.unwrap().contains(".A(1'b1),"));
+
+    let mut strict = VerilogEmitter::new().with_unconnected_inputs(UnconnectedInput::Error);
+    assert_eq!(
+        emit(&mut strict),
+        Err(EmitError::Unsupported(
+            "Input A of instance inst_0 is not connected".to_string()
+        ))
+    );
+    let mut by_default =
+        VerilogEmitter::new().with_unconnected_inputs(UnconnectedInput::TieDefault);
+    assert!(emit(&mut by_default).is_err());
+}
+
+#[test]
+fn inout_ports() {
+    let netlist = GateNetlist::new("pad_ring".to_string());
+    let a = netlist.insert_input("a".into());
+    let en = netlist.insert_input("en".into());
+    let pad = netlist.insert_inout("pad".into());
+    let tbuf = Gate::new_logical("TBUF".into(), vec!["A".into(), "EN".into()], "Y".into());
+    let drivers: Vec<_> = (0..2)
+        .map(|i| {
+            netlist
+                .insert_gate(
+                    tbuf.clone(),
+                    format!("drv_{i}").into(),
+                    &[a.clone(), en.clone()],
+                )
+                .unwrap()
+        })
+        .collect();
+    for driver in &drivers {
+        netlist.drive_inout(&driver.get_output(0), &pad).unwrap();
+    }
+    let buf = Gate::new_logical("BUF".into(), vec!["A".into()], "Y".into());
+    let rx = netlist
+        .insert_gate(buf, "rx".into(), std::slice::from_ref(&pad))
+        .unwrap();
+    let rx = rx.expose_with_name("y".into());
+
+    assert!(netlist.find_port(&"pad".into()).unwrap().is_inout());
+    // The pad has three drivers, which is only allowed on inout nets
+    assert!(netlist.verify().is_ok());
+    assert!(netlist.drive_inout(&rx.get_output(0), &a).is_err());
+
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module pad_ring (
+           a,
+           en,
+           pad,
+           y
+         );
+           input a;
+           wire a;
+           input en;
+           wire en;
+           inout pad;
+           wire pad;
+           output y;
+           wire y;
+           wire rx_Y;
+           TBUF drv_0 (
+             .A(a),
+             .EN(en),
+             .Y(pad)
+           );
+           TBUF drv_1 (
+             .A(a),
+             .EN(en),
+             .Y(pad)
+           );
+           BUF rx (
+             .A(pad),
+             .Y(rx_Y)
+           );
+           assign y = rx_Y;
+         endmodule\n"
+    );
+    assert_roundtrip(&netlist);
+}
+
+/// An I/O buffer whose pad pin is bidirectional, declared in the order I, IO, O, T
+#[derive(Debug, Clone)]
+struct Iobuf {
+    name: Identifier,
+    inputs: Vec<Net>,
+    output: Net,
+}
+
+impl Iobuf {
+    fn new() -> Self {
+        Self {
+            name: "IOBUF".into(),
+            inputs: vec!["I".into(), "IO".into(), "T".into()],
+            output: "O".into(),
+        }
+    }
+}
+
+impl Instantiable for Iobuf {
+    fn get_name(&self) -> &Identifier {
+        &self.name
+    }
+
+    fn get_input_ports(&self) -> impl IntoIterator<Item = &Net> {
+        &self.inputs
+    }
+
+    fn get_output_ports(&self) -> impl IntoIterator<Item = &Net> {
+        std::slice::from_ref(&self.output)
+    }
+
+    fn has_parameter(&self, _id: &Identifier) -> bool {
+        false
+    }
+
+    fn get_parameter(&self, _id: &Identifier) -> Option<Parameter> {
+        None
+    }
+
+    fn parameters(&self) -> impl Iterator<Item = (Identifier, Parameter)> {
+        std::iter::empty()
+    }
+
+    fn is_inout_port(&self, index: usize) -> bool {
+        index == 1
+    }
+
+    fn get_ports(&self) -> Vec<(&Net, PortDirection)> {
+        vec![
+            (&self.inputs[0], PortDirection::Input),
+            (&self.inputs[1], PortDirection::Inout),
+            (&self.output, PortDirection::Output),
+            (&self.inputs[2], PortDirection::Input),
+        ]
+    }
+}
+
+#[test]
+fn port_directions() {
+    let iobuf = Iobuf::new();
+    assert_eq!(
+        iobuf.get_port_direction(&"IO".into()),
+        Some(PortDirection::Inout)
+    );
+    assert_eq!(
+        iobuf.get_port_direction(&"O".into()),
+        Some(PortDirection::Output)
+    );
+    assert_eq!(iobuf.get_port_direction(&"X".into()), None);
+    assert_eq!(
+        and_gate().get_port_direction(&"Y".into()),
+        Some(PortDirection::Output)
+    );
+
+    let netlist: std::rc::Rc<Netlist<Iobuf>> = Netlist::new("io".to_string());
+    let i = netlist.insert_input("i".into());
+    let t = netlist.insert_input("t".into());
+    let pad = netlist.insert_inout("pad".into());
+    let buf = netlist
+        .insert_gate(iobuf, "u_io".into(), &[i, pad, t])
+        .unwrap();
+    buf.expose_with_name("o".into());
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module io (
+           i,
+           t,
+           pad,
+           o
+         );
+           input i;
+           wire i;
+           input t;
+           wire t;
+           inout pad;
+           wire pad;
+           output o;
+           wire o;
+           wire u_io_O;
+           IOBUF u_io (
+             .I(i),
+             .IO(pad),
+             .O(u_io_O),
+             .T(t)
+           );
+           assign o = u_io_O;
+         endmodule\n"
+    );
+}
+
+/// Lists the instances of a netlist with their connections, one per line
+struct ListEmitter;
+
+impl NetlistEmitter<Gate> for ListEmitter {
+    fn header(&mut self, w: &mut dyn Write, name: &str, ports: &[Port]) -> EmitResult {
+        Ok(writeln!(w, "{name}: {} ports", ports.len())?)
+    }
+
+    fn port(&mut self, _w: &mut dyn Write, _port: &Port) -> EmitResult {
+        Ok(())
+    }
+
+    fn wire(&mut self, w: &mut dyn Write, net: &Net) -> EmitResult {
+        Ok(writeln!(w, "net {}", net.get_identifier())?)
+    }
+
+    fn instance(&mut self, w: &mut dyn Write, inst: &InstanceView<'_, Gate>) -> EmitResult {
+        let inputs: Vec<String> = inst
+            .inputs()
+            .iter()
+            .map(|(p, n)| format!("{}={}", p.get_identifier(), n.get_identifier()))
+            .collect();
+        Ok(writeln!(
+            w,
+            "{} {}({})",
+            inst.get_instance_type().get_gate_name(),
+            inst.get_name(),
+            inputs.join(", ")
+        )?)
+    }
+
+    fn assign(&mut self, w: &mut dyn Write, output: &Net, driver: &Net) -> EmitResult {
+        Ok(writeln!(
+            w,
+            "{} <- {}",
+            output.get_identifier(),
+            driver.get_identifier()
+        )?)
+    }
+
+    fn footer(&mut self, _w: &mut dyn Write, _name: &str) -> EmitResult {
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_emitter() {
+    let netlist = get_simple_example();
+    let mut listing = String::new();
+    netlist.emit(&mut ListEmitter, &mut listing).unwrap();
+    assert_eq!(
+        listing,
+        "example: 3 ports\nnet inst_0_Y\nAND inst_0(A=a, B=b)\ny <- inst_0_Y\n"
+    );
+}
+
+#[test]
+fn roundtrip() {
+    let netlist = Netlist::new("example".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let instance = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+    instance
+        .clone()
+        .expose_with_name("y".into())
+        .insert_attribute("xilinx.LOC".to_string(), "SLICE_X0Y0".to_string());
+    assert!(
+        netlist
+            .to_string()
+            .contains("(* \\xilinx.LOC  = \"SLICE_X0Y0\" *)")
+    );
+    assert_roundtrip(&netlist);
+
+    let netlist = GateNetlist::new("vector_ports".to_string());
+    let a1 = netlist.insert_input("a[1]".into());
+    let a0 = netlist.insert_input("a[0]".into());
+    let and = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a0.clone(), a1])
+        .unwrap();
+    and.expose_with_name("y[1]".into());
+    a0.expose_with_name("y[0]".into());
+    assert_roundtrip(&netlist);
+
+    let netlist = GateNetlist::new("escaped".to_string());
+    let a = netlist.insert_input_escaped_logic_bus("a".to_string(), 2);
+    a[1].clone().expose_with_name("y".into());
+    netlist
+        .set_port_attribute(&"y".into(), "keep".to_string(), None)
+        .unwrap();
+    assert_roundtrip(&netlist);
+
+    let netlist = GateNetlist::new("initial_value".to_string());
+    let d = netlist.insert_input("d".into());
+    let clk = netlist.insert_input("clk".into());
+    let dff = Gate::new_logical("DFF".into(), vec!["D".into(), "C".into()], "Q".into());
+    let q = netlist.insert_gate(dff, "q_reg".into(), &[d, clk]).unwrap();
+    q.expose_with_name("q".into()).set_initial_value(Some(true));
+    assert_roundtrip(&netlist);
+}
+
+#[test]
+fn roundtrip_escaped_names() {
+    // Keywords are written escaped and read back as the plain names
+    let netlist = GateNetlist::new("keywords".to_string());
+    let a = netlist.insert_input("module".into());
+    let b = netlist.insert_input("\\b".into());
+    let c = netlist.insert_input("a+b".into());
+    let wire = netlist
+        .insert_gate(and_gate(), "wire".into(), &[a, b])
+        .unwrap();
+    let inst = netlist
+        .insert_gate(and_gate(), "inst".into(), &[wire.into(), c])
+        .unwrap();
+    inst.expose_with_name("output".into());
+    let verilog = netlist.to_string();
+    for name in ["\\wire ", "\\module ", "\\b ", "\\a+b ", "\\output "] {
+        assert!(verilog.contains(name));
+    }
+    assert_roundtrip(&netlist);
+}
+
+#[test]
+fn read_back() {
+    let src = "module top (a, b, y);
+      input a;
+      input b;
+      output y;
+      wire n0;
+      (* keep *)
+      AND u0 (.A(a), .B(b), .Y(n0));
+      AND u1 (.A(n0), .B(a), .Y(y));
+    endmodule";
+    let netlist = read_verilog(src.as_bytes(), |spec: &InstanceSpec| {
+        match spec.cell_type.to_string().as_str() {
+            "AND" => Ok(and_gate()),
+            t => Err(format!("Unknown cell {t}")),
+        }
+    })
+    .unwrap();
+    assert!(netlist.verify().is_ok());
+    assert_eq!(netlist.get_name().to_string(), "top");
+    let u0 = netlist.find_instance(&"u0".into()).unwrap();
+    assert!(u0.attributes().any(|a| a.key() == "keep"));
+    assert_eq!(
+        netlist.find_net(&"n0".into()).unwrap().get_identifier(),
+        "n0".into()
+    );
+    assert_roundtrip(&netlist);
+
+    let undriven = src.replace(".B(a), .Y(y)", ".B(c), .Y(y)");
+    assert!(read_verilog(undriven.as_bytes(), |_: &InstanceSpec| Ok(and_gate())).is_err());
+    let unknown = src.replace("AND u1", "OR u1");
+    assert!(
+        read_verilog(unknown.as_bytes(), |s: &InstanceSpec| {
+            match s.cell_type == "AND".into() {
+                true => Ok(and_gate()),
+                false => Err("Unknown cell".to_string()),
+            }
+        })
+        .is_err()
+    );
+
+    // A second driver of a net is an error rather than replacing the first
+    let read = |src: &str| read_verilog(src.as_bytes(), |_: &InstanceSpec| Ok(and_gate()));
+    let twice = src.replace(
+        "AND u1 (.A(n0), .B(a), .Y(y));",
+        "AND u1 (.A(a), .B(a), .Y(n0));",
+    );
+    let err = read(&twice).err().unwrap();
+    assert!(err.contains("Multiple drivers for net n0"), "{err}");
+    let aliased = src.replace("endmodule", "assign n0 = a;\n    endmodule");
+    let err = read(&aliased).err().unwrap();
+    assert!(err.contains("Multiple drivers for net n0"), "{err}");
+    let assigned = src.replace(".Y(y)", ".Y(n1)").replace(
+        "endmodule",
+        "assign y = n0;\n    assign y = n1;\n    endmodule",
+    );
+    let err = read(&assigned).err().unwrap();
+    assert!(err.contains("Multiple drivers for net y"), "{err}");
+}
+
+#[test]
+fn buffered_assigns() {
+    let src = "module top (a, b, y, z);
+      input a;
+      input b;
+      output y;
+      output z;
+      wire n0;
+      wire n1;
+      wire n2;
+      AND u0 (.A(a), .B(b), .Y(n0));
+      assign n2 = n1;
+      assign n1 = n0;
+      AND u1 (.A(n2), .B(a), .Y(y));
+      assign z = n1;
+    endmodule";
+    let netlist = read_verilog(src.as_bytes(), |_: &InstanceSpec| Ok(and_gate())).unwrap();
+    assert!(netlist.verify().is_ok());
+    assert_eq!(netlist.aliases().count(), 2);
+    let u1 = netlist.find_instance(&"u1".into()).unwrap();
+    assert_eq!(
+        u1.get_input(0).get_driver().unwrap().get_identifier(),
+        "n2".into()
+    );
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module top (
+           a,
+           b,
+           y,
+           z
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           output z;
+           wire z;
+           wire n0;
+           wire n1;
+           wire n2;
+           AND u0 (
+             .A(a),
+             .B(b),
+             .Y(n0)
+           );
+           AND u1 (
+             .A(n2),
+             .B(a),
+             .Y(y)
+           );
+           assign n1 = n0;
+           assign n2 = n1;
+           assign z = n1;
+         endmodule\n"
+    );
+    assert_roundtrip(&netlist);
+
+    assert_eq!(netlist.collapse_aliases().unwrap(), 2);
+    assert!(netlist.verify().is_ok());
+    assert_eq!(
+        u1.get_input(0).get_driver().unwrap().get_identifier(),
+        "n0".into()
+    );
+    assert!(netlist.to_string().contains("assign z = n0;"));
+
+    let cyclic = src.replace("assign n1 = n0;", "assign n1 = n2;");
+    assert!(read_verilog(cyclic.as_bytes(), |_: &InstanceSpec| Ok(and_gate())).is_err());
+}
+
+#[test]
+fn instance_arrays() {
+    let netlist = GateNetlist::new("arrays".to_string());
+    let a: Vec<_> = (0..2)
+        .map(|i| netlist.insert_input(format!("a[{i}]").as_str().into()))
+        .collect();
+    let b: Vec<_> = (0..2)
+        .map(|i| netlist.insert_input(format!("b[{i}]").as_str().into()))
+        .collect();
+    let en = netlist.insert_input("en".into());
+    // A single net is shared by every instance of the array
+    let ands = netlist
+        .insert_gate_array(and_gate(), "u_and".into(), &[a.clone(), vec![en.clone()]])
+        .unwrap();
+    for (i, and) in ands.iter().enumerate() {
+        and.get_output(0)
+            .expose_with_name(format!("y[{i}]").as_str().into());
+    }
+    let ors = netlist
+        .insert_gate_array(
+            Gate::new_logical("OR".into(), vec!["A".into(), "B".into()], "Y".into()),
+            "u_or".into(),
+            &[b, vec![ands[0].get_output(0)]],
+        )
+        .unwrap();
+    ors[1].insert_attribute("keep".to_string(), "1".to_string());
+    ors[1].clone().expose_with_name("z".into());
+    assert!(
+        netlist
+            .insert_gate_array(
+                and_gate(),
+                "bad".into(),
+                &[a.clone(), vec![en.clone(), en.clone(), en.clone()]]
+            )
+            .is_err()
+    );
+    for name in ["\\bad", "bad[0]"] {
+        let err = netlist
+            .insert_gate_array(and_gate(), name.into(), &[a.clone(), vec![en.clone()]])
+            .unwrap_err();
+        assert!(matches!(err, NetlistError::EscapedName(_)));
+    }
+
+    let arrays = netlist.instance_arrays();
+    assert_eq!(arrays.len(), 2);
+    assert_eq!(*arrays[0].get_name(), "u_and".into());
+    assert_eq!(arrays[0].get_range(), (1, 0));
+    assert_eq!(
+        arrays[0].instances()[1].get_instance_name(),
+        Some("u_and[1]".into())
+    );
+
+    // Arrays whose instances differ in attributes are written one instance at a time
+    let mut verilog = String::new();
+    netlist
+        .emit(
+            &mut VerilogEmitter::new()
+                .with_buses(true)
+                .with_instance_arrays(true),
+            &mut verilog,
+        )
+        .unwrap();
+    assert_verilog_eq!(
+        verilog,
+        "module arrays (
+           a,
+           b,
+           en,
+           y,
+           z
+         );
+           input [1:0] a;
+           wire [1:0] a;
+           input [1:0] b;
+           wire [1:0] b;
+           input en;
+           wire en;
+           output [1:0] y;
+           wire [1:0] y;
+           output z;
+           wire z;
+           wire [1:0] u_and_Y;
+           wire [1:0] u_or_Y;
+           AND u_and [1:0] (
+             .A({a[1], a[0]}),
+             .B(en),
+             .Y({u_and_Y[1], u_and_Y[0]})
+           );
+           OR \\u_or[0]  (
+             .A(b[0]),
+             .B(u_and_Y[0]),
+             .Y(u_or_Y[0])
+           );
+           (* keep = \"1\" *)
+           OR \\u_or[1]  (
+             .A(b[1]),
+             .B(u_and_Y[0]),
+             .Y(u_or_Y[1])
+           );
+           assign y[0] = u_and_Y[0];
+           assign y[1] = u_and_Y[1];
+           assign z = u_or_Y[1];
+         endmodule\n"
+    );
+    assert_roundtrip(&netlist);
+
+    // Arrays are found from the names of the instances, so they survive serialization
+    #[cfg(feature = "serde")]
+    {
+        use safety_net::netlist::serde::netlist_deserialize;
+        use std::io::Cursor;
+
+        drop((ands, ors, arrays, a, en));
+        let mut buf: Vec<u8> = Vec::new();
+        netlist.reclaim().unwrap().serialize(&mut buf).unwrap();
+        let netlist: std::rc::Rc<GateNetlist> = netlist_deserialize(Cursor::new(buf)).unwrap();
+        assert_eq!(netlist.instance_arrays().len(), 2);
+        assert_eq!(netlist.instance_arrays()[1].len(), 2);
+    }
+}
+
+#[test]
+fn annotations() {
+    let netlist = Netlist::new("annotated".to_string());
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+    let u0 = netlist
+        .insert_gate(and_gate(), "u0".into(), &[a.clone(), b])
+        .unwrap();
+    let u1 = netlist
+        .insert_gate(and_gate(), "u1".into(), &[u0.get_output(0), a])
+        .unwrap();
+    u1.expose_with_name("y".into());
+
+    let levels: Vec<(Identifier, String)> = {
+        let depth = netlist.get_analysis::<SimpleCombDepth<_>>().unwrap();
+        netlist
+            .objects()
+            .filter_map(|n| {
+                Some((
+                    n.get_instance_name()?,
+                    depth.get_comb_depth(&n)?.to_string(),
+                ))
+            })
+            .collect()
+    };
+    let mut emitter = VerilogEmitter::new()
+        .with_annotation("level", levels)
+        .with_annotation("slack", [("u1".into(), "-0.5".to_string())]);
+    let mut verilog = String::new();
+    netlist.emit(&mut emitter, &mut verilog).unwrap();
+    assert_verilog_eq!(
+        verilog,
+        "module annotated (
+           a,
+           b,
+           y
+         );
+           input a;
+           wire a;
+           input b;
+           wire b;
+           output y;
+           wire y;
+           wire u0_Y;
+           wire u1_Y;
+           AND u0 (
+             .A(a),
+             .B(b),
+             .Y(u0_Y)
+           ); // level=1
+           AND u1 (
+             .A(u0_Y),
+             .B(a),
+             .Y(u1_Y)
+           ); // level=2 slack=-0.5
+           assign y = u1_Y;
+         endmodule\n"
+    );
+
+    // Annotations are comments, so the Verilog reads back
+    let netlist = read_verilog(verilog.as_bytes(), |_: &InstanceSpec| Ok(and_gate())).unwrap();
+    assert!(netlist.find_instance(&"u1".into()).is_some());
+    assert!(!netlist.to_string().contains("//"));
+}
@@ -0,0 +1,88 @@
+use safety_net::netlist::{Gate, GateNetlist, Netlist};
+use safety_net::xref::Xref;
+use std::rc::Rc;
+
+fn and_gate() -> Gate {
+    Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into())
+}
+
+fn get_example() -> Rc<GateNetlist> {
+    let netlist = Netlist::new("example".to_string());
+
+    let a = netlist.insert_input("a".into());
+    let b = netlist.insert_input("b".into());
+
+    let inst_0 = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a.clone(), b])
+        .unwrap();
+    let inst_1 = netlist
+        .insert_gate(and_gate(), "inst_1".into(), &[a, inst_0.get_output(0)])
+        .unwrap();
+
+    inst_1.expose_with_name("y".into());
+
+    netlist
+}
+
+#[test]
+fn test_xref_tables() {
+    let netlist = get_example();
+    let xref = Xref::new(&netlist);
+
+    let fanout: Vec<(&str, usize)> = xref
+        .nets()
+        .iter()
+        .map(|n| (n.net.as_str(), n.fanout))
+        .collect();
+    assert_eq!(
+        fanout,
+        vec![("a", 2), ("b", 1), ("inst_0_Y", 1), ("inst_1_Y", 1)]
+    );
+    assert_eq!(xref.nets()[2].driver.as_deref(), Some("inst_0"));
+    assert_eq!(xref.nets()[2].driver_pin.as_deref(), Some("Y"));
+    assert_eq!(xref.nets()[0].driver, None);
+
+    let used: Vec<String> = xref
+        .where_used("a")
+        .map(|p| format!("{}.{}", p.instance.as_deref().unwrap_or("top"), p.pin))
+        .collect();
+    assert_eq!(used, vec!["inst_0.A", "inst_1.A", "top.a"]);
+}
+
+#[test]
+fn test_xref_export() {
+    let netlist = get_example();
+    let xref = Xref::new(&netlist);
+
+    let (mut nets, mut pins) = (Vec::new(), Vec::new());
+    xref.write_csv(&mut nets, &mut pins).unwrap();
+    assert_eq!(
+        String::from_utf8(nets).unwrap(),
+        "net,driver,driver_pin,fanout
+a,,,2
+b,,,1
+inst_0_Y,inst_0,Y,1
+inst_1_Y,inst_1,Y,1
+"
+    );
+    assert_eq!(
+        String::from_utf8(pins).unwrap(),
+        "instance,type,pin,direction,net
+inst_0,AND,A,input,a
+inst_0,AND,B,input,b
+inst_0,AND,Y,output,inst_0_Y
+inst_1,AND,A,input,a
+inst_1,AND,B,input,inst_0_Y
+inst_1,AND,Y,output,inst_1_Y
+,,a,input,a
+,,b,input,b
+,,y,output,inst_1_Y
+"
+    );
+
+    let mut sql = Vec::new();
+    xref.write_sql(&mut sql).unwrap();
+    let sql = String::from_utf8(sql).unwrap();
+    assert!(sql.contains("INSERT INTO nets VALUES ('a', NULL, NULL, 2);"));
+    assert!(sql.contains("INSERT INTO pins VALUES (NULL, NULL, 'y', 'output', 'inst_1_Y');"));
+}
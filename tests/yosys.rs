@@ -0,0 +1,195 @@
+#![cfg(feature = "yosys")]
+
+use safety_net::{
+    assert_verilog_eq,
+    attribute::Parameter,
+    bus::{BusCell, BusCellDef, insert_bus_cell},
+    circuit::Instantiable,
+    netlist::{DrivenNet, Gate, GateNetlist, Netlist},
+    yosys::{CellSpec, read_yosys_json, write_yosys_json},
+};
+use std::rc::Rc;
+
+fn and_gate() -> Gate {
+    Gate::new_logical("AND".into(), vec!["A".into(), "B".into()], "Y".into())
+}
+
+fn to_gate(spec: &CellSpec) -> Result<Gate, String> {
+    let name = match spec.cell_type.as_str() {
+        "1'b0" => "TIE0",
+        "1'b1" => "TIE1",
+        t => t.trim_start_matches('$').trim_matches('_'),
+    };
+    Ok(Gate::new_logical_multi(
+        name.into(),
+        spec.inputs.iter().map(|p| p.as_str().into()).collect(),
+        spec.outputs.iter().map(|p| p.as_str().into()).collect(),
+    ))
+}
+
+#[test]
+fn yosys_round_trip() {
+    let netlist = Netlist::new("example".to_string());
+    let a = netlist.insert_input("a[0]".into());
+    let b = netlist.insert_input("a[1]".into());
+    let instance = netlist
+        .insert_gate(and_gate(), "inst_0".into(), &[a, b])
+        .unwrap();
+    instance.set_attribute("keep".to_string());
+    instance.expose_with_name("y".into());
+
+    let mut buf = Vec::new();
+    write_yosys_json(&netlist, &mut buf).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+    let module = &json["modules"]["example"];
+    assert_eq!(module["ports"]["a"]["bits"], serde_json::json!([2, 3]));
+    assert_eq!(module["ports"]["y"]["bits"], serde_json::json!([4]));
+    assert_eq!(
+        module["cells"]["inst_0"]["connections"]["B"],
+        serde_json::json!([3])
+    );
+
+    let read: Rc<GateNetlist> = read_yosys_json(buf.as_slice(), None, to_gate).unwrap();
+    assert!(read.verify().is_ok());
+    assert_eq!(read.to_string(), netlist.to_string());
+}
+
+#[test]
+fn yosys_round_trip_buses() {
+    let def = Rc::new(
+        BusCellDef::new("ADD".into())
+            .with_parameter("N".into(), Parameter::Integer(4))
+            .with_input("A".into(), "N".parse().unwrap())
+            .with_input("B".into(), "N".parse().unwrap())
+            .with_output("S".into(), "N+1".parse().unwrap()),
+    );
+    let netlist = Netlist::<BusCell>::new("top".to_string());
+    let inputs: Vec<Vec<DrivenNet<BusCell>>> = ["a", "b"]
+        .iter()
+        .map(|n| {
+            (0..2)
+                .map(|i| netlist.insert_input(format!("{n}[{i}]").as_str().into()))
+                .collect()
+        })
+        .collect();
+    let cell = def
+        .instantiate(&[("N".into(), Parameter::Integer(2))])
+        .unwrap();
+    let add = insert_bus_cell(&netlist, cell, "add0".into(), &inputs).unwrap();
+    for (i, sum) in add.outputs().enumerate() {
+        netlist.expose_net_with_name(sum, format!("s[{i}]").as_str().into());
+    }
+
+    // Buses are single connections, and integers stay numbers
+    let mut buf = Vec::new();
+    write_yosys_json(&netlist, &mut buf).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+    let cell = &json["modules"]["top"]["cells"]["add0"];
+    assert_eq!(cell["connections"]["A"], serde_json::json!([2, 3]));
+    assert_eq!(cell["connections"]["S"], serde_json::json!([6, 7, 8]));
+    assert_eq!(cell["parameters"]["N"], serde_json::json!(2));
+
+    let read: Rc<Netlist<BusCell>> = read_yosys_json(buf.as_slice(), None, |spec| {
+        assert_eq!(spec.inputs, vec!["A[0]", "A[1]", "B[0]", "B[1]"]);
+        def.instantiate(&spec.parameters)
+    })
+    .unwrap();
+    assert!(read.verify().is_ok());
+    assert_eq!(read.to_string(), netlist.to_string());
+}
+
+#[test]
+fn yosys_read_design() {
+    let json = r#"{
+      "creator": "Yosys",
+      "modules": {
+        "sub": { "ports": {}, "cells": {}, "netnames": {} },
+        "top": {
+          "attributes": { "top": "00000000000000000000000000000001" },
+          "ports": {
+            "en": { "direction": "input", "bits": [ 2 ] },
+            "d": { "direction": "input", "bits": [ 3, 4 ] },
+            "q": { "direction": "output", "bits": [ 5, "0" ] }
+          },
+          "cells": {
+            "$and$top.v:4$1": {
+              "hide_name": 1,
+              "type": "$_AND_",
+              "parameters": { },
+              "attributes": { "src": "top.v:4.3-4.20" },
+              "port_directions": { "A": "input", "B": "input", "Y": "output" },
+              "connections": { "A": [ 2 ], "B": [ 3 ], "Y": [ 6 ] }
+            },
+            "u_or": {
+              "hide_name": 0,
+              "type": "$_OR_",
+              "parameters": { },
+              "attributes": { },
+              "port_directions": { "A": "input", "B": "input", "Y": "output" },
+              "connections": { "A": [ 6 ], "B": [ "1" ], "Y": [ 5 ] }
+            }
+          },
+          "netnames": {
+            "q": { "hide_name": 0, "bits": [ 5, "0" ], "attributes": { "keep": "00000000000000000000000000000001" } }
+          }
+        }
+      }
+    }"#;
+
+    // Multi-bit cell ports are read bit by bit
+    let wide = json.replace(r#""B": [ 3 ]"#, r#""B": [ 3, 4 ]"#);
+    let netlist: Rc<GateNetlist> = read_yosys_json(wide.as_bytes(), None, to_gate).unwrap();
+    let and = netlist.find_instance(&"_and_top_v_4_1".into()).unwrap();
+    let inputs: Vec<String> = and.inputs().map(|i| i.get_port().to_string()).collect();
+    assert_eq!(inputs, vec!["A", "B[0]", "B[1]"]);
+    assert_eq!(and.get_driver(2).unwrap().get_identifier(), "d[1]".into());
+    assert!(read_yosys_json::<Gate, _>(json.as_bytes(), Some("nope"), to_gate).is_err());
+
+    // Constants skip the names of cells
+    let taken = json.replace("u_or", "tie_1");
+    let netlist: Rc<GateNetlist> = read_yosys_json(taken.as_bytes(), None, to_gate).unwrap();
+    let tie = netlist.find_instance(&"tie_1_0".into()).unwrap();
+    assert_eq!(tie.get_instance_type().unwrap().get_name(), &"TIE1".into());
+
+    let netlist = read_yosys_json(json.as_bytes(), None, to_gate).unwrap();
+    assert!(netlist.find_port(&"q".into()).unwrap().attributes().count() == 1);
+    assert_verilog_eq!(
+        netlist.to_string(),
+        "module top (
+           en,
+           d,
+           q
+         );
+           input en;
+           wire en;
+           input [1:0] d;
+           wire [1:0] d;
+           (* keep *)
+           output [1:0] q;
+           wire [1:0] q;
+           wire _and_top_v_4_1_Y;
+           wire u_or_Y;
+           wire tie_1_Y;
+           wire tie_0_Y;
+           (* src = \"top.v:4.3-4.20\" *)
+           AND _and_top_v_4_1 (
+             .A(en),
+             .B(d[0]),
+             .Y(_and_top_v_4_1_Y)
+           );
+           OR u_or (
+             .A(_and_top_v_4_1_Y),
+             .B(tie_1_Y),
+             .Y(u_or_Y)
+           );
+           TIE1 tie_1 (
+             .Y(tie_1_Y)
+           );
+           TIE0 tie_0 (
+             .Y(tie_0_Y)
+           );
+           assign q[0] = u_or_Y;
+           assign q[1] = tie_0_Y;
+         endmodule\n"
+    );
+}